@@ -0,0 +1,313 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::integrated_autocorrelation_time;
+use crate::error::{Error, Result};
+use crate::grid::{BoundaryCondition, Grid};
+use crate::random_cluster::swendsen_wang_step;
+use crate::simulation;
+use crate::temperature::Temperature;
+
+/// The name of the adaptive selection of the schedule in a configuration.
+pub const ALGORITHM: &str = "adaptive";
+
+/// The cost of a Swendsen–Wang update in Metropolis sweeps, for weighing autocorrelation times
+/// against each other. Drawing the bonds and labelling the clusters takes about twice as long as
+/// a sweep.
+pub const CLUSTER_COST: f64 = 2.0;
+
+/// # Update schedule
+/// The updates that make up one step of a simulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Schedule {
+    /// A sequential Metropolis sweep.
+    #[default]
+    Metropolis,
+    /// A sequential Metropolis sweep followed by a Swendsen–Wang update.
+    Mixed,
+    /// A Swendsen–Wang update.
+    SwendsenWang,
+}
+
+impl Schedule {
+    /// # All schedules
+    pub const ALL: [Schedule; 3] = [
+        Schedule::Metropolis,
+        Schedule::Mixed,
+        Schedule::SwendsenWang,
+    ];
+
+    /// # Name
+    /// The name the schedule is given in a configuration and in the provenance of a run.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Schedule::Metropolis => simulation::ALGORITHM,
+            Schedule::Mixed => "metropolis-sequential+swendsen-wang",
+            Schedule::SwendsenWang => "swendsen-wang",
+        }
+    }
+
+    /// # Metropolis sweeps
+    /// The number of Metropolis sweeps in a step.
+    pub fn metropolis_sweeps(&self) -> usize {
+        match self {
+            Schedule::Metropolis | Schedule::Mixed => 1,
+            Schedule::SwendsenWang => 0,
+        }
+    }
+
+    /// # Cluster updates
+    /// The number of Swendsen–Wang updates in a step.
+    pub fn cluster_updates(&self) -> usize {
+        match self {
+            Schedule::Metropolis => 0,
+            Schedule::Mixed | Schedule::SwendsenWang => 1,
+        }
+    }
+
+    /// # Cost
+    /// The time a step takes, in Metropolis sweeps.
+    pub fn cost(&self) -> f64 {
+        self.metropolis_sweeps() as f64 + CLUSTER_COST * self.cluster_updates() as f64
+    }
+
+    /// # Check support
+    /// Fails if the schedule cannot sample the grid at the given field. The Swendsen–Wang update
+    /// needs zero field, no field map, spin-1/2, periodic boundaries and no diagonal coupling.
+    pub fn check(&self, grid: &Grid, field: f64) -> Result<()> {
+        if self.cluster_updates() == 0 {
+            return Ok(());
+        }
+        let unsupported = if field != 0.0 || grid.field_map().is_some() {
+            Some("the Swendsen–Wang update needs zero field")
+        } else if grid.crystal_field_ratio().is_some() {
+            Some("the Swendsen–Wang update needs spin-1/2")
+        } else if grid.boundary_conditions()
+            != (BoundaryCondition::Periodic, BoundaryCondition::Periodic)
+        {
+            Some("the Swendsen–Wang update needs periodic boundaries")
+        } else if grid.next_nearest_ratio() != 0.0 {
+            Some("the Swendsen–Wang update does not support diagonal couplings")
+        } else {
+            None
+        };
+        unsupported.map_or(Ok(()), |reason| Err(Error::Unsupported(reason)))
+    }
+
+    /// # Step
+    /// Performs one step of the schedule at temperature T and field h in units of the coupling,
+    /// and returns the number of accepted Metropolis moves.
+    pub fn step<R: Rng>(
+        &self,
+        grid: &mut Grid,
+        temperature: Temperature,
+        field: f64,
+        rng: &mut R,
+    ) -> usize {
+        self.step_reduced(grid, temperature.beta(), field * temperature.beta(), rng)
+    }
+
+    /// # Step at a reduced coupling
+    /// Performs the step of `step` at the reduced coupling K = βJ and field H = βh of
+    /// `Grid::step_reduced`.
+    pub fn step_reduced<R: Rng>(
+        &self,
+        grid: &mut Grid,
+        coupling: f64,
+        field: f64,
+        rng: &mut R,
+    ) -> usize {
+        let mut accepted = 0;
+        for _ in 0..self.metropolis_sweeps() {
+            accepted += grid.step_reduced_with_rng(coupling, field, rng);
+        }
+        for _ in 0..self.cluster_updates() {
+            swendsen_wang_step(grid, coupling, rng);
+        }
+        accepted
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|schedule| schedule.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(Schedule::name).collect();
+                format!(
+                    "unknown algorithm {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// # Adaptive selection
+/// Picks the schedule of a run during its warm-up, so that nobody has to tune it by hand near
+/// the critical point. The warm-up starts with Metropolis sweeps and measures the integrated
+/// autocorrelation time of the absolute magnetization over the second half of them. If it is
+/// above the threshold, the dynamics is slowing down, and the schedules with cluster updates are
+/// warmed up and measured in turn, as far as the grid allows them. The schedule with the smallest
+/// autocorrelation time times cost wins.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveSelection {
+    pub warmup_steps: usize,
+    pub threshold: f64,
+}
+
+impl Default for AdaptiveSelection {
+    fn default() -> Self {
+        Self {
+            warmup_steps: 200,
+            threshold: 5.0,
+        }
+    }
+}
+
+/// # Selection
+/// The schedules that were measured during the warm-up with the integrated autocorrelation times
+/// of the absolute magnetization, in steps, and the one that was chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Selection {
+    pub measurements: Vec<(Schedule, f64)>,
+    pub chosen: Schedule,
+}
+
+impl fmt::Display for Selection {
+    /// Reports the chosen schedule and the measurements behind it, one per line.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "chosen schedule: {}", self.chosen)?;
+        for (schedule, time) in &self.measurements {
+            write!(
+                f,
+                "\n{}: τ_int = {:.2} steps, {:.2} sweeps with the cost",
+                schedule,
+                time,
+                time * schedule.cost()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl AdaptiveSelection {
+    /// # Select
+    /// Warms the grid up and picks its schedule. The warm-up steps of every measured schedule
+    /// change the grid, which is then thermalized for the chosen one.
+    pub fn select<R: Rng>(
+        &self,
+        grid: &mut Grid,
+        coupling: f64,
+        field: f64,
+        rng: &mut R,
+    ) -> Selection {
+        let mut measurements = vec![(
+            Schedule::Metropolis,
+            self.measure(Schedule::Metropolis, grid, coupling, field, rng),
+        )];
+        if measurements[0].1 > self.threshold {
+            for schedule in [Schedule::Mixed, Schedule::SwendsenWang] {
+                if schedule.check(grid, field).is_ok() {
+                    let time = self.measure(schedule, grid, coupling, field, rng);
+                    measurements.push((schedule, time));
+                }
+            }
+        }
+        let chosen = measurements
+            .iter()
+            .min_by(|a, b| (a.1 * a.0.cost()).total_cmp(&(b.1 * b.0.cost())))
+            .unwrap()
+            .0;
+        Selection {
+            measurements,
+            chosen,
+        }
+    }
+
+    /// Runs the warm-up steps of a schedule and returns the autocorrelation time of the absolute
+    /// magnetization over their second half.
+    fn measure<R: Rng>(
+        &self,
+        schedule: Schedule,
+        grid: &mut Grid,
+        coupling: f64,
+        field: f64,
+        rng: &mut R,
+    ) -> f64 {
+        let mut magnetizations = Vec::new();
+        for step in 0..self.warmup_steps {
+            schedule.step_reduced(grid, coupling, field, rng);
+            if step >= self.warmup_steps / 2 {
+                magnetizations.push(grid.magnetization().abs());
+            }
+        }
+        integrated_autocorrelation_time(&magnetizations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_schedule_names() {
+        for schedule in Schedule::ALL {
+            assert_eq!(schedule.to_string().parse(), Ok(schedule));
+        }
+        assert!("wolff".parse::<Schedule>().is_err());
+        assert_eq!(Schedule::Mixed.cost(), 1.0 + CLUSTER_COST);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut grid = Grid::new_random(8, 8).unwrap();
+        assert!(Schedule::SwendsenWang.check(&grid, 0.0).is_ok());
+        assert!(Schedule::Metropolis.check(&grid, 0.1).is_ok());
+        assert!(matches!(
+            Schedule::Mixed.check(&grid, 0.1),
+            Err(Error::Unsupported(_))
+        ));
+        grid.set_next_nearest_ratio(0.5);
+        assert!(Schedule::SwendsenWang.check(&grid, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_adaptive_selection() {
+        let selection = AdaptiveSelection::default();
+        let mut rng = ChaCha8Rng::seed_from_u64(8);
+
+        // Far above the critical point Metropolis decorrelates at once.
+        let mut grid = Grid::new_random(16, 16).unwrap();
+        let hot = selection.select(&mut grid, 0.1, 0.0, &mut rng);
+        assert_eq!(hot.chosen, Schedule::Metropolis);
+        assert_eq!(hot.measurements.len(), 1);
+
+        // At the critical point it slows down, and the clusters take over.
+        let mut grid = Grid::new_random(32, 32).unwrap();
+        let critical = selection.select(&mut grid, 0.4407, 0.0, &mut rng);
+        assert_eq!(critical.measurements.len(), 3);
+        assert_ne!(critical.chosen, Schedule::Metropolis);
+        assert!(critical.to_string().starts_with("chosen schedule: "));
+
+        // In a field the clusters cannot be used.
+        let mut grid = Grid::new_random(32, 32).unwrap();
+        let field = selection.select(&mut grid, 0.4407, 0.01, &mut rng);
+        assert_eq!(field.chosen, Schedule::Metropolis);
+    }
+}
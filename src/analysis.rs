@@ -0,0 +1,403 @@
+use crate::grid::Grid;
+use crate::measurement::Measurement;
+use crate::output::Observation;
+use crate::temperature::Temperature;
+
+/// The number of blocks that the measurements are split into for the error bars.
+const BLOCKS: usize = 16;
+
+/// The window of the integrated autocorrelation time stops at this many times the estimate.
+const WINDOW_FACTOR: f64 = 6.0;
+
+/// # Blocked mean
+/// The mean of a time series and its standard error, from the spread of the means of 16 equal
+/// blocks, which accounts for the correlations as long as the blocks are longer than them. The
+/// error is NaN for series shorter than the number of blocks.
+pub fn blocked_mean(values: &[f64]) -> Measurement {
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let length = values.len() / BLOCKS;
+    if length == 0 {
+        return Measurement::new(mean, f64::NAN);
+    }
+    let block_means: Vec<f64> = values
+        .chunks_exact(length)
+        .take(BLOCKS)
+        .map(|block| block.iter().sum::<f64>() / length as f64)
+        .collect();
+    let block_mean = block_means.iter().sum::<f64>() / BLOCKS as f64;
+    let variance = block_means
+        .iter()
+        .map(|value| (value - block_mean).powi(2))
+        .sum::<f64>()
+        / (BLOCKS - 1) as f64;
+    Measurement::new(mean, (variance / BLOCKS as f64).sqrt())
+}
+
+/// # Integrated autocorrelation time
+/// τ_int = 1/2 + Σ_t ρ(t) of a time series in units of its spacing, summing the normalized
+/// autocorrelation ρ over the window of Sokal, the smallest W with W ≥ 6 τ_int(W), which keeps the
+/// noise of the long lags out. The error of a mean is √(2 τ_int) times the naive one. A constant
+/// series has τ_int = 1/2, and a series too short for the window gives the sum up to half its
+/// length, which underestimates τ_int.
+pub fn integrated_autocorrelation_time(values: &[f64]) -> f64 {
+    let length = values.len();
+    let mean = values.iter().sum::<f64>() / length as f64;
+    let deviations: Vec<f64> = values.iter().map(|value| value - mean).collect();
+    let autocovariance = |lag: usize| {
+        deviations[lag..]
+            .iter()
+            .zip(&deviations)
+            .map(|(later, earlier)| later * earlier)
+            .sum::<f64>()
+            / (length - lag) as f64
+    };
+    let variance = autocovariance(0);
+    let mut time = 0.5;
+    if variance == 0.0 {
+        return time;
+    }
+    for lag in 1..length / 2 {
+        time += autocovariance(lag) / variance;
+        if lag as f64 >= WINDOW_FACTOR * time {
+            break;
+        }
+    }
+    time
+}
+
+/// # Analysis
+/// The observables of a time series at one temperature, in the units of `ScanResult`: the energy
+/// per site in units of the coupling, the susceptibility χ = N (⟨m²⟩ - ⟨|m|⟩²)/T, the specific
+/// heat C = N (⟨e²⟩ - ⟨e⟩²)/T² and the Binder cumulant U = 1 - ⟨m⁴⟩/(3⟨m²⟩²). The errors come
+/// from a jackknife over 16 blocks, and the integrated autocorrelation times of the energy and the
+/// absolute magnetization are in measurements.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Analysis {
+    pub temperature: Temperature,
+    pub energy: Measurement,
+    pub magnetization: Measurement,
+    pub absolute_magnetization: Measurement,
+    pub susceptibility: Measurement,
+    pub specific_heat: Measurement,
+    pub binder_cumulant: Measurement,
+    pub energy_time: f64,
+    pub absolute_magnetization_time: f64,
+}
+
+/// # Time series
+/// The measurements of a run at the temperature T = k_BT/J and the field h in units of the
+/// coupling, kept as the sum of the nearest-neighbour bonds Σ_⟨ij⟩ s_i s_j and the sum of the
+/// spins per site, from which every observable follows. They are all that histogram reweighting
+/// needs to move the averages to nearby temperatures at the same field without running again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeries {
+    pub number_of_sites: usize,
+    pub temperature: Temperature,
+    pub field: f64,
+    pub bonds: Vec<f64>,
+    pub magnetizations: Vec<f64>,
+}
+
+/// Weighted sums of the powers of the energy and the magnetization over some measurements.
+#[derive(Debug, Clone, Copy, Default)]
+struct Sums {
+    weight: f64,
+    energy: f64,
+    energy_squared: f64,
+    magnetization: f64,
+    absolute_magnetization: f64,
+    magnetization_squared: f64,
+    magnetization_fourth: f64,
+}
+
+impl Sums {
+    fn add(&mut self, weight: f64, energy: f64, magnetization: f64) {
+        let squared = magnetization * magnetization;
+        self.weight += weight;
+        self.energy += weight * energy;
+        self.energy_squared += weight * energy * energy;
+        self.magnetization += weight * magnetization;
+        self.absolute_magnetization += weight * magnetization.abs();
+        self.magnetization_squared += weight * squared;
+        self.magnetization_fourth += weight * squared * squared;
+    }
+
+    fn without(&self, other: &Sums) -> Sums {
+        Sums {
+            weight: self.weight - other.weight,
+            energy: self.energy - other.energy,
+            energy_squared: self.energy_squared - other.energy_squared,
+            magnetization: self.magnetization - other.magnetization,
+            absolute_magnetization: self.absolute_magnetization - other.absolute_magnetization,
+            magnetization_squared: self.magnetization_squared - other.magnetization_squared,
+            magnetization_fourth: self.magnetization_fourth - other.magnetization_fourth,
+        }
+    }
+
+    /// The energy, magnetization, absolute magnetization, susceptibility, specific heat and
+    /// Binder cumulant of the weighted averages.
+    fn observables(&self, number_of_sites: f64, temperature: f64) -> [f64; 6] {
+        let energy = self.energy / self.weight;
+        let absolute_magnetization = self.absolute_magnetization / self.weight;
+        let second = self.magnetization_squared / self.weight;
+        [
+            energy,
+            self.magnetization / self.weight,
+            absolute_magnetization,
+            number_of_sites * (second - absolute_magnetization.powi(2)) / temperature,
+            number_of_sites * (self.energy_squared / self.weight - energy.powi(2))
+                / temperature.powi(2),
+            1.0 - self.magnetization_fourth / self.weight / (3.0 * second * second),
+        ]
+    }
+}
+
+impl TimeSeries {
+    /// # New time series
+    /// An empty series of a grid of the given number of occupied sites at the given temperature
+    /// and field h in units of the coupling.
+    pub fn new(number_of_sites: usize, temperature: Temperature, field: f64) -> Self {
+        Self {
+            number_of_sites,
+            temperature,
+            field,
+            bonds: Vec::new(),
+            magnetizations: Vec::new(),
+        }
+    }
+
+    /// # From observations
+    /// The series of observations written by a run at the given temperature and field h,
+    /// recovering the bonds from their reduced energies -(Σ s_i s_j + h Σ s_i)/T per site.
+    pub fn from_observations(
+        observations: &[Observation],
+        number_of_sites: usize,
+        temperature: Temperature,
+        field: f64,
+    ) -> Self {
+        let mut series = Self::new(number_of_sites, temperature, field);
+        for observation in observations {
+            series.bonds.push(
+                -(observation.energy * temperature.value() + field * observation.magnetization),
+            );
+            series.magnetizations.push(observation.magnetization);
+        }
+        series
+    }
+
+    /// # Push a configuration
+    /// Measures a configuration, such as a frame of a trajectory, and appends it to the series.
+    pub fn push_grid(&mut self, grid: &Grid) {
+        // The bonds are the part of the energy that the coupling multiplies.
+        let bonds = grid.energy_reduced(0.0, 0.0) - grid.energy_reduced(1.0, 0.0);
+        let occupied = grid.number_of_occupied_sites() as f64;
+        self.bonds.push(bonds / occupied);
+        self.magnetizations.push(grid.magnetization() / occupied);
+    }
+
+    /// # Number of measurements
+    pub fn len(&self) -> usize {
+        self.bonds.len()
+    }
+
+    /// # Is empty
+    /// Whether the series holds no measurements.
+    pub fn is_empty(&self) -> bool {
+        self.bonds.is_empty()
+    }
+
+    /// # Energies
+    /// The energy per site of every measurement in units of the coupling.
+    pub fn energies(&self) -> Vec<f64> {
+        self.bonds
+            .iter()
+            .zip(&self.magnetizations)
+            .map(|(bonds, magnetization)| -(bonds + self.field * magnetization))
+            .collect()
+    }
+
+    /// # Analyze
+    /// The observables at the temperature of the run.
+    pub fn analyze(&self) -> Analysis {
+        self.reweight(self.temperature)
+    }
+
+    /// # Reweight
+    /// The observables at another temperature and the same field, weighting every measurement by
+    /// exp(-(1/T' - 1/T) N e) with its energy per site e. The result is only reliable while the
+    /// energy histograms of the two temperatures overlap, which for N sites means that they
+    /// differ by much less than T/√(N C).
+    pub fn reweight(&self, temperature: Temperature) -> Analysis {
+        let energies = self.energies();
+        let number_of_sites = self.number_of_sites as f64;
+        let exponents: Vec<f64> = energies
+            .iter()
+            .map(|energy| {
+                -(temperature.beta() - self.temperature.beta()) * number_of_sites * energy
+            })
+            .collect();
+        let largest = exponents.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+
+        let length = self.len() / BLOCKS;
+        let mut total = Sums::default();
+        let mut blocks = vec![Sums::default(); BLOCKS];
+        for (index, ((energy, magnetization), exponent)) in energies
+            .iter()
+            .zip(&self.magnetizations)
+            .zip(&exponents)
+            .enumerate()
+        {
+            let weight = (exponent - largest).exp();
+            total.add(weight, *energy, *magnetization);
+            if length > 0 && index < BLOCKS * length {
+                blocks[index / length].add(weight, *energy, *magnetization);
+            }
+        }
+
+        let values = total.observables(number_of_sites, temperature.value());
+        let mut errors = [f64::NAN; 6];
+        if length > 0 {
+            let estimates: Vec<[f64; 6]> = blocks
+                .iter()
+                .map(|block| {
+                    total
+                        .without(block)
+                        .observables(number_of_sites, temperature.value())
+                })
+                .collect();
+            for (observable, error) in errors.iter_mut().enumerate() {
+                let mean = estimates
+                    .iter()
+                    .map(|estimate| estimate[observable])
+                    .sum::<f64>()
+                    / BLOCKS as f64;
+                let spread = estimates
+                    .iter()
+                    .map(|estimate| (estimate[observable] - mean).powi(2))
+                    .sum::<f64>();
+                *error = ((BLOCKS - 1) as f64 / BLOCKS as f64 * spread).sqrt();
+            }
+        }
+        let estimate = |observable: usize| Measurement::new(values[observable], errors[observable]);
+        let absolute: Vec<f64> = self.magnetizations.iter().map(|m| m.abs()).collect();
+        Analysis {
+            temperature,
+            energy: estimate(0),
+            magnetization: estimate(1),
+            absolute_magnetization: estimate(2),
+            susceptibility: estimate(3),
+            specific_heat: estimate(4),
+            binder_cumulant: estimate(5),
+            energy_time: integrated_autocorrelation_time(&energies),
+            absolute_magnetization_time: integrated_autocorrelation_time(&absolute),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::simulation::{Simulation, SimulationParameters};
+    use crate::spin::Spin;
+
+    #[test]
+    fn test_blocked_mean() {
+        // Alternating values average out within every block.
+        let values: Vec<f64> = (0..64)
+            .map(|i| if i % 2 == 0 { 1.0 } else { 3.0 })
+            .collect();
+        assert_eq!(blocked_mean(&values), Measurement::new(2.0, 0.0));
+
+        // Blocks of constant values give the standard error of the block means.
+        let values: Vec<f64> = (0..32).map(|i| (i / 2 % 2) as f64).collect();
+        let mean = blocked_mean(&values);
+        assert_eq!(mean.value, 0.5);
+        assert!((mean.error - (0.25 * 16.0 / 15.0 / 16.0_f64).sqrt()).abs() < 1e-12);
+
+        assert!(blocked_mean(&[1.0, 2.0]).error.is_nan());
+    }
+
+    #[test]
+    fn test_integrated_autocorrelation_time() {
+        assert_eq!(integrated_autocorrelation_time(&[1.0; 10]), 0.5);
+
+        // An autoregressive series x_t = a x_(t-1) + noise has τ_int = (1 + a)/(2 (1 - a)).
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let correlation = 0.8;
+        let mut value = 0.0;
+        let values: Vec<f64> = (0..200_000)
+            .map(|_| {
+                value = correlation * value + rng.gen_range(-1.0..1.0);
+                value
+            })
+            .collect();
+        let expected = (1.0 + correlation) / (2.0 * (1.0 - correlation));
+        let time = integrated_autocorrelation_time(&values);
+        assert!((time - expected).abs() < 0.1 * expected, "{}", time);
+    }
+
+    #[test]
+    fn test_time_series() {
+        let observations: Vec<Observation> = (0..32)
+            .map(|sweep| Observation {
+                sweep,
+                energy: -0.5 * (1.0 + (sweep % 2) as f64) - 0.1 * 0.25,
+                magnetization: 0.25,
+                acceptance: 0.0,
+            })
+            .collect();
+        let temperature = Temperature::new(2.0).unwrap();
+        let series = TimeSeries::from_observations(&observations, 16, temperature, 0.2);
+        assert_eq!(series.len(), 32);
+        assert_eq!((series.temperature, series.field), (temperature, 0.2));
+        assert!((series.bonds[1] - 2.0).abs() < 1e-12);
+
+        // The energies in units of the coupling are those of the observations times T.
+        let analysis = series.analyze();
+        assert!((analysis.energy.value + 1.55).abs() < 1e-12);
+        assert!(analysis.energy.error.abs() < 1e-12);
+        assert!((analysis.specific_heat.value - 16.0 * 0.25 / 4.0).abs() < 1e-12);
+        assert_eq!(analysis.magnetization.value, 0.25);
+        assert!(analysis.susceptibility.value.abs() < 1e-12);
+
+        // Cooling favours the lower of the two energies, whose weight grows by exp(N Δ(1/T)).
+        let cold = series.reweight(Temperature::new(1.0).unwrap());
+        let ratio = (16.0 * 0.5_f64).exp();
+        let expected =
+            -(2.0 + 0.2 * 0.25) * ratio / (1.0 + ratio) - (1.0 + 0.2 * 0.25) / (1.0 + ratio);
+        assert!((cold.energy.value - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_reweighting_matches_simulation() {
+        // Reweighting a run a little way to a nearby temperature agrees with a run there.
+        let run = |coupling: f64| {
+            let mut simulation = Simulation::with_seed(
+                Grid::new_constant(8, 8, Spin::Up).unwrap(),
+                SimulationParameters {
+                    coupling,
+                    field: 0.0,
+                },
+                11,
+            )
+            .unwrap();
+            let mut series = TimeSeries::new(64, Temperature::from_beta(coupling).unwrap(), 0.0);
+            for sweep in 0..20_000 {
+                simulation.step();
+                if sweep >= 1000 {
+                    series.push_grid(simulation.grid());
+                }
+            }
+            series
+        };
+        let reweighted = run(0.4).reweight(Temperature::from_beta(0.41).unwrap());
+        let direct = run(0.41).analyze();
+        let difference = (reweighted.energy.value - direct.energy.value).abs();
+        let error = reweighted.energy.error.hypot(direct.energy.error);
+        assert!(difference < 4.0 * error, "{} ± {}", difference, error);
+        assert!(reweighted.energy_time >= 0.5);
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+use std::str::FromStr;
+use std::thread;
+use std::time::Instant;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::error::{check_size, Error, Result};
+use crate::grid::Grid;
+use crate::lattice::Hypercubic;
+use crate::model::IsingModel;
+use crate::random_cluster::swendsen_wang_step;
+
+/// # Benchmarked algorithm
+/// An update whose speed can be measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Sequential Metropolis sweeps of a `Grid`, the update of `Simulation`.
+    Metropolis,
+    /// Random-site Metropolis sweeps of an `IsingModel` on a square `Hypercubic` lattice.
+    Hypercubic,
+    /// Swendsen–Wang cluster updates of a `Grid`.
+    SwendsenWang,
+}
+
+impl Algorithm {
+    /// # All algorithms
+    pub const ALL: [Algorithm; 3] = [
+        Algorithm::Metropolis,
+        Algorithm::Hypercubic,
+        Algorithm::SwendsenWang,
+    ];
+
+    /// # Name
+    /// The name the algorithm is given on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Metropolis => "metropolis",
+            Algorithm::Hypercubic => "hypercubic",
+            Algorithm::SwendsenWang => "swendsen-wang",
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(Algorithm::name).collect();
+                format!(
+                    "unknown algorithm {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// # Benchmark result
+/// The speed of one algorithm on grids of one size, summed over the threads that each updated a
+/// grid of their own: the sweeps of all the grids per second of wall-clock time, and the spin
+/// updates, a sweep being one update per site.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BenchResult {
+    pub size: usize,
+    pub algorithm: Algorithm,
+    pub threads: usize,
+    pub elapsed_seconds: f64,
+    pub sweeps_per_second: f64,
+    pub updates_per_second: f64,
+}
+
+/// # Benchmark
+/// Every combination of the given square grid sizes, algorithms and thread counts, each timed
+/// over a number of sweeps at the given reduced coupling after a tenth as many sweeps of warm-up.
+/// The threads update independent grids, as the runs of a scan do, so the results show how the
+/// throughput of a campaign grows with the cores.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Benchmark {
+    pub sizes: Vec<usize>,
+    pub algorithms: Vec<Algorithm>,
+    pub threads: Vec<usize>,
+    pub sweeps: usize,
+    pub coupling: f64,
+}
+
+impl Benchmark {
+    /// # Run
+    /// Times every combination in turn, so that they do not compete for the cores, and returns
+    /// the results by size, then algorithm, then number of threads. Fails if a size has no sites
+    /// or a thread count is zero.
+    pub fn run(&self) -> Result<Vec<BenchResult>> {
+        for &size in &self.sizes {
+            check_size(size, size)?;
+        }
+        if self.threads.contains(&0) {
+            return Err(Error::InvalidParameter {
+                name: "threads",
+                value: 0.0,
+            });
+        }
+        let mut results = Vec::new();
+        for &size in &self.sizes {
+            for &algorithm in &self.algorithms {
+                for &threads in &self.threads {
+                    results.push(self.run_case(size, algorithm, threads));
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    fn run_case(&self, size: usize, algorithm: Algorithm, threads: usize) -> BenchResult {
+        let elapsed_seconds = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|thread| scope.spawn(move || self.time(size, algorithm, thread as u64)))
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .fold(0.0, f64::max)
+        });
+        let sweeps = (threads * self.sweeps) as f64;
+        BenchResult {
+            size,
+            algorithm,
+            threads,
+            elapsed_seconds,
+            sweeps_per_second: sweeps / elapsed_seconds,
+            updates_per_second: sweeps * (size * size) as f64 / elapsed_seconds,
+        }
+    }
+
+    /// Warms up a grid and returns the seconds its timed sweeps took.
+    fn time(&self, size: usize, algorithm: Algorithm, seed: u64) -> f64 {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let warm_up = self.sweeps / 10;
+        match algorithm {
+            Algorithm::Metropolis => {
+                let mut grid = Grid::new_random(size, size).expect("the sizes were checked");
+                let mut sweep = || {
+                    grid.step_reduced_with_rng(self.coupling, 0.0, &mut rng);
+                };
+                timed(warm_up, self.sweeps, &mut sweep)
+            }
+            Algorithm::Hypercubic => {
+                let lattice = Hypercubic::new([size, size]).expect("the sizes were checked");
+                let mut model = IsingModel::new_random(lattice).expect("the lattice has sites");
+                timed(warm_up, self.sweeps, &mut || {
+                    model.step_reduced(self.coupling, 0.0)
+                })
+            }
+            Algorithm::SwendsenWang => {
+                let mut grid = Grid::new_random(size, size).expect("the sizes were checked");
+                let mut sweep = || {
+                    swendsen_wang_step(&mut grid, self.coupling, &mut rng);
+                };
+                timed(warm_up, self.sweeps, &mut sweep)
+            }
+        }
+    }
+}
+
+/// Runs the warm-up sweeps, then returns the seconds the timed sweeps took.
+fn timed(warm_up: usize, sweeps: usize, sweep: &mut dyn FnMut()) -> f64 {
+    for _ in 0..warm_up {
+        sweep();
+    }
+    let start = Instant::now();
+    for _ in 0..sweeps {
+        sweep();
+    }
+    start.elapsed().as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_algorithm_names() {
+        for algorithm in Algorithm::ALL {
+            assert_eq!(algorithm.to_string().parse(), Ok(algorithm));
+        }
+        assert!("wolff".parse::<Algorithm>().is_err());
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let benchmark = Benchmark {
+            sizes: vec![4, 8],
+            algorithms: Algorithm::ALL.to_vec(),
+            threads: vec![1, 2],
+            sweeps: 20,
+            coupling: 0.44,
+        };
+        let results = benchmark.run().unwrap();
+        assert_eq!(results.len(), 12);
+        assert_eq!(
+            (results[5].size, results[5].algorithm, results[5].threads),
+            (4, Algorithm::SwendsenWang, 2)
+        );
+        for result in &results {
+            assert!(result.elapsed_seconds > 0.0);
+            let sites = (result.size * result.size) as f64;
+            let ratio = result.updates_per_second / result.sweeps_per_second;
+            assert!((ratio - sites).abs() < 1e-9 * sites);
+        }
+        let empty = Benchmark {
+            sizes: vec![0],
+            ..benchmark
+        };
+        assert!(empty.run().is_err());
+    }
+}
@@ -0,0 +1,406 @@
+use rand::Rng;
+
+use crate::couplings::BondDirection;
+use crate::error::{Error, Result};
+use crate::grid::{BoundaryCondition, Grid, NEIGHBOR_OFFSETS};
+use crate::random_cluster::BondConfiguration;
+use crate::spin::Spin;
+
+/// # Exchange range
+/// Which pairs of spins an exchange move may swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExchangeRange {
+    /// Nearest neighbours only, as in Kawasaki dynamics, so the magnetization is also conserved
+    /// locally and the moves follow the physical coarsening of the domains.
+    Local,
+    /// Any two sites of the grid. The equilibrium states are the same, but they are reached much
+    /// faster because a spin can cross the grid in one move.
+    Global,
+}
+
+/// # Exchange step
+/// Performs one sweep of exchange moves, each of which picks a pair of sites and swaps their spins
+/// with the Metropolis probability min(1, exp(-ΔE)). Swapping two spins keeps the number of spins
+/// of each value, so the grid samples the ensemble at fixed magnetization; with a crystal field
+/// the number of zero spins is fixed too. Pairs with equal spins, vacancies or pinned sites are
+/// skipped. Returns the fraction of the moves that were accepted.
+pub fn exchange_step<R: Rng>(
+    grid: &mut Grid,
+    coupling: f64,
+    field: f64,
+    range: ExchangeRange,
+    rng: &mut R,
+) -> f64 {
+    let (width, height) = (grid.width(), grid.height());
+    let number_of_sites = width * height;
+    let mut accepted = 0;
+    for _ in 0..number_of_sites {
+        let site = rng.gen_range(0..number_of_sites);
+        let (x, y) = ((site % width) as i64, (site / width) as i64);
+        let (other_x, other_y) = match range {
+            ExchangeRange::Local => {
+                let (dx, dy) = NEIGHBOR_OFFSETS[rng.gen_range(0..4)];
+                (x + dx, y + dy)
+            }
+            ExchangeRange::Global => {
+                let other = rng.gen_range(0..number_of_sites);
+                ((other % width) as i64, (other / width) as i64)
+            }
+        };
+        let (spin, other_spin) = (grid.get(x, y), grid.get(other_x, other_y));
+        if spin == other_spin
+            || spin == Spin::Vacant
+            || other_spin == Spin::Vacant
+            || grid.is_pinned(x, y)
+            || grid.is_pinned(other_x, other_y)
+        {
+            continue;
+        }
+
+        // The energies at the two sites count every bond that the swap changes once. A bond
+        // between the two sites is counted twice, but its energy does not change.
+        let site_energies = |grid: &Grid| {
+            grid.total_energy(x, y, coupling, field)
+                + grid.total_energy(other_x, other_y, coupling, field)
+        };
+        let energy_before = site_energies(grid);
+        grid.set(x, y, other_spin);
+        grid.set(other_x, other_y, spin);
+        let energy_change = site_energies(grid) - energy_before;
+        if energy_change <= 0.0 || rng.gen::<f64>() < (-energy_change).exp() {
+            accepted += 1;
+        } else {
+            grid.set(x, y, spin);
+            grid.set(other_x, other_y, other_spin);
+        }
+    }
+    accepted as f64 / number_of_sites as f64
+}
+
+/// # Geometric cluster step
+/// Performs one move of the geometric cluster algorithm of Heringa and Blöte, which reflects a
+/// cluster of spins through a random pivot and so, like the exchange moves, keeps the number of
+/// spins of each value. The cluster starts from a random site and its image, and grows in pairs:
+/// a neighbour k of a site j of the cluster joins together with its image k' with probability
+/// 1 - exp(-ΔE), where ΔE = K (s_j - s_j')(s_k - s_k') is the energy that swapping j with j' but
+/// not k with k' would cost the two bonds. All the pairs of the cluster then swap their spins,
+/// which needs no acceptance step. Large domains move in one step, so the moves do not slow down
+/// at low temperature as the local exchanges do. A cluster that contains a vacant or pinned site
+/// is left in place. The grid needs periodic boundaries, uniform couplings without a diagonal
+/// part and no field map, since the reflection must leave the energy unchanged. Returns the number
+/// of sites whose spins changed, or fails if the grid is not supported.
+pub fn geometric_cluster_step<R: Rng>(
+    grid: &mut Grid,
+    coupling: f64,
+    rng: &mut R,
+) -> Result<usize> {
+    if grid.boundary_conditions() != (BoundaryCondition::Periodic, BoundaryCondition::Periodic) {
+        return Err(Error::Unsupported(
+            "the geometric cluster update needs periodic boundaries",
+        ));
+    }
+    if grid.next_nearest_ratio() != 0.0
+        || grid.bond_couplings().is_some()
+        || grid.field_map().is_some()
+    {
+        return Err(Error::Unsupported(
+            "the geometric cluster update needs uniform nearest-neighbour couplings and fields",
+        ));
+    }
+    let (width, height) = (grid.width() as i64, grid.height() as i64);
+    // Reflecting through the pivot maps the site (x, y) to (p_x - x, p_y - y), with the pivot
+    // on a site or halfway between two.
+    let (pivot_x, pivot_y) = (rng.gen_range(0..width), rng.gen_range(0..height));
+    let image = |(x, y): (i64, i64)| {
+        (
+            (pivot_x - x).rem_euclid(width),
+            (pivot_y - y).rem_euclid(height),
+        )
+    };
+    let index = |(x, y): (i64, i64)| (y * width + x) as usize;
+
+    let start = (rng.gen_range(0..width), rng.gen_range(0..height));
+    let mut in_cluster = vec![false; (width * height) as usize];
+    in_cluster[index(start)] = true;
+    in_cluster[index(image(start))] = true;
+    let mut cluster = vec![start];
+    let mut stack = vec![start];
+    while let Some((x, y)) = stack.pop() {
+        let (image_x, image_y) = image((x, y));
+        let difference = grid.get_spin_as_float(x, y) - grid.get_spin_as_float(image_x, image_y);
+        for (dx, dy) in NEIGHBOR_OFFSETS {
+            let neighbor = ((x + dx).rem_euclid(width), (y + dy).rem_euclid(height));
+            if in_cluster[index(neighbor)] {
+                continue;
+            }
+            let neighbor_image = image(neighbor);
+            let energy_change = coupling
+                * difference
+                * (grid.get_spin_as_float(neighbor.0, neighbor.1)
+                    - grid.get_spin_as_float(neighbor_image.0, neighbor_image.1));
+            if energy_change > 0.0 && rng.gen::<f64>() < 1.0 - (-energy_change).exp() {
+                in_cluster[index(neighbor)] = true;
+                in_cluster[index(neighbor_image)] = true;
+                cluster.push(neighbor);
+                stack.push(neighbor);
+            }
+        }
+    }
+
+    let movable = |(x, y): (i64, i64)| grid.get(x, y) != Spin::Vacant && !grid.is_pinned(x, y);
+    if !cluster
+        .iter()
+        .all(|&site| movable(site) && movable(image(site)))
+    {
+        return Ok(0);
+    }
+    let mut swapped = 0;
+    for site in cluster {
+        let other = image(site);
+        let (spin, other_spin) = (grid.get(site.0, site.1), grid.get(other.0, other.1));
+        if spin != other_spin {
+            grid.set(site.0, site.1, other_spin);
+            grid.set(other.0, other.1, spin);
+            swapped += 2;
+        }
+    }
+    Ok(swapped)
+}
+
+/// # Droplet shape
+/// The shape of the largest domain of the minority spins at fixed magnetization. On a periodic
+/// grid at low temperature a small excess of minority spins stays dissolved as a gas, a larger
+/// one condenses into a droplet, and beyond a fraction 1/π of the sites the domain becomes a
+/// stripe that wraps around the grid, since two straight interfaces are then shorter than the
+/// boundary of a circle of the same area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropletShape {
+    /// The domain does not wrap around the grid.
+    Droplet,
+    /// The domain wraps around the grid along one axis.
+    Stripe,
+    /// The domain wraps around the grid along both axes, as the majority does.
+    Spanning,
+}
+
+/// # Droplet
+/// The largest connected domain of nearest-neighbour spins of a given value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Droplet {
+    size: usize,
+    perimeter: usize,
+    number_of_spins: usize,
+    wraps: (bool, bool),
+}
+
+impl Droplet {
+    /// # Find the largest droplet
+    /// Finds the largest domain of the given spin value on a periodic grid, or `None` if the grid
+    /// holds no such spin.
+    pub fn largest(grid: &Grid, spin: Spin) -> Option<Self> {
+        let (width, height) = (grid.width(), grid.height());
+        let neighbor_offset = |direction| match direction {
+            BondDirection::Horizontal => (1, 0),
+            BondDirection::Vertical => (0, 1),
+        };
+        let bonds = BondConfiguration::from_fn(width, height, |x, y, direction| {
+            let (dx, dy) = neighbor_offset(direction);
+            let (x, y) = (x as i64, y as i64);
+            grid.get(x, y) == spin && grid.get(x + dx, y + dy) == spin
+        });
+        let clusters = bonds.clusters();
+
+        let mut number_of_spins = 0;
+        let mut sizes = vec![0; clusters.number_of_clusters()];
+        for y in 0..height {
+            for x in 0..width {
+                if grid.get(x as i64, y as i64) == spin {
+                    number_of_spins += 1;
+                    sizes[clusters.label(x, y)] += 1;
+                }
+            }
+        }
+        let (label, &size) = sizes
+            .iter()
+            .enumerate()
+            .filter(|(_, &size)| size > 0)
+            .max_by_key(|(_, &size)| size)?;
+
+        let mut perimeter = 0;
+        for y in 0..height {
+            for x in 0..width {
+                if clusters.label(x, y) != label {
+                    continue;
+                }
+                let (x, y) = (x as i64, y as i64);
+                perimeter += NEIGHBOR_OFFSETS
+                    .iter()
+                    .filter(|&&(dx, dy)| grid.get(x + dx, y + dy) != spin)
+                    .count();
+            }
+        }
+
+        Some(Self {
+            size,
+            perimeter,
+            number_of_spins,
+            wraps: clusters.cluster_wraps(label),
+        })
+    }
+
+    /// # Size
+    /// The number of sites in the droplet.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// # Perimeter
+    /// The number of bonds between the droplet and the other spins, its interface length in
+    /// lattice units.
+    pub fn perimeter(&self) -> usize {
+        self.perimeter
+    }
+
+    /// # Condensed fraction
+    /// The fraction of the spins of the droplet's value that belong to it. It jumps from near
+    /// zero to near one at the evaporation–condensation transition.
+    pub fn condensed_fraction(&self) -> f64 {
+        self.size as f64 / self.number_of_spins as f64
+    }
+
+    /// # Compactness
+    /// The ratio 4√A / P of the perimeter of a square of the same area to the perimeter of the
+    /// droplet, which is one for a square and smaller for ragged or elongated domains.
+    pub fn compactness(&self) -> f64 {
+        4.0 * (self.size as f64).sqrt() / self.perimeter as f64
+    }
+
+    /// # Shape
+    /// Whether the droplet is a droplet, a stripe or spans the grid.
+    pub fn shape(&self) -> DropletShape {
+        match self.wraps {
+            (false, false) => DropletShape::Droplet,
+            (true, true) => DropletShape::Spanning,
+            _ => DropletShape::Stripe,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::analysis::blocked_mean;
+
+    fn number_up(grid: &Grid) -> usize {
+        (0..grid.height() as i64)
+            .flat_map(|y| (0..grid.width() as i64).map(move |x| (x, y)))
+            .filter(|&(x, y)| grid.get(x, y) == Spin::Up)
+            .count()
+    }
+
+    #[test]
+    fn test_exchange_conserves_magnetization() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut grid = Grid::new_with_magnetization(12, 12, 0.25, &mut rng).unwrap();
+        let initial = number_up(&grid);
+        for range in [ExchangeRange::Local, ExchangeRange::Global] {
+            for _ in 0..20 {
+                let acceptance = exchange_step(&mut grid, 0.3, 0.5, range, &mut rng);
+                assert!(acceptance > 0.0);
+            }
+            assert_eq!(number_up(&grid), initial);
+        }
+    }
+
+    #[test]
+    fn test_geometric_cluster_samples_fixed_magnetization() {
+        // The mean energy of a 4 × 4 grid with eight up spins, by enumerating its 12870 states.
+        let coupling = 0.5;
+        let (mut weights, mut energies) = (0.0, 0.0);
+        for state in 0_u32..1 << 16 {
+            if state.count_ones() != 8 {
+                continue;
+            }
+            let grid = Grid::from_fn(4, 4, |x, y| {
+                if state >> (y * 4 + x) & 1 == 1 {
+                    Spin::Up
+                } else {
+                    Spin::Down
+                }
+            })
+            .unwrap();
+            let energy = grid.energy_reduced(coupling, 0.0);
+            weights += (-energy).exp();
+            energies += energy * (-energy).exp();
+        }
+        let expected = energies / weights;
+
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut grid = Grid::new_with_magnetization(4, 4, 0.0, &mut rng).unwrap();
+        let energies: Vec<f64> = (0..50_000)
+            .map(|_| {
+                geometric_cluster_step(&mut grid, coupling, &mut rng).unwrap();
+                grid.energy_reduced(coupling, 0.0)
+            })
+            .collect();
+        assert_eq!(number_up(&grid), 8);
+        let mean = blocked_mean(&energies);
+        assert!(
+            (mean.value - expected).abs() < 4.0 * mean.error,
+            "{} against {}",
+            mean,
+            expected
+        );
+
+        grid.set_next_nearest_ratio(0.5);
+        assert!(matches!(
+            geometric_cluster_step(&mut grid, coupling, &mut rng),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_droplet_observables() {
+        let grid = Grid::new_droplet(16, 16, 3.0).unwrap();
+        let droplet = Droplet::largest(&grid, Spin::Up).unwrap();
+        assert_eq!(droplet.size(), 32);
+        assert_eq!(droplet.perimeter(), 24);
+        assert_eq!(droplet.condensed_fraction(), 1.0);
+        assert_eq!(droplet.shape(), DropletShape::Droplet);
+
+        let stripes = Grid::new_stripes(16, 16, 4).unwrap();
+        let stripe = Droplet::largest(&stripes, Spin::Down).unwrap();
+        assert_eq!(stripe.size(), 64);
+        assert_eq!(stripe.condensed_fraction(), 0.5);
+        assert_eq!(stripe.shape(), DropletShape::Stripe);
+
+        let empty = Grid::new_constant(4, 4, Spin::Down).unwrap();
+        assert!(Droplet::largest(&empty, Spin::Up).is_none());
+        assert_eq!(
+            Droplet::largest(&empty, Spin::Down).unwrap().shape(),
+            DropletShape::Spanning
+        );
+    }
+
+    #[test]
+    fn test_droplet_and_stripe() {
+        // Deep in the ordered phase a few minority spins condense into a droplet, while at zero
+        // magnetization the domains become stripes.
+        let mut rng = StdRng::seed_from_u64(8);
+        let coupling = 0.7;
+        let mut shapes = Vec::new();
+        for magnetization in [0.8, 0.0] {
+            let mut grid = Grid::new_with_magnetization(16, 16, magnetization, &mut rng).unwrap();
+            for _ in 0..1000 {
+                exchange_step(&mut grid, coupling, 0.0, ExchangeRange::Global, &mut rng);
+            }
+            let droplet = Droplet::largest(&grid, Spin::Down).unwrap();
+            assert!(droplet.condensed_fraction() > 0.7);
+            shapes.push(droplet.shape());
+        }
+        assert_eq!(shapes, [DropletShape::Droplet, DropletShape::Stripe]);
+    }
+}
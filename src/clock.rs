@@ -0,0 +1,292 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::error::{check_size, Error, Result};
+use crate::lattice::{Hypercubic, Lattice};
+use crate::temperature::Temperature;
+
+/// # Clock model
+/// The q-state clock model on a periodic width × height grid. Every site holds a planar spin that
+/// points at one of q equally spaced angles θ = 2πn/q, and neighbours interact through the cosine
+/// of their relative angle, E = -K Σ_⟨ij⟩ cos(θ_i - θ_j), in the same reduced units as
+/// `Grid::step`. For q = 2 it is the Ising model and for q = 4 two decoupled Ising models, while
+/// for q ≥ 5 an intermediate critical phase opens up between two Kosterlitz–Thouless transitions,
+/// which merge into the single transition of the XY model as q grows.
+#[derive(Debug, Clone)]
+pub struct ClockModel {
+    states: Vec<usize>,
+    lattice: Hypercubic<2>,
+    q: usize,
+    cosines: Vec<f64>,
+    sines: Vec<f64>,
+}
+
+impl ClockModel {
+    /// # New random clock model
+    /// Creates a grid where every spin points at a uniformly random angle.
+    pub fn new_random<R: Rng>(width: usize, height: usize, q: usize, rng: &mut R) -> Result<Self> {
+        let mut model = Self::new_constant(width, height, q, 0)?;
+        for state in model.states.iter_mut() {
+            *state = rng.gen_range(0..q);
+        }
+        Ok(model)
+    }
+
+    /// # New constant clock model
+    /// Creates a grid where every spin points at the same angle 2π state / q. Fails if the grid has
+    /// no sites, there are fewer than two states or the state is not one of them.
+    pub fn new_constant(width: usize, height: usize, q: usize, state: usize) -> Result<Self> {
+        check_size(width, height)?;
+        if q < 2 {
+            return Err(Error::InvalidParameter {
+                name: "states",
+                value: q as f64,
+            });
+        }
+        if state >= q {
+            return Err(Error::InvalidParameter {
+                name: "state",
+                value: state as f64,
+            });
+        }
+        let angles = (0..q).map(|n| 2.0 * PI * n as f64 / q as f64);
+        Ok(Self {
+            states: vec![state; width * height],
+            lattice: Hypercubic::new([width, height])?,
+            q,
+            cosines: angles.clone().map(f64::cos).collect(),
+            sines: angles.map(f64::sin).collect(),
+        })
+    }
+
+    /// # Number of states
+    /// The number of angles q.
+    pub fn q(&self) -> usize {
+        self.q
+    }
+
+    /// # Width
+    /// Returns the number of columns in the grid.
+    pub fn width(&self) -> usize {
+        self.lattice.shape()[0]
+    }
+
+    /// # Height
+    /// Returns the number of rows in the grid.
+    pub fn height(&self) -> usize {
+        self.lattice.shape()[1]
+    }
+
+    /// # Get a state
+    /// Retrieves the state n at the given coordinates, with periodic boundary conditions.
+    pub fn get(&self, x: i64, y: i64) -> usize {
+        self.states[self.lattice.site([x, y])]
+    }
+
+    /// # Get an angle
+    /// Retrieves the angle 2πn/q of the spin at the given coordinates.
+    pub fn get_angle(&self, x: i64, y: i64) -> f64 {
+        2.0 * PI * self.get(x, y) as f64 / self.q as f64
+    }
+
+    /// # Set a state
+    /// Sets the state at the given coordinates, with periodic boundary conditions.
+    pub fn set(&mut self, x: i64, y: i64, state: usize) {
+        assert!(state < self.q, "the state must be smaller than q");
+        let site = self.lattice.site([x, y]);
+        self.states[site] = state;
+    }
+
+    /// # Bond cosine
+    /// cos(θ_a - θ_b) for two states, read from a table.
+    fn bond_cosine(&self, a: usize, b: usize) -> f64 {
+        self.cosines[(a + self.q - b) % self.q]
+    }
+
+    /// # Magnetization
+    /// The magnetization vector per site, (⟨cos θ⟩, ⟨sin θ⟩).
+    pub fn magnetization(&self) -> (f64, f64) {
+        let (mut mx, mut my) = (0.0, 0.0);
+        for &state in &self.states {
+            mx += self.cosines[state];
+            my += self.sines[state];
+        }
+        let number_of_sites = self.states.len() as f64;
+        (mx / number_of_sites, my / number_of_sites)
+    }
+
+    /// # Absolute magnetization
+    /// The length of the magnetization vector per site.
+    pub fn absolute_magnetization(&self) -> f64 {
+        let (mx, my) = self.magnetization();
+        mx.hypot(my)
+    }
+
+    /// # Energy
+    /// The energy per site at temperature T in units of the coupling, in the reduced units βE of
+    /// the Boltzmann weight, with every bond counted once.
+    pub fn energy(&self, temperature: Temperature) -> f64 {
+        let coupling = temperature.beta();
+        let mut energy = 0.0;
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                let state = self.get(x, y);
+                energy -= coupling
+                    * (self.bond_cosine(state, self.get(x + 1, y))
+                        + self.bond_cosine(state, self.get(x, y + 1)));
+            }
+        }
+        energy / self.states.len() as f64
+    }
+
+    /// # Heat-bath step
+    /// Draws a new angle for a single site from its conditional distribution given the
+    /// neighbours, P(n) ∝ exp(K Σ_j cos(θ_n - θ_j)).
+    pub fn heat_bath_step<R: Rng>(
+        &mut self,
+        x: i64,
+        y: i64,
+        temperature: Temperature,
+        rng: &mut R,
+    ) {
+        let coupling = temperature.beta();
+        let site = self.lattice.site([x, y]);
+        let neighbors = self.lattice.neighbors(site);
+        let weights: Vec<f64> = (0..self.q)
+            .map(|state| {
+                let local_energy: f64 = neighbors
+                    .iter()
+                    .map(|&neighbor| self.bond_cosine(state, self.states[neighbor]))
+                    .sum();
+                (coupling * local_energy).exp()
+            })
+            .collect();
+
+        let mut threshold = rng.gen::<f64>() * weights.iter().sum::<f64>();
+        let mut new_state = self.q - 1;
+        for (state, weight) in weights.iter().enumerate() {
+            if threshold < *weight {
+                new_state = state;
+                break;
+            }
+            threshold -= weight;
+        }
+        self.states[site] = new_state;
+    }
+
+    /// # Step
+    /// Performs a heat-bath sweep over all the sites at temperature T = 1/K.
+    pub fn step<R: Rng>(&mut self, temperature: Temperature, rng: &mut R) {
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                self.heat_bath_step(x, y, temperature, rng);
+            }
+        }
+    }
+
+    /// # Wolff step
+    /// Grows and reflects a single cluster with Wolff's embedding, and returns its size. A mirror
+    /// line at angle φ = πk/q, with k random, maps the q angles onto each other by n → k - n. The
+    /// projections s_i = sin(θ_i - φ) onto the normal of the line then behave as Ising spins, and a
+    /// bond joins the cluster with probability 1 - exp(-2K s_i s_j) when s_i s_j > 0.
+    pub fn wolff_step<R: Rng>(&mut self, temperature: Temperature, rng: &mut R) -> usize {
+        let coupling = temperature.beta();
+        let mirror = rng.gen_range(0..self.q);
+        // sin(θ_n - φ) = sin(π (2n - k) / q), which only depends on 2n - k.
+        let q = self.q;
+        let projection =
+            |state: usize| (PI * (2.0 * state as f64 - mirror as f64) / q as f64).sin();
+        let reflect = |state: usize| (mirror + q - state) % q;
+
+        let seed = rng.gen_range(0..self.states.len());
+        let mut in_cluster = vec![false; self.states.len()];
+        in_cluster[seed] = true;
+        let mut stack = vec![(seed, projection(self.states[seed]))];
+        self.states[seed] = reflect(self.states[seed]);
+        let mut size = 1;
+
+        while let Some((site, site_projection)) = stack.pop() {
+            for &neighbor in self.lattice.neighbors(site) {
+                if in_cluster[neighbor] {
+                    continue;
+                }
+                let neighbor_projection = projection(self.states[neighbor]);
+                let product = site_projection * neighbor_projection;
+                if product > 0.0 && rng.gen::<f64>() < 1.0 - (-2.0 * coupling * product).exp() {
+                    in_cluster[neighbor] = true;
+                    self.states[neighbor] = reflect(self.states[neighbor]);
+                    stack.push((neighbor, neighbor_projection));
+                    size += 1;
+                }
+            }
+        }
+        size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::exact::ExactEnumeration;
+
+    #[test]
+    fn test_ordered_observables() {
+        let model = ClockModel::new_constant(4, 4, 6, 1).unwrap();
+        assert!((model.energy(Temperature::new(1.0).unwrap()) + 2.0).abs() < 1e-12);
+        let (mx, my) = model.magnetization();
+        assert!((mx - 0.5).abs() < 1e-12);
+        assert!((my - 0.75_f64.sqrt()).abs() < 1e-12);
+        assert!((model.absolute_magnetization() - 1.0).abs() < 1e-12);
+
+        assert!(ClockModel::new_constant(4, 0, 6, 1).is_err());
+        assert!(ClockModel::new_constant(4, 4, 6, 6).is_err());
+    }
+
+    #[test]
+    fn test_heat_bath_matches_ising() {
+        // With two states the clock model is the Ising model.
+        let temperature = Temperature::from_beta(0.4).unwrap();
+        let exact = ExactEnumeration::new(3, 3)
+            .unwrap()
+            .observables(temperature, 0.0);
+        let mut rng = StdRng::seed_from_u64(10);
+        let mut model = ClockModel::new_random(3, 3, 2, &mut rng).unwrap();
+        for _ in 0..1000 {
+            model.step(temperature, &mut rng);
+        }
+        let number_of_sweeps = 40_000;
+        let mut energy = 0.0;
+        for _ in 0..number_of_sweeps {
+            model.step(temperature, &mut rng);
+            energy += model.energy(temperature);
+        }
+        energy /= number_of_sweeps as f64;
+        assert!((energy - exact.energy).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_wolff_matches_heat_bath() {
+        let (q, temperature) = (5, Temperature::from_beta(0.8).unwrap());
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut local = ClockModel::new_random(4, 4, q, &mut rng).unwrap();
+        let mut cluster = ClockModel::new_random(4, 4, q, &mut rng).unwrap();
+        for _ in 0..1000 {
+            local.step(temperature, &mut rng);
+            cluster.wolff_step(temperature, &mut rng);
+        }
+        let number_of_steps = 40_000;
+        let (mut local_energy, mut cluster_energy) = (0.0, 0.0);
+        for _ in 0..number_of_steps {
+            local.step(temperature, &mut rng);
+            cluster.wolff_step(temperature, &mut rng);
+            local_energy += local.energy(temperature);
+            cluster_energy += cluster.energy(temperature);
+        }
+        let difference = (local_energy - cluster_energy) / number_of_steps as f64;
+        assert!(difference.abs() < 0.03);
+    }
+}
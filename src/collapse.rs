@@ -0,0 +1,361 @@
+use crate::measurement::Measurement;
+use crate::temperature::Temperature;
+
+/// # Scaled observable
+/// The observable whose finite-size data is being collapsed. This decides how the exponent ratio
+/// enters the scaled value: the susceptibility is scaled as χ L^(-γ/ν), while the magnetization is
+/// scaled as m L^(β/ν).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaledObservable {
+    Susceptibility,
+    Magnetization,
+}
+
+/// # Data point
+/// A single measurement of an observable at a given temperature. An error of zero means that the
+/// error is unknown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DataPoint {
+    pub temperature: Temperature,
+    pub value: Measurement,
+}
+
+/// # Collapse parameters
+/// The parameters of the finite-size scaling ansatz: the critical temperature, the correlation
+/// length exponent ν, and the exponent ratio (γ/ν or β/ν, depending on the observable).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollapseParameters {
+    pub critical_temperature: Temperature,
+    pub nu: f64,
+    pub exponent_ratio: f64,
+}
+
+/// # Collapse fit
+/// The best-fit parameters found by the optimizer, and the quality of the collapse at those
+/// parameters. A quality close to zero (or close to one when error bars are given) indicates a good
+/// collapse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CollapseFit {
+    pub parameters: CollapseParameters,
+    pub quality: f64,
+    pub iterations: usize,
+}
+
+/// # Data collapse
+/// This struct holds the measurements of one observable across several lattice sizes and finds the
+/// scaling parameters that make the curves fall on top of each other.
+#[derive(Debug, Clone)]
+pub struct DataCollapse {
+    observable: ScaledObservable,
+    data: Vec<(usize, Vec<DataPoint>)>,
+}
+
+impl DataCollapse {
+    /// # New data collapse
+    /// Creates an empty data collapse for the given observable.
+    pub fn new(observable: ScaledObservable) -> Self {
+        Self {
+            observable,
+            data: Vec::new(),
+        }
+    }
+
+    /// # Add a lattice size
+    /// Adds the measurements taken at a single lattice size. The points are sorted by temperature.
+    pub fn add_size(&mut self, size: usize, mut points: Vec<DataPoint>) {
+        points.sort_by(|a, b| a.temperature.value().total_cmp(&b.temperature.value()));
+        self.data.push((size, points));
+    }
+
+    /// # Scaled data
+    /// Returns the data of every lattice size in scaled coordinates, as (x, y) pairs with the
+    /// error bar on y, where x = (T - T_c) L^(1/ν).
+    pub fn scaled(&self, parameters: &CollapseParameters) -> Vec<Vec<(f64, Measurement)>> {
+        let sign = match self.observable {
+            ScaledObservable::Susceptibility => -1.0,
+            ScaledObservable::Magnetization => 1.0,
+        };
+
+        self.data
+            .iter()
+            .map(|(size, points)| {
+                let length = *size as f64;
+                let x_factor = length.powf(1.0 / parameters.nu);
+                let y_factor = length.powf(sign * parameters.exponent_ratio);
+                points
+                    .iter()
+                    .map(|point| {
+                        (
+                            (point.temperature.value() - parameters.critical_temperature.value())
+                                * x_factor,
+                            point.value * y_factor,
+                        )
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// # Quality of the collapse
+    /// Measures the scatter of the scaled data. Every scaled point is compared with the value that
+    /// each of the other lattice sizes predicts at the same x, obtained by linear interpolation.
+    /// When errors are known the squared deviations are weighted by the combined variance,
+    /// otherwise relative deviations are used, so that the result does not depend on the overall
+    /// scale.
+    pub fn quality(&self, parameters: &CollapseParameters) -> f64 {
+        if parameters.nu <= 0.0 || !parameters.nu.is_finite() {
+            return f64::INFINITY;
+        }
+
+        let scaled = self.scaled(parameters);
+        let mut sum_of_deviations = 0.0;
+        let mut number_of_comparisons = 0;
+
+        for (i, curve) in scaled.iter().enumerate() {
+            for (j, other) in scaled.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                for &(x, y) in curve {
+                    let Some(other_y) = interpolate(other, x) else {
+                        continue;
+                    };
+
+                    let difference = y - other_y;
+                    let deviation = if difference.error > 0.0 {
+                        (difference.value / difference.error).powi(2)
+                    } else {
+                        let scale = 0.5 * (y.value.abs() + other_y.value.abs());
+                        if scale > 0.0 {
+                            (difference.value / scale).powi(2)
+                        } else {
+                            0.0
+                        }
+                    };
+                    sum_of_deviations += deviation;
+                    number_of_comparisons += 1;
+                }
+            }
+        }
+
+        // Without any overlap between the curves there is nothing to compare, which is the worst
+        // possible collapse.
+        if number_of_comparisons == 0 {
+            return f64::INFINITY;
+        }
+        sum_of_deviations / number_of_comparisons as f64
+    }
+
+    /// # Optimize
+    /// Minimizes the quality of the collapse with the Nelder–Mead simplex method, starting from
+    /// the given initial guess. Simplex points at a temperature that is not positive count as the
+    /// worst possible collapse.
+    pub fn optimize(&self, initial_guess: CollapseParameters) -> CollapseFit {
+        let start = [
+            initial_guess.critical_temperature.value(),
+            initial_guess.nu,
+            initial_guess.exponent_ratio,
+        ];
+        // The initial simplex spans a few percent around the initial guess.
+        let steps = start.map(|value: f64| 0.05 * value.abs().max(0.1));
+
+        let (best, quality, iterations) = nelder_mead(
+            |point| match Temperature::new(point[0]) {
+                Ok(critical_temperature) => self.quality(&CollapseParameters {
+                    critical_temperature,
+                    nu: point[1],
+                    exponent_ratio: point[2],
+                }),
+                Err(_) => f64::INFINITY,
+            },
+            start,
+            steps,
+            1e-10,
+            5000,
+        );
+
+        CollapseFit {
+            parameters: CollapseParameters {
+                critical_temperature: Temperature::new(best[0])
+                    .unwrap_or(initial_guess.critical_temperature),
+                nu: best[1],
+                exponent_ratio: best[2],
+            },
+            quality,
+            iterations,
+        }
+    }
+}
+
+/// # Interpolate
+/// Linearly interpolates a curve, sorted by x, at the given x. Returns `None` when x lies outside
+/// the range of the curve.
+fn interpolate(curve: &[(f64, Measurement)], x: f64) -> Option<Measurement> {
+    let upper = curve.iter().position(|&(other_x, _)| other_x >= x)?;
+    if upper == 0 {
+        let (first_x, first_y) = curve[0];
+        return (first_x == x).then_some(first_y);
+    }
+
+    let (x0, y0) = curve[upper - 1];
+    let (x1, y1) = curve[upper];
+    let t = (x - x0) / (x1 - x0);
+    Some(y0 * (1.0 - t) + y1 * t)
+}
+
+/// # Nelder–Mead
+/// Minimizes a function of N variables with the downhill simplex method. Returns the best point,
+/// the function value there, and the number of iterations used.
+fn nelder_mead<const N: usize>(
+    function: impl Fn(&[f64; N]) -> f64,
+    start: [f64; N],
+    steps: [f64; N],
+    tolerance: f64,
+    max_iterations: usize,
+) -> ([f64; N], f64, usize) {
+    // Build the initial simplex by stepping along each axis.
+    let mut simplex: Vec<([f64; N], f64)> = (0..=N)
+        .map(|i| {
+            let mut point = start;
+            if i > 0 {
+                point[i - 1] += steps[i - 1];
+            }
+            (point, function(&point))
+        })
+        .collect();
+
+    let mut iterations = 0;
+    while iterations < max_iterations {
+        iterations += 1;
+        simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        let best = simplex[0].1;
+        let worst = simplex[N].1;
+        if (worst - best).abs() <= tolerance * (best.abs() + tolerance) {
+            break;
+        }
+
+        // The centroid of every point except the worst one.
+        let mut centroid = [0.0; N];
+        for (point, _) in &simplex[..N] {
+            for k in 0..N {
+                centroid[k] += point[k] / N as f64;
+            }
+        }
+        let along = |factor: f64| {
+            let mut point = centroid;
+            for k in 0..N {
+                point[k] += factor * (simplex[N].0[k] - centroid[k]);
+            }
+            point
+        };
+
+        let reflected = along(-1.0);
+        let reflected_value = function(&reflected);
+        if reflected_value < simplex[0].1 {
+            let expanded = along(-2.0);
+            let expanded_value = function(&expanded);
+            simplex[N] = if expanded_value < reflected_value {
+                (expanded, expanded_value)
+            } else {
+                (reflected, reflected_value)
+            };
+        } else if reflected_value < simplex[N - 1].1 {
+            simplex[N] = (reflected, reflected_value);
+        } else {
+            let contracted = along(0.5);
+            let contracted_value = function(&contracted);
+            if contracted_value < simplex[N].1 {
+                simplex[N] = (contracted, contracted_value);
+            } else {
+                // Shrink every point towards the best one.
+                let best_point = simplex[0].0;
+                for (point, value) in simplex.iter_mut().skip(1) {
+                    for k in 0..N {
+                        point[k] = best_point[k] + 0.5 * (point[k] - best_point[k]);
+                    }
+                    *value = function(point);
+                }
+            }
+        }
+    }
+
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    (simplex[0].0, simplex[0].1, iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a synthetic susceptibility that obeys finite-size scaling exactly.
+    fn synthetic_collapse(parameters: &CollapseParameters) -> DataCollapse {
+        let mut collapse = DataCollapse::new(ScaledObservable::Susceptibility);
+        for size in [8, 16, 32] {
+            let length = size as f64;
+            let points = (0..41)
+                .map(|i| {
+                    let temperature = 2.0 + 0.0125 * i as f64;
+                    let x = (temperature - parameters.critical_temperature.value())
+                        * length.powf(1.0 / parameters.nu);
+                    DataPoint {
+                        temperature: Temperature::new(temperature).unwrap(),
+                        value: Measurement::exact(
+                            length.powf(parameters.exponent_ratio) / (1.0 + x * x),
+                        ),
+                    }
+                })
+                .collect();
+            collapse.add_size(size, points);
+        }
+        collapse
+    }
+
+    #[test]
+    fn test_quality_at_exact_parameters() {
+        let exact = CollapseParameters {
+            critical_temperature: Temperature::new(2.269).unwrap(),
+            nu: 1.0,
+            exponent_ratio: 1.75,
+        };
+        let collapse = synthetic_collapse(&exact);
+        let wrong = CollapseParameters {
+            critical_temperature: Temperature::new(2.3).unwrap(),
+            ..exact
+        };
+
+        assert!(collapse.quality(&exact) < 1e-3);
+        assert!(collapse.quality(&wrong) > collapse.quality(&exact));
+    }
+
+    #[test]
+    fn test_optimize() {
+        let exact = CollapseParameters {
+            critical_temperature: Temperature::new(2.269).unwrap(),
+            nu: 1.0,
+            exponent_ratio: 1.75,
+        };
+        let collapse = synthetic_collapse(&exact);
+        let fit = collapse.optimize(CollapseParameters {
+            critical_temperature: Temperature::new(2.25).unwrap(),
+            nu: 0.9,
+            exponent_ratio: 1.6,
+        });
+
+        assert!((fit.parameters.critical_temperature.value() - 2.269).abs() < 5e-3);
+        assert!((fit.parameters.nu - 1.0).abs() < 0.05);
+        assert!((fit.parameters.exponent_ratio - 1.75).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let curve = [
+            (0.0, Measurement::new(0.0, 6.0)),
+            (1.0, Measurement::new(2.0, 8.0)),
+        ];
+        assert_eq!(interpolate(&curve, 0.5), Some(Measurement::new(1.0, 5.0)));
+        assert_eq!(interpolate(&curve, 0.0), Some(Measurement::new(0.0, 6.0)));
+        assert_eq!(interpolate(&curve, 1.5), None);
+        assert_eq!(interpolate(&curve, -0.5), None);
+    }
+}
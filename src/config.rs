@@ -0,0 +1,292 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::adaptive::{self, Schedule};
+use crate::simulation;
+
+/// # Run configuration
+/// Everything a run of the binary needs, read from a TOML or YAML file so that a protocol can be
+/// kept under version control instead of in shell history. Every section and field is optional
+/// and falls back to the defaults of the binary, and unknown fields are rejected so that a typo
+/// does not go unnoticed. The algorithm is the name of a `Schedule`, or `adaptive` to pick one
+/// during a warm-up. A TOML file looks like
+///
+/// ```toml
+/// algorithm = "metropolis-sequential"
+///
+/// [lattice]
+/// width = 64
+/// height = 64
+///
+/// [model]
+/// coupling = 0.44
+/// field = 0.0
+///
+/// [schedule]
+/// sweeps = 10000
+/// seed = 7
+///
+/// [outputs]
+/// csv = "observables.csv"
+/// png = "final.png"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    pub lattice: LatticeConfig,
+    pub model: ModelConfig,
+    pub algorithm: String,
+    pub schedule: ScheduleConfig,
+    pub outputs: OutputConfig,
+}
+
+/// # Lattice configuration
+/// The size of the periodic grid.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LatticeConfig {
+    pub width: usize,
+    pub height: usize,
+}
+
+/// # Model configuration
+/// The reduced coupling K = J/k_BT and field H = h/k_BT.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelConfig {
+    pub coupling: f64,
+    pub field: f64,
+}
+
+/// # Schedule configuration
+/// The number of sweeps and the seed of the run, which is drawn at random when it is missing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    pub sweeps: usize,
+    pub seed: Option<u64>,
+}
+
+/// # Output configuration
+/// The files the run writes, named like the command-line options that ask for them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub checkpoint: Option<String>,
+    pub csv: Option<String>,
+    pub database: Option<String>,
+    pub dump: Option<String>,
+    pub gif: Option<String>,
+    pub hdf5: Option<String>,
+    pub jsonl: Option<String>,
+    pub png: Option<String>,
+    pub svg: Option<String>,
+    pub trajectory: Option<String>,
+    pub video: Option<String>,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            lattice: LatticeConfig::default(),
+            model: ModelConfig::default(),
+            algorithm: simulation::ALGORITHM.to_string(),
+            schedule: ScheduleConfig::default(),
+            outputs: OutputConfig::default(),
+        }
+    }
+}
+
+impl Default for LatticeConfig {
+    fn default() -> Self {
+        Self {
+            width: 100,
+            height: 100,
+        }
+    }
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self {
+            coupling: 0.44,
+            field: 0.02,
+        }
+    }
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            sweeps: 7000,
+            seed: None,
+        }
+    }
+}
+
+impl OutputConfig {
+    /// # Get an output
+    /// The file of the output with the given name, if it is configured.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        let path = match name {
+            "checkpoint" => &self.checkpoint,
+            "csv" => &self.csv,
+            "database" => &self.database,
+            "dump" => &self.dump,
+            "gif" => &self.gif,
+            "hdf5" => &self.hdf5,
+            "jsonl" => &self.jsonl,
+            "png" => &self.png,
+            "svg" => &self.svg,
+            "trajectory" => &self.trajectory,
+            "video" => &self.video,
+            _ => return None,
+        };
+        path.as_deref()
+    }
+}
+
+impl RunConfig {
+    /// # Load a configuration
+    /// Reads a configuration from a `.yaml` or `.yml` file as YAML, and from any other file as
+    /// TOML.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        let config = if matches!(extension, Some("yaml" | "yml")) {
+            Self::from_yaml(&contents)?
+        } else {
+            Self::from_toml(&contents)?
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// # From TOML
+    /// Parses a configuration written in TOML.
+    pub fn from_toml(contents: &str) -> io::Result<Self> {
+        toml::from_str(contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+    }
+
+    /// # From YAML
+    /// Parses a configuration written in YAML.
+    pub fn from_yaml(contents: &str) -> io::Result<Self> {
+        serde_yaml::from_str(contents)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()))
+    }
+
+    /// # Set a field
+    /// Overrides one field from the command line, given as `section.field=value`, such as
+    /// `schedule.sweeps=500` or `outputs.csv=run.csv`, or `algorithm=...` for the top level. The
+    /// value is read as TOML, falling back to a plain string.
+    pub fn set(&mut self, assignment: &str) -> io::Result<()> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+        let (key, text) = assignment
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("expected key=value, got {}", assignment)))?;
+        let value: Value = toml::from_str::<toml::Table>(&format!("value = {}", text))
+            .ok()
+            .and_then(|mut table| table.remove("value"))
+            .map_or_else(|| Ok(Value::String(text.to_string())), serde_json::to_value)?;
+
+        let mut tree = serde_json::to_value(&*self)?;
+        let mut node = &mut tree;
+        for part in key.split('.') {
+            node = node
+                .get_mut(part)
+                .ok_or_else(|| invalid(format!("unknown configuration key {}", key)))?;
+        }
+        *node = value;
+        let config: Self = serde_json::from_value(tree)
+            .map_err(|error| invalid(format!("invalid value for {}: {}", key, error)))?;
+        config.validate()?;
+        *self = config;
+        Ok(())
+    }
+
+    /// Checks the values that the types alone do not rule out.
+    fn validate(&self) -> io::Result<()> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        if self.lattice.width == 0 || self.lattice.height == 0 {
+            return Err(invalid("the lattice must have at least one site"));
+        }
+        if self.algorithm != adaptive::ALGORITHM {
+            self.algorithm
+                .parse::<Schedule>()
+                .map_err(|error| invalid(&format!("{}, or {}", error, adaptive::ALGORITHM)))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let path = env::temp_dir().join("ising_model_test_config.toml");
+        fs::write(
+            &path,
+            "[lattice]\nwidth = 32\n\n[schedule]\nsweeps = 500\nseed = 9\n\n[outputs]\ncsv = \"run.csv\"\n",
+        )
+        .unwrap();
+        let config = RunConfig::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.lattice.width, 32);
+        assert_eq!(config.lattice.height, 100);
+        assert_eq!(config.model, ModelConfig::default());
+        assert_eq!(config.schedule.seed, Some(9));
+        assert_eq!(config.outputs.get("csv"), Some("run.csv"));
+        assert_eq!(config.outputs.get("png"), None);
+
+        let path = env::temp_dir().join("ising_model_test_config.yaml");
+        fs::write(
+            &path,
+            "model:\n  coupling: 0.3\n  field: -0.1\nschedule:\n  sweeps: 50\n",
+        )
+        .unwrap();
+        let config = RunConfig::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.model.coupling, 0.3);
+        assert_eq!(config.schedule.sweeps, 50);
+    }
+
+    #[test]
+    fn test_invalid_configurations() {
+        assert!(RunConfig::from_toml("[lattice]\nwidht = 3\n").is_err());
+        assert!(RunConfig::from_yaml("model:\n  coupling: strong\n").is_err());
+        let config = RunConfig::from_toml("algorithm = \"wolff\"\n").unwrap();
+        assert!(config.validate().is_err());
+        for algorithm in ["swendsen-wang", "adaptive"] {
+            let config = RunConfig::from_toml(&format!("algorithm = \"{}\"\n", algorithm));
+            assert!(config.unwrap().validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_set() {
+        let mut config = RunConfig::default();
+        config.set("schedule.sweeps=250").unwrap();
+        config.set("model.field=-0.5").unwrap();
+        config.set("schedule.seed=12").unwrap();
+        config.set("outputs.svg=final.svg").unwrap();
+        assert_eq!(config.schedule.sweeps, 250);
+        assert_eq!(config.model.field, -0.5);
+        assert_eq!(config.schedule.seed, Some(12));
+        assert_eq!(config.outputs.svg.as_deref(), Some("final.svg"));
+
+        assert!(config.set("schedule.steps=3").is_err());
+        assert!(config.set("lattice.width=0").is_err());
+        assert!(config.set("schedule.sweeps").is_err());
+        assert_eq!(config.lattice.width, 100);
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// # Bond direction
+/// The direction of a nearest-neighbour bond, pointing from a site to its neighbour at x + 1 or
+/// at y + 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BondDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// # Bond couplings
+/// The strengths J_ij of the nearest-neighbour bonds of a periodic width × height grid, as
+/// multiples of the coupling passed to `Grid::step`. Each site owns the bond to its right and the
+/// bond below it, so the 2N bonds are stored in two flat arrays indexed like the spins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BondCouplings {
+    width: usize,
+    height: usize,
+    horizontal: Vec<f64>,
+    vertical: Vec<f64>,
+}
+
+impl BondCouplings {
+    /// # Uniform couplings
+    /// Creates couplings where every bond has the same strength.
+    pub fn uniform(width: usize, height: usize, value: f64) -> Self {
+        Self {
+            width,
+            height,
+            horizontal: vec![value; width * height],
+            vertical: vec![value; width * height],
+        }
+    }
+
+    /// # Couplings from a function
+    /// Creates couplings by calling `f(x, y, direction)` for the bond that leaves each site in
+    /// each direction.
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        mut f: impl FnMut(usize, usize, BondDirection) -> f64,
+    ) -> Self {
+        let mut couplings = Self::uniform(width, height, 0.0);
+        for y in 0..height {
+            for x in 0..width {
+                couplings.horizontal[y * width + x] = f(x, y, BondDirection::Horizontal);
+                couplings.vertical[y * width + x] = f(x, y, BondDirection::Vertical);
+            }
+        }
+        couplings
+    }
+
+    /// # Load couplings
+    /// Reads the bonds of a width × height grid from a text file. Each line holds the coordinates
+    /// of two neighbouring sites followed by the strength of their bond, as in `x1 y1 x2 y2 J`.
+    /// Bonds that are not listed keep a strength of one, and lines that are empty or start with
+    /// `#` are ignored.
+    pub fn load(path: impl AsRef<Path>, width: usize, height: usize) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let invalid = |line_number: usize, message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line_number + 1, message),
+            )
+        };
+
+        let mut couplings = Self::uniform(width, height, 1.0);
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let &[x1, y1, x2, y2, value] = fields.as_slice() else {
+                return Err(invalid(
+                    line_number,
+                    "expected two pairs of coordinates and a coupling",
+                ));
+            };
+            let coordinates: Vec<i64> = [x1, y1, x2, y2]
+                .iter()
+                .map(|field| field.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(line_number, "coordinates must be integers"))?;
+            let value: f64 = value
+                .parse()
+                .map_err(|_| invalid(line_number, "the coupling must be a number"))?;
+
+            let (x1, y1, x2, y2) = (
+                coordinates[0],
+                coordinates[1],
+                coordinates[2],
+                coordinates[3],
+            );
+            let (x, y, direction) = couplings
+                .bond_between(x1, y1, x2, y2)
+                .ok_or_else(|| invalid(line_number, "the sites are not nearest neighbours"))?;
+            couplings.set(x, y, direction, value);
+        }
+        Ok(couplings)
+    }
+
+    /// # Width
+    /// The number of columns of the grid that the couplings belong to.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows of the grid that the couplings belong to.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Get a coupling
+    /// The strength of the bond leaving the site at the given coordinates in the given direction,
+    /// with periodic boundary conditions.
+    pub fn get(&self, x: i64, y: i64, direction: BondDirection) -> f64 {
+        let index = self.index(x, y);
+        match direction {
+            BondDirection::Horizontal => self.horizontal[index],
+            BondDirection::Vertical => self.vertical[index],
+        }
+    }
+
+    /// # Set a coupling
+    /// Sets the strength of the bond leaving the site at the given coordinates in the given
+    /// direction, with periodic boundary conditions.
+    pub fn set(&mut self, x: i64, y: i64, direction: BondDirection, value: f64) {
+        let index = self.index(x, y);
+        match direction {
+            BondDirection::Horizontal => self.horizontal[index] = value,
+            BondDirection::Vertical => self.vertical[index] = value,
+        }
+    }
+
+    /// # Neighbour couplings
+    /// The strengths of the four bonds of a site, in the order up (y + 1), down, left and right.
+    pub fn neighbor_couplings(&self, x: i64, y: i64) -> [f64; 4] {
+        [
+            self.get(x, y, BondDirection::Vertical),
+            self.get(x, y - 1, BondDirection::Vertical),
+            self.get(x - 1, y, BondDirection::Horizontal),
+            self.get(x, y, BondDirection::Horizontal),
+        ]
+    }
+
+    /// # Bond between two sites
+    /// Finds the site and direction that own the bond between two sites, if they are nearest
+    /// neighbours.
+    fn bond_between(
+        &self,
+        x1: i64,
+        y1: i64,
+        x2: i64,
+        y2: i64,
+    ) -> Option<(i64, i64, BondDirection)> {
+        let (width, height) = (self.width as i64, self.height as i64);
+        let dx = (x2 - x1).rem_euclid(width);
+        let dy = (y2 - y1).rem_euclid(height);
+        if dy == 0 && dx == 1 % width {
+            Some((x1, y1, BondDirection::Horizontal))
+        } else if dy == 0 && dx == width - 1 {
+            Some((x2, y2, BondDirection::Horizontal))
+        } else if dx == 0 && dy == 1 % height {
+            Some((x1, y1, BondDirection::Vertical))
+        } else if dx == 0 && dy == height - 1 {
+            Some((x2, y2, BondDirection::Vertical))
+        } else {
+            None
+        }
+    }
+
+    fn index(&self, x: i64, y: i64) -> usize {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        y * self.width + x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbor_couplings() {
+        let couplings = BondCouplings::from_fn(3, 3, |x, y, direction| {
+            let base = (y * 3 + x) as f64;
+            match direction {
+                BondDirection::Horizontal => base,
+                BondDirection::Vertical => -base,
+            }
+        });
+        // The site (0, 0) borrows its left bond from (2, 0) and its lower bond from (0, 2).
+        assert_eq!(couplings.neighbor_couplings(0, 0), [-0.0, -6.0, 2.0, 0.0]);
+        assert_eq!(couplings.neighbor_couplings(1, 1), [-4.0, -1.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn test_load_couplings() {
+        let path = std::env::temp_dir().join("ising_model_test_load_couplings.txt");
+        fs::write(
+            &path,
+            "# Two modified bonds\n0 0 1 0 -1.5\n\n2 3 2 0 0.25\n",
+        )
+        .unwrap();
+        let couplings = BondCouplings::load(&path, 4, 4).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(couplings.get(0, 0, BondDirection::Horizontal), -1.5);
+        assert_eq!(couplings.get(2, 3, BondDirection::Vertical), 0.25);
+        assert_eq!(couplings.get(2, 0, BondDirection::Vertical), 1.0);
+        assert_eq!(couplings.neighbor_couplings(1, 0)[2], -1.5);
+    }
+
+    #[test]
+    fn test_load_invalid_couplings() {
+        let path = std::env::temp_dir().join("ising_model_test_load_invalid_couplings.txt");
+        fs::write(&path, "0 0 1 0 1.0\n0 0 1 1 1.0\n").unwrap();
+        let error = BondCouplings::load(&path, 4, 4).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 2"));
+    }
+}
@@ -0,0 +1,167 @@
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::grid::Grid;
+use crate::spin::Spin;
+
+/// The page served to browsers, which connects back to the WebSocket of the same address.
+const PAGE: &str = include_str!("../web/dashboard.html");
+
+/// # Dashboard
+/// A small server that streams a run to browsers. A plain HTTP request gets the bundled dashboard
+/// page, and WebSocket connections to the same address receive every published configuration
+/// together with its observables as a JSON message:
+/// `{"sweep": 100, "width": 4, "height": 2, "spins": "++--+-+-", "observables": {"m": 0.0}}`,
+/// with the spins row by row in the characters of `Grid::save`. Connections are accepted on a
+/// background thread, and clients that stop reading are dropped.
+pub struct Dashboard {
+    address: SocketAddr,
+    clients: Arc<Mutex<Vec<WebSocket<TcpStream>>>>,
+}
+
+impl Dashboard {
+    /// # Bind a dashboard
+    /// Listens on the given address, such as `0.0.0.0:8080`, and starts accepting connections.
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let accepted = Arc::clone(&clients);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A failing connection only affects its own browser.
+                if let Ok(Some(client)) = Self::accept(stream) {
+                    accepted.lock().unwrap().push(client);
+                }
+            }
+        });
+        Ok(Self { address, clients })
+    }
+
+    /// Answers a connection, returning the WebSocket if it asked for an upgrade and serving the
+    /// page otherwise.
+    fn accept(mut stream: TcpStream) -> io::Result<Option<WebSocket<TcpStream>>> {
+        stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+        let mut request = [0; 2048];
+        let length = stream.peek(&mut request)?;
+        let request = String::from_utf8_lossy(&request[..length]).to_ascii_lowercase();
+        if request.contains("upgrade: websocket") {
+            return tungstenite::accept(stream)
+                .map(Some)
+                .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.to_string()));
+        }
+
+        let mut discarded = [0; 2048];
+        let _ = stream.read(&mut discarded)?;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            PAGE.len(),
+            PAGE
+        )?;
+        Ok(None)
+    }
+
+    /// # Address
+    /// The address the dashboard listens on, with the actual port if it was bound to port 0.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// # Number of clients
+    /// The number of browsers currently connected.
+    pub fn clients(&self) -> usize {
+        self.clients.lock().unwrap().len()
+    }
+
+    /// # Publish
+    /// Sends the configuration after the given sweep and the named observables to every connected
+    /// browser.
+    pub fn publish(&self, sweep: usize, grid: &Grid, observables: &[(&str, f64)]) {
+        let mut spins = String::with_capacity(grid.width() * grid.height());
+        for y in 0..grid.height() as i64 {
+            for x in 0..grid.width() as i64 {
+                spins.push(match grid.get(x, y) {
+                    Spin::Up => '+',
+                    Spin::Down => '-',
+                    Spin::Zero => '0',
+                    Spin::Vacant => '.',
+                });
+            }
+        }
+        let observables: Map<String, Value> = observables
+            .iter()
+            .map(|&(name, value)| (name.to_string(), json!(value)))
+            .collect();
+        let message = json!({
+            "sweep": sweep,
+            "width": grid.width(),
+            "height": grid.height(),
+            "spins": spins,
+            "observables": observables,
+        })
+        .to_string();
+
+        self.clients
+            .lock()
+            .unwrap()
+            .retain_mut(|client| client.send(Message::text(message.clone())).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_dashboard() {
+        let dashboard = Dashboard::bind("127.0.0.1:0").unwrap();
+        let address = dashboard.address();
+
+        // A browser first loads the page.
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET / HTTP/1.1\r\nHost: {}\r\n\r\n", address).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("<canvas"));
+
+        let (mut client, _) = tungstenite::connect(format!("ws://{}/ws", address)).unwrap();
+        let start = Instant::now();
+        while dashboard.clients() == 0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let mut grid = Grid::new_constant(3, 2, Spin::Up).unwrap();
+        grid.set(1, 1, Spin::Down);
+        dashboard.publish(
+            40,
+            &grid,
+            &[("magnetization", 2.0 / 3.0), ("acceptance", 0.5)],
+        );
+        let message: Value =
+            serde_json::from_str(client.read().unwrap().to_text().unwrap()).unwrap();
+        assert_eq!(message["sweep"], 40);
+        assert_eq!(message["width"], 3);
+        assert_eq!(message["spins"], "++++-+");
+        assert_eq!(message["observables"]["acceptance"], 0.5);
+
+        // A browser that went away is dropped on the next message.
+        drop(client);
+        let start = Instant::now();
+        while dashboard.clients() > 0 {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            dashboard.publish(41, &grid, &[]);
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::io;
+use std::path::Path;
+
+use rusqlite::types::Value;
+use rusqlite::{params_from_iter, Connection, Row};
+
+use crate::measurement::Measurement;
+use crate::provenance::Provenance;
+use crate::scan::ScanResult;
+
+/// The version of the schema, stored as the `user_version` of the database. It only changes
+/// together with a migration of the existing databases.
+const SCHEMA_VERSION: i64 = 3;
+
+/// The columns of the observables, in the order of the fields of `ScanResult`.
+const OBSERVABLES: [&str; 10] = [
+    "energy",
+    "energy_error",
+    "magnetization",
+    "magnetization_error",
+    "absolute_magnetization",
+    "absolute_magnetization_error",
+    "susceptibility",
+    "specific_heat",
+    "binder_cumulant",
+    "acceptance",
+];
+
+/// # Results database
+/// An SQLite database that collects the results of runs, so that a whole campaign can be queried
+/// in one place, for example with
+/// `SELECT temperature, avg(binder_cumulant) FROM runs WHERE size = 32 GROUP BY temperature`.
+/// Every run is a row of the `runs` table with an `id`, the UTC time `recorded_at`, the `size`,
+/// `temperature`, `field`, `crystal_field` and `seed` of the run, the number of measurement
+/// `sweeps`, the `elapsed_seconds`, one column per observable and error bar of `ScanResult`, and
+/// the `provenance` of the run as JSON. Missing error bars and the crystal field of Ising runs are
+/// stored as NULL, and the seeds as the signed integers with the same bits. Databases of an older schema are migrated when opened.
+pub struct ResultsDatabase {
+    connection: Connection,
+}
+
+impl ResultsDatabase {
+    /// # Open a database
+    /// Opens the database at the given path, creating it and its schema if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_connection(Connection::open(path).map_err(io::Error::other)?)
+    }
+
+    /// # Open an in-memory database
+    /// Opens a database that only lives as long as the value.
+    pub fn open_in_memory() -> io::Result<Self> {
+        Self::from_connection(Connection::open_in_memory().map_err(io::Error::other)?)
+    }
+
+    fn from_connection(connection: Connection) -> io::Result<Self> {
+        let version: i64 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(io::Error::other)?;
+        if version == 1 {
+            // The first schema had no provenance, which stays NULL for the old runs.
+            connection
+                .execute_batch(
+                    "ALTER TABLE runs ADD COLUMN provenance TEXT;
+                     PRAGMA user_version = 2;",
+                )
+                .map_err(io::Error::other)?;
+        }
+        if matches!(version, 1 | 2) {
+            // The second schema had no crystal field, which stays NULL for the old Ising runs.
+            connection
+                .execute_batch(
+                    "ALTER TABLE runs ADD COLUMN crystal_field REAL;
+                     PRAGMA user_version = 3;",
+                )
+                .map_err(io::Error::other)?;
+        }
+        match version {
+            0 => {
+                let observables: Vec<String> = OBSERVABLES
+                    .iter()
+                    .map(|column| format!("{} REAL", column))
+                    .collect();
+                connection
+                    .execute_batch(&format!(
+                        "CREATE TABLE runs (
+                            id INTEGER PRIMARY KEY,
+                            recorded_at TEXT NOT NULL
+                                DEFAULT (strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+                            size INTEGER NOT NULL,
+                            temperature REAL NOT NULL,
+                            field REAL NOT NULL,
+                            crystal_field REAL,
+                            seed INTEGER NOT NULL,
+                            sweeps INTEGER NOT NULL,
+                            elapsed_seconds REAL NOT NULL,
+                            {},
+                            provenance TEXT
+                        );
+                        CREATE INDEX runs_parameters ON runs (size, temperature, field);
+                        PRAGMA user_version = {};",
+                        observables.join(",\n"),
+                        SCHEMA_VERSION
+                    ))
+                    .map_err(io::Error::other)?;
+            }
+            1 | 2 | SCHEMA_VERSION => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown results database schema version {}", version),
+                ))
+            }
+        }
+        Ok(Self { connection })
+    }
+
+    /// # Record a run
+    /// Adds the result of a run over the given number of measurement sweeps, which took the given
+    /// time, with its provenance, and returns its id.
+    pub fn record(
+        &self,
+        result: &ScanResult,
+        sweeps: usize,
+        elapsed_seconds: f64,
+        provenance: &Provenance,
+    ) -> io::Result<i64> {
+        let placeholders = vec!["?"; OBSERVABLES.len()].join(", ");
+        let mut statement = self
+            .connection
+            .prepare_cached(&format!(
+                "INSERT INTO runs (size, temperature, field, crystal_field, seed, sweeps, \
+                 elapsed_seconds, {}, provenance) VALUES (?, ?, ?, ?, ?, ?, ?, {}, ?)",
+                OBSERVABLES.join(", "),
+                placeholders
+            ))
+            .map_err(io::Error::other)?;
+        let mut values = vec![
+            Value::Integer(result.size as i64),
+            Value::Real(result.temperature),
+            Value::Real(result.field),
+            result.crystal_field.map_or(Value::Null, Value::Real),
+            Value::Integer(result.seed as i64),
+            Value::Integer(sweeps as i64),
+            Value::Real(elapsed_seconds),
+        ];
+        values.extend(observables(result).map(|value| {
+            if value.is_nan() {
+                Value::Null
+            } else {
+                Value::Real(value)
+            }
+        }));
+        values.push(Value::Text(provenance.to_json()));
+        statement
+            .execute(params_from_iter(values))
+            .map_err(io::Error::other)?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    /// # Results
+    /// The results of all the recorded runs, in the order they were recorded.
+    pub fn results(&self) -> io::Result<Vec<ScanResult>> {
+        let mut statement = self
+            .connection
+            .prepare(&format!(
+                "SELECT size, temperature, field, crystal_field, seed, {} FROM runs ORDER BY id",
+                OBSERVABLES.join(", ")
+            ))
+            .map_err(io::Error::other)?;
+        let rows = statement
+            .query_map([], read_result)
+            .map_err(io::Error::other)?;
+        rows.collect::<Result<_, _>>().map_err(io::Error::other)
+    }
+
+    /// # Connection
+    /// The underlying connection, for queries of its own.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+/// The observables of a result, in the order of `OBSERVABLES`.
+fn observables(result: &ScanResult) -> [f64; 10] {
+    [
+        result.energy.value,
+        result.energy.error,
+        result.magnetization.value,
+        result.magnetization.error,
+        result.absolute_magnetization.value,
+        result.absolute_magnetization.error,
+        result.susceptibility,
+        result.specific_heat,
+        result.binder_cumulant,
+        result.acceptance,
+    ]
+}
+
+/// Reads a row of `size, temperature, field, crystal_field, seed` and the observables, with NULL
+/// as NaN.
+fn read_result(row: &Row) -> rusqlite::Result<ScanResult> {
+    let observable = |index: usize| -> rusqlite::Result<f64> {
+        Ok(row.get::<_, Option<f64>>(5 + index)?.unwrap_or(f64::NAN))
+    };
+    Ok(ScanResult {
+        size: row.get::<_, i64>(0)? as usize,
+        temperature: row.get(1)?,
+        field: row.get(2)?,
+        crystal_field: row.get(3)?,
+        seed: row.get::<_, i64>(4)? as u64,
+        energy: Measurement::new(observable(0)?, observable(1)?),
+        magnetization: Measurement::new(observable(2)?, observable(3)?),
+        absolute_magnetization: Measurement::new(observable(4)?, observable(5)?),
+        susceptibility: observable(6)?,
+        specific_heat: observable(7)?,
+        binder_cumulant: observable(8)?,
+        acceptance: observable(9)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+
+    fn result(seed: u64, temperature: f64) -> ScanResult {
+        ScanResult {
+            size: 16,
+            temperature,
+            field: 0.0,
+            crystal_field: (temperature == 2.4).then_some(1.9),
+            seed,
+            energy: Measurement::new(-1.4, 0.01),
+            magnetization: Measurement::new(0.02, f64::NAN),
+            absolute_magnetization: Measurement::new(0.7, 0.03),
+            susceptibility: 12.0,
+            specific_heat: 1.6,
+            binder_cumulant: 0.55,
+            acceptance: 0.2,
+        }
+    }
+
+    #[test]
+    fn test_results_database() {
+        let path = env::temp_dir().join("ising_model_test_results.sqlite");
+        let _ = fs::remove_file(&path);
+        let provenance = Provenance::new("metropolis-sequential", Some(1));
+        {
+            let database = ResultsDatabase::open(&path).unwrap();
+            let id = database.record(&result(1, 2.2), 1000, 0.5, &provenance);
+            assert_eq!(id.unwrap(), 1);
+            let id = database.record(&result(u64::MAX, 2.4), 1000, 0.6, &provenance);
+            assert_eq!(id.unwrap(), 2);
+        }
+
+        // The runs are still there when the database is opened again.
+        let database = ResultsDatabase::open(&path).unwrap();
+        database
+            .record(&result(3, 2.2), 2000, 1.0, &provenance)
+            .unwrap();
+        let results = database.results().unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].seed, u64::MAX);
+        assert_eq!(results[1].crystal_field, Some(1.9));
+        assert_eq!(results[0].crystal_field, None);
+        assert_eq!(results[2].energy.value, -1.4);
+        assert!(results[0].magnetization.error.is_nan());
+
+        let (runs, sweeps): (i64, i64) = database
+            .connection()
+            .query_row(
+                "SELECT count(*), sum(sweeps) FROM runs WHERE temperature = 2.2",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!((runs, sweeps), (2, 3000));
+        let stored: String = database
+            .connection()
+            .query_row("SELECT provenance FROM runs WHERE id = 3", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(stored, provenance.to_json());
+        drop(database);
+        fs::remove_file(&path).unwrap();
+
+        let database = ResultsDatabase::open_in_memory().unwrap();
+        database
+            .connection()
+            .execute_batch("PRAGMA user_version = 99")
+            .unwrap();
+        assert!(ResultsDatabase::from_connection(database.connection).is_err());
+    }
+
+    #[test]
+    fn test_schema_migration() {
+        // A database of the first schema, which had no provenance and no crystal field.
+        let database = ResultsDatabase::open_in_memory().unwrap();
+        database
+            .connection()
+            .execute_batch(
+                "ALTER TABLE runs DROP COLUMN provenance;
+                 ALTER TABLE runs DROP COLUMN crystal_field;
+                 PRAGMA user_version = 1",
+            )
+            .unwrap();
+        database
+            .connection()
+            .execute(
+                "INSERT INTO runs (size, temperature, field, seed, sweeps, elapsed_seconds) \
+                 VALUES (8, 2.0, 0.0, 5, 100, 0.1)",
+                [],
+            )
+            .unwrap();
+
+        let database = ResultsDatabase::from_connection(database.connection).unwrap();
+        let version: i64 = database
+            .connection()
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+        let provenance = Provenance::new("metropolis-sequential", Some(6));
+        database
+            .record(&result(6, 2.0), 100, 0.1, &provenance)
+            .unwrap();
+        let provenances: Vec<Option<String>> = database
+            .connection()
+            .prepare("SELECT provenance FROM runs ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(provenances, vec![None, Some(provenance.to_json())]);
+        assert_eq!(database.results().unwrap()[0].seed, 5);
+        assert_eq!(database.results().unwrap()[0].crystal_field, None);
+    }
+}
@@ -0,0 +1,473 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use tracing::{debug, info_span};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::error::{check_size, Error, Result};
+use crate::grid::Grid;
+use crate::provenance::Provenance;
+use crate::random_cluster::swendsen_wang_step;
+use crate::render::Palette;
+use crate::spin::Spin;
+use crate::temperature::Temperature;
+
+/// # Dataset layout
+/// How a dataset is written to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatasetLayout {
+    /// One NumPy archive per size, `L{size}.npz`, with the arrays `x_train`, `y_train` and
+    /// `t_train` and their `_test` counterparts: the spins as int8 ±1 of shape (n, L, L), the
+    /// labels as uint8 and the temperatures as float64.
+    Npz,
+    /// One black-and-white PNG per configuration, at one pixel per spin, in the class folders of
+    /// `L{size}/{train,test}/{ordered,disordered}/`, as image-folder loaders expect.
+    Images,
+}
+
+impl DatasetLayout {
+    /// # All layouts
+    pub const ALL: [DatasetLayout; 2] = [DatasetLayout::Npz, DatasetLayout::Images];
+
+    /// # Name
+    /// The name the layout is given on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DatasetLayout::Npz => "npz",
+            DatasetLayout::Images => "images",
+        }
+    }
+}
+
+impl fmt::Display for DatasetLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for DatasetLayout {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|layout| layout.name() == name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(DatasetLayout::name).collect();
+                format!(
+                    "unknown layout {}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+/// # Sample
+/// One labeled configuration: its spins row by row as ±1, the temperature it was drawn at, and
+/// its label, 1 below the critical temperature of the infinite lattice and 0 above it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub spins: Vec<i8>,
+    pub temperature: f64,
+    pub label: u8,
+}
+
+/// # Dataset split
+/// The training and test samples of the L × L grids of one size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatasetSplit {
+    pub size: usize,
+    pub train: Vec<Sample>,
+    pub test: Vec<Sample>,
+}
+
+/// # Dataset
+/// Labeled configurations of periodic L × L grids in zero field for phase classification, the
+/// usual benchmark of machine learning on the Ising model. At every size and temperature a grid
+/// is thermalized from a random start with Swendsen–Wang updates, which stay fast at the critical
+/// point, and then sampled every `decorrelation_sweeps` updates. The last `test_fraction` of the
+/// samples of every temperature form the test set, so both sets cover every temperature. The size
+/// and temperature with index k in the order of `points` use the seed `seed + k`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dataset {
+    pub sizes: Vec<usize>,
+    pub temperatures: Vec<Temperature>,
+    pub samples: usize,
+    pub thermalization_sweeps: usize,
+    pub decorrelation_sweeps: usize,
+    pub test_fraction: f64,
+    pub seed: u64,
+}
+
+impl Dataset {
+    /// # Points
+    /// The sizes and temperatures of the dataset, with the temperatures varying fastest.
+    pub fn points(&self) -> Vec<(usize, Temperature)> {
+        self.sizes
+            .iter()
+            .flat_map(|&size| self.temperatures.iter().map(move |&t| (size, t)))
+            .collect()
+    }
+
+    /// # Validate
+    /// Checks that every size has sites and that the test fraction lies between 0 and 1.
+    pub fn validate(&self) -> Result<()> {
+        for &size in &self.sizes {
+            check_size(size, size)?;
+        }
+        if !(0.0..=1.0).contains(&self.test_fraction) {
+            return Err(Error::InvalidParameter {
+                name: "test fraction",
+                value: self.test_fraction,
+            });
+        }
+        Ok(())
+    }
+
+    /// # Samples of a point
+    /// Draws the samples of one size and temperature with the given seed. Fails if the size has
+    /// no sites.
+    pub fn sample_point(
+        &self,
+        size: usize,
+        temperature: Temperature,
+        seed: u64,
+    ) -> Result<Vec<Sample>> {
+        let _span = info_span!("dataset_point", size, temperature = temperature.value()).entered();
+        let coupling = temperature.beta();
+        let label = u8::from(coupling > 0.5 * (1.0 + 2.0_f64.sqrt()).ln());
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut grid = Grid::new_with_magnetization(size, size, 0.0, &mut rng)?;
+        for _ in 0..self.thermalization_sweeps {
+            swendsen_wang_step(&mut grid, coupling, &mut rng);
+        }
+        Ok((0..self.samples)
+            .map(|_| {
+                for _ in 0..self.decorrelation_sweeps {
+                    swendsen_wang_step(&mut grid, coupling, &mut rng);
+                }
+                Sample {
+                    spins: grid
+                        .iter_sites()
+                        .map(|(_, spin)| if spin == Spin::Up { 1 } else { -1 })
+                        .collect(),
+                    temperature: temperature.value(),
+                    label,
+                }
+            })
+            .collect())
+    }
+
+    /// # Generate
+    /// Draws the samples of every point, spread over the given number of threads, and splits them
+    /// into one training and one test set per size, in the order of the sizes. Fails if the
+    /// dataset does not validate or there are no threads.
+    pub fn generate(&self, threads: usize) -> Result<Vec<DatasetSplit>> {
+        self.validate()?;
+        if threads == 0 {
+            return Err(Error::InvalidParameter {
+                name: "threads",
+                value: 0.0,
+            });
+        }
+        let points = self.points();
+        debug!(points = points.len(), threads, "generating dataset");
+        let points = &points;
+        let mut samples = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|thread| {
+                    scope.spawn(move || {
+                        (thread..points.len())
+                            .step_by(threads)
+                            .map(|index| {
+                                let (size, temperature) = points[index];
+                                let seed = self.seed.wrapping_add(index as u64);
+                                self.sample_point(size, temperature, seed)
+                                    .map(|samples| (index, samples))
+                            })
+                            .collect::<Result<Vec<_>>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect::<Result<Vec<_>>>()
+        })?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<(usize, Vec<Sample>)>>();
+        samples.sort_by_key(|&(index, _)| index);
+
+        let train_samples =
+            self.samples - (self.test_fraction * self.samples as f64).round() as usize;
+        let mut samples = samples.into_iter().map(|(_, samples)| samples);
+        Ok(self
+            .sizes
+            .iter()
+            .map(|&size| {
+                let mut split = DatasetSplit {
+                    size,
+                    train: Vec::new(),
+                    test: Vec::new(),
+                };
+                for mut point in samples.by_ref().take(self.temperatures.len()) {
+                    split.test.extend(point.split_off(train_samples));
+                    split.train.extend(point);
+                }
+                split
+            })
+            .collect())
+    }
+
+    /// # Provenance
+    /// A record of the algorithm and all the parameters of the dataset.
+    pub fn provenance(&self) -> Provenance {
+        Provenance::new("Swendsen–Wang", Some(self.seed))
+            .with_parameter("sizes", &self.sizes)
+            .with_parameter("temperatures", &self.temperatures)
+            .with_parameter("samples", self.samples)
+            .with_parameter("thermalization_sweeps", self.thermalization_sweeps)
+            .with_parameter("decorrelation_sweeps", self.decorrelation_sweeps)
+            .with_parameter("test_fraction", self.test_fraction)
+    }
+
+    /// # Write
+    /// Generates the dataset and writes it to the directory in the given layout, next to its
+    /// provenance in `provenance.json`. Each NumPy archive holds a copy of the provenance too, so
+    /// that it stays attributable when it is moved on its own.
+    pub fn write(
+        &self,
+        directory: impl AsRef<Path>,
+        layout: DatasetLayout,
+        threads: usize,
+    ) -> io::Result<()> {
+        let directory = directory.as_ref();
+        let splits = self.generate(threads)?;
+        fs::create_dir_all(directory)?;
+        let provenance = self.provenance();
+        fs::write(
+            directory.join("provenance.json"),
+            serde_json::to_string_pretty(&provenance)?,
+        )?;
+        for split in &splits {
+            match layout {
+                DatasetLayout::Npz => write_npz(
+                    directory.join(format!("L{}.npz", split.size)),
+                    split,
+                    &provenance,
+                )?,
+                DatasetLayout::Images => {
+                    write_images(directory.join(format!("L{}", split.size)), split)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// # NumPy array
+/// The bytes of a C-ordered `.npy` file of version 1.0 with the given dtype, shape and data. The
+/// header is padded with spaces so that the data starts at a multiple of 64 bytes.
+fn npy(descr: &str, shape: &[usize], data: &[u8]) -> Vec<u8> {
+    let shape = match shape {
+        [length] => format!("({},)", length),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}",
+        descr, shape
+    );
+    // The magic string, the version and the header length take ten bytes, and the header ends
+    // in a newline.
+    let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+    let mut bytes = Vec::with_capacity(10 + header.len() + data.len());
+    bytes.extend_from_slice(b"\x93NUMPY\x01\x00");
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// # Write a NumPy archive
+/// Writes a split as an uncompressed `.npz` archive that `numpy.load` reads, with the arrays
+/// described by `DatasetLayout::Npz` and the provenance as a `provenance.json` member, which
+/// `numpy.load` returns as bytes.
+pub fn write_npz(
+    path: impl AsRef<Path>,
+    split: &DatasetSplit,
+    provenance: &Provenance,
+) -> io::Result<()> {
+    let mut archive = ZipWriter::new(File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+    archive
+        .start_file("provenance.json", options)
+        .map_err(io::Error::other)?;
+    archive.write_all(serde_json::to_string_pretty(provenance)?.as_bytes())?;
+    for (name, samples) in [("train", &split.train), ("test", &split.test)] {
+        let spins: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.spins.iter().map(|&spin| spin as u8))
+            .collect();
+        let labels: Vec<u8> = samples.iter().map(|sample| sample.label).collect();
+        let temperatures: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.temperature.to_le_bytes())
+            .collect();
+        let n = samples.len();
+        for (prefix, descr, shape, data) in [
+            ("x", "|i1", vec![n, split.size, split.size], spins),
+            ("y", "|u1", vec![n], labels),
+            ("t", "<f8", vec![n], temperatures),
+        ] {
+            archive
+                .start_file(format!("{}_{}.npy", prefix, name), options)
+                .map_err(io::Error::other)?;
+            archive.write_all(&npy(descr, &shape, &data))?;
+        }
+    }
+    archive.finish().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// # Write images
+/// Writes a split as PNG images in the class folders described by `DatasetLayout::Images`, each
+/// named after its temperature and its index among the samples of that temperature.
+pub fn write_images(directory: impl AsRef<Path>, split: &DatasetSplit) -> io::Result<()> {
+    let directory = directory.as_ref();
+    let palette = Palette::default();
+    for (name, samples) in [("train", &split.train), ("test", &split.test)] {
+        for class in ["ordered", "disordered"] {
+            fs::create_dir_all(directory.join(name).join(class))?;
+        }
+        let mut index = 0;
+        for (number, sample) in samples.iter().enumerate() {
+            if number > 0 && sample.temperature != samples[number - 1].temperature {
+                index = 0;
+            }
+            let class = if sample.label == 1 {
+                "ordered"
+            } else {
+                "disordered"
+            };
+            let grid = Grid::from_fn(split.size, split.size, |x, y| {
+                if sample.spins[y * split.size + x] == 1 {
+                    Spin::Up
+                } else {
+                    Spin::Down
+                }
+            })?;
+            let file = format!("T{:.4}_{:05}.png", sample.temperature, index);
+            grid.save_png(directory.join(name).join(class).join(file), &palette, 1)?;
+            index += 1;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::io::Read;
+
+    use super::*;
+
+    fn dataset() -> Dataset {
+        Dataset {
+            sizes: vec![4, 6],
+            temperatures: [1.5, 3.5].map(|t| Temperature::new(t).unwrap()).to_vec(),
+            samples: 5,
+            thermalization_sweeps: 20,
+            decorrelation_sweeps: 2,
+            test_fraction: 0.4,
+            seed: 3,
+        }
+    }
+
+    #[test]
+    fn test_generate() {
+        let splits = dataset().generate(2).unwrap();
+        assert_eq!(splits.len(), 2);
+        assert_eq!(splits[1].size, 6);
+        assert_eq!((splits[1].train.len(), splits[1].test.len()), (6, 4));
+        assert_eq!(splits[1].train[0].spins.len(), 36);
+        let labels: Vec<(f64, u8)> = splits[0]
+            .test
+            .iter()
+            .map(|sample| (sample.temperature, sample.label))
+            .collect();
+        assert_eq!(labels, [(1.5, 1), (1.5, 1), (3.5, 0), (3.5, 0)]);
+        // Deep in the ordered phase the grid is nearly aligned.
+        let magnetization: i32 = splits[1].train[0].spins.iter().map(|&s| i32::from(s)).sum();
+        assert!(magnetization.abs() >= 30);
+        assert_eq!(splits, dataset().generate(1).unwrap());
+
+        let mut invalid = dataset();
+        invalid.test_fraction = 1.5;
+        assert!(invalid.generate(1).is_err());
+    }
+
+    #[test]
+    fn test_npy_header() {
+        let bytes = npy("|u1", &[3], &[1, 2, 3]);
+        assert_eq!(bytes.len(), 128 + 3);
+        assert!(bytes.starts_with(b"\x93NUMPY\x01\x00"));
+        let header = String::from_utf8(bytes[10..128].to_vec()).unwrap();
+        assert!(header.starts_with("{'descr': '|u1', 'fortran_order': False, 'shape': (3,), }"));
+        assert!(header.ends_with(" \n"));
+        assert!(String::from_utf8_lossy(&npy("|i1", &[2, 4, 4], &[])).contains("(2, 4, 4)"));
+    }
+
+    #[test]
+    fn test_write() {
+        let directory = env::temp_dir().join("ising_dataset_test");
+        let mut dataset = dataset();
+        dataset.sizes = vec![4];
+        dataset.write(&directory, DatasetLayout::Npz, 2).unwrap();
+        let mut archive =
+            zip::ZipArchive::new(File::open(directory.join("L4.npz")).unwrap()).unwrap();
+        assert_eq!(archive.len(), 7);
+        let mut provenance = String::new();
+        archive
+            .by_name("provenance.json")
+            .unwrap()
+            .read_to_string(&mut provenance)
+            .unwrap();
+        let provenance: Provenance = serde_json::from_str(&provenance).unwrap();
+        assert_eq!(provenance.seed, Some(3));
+        let mut labels = Vec::new();
+        archive
+            .by_name("y_train.npy")
+            .unwrap()
+            .read_to_end(&mut labels)
+            .unwrap();
+        assert_eq!(&labels[128..], [1, 1, 1, 0, 0, 0]);
+
+        dataset.write(&directory, DatasetLayout::Images, 2).unwrap();
+        let ordered = directory.join("L4").join("train").join("ordered");
+        assert_eq!(fs::read_dir(&ordered).unwrap().count(), 3);
+        assert!(ordered.join("T1.5000_00002.png").exists());
+        assert!(directory.join("provenance.json").exists());
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!("images".parse(), Ok(DatasetLayout::Images));
+        assert!("hdf5".parse::<DatasetLayout>().is_err());
+    }
+}
@@ -0,0 +1,406 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::error::{check_size, Error, Result};
+use crate::output::Observation;
+use crate::spin::Spin;
+use crate::temperature::Temperature;
+
+/// # Halo exchange
+/// How a domain trades its outermost rows for the rows next to it, which the domains above and
+/// below own. The threads of one machine pass them through channels with `ChannelExchange`, and
+/// a message-passing transport between machines only has to implement this as well.
+pub trait HaloExchange {
+    /// Sends the first row of the domain to the domain above and its last row to the domain
+    /// below, and returns the rows received from them: the last row of the domain above and the
+    /// first row of the domain below.
+    fn exchange(&mut self, first: &[i8], last: &[i8]) -> (Vec<i8>, Vec<i8>);
+}
+
+/// # Channel exchange
+/// The halo exchange of one domain in a ring of domains on threads of the same process, with the
+/// last domain above the first one for periodic boundaries.
+pub struct ChannelExchange {
+    to_above: Sender<Vec<i8>>,
+    to_below: Sender<Vec<i8>>,
+    from_above: Receiver<Vec<i8>>,
+    from_below: Receiver<Vec<i8>>,
+}
+
+impl ChannelExchange {
+    /// # Ring of exchanges
+    /// The exchanges of the given number of domains, from the top one down. A single domain
+    /// exchanges with itself. Fails for a ring without domains.
+    pub fn ring(domains: usize) -> Result<Vec<Self>> {
+        if domains == 0 {
+            return Err(Error::InvalidParameter {
+                name: "domains",
+                value: 0.0,
+            });
+        }
+        // Rows travelling down end up in `from_above` of the next domain, and rows travelling
+        // up in `from_below` of the previous one.
+        let (down_senders, down_receivers): (Vec<_>, Vec<_>) =
+            (0..domains).map(|_| mpsc::channel()).unzip();
+        let (up_senders, up_receivers): (Vec<_>, Vec<_>) =
+            (0..domains).map(|_| mpsc::channel()).unzip();
+        let mut down_receivers: Vec<Option<Receiver<Vec<i8>>>> =
+            down_receivers.into_iter().map(Some).collect();
+        let mut up_receivers: Vec<Option<Receiver<Vec<i8>>>> =
+            up_receivers.into_iter().map(Some).collect();
+        Ok((0..domains)
+            .map(|rank| {
+                let below = (rank + 1) % domains;
+                let above = (rank + domains - 1) % domains;
+                Self {
+                    to_below: down_senders[below].clone(),
+                    to_above: up_senders[above].clone(),
+                    from_above: down_receivers[rank].take().unwrap(),
+                    from_below: up_receivers[rank].take().unwrap(),
+                }
+            })
+            .collect())
+    }
+}
+
+impl HaloExchange for ChannelExchange {
+    fn exchange(&mut self, first: &[i8], last: &[i8]) -> (Vec<i8>, Vec<i8>) {
+        self.to_above
+            .send(first.to_vec())
+            .expect("the domain above is gone");
+        self.to_below
+            .send(last.to_vec())
+            .expect("the domain below is gone");
+        (
+            self.from_above.recv().expect("the domain above is gone"),
+            self.from_below.recv().expect("the domain below is gone"),
+        )
+    }
+}
+
+/// A strip of whole rows of the lattice, stored as ±1 with a halo row above and below.
+#[derive(Debug, Clone)]
+struct Domain {
+    width: usize,
+    first_row: usize,
+    rows: usize,
+    spins: Vec<i8>,
+}
+
+impl Domain {
+    /// The spin at column x of row y of the domain, where row -1 and row `rows` are the halos.
+    fn get(&self, x: usize, y: isize) -> i8 {
+        self.spins[(y + 1) as usize * self.width + x]
+    }
+
+    fn row(&self, y: usize) -> &[i8] {
+        &self.spins[(y + 1) * self.width..(y + 2) * self.width]
+    }
+
+    fn exchange_halos(&mut self, exchange: &mut impl HaloExchange) {
+        let (above, below) = exchange.exchange(self.row(0), self.row(self.rows - 1));
+        let width = self.width;
+        self.spins[..width].copy_from_slice(&above);
+        self.spins[(self.rows + 1) * width..].copy_from_slice(&below);
+    }
+
+    /// Metropolis updates of the sites of one colour of the checkerboard, whose neighbours all
+    /// have the other colour and are left alone. Returns the number of accepted flips.
+    fn update_colour(
+        &mut self,
+        colour: usize,
+        coupling: f64,
+        field: f64,
+        rng: &mut impl Rng,
+    ) -> usize {
+        let mut accepted = 0;
+        for y in 0..self.rows {
+            let start = (self.first_row + y + colour) % 2;
+            for x in (start..self.width).step_by(2) {
+                let neighbours = self.get((x + 1) % self.width, y as isize)
+                    + self.get((x + self.width - 1) % self.width, y as isize)
+                    + self.get(x, y as isize - 1)
+                    + self.get(x, y as isize + 1);
+                let spin = self.get(x, y as isize);
+                let change = 2.0 * f64::from(spin) * (coupling * f64::from(neighbours) + field);
+                if change <= 0.0 || rng.gen::<f64>() < (-change).exp() {
+                    self.spins[(y + 1) * self.width + x] = -spin;
+                    accepted += 1;
+                }
+            }
+        }
+        accepted
+    }
+
+    /// The sums of the spins and of the bonds to the right of and below every site. The halo
+    /// below has to be up to date.
+    fn sums(&self) -> (i64, i64) {
+        let (mut spins, mut bonds) = (0, 0);
+        for y in 0..self.rows as isize {
+            for x in 0..self.width {
+                let spin = i64::from(self.get(x, y));
+                spins += spin;
+                bonds += spin * i64::from(self.get((x + 1) % self.width, y) + self.get(x, y + 1));
+            }
+        }
+        (spins, bonds)
+    }
+}
+
+/// # Decomposed lattice
+/// A periodic width × height Ising lattice of ±1 spins split into horizontal strips of rows, each
+/// simulated by a worker of its own that only holds its strip and two halo rows. Lattices too
+/// large for the `Grid` of one worker can be simulated this way, as every worker needs about
+/// width × height / domains bytes. A sweep updates the two colours of the checkerboard in turn
+/// and exchanges the halos after each, so the result is a valid Metropolis chain whatever the
+/// number of domains, although the random numbers, and so the exact trajectory, depend on it.
+#[derive(Debug, Clone)]
+pub struct DecomposedLattice {
+    width: usize,
+    height: usize,
+    domains: Vec<Domain>,
+}
+
+impl DecomposedLattice {
+    /// # New decomposed lattice
+    /// Splits the lattice into the given number of strips of nearly equal height, with the spin
+    /// of every site given by `spin(x, y)`. The width and height have to be even for the
+    /// checkerboard to fit the periodic boundaries, and every strip needs at least one row, or
+    /// this fails.
+    pub fn new(
+        width: usize,
+        height: usize,
+        domains: usize,
+        spin: impl Fn(usize, usize) -> Spin,
+    ) -> Result<Self> {
+        check_size(width, height)?;
+        for (name, length) in [("width", width), ("height", height)] {
+            if !length.is_multiple_of(2) {
+                return Err(Error::InvalidParameter {
+                    name,
+                    value: length as f64,
+                });
+            }
+        }
+        if domains == 0 || domains > height {
+            return Err(Error::InvalidParameter {
+                name: "domains",
+                value: domains as f64,
+            });
+        }
+        let domains = (0..domains)
+            .map(|rank| {
+                let first_row = rank * height / domains;
+                let rows = (rank + 1) * height / domains - first_row;
+                let mut spins = vec![0; (rows + 2) * width];
+                for y in 0..rows {
+                    for x in 0..width {
+                        spins[(y + 1) * width + x] = match spin(x, first_row + y) {
+                            Spin::Up => 1,
+                            _ => -1,
+                        };
+                    }
+                }
+                Domain {
+                    width,
+                    first_row,
+                    rows,
+                    spins,
+                }
+            })
+            .collect();
+        Ok(Self {
+            width,
+            height,
+            domains,
+        })
+    }
+
+    /// # Width
+    /// The number of columns of the lattice.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows of the lattice.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Number of domains
+    /// The number of strips the lattice is split into, one per thread.
+    pub fn number_of_domains(&self) -> usize {
+        self.domains.len()
+    }
+
+    /// # Get a spin
+    /// Retrieves the spin at the given site.
+    pub fn get(&self, x: usize, y: usize) -> Spin {
+        let domain = self
+            .domains
+            .iter()
+            .find(|domain| y < domain.first_row + domain.rows)
+            .expect("the site lies on the lattice");
+        match domain.get(x, (y - domain.first_row) as isize) {
+            1 => Spin::Up,
+            _ => Spin::Down,
+        }
+    }
+
+    /// # Run
+    /// Performs the given number of sweeps at temperature T and field h in units of the coupling,
+    /// with every domain on a thread of its own and a generator seeded from the seed and the rank
+    /// of the domain, and returns the observables after every sweep, summed over the domains at
+    /// the end. The energies are in the reduced units of `Grid::energy`.
+    pub fn run(
+        &mut self,
+        temperature: Temperature,
+        field: f64,
+        sweeps: usize,
+        seed: u64,
+    ) -> Vec<Observation> {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        let exchanges =
+            ChannelExchange::ring(self.domains.len()).expect("the lattice has a domain");
+        let series: Vec<Vec<(i64, i64, usize)>> = thread::scope(|scope| {
+            let workers: Vec<_> = self
+                .domains
+                .iter_mut()
+                .zip(exchanges)
+                .enumerate()
+                .map(|(rank, (domain, mut exchange))| {
+                    scope.spawn(move || {
+                        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                        rng.set_stream(rank as u64);
+                        domain.exchange_halos(&mut exchange);
+                        (0..sweeps)
+                            .map(|_| {
+                                let mut accepted = 0;
+                                for colour in 0..2 {
+                                    accepted +=
+                                        domain.update_colour(colour, coupling, field, &mut rng);
+                                    domain.exchange_halos(&mut exchange);
+                                }
+                                let (spins, bonds) = domain.sums();
+                                (spins, bonds, accepted)
+                            })
+                            .collect()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect()
+        });
+
+        let number_of_sites = (self.width * self.height) as f64;
+        (0..sweeps)
+            .map(|sweep| {
+                let (mut spins, mut bonds, mut accepted) = (0, 0, 0);
+                for domain in &series {
+                    spins += domain[sweep].0;
+                    bonds += domain[sweep].1;
+                    accepted += domain[sweep].2;
+                }
+                Observation {
+                    sweep: sweep + 1,
+                    energy: -(coupling * bonds as f64 + field * spins as f64) / number_of_sites,
+                    magnetization: spins as f64 / number_of_sites,
+                    acceptance: accepted as f64 / number_of_sites,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::blocked_mean;
+
+    #[test]
+    fn test_decomposition() {
+        let pattern = |x: usize, y: usize| {
+            if (x * 7 + y * 3) % 5 < 2 {
+                Spin::Up
+            } else {
+                Spin::Down
+            }
+        };
+        let lattice = DecomposedLattice::new(6, 10, 3, pattern).unwrap();
+        assert_eq!(
+            lattice
+                .domains
+                .iter()
+                .map(|domain| domain.rows)
+                .collect::<Vec<_>>(),
+            vec![3, 3, 4]
+        );
+        for y in 0..10 {
+            for x in 0..6 {
+                assert_eq!(lattice.get(x, y), pattern(x, y));
+            }
+        }
+        assert!(DecomposedLattice::new(5, 10, 3, pattern).is_err());
+        assert!(DecomposedLattice::new(6, 10, 11, pattern).is_err());
+        assert!(DecomposedLattice::new(6, 10, 0, pattern).is_err());
+    }
+
+    #[test]
+    fn test_halo_exchange() {
+        // Without updates, the observables after a sweep are those of the starting pattern: a
+        // checkerboard has every bond broken.
+        let checkerboard = |x: usize, y: usize| {
+            if (x + y).is_multiple_of(2) {
+                Spin::Up
+            } else {
+                Spin::Down
+            }
+        };
+        let (hot, cold) = (
+            Temperature::new(1e15).unwrap(),
+            Temperature::new(0.01).unwrap(),
+        );
+        for domains in [1, 2, 4] {
+            let mut lattice = DecomposedLattice::new(8, 8, domains, checkerboard).unwrap();
+            let observations = lattice.run(hot, 0.0, 1, 1);
+            // Far above the coupling every flip is accepted, which maps a checkerboard onto its
+            // opposite, still with every bond broken.
+            assert_eq!(observations[0].acceptance, 1.0);
+            assert_eq!(observations[0].magnetization, 0.0);
+            assert_eq!(lattice.get(0, 0), Spin::Down);
+
+            // Far below it no flip of an ordered lattice is accepted, and every bond is kept.
+            let mut lattice = DecomposedLattice::new(8, 8, domains, |_, _| Spin::Up).unwrap();
+            let observations = lattice.run(cold, 0.0, 1, 1);
+            assert_eq!(observations[0].acceptance, 0.0);
+            assert_eq!(observations[0].energy, -2.0 * cold.beta());
+        }
+        assert!(ChannelExchange::ring(0).is_err());
+    }
+
+    #[test]
+    fn test_decomposed_equilibrium() {
+        // The mean energy is the same however the lattice is split.
+        let energy = |domains: usize| {
+            let mut lattice = DecomposedLattice::new(16, 16, domains, |_, _| Spin::Up).unwrap();
+            let observations = lattice.run(Temperature::from_beta(0.3).unwrap(), 0.0, 3000, 5);
+            let energies: Vec<f64> = observations[500..]
+                .iter()
+                .map(|observation| observation.energy)
+                .collect();
+            blocked_mean(&energies)
+        };
+        let difference = energy(1) - energy(4);
+        assert!(
+            difference.value.abs() < 4.0 * difference.error,
+            "{}",
+            difference
+        );
+    }
+}
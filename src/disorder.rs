@@ -0,0 +1,257 @@
+use std::thread;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::error::{Error, Result};
+use crate::grid::Grid;
+use crate::random_field::gaussian_random_field;
+use crate::spin_glass::plus_minus_couplings;
+
+/// # Disorder
+/// One kind of quenched disorder that can be drawn for a grid.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Disorder {
+    /// ±J bonds, antiferromagnetic with the given probability.
+    PlusMinusBonds { antiferromagnetic_fraction: f64 },
+    /// A Gaussian random field with the given standard deviation.
+    RandomField { strength: f64 },
+    /// Vacancies at the given concentration.
+    Dilution { vacancy_concentration: f64 },
+}
+
+impl Disorder {
+    /// # Apply the disorder
+    /// Draws a realization of this disorder with the given generator and applies it to the grid.
+    /// Fails for a fraction or vacancy concentration that is not between zero and one.
+    pub fn apply<R: Rng>(&self, grid: &mut Grid, rng: &mut R) -> Result<()> {
+        let (width, height) = (grid.width(), grid.height());
+        match *self {
+            Disorder::PlusMinusBonds {
+                antiferromagnetic_fraction,
+            } => grid.set_bond_couplings(Some(plus_minus_couplings(
+                width,
+                height,
+                antiferromagnetic_fraction,
+                rng,
+            )?)),
+            Disorder::RandomField { strength } => {
+                grid.set_field_map(Some(gaussian_random_field(width, height, strength, rng)))
+            }
+            Disorder::Dilution {
+                vacancy_concentration,
+            } => grid.dilute(vacancy_concentration, rng),
+        }
+    }
+}
+
+/// # Disorder run
+/// The simulation that is repeated for every disorder realization: a random grid with the given
+/// disorder is thermalized and then measured once per sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisorderRun {
+    pub width: usize,
+    pub height: usize,
+    pub disorder: Vec<Disorder>,
+    pub coupling: f64,
+    pub field: f64,
+    pub thermalization_sweeps: usize,
+    pub measurement_sweeps: usize,
+}
+
+impl DisorderRun {
+    /// # Run one realization
+    /// Draws the realization with the given seed, simulates it and returns the thermal average of
+    /// each observable. Fails if the grid has no sites or the disorder cannot be applied.
+    pub fn run_realization(
+        &self,
+        seed: u64,
+        measure: &impl Fn(&Grid) -> Vec<f64>,
+    ) -> Result<Vec<f64>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut grid = Grid::new_random(self.width, self.height)?;
+        for disorder in &self.disorder {
+            disorder.apply(&mut grid, &mut rng)?;
+        }
+        for _ in 0..self.thermalization_sweeps {
+            grid.step_reduced(self.coupling, self.field);
+        }
+
+        let mut sums: Vec<f64> = Vec::new();
+        for _ in 0..self.measurement_sweeps {
+            grid.step_reduced(self.coupling, self.field);
+            let values = measure(&grid);
+            sums.resize(values.len(), 0.0);
+            for (sum, value) in sums.iter_mut().zip(values) {
+                *sum += value;
+            }
+        }
+        Ok(sums
+            .iter()
+            .map(|sum| sum / self.measurement_sweeps as f64)
+            .collect())
+    }
+
+    /// # Disorder average
+    /// Runs `realizations` independent disorder realizations spread over `threads` threads and
+    /// collects the thermal averages of the observables returned by `measure`. Realization r draws
+    /// its disorder from the seed `seed + r`, so the same realizations are simulated whatever the
+    /// number of threads. Fails if the grid has no sites or there are no threads.
+    pub fn disorder_average(
+        &self,
+        realizations: usize,
+        threads: usize,
+        seed: u64,
+        measure: impl Fn(&Grid) -> Vec<f64> + Sync,
+    ) -> Result<DisorderAverage> {
+        if threads == 0 {
+            return Err(Error::InvalidParameter {
+                name: "threads",
+                value: 0.0,
+            });
+        }
+        let measure = &measure;
+        let mut samples = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|thread| {
+                    scope.spawn(move || {
+                        (thread..realizations)
+                            .step_by(threads)
+                            .map(|realization| {
+                                self.run_realization(seed + realization as u64, measure)
+                                    .map(|values| (realization, values))
+                            })
+                            .collect::<Result<Vec<_>>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .map(|worker| worker.join().unwrap())
+                .collect::<Result<Vec<_>>>()
+        })?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<(usize, Vec<f64>)>>();
+        samples.sort_by_key(|&(realization, _)| realization);
+        Ok(DisorderAverage {
+            samples: samples.into_iter().map(|(_, values)| values).collect(),
+        })
+    }
+}
+
+/// # Disorder average
+/// The thermal averages of a set of observables for each disorder realization, from which the
+/// disorder average and its sample-to-sample error follow.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisorderAverage {
+    samples: Vec<Vec<f64>>,
+}
+
+impl DisorderAverage {
+    /// # Number of realizations
+    /// The number of disorder realizations.
+    pub fn realizations(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// # Samples
+    /// The thermal averages of one observable, one per realization.
+    pub fn samples(&self, observable: usize) -> Vec<f64> {
+        self.samples
+            .iter()
+            .map(|values| values[observable])
+            .collect()
+    }
+
+    /// # Mean
+    /// The disorder average [⟨A⟩] of one observable.
+    pub fn mean(&self, observable: usize) -> f64 {
+        self.samples(observable).iter().sum::<f64>() / self.realizations() as f64
+    }
+
+    /// # Error
+    /// The standard error of the disorder average, from the sample-to-sample spread of the thermal
+    /// averages. With fewer than two realizations it is not defined and NaN is returned.
+    pub fn error(&self, observable: usize) -> f64 {
+        let realizations = self.realizations() as f64;
+        let mean = self.mean(observable);
+        let variance = self
+            .samples(observable)
+            .iter()
+            .map(|value| (value - mean).powi(2))
+            .sum::<f64>()
+            / (realizations - 1.0);
+        (variance / realizations).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spin::Spin;
+
+    fn absolute_magnetization(grid: &Grid) -> Vec<f64> {
+        let sum = grid.magnetization();
+        let vacancies = grid.number_of_vacancies() as f64;
+        let occupied = (grid.width() * grid.height()) as f64 - vacancies;
+        vec![sum.abs() / occupied, vacancies]
+    }
+
+    #[test]
+    fn test_realizations_do_not_depend_on_threads() {
+        let run = DisorderRun {
+            width: 10,
+            height: 10,
+            disorder: vec![Disorder::Dilution {
+                vacancy_concentration: 0.2,
+            }],
+            coupling: 0.3,
+            field: 0.0,
+            thermalization_sweeps: 0,
+            measurement_sweeps: 1,
+        };
+        let one = run
+            .disorder_average(6, 1, 11, absolute_magnetization)
+            .unwrap();
+        let three = run
+            .disorder_average(6, 3, 11, absolute_magnetization)
+            .unwrap();
+        assert_eq!(one.realizations(), 6);
+        assert_eq!(one.samples(1), three.samples(1));
+        assert!((one.mean(1) - 20.0).abs() < 8.0);
+        assert!(one.error(1) > 0.0);
+    }
+
+    #[test]
+    fn test_dilution_weakens_order() {
+        let run = |vacancy_concentration| DisorderRun {
+            width: 16,
+            height: 16,
+            disorder: vec![
+                Disorder::PlusMinusBonds {
+                    antiferromagnetic_fraction: 0.0,
+                },
+                Disorder::Dilution {
+                    vacancy_concentration,
+                },
+            ],
+            coupling: 0.6,
+            field: 0.0,
+            thermalization_sweeps: 200,
+            measurement_sweeps: 100,
+        };
+        let pure = run(0.0)
+            .disorder_average(4, 4, 1, absolute_magnetization)
+            .unwrap();
+        let diluted = run(0.5)
+            .disorder_average(4, 4, 1, absolute_magnetization)
+            .unwrap();
+        assert!(pure.mean(0) > 0.6);
+        assert!(diluted.mean(0) < 0.4);
+        assert!(diluted.error(0) > 0.0);
+
+        let grid = Grid::new_constant(4, 4, Spin::Up).unwrap();
+        assert_eq!(absolute_magnetization(&grid), vec![1.0, 0.0]);
+    }
+}
@@ -0,0 +1,439 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+
+use crate::error::{Error, Result};
+use crate::measurement::Measurement;
+use crate::scan::{Scan, ScanPoint, ScanResult};
+
+/// The version of the messages, which a coordinator and its workers have to agree on.
+const PROTOCOL_VERSION: u32 = 2;
+
+/// How long the coordinator waits between looking for new workers.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a new connection has to introduce itself as a worker.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// # Worker message
+/// What a worker sends to the coordinator, as one line of JSON tagged by `message`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "message", rename_all = "snake_case")]
+pub enum WorkerMessage {
+    /// The worker connected and waits for its first task.
+    Ready { version: u32 },
+    /// The observables of the task with the given index, in the order of the fields of
+    /// `ScanResult` from the energy on, with NaN sent as null since JSON has no NaN. The worker
+    /// then waits for its next task.
+    Result {
+        index: usize,
+        observables: [Option<f64>; 10],
+    },
+}
+
+/// # Coordinator message
+/// What the coordinator sends to a worker, as one line of JSON tagged by `message`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "message", rename_all = "snake_case")]
+pub enum CoordinatorMessage {
+    /// Simulate a scan point with the given numbers of sweeps.
+    Task {
+        index: usize,
+        point: ScanPoint,
+        thermalization_sweeps: usize,
+        measurement_sweeps: usize,
+    },
+    /// There is nothing left to do, and the worker can disconnect.
+    Finished,
+}
+
+/// The progress of a distributed scan, shared by the connections of the coordinator.
+struct Progress {
+    pending: VecDeque<usize>,
+    results: Vec<Option<ScanResult>>,
+}
+
+impl Progress {
+    fn is_finished(&self) -> bool {
+        self.results.iter().all(Option::is_some)
+    }
+}
+
+/// Reads a line of JSON, failing at the end of the stream.
+fn receive<T: for<'de> Deserialize<'de>>(reader: &mut impl BufRead) -> io::Result<T> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the connection was closed",
+        ));
+    }
+    Ok(serde_json::from_str(&line)?)
+}
+
+/// Writes a message as a line of JSON.
+fn send(stream: &mut TcpStream, message: &impl Serialize) -> io::Result<()> {
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    stream.write_all(&line)
+}
+
+impl Scan {
+    /// # Run distributed
+    /// Simulates all the scan points on the workers that connect to the listener, such as
+    /// `Ising_Model worker --connect head:7070` on other machines, and returns the results in the
+    /// order of `points`. Every worker gets one point at a time, and the point of a worker that
+    /// disconnects before sending its result goes to another one, so workers can come and go
+    /// while the scan runs. Given a task timeout, a worker that takes longer than that over a
+    /// point is dropped as well and the point handed to another one, so a hung machine cannot
+    /// stall the scan; the timeout has to leave room for the slowest point, which would otherwise
+    /// be handed out forever. Since a point only depends on its seed, the results are the same as
+    /// those of `run`. Fails if the scan does not validate, before any worker is accepted.
+    pub fn run_distributed(
+        &self,
+        listener: &TcpListener,
+        task_timeout: Option<Duration>,
+    ) -> Result<Vec<ScanResult>> {
+        self.validate()?;
+        let points = self.points();
+        let progress = Mutex::new(Progress {
+            pending: (0..points.len()).collect(),
+            results: vec![None; points.len()],
+        });
+        let changed = Condvar::new();
+        listener.set_nonblocking(true)?;
+        info!(
+            address = %listener.local_addr()?,
+            points = points.len(),
+            "waiting for workers"
+        );
+
+        thread::scope(|scope| {
+            while !progress.lock().unwrap().is_finished() {
+                match listener.accept() {
+                    Ok((stream, address)) => {
+                        info!(%address, "worker connected");
+                        let (points, progress, changed) = (&points, &progress, &changed);
+                        scope.spawn(move || {
+                            let served =
+                                self.serve(stream, points, progress, changed, task_timeout);
+                            if let Err(error) = served {
+                                warn!(%address, %error, "lost a worker");
+                            }
+                        });
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_INTERVAL);
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
+            Ok(())
+        })?;
+
+        let results = progress.into_inner().unwrap().results;
+        Ok(results.into_iter().map(Option::unwrap).collect())
+    }
+
+    /// Hands out points to one worker until the scan is finished, putting its point back if the
+    /// connection fails or the result takes longer than the task timeout.
+    fn serve(
+        &self,
+        mut stream: TcpStream,
+        points: &[ScanPoint],
+        progress: &Mutex<Progress>,
+        changed: &Condvar,
+        task_timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        match receive(&mut reader)? {
+            WorkerMessage::Ready { version } if version == PROTOCOL_VERSION => {}
+            message => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "expected a worker of version {}, got {:?}",
+                        PROTOCOL_VERSION, message
+                    ),
+                ))
+            }
+        }
+        // Without a task timeout, a point may take as long as it takes.
+        stream.set_read_timeout(task_timeout)?;
+
+        loop {
+            let index = {
+                let mut progress = changed
+                    .wait_while(progress.lock().unwrap(), |progress| {
+                        progress.pending.is_empty() && !progress.is_finished()
+                    })
+                    .unwrap();
+                match progress.pending.pop_front() {
+                    Some(index) => index,
+                    None => break,
+                }
+            };
+            debug!(index, "assigning point");
+            let outcome = send(
+                &mut stream,
+                &CoordinatorMessage::Task {
+                    index,
+                    point: points[index],
+                    thermalization_sweeps: self.thermalization_sweeps,
+                    measurement_sweeps: self.measurement_sweeps,
+                },
+            )
+            .and_then(|()| receive(&mut reader));
+            let mut progress = progress.lock().unwrap();
+            match outcome {
+                Ok(WorkerMessage::Result {
+                    index: done,
+                    observables,
+                }) if done == index => {
+                    progress.results[index] = Some(from_observables(points[index], observables));
+                    changed.notify_all();
+                }
+                outcome => {
+                    progress.pending.push_back(index);
+                    changed.notify_all();
+                    return match outcome {
+                        // A read that times out fails with either kind, depending on the platform.
+                        Err(error)
+                            if matches!(
+                                error.kind(),
+                                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                            ) =>
+                        {
+                            Err(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                format!("no result for point {} within the task timeout", index),
+                            ))
+                        }
+                        Err(error) => Err(error),
+                        Ok(message) => Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("expected the result of point {}, got {:?}", index, message),
+                        )),
+                    };
+                }
+            }
+        }
+        send(&mut stream, &CoordinatorMessage::Finished)
+    }
+}
+
+/// # Work for a coordinator
+/// Connects to the coordinator of a distributed scan with the given number of connections, each
+/// simulating one point at a time on a thread of its own, until the coordinator has nothing left.
+/// Returns the number of points simulated. Fails if there are no threads or a connection fails.
+pub fn work(address: impl ToSocketAddrs, threads: usize) -> Result<usize> {
+    if threads == 0 {
+        return Err(Error::InvalidParameter {
+            name: "threads",
+            value: 0.0,
+        });
+    }
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to"))?;
+    thread::scope(|scope| {
+        let connections: Vec<_> = (0..threads)
+            .map(|_| scope.spawn(move || work_connection(TcpStream::connect(address)?)))
+            .collect();
+        connections
+            .into_iter()
+            .map(|connection| connection.join().unwrap())
+            .sum::<io::Result<usize>>()
+            .map_err(Error::from)
+    })
+}
+
+/// Simulates the points that the coordinator sends over one connection.
+fn work_connection(mut stream: TcpStream) -> io::Result<usize> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    send(
+        &mut stream,
+        &WorkerMessage::Ready {
+            version: PROTOCOL_VERSION,
+        },
+    )?;
+    let mut done = 0;
+    loop {
+        match receive(&mut reader)? {
+            CoordinatorMessage::Task {
+                index,
+                point,
+                thermalization_sweeps,
+                measurement_sweeps,
+            } => {
+                let scan = Scan {
+                    sizes: Vec::new(),
+                    temperatures: Vec::new(),
+                    fields: Vec::new(),
+                    crystal_fields: Vec::new(),
+                    seeds: Vec::new(),
+                    thermalization_sweeps,
+                    measurement_sweeps,
+                };
+                let result = scan.run_point(point)?;
+                send(
+                    &mut stream,
+                    &WorkerMessage::Result {
+                        index,
+                        observables: to_observables(&result),
+                    },
+                )?;
+                done += 1;
+            }
+            CoordinatorMessage::Finished => return Ok(done),
+        }
+    }
+}
+
+/// The observables of a result, with NaN as `None`.
+fn to_observables(result: &ScanResult) -> [Option<f64>; 10] {
+    [
+        result.energy.value,
+        result.energy.error,
+        result.magnetization.value,
+        result.magnetization.error,
+        result.absolute_magnetization.value,
+        result.absolute_magnetization.error,
+        result.susceptibility,
+        result.specific_heat,
+        result.binder_cumulant,
+        result.acceptance,
+    ]
+    .map(|value| (!value.is_nan()).then_some(value))
+}
+
+/// The result of a point from the observables sent by a worker.
+fn from_observables(point: ScanPoint, observables: [Option<f64>; 10]) -> ScanResult {
+    // The observables come in the order of `to_observables`, each value followed by its error.
+    let value = |index: usize| observables[index].unwrap_or(f64::NAN);
+    let measurement = |index: usize| Measurement::new(value(index), value(index + 1));
+    ScanResult {
+        size: point.size,
+        temperature: point.temperature.value(),
+        field: point.field,
+        crystal_field: point.crystal_field,
+        seed: point.seed,
+        energy: measurement(0),
+        magnetization: measurement(2),
+        absolute_magnetization: measurement(4),
+        susceptibility: value(6),
+        specific_heat: value(7),
+        binder_cumulant: value(8),
+        acceptance: value(9),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::temperature::Temperature;
+
+    fn scan() -> Scan {
+        Scan {
+            sizes: vec![4, 6],
+            temperatures: [1.5, 3.0].map(|t| Temperature::new(t).unwrap()).to_vec(),
+            fields: vec![0.0],
+            crystal_fields: vec![None, Some(0.5)],
+            seeds: vec![1, 2],
+            thermalization_sweeps: 20,
+            measurement_sweeps: 8,
+        }
+    }
+
+    /// Compares results whose error bars may be NaN.
+    fn same(a: &ScanResult, b: &ScanResult) -> bool {
+        to_observables(a) == to_observables(b)
+            && (a.size, a.temperature, a.field, a.crystal_field, a.seed)
+                == (b.size, b.temperature, b.field, b.crystal_field, b.seed)
+    }
+
+    #[test]
+    fn test_distributed_scan() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let scan = scan();
+        let workers = thread::spawn(move || {
+            let first = thread::spawn(move || work(address, 2).unwrap());
+            work(address, 1).unwrap() + first.join().unwrap()
+        });
+        let results = scan.run_distributed(&listener, None).unwrap();
+        assert_eq!(workers.join().unwrap(), 16);
+        assert!(work(address, 0).is_err());
+
+        // The measurement series are shorter than the blocks, so the errors are NaN.
+        assert!(results[0].energy.error.is_nan());
+        let expected = scan.run(1).unwrap();
+        assert!(results.iter().zip(&expected).all(|(a, b)| same(a, b)));
+    }
+
+    #[test]
+    fn test_lost_worker() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let scan = scan();
+        let workers = thread::spawn(move || {
+            // A worker that takes a point and disconnects without a result.
+            let mut stream = TcpStream::connect(address).unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let ready = WorkerMessage::Ready {
+                version: PROTOCOL_VERSION,
+            };
+            send(&mut stream, &ready).unwrap();
+            let task: CoordinatorMessage = receive(&mut reader).unwrap();
+            assert!(matches!(task, CoordinatorMessage::Task { index: 0, .. }));
+            drop((stream, reader));
+            work(address, 1).unwrap()
+        });
+        let results = scan.run_distributed(&listener, None).unwrap();
+        assert_eq!(workers.join().unwrap(), 16);
+        assert!(same(
+            &results[0],
+            &scan.run_point(scan.points()[0]).unwrap()
+        ));
+    }
+
+    #[test]
+    fn test_task_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let scan = scan();
+        let workers = thread::spawn(move || {
+            // A worker that takes a point and hangs without a result, until the coordinator
+            // gives up on it and closes the connection.
+            let mut stream = TcpStream::connect(address).unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let ready = WorkerMessage::Ready {
+                version: PROTOCOL_VERSION,
+            };
+            send(&mut stream, &ready).unwrap();
+            let task: CoordinatorMessage = receive(&mut reader).unwrap();
+            assert!(matches!(task, CoordinatorMessage::Task { index: 0, .. }));
+            let other = thread::spawn(move || work(address, 1).unwrap());
+            let closed = receive::<CoordinatorMessage>(&mut reader).unwrap_err();
+            assert_eq!(closed.kind(), io::ErrorKind::UnexpectedEof);
+            other.join().unwrap()
+        });
+        let results = scan
+            .run_distributed(&listener, Some(Duration::from_secs(2)))
+            .unwrap();
+        assert_eq!(workers.join().unwrap(), 16);
+        assert!(same(
+            &results[0],
+            &scan.run_point(scan.points()[0]).unwrap()
+        ));
+    }
+}
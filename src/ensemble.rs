@@ -0,0 +1,158 @@
+use crate::error::{Error, Result};
+use crate::measurement::Measurement;
+use crate::scan::{Scan, ScanResult};
+use crate::temperature::Temperature;
+
+/// # Ensemble of replicas
+/// Independent simulations of one L × L periodic grid at temperature T and field h, in units of
+/// the coupling, one for each of the given seeds. The replicas share nothing, so they keep every
+/// core busy without any care for correlations between them, and their spread gives error bars
+/// that do not depend on estimating autocorrelation times.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ensemble {
+    pub size: usize,
+    pub temperature: Temperature,
+    pub field: f64,
+    pub seeds: Vec<u64>,
+    pub thermalization_sweeps: usize,
+    pub measurement_sweeps: usize,
+}
+
+impl Ensemble {
+    /// # As a scan
+    /// The scan whose points are the replicas.
+    pub fn scan(&self) -> Scan {
+        Scan {
+            sizes: vec![self.size],
+            temperatures: vec![self.temperature],
+            fields: vec![self.field],
+            crystal_fields: vec![None],
+            seeds: self.seeds.clone(),
+            thermalization_sweeps: self.thermalization_sweeps,
+            measurement_sweeps: self.measurement_sweeps,
+        }
+    }
+
+    /// # Run
+    /// Simulates the replicas, spread over the given number of threads, and merges them. Fails
+    /// like `Scan::run`, or if there are no seeds.
+    pub fn run(&self, threads: usize) -> Result<EnsembleResult> {
+        EnsembleResult::from_replicas(self.scan().run(threads)?)
+    }
+}
+
+/// # Ensemble result
+/// The results of the replicas in the order of the seeds, and the means of their observables with
+/// the standard errors of the means across the replicas, which are NaN for a single replica.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnsembleResult {
+    pub replicas: Vec<ScanResult>,
+    pub energy: Measurement,
+    pub magnetization: Measurement,
+    pub absolute_magnetization: Measurement,
+    pub susceptibility: Measurement,
+    pub specific_heat: Measurement,
+    pub binder_cumulant: Measurement,
+    pub acceptance: Measurement,
+}
+
+impl EnsembleResult {
+    /// # Merge replicas
+    /// Merges the results of independent replicas of the same point. Fails if there are none.
+    pub fn from_replicas(replicas: Vec<ScanResult>) -> Result<Self> {
+        if replicas.is_empty() {
+            return Err(Error::InvalidParameter {
+                name: "replicas",
+                value: 0.0,
+            });
+        }
+        let across = |f: fn(&ScanResult) -> f64| {
+            let values: Vec<f64> = replicas.iter().map(f).collect();
+            mean_across(&values)
+        };
+        Ok(Self {
+            energy: across(|result| result.energy.value),
+            magnetization: across(|result| result.magnetization.value),
+            absolute_magnetization: across(|result| result.absolute_magnetization.value),
+            susceptibility: across(|result| result.susceptibility),
+            specific_heat: across(|result| result.specific_heat),
+            binder_cumulant: across(|result| result.binder_cumulant),
+            acceptance: across(|result| result.acceptance),
+            replicas,
+        })
+    }
+}
+
+/// The mean of independent values with its standard error.
+fn mean_across(values: &[f64]) -> Measurement {
+    let count = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / count;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+    Measurement {
+        value: mean,
+        error: if values.len() > 1 {
+            (variance / count).sqrt()
+        } else {
+            f64::NAN
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exact::ExactEnumeration;
+
+    #[test]
+    fn test_mean_across() {
+        let estimate = mean_across(&[1.0, 2.0, 3.0, 6.0]);
+        assert_eq!(estimate.value, 3.0);
+        assert!((estimate.error - (14.0_f64 / 3.0 / 4.0).sqrt()).abs() < 1e-12);
+        assert!(mean_across(&[1.0]).error.is_nan());
+    }
+
+    #[test]
+    fn test_ensemble() {
+        let ensemble = Ensemble {
+            size: 4,
+            temperature: Temperature::new(3.0).unwrap(),
+            field: 0.0,
+            seeds: (10..18).collect(),
+            thermalization_sweeps: 100,
+            measurement_sweeps: 400,
+        };
+        let result = ensemble.run(3).unwrap();
+        assert_eq!(result.replicas.len(), 8);
+        assert_eq!(result.replicas[2].seed, 12);
+        assert_eq!(
+            result.replicas[2],
+            ensemble
+                .scan()
+                .run_point(ensemble.scan().points()[2])
+                .unwrap()
+        );
+
+        let expected = ExactEnumeration::new(4, 4)
+            .unwrap()
+            .observables(Temperature::new(3.0).unwrap(), 0.0)
+            .energy
+            * 3.0;
+        assert!(result.energy.error > 0.0);
+        assert!(
+            (result.energy.value - expected).abs() < 5.0 * result.energy.error,
+            "{}",
+            result.energy
+        );
+
+        let empty = Ensemble {
+            seeds: Vec::new(),
+            ..ensemble
+        };
+        assert!(empty.run(1).is_err());
+        assert!(EnsembleResult::from_replicas(Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt;
+use std::io;
+
+/// # Error
+/// What can go wrong when setting up or running a simulation, returned by the constructors and
+/// drivers that take parameters from outside, such as a configuration file or an embedding
+/// program, instead of panicking on them.
+#[derive(Debug)]
+pub enum Error {
+    /// A grid without any sites, which has no observables per site.
+    EmptyGrid { width: usize, height: usize },
+    /// A temperature that is zero, negative or not finite.
+    InvalidTemperature(f64),
+    /// A parameter with a value it cannot take, such as a coupling that is not finite or zero
+    /// threads.
+    InvalidParameter { name: &'static str, value: f64 },
+    /// A map, such as a field map or a mask, whose size is not that of the grid it is given to, or
+    /// a grid smaller than an operation needs.
+    SizeMismatch {
+        name: &'static str,
+        expected: (usize, usize),
+        found: (usize, usize),
+    },
+    /// An update that cannot sample the model it is given, such as a cluster update in a field.
+    Unsupported(&'static str),
+    /// Reading or writing a file, or talking over the network, failed.
+    Io(io::Error),
+}
+
+/// # Result
+/// A result whose error is the `Error` of this crate.
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::EmptyGrid { width, height } => {
+                write!(f, "a {} × {} grid has no sites", width, height)
+            }
+            Error::InvalidTemperature(temperature) => write!(
+                f,
+                "the temperature must be positive and finite, got {}",
+                temperature
+            ),
+            Error::InvalidParameter { name, value } => {
+                write!(f, "invalid {}: {}", name, value)
+            }
+            Error::SizeMismatch {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "the {} is {} × {} but {} × {} is needed",
+                name, found.0, found.1, expected.0, expected.1
+            ),
+            Error::Unsupported(reason) => f.write_str(reason),
+            Error::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<Error> for io::Error {
+    /// Lets the functions that return `io::Result` pass the errors on, with invalid parameters as
+    /// `InvalidInput`.
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            error => io::Error::new(io::ErrorKind::InvalidInput, error),
+        }
+    }
+}
+
+/// Checks that a grid of the given size has sites.
+pub(crate) fn check_size(width: usize, height: usize) -> Result<()> {
+    if width == 0 || height == 0 {
+        return Err(Error::EmptyGrid { width, height });
+    }
+    Ok(())
+}
+
+/// Checks that a map of the given name has the size of the grid it is given to.
+pub(crate) fn check_same_size(
+    name: &'static str,
+    expected: (usize, usize),
+    found: (usize, usize),
+) -> Result<()> {
+    if expected != found {
+        return Err(Error::SizeMismatch {
+            name,
+            expected,
+            found,
+        });
+    }
+    Ok(())
+}
+
+/// Checks that a temperature is positive and finite.
+pub(crate) fn check_temperature(temperature: f64) -> Result<()> {
+    if !(temperature > 0.0 && temperature.is_finite()) {
+        return Err(Error::InvalidTemperature(temperature));
+    }
+    Ok(())
+}
+
+/// Checks that a parameter is finite.
+pub(crate) fn check_finite(name: &'static str, value: f64) -> Result<()> {
+    if !value.is_finite() {
+        return Err(Error::InvalidParameter { name, value });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checks() {
+        assert!(check_size(3, 1).is_ok());
+        assert!(matches!(
+            check_size(0, 4),
+            Err(Error::EmptyGrid {
+                width: 0,
+                height: 4
+            })
+        ));
+        assert!(check_temperature(2.5).is_ok());
+        for temperature in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(check_temperature(temperature).is_err());
+        }
+        assert!(check_finite("coupling", f64::NAN).is_err());
+
+        let error = check_same_size("field map", (4, 4), (4, 3)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the field map is 4 × 3 but 4 × 4 is needed"
+        );
+        let error = io::Error::from(error);
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
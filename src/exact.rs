@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use crate::error::{check_size, Error, Result};
+use crate::temperature::Temperature;
+
+/// The largest number of sites that can be enumerated. Beyond this the 2^N states take far too long
+/// to visit.
+const MAX_SITES: usize = 30;
+
+/// # State count
+/// The number of configurations that share the same bond sum Σ s_i s_j and magnetization Σ s_i.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StateCount {
+    pub bond_sum: i64,
+    pub magnetization: i64,
+    pub count: u64,
+}
+
+/// # Exact observables
+/// The exact thermodynamic averages of a small lattice. All quantities except the partition
+/// function are per site, energies are in units of k_BT, following the reduced convention of
+/// `Grid::step`, and the entropy S/N k_B = ln Z/N + ⟨E⟩/N k_BT is in units of k_B.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExactObservables {
+    pub log_partition_function: f64,
+    pub energy: f64,
+    pub specific_heat: f64,
+    pub magnetization: f64,
+    pub absolute_magnetization: f64,
+    pub magnetization_squared: f64,
+    pub susceptibility: f64,
+    pub entropy: f64,
+}
+
+/// # Exact enumeration
+/// Visits all 2^N configurations of a small periodic square lattice and records the joint density
+/// of states g(B, M), from which the partition function and every observable follow exactly for
+/// any coupling and field. Bonds are counted the same way as in `Grid`, so for widths or heights of
+/// two the wrapped neighbours are counted twice.
+#[derive(Debug, Clone)]
+pub struct ExactEnumeration {
+    width: usize,
+    height: usize,
+    density_of_states: Vec<StateCount>,
+}
+
+impl ExactEnumeration {
+    /// # New exact enumeration
+    /// Enumerates every configuration of a width × height lattice. Fails if the lattice has no
+    /// sites or more than 30.
+    pub fn new(width: usize, height: usize) -> Result<Self> {
+        check_size(width, height)?;
+        let number_of_sites = width * height;
+        if number_of_sites > MAX_SITES {
+            return Err(Error::InvalidParameter {
+                name: "number of sites",
+                value: number_of_sites as f64,
+            });
+        }
+
+        // The four nearest neighbours of every site, with periodic boundary conditions.
+        let neighbours: Vec<[usize; 4]> = (0..number_of_sites)
+            .map(|site| {
+                let (x, y) = (site % width, site / width);
+                [
+                    y * width + (x + 1) % width,
+                    y * width + (x + width - 1) % width,
+                    ((y + 1) % height) * width + x,
+                    ((y + height - 1) % height) * width + x,
+                ]
+            })
+            .collect();
+
+        // Start from the all-up state and walk through the states in Gray-code order, so that
+        // consecutive states differ by a single flip and the sums can be updated in O(1).
+        let mut spins = vec![1_i64; number_of_sites];
+        let mut bond_sum = 2 * number_of_sites as i64;
+        let mut magnetization = number_of_sites as i64;
+        let mut counts: HashMap<(i64, i64), u64> = HashMap::new();
+        *counts.entry((bond_sum, magnetization)).or_insert(0) += 1;
+
+        for step in 1..1_u64 << number_of_sites {
+            let site = step.trailing_zeros() as usize;
+            let neighbour_sum: i64 = neighbours[site].iter().map(|&n| spins[n]).sum();
+            bond_sum -= 2 * spins[site] * neighbour_sum;
+            magnetization -= 2 * spins[site];
+            spins[site] = -spins[site];
+            *counts.entry((bond_sum, magnetization)).or_insert(0) += 1;
+        }
+
+        let mut density_of_states: Vec<StateCount> = counts
+            .into_iter()
+            .map(|((bond_sum, magnetization), count)| StateCount {
+                bond_sum,
+                magnetization,
+                count,
+            })
+            .collect();
+        density_of_states.sort_by_key(|state| (state.bond_sum, state.magnetization));
+
+        Ok(Self {
+            width,
+            height,
+            density_of_states,
+        })
+    }
+
+    /// # Number of sites
+    /// The number of spins on the enumerated lattice.
+    pub fn number_of_sites(&self) -> usize {
+        self.width * self.height
+    }
+
+    /// # Density of states
+    /// The joint density of states, sorted by bond sum and then magnetization.
+    pub fn density_of_states(&self) -> &[StateCount] {
+        &self.density_of_states
+    }
+
+    /// # Observables
+    /// Computes the exact averages at the given temperature and field h in units of the coupling,
+    /// that is at the reduced coupling β and field βh. The Boltzmann weights are handled in
+    /// logarithmic form so that large lattices and low temperatures do not overflow.
+    pub fn observables(&self, temperature: Temperature, field: f64) -> ExactObservables {
+        let coupling = temperature.beta();
+        let field = field * coupling;
+        let log_weights: Vec<f64> = self
+            .density_of_states
+            .iter()
+            .map(|state| {
+                (state.count as f64).ln()
+                    + coupling * state.bond_sum as f64
+                    + field * state.magnetization as f64
+            })
+            .collect();
+        let largest = log_weights
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+
+        // Accumulate the moments with weights relative to the largest one.
+        let mut partition_function = 0.0;
+        let (mut energy, mut energy_squared) = (0.0, 0.0);
+        let (mut magnetization, mut absolute_magnetization, mut magnetization_squared) =
+            (0.0, 0.0, 0.0);
+        for (state, log_weight) in self.density_of_states.iter().zip(&log_weights) {
+            let weight = (log_weight - largest).exp();
+            let state_energy =
+                -coupling * state.bond_sum as f64 - field * state.magnetization as f64;
+            let state_magnetization = state.magnetization as f64;
+
+            partition_function += weight;
+            energy += weight * state_energy;
+            energy_squared += weight * state_energy * state_energy;
+            magnetization += weight * state_magnetization;
+            absolute_magnetization += weight * state_magnetization.abs();
+            magnetization_squared += weight * state_magnetization * state_magnetization;
+        }
+
+        let n = self.number_of_sites() as f64;
+        let mean_energy = energy / partition_function;
+        let mean_magnetization = magnetization / partition_function;
+        let mean_magnetization_squared = magnetization_squared / partition_function;
+
+        ExactObservables {
+            log_partition_function: largest + partition_function.ln(),
+            energy: mean_energy / n,
+            specific_heat: (energy_squared / partition_function - mean_energy * mean_energy) / n,
+            magnetization: mean_magnetization / n,
+            absolute_magnetization: absolute_magnetization / partition_function / n,
+            magnetization_squared: mean_magnetization_squared / (n * n),
+            susceptibility: (mean_magnetization_squared - mean_magnetization * mean_magnetization)
+                / n,
+            entropy: (largest + partition_function.ln() + mean_energy) / n,
+        }
+    }
+}
+
+/// # Exact chain
+/// The exact solution of the Ising model on a periodic chain, obtained from its 2×2 transfer
+/// matrix. The finite-ring results are exact for any length, so sampled observables of a `Chain`
+/// can be compared against them without any thermodynamic-limit corrections. The temperature T and
+/// the field h are in units of the coupling, and the formulas use the reduced K = 1/T and H = h/T.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExactChain {
+    length: usize,
+}
+
+impl ExactChain {
+    /// # New exact chain
+    /// Creates the exact solution for a ring of the given length.
+    pub fn new(length: usize) -> Self {
+        Self { length }
+    }
+
+    /// # Log partition function
+    /// ln Z = ln(λ₊^N + λ₋^N), where λ± = e^K cosh H ± sqrt(e^(2K) sinh² H + e^(-2K)) are the
+    /// eigenvalues of the transfer matrix.
+    pub fn log_partition_function(&self, temperature: Temperature, field: f64) -> f64 {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        let root = ((2.0 * coupling).exp() * field.sinh().powi(2) + (-2.0 * coupling).exp()).sqrt();
+        let larger = coupling.exp() * field.cosh() + root;
+        let smaller = coupling.exp() * field.cosh() - root;
+        let n = self.length as i32;
+        n as f64 * larger.ln() + (1.0 + (smaller / larger).powi(n)).ln()
+    }
+
+    /// # Correlation
+    /// The zero-field spin–spin correlation ⟨s_0 s_r⟩ = (t^r + t^(N-r)) / (1 + t^N), where
+    /// t = tanh K.
+    pub fn correlation(&self, temperature: Temperature, distance: usize) -> f64 {
+        let t = temperature.beta().tanh();
+        let n = self.length as i32;
+        let r = (distance % self.length) as i32;
+        (t.powi(r) + t.powi(n - r)) / (1.0 + t.powi(n))
+    }
+
+    /// # Susceptibility
+    /// The zero-field susceptibility per site in reduced units, N⟨m²⟩, which is the sum of the
+    /// correlations over all distances.
+    pub fn susceptibility(&self, temperature: Temperature) -> f64 {
+        (0..self.length)
+            .map(|distance| self.correlation(temperature, distance))
+            .sum()
+    }
+
+    /// # Correlation length
+    /// The correlation length of the infinite chain, ξ = -1 / ln(tanh K).
+    pub fn correlation_length(temperature: Temperature) -> f64 {
+        -1.0 / temperature.beta().tanh().ln()
+    }
+
+    /// # Magnetization
+    /// The magnetization per site of the infinite chain, m = sinh H / sqrt(sinh² H + e^(-4K)).
+    pub fn magnetization(temperature: Temperature, field: f64) -> f64 {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        field.sinh() / (field.sinh().powi(2) + (-4.0 * coupling).exp()).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::grid::Grid;
+
+    #[test]
+    fn test_number_of_states() {
+        let enumeration = ExactEnumeration::new(3, 3).unwrap();
+        let total: u64 = enumeration
+            .density_of_states()
+            .iter()
+            .map(|state| state.count)
+            .sum();
+        assert_eq!(total, 512);
+        assert!(ExactEnumeration::new(0, 3).is_err());
+        assert!(ExactEnumeration::new(6, 6).is_err());
+    }
+
+    #[test]
+    fn test_free_spins() {
+        // Far above the coupling every spin is independent: ln Z = N ln(2 cosh H) and m = tanh H.
+        let field = 0.4_f64;
+        let temperature = Temperature::new(1e15).unwrap();
+        let observables = ExactEnumeration::new(2, 3)
+            .unwrap()
+            .observables(temperature, field * temperature.value());
+        assert!(
+            (observables.log_partition_function - 6.0 * (2.0 * field.cosh()).ln()).abs() < 1e-12
+        );
+        assert!((observables.magnetization - field.tanh()).abs() < 1e-12);
+        assert!((observables.susceptibility - 1.0 / field.cosh().powi(2)).abs() < 1e-12);
+        let entropy = (2.0 * field.cosh()).ln() - field * field.tanh();
+        assert!((observables.entropy - entropy).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_ground_state() {
+        let observables = ExactEnumeration::new(4, 4)
+            .unwrap()
+            .observables(Temperature::from_beta(5.0).unwrap(), 0.0);
+        assert!((observables.energy + 2.0 * 5.0).abs() < 1e-6);
+        assert!((observables.absolute_magnetization - 1.0).abs() < 1e-6);
+        assert!(observables.magnetization.abs() < 1e-12);
+        // Only the two ordered states are left.
+        assert!((observables.entropy - 2.0_f64.ln() / 16.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_exact_chain_partition_function() {
+        // Sum the Boltzmann weights of every state of a ring of six spins directly.
+        let (length, coupling, field) = (6, 0.4, 0.3);
+        let mut partition_function = 0.0;
+        for state in 0..1_u32 << length {
+            let spin = |site: usize| {
+                if state >> (site % length) & 1 == 0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            };
+            let exponent: f64 = (0..length)
+                .map(|site| coupling * spin(site) * spin(site + 1) + field * spin(site))
+                .sum();
+            partition_function += exponent.exp();
+        }
+
+        let temperature = Temperature::from_beta(coupling).unwrap();
+        let exact = ExactChain::new(length).log_partition_function(temperature, field / coupling);
+        assert!((exact - partition_function.ln()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_exact_chain_limits() {
+        let chain = ExactChain::new(1000);
+        let temperature = Temperature::new(2.0).unwrap();
+        assert!((chain.susceptibility(temperature) - 1.0_f64.exp()).abs() < 1e-9);
+        assert!((chain.correlation(temperature, 3) - 0.5_f64.tanh().powi(3)).abs() < 1e-12);
+        assert!(
+            (ExactChain::correlation_length(temperature) + 1.0 / 0.5_f64.tanh().ln()).abs() < 1e-12
+        );
+        assert_eq!(ExactChain::magnetization(temperature, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_metropolis_matches_exact() {
+        let (temperature, field) = (Temperature::from_beta(0.3).unwrap(), 1.0 / 3.0);
+        let exact = ExactEnumeration::new(3, 3)
+            .unwrap()
+            .observables(temperature, field);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut grid = Grid::new_with_magnetization(3, 3, 0.0, &mut rng).unwrap();
+        for _ in 0..1000 {
+            grid.step_with_rng(temperature, field, &mut rng);
+        }
+
+        let number_of_sweeps = 40_000;
+        let mut magnetization = 0.0;
+        let mut energy = 0.0;
+        for _ in 0..number_of_sweeps {
+            grid.step_with_rng(temperature, field, &mut rng);
+            magnetization += grid.magnetization();
+            energy += grid.energy(temperature, field);
+        }
+        magnetization /= (9 * number_of_sweeps) as f64;
+        energy /= (9 * number_of_sweeps) as f64;
+
+        assert!((magnetization - exact.magnetization).abs() < 0.02);
+        assert!((energy - exact.energy).abs() < 0.02);
+    }
+}
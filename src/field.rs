@@ -0,0 +1,262 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{check_same_size, Result};
+
+/// # Field protocol
+/// How the strength of a field changes with time, measured in sweeps. The value can be passed to
+/// `Grid::step` as the uniform field, or used to scale a field map.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum FieldProtocol {
+    /// The same value at all times.
+    Constant(f64),
+    /// Switches from `before` to `after` at the given sweep.
+    Step {
+        before: f64,
+        after: f64,
+        time: usize,
+    },
+    /// Equal to `amplitude` for `duration` sweeps starting at `start`, and zero otherwise.
+    Pulse {
+        amplitude: f64,
+        start: usize,
+        duration: usize,
+    },
+    /// Changes linearly from `from` to `to` over `duration` sweeps, and stays at `to` afterwards.
+    Ramp { from: f64, to: f64, duration: usize },
+    /// Oscillates as `amplitude · sin(2π t / period)`, as in dynamic hysteresis experiments.
+    Oscillating { amplitude: f64, period: f64 },
+}
+
+impl FieldProtocol {
+    /// # Value
+    /// The field strength at the given sweep.
+    pub fn value(&self, sweep: usize) -> f64 {
+        match *self {
+            FieldProtocol::Constant(value) => value,
+            FieldProtocol::Step {
+                before,
+                after,
+                time,
+            } => {
+                if sweep < time {
+                    before
+                } else {
+                    after
+                }
+            }
+            FieldProtocol::Pulse {
+                amplitude,
+                start,
+                duration,
+            } => {
+                if (start..start + duration).contains(&sweep) {
+                    amplitude
+                } else {
+                    0.0
+                }
+            }
+            FieldProtocol::Ramp { from, to, duration } => {
+                let progress = if duration == 0 {
+                    1.0
+                } else {
+                    (sweep as f64 / duration as f64).min(1.0)
+                };
+                from + (to - from) * progress
+            }
+            FieldProtocol::Oscillating { amplitude, period } => {
+                amplitude * (std::f64::consts::TAU * sweep as f64 / period).sin()
+            }
+        }
+    }
+}
+
+/// # Field map
+/// A spatially varying external field h(x, y) on a periodic width × height grid, in the same
+/// reduced units as the field passed to `Grid::step`. It is stored as one value per site, so a
+/// closure only has to be evaluated once when the map is built.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldMap {
+    width: usize,
+    height: usize,
+    values: Vec<f64>,
+}
+
+impl FieldMap {
+    /// # Uniform field map
+    /// Creates a map with the same field at every site.
+    pub fn uniform(width: usize, height: usize, value: f64) -> Self {
+        Self {
+            width,
+            height,
+            values: vec![value; width * height],
+        }
+    }
+
+    /// # Field map from a function
+    /// Creates a map by evaluating `f(x, y)` at every site.
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> f64) -> Self {
+        let values = (0..width * height)
+            .map(|index| f(index % width, index / width))
+            .collect();
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+
+    /// # Linear gradient
+    /// Creates a map that changes linearly across the grid, h(x, y) = base + gx·x + gy·y. On a
+    /// periodic grid the field jumps back to its base value across the edge, so open or fixed
+    /// boundary conditions are usually the better match.
+    pub fn linear_gradient(width: usize, height: usize, base: f64, gradient: (f64, f64)) -> Self {
+        Self::from_fn(width, height, |x, y| {
+            base + gradient.0 * x as f64 + gradient.1 * y as f64
+        })
+    }
+
+    /// # Gaussian spot
+    /// Creates a localized field h = amplitude · exp(-r² / 2σ²) around a centre, with the distance
+    /// measured to the nearest periodic image of the centre. This models the spot of a writing
+    /// head or a focused laser.
+    pub fn gaussian_spot(
+        width: usize,
+        height: usize,
+        center: (f64, f64),
+        amplitude: f64,
+        sigma: f64,
+    ) -> Self {
+        let nearest = |distance: f64, length: usize| {
+            let length = length as f64;
+            let distance = distance.rem_euclid(length);
+            distance.min(length - distance)
+        };
+        Self::from_fn(width, height, |x, y| {
+            let dx = nearest(x as f64 - center.0, width);
+            let dy = nearest(y as f64 - center.1, height);
+            amplitude * (-(dx * dx + dy * dy) / (2.0 * sigma * sigma)).exp()
+        })
+    }
+
+    /// # Scaled map
+    /// Returns a copy of the map with every value multiplied by a factor, for example the value of
+    /// a `FieldProtocol` at the current sweep.
+    pub fn scaled(&self, factor: f64) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            values: self.values.iter().map(|value| value * factor).collect(),
+        }
+    }
+
+    /// # Add a map
+    /// Adds another map of the same size to this one, site by site, so that simple profiles can be
+    /// combined. Fails if the other map has a different size.
+    pub fn add(&mut self, other: &FieldMap) -> Result<()> {
+        check_same_size(
+            "field map",
+            (self.width, self.height),
+            (other.width, other.height),
+        )?;
+        for (value, other) in self.values.iter_mut().zip(&other.values) {
+            *value += other;
+        }
+        Ok(())
+    }
+
+    /// # Width
+    /// The number of columns of the map.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows of the map.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Get the field
+    /// The field at the given coordinates, with periodic boundary conditions.
+    pub fn get(&self, x: i64, y: i64) -> f64 {
+        self.values[self.index(x, y)]
+    }
+
+    /// # Set the field
+    /// Sets the field at the given coordinates, with periodic boundary conditions.
+    pub fn set(&mut self, x: i64, y: i64, value: f64) {
+        let index = self.index(x, y);
+        self.values[index] = value;
+    }
+
+    fn index(&self, x: i64, y: i64) -> usize {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        y * self.width + x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_fn() {
+        let map = FieldMap::from_fn(4, 3, |x, y| (10 * y + x) as f64);
+        assert_eq!(map.get(2, 1), 12.0);
+        assert_eq!(map.get(-1, 3), 3.0);
+    }
+
+    #[test]
+    fn test_profiles() {
+        let mut map = FieldMap::linear_gradient(5, 5, 1.0, (0.5, -0.25));
+        assert_eq!(map.get(0, 0), 1.0);
+        assert_eq!(map.get(4, 2), 2.5);
+
+        // The spot is centred on a corner, so it wraps around to the other corners.
+        let spot = FieldMap::gaussian_spot(5, 5, (0.0, 0.0), 2.0, 1.0);
+        assert_eq!(spot.get(0, 0), 2.0);
+        assert_eq!(spot.get(4, 0), spot.get(1, 0));
+        assert!((spot.get(1, 1) - 2.0 * (-1.0_f64).exp()).abs() < 1e-12);
+
+        map.add(&spot.scaled(0.5)).unwrap();
+        assert_eq!(map.get(0, 0), 2.0);
+        assert!(map.add(&FieldMap::uniform(4, 5, 1.0)).is_err());
+    }
+
+    #[test]
+    fn test_protocols() {
+        let step = FieldProtocol::Step {
+            before: -1.0,
+            after: 1.0,
+            time: 10,
+        };
+        assert_eq!(step.value(9), -1.0);
+        assert_eq!(step.value(10), 1.0);
+
+        let pulse = FieldProtocol::Pulse {
+            amplitude: 3.0,
+            start: 5,
+            duration: 2,
+        };
+        assert_eq!(
+            (4..8).map(|t| pulse.value(t)).collect::<Vec<_>>(),
+            [0.0, 3.0, 3.0, 0.0]
+        );
+
+        let ramp = FieldProtocol::Ramp {
+            from: 0.0,
+            to: 2.0,
+            duration: 4,
+        };
+        assert_eq!(ramp.value(1), 0.5);
+        assert_eq!(ramp.value(100), 2.0);
+
+        let oscillating = FieldProtocol::Oscillating {
+            amplitude: 1.0,
+            period: 8.0,
+        };
+        assert!((oscillating.value(2) - 1.0).abs() < 1e-12);
+        assert_eq!(FieldProtocol::Constant(0.7).value(3), 0.7);
+    }
+}
@@ -0,0 +1,325 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::error::{check_same_size, Result};
+use crate::grid::Grid;
+use crate::spin::Spin;
+
+/// The bytes at the start of every flip log file.
+const MAGIC: &[u8; 4] = b"ISFL";
+
+/// The format version written after the magic bytes.
+const VERSION: u8 = 1;
+
+/// The length of an event in a file: sweep, site, spin and energy change.
+const EVENT_LENGTH: usize = 13;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// # Flip event
+/// An accepted move: the sweep it happened in, counted from the start of the log, the site it
+/// changed as `y * width + x`, the spin the site was given and the change of the total energy it
+/// caused, in units of the temperature of its bath. The energy change is kept in single
+/// precision, which is plenty for the multiples of the coupling that it takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlipEvent {
+    pub sweep: u32,
+    pub site: u32,
+    pub spin: Spin,
+    pub energy_change: f32,
+}
+
+/// # Flip log
+/// Every accepted move of a run on a width × height grid, in the order they were made, at 16
+/// bytes each. Together with the starting configuration it replays the dynamics exactly, without
+/// storing any snapshots, and it holds what avalanche statistics and kinetic analyses need:
+/// which sites moved when, and what each move cost.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlipLog {
+    width: usize,
+    height: usize,
+    sweeps: usize,
+    events: Vec<FlipEvent>,
+}
+
+impl FlipLog {
+    /// # New flip log
+    /// Creates an empty log for a width × height grid.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sweeps: 0,
+            events: Vec::new(),
+        }
+    }
+
+    /// # Width
+    /// The number of columns of the grid.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows of the grid.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Add a flip
+    /// Records that the site at (x, y) was given the spin `spin` in the current sweep, changing
+    /// the energy by `energy_change`.
+    pub fn add_flip(&mut self, x: i64, y: i64, spin: Spin, energy_change: f64) {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.events.push(FlipEvent {
+            sweep: self.sweeps as u32,
+            site: (y * self.width + x) as u32,
+            spin,
+            energy_change: energy_change as f32,
+        });
+    }
+
+    /// # Add a sweep
+    /// Marks the end of a sweep, so that the next flips belong to the next one.
+    pub fn add_sweep(&mut self) {
+        self.sweeps += 1;
+    }
+
+    /// # Number of sweeps
+    /// The number of sweeps recorded so far.
+    pub fn sweeps(&self) -> usize {
+        self.sweeps
+    }
+
+    /// # Events
+    /// The recorded flips in the order they were made.
+    pub fn events(&self) -> &[FlipEvent] {
+        &self.events
+    }
+
+    /// # Number of events
+    /// The number of recorded flips.
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    /// # Is empty
+    /// Whether no flip has been recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// # Site of an event
+    /// The coordinates of the site that an event changed.
+    pub fn position(&self, event: &FlipEvent) -> (i64, i64) {
+        let site = event.site as usize;
+        ((site % self.width) as i64, (site / self.width) as i64)
+    }
+
+    /// # Flips per sweep
+    /// The number of flips in each recorded sweep, which at low temperature or in a slowly driven
+    /// field is the size distribution of the avalanches.
+    pub fn flips_per_sweep(&self) -> Vec<usize> {
+        let mut flips = vec![0; self.sweeps];
+        for event in &self.events {
+            flips[event.sweep as usize] += 1;
+        }
+        flips
+    }
+
+    /// # Flips per site
+    /// The number of times each site was flipped, as `y * width + x`, which shows where the
+    /// dynamics is fast and where it is frozen.
+    pub fn flips_per_site(&self) -> Vec<usize> {
+        let mut flips = vec![0; self.width * self.height];
+        for event in &self.events {
+            flips[event.site as usize] += 1;
+        }
+        flips
+    }
+
+    /// # Energy change
+    /// The total change of the energy over the recorded sweeps.
+    pub fn energy_change(&self) -> f64 {
+        self.events
+            .iter()
+            .map(|event| event.energy_change as f64)
+            .sum()
+    }
+
+    /// # Replay
+    /// Applies the flips of the first `sweeps` recorded sweeps to the grid, which takes a copy of
+    /// the starting configuration to the configuration after them. Fails unless the grid has the
+    /// size of the log.
+    pub fn replay(&self, grid: &mut Grid, sweeps: usize) -> Result<()> {
+        check_same_size(
+            "flip log",
+            (grid.width(), grid.height()),
+            (self.width, self.height),
+        )?;
+        for event in self
+            .events
+            .iter()
+            .take_while(|event| (event.sweep as usize) < sweeps)
+        {
+            let (x, y) = self.position(event);
+            grid.set(x, y, event.spin);
+        }
+        Ok(())
+    }
+
+    /// # Save
+    /// Writes the log to a compact binary file of 13 bytes per flip.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        file.write_all(&(self.width as u32).to_le_bytes())?;
+        file.write_all(&(self.height as u32).to_le_bytes())?;
+        file.write_all(&(self.sweeps as u64).to_le_bytes())?;
+        file.write_all(&(self.events.len() as u64).to_le_bytes())?;
+        for event in &self.events {
+            let code = match event.spin {
+                Spin::Up => 0,
+                Spin::Down => 1,
+                Spin::Zero => 2,
+                Spin::Vacant => 3,
+            };
+            file.write_all(&event.sweep.to_le_bytes())?;
+            file.write_all(&event.site.to_le_bytes())?;
+            file.write_all(&[code])?;
+            file.write_all(&event.energy_change.to_le_bytes())?;
+        }
+        file.flush()
+    }
+
+    /// # Load
+    /// Reads a log written by `save`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+        let mut header = [0; 29];
+        file.read_exact(&mut header)
+            .map_err(|_| invalid("not a flip log"))?;
+        if &header[..4] != MAGIC {
+            return Err(invalid("not a flip log"));
+        }
+        if header[4] != VERSION {
+            return Err(invalid("unsupported flip log version"));
+        }
+        let width = u32::from_le_bytes(header[5..9].try_into().unwrap()) as usize;
+        let height = u32::from_le_bytes(header[9..13].try_into().unwrap()) as usize;
+        let sweeps = u64::from_le_bytes(header[13..21].try_into().unwrap()) as usize;
+        let count = u64::from_le_bytes(header[21..29].try_into().unwrap()) as usize;
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        if bytes.len() != count * EVENT_LENGTH {
+            return Err(invalid("the flip log is cut off"));
+        }
+        let events = bytes
+            .chunks(EVENT_LENGTH)
+            .map(|chunk| {
+                let event = FlipEvent {
+                    sweep: u32::from_le_bytes(chunk[0..4].try_into().unwrap()),
+                    site: u32::from_le_bytes(chunk[4..8].try_into().unwrap()),
+                    spin: match chunk[8] {
+                        0 => Spin::Up,
+                        1 => Spin::Down,
+                        2 => Spin::Zero,
+                        _ => Spin::Vacant,
+                    },
+                    energy_change: f32::from_le_bytes(chunk[9..13].try_into().unwrap()),
+                };
+                if event.sweep as usize >= sweeps || event.site as usize >= width * height {
+                    return Err(invalid("a flip lies outside the log"));
+                }
+                Ok(event)
+            })
+            .collect::<io::Result<_>>()?;
+        Ok(Self {
+            width,
+            height,
+            sweeps,
+            events,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_flip_log() {
+        let (coupling, field) = (0.4, 0.05);
+        let start = Grid::new_checkerboard(8, 6).unwrap();
+        let mut grid = Grid::new_checkerboard(8, 6).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        let mut log = FlipLog::new(8, 6);
+        let mut accepted = 0;
+        let mut halfway = None;
+        for sweep in 0..20 {
+            accepted += grid
+                .step_with_flip_log(coupling, field, &mut rng, &mut log)
+                .unwrap();
+            if sweep == 9 {
+                halfway = Some(grid.to_string());
+            }
+        }
+        assert_eq!(log.sweeps(), 20);
+        assert_eq!(log.len(), accepted);
+        assert_eq!(log.flips_per_sweep().iter().sum::<usize>(), accepted);
+        assert_eq!(log.flips_per_site().iter().sum::<usize>(), accepted);
+
+        // The same seed without a log makes the same moves.
+        let mut unlogged = Grid::new_checkerboard(8, 6).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(5);
+        for _ in 0..20 {
+            unlogged.step_reduced_with_rng(coupling, field, &mut rng);
+        }
+        assert_eq!(unlogged.hamming_distance(&grid).unwrap(), 0);
+
+        let mut replayed = Grid::new_checkerboard(8, 6).unwrap();
+        log.replay(&mut replayed, 10).unwrap();
+        assert_eq!(Some(replayed.to_string()), halfway);
+        log.replay(&mut replayed, 20).unwrap();
+        assert!(log
+            .replay(&mut Grid::new_checkerboard(6, 8).unwrap(), 20)
+            .is_err());
+        assert_eq!(replayed.hamming_distance(&grid).unwrap(), 0);
+
+        let expected = grid.energy_reduced(coupling, field) - start.energy_reduced(coupling, field);
+        assert!((log.energy_change() - expected).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut grid = Grid::new_random(5, 7).unwrap();
+        grid.set_crystal_field_ratio(Some(0.5));
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let mut log = FlipLog::new(5, 7);
+        for _ in 0..6 {
+            grid.step_with_flip_log(0.3, 0.0, &mut rng, &mut log)
+                .unwrap();
+        }
+        assert!(log.events().iter().any(|event| event.spin == Spin::Zero));
+
+        let path = env::temp_dir().join("ising_flip_log_test.bin");
+        log.save(&path).unwrap();
+        assert_eq!(FlipLog::load(&path).unwrap(), log);
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+        assert!(FlipLog::load(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,256 @@
+use std::f64::consts::LN_2;
+
+use crate::error::{Error, Result};
+use crate::measurement::Measurement;
+use crate::scan::Scan;
+use crate::temperature::Temperature;
+
+/// # Integrate the energy
+/// The free energy βf per site at each of the given temperatures, with β = J/k_BT, from the
+/// energies e per site in units of the coupling measured there, with their standard errors. It
+/// integrates d(βf)/dβ = e with the trapezoidal rule from infinite temperature, where the spins
+/// are independent, so that e = 0 and βf = -ln 2 exactly, whatever the field. The errors of the
+/// energies are propagated as independent, while the error of the rule itself is not included:
+/// it shrinks with the square of the spacing, which has to be small where the energy bends, near
+/// the critical point. The temperatures must be decreasing.
+pub fn integrate_energy(
+    temperatures: &[Temperature],
+    energies: &[Measurement],
+) -> Vec<Measurement> {
+    integrate(temperatures, energies)
+        .into_iter()
+        .map(|(free_energy, _)| free_energy)
+        .collect()
+}
+
+/// # Entropy from the energy
+/// The entropy s = β(e - f) per site in units of k_B at each of the given temperatures, from the
+/// same integration as `integrate_energy`. Its error accounts for the energy at the temperature
+/// itself entering both e and f.
+pub fn integrate_entropy(
+    temperatures: &[Temperature],
+    energies: &[Measurement],
+) -> Vec<Measurement> {
+    integrate(temperatures, energies)
+        .into_iter()
+        .map(|(_, entropy)| entropy)
+        .collect()
+}
+
+/// The free energy βf and the entropy s per site at every node of the trapezoidal rule.
+fn integrate(
+    temperatures: &[Temperature],
+    energies: &[Measurement],
+) -> Vec<(Measurement, Measurement)> {
+    assert_eq!(
+        temperatures.len(),
+        energies.len(),
+        "every temperature needs an energy"
+    );
+    let mut potentials = Vec::with_capacity(energies.len());
+    let (mut previous_beta, mut previous_energy) = (0.0, 0.0);
+    let mut value = -LN_2;
+    // The weight of every energy so far in the integral up to the last node.
+    let mut weights: Vec<f64> = Vec::with_capacity(energies.len());
+    for (temperature, energy) in temperatures.iter().zip(energies) {
+        let beta = temperature.beta();
+        let step = beta - previous_beta;
+        value += 0.5 * step * (previous_energy + energy.value);
+        if let Some(last) = weights.last_mut() {
+            *last += 0.5 * step;
+        }
+        weights.push(0.5 * step);
+        let variance = |last: f64| {
+            let (earlier, _) = weights.split_at(weights.len() - 1);
+            earlier
+                .iter()
+                .zip(energies)
+                .map(|(weight, energy)| (weight * energy.error).powi(2))
+                .sum::<f64>()
+                + (last * energy.error).powi(2)
+        };
+        let free_energy = Measurement {
+            value,
+            error: variance(0.5 * step).sqrt(),
+        };
+        // s = βe - βf, in which e has the weight β - step/2 and the earlier energies change sign.
+        let entropy = Measurement {
+            value: beta * energy.value - value,
+            error: variance(beta - 0.5 * step).sqrt(),
+        };
+        potentials.push((free_energy, entropy));
+        (previous_beta, previous_energy) = (beta, energy.value);
+    }
+    potentials
+}
+
+/// # Thermodynamic integration
+/// Simulates an L × L periodic grid at a fixed field h in units of the coupling at each of the
+/// given temperatures, from the highest down, one seed for all, and integrates the measured
+/// energies over the inverse temperature β = J/k_BT into absolute free energies and entropies.
+/// This complements the exact density of states of small grids with a method that works at any
+/// size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThermodynamicIntegration {
+    pub size: usize,
+    pub field: f64,
+    pub temperatures: Vec<Temperature>,
+    pub seed: u64,
+    pub thermalization_sweeps: usize,
+    pub measurement_sweeps: usize,
+}
+
+/// # Free energy point
+/// The energy and free energy per site at one temperature, both in units of the coupling, and
+/// the entropy per site in units of k_B, with their standard errors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FreeEnergyPoint {
+    pub temperature: f64,
+    pub energy: Measurement,
+    pub free_energy: Measurement,
+    pub entropy: Measurement,
+}
+
+impl ThermodynamicIntegration {
+    /// # As a scan
+    /// The scan whose points are the temperatures of the integration.
+    pub fn scan(&self) -> Scan {
+        Scan {
+            sizes: vec![self.size],
+            temperatures: self.temperatures.clone(),
+            fields: vec![self.field],
+            crystal_fields: vec![None],
+            seeds: vec![self.seed],
+            thermalization_sweeps: self.thermalization_sweeps,
+            measurement_sweeps: self.measurement_sweeps,
+        }
+    }
+
+    /// # Run
+    /// Simulates the temperatures, spread over the given number of threads, and integrates their
+    /// energies. Fails like `Scan::run`, or if the temperatures are not decreasing.
+    pub fn run(&self, threads: usize) -> Result<Vec<FreeEnergyPoint>> {
+        for pair in self.temperatures.windows(2) {
+            if pair[1] >= pair[0] {
+                return Err(Error::InvalidParameter {
+                    name: "temperature",
+                    value: pair[1].value(),
+                });
+            }
+        }
+        let results = self.scan().run(threads)?;
+        let energies: Vec<Measurement> = results.iter().map(|result| result.energy).collect();
+        let potentials = integrate(&self.temperatures, &energies);
+        Ok(results
+            .iter()
+            .zip(energies)
+            .zip(potentials)
+            .map(|((result, energy), (reduced, entropy))| FreeEnergyPoint {
+                temperature: result.temperature,
+                energy,
+                free_energy: reduced * result.temperature,
+                entropy,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exact::ExactEnumeration;
+
+    /// The exact βf per site of a 4 × 4 grid at a temperature and field h.
+    fn exact_free_energy(
+        enumeration: &ExactEnumeration,
+        temperature: Temperature,
+        field: f64,
+    ) -> f64 {
+        -enumeration
+            .observables(temperature, field)
+            .log_partition_function
+            / 16.0
+    }
+
+    #[test]
+    fn test_integrate_energy() {
+        let enumeration = ExactEnumeration::new(4, 4).unwrap();
+        let field = 0.3;
+        let temperatures: Vec<Temperature> = (1..=200)
+            .map(|step| Temperature::from_beta(step as f64 * 0.005).unwrap())
+            .collect();
+        let energies: Vec<Measurement> = temperatures
+            .iter()
+            .map(|&temperature| Measurement {
+                value: enumeration.observables(temperature, field).energy * temperature.value(),
+                error: 0.01,
+            })
+            .collect();
+        let free_energies = integrate_energy(&temperatures, &energies);
+        let entropies = integrate_entropy(&temperatures, &energies);
+        for (index, &temperature) in temperatures.iter().enumerate().step_by(40) {
+            let expected = exact_free_energy(&enumeration, temperature, field);
+            assert!((free_energies[index].value - expected).abs() < 1e-4);
+            let exact = enumeration.observables(temperature, field);
+            assert!((entropies[index].value - exact.entropy).abs() < 1e-4);
+        }
+        // With equal errors and spacing the weights are the spacing, and half of it at the end.
+        let error = free_energies[199].error;
+        let expected = 0.01 * 0.005 * (199.0 + 0.25_f64).sqrt();
+        assert!((error - expected).abs() < 1e-12);
+        let error = entropies[199].error;
+        let expected = 0.01 * (0.005_f64.powi(2) * 199.0 + (1.0 - 0.0025_f64).powi(2)).sqrt();
+        assert!((error - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_thermodynamic_integration() {
+        let integration = ThermodynamicIntegration {
+            size: 4,
+            field: 0.0,
+            temperatures: (1..=12)
+                .map(|step| Temperature::from_beta(step as f64 * 0.05).unwrap())
+                .collect(),
+            seed: 3,
+            thermalization_sweeps: 200,
+            measurement_sweeps: 4000,
+        };
+        let points = integration.run(4).unwrap();
+        assert_eq!(points.len(), 12);
+        let enumeration = ExactEnumeration::new(4, 4).unwrap();
+        for (point, temperature) in points.iter().zip(&integration.temperatures) {
+            assert_eq!(point.temperature, temperature.value());
+            let expected = exact_free_energy(&enumeration, *temperature, 0.0) * temperature.value();
+            let tolerance = 5.0 * point.free_energy.error + 0.01 * expected.abs();
+            assert!(
+                (point.free_energy.value - expected).abs() < tolerance,
+                "{} {}",
+                point.free_energy,
+                expected
+            );
+            let expected = enumeration.observables(*temperature, 0.0).entropy;
+            let tolerance = 5.0 * point.entropy.error + 0.01;
+            assert!(
+                (point.entropy.value - expected).abs() < tolerance,
+                "{} {}",
+                point.entropy,
+                expected
+            );
+        }
+
+        let increasing = ThermodynamicIntegration {
+            temperatures: vec![
+                Temperature::new(2.5).unwrap(),
+                Temperature::new(5.0).unwrap(),
+            ],
+            ..integration
+        };
+        assert!(matches!(
+            increasing.run(1),
+            Err(Error::InvalidParameter {
+                name: "temperature",
+                value: 5.0
+            })
+        ));
+    }
+}
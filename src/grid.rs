@@ -1,51 +1,474 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::{Index, IndexMut};
+use std::path::Path;
+
+use image::{ImageError, Rgb, RgbImage};
+use rand::seq::SliceRandom;
+use rand::Rng;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::couplings::BondCouplings;
+use crate::error::{check_same_size, check_size, Error, Result};
+use crate::field::FieldMap;
+use crate::flip_log::FlipLog;
+use crate::mask::SiteMask;
+use crate::render::Palette;
 use crate::spin::Spin;
+use crate::temperature::Temperature;
+use crate::thermostat::{EnergyCurrent, ThermostatMap};
+
+/// The offsets of the four nearest neighbours of a site, at y + 1, y - 1, x - 1 and x + 1 in the
+/// order of `BondCouplings::neighbor_couplings`.
+pub(crate) const NEIGHBOR_OFFSETS: [(i64, i64); 4] = [(0, 1), (0, -1), (-1, 0), (1, 0)];
+
+/// # Coarse-graining rule
+/// How a block of spins is replaced by a single spin when coarse-graining a grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoarseGrainRule {
+    /// The block spin follows the majority of the block. Ties are broken by the first up or down
+    /// spin of the block, read row by row from its top-left corner, so that the blocked grid can be
+    /// reproduced; a block holding neither becomes a zero spin.
+    Majority,
+    /// The block spin is the spin in the top-left corner of the block, and the rest are discarded.
+    Decimation,
+}
+
+/// # Boundary condition
+/// What a site at the edge of the grid sees beyond it, along one axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoundaryCondition {
+    /// The grid wraps around, so the last site along the axis neighbours the first.
+    Periodic,
+    /// The grid wraps around, but every bond that crosses the edge has its sign reversed. This
+    /// forces an odd number of domain walls along the axis and is used to measure interface
+    /// tensions.
+    Antiperiodic,
+    /// There is nothing beyond the edge, so the edge sites have fewer bonds.
+    Open,
+    /// The edge sites couple to a frozen layer of the given spin just beyond the edge.
+    Fixed(Spin),
+    /// Stepping off the end of a row leads to the start of the next one, so the rows form a
+    /// single helix. Along the x axis, and with periodic y, the neighbours of the site with flat
+    /// index i = y·width + x are simply i ± 1 and i ± width modulo the number of sites. Along the
+    /// y axis the columns are joined in the same way.
+    Helical,
+}
 
 /// # Grid
-/// This is a struct that represents a grid of spins.
-#[derive(Debug)]
+/// This is a struct that represents a grid of spins. Besides the nearest-neighbour coupling that is
+/// passed to `step`, each spin can couple to its four diagonal neighbours with a strength given as
+/// a ratio κ = J₂/J₁ of the nearest-neighbour coupling, which is zero by default. The
+/// nearest-neighbour bonds can also be given individual strengths, which are likewise multiples of
+/// the coupling passed to `step`. The bonds follow the boundary conditions along each axis, which
+/// are periodic by default. An optional field map adds a site-dependent field to the uniform field
+/// passed to `step`. Pinned spins are never updated, but still act on their neighbours. With a
+/// crystal field the spins become spin-1 and can also take the zero state. A thermostat map
+/// holds different parts of the grid at different temperatures.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Grid {
     spins: Vec<Spin>,
+    pinned: Vec<bool>,
     width: usize,
     height: usize,
+    boundary_conditions: [BoundaryCondition; 2],
+    next_nearest_ratio: f64,
+    crystal_field_ratio: Option<f64>,
+    bond_couplings: Option<BondCouplings>,
+    field_map: Option<FieldMap>,
+    thermostat_map: Option<ThermostatMap>,
 }
 
 impl Grid {
     /// # New random grid
-    /// This function creates a new grid of spins, where each spin has a random orientation.
-    pub fn new_random(width: usize, height: usize) -> Self {
+    /// This function creates a new grid of spins, where each spin has a random orientation. Fails
+    /// if the grid has no sites.
+    pub fn new_random(width: usize, height: usize) -> Result<Self> {
+        check_size(width, height)?;
         let spins = (0..width * height)
-            .map(|_| {
-                if rand::random::<bool>() {
-                    Spin::Up
-                } else {
-                    Spin::Down
-                }
-            })
+            .map(|_| rand::random::<Spin>())
             .collect();
 
-        Self {
+        Ok(Self {
             spins,
+            pinned: vec![false; width * height],
             width,
             height,
-        }
+            boundary_conditions: [BoundaryCondition::Periodic; 2],
+            next_nearest_ratio: 0.0,
+            crystal_field_ratio: None,
+            bond_couplings: None,
+            field_map: None,
+            thermostat_map: None,
+        })
     }
 
     /// # New constant grid
-    /// This function creates a new grid of spins, where each spin has the same orientation.
-    pub fn new_constant(width: usize, height: usize, spin: Spin) -> Self {
+    /// This function creates a new grid of spins, where each spin has the same orientation. Fails
+    /// if the grid has no sites.
+    pub fn new_constant(width: usize, height: usize, spin: Spin) -> Result<Self> {
+        check_size(width, height)?;
         let spins = vec![spin; width * height];
 
-        Self {
+        Ok(Self {
             spins,
+            pinned: vec![false; width * height],
             width,
             height,
+            boundary_conditions: [BoundaryCondition::Periodic; 2],
+            next_nearest_ratio: 0.0,
+            crystal_field_ratio: None,
+            bond_couplings: None,
+            field_map: None,
+            thermostat_map: None,
+        })
+    }
+
+    /// # New grid from a function
+    /// Creates a grid whose spin at (x, y) is `f(x, y)`, for any initial condition that can be
+    /// written down, such as interfaces, patterns or seeded droplets. The function is called for
+    /// every site, row by row. Fails if the grid has no sites.
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        mut f: impl FnMut(usize, usize) -> Spin,
+    ) -> Result<Self> {
+        let mut grid = Self::new_constant(width, height, Spin::Up)?;
+        for (index, spin) in grid.spins.iter_mut().enumerate() {
+            *spin = f(index % width, index / width);
+        }
+        Ok(grid)
+    }
+
+    /// # New striped grid
+    /// Creates a grid of vertical stripes, `stripe_width` columns wide, that alternate between up
+    /// and down starting with up at x = 0. Fails if the grid has no sites or the stripes no
+    /// columns.
+    pub fn new_stripes(width: usize, height: usize, stripe_width: usize) -> Result<Self> {
+        if stripe_width == 0 {
+            return Err(Error::InvalidParameter {
+                name: "stripe width",
+                value: 0.0,
+            });
+        }
+        Self::from_fn(width, height, |x, _| {
+            if (x / stripe_width) % 2 == 1 {
+                Spin::Down
+            } else {
+                Spin::Up
+            }
+        })
+    }
+
+    /// # New checkerboard grid
+    /// Creates the antiferromagnetic ground state, with up spins where x + y is even.
+    pub fn new_checkerboard(width: usize, height: usize) -> Result<Self> {
+        Self::from_fn(width, height, |x, y| {
+            if (x + y) % 2 == 1 {
+                Spin::Down
+            } else {
+                Spin::Up
+            }
+        })
+    }
+
+    /// # New droplet grid
+    /// Creates a circular droplet of up spins with the given radius in the centre of a grid of
+    /// down spins, as used to study the shrinking of a minority domain or nucleation.
+    pub fn new_droplet(width: usize, height: usize, radius: f64) -> Result<Self> {
+        let center = ((width as f64 - 1.0) / 2.0, (height as f64 - 1.0) / 2.0);
+        Self::from_fn(width, height, |x, y| {
+            if (x as f64 - center.0).hypot(y as f64 - center.1) <= radius {
+                Spin::Up
+            } else {
+                Spin::Down
+            }
+        })
+    }
+
+    /// # New interface grid
+    /// Creates a grid whose left half is up and right half is down. With periodic boundary
+    /// conditions along x there is a second interface across the edge, while with fixed or
+    /// antiperiodic boundaries the grid holds a single one.
+    pub fn new_interface(width: usize, height: usize) -> Result<Self> {
+        Self::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                Spin::Up
+            } else {
+                Spin::Down
+            }
+        })
+    }
+
+    /// # New grid with a fixed magnetization
+    /// Creates a random grid with exactly the number of up spins that comes closest to the given
+    /// magnetization per site, for protocols that conserve the magnetization. Fails if the grid
+    /// has no sites or the magnetization is not between minus one and one.
+    pub fn new_with_magnetization<R: Rng>(
+        width: usize,
+        height: usize,
+        magnetization: f64,
+        rng: &mut R,
+    ) -> Result<Self> {
+        if !(-1.0..=1.0).contains(&magnetization) {
+            return Err(Error::InvalidParameter {
+                name: "magnetization",
+                value: magnetization,
+            });
         }
+        let number_of_sites = width * height;
+        let number_up = ((1.0 + magnetization) / 2.0 * number_of_sites as f64).round() as usize;
+
+        let mut grid = Self::new_constant(width, height, Spin::Down)?;
+        grid.spins[..number_up].fill(Spin::Up);
+        grid.spins.shuffle(rng);
+        Ok(grid)
+    }
+
+    /// # New grid from an image
+    /// Creates a grid with one spin per pixel of a PNG or BMP image, so that letters, photos or
+    /// experimental domain images can be used as starting patterns. Pixels whose brightness, from
+    /// zero for black to one for white, is at least `threshold` become up spins and the rest down
+    /// spins. The top row of the image becomes y = 0.
+    pub fn from_image(path: impl AsRef<Path>, threshold: f64) -> Result<Self> {
+        let image = image::open(path).map_err(image_error)?.into_luma8();
+
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let mut grid = Self::new_constant(width, height, Spin::Down)?;
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if pixel.0[0] as f64 / 255.0 >= threshold {
+                grid.set(x as i64, y as i64, Spin::Up);
+            }
+        }
+        Ok(grid)
+    }
+
+    /// # To an image
+    /// Draws the grid with one square of `scale` × `scale` pixels per spin in the colours of the
+    /// palette. The top row of the image is y = 0, as in `from_image`. Fails if the scale is zero.
+    pub fn to_image(&self, palette: &Palette, scale: u32) -> Result<RgbImage> {
+        if scale == 0 {
+            return Err(Error::InvalidParameter {
+                name: "scale",
+                value: 0.0,
+            });
+        }
+        Ok(RgbImage::from_fn(
+            self.width as u32 * scale,
+            self.height as u32 * scale,
+            |x, y| Rgb(palette.color(self.get((x / scale) as i64, (y / scale) as i64))),
+        ))
+    }
+
+    /// # Save as PNG
+    /// Writes the image drawn by `to_image` to a PNG file.
+    pub fn save_png(&self, path: impl AsRef<Path>, palette: &Palette, scale: u32) -> Result<()> {
+        self.to_image(palette, scale)?
+            .save_with_format(path, image::ImageFormat::Png)
+            .map_err(image_error)
+    }
+
+    /// # Save
+    /// Writes the spins to a plain text file. After a comment line, the first line holds the width
+    /// and height, and each following line holds one row of the grid, starting at y = 0, with
+    /// `+` for an up spin, `-` for a down spin, `0` for a zero spin and `.` for a vacant site.
+    /// Only the spins are stored; couplings, fields, boundary conditions and pinning belong to the
+    /// simulation setup.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let contents = format!("# Ising grid\n{} {}\n{}\n", self.width, self.height, self);
+        fs::write(path, contents)
+    }
+
+    /// # Load
+    /// Reads a grid written by `save`. Lines that are empty or start with `#` are ignored. Fails
+    /// if the file cannot be read or parsed, or the grid has no sites.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let invalid = |line_number: usize, message: &str| {
+            Error::Io(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line_number + 1, message),
+            ))
+        };
+
+        let mut lines = contents
+            .lines()
+            .enumerate()
+            .map(|(line_number, line)| (line_number, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (line_number, header) = lines
+            .next()
+            .ok_or_else(|| invalid(0, "expected the width and height"))?;
+        let dimensions: Vec<usize> = header
+            .split_whitespace()
+            .map(|field| field.parse())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| invalid(line_number, "the dimensions must be positive integers"))?;
+        let &[width, height] = dimensions.as_slice() else {
+            return Err(invalid(line_number, "expected the width and height"));
+        };
+        check_size(width, height)?;
+
+        let mut spins = Vec::with_capacity(width * height);
+        let mut last_line = line_number;
+        for (line_number, line) in lines {
+            last_line = line_number;
+            if line.chars().count() != width {
+                return Err(invalid(line_number, "the row does not match the width"));
+            }
+            for character in line.chars() {
+                spins.push(match character {
+                    '+' => Spin::Up,
+                    '-' => Spin::Down,
+                    '0' => Spin::Zero,
+                    '.' => Spin::Vacant,
+                    _ => return Err(invalid(line_number, "spins must be '+', '-', '0' or '.'")),
+                });
+            }
+        }
+        if spins.len() != width * height {
+            return Err(invalid(
+                last_line,
+                "the number of rows does not match the height",
+            ));
+        }
+
+        let mut grid = Self::new_constant(width, height, Spin::Up)?;
+        grid.spins = spins;
+        Ok(grid)
+    }
+
+    /// # Width
+    /// Returns the number of columns in the grid.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// Returns the number of rows in the grid.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Boundary conditions
+    /// Returns the boundary conditions along the x and y axes.
+    pub fn boundary_conditions(&self) -> (BoundaryCondition, BoundaryCondition) {
+        (self.boundary_conditions[0], self.boundary_conditions[1])
+    }
+
+    /// # Set the boundary conditions
+    /// Sets the boundary conditions along the x and y axes. They change which bonds the edge
+    /// sites have, while `get` and `set` keep wrapping coordinates periodically.
+    pub fn set_boundary_conditions(&mut self, x: BoundaryCondition, y: BoundaryCondition) {
+        self.boundary_conditions = [x, y];
+    }
+
+    /// # Next-nearest ratio
+    /// Returns the ratio κ = J₂/J₁ of the diagonal coupling to the nearest-neighbour coupling.
+    pub fn next_nearest_ratio(&self) -> f64 {
+        self.next_nearest_ratio
+    }
+
+    /// # Set the next-nearest ratio
+    /// Sets the ratio κ = J₂/J₁ of the diagonal coupling to the nearest-neighbour coupling. A
+    /// negative ratio makes the diagonal bonds antiferromagnetic, and below κ = -1/2 the ground
+    /// state changes from the ferromagnet to rows or columns of alternating stripes.
+    pub fn set_next_nearest_ratio(&mut self, ratio: f64) {
+        self.next_nearest_ratio = ratio;
+    }
+
+    /// # Crystal field ratio
+    /// Returns the ratio Δ = D/J of the single-ion anisotropy to the nearest-neighbour coupling,
+    /// if the grid is a spin-1 Blume–Capel model.
+    pub fn crystal_field_ratio(&self) -> Option<f64> {
+        self.crystal_field_ratio
+    }
+
+    /// # Set the crystal field ratio
+    /// Turns the grid into the spin-1 Blume–Capel model, where every spin takes the values +1, 0
+    /// or -1 and a single-ion anisotropy adds K·Δ·S² to the energy of each site, or back into an
+    /// Ising model with `None`. A positive Δ favours the zero state. On the square lattice the
+    /// transition is continuous up to the tricritical point at Δ ≈ 1.966 and 1/K ≈ 0.608, first
+    /// order beyond it, and the ordered phase disappears at Δ = 2. Existing zero spins are left
+    /// alone when the crystal field is removed, but they are never proposed again.
+    pub fn set_crystal_field_ratio(&mut self, ratio: Option<f64>) {
+        self.crystal_field_ratio = ratio;
+    }
+
+    /// # Bond couplings
+    /// Returns the individual strengths of the nearest-neighbour bonds, if any have been set.
+    pub fn bond_couplings(&self) -> Option<&BondCouplings> {
+        self.bond_couplings.as_ref()
+    }
+
+    /// # Set the bond couplings
+    /// Gives every nearest-neighbour bond its own strength. Passing `None` makes all the bonds
+    /// equal again. Fails if the couplings are not of the size of the grid.
+    pub fn set_bond_couplings(&mut self, bond_couplings: Option<BondCouplings>) -> Result<()> {
+        if let Some(couplings) = &bond_couplings {
+            check_same_size(
+                "bond couplings",
+                (self.width, self.height),
+                (couplings.width(), couplings.height()),
+            )?;
+        }
+        self.bond_couplings = bond_couplings;
+        Ok(())
+    }
+
+    /// # Field map
+    /// Returns the site-dependent field, if one has been set.
+    pub fn field_map(&self) -> Option<&FieldMap> {
+        self.field_map.as_ref()
+    }
+
+    /// # Set the field map
+    /// Adds a site-dependent field h(x, y) on top of the uniform field passed to `step`, for
+    /// local field pulses, patterned writing or field steps across the grid. Passing `None`
+    /// leaves only the uniform field. Fails if the map is not of the size of the grid.
+    pub fn set_field_map(&mut self, field_map: Option<FieldMap>) -> Result<()> {
+        if let Some(map) = &field_map {
+            check_same_size(
+                "field map",
+                (self.width, self.height),
+                (map.width(), map.height()),
+            )?;
+        }
+        self.field_map = field_map;
+        Ok(())
+    }
+
+    /// # Thermostat map
+    /// Returns the site-dependent bath temperatures, if they have been set.
+    pub fn thermostat_map(&self) -> Option<&ThermostatMap> {
+        self.thermostat_map.as_ref()
+    }
+
+    /// # Set the thermostat map
+    /// Couples each site to a heat bath at its own temperature, relative to the temperature of
+    /// the coupling and field passed to `step`, for two-temperature and temperature-gradient
+    /// setups. Passing `None` couples every site to the same bath again. Fails if the map is not
+    /// of the size of the grid.
+    pub fn set_thermostat_map(&mut self, thermostat_map: Option<ThermostatMap>) -> Result<()> {
+        if let Some(map) = &thermostat_map {
+            check_same_size(
+                "thermostat map",
+                (self.width, self.height),
+                (map.width(), map.height()),
+            )?;
+        }
+        self.thermostat_map = thermostat_map;
+        Ok(())
     }
 
     /// # Get index
     /// This function gets the index of a spin at the given coordinates. It applies periodic
     /// boundary conditions to the input coordinates.
-    fn get_index(&self, x: i64, y: i64) -> usize {
+    pub(crate) fn get_index(&self, x: i64, y: i64) -> usize {
         // The modulo operator can return negative values, so we add the width/height after the
         // first modulo to ensure that the result is always positive.
         let x_periodic = ((x % self.width as i64) + self.width as i64) % self.width as i64;
@@ -65,10 +488,99 @@ impl Grid {
     /// This retrieves the spin at the given coordinates as a plus/minus one, also accounting for
     /// periodic boundary conditions.
     pub fn get_spin_as_float(&self, x: i64, y: i64) -> f64 {
-        match self.get(x, y) {
-            Spin::Up => 1.0,
-            Spin::Down => -1.0,
+        self.get(x, y).value()
+    }
+
+    /// # Get a neighbour as a plus/minus one
+    /// Retrieves the spin at (x + dx, y + dy) as seen from the site at (x, y), applying the
+    /// boundary condition of every edge that the step crosses. A neighbour beyond an open edge
+    /// counts as zero, so its bond drops out of every sum.
+    pub fn get_neighbor_as_float(&self, x: i64, y: i64, dx: i64, dy: i64) -> f64 {
+        self.neighbor(x, y, dx, dy).0
+    }
+
+    /// The neighbour at (x + dx, y + dy) as seen from (x, y), like `get_neighbor_as_float`, and
+    /// whether it belongs to the frozen layer beyond a fixed edge rather than to the grid.
+    fn neighbor(&self, x: i64, y: i64, dx: i64, dy: i64) -> (f64, bool) {
+        let mut neighbor = [
+            x.rem_euclid(self.width as i64) + dx,
+            y.rem_euclid(self.height as i64) + dy,
+        ];
+        let lengths = [self.width as i64, self.height as i64];
+
+        // The axes are handled in order, so a helical step off the end of a row can still cross
+        // the edge of the y axis afterwards.
+        let mut sign = 1.0;
+        for axis in 0..2 {
+            if (0..lengths[axis]).contains(&neighbor[axis]) {
+                continue;
+            }
+            match self.boundary_conditions[axis] {
+                BoundaryCondition::Periodic => {}
+                BoundaryCondition::Helical => {
+                    neighbor[1 - axis] += neighbor[axis].div_euclid(lengths[axis]);
+                }
+                BoundaryCondition::Antiperiodic => sign = -sign,
+                BoundaryCondition::Open => return (0.0, false),
+                BoundaryCondition::Fixed(spin) => {
+                    let value = match spin {
+                        Spin::Up => sign,
+                        Spin::Down => -sign,
+                        Spin::Zero | Spin::Vacant => 0.0,
+                    };
+                    return (value, true);
+                }
+            }
         }
+        (
+            sign * self.get_spin_as_float(neighbor[0], neighbor[1]),
+            false,
+        )
+    }
+
+    /// # Iterate over the sites
+    /// The coordinates and spin of every site, row by row from y = 0.
+    pub fn iter_sites(&self) -> impl Iterator<Item = ((i64, i64), Spin)> + '_ {
+        let width = self.width;
+        self.spins
+            .iter()
+            .enumerate()
+            .map(move |(index, &spin)| (((index % width) as i64, (index / width) as i64), spin))
+    }
+
+    /// # Iterate over the rows
+    /// The spins of every row, from y = 0.
+    pub fn iter_rows(&self) -> impl Iterator<Item = &[Spin]> + '_ {
+        self.spins.chunks(self.width)
+    }
+
+    /// # Neighbours
+    /// The four nearest neighbours of the site at (x, y) as plus or minus one, at y + 1, y - 1,
+    /// x - 1 and x + 1 in the order of `BondCouplings::neighbor_couplings`, as seen through the
+    /// boundary conditions like `get_neighbor_as_float`.
+    pub fn neighbors(&self, x: i64, y: i64) -> impl Iterator<Item = f64> + '_ {
+        NEIGHBOR_OFFSETS
+            .into_iter()
+            .map(move |(dx, dy)| self.get_neighbor_as_float(x, y, dx, dy))
+    }
+
+    /// # Iterate over the sites in parallel
+    /// The coordinates and spin of every site, like `iter_sites`, spread over the rayon thread
+    /// pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter_sites(&self) -> impl IndexedParallelIterator<Item = ((i64, i64), Spin)> + '_ {
+        let width = self.width;
+        self.spins
+            .par_iter()
+            .enumerate()
+            .map(move |(index, &spin)| (((index % width) as i64, (index / width) as i64), spin))
+    }
+
+    /// # Iterate over the rows in parallel
+    /// The spins of every row, like `iter_rows`, spread over the rayon thread pool.
+    #[cfg(feature = "rayon")]
+    pub fn par_iter_rows(&self) -> impl IndexedParallelIterator<Item = &[Spin]> + '_ {
+        self.spins.par_chunks(self.width)
     }
 
     /// # Set a spin
@@ -79,18 +591,181 @@ impl Grid {
         self.spins[index] = spin;
     }
 
+    /// # Dilute
+    /// Removes the spin from each site independently with probability `vacancy_concentration`.
+    /// Vacant sites drop out of every energy and are skipped by the updates, so below the
+    /// percolation threshold of the occupied sites no long-range order can form. Fails unless the
+    /// concentration is between zero and one.
+    pub fn dilute<R: Rng>(&mut self, vacancy_concentration: f64, rng: &mut R) -> Result<()> {
+        if !(0.0..=1.0).contains(&vacancy_concentration) {
+            return Err(Error::InvalidParameter {
+                name: "vacancy concentration",
+                value: vacancy_concentration,
+            });
+        }
+        for spin in self.spins.iter_mut() {
+            if rng.gen_bool(vacancy_concentration) {
+                *spin = Spin::Vacant;
+            }
+        }
+        Ok(())
+    }
+
+    /// # Apply a mask
+    /// Confines the grid to the active region of a mask by emptying every site outside it. The
+    /// empty sites drop out of all the sums and updates, exactly like vacancies, so the region can
+    /// have any shape. Sites inside the region keep their spins. Fails if the mask is not of the
+    /// size of the grid.
+    pub fn apply_mask(&mut self, mask: &SiteMask) -> Result<()> {
+        check_same_size(
+            "mask",
+            (self.width, self.height),
+            (mask.width(), mask.height()),
+        )?;
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                if !mask.is_active(x, y) {
+                    self.set(x, y, Spin::Vacant);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// # Number of vacancies
+    /// Returns the number of sites that carry no spin.
+    pub fn number_of_vacancies(&self) -> usize {
+        self.spins
+            .iter()
+            .filter(|&&spin| spin == Spin::Vacant)
+            .count()
+    }
+
+    /// # Number of occupied sites
+    /// Returns the number of sites that carry a spin, by which the per-site observables are
+    /// normalised.
+    pub fn number_of_occupied_sites(&self) -> usize {
+        self.spins.len() - self.number_of_vacancies()
+    }
+
+    /// # Quadrupole moment
+    /// Returns the mean of S² over the occupied sites, which is one for an Ising grid and drops
+    /// as the crystal field fills the grid with zero spins. Its jump marks the first-order part
+    /// of the Blume–Capel transition line.
+    pub fn quadrupole_moment(&self) -> f64 {
+        let occupied = self.number_of_occupied_sites();
+        let nonzero = self
+            .spins
+            .iter()
+            .filter(|&&spin| spin == Spin::Up || spin == Spin::Down)
+            .count();
+        nonzero as f64 / occupied as f64
+    }
+
+    /// # Pin a spin
+    /// Freezes the spin at the given coordinates, so that the updates skip it while it keeps
+    /// contributing to the energy of its neighbours. Pinned spins act as pinning centres, patterned
+    /// boundaries or, along an interface, as the fixed layer that causes exchange bias.
+    pub fn pin(&mut self, x: i64, y: i64) {
+        let index = self.get_index(x, y);
+        self.pinned[index] = true;
+    }
+
+    /// # Unpin a spin
+    /// Lets the spin at the given coordinates be updated again.
+    pub fn unpin(&mut self, x: i64, y: i64) {
+        let index = self.get_index(x, y);
+        self.pinned[index] = false;
+    }
+
+    /// # Is pinned
+    /// Whether the spin at the given coordinates is frozen.
+    pub fn is_pinned(&self, x: i64, y: i64) -> bool {
+        self.pinned[self.get_index(x, y)]
+    }
+
+    /// # Number of pinned spins
+    /// Returns the number of frozen sites.
+    pub fn number_of_pinned(&self) -> usize {
+        self.pinned.iter().filter(|&&pinned| pinned).count()
+    }
+
+    /// # Coarse grain
+    /// Returns a grid that is `block_size` times smaller in each direction, where each block of
+    /// spins has been replaced by a single spin according to the given rule. Any rows or columns
+    /// that do not fill a whole block are dropped, and a block without any spins stays vacant.
+    /// Fails unless at least one block fits in each direction.
+    pub fn coarse_grain(&self, block_size: usize, rule: CoarseGrainRule) -> Result<Grid> {
+        if block_size == 0 || block_size > self.width.min(self.height) {
+            return Err(Error::InvalidParameter {
+                name: "block size",
+                value: block_size as f64,
+            });
+        }
+        let width = self.width / block_size;
+        let height = self.height / block_size;
+
+        let mut spins = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (corner_x, corner_y) = (x * block_size, y * block_size);
+                let spin = match rule {
+                    CoarseGrainRule::Majority => {
+                        let mut sum = 0.0;
+                        let mut occupied = false;
+                        let mut first = None;
+                        for dy in 0..block_size {
+                            for dx in 0..block_size {
+                                let (x, y) = ((corner_x + dx) as i64, (corner_y + dy) as i64);
+                                let spin = self.get(x, y);
+                                occupied |= spin != Spin::Vacant;
+                                if matches!(spin, Spin::Up | Spin::Down) {
+                                    first = first.or(Some(spin));
+                                }
+                                sum += spin.value();
+                            }
+                        }
+
+                        if !occupied {
+                            Spin::Vacant
+                        } else if sum > 0.0 {
+                            Spin::Up
+                        } else if sum < 0.0 {
+                            Spin::Down
+                        } else {
+                            first.unwrap_or(Spin::Zero)
+                        }
+                    }
+                    CoarseGrainRule::Decimation => self.get(corner_x as i64, corner_y as i64),
+                };
+                spins.push(spin);
+            }
+        }
+
+        Ok(Self {
+            spins,
+            pinned: vec![false; width * height],
+            width,
+            height,
+            boundary_conditions: self.boundary_conditions,
+            next_nearest_ratio: self.next_nearest_ratio,
+            crystal_field_ratio: self.crystal_field_ratio,
+            bond_couplings: None,
+            field_map: None,
+            thermostat_map: None,
+        })
+    }
+
     /// # Get field energy
-    /// Gets the magnetic field energy at a site.
+    /// Gets the magnetic field energy at a site. Only the spin at the site couples to the field,
+    /// so a spin aligned with the field lowers the energy. The local field is the uniform field
+    /// plus the value of the field map at the site.
     fn field_energy(&self, x: i64, y: i64, field: f64) -> f64 {
-        // Get the nearest neighbours and the spin at the site.
-        let our_spin = self.get_spin_as_float(x, y);
-        let upper_neighbor = self.get_spin_as_float(x, y + 1);
-        let lower_neighbor = self.get_spin_as_float(x, y - 1);
-        let left_neighbor = self.get_spin_as_float(x - 1, y);
-        let right_neighbor = self.get_spin_as_float(x + 1, y);
-
-        // Calculate the magnetic field energy.
-        (our_spin + upper_neighbor + lower_neighbor + left_neighbor + right_neighbor) * field
+        let local_field = match &self.field_map {
+            Some(map) => field + map.get(x, y),
+            None => field,
+        };
+        -local_field * self.get_spin_as_float(x, y)
     }
 
     /// # Get the interaction energy
@@ -98,57 +773,409 @@ impl Grid {
     fn interaction_energy(&self, x: i64, y: i64, coupling: f64) -> f64 {
         // Get the nearest neighbours and the spin at the site.
         let our_spin = self.get_spin_as_float(x, y);
-        let upper_neighbor = self.get_spin_as_float(x, y + 1);
-        let lower_neighbor = self.get_spin_as_float(x, y - 1);
-        let left_neighbor = self.get_spin_as_float(x - 1, y);
-        let right_neighbor = self.get_spin_as_float(x + 1, y);
+        let upper_neighbor = self.get_neighbor_as_float(x, y, 0, 1);
+        let lower_neighbor = self.get_neighbor_as_float(x, y, 0, -1);
+        let left_neighbor = self.get_neighbor_as_float(x, y, -1, 0);
+        let right_neighbor = self.get_neighbor_as_float(x, y, 1, 0);
+
+        let nearest_sum = match &self.bond_couplings {
+            Some(couplings) => {
+                let [upper, lower, left, right] = couplings.neighbor_couplings(x, y);
+                upper * upper_neighbor
+                    + lower * lower_neighbor
+                    + left * left_neighbor
+                    + right * right_neighbor
+            }
+            None => upper_neighbor + lower_neighbor + left_neighbor + right_neighbor,
+        };
+
+        // The diagonal neighbours only need to be visited when they are coupled.
+        let next_nearest_sum = if self.next_nearest_ratio == 0.0 {
+            0.0
+        } else {
+            self.get_neighbor_as_float(x, y, 1, 1)
+                + self.get_neighbor_as_float(x, y, -1, 1)
+                + self.get_neighbor_as_float(x, y, 1, -1)
+                + self.get_neighbor_as_float(x, y, -1, -1)
+        };
 
         // Calculate the interaction energy.
-        -coupling * our_spin * (upper_neighbor + lower_neighbor + left_neighbor + right_neighbor)
+        -coupling * our_spin * (nearest_sum + self.next_nearest_ratio * next_nearest_sum)
+    }
+
+    /// # Get the bond energies
+    /// Gets the energies of the bonds from a site to its neighbours at y + 1, y - 1, x - 1 and
+    /// x + 1, in the order of `BondCouplings::neighbor_couplings`.
+    fn bond_energies(&self, x: i64, y: i64, coupling: f64) -> [f64; 4] {
+        let our_spin = self.get_spin_as_float(x, y);
+        let strengths = match &self.bond_couplings {
+            Some(couplings) => couplings.neighbor_couplings(x, y),
+            None => [1.0; 4],
+        };
+        let neighbors = [
+            self.get_neighbor_as_float(x, y, 0, 1),
+            self.get_neighbor_as_float(x, y, 0, -1),
+            self.get_neighbor_as_float(x, y, -1, 0),
+            self.get_neighbor_as_float(x, y, 1, 0),
+        ];
+        [0, 1, 2, 3].map(|k| -coupling * strengths[k] * our_spin * neighbors[k])
+    }
+
+    /// # Get the anisotropy energy
+    /// Gets the single-ion anisotropy energy K·Δ·S² at a site, which only a crystal field gives.
+    fn anisotropy_energy(&self, x: i64, y: i64, coupling: f64) -> f64 {
+        match self.crystal_field_ratio {
+            Some(ratio) => coupling * ratio * self.get_spin_as_float(x, y).powi(2),
+            None => 0.0,
+        }
+    }
+
+    /// # Get the boundary energy
+    /// Gets the energy of the bonds from a site to the frozen layers beyond fixed edges, which
+    /// unlike the bonds between two sites are only seen from one end.
+    fn boundary_energy(&self, x: i64, y: i64, coupling: f64) -> f64 {
+        let strengths = match &self.bond_couplings {
+            Some(couplings) => couplings.neighbor_couplings(x, y),
+            None => [1.0; 4],
+        };
+        let nearest = NEIGHBOR_OFFSETS.into_iter().zip(strengths);
+        let diagonal = [(1, 1), (-1, 1), (1, -1), (-1, -1)]
+            .into_iter()
+            .map(|offset| (offset, self.next_nearest_ratio));
+        let frozen_sum: f64 = nearest
+            .chain(diagonal)
+            .filter(|&(_, strength)| strength != 0.0)
+            .map(|((dx, dy), strength)| match self.neighbor(x, y, dx, dy) {
+                (value, true) => strength * value,
+                (_, false) => 0.0,
+            })
+            .sum();
+        -coupling * self.get_spin_as_float(x, y) * frozen_sum
+    }
+
+    /// # Magnetization
+    /// The total magnetization Σ s of the whole grid, to which vacancies and spins in the zero
+    /// state contribute nothing. It is a total, unlike the per-site `IsingModel::magnetization`.
+    pub fn magnetization(&self) -> f64 {
+        self.spins.iter().map(Spin::value).sum()
+    }
+
+    /// # Hamming distance
+    /// The number of sites at which the two grids hold different spins, such as the damage
+    /// between two copies of a grid updated with the same random numbers. Fails unless the grids
+    /// have the same size.
+    pub fn hamming_distance(&self, other: &Grid) -> Result<usize> {
+        Ok(self.diff(other)?.len())
+    }
+
+    /// # Overlap
+    /// The overlap q = (1/N) Σ s_i s'_i between the two grids, which is one for equal grids,
+    /// minus one for opposite ones and near zero for unrelated ones. Fails unless the grids have
+    /// the same size.
+    pub fn overlap(&self, other: &Grid) -> Result<f64> {
+        self.check_same_size(other)?;
+        let sum: f64 = self
+            .spins
+            .iter()
+            .zip(&other.spins)
+            .map(|(a, b)| a.value() * b.value())
+            .sum();
+        Ok(sum / self.spins.len() as f64)
+    }
+
+    /// # Difference
+    /// The sites at which the two grids hold different spins, row by row from y = 0. Fails unless
+    /// the grids have the same size.
+    pub fn diff(&self, other: &Grid) -> Result<Vec<(i64, i64)>> {
+        self.check_same_size(other)?;
+        Ok(self
+            .iter_sites()
+            .zip(&other.spins)
+            .filter(|((_, spin), other)| spin != *other)
+            .map(|(((x, y), _), _)| (x, y))
+            .collect())
+    }
+
+    fn check_same_size(&self, other: &Grid) -> Result<()> {
+        check_same_size(
+            "other grid",
+            (self.width, self.height),
+            (other.width, other.height),
+        )
+    }
+
+    /// # Energy
+    /// The total energy of the whole grid at temperature T and field h in units of the coupling,
+    /// in the reduced units βE of the Boltzmann weight, with every bond counted once. It includes
+    /// everything that `step` samples: the individual bond strengths, the diagonal bonds, the
+    /// bonds to the frozen layers of fixed edges, the field map and the crystal field. It is a
+    /// total, unlike the per-site `IsingModel::energy` and the energy of a single site given by
+    /// `total_energy`.
+    pub fn energy(&self, temperature: Temperature, field: f64) -> f64 {
+        self.energy_reduced(temperature.beta(), field * temperature.beta())
+    }
+
+    /// # Energy at a reduced coupling
+    /// The energy of `energy` at the reduced coupling K = βJ and field H = βh, which may be
+    /// negative for an antiferromagnet.
+    pub fn energy_reduced(&self, coupling: f64, field: f64) -> f64 {
+        let mut energy = 0.0;
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                // Each bond between two sites is seen from both of them.
+                energy += 0.5 * self.interaction_energy(x, y, coupling)
+                    + 0.5 * self.boundary_energy(x, y, coupling)
+                    + self.field_energy(x, y, field)
+                    + self.anisotropy_energy(x, y, coupling);
+            }
+        }
+        energy
     }
 
     /// # Get total energy
     /// Gets the total energy at a site.
     pub fn total_energy(&self, x: i64, y: i64, coupling: f64, field: f64) -> f64 {
-        self.interaction_energy(x, y, coupling) + self.field_energy(x, y, field)
+        self.interaction_energy(x, y, coupling)
+            + self.field_energy(x, y, field)
+            + self.anisotropy_energy(x, y, coupling)
     }
 
     /// # Single site step
-    /// This function performs a single Monte Carlo step at a single site.
-    pub fn single_site_step(&mut self, x: i64, y: i64, coupling: f64, field: f64) {
+    /// This function performs a single Monte Carlo step at a single site, at temperature T and
+    /// field h in units of the coupling. Vacant and pinned sites are left alone. With a crystal
+    /// field the spin is moved to one of its two other values, chosen at random, instead of being
+    /// flipped. Returns whether the move was accepted.
+    pub fn single_site_step(
+        &mut self,
+        x: i64,
+        y: i64,
+        temperature: Temperature,
+        field: f64,
+    ) -> bool {
+        self.single_site_step_with_rng(x, y, temperature, field, &mut rand::thread_rng())
+    }
+
+    /// # Single site step with a generator
+    /// Performs the same update as `single_site_step`, drawing its random numbers from the given
+    /// generator so that a seeded run can be reproduced exactly.
+    pub fn single_site_step_with_rng<R: Rng>(
+        &mut self,
+        x: i64,
+        y: i64,
+        temperature: Temperature,
+        field: f64,
+        rng: &mut R,
+    ) -> bool {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        self.attempt_move(x, y, coupling, field, rng).is_some()
+    }
+
+    /// Performs a single site update at the reduced coupling K = βJ and field H = βh, returning
+    /// the new spin and the change of the energy if the move was accepted.
+    fn attempt_move<R: Rng>(
+        &mut self,
+        x: i64,
+        y: i64,
+        coupling: f64,
+        field: f64,
+        rng: &mut R,
+    ) -> Option<(Spin, f64)> {
+        if self.get(x, y) == Spin::Vacant || self.is_pinned(x, y) {
+            return None;
+        }
+
         // Get the current energy at the site.
         let current_energy = self.total_energy(x, y, coupling, field);
 
-        // Flip the spin.
+        // Flip the spin, or pick one of the two other spin-1 values.
         let current_spin = self.get(x, y);
-        let new_spin = current_spin.flip();
+        let new_spin = match self.crystal_field_ratio {
+            Some(_) => {
+                let others = match current_spin {
+                    Spin::Up => [Spin::Down, Spin::Zero],
+                    Spin::Down => [Spin::Up, Spin::Zero],
+                    _ => [Spin::Up, Spin::Down],
+                };
+                others[usize::from(rng.gen::<bool>())]
+            }
+            None => current_spin.flip(),
+        };
         self.set(x, y, new_spin);
 
         // Get the new energy at the site.
         let new_energy = self.total_energy(x, y, coupling, field);
 
-        // Calculate exp(-ΔE); this is the probability of accepting the new configuration.
-        let probability_of_acceptance = (-(new_energy - current_energy).exp()).min(1.0);
+        // Calculate exp(-ΔE/τ), with τ the temperature of the bath that the site is coupled to;
+        // this is the probability of accepting the new configuration.
+        let temperature = self
+            .thermostat_map
+            .as_ref()
+            .map_or(1.0, |map| map.get(x, y));
+        let energy_change = new_energy - current_energy;
+        let probability_of_acceptance = (-energy_change / temperature).exp().min(1.0);
 
         // Create a random number between 0 and 1.
-        let random_number = rand::random::<f64>();
+        let random_number = rng.gen::<f64>();
 
         // If the random number is less than the probability of accepting the new
         // configuration, accept the new configuration.
         if random_number > probability_of_acceptance {
             self.set(x, y, current_spin);
+            return None;
         }
+        Some((new_spin, energy_change))
     }
 
     /// # Step
-    /// This function performs a single Monte Carlo step.
-    pub fn step(&mut self, coupling: f64, field: f64) {
-        // Iterate over all the spins.
-        for y in 0..self.height {
-            for x in 0..self.width {
-                self.single_site_step(x as i64, y as i64, coupling, field);
+    /// This function performs a single Monte Carlo step at temperature T and field h in units of
+    /// the coupling.
+    pub fn step(&mut self, temperature: Temperature, field: f64) {
+        self.step_with_rng(temperature, field, &mut rand::thread_rng());
+    }
+
+    /// # Step with a generator
+    /// Performs the same sweep as `step`, drawing its random numbers from the given generator.
+    /// Returns the number of accepted moves.
+    pub fn step_with_rng<R: Rng>(
+        &mut self,
+        temperature: Temperature,
+        field: f64,
+        rng: &mut R,
+    ) -> usize {
+        self.step_reduced_with_rng(temperature.beta(), field * temperature.beta(), rng)
+    }
+
+    /// # Step at a reduced coupling
+    /// Performs the sweep of `step` at the reduced coupling K = βJ and field H = βh, which may be
+    /// negative for an antiferromagnet.
+    pub fn step_reduced(&mut self, coupling: f64, field: f64) {
+        self.step_reduced_with_rng(coupling, field, &mut rand::thread_rng());
+    }
+
+    /// # Step at a reduced coupling with a generator
+    /// Performs the same sweep as `step_reduced`, drawing its random numbers from the given
+    /// generator. Returns the number of accepted moves.
+    pub fn step_reduced_with_rng<R: Rng>(
+        &mut self,
+        coupling: f64,
+        field: f64,
+        rng: &mut R,
+    ) -> usize {
+        let mut accepted = 0;
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                accepted += usize::from(self.attempt_move(x, y, coupling, field, rng).is_some());
+            }
+        }
+        accepted
+    }
+
+    /// # Step with the energy current
+    /// Performs a single Monte Carlo step like `step_reduced`, and records in `current` the heat that
+    /// each accepted update draws from its bath and the energy current it sends along the
+    /// nearest-neighbour bonds. Together with a thermostat map this measures heat transport
+    /// through the grid. The diagonal bonds of a next-nearest-neighbour coupling are not
+    /// tracked, so with them the bond currents miss part of the flow. Fails unless the energy
+    /// current has the size of the grid.
+    pub fn step_with_energy_current(
+        &mut self,
+        coupling: f64,
+        field: f64,
+        current: &mut EnergyCurrent,
+    ) -> Result<()> {
+        check_same_size(
+            "energy current",
+            (self.width, self.height),
+            (current.width(), current.height()),
+        )?;
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                let bonds_before = self.bond_energies(x, y, coupling);
+                let energy_before = self.total_energy(x, y, coupling, field);
+                if self
+                    .attempt_move(x, y, coupling, field, &mut rand::thread_rng())
+                    .is_some()
+                {
+                    let bonds_after = self.bond_energies(x, y, coupling);
+                    let heat = self.total_energy(x, y, coupling, field) - energy_before;
+                    let changes = [0, 1, 2, 3].map(|k| bonds_after[k] - bonds_before[k]);
+                    current.add_update(x, y, heat, changes);
+                }
+            }
+        }
+        current.add_sweep();
+        Ok(())
+    }
+
+    /// # Step with a flip log
+    /// Performs the same sweep as `step_reduced_with_rng`, with the same random numbers, and records
+    /// every accepted move in `log`. Returns the number of accepted moves, or fails unless the log
+    /// has the size of the grid.
+    pub fn step_with_flip_log<R: Rng>(
+        &mut self,
+        coupling: f64,
+        field: f64,
+        rng: &mut R,
+        log: &mut FlipLog,
+    ) -> Result<usize> {
+        check_same_size(
+            "flip log",
+            (self.width, self.height),
+            (log.width(), log.height()),
+        )?;
+        let mut accepted = 0;
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                if let Some((spin, energy_change)) = self.attempt_move(x, y, coupling, field, rng) {
+                    log.add_flip(x, y, spin, energy_change);
+                    accepted += 1;
+                }
+            }
+        }
+        log.add_sweep();
+        Ok(accepted)
+    }
+}
+
+impl Index<(i64, i64)> for Grid {
+    type Output = Spin;
+
+    /// The spin at (x, y), with periodic boundary conditions as in `get`.
+    fn index(&self, (x, y): (i64, i64)) -> &Spin {
+        &self.spins[self.get_index(x, y)]
+    }
+}
+
+impl IndexMut<(i64, i64)> for Grid {
+    /// The spin at (x, y), with periodic boundary conditions as in `set`.
+    fn index_mut(&mut self, (x, y): (i64, i64)) -> &mut Spin {
+        let index = self.get_index(x, y);
+        &mut self.spins[index]
+    }
+}
+
+impl fmt::Display for Grid {
+    /// Draws the grid as one line of spin symbols per row, from y = 0 down, such as `+-+.` for up,
+    /// down, up and a vacancy. This is the picture that `save` writes.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (y, row) in self.spins.chunks(self.width).enumerate() {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for spin in row {
+                write!(f, "{}", spin)?;
             }
         }
+        Ok(())
+    }
+}
+
+/// Turns an error of the image crate into an error of this crate, passing io errors on as they
+/// are and reporting the rest as invalid data.
+fn image_error(error: ImageError) -> Error {
+    match error {
+        ImageError::IoError(error) => Error::Io(error),
+        error => Error::Io(io::Error::new(io::ErrorKind::InvalidData, error)),
     }
 }
 
@@ -156,13 +1183,19 @@ impl Grid {
 mod tests {
     use std::collections::HashSet;
 
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
     use super::*;
+    use crate::couplings::BondDirection;
+    use crate::error::Error;
+    use crate::temperature::Temperature;
 
     #[test]
     fn test_new_random() {
         let width = 50;
         let height = 50;
-        let grid = Grid::new_random(width, height);
+        let grid = Grid::new_random(width, height).unwrap();
         assert_eq!(grid.spins.len(), width * height);
 
         // Make sure that not all the spins are the same.
@@ -174,7 +1207,7 @@ mod tests {
     fn test_new_constant() {
         let width = 50;
         let height = 50;
-        let grid = Grid::new_constant(width, height, Spin::Up);
+        let grid = Grid::new_constant(width, height, Spin::Up).unwrap();
         assert_eq!(grid.spins.len(), width * height);
 
         // Make sure that all the spins are the same.
@@ -184,13 +1217,251 @@ mod tests {
         // Make sure that all the spins are the same as the one we set.
         let spin_value = **unique_spins.iter().next().unwrap();
         assert_eq!(spin_value, Spin::Up);
+
+        assert!(matches!(
+            Grid::new_constant(0, 0, Spin::Up),
+            Err(Error::EmptyGrid {
+                width: 0,
+                height: 0
+            })
+        ));
+        assert!(Grid::new_random(4, 0).is_err());
+        assert!(Grid::new_stripes(4, 4, 0).is_err());
+    }
+
+    #[test]
+    fn test_from_fn() {
+        let mut calls = Vec::new();
+        let grid = Grid::from_fn(3, 2, |x, y| {
+            calls.push((x, y));
+            if x == 2 && y == 1 {
+                Spin::Zero
+            } else if x == y {
+                Spin::Up
+            } else {
+                Spin::Down
+            }
+        })
+        .unwrap();
+        assert_eq!(calls, [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]);
+        assert_eq!(grid.to_string(), "+--\n-+0");
+        assert_eq!(
+            Grid::new_stripes(4, 2, 1).unwrap().to_string(),
+            Grid::from_fn(4, 2, |x, _| [Spin::Up, Spin::Down][x % 2])
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_iterators() {
+        let grid = Grid::new_stripes(4, 3, 1).unwrap();
+        let sites: Vec<((i64, i64), Spin)> = grid.iter_sites().collect();
+        assert_eq!(sites.len(), 12);
+        assert_eq!(sites[5], ((1, 1), Spin::Down));
+        assert!(grid
+            .iter_rows()
+            .all(|row| row == [Spin::Up, Spin::Down, Spin::Up, Spin::Down]));
+
+        // Every horizontal bond of the stripes is broken and every vertical one satisfied.
+        let bonds: f64 = grid
+            .iter_sites()
+            .map(|((x, y), _)| grid.get_spin_as_float(x, y) * grid.neighbors(x, y).sum::<f64>())
+            .sum();
+        assert_eq!(bonds, 0.0);
+        let mut open = Grid::new_stripes(4, 3, 1).unwrap();
+        open.set_boundary_conditions(BoundaryCondition::Open, BoundaryCondition::Periodic);
+        assert_eq!(
+            open.neighbors(0, 0).collect::<Vec<_>>(),
+            [1.0, 1.0, 0.0, -1.0]
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_iterators() {
+        let grid = Grid::new_random(7, 5).unwrap();
+        let sites: Vec<((i64, i64), Spin)> = grid.par_iter_sites().collect();
+        assert_eq!(sites, grid.iter_sites().collect::<Vec<_>>());
+        let up: usize = grid
+            .par_iter_rows()
+            .map(|row| row.iter().filter(|&&spin| spin == Spin::Up).count())
+            .sum();
+        assert_eq!(
+            up,
+            sites.iter().filter(|(_, spin)| *spin == Spin::Up).count()
+        );
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let grid = Grid::new_checkerboard(4, 2).unwrap();
+        let mut other = Grid::new_checkerboard(4, 2).unwrap();
+        assert_eq!(grid.hamming_distance(&other).unwrap(), 0);
+        assert_eq!(grid.overlap(&other).unwrap(), 1.0);
+
+        other[(1, 0)] = Spin::Up;
+        other[(3, 1)] = Spin::Vacant;
+        assert_eq!(grid.diff(&other).unwrap(), [(1, 0), (3, 1)]);
+        assert_eq!(grid.hamming_distance(&other).unwrap(), 2);
+        assert_eq!(grid.overlap(&other).unwrap(), (6.0 - 1.0) / 8.0);
+
+        let mut flipped = Grid::new_checkerboard(4, 2).unwrap();
+        for ((x, y), spin) in grid.iter_sites() {
+            flipped[(x, y)] = spin.flip();
+        }
+        assert_eq!(grid.overlap(&flipped).unwrap(), -1.0);
+        assert!(matches!(
+            grid.overlap(&Grid::new_checkerboard(2, 4).unwrap()),
+            Err(Error::SizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_index_and_display() {
+        let mut grid = Grid::new_checkerboard(4, 2).unwrap();
+        assert_eq!(grid[(0, 0)], Spin::Up);
+        assert_eq!(grid[(-1, 0)], grid.get(3, 0));
+        grid[(5, -1)] = Spin::Vacant;
+        assert_eq!(grid.get(1, 1), Spin::Vacant);
+        grid[(2, 0)] = grid[(2, 0)].flip();
+        assert_eq!(grid.to_string(), "+---\n-.-+");
+    }
+
+    #[test]
+    fn test_structured_configurations() {
+        let stripes = Grid::new_stripes(8, 3, 2).unwrap();
+        let row: Vec<Spin> = (0..8).map(|x| stripes.get(x, 2)).collect();
+        assert_eq!(row[..4], [Spin::Up, Spin::Up, Spin::Down, Spin::Down]);
+        assert_eq!(row[6..], [Spin::Down, Spin::Down]);
+
+        let checkerboard = Grid::new_checkerboard(4, 4).unwrap();
+        assert_eq!(checkerboard.get(0, 0), Spin::Up);
+        assert_eq!(checkerboard.get(1, 0), Spin::Down);
+        assert_eq!(checkerboard.interaction_energy(2, 1, 1.0), 4.0);
+
+        let droplet = Grid::new_droplet(11, 11, 2.0).unwrap();
+        assert_eq!(droplet.get(5, 5), Spin::Up);
+        assert_eq!(droplet.get(5, 7), Spin::Up);
+        assert_eq!(droplet.get(7, 7), Spin::Down);
+
+        let interface = Grid::new_interface(6, 2).unwrap();
+        assert_eq!(interface.get(2, 1), Spin::Up);
+        assert_eq!(interface.get(3, 1), Spin::Down);
+    }
+
+    #[test]
+    fn test_fixed_magnetization() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let grid = Grid::new_with_magnetization(10, 10, 0.3, &mut rng).unwrap();
+        let up = grid.spins.iter().filter(|&&spin| spin == Spin::Up).count();
+        assert_eq!(up, 65);
+
+        // The up spins are scattered rather than packed into the first rows.
+        let first_rows = (0..10)
+            .flat_map(|x| (0..6).map(move |y| (x, y)))
+            .filter(|&(x, y)| grid.get(x, y) == Spin::Up)
+            .count();
+        assert!(first_rows < 60);
+
+        assert!(matches!(
+            Grid::new_with_magnetization(10, 10, 1.5, &mut rng),
+            Err(Error::InvalidParameter {
+                name: "magnetization",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_from_image() {
+        // A white letter L on a black background.
+        let path = std::env::temp_dir().join("ising_model_test_from_image.png");
+        let image = image::GrayImage::from_fn(5, 4, |x, y| {
+            if x == 1 || (y == 3 && x < 4) {
+                image::Luma([230])
+            } else {
+                image::Luma([20])
+            }
+        });
+        image.save(&path).unwrap();
+        let grid = Grid::from_image(&path, 0.5).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!((grid.width(), grid.height()), (5, 4));
+        assert_eq!(grid.get(1, 0), Spin::Up);
+        assert_eq!(grid.get(3, 3), Spin::Up);
+        assert_eq!(grid.get(0, 0), Spin::Down);
+        assert_eq!(grid.get(4, 3), Spin::Down);
+
+        let missing = Grid::from_image(std::env::temp_dir().join("ising_model_missing.png"), 0.5);
+        assert!(matches!(
+            missing,
+            Err(Error::Io(error)) if error.kind() == io::ErrorKind::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_to_image() {
+        let mut grid = Grid::new_random(6, 4).unwrap();
+        grid.set(5, 3, Spin::Vacant);
+        let palette = Palette::blue_red();
+        let image = grid.to_image(&palette, 3).unwrap();
+        assert_eq!(image.dimensions(), (18, 12));
+        assert_eq!(image.get_pixel(17, 11).0, palette.vacant);
+        assert_eq!(image.get_pixel(4, 7).0, palette.color(grid.get(1, 2)));
+        assert!(grid.to_image(&palette, 0).is_err());
+
+        // The default palette survives a round trip through a PNG file.
+        let path = std::env::temp_dir().join("ising_model_test_to_image.png");
+        let grid = Grid::new_random(9, 7).unwrap();
+        grid.save_png(&path, &Palette::default(), 1).unwrap();
+        let read = Grid::from_image(&path, 0.5).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for y in 0..7 {
+            for x in 0..9 {
+                assert_eq!(read.get(x, y), grid.get(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("ising_model_test_save_and_load.txt");
+        let mut grid = Grid::new_random(7, 5).unwrap();
+        grid.set(3, 4, Spin::Vacant);
+        grid.set(1, 2, Spin::Zero);
+        grid.save(&path).unwrap();
+        let loaded = Grid::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((loaded.width(), loaded.height()), (7, 5));
+        assert_eq!(loaded.spins, grid.spins);
+    }
+
+    #[test]
+    fn test_load_invalid_grid() {
+        let path = std::env::temp_dir().join("ising_model_test_load_invalid_grid.txt");
+        fs::write(&path, "3 2\n+-+\n+x+\n").unwrap();
+        let error = Grid::load(&path).unwrap_err();
+        assert!(matches!(&error, Error::Io(error) if error.kind() == io::ErrorKind::InvalidData));
+        assert!(error.to_string().starts_with("line 3"));
+
+        fs::write(&path, "# Too short\n3 2\n+-+\n").unwrap();
+        let error = Grid::load(&path).unwrap_err();
+        assert!(error.to_string().contains("height"));
+
+        fs::write(&path, "0 0\n").unwrap();
+        let error = Grid::load(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(error, Error::EmptyGrid { .. }));
     }
 
     #[test]
     fn test_get() {
         let width = 50;
         let height = 50;
-        let mut grid = Grid::new_constant(width, height, Spin::Up);
+        let mut grid = Grid::new_constant(width, height, Spin::Up).unwrap();
         grid.set(0, 0, Spin::Down);
 
         // Test the periodic boundary conditions that get is using.
@@ -205,7 +1476,7 @@ mod tests {
     fn test_get_spin_as_float() {
         let width = 50;
         let height = 50;
-        let mut grid = Grid::new_constant(width, height, Spin::Up);
+        let mut grid = Grid::new_constant(width, height, Spin::Up).unwrap();
         grid.set(0, 0, Spin::Down);
 
         // Test the periodic boundary conditions that get_spin_as_float is using.
@@ -220,7 +1491,7 @@ mod tests {
     fn test_get_index() {
         let width = 50;
         let height = 50;
-        let grid = Grid::new_constant(width, height, Spin::Up);
+        let grid = Grid::new_constant(width, height, Spin::Up).unwrap();
 
         // Test the periodic boundary conditions that get_index is using.
         assert_eq!(grid.get_index(0, 0), 0);
@@ -235,7 +1506,7 @@ mod tests {
     fn test_set() {
         let width = 50;
         let height = 50;
-        let mut grid = Grid::new_constant(width, height, Spin::Up);
+        let mut grid = Grid::new_constant(width, height, Spin::Up).unwrap();
         grid.set(65, 14, Spin::Down);
         grid.set(-1, 14, Spin::Down);
 
@@ -243,19 +1514,497 @@ mod tests {
         assert_eq!(grid.get(49, 14), Spin::Down);
     }
 
+    #[test]
+    fn test_coarse_grain_majority() {
+        let mut grid = Grid::new_constant(6, 3, Spin::Up).unwrap();
+        // Two down spins in the first block keep it up, five in the second flip it.
+        grid.set(0, 0, Spin::Down);
+        grid.set(1, 1, Spin::Down);
+        for (x, y) in [(3, 0), (4, 0), (5, 0), (3, 1), (4, 2)] {
+            grid.set(x, y, Spin::Down);
+        }
+
+        let coarse = grid.coarse_grain(3, CoarseGrainRule::Majority).unwrap();
+        assert_eq!(coarse.width(), 2);
+        assert_eq!(coarse.height(), 1);
+        assert_eq!(coarse.get(0, 0), Spin::Up);
+        assert_eq!(coarse.get(1, 0), Spin::Down);
+
+        // A tie follows the first up or down spin of the block.
+        let mut grid = Grid::new_constant(4, 2, Spin::Up).unwrap();
+        grid.set(0, 0, Spin::Down);
+        grid.set(1, 1, Spin::Down);
+        grid.set(2, 0, Spin::Zero);
+        grid.set(3, 0, Spin::Zero);
+        grid.set(2, 1, Spin::Zero);
+        grid.set(3, 1, Spin::Zero);
+        let coarse = grid.coarse_grain(2, CoarseGrainRule::Majority).unwrap();
+        assert_eq!(coarse.get(0, 0), Spin::Down);
+        assert_eq!(coarse.get(1, 0), Spin::Zero);
+    }
+
+    #[test]
+    fn test_coarse_grain_decimation() {
+        let mut grid = Grid::new_constant(9, 8, Spin::Up).unwrap();
+        grid.set(0, 0, Spin::Down);
+        grid.set(4, 4, Spin::Down);
+        grid.set(5, 4, Spin::Down);
+
+        let coarse = grid.coarse_grain(2, CoarseGrainRule::Decimation).unwrap();
+        assert_eq!(coarse.width(), 4);
+        assert_eq!(coarse.height(), 4);
+        assert_eq!(coarse.get(0, 0), Spin::Down);
+        assert_eq!(coarse.get(2, 2), Spin::Down);
+        assert_eq!(coarse.get(1, 1), Spin::Up);
+
+        // A block larger than the grid would leave nothing, and is refused.
+        for block_size in [0, 9] {
+            assert!(grid
+                .coarse_grain(block_size, CoarseGrainRule::Decimation)
+                .is_err());
+        }
+    }
+
     #[test]
     fn test_field_energy() {
         let width = 50;
         let height = 50;
-        let grid = Grid::new_constant(width, height, Spin::Up);
-        assert_eq!(grid.field_energy(0, 0, 1.0), 4.0);
+        let grid = Grid::new_constant(width, height, Spin::Up).unwrap();
+        assert_eq!(grid.field_energy(0, 0, 1.0), -1.0);
+    }
+
+    #[test]
+    fn test_field_map_energy() {
+        let mut grid = Grid::new_constant(4, 4, Spin::Up).unwrap();
+        grid.set_field_map(Some(FieldMap::from_fn(4, 4, |x, _| x as f64)))
+            .unwrap();
+        assert_eq!(grid.field_energy(0, 0, 0.5), -0.5);
+        assert_eq!(grid.field_energy(3, 2, 0.5), -3.5);
+    }
+
+    #[test]
+    fn test_field_step_orders_each_half() {
+        // A field that points up on the left half of the grid and down on the right half.
+        let mut grid = Grid::new_random(8, 8).unwrap();
+        grid.set_field_map(Some(FieldMap::from_fn(8, 8, |x, _| {
+            if x < 4 {
+                2.0
+            } else {
+                -2.0
+            }
+        })))
+        .unwrap();
+        for _ in 0..100 {
+            grid.step(Temperature::new(10.0).unwrap(), 0.0);
+        }
+
+        let half_sum = |columns: std::ops::Range<i64>| -> f64 {
+            columns
+                .flat_map(|x| (0..8).map(move |y| (x, y)))
+                .map(|(x, y)| grid.get_spin_as_float(x, y))
+                .sum()
+        };
+        assert!(half_sum(0..4) > 20.0);
+        assert!(half_sum(4..8) < -20.0);
+    }
+
+    #[test]
+    fn test_totals() {
+        let mut grid = Grid::new_constant(4, 3, Spin::Up).unwrap();
+        assert_eq!(grid.magnetization(), 12.0);
+        assert_eq!(
+            grid.energy(Temperature::new(2.0).unwrap(), 0.5),
+            -(0.5 * 24.0 + 0.25 * 12.0)
+        );
+
+        // The frozen layers above and below add a bond for each of the four columns.
+        grid.set_boundary_conditions(
+            BoundaryCondition::Periodic,
+            BoundaryCondition::Fixed(Spin::Up),
+        );
+        assert_eq!(grid.energy(Temperature::new(1.0).unwrap(), 0.0), -28.0);
+
+        // Flipping a spin changes the energy by twice its local energy, whatever the bonds.
+        let mut grid = Grid::new_random(5, 4).unwrap();
+        grid.set(1, 2, Spin::Vacant);
+        grid.set_boundary_conditions(
+            BoundaryCondition::Antiperiodic,
+            BoundaryCondition::Fixed(Spin::Down),
+        );
+        grid.set_next_nearest_ratio(-0.3);
+        grid.set_bond_couplings(Some(BondCouplings::from_fn(5, 4, |x, y, _| {
+            1.0 + 0.1 * (x + 2 * y) as f64
+        })))
+        .unwrap();
+        grid.set_field_map(Some(FieldMap::from_fn(5, 4, |x, y| 0.05 * (x * y) as f64)))
+            .unwrap();
+        for (x, y) in [(0, 0), (4, 3), (2, 1), (0, 3)] {
+            let before = grid.energy_reduced(0.7, 0.2);
+            let local = grid.total_energy(x, y, 0.7, 0.2);
+            grid[(x, y)] = grid[(x, y)].flip();
+            assert!((grid.energy_reduced(0.7, 0.2) - before + 2.0 * local).abs() < 1e-12);
+        }
     }
 
     #[test]
     fn test_interaction_energy() {
         let width = 50;
         let height = 50;
-        let grid = Grid::new_constant(width, height, Spin::Up);
+        let grid = Grid::new_constant(width, height, Spin::Up).unwrap();
+        assert_eq!(grid.interaction_energy(0, 0, 1.0), -4.0);
+    }
+
+    #[test]
+    fn test_boundary_conditions() {
+        let mut grid = Grid::new_constant(4, 3, Spin::Up).unwrap();
+
+        grid.set_boundary_conditions(BoundaryCondition::Open, BoundaryCondition::Periodic);
+        assert_eq!(grid.interaction_energy(0, 1, 1.0), -3.0);
+        assert_eq!(grid.interaction_energy(1, 0, 1.0), -4.0);
+
+        grid.set_boundary_conditions(BoundaryCondition::Open, BoundaryCondition::Open);
+        assert_eq!(grid.interaction_energy(0, 0, 1.0), -2.0);
+        assert_eq!(grid.interaction_energy(3, 2, 1.0), -2.0);
+        assert_eq!(grid.interaction_energy(1, 1, 1.0), -4.0);
+
+        // Across an antiperiodic edge the aligned spins see each other as opposite.
+        grid.set_boundary_conditions(BoundaryCondition::Antiperiodic, BoundaryCondition::Periodic);
+        assert_eq!(grid.interaction_energy(3, 1, 1.0), -2.0);
+        assert_eq!(grid.get_neighbor_as_float(3, 1, 1, 0), -1.0);
+        assert_eq!(grid.get_neighbor_as_float(3, 1, -1, 0), 1.0);
+
+        grid.set_boundary_conditions(
+            BoundaryCondition::Periodic,
+            BoundaryCondition::Fixed(Spin::Down),
+        );
+        assert_eq!(grid.interaction_energy(2, 0, 1.0), -2.0);
+        assert_eq!(grid.get_neighbor_as_float(2, 2, 0, 1), -1.0);
+    }
+
+    #[test]
+    fn test_helical_neighbors_follow_the_flat_index() {
+        let (width, height) = (5, 4);
+        let mut grid = Grid::new_random(width, height).unwrap();
+        grid.set_boundary_conditions(BoundaryCondition::Helical, BoundaryCondition::Periodic);
+
+        let number_of_sites = (width * height) as i64;
+        let spin_at = |index: i64| {
+            let index = index.rem_euclid(number_of_sites);
+            grid.get_spin_as_float(index % width as i64, index / width as i64)
+        };
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let index = y * width as i64 + x;
+                assert_eq!(grid.get_neighbor_as_float(x, y, 1, 0), spin_at(index + 1));
+                assert_eq!(grid.get_neighbor_as_float(x, y, -1, 0), spin_at(index - 1));
+                assert_eq!(
+                    grid.get_neighbor_as_float(x, y, 0, 1),
+                    spin_at(index + width as i64)
+                );
+                assert_eq!(
+                    grid.get_neighbor_as_float(x, y, 0, -1),
+                    spin_at(index - width as i64)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_helical_matches_periodic_observables() {
+        let coupling = 0.3;
+        let mean_energy = |boundary: BoundaryCondition| {
+            let mut rng = StdRng::seed_from_u64(4);
+            let mut grid = Grid::new_with_magnetization(16, 16, 0.0, &mut rng).unwrap();
+            grid.set_boundary_conditions(boundary, BoundaryCondition::Periodic);
+            for _ in 0..200 {
+                grid.step_reduced_with_rng(coupling, 0.0, &mut rng);
+            }
+
+            let number_of_sweeps = 2000;
+            let mut energy = 0.0;
+            for _ in 0..number_of_sweeps {
+                grid.step_reduced_with_rng(coupling, 0.0, &mut rng);
+                for y in 0..16 {
+                    for x in 0..16 {
+                        energy += 0.5 * grid.interaction_energy(x, y, coupling);
+                    }
+                }
+            }
+            energy / (256 * number_of_sweeps) as f64
+        };
+
+        // Away from criticality the seam of the helix only shifts observables by O(1/L²).
+        let periodic = mean_energy(BoundaryCondition::Periodic);
+        let helical = mean_energy(BoundaryCondition::Helical);
+        assert!((periodic - helical).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_antiperiodic_boundary_favours_a_domain_wall() {
+        let mut grid = Grid::new_constant(6, 6, Spin::Up).unwrap();
+        grid.set_boundary_conditions(BoundaryCondition::Antiperiodic, BoundaryCondition::Periodic);
+        let energy = |grid: &Grid| -> f64 {
+            (0..6)
+                .flat_map(|y| (0..6).map(move |x| (x, y)))
+                .map(|(x, y)| grid.interaction_energy(x, y, 1.0))
+                .sum()
+        };
+        let uniform = energy(&grid);
+
+        // Moving the frustrated bonds from the edge into the bulk costs nothing, and a half-flipped
+        // grid has exactly one wall either way.
+        for y in 0..6 {
+            for x in 3..6 {
+                grid.set(x, y, Spin::Down);
+            }
+        }
+        assert_eq!(energy(&grid), uniform);
+    }
+
+    #[test]
+    fn test_pinned_spins() {
+        let mut grid = Grid::new_constant(6, 6, Spin::Up).unwrap();
+        grid.set(2, 2, Spin::Down);
+        grid.pin(2, 2);
+        assert!(grid.is_pinned(8, 2));
+        assert_eq!(grid.number_of_pinned(), 1);
+
+        // A strong field would flip the pinned spin at once, but it holds.
+        for _ in 0..10 {
+            grid.step(Temperature::new(10.0).unwrap(), 50.0);
+        }
+        assert_eq!(grid.get(2, 2), Spin::Down);
+        assert_eq!(grid.get(3, 2), Spin::Up);
+
+        grid.unpin(2, 2);
+        for _ in 0..10 {
+            grid.step(Temperature::new(10.0).unwrap(), 50.0);
+        }
+        assert_eq!(grid.get(2, 2), Spin::Up);
+    }
+
+    #[test]
+    fn test_pinned_column_orders_its_neighbours() {
+        // A pinned column of down spins acts on the free spins next to it like a local field.
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut grid = Grid::new_with_magnetization(4, 8, 0.0, &mut rng).unwrap();
+        grid.set_boundary_conditions(BoundaryCondition::Open, BoundaryCondition::Periodic);
+        for y in 0..8 {
+            grid.set(0, y, Spin::Down);
+            grid.pin(0, y);
+        }
+        for _ in 0..500 {
+            grid.step_with_rng(Temperature::new(1.25).unwrap(), 0.0, &mut rng);
+        }
+        let down = (0..8)
+            .flat_map(|y| (1..4).map(move |x| (x, y)))
+            .filter(|&(x, y)| grid.get(x, y) == Spin::Down)
+            .count();
+        assert!(down >= 20);
+    }
+
+    #[test]
+    fn test_masked_disk() {
+        let mask = SiteMask::disk(12, 12, (5.5, 5.5), 4.0);
+        let mut grid = Grid::new_random(12, 12).unwrap();
+        grid.apply_mask(&mask).unwrap();
+        assert_eq!(grid.number_of_vacancies(), 144 - mask.number_of_active());
+        assert!(matches!(
+            Grid::new_random(12, 10).unwrap().apply_mask(&mask),
+            Err(Error::SizeMismatch {
+                name: "mask",
+                expected: (12, 10),
+                found: (12, 12)
+            })
+        ));
+
+        // The disk never touches the edges, so its sites only see each other.
+        for _ in 0..200 {
+            grid.step(Temperature::new(0.5).unwrap(), 0.5);
+        }
+        for y in 0..12 {
+            for x in 0..12 {
+                let expected = if mask.is_active(x, y) {
+                    Spin::Up
+                } else {
+                    Spin::Vacant
+                };
+                assert_eq!(grid.get(x, y), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_two_temperatures() {
+        // The left half is held far below the critical temperature and the right half far above.
+        let mut grid = Grid::new_constant(32, 16, Spin::Up).unwrap();
+        grid.set_thermostat_map(Some(ThermostatMap::halves(
+            32,
+            16,
+            Temperature::new(0.5).unwrap(),
+            Temperature::new(4.0).unwrap(),
+        )))
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(9);
+        let (mut left, mut right) = (0.0, 0.0);
+        for sweep in 0..400 {
+            grid.step_with_rng(Temperature::new(2.0).unwrap(), 0.0, &mut rng);
+            if sweep >= 200 {
+                for y in 0..16 {
+                    for x in 0..16 {
+                        left += grid.get_spin_as_float(x, y);
+                        right += grid.get_spin_as_float(x + 16, y);
+                    }
+                }
+            }
+        }
+        assert!(left.abs() / (200.0 * 256.0) > 0.8);
+        assert!(right.abs() / (200.0 * 256.0) < 0.4);
+    }
+
+    #[test]
+    fn test_energy_current() {
+        // The heat drawn from the baths is the change of the total bond energy, and in the steady
+        // state heat flows from the hot half through both interfaces into the cold half.
+        let coupling = 0.5;
+        let lattice_energy = |grid: &Grid| -> f64 {
+            let mut total = 0.0;
+            for y in 0..16 {
+                for x in 0..32 {
+                    total += grid.total_energy(x, y, coupling, 0.0);
+                }
+            }
+            0.5 * total
+        };
+        let mut grid = Grid::new_constant(32, 16, Spin::Up).unwrap();
+        grid.set_thermostat_map(Some(ThermostatMap::halves(
+            32,
+            16,
+            Temperature::new(2.0).unwrap(),
+            Temperature::new(0.5).unwrap(),
+        )))
+        .unwrap();
+        let mut current = EnergyCurrent::new(32, 16);
+        let initial_energy = lattice_energy(&grid);
+        for _ in 0..200 {
+            grid.step_with_energy_current(coupling, 0.0, &mut current)
+                .unwrap();
+        }
+        let heat = current.total_heat() * current.sweeps() as f64;
+        assert!((heat - (lattice_energy(&grid) - initial_energy)).abs() < 1e-9);
+
+        let mut current = EnergyCurrent::new(32, 16);
+        for _ in 0..1000 {
+            grid.step_with_energy_current(coupling, 0.0, &mut current)
+                .unwrap();
+        }
+        assert!(current.current_across_column(15) > 0.0);
+        assert!(current.current_across_column(31) < 0.0);
+        let hot_heat: f64 = (0..16)
+            .flat_map(|y| (0..16).map(move |x| (x, y)))
+            .map(|(x, y)| current.heat_absorbed(x, y))
+            .sum();
+        assert!(hot_heat > 0.0);
+    }
+
+    #[test]
+    fn test_crystal_field_energy() {
+        let mut grid = Grid::new_constant(4, 4, Spin::Up).unwrap();
+        grid.set_crystal_field_ratio(Some(1.5));
+        assert_eq!(grid.total_energy(1, 1, 1.0, 0.0), -2.5);
+        grid.set(1, 1, Spin::Zero);
+        assert_eq!(grid.total_energy(1, 1, 1.0, 0.0), 0.0);
+        assert_eq!(grid.quadrupole_moment(), 15.0 / 16.0);
+    }
+
+    #[test]
+    fn test_crystal_field_scan() {
+        // A strong crystal field fills the grid with zero spins.
+        let temperature = Temperature::new(0.8).unwrap();
+        let mut moments = Vec::new();
+        for ratio in [0.0, 3.0] {
+            let mut grid = Grid::new_random(16, 16).unwrap();
+            grid.set_crystal_field_ratio(Some(ratio));
+            for _ in 0..300 {
+                grid.step(temperature, 0.0);
+            }
+            moments.push(grid.quadrupole_moment());
+        }
+        assert!(moments[0] > 0.85);
+        assert!(moments[1] < 0.1);
+    }
+
+    #[test]
+    fn test_dilution() {
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut grid = Grid::new_constant(100, 100, Spin::Up).unwrap();
+        grid.dilute(0.2, &mut rng).unwrap();
+        assert!(grid.dilute(1.5, &mut rng).is_err());
+        let vacancies = grid.number_of_vacancies();
+        assert!((vacancies as f64 / 10_000.0 - 0.2).abs() < 0.02);
+
+        // Vacancies stay vacant and contribute nothing to the energy of their neighbours.
+        for _ in 0..5 {
+            grid.step(Temperature::new(2.0).unwrap(), 0.2);
+        }
+        assert_eq!(grid.number_of_vacancies(), vacancies);
+
+        let mut pair = Grid::new_constant(3, 3, Spin::Up).unwrap();
+        pair.set(1, 0, Spin::Vacant);
+        assert_eq!(pair.interaction_energy(0, 0, 1.0), -3.0);
+        assert_eq!(pair.total_energy(1, 0, 1.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_next_nearest_interaction_energy() {
+        let mut grid = Grid::new_constant(4, 4, Spin::Up).unwrap();
+        grid.set_next_nearest_ratio(-1.0);
+        assert_eq!(grid.interaction_energy(0, 0, 1.0), 0.0);
+
+        // In a state of alternating columns every diagonal bond is satisfied, and the horizontal
+        // bonds cancel the vertical ones.
+        for y in 0..4 {
+            grid.set(1, y, Spin::Down);
+            grid.set(3, y, Spin::Down);
+        }
         assert_eq!(grid.interaction_energy(0, 0, 1.0), -4.0);
+        assert_eq!(grid.interaction_energy(1, 2, 1.0), -4.0);
+    }
+
+    #[test]
+    fn test_bond_coupling_energy() {
+        let mut grid = Grid::new_constant(4, 4, Spin::Up).unwrap();
+        let mut couplings = BondCouplings::uniform(4, 4, 1.0);
+        couplings.set(0, 0, BondDirection::Horizontal, -2.0);
+        couplings.set(0, 3, BondDirection::Vertical, 0.5);
+        grid.set_bond_couplings(Some(couplings)).unwrap();
+
+        // The site (0, 0) has its right bond reversed and its lower bond, which wraps around to
+        // (0, 3), halved.
+        assert_eq!(grid.interaction_energy(0, 0, 1.0), -0.5);
+        assert_eq!(grid.interaction_energy(1, 0, 1.0), -1.0);
+        assert_eq!(grid.interaction_energy(2, 2, 1.0), -4.0);
+    }
+
+    #[test]
+    fn test_stripes_are_stable() {
+        let mut grid = Grid::new_constant(8, 8, Spin::Up).unwrap();
+        grid.set_next_nearest_ratio(-1.0);
+        for y in 0..8 {
+            for x in (1..8).step_by(2) {
+                grid.set(x, y, Spin::Down);
+            }
+        }
+
+        // Deep in the stripe phase no flip is ever accepted, since every one costs energy.
+        for _ in 0..10 {
+            grid.step(Temperature::new(0.2).unwrap(), 0.0);
+        }
+        for y in 0..8 {
+            for x in 0..8 {
+                let expected = if x % 2 == 0 { Spin::Up } else { Spin::Down };
+                assert_eq!(grid.get(x, y), expected);
+            }
+        }
     }
 }
@@ -0,0 +1,214 @@
+use std::collections::VecDeque;
+
+use eframe::egui::{self, ColorImage, Slider, TextureHandle, TextureOptions};
+use egui_plot::{Legend, Line, Plot, PlotPoints};
+
+use crate::error::{Error, Result};
+use crate::render::Palette;
+use crate::simulation::{Simulation, SimulationParameters};
+
+/// The number of sweeps kept for the plots.
+const HISTORY_LENGTH: usize = 1000;
+
+/// # Viewer
+/// A native window on top of a simulation, built with egui. It shows the lattice as a texture that
+/// is updated every frame, rolling plots of the energy and magnetization per site, and sliders for
+/// the temperature T, the field h and the coupling J, which set the reduced parameters K = J/T and
+/// H = h/T of the simulation.
+pub struct Viewer {
+    simulation: Simulation,
+    temperature: f64,
+    field: f64,
+    coupling: f64,
+    sweeps_per_frame: usize,
+    running: bool,
+    acceptance: f64,
+    history: VecDeque<[f64; 3]>,
+    texture: Option<TextureHandle>,
+}
+
+impl Viewer {
+    /// # New viewer
+    /// Starts from the state and parameters of the given simulation, which needs a positive and
+    /// finite coupling, reading them as J = 1. Fails for any other coupling.
+    pub fn new(simulation: Simulation) -> Result<Self> {
+        let parameters = simulation.parameters();
+        if !(parameters.coupling > 0.0 && parameters.coupling.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "coupling",
+                value: parameters.coupling,
+            });
+        }
+        Ok(Self {
+            temperature: 1.0 / parameters.coupling,
+            field: parameters.field / parameters.coupling,
+            coupling: 1.0,
+            simulation,
+            sweeps_per_frame: 1,
+            running: true,
+            acceptance: 0.0,
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            texture: None,
+        })
+    }
+
+    /// # Simulation
+    /// The simulation being shown.
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    fn apply_parameters(&mut self) {
+        self.simulation.set_parameters(SimulationParameters {
+            coupling: self.coupling / self.temperature,
+            field: self.field / self.temperature,
+        });
+    }
+
+    fn sweep(&mut self) {
+        self.acceptance = self.simulation.step();
+        let grid = self.simulation.grid();
+        let occupied = grid.number_of_occupied_sites() as f64;
+        let magnetization = grid.magnetization() / occupied;
+        let energy = grid.energy_reduced(self.coupling, self.field) / occupied;
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back([self.simulation.sweep() as f64, energy, magnetization]);
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Parameters");
+        let mut changed = false;
+        changed |= ui
+            .add(Slider::new(&mut self.temperature, 0.1..=5.0).text("temperature T"))
+            .changed();
+        changed |= ui
+            .add(Slider::new(&mut self.field, -1.0..=1.0).text("field h"))
+            .changed();
+        changed |= ui
+            .add(Slider::new(&mut self.coupling, -2.0..=2.0).text("coupling J"))
+            .changed();
+        if changed {
+            self.apply_parameters();
+        }
+        ui.add(Slider::new(&mut self.sweeps_per_frame, 1..=50).text("sweeps per frame"));
+        ui.horizontal(|ui| {
+            if ui
+                .button(if self.running { "Pause" } else { "Run" })
+                .clicked()
+            {
+                self.running = !self.running;
+            }
+            if ui.button("Step").clicked() {
+                self.sweep();
+            }
+        });
+
+        ui.separator();
+        ui.label(format!("Sweep: {}", self.simulation.sweep()));
+        ui.label(format!("Acceptance rate: {:.3}", self.acceptance));
+        let points = |column: usize| -> PlotPoints {
+            self.history
+                .iter()
+                .map(|point| [point[0], point[column]])
+                .collect()
+        };
+        Plot::new("observables")
+            .legend(Legend::default())
+            .height(240.0)
+            .include_y(-1.0)
+            .include_y(1.0)
+            .show(ui, |plot_ui| {
+                plot_ui.line(Line::new(points(1)).name("E/N"));
+                plot_ui.line(Line::new(points(2)).name("M/N"));
+            });
+    }
+}
+
+impl eframe::App for Viewer {
+    fn update(&mut self, context: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.running {
+            for _ in 0..self.sweeps_per_frame {
+                self.sweep();
+            }
+            context.request_repaint();
+        }
+
+        let grid = self.simulation.grid();
+        let size = [grid.width(), grid.height()];
+        let image = grid
+            .to_image(&Palette::default(), 1)
+            .expect("one pixel per spin is a valid scale");
+        let image = ColorImage::from_rgb(size, image.as_raw());
+        let texture = match &mut self.texture {
+            Some(texture) => {
+                texture.set(image, TextureOptions::NEAREST);
+                texture.clone()
+            }
+            None => self
+                .texture
+                .insert(context.load_texture("lattice", image, TextureOptions::NEAREST))
+                .clone(),
+        };
+
+        egui::SidePanel::right("controls")
+            .min_width(320.0)
+            .show(context, |ui| self.controls(ui));
+        egui::CentralPanel::default().show(context, |ui| {
+            let available = ui.available_size();
+            let scale = (available.x / size[0] as f32).min(available.y / size[1] as f32);
+            let extent = egui::vec2(size[0] as f32 * scale, size[1] as f32 * scale);
+            ui.image(egui::load::SizedTexture::new(texture.id(), extent));
+        });
+    }
+}
+
+/// # Run the viewer
+/// Opens the viewer window for the simulation and returns when it is closed. Fails like
+/// `Viewer::new`, or if the window cannot be opened.
+pub fn run(simulation: Simulation) -> eframe::Result {
+    let viewer =
+        Viewer::new(simulation).map_err(|error| eframe::Error::AppCreation(Box::new(error)))?;
+    eframe::run_native(
+        "Ising model",
+        eframe::NativeOptions::default(),
+        Box::new(|_| Ok(Box::new(viewer))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::Grid;
+
+    #[test]
+    fn test_viewer_parameters() {
+        let parameters = SimulationParameters {
+            coupling: 0.5,
+            field: 0.1,
+        };
+        let simulation =
+            Simulation::with_seed(Grid::new_random(8, 8).unwrap(), parameters, 5).unwrap();
+        let mut viewer = Viewer::new(simulation).unwrap();
+        assert_eq!((viewer.temperature, viewer.field), (2.0, 0.2));
+
+        viewer.coupling = -1.0;
+        viewer.apply_parameters();
+        assert_eq!(viewer.simulation().parameters().coupling, -0.5);
+        assert_eq!(viewer.simulation().parameters().field, 0.1);
+
+        viewer.sweep();
+        assert_eq!(viewer.history.len(), 1);
+        assert_eq!(viewer.history[0][0], 1.0);
+
+        let antiferromagnet = SimulationParameters {
+            coupling: -0.5,
+            field: 0.0,
+        };
+        let simulation =
+            Simulation::with_seed(Grid::new_random(8, 8).unwrap(), antiferromagnet, 5).unwrap();
+        assert!(Viewer::new(simulation).is_err());
+    }
+}
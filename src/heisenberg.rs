@@ -0,0 +1,368 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::error::{check_size, Error, Result};
+use crate::helicity::TwistResponse;
+use crate::lattice::{Hypercubic, Lattice};
+use crate::temperature::Temperature;
+
+/// # Heisenberg model
+/// Classical O(3) spins, unit vectors s = (s_x, s_y, s_z), on a periodic width × height grid.
+/// Neighbours interact through their dot product and the field points along z,
+/// E = -K Σ_⟨ij⟩ s_i · s_j - H Σ_i s_z,i, in the same reduced units as `Grid::step`. In two
+/// dimensions the model is disordered at every finite temperature, with a correlation length that
+/// grows exponentially with K.
+#[derive(Debug, Clone)]
+pub struct HeisenbergModel {
+    spins: Vec<[f64; 3]>,
+    lattice: Hypercubic<2>,
+    proposal_width: f64,
+}
+
+impl HeisenbergModel {
+    /// # New random Heisenberg model
+    /// Creates a grid where every spin points in a uniformly random direction.
+    pub fn new_random<R: Rng>(width: usize, height: usize, rng: &mut R) -> Result<Self> {
+        let mut model = Self::new_constant(width, height, [0.0, 0.0, 1.0])?;
+        for spin in model.spins.iter_mut() {
+            *spin = random_unit_vector(rng);
+        }
+        Ok(model)
+    }
+
+    /// # New constant Heisenberg model
+    /// Creates a grid where every spin points in the given direction, which is normalized. Fails
+    /// if the grid has no sites or the direction has no finite, nonzero length.
+    pub fn new_constant(width: usize, height: usize, spin: [f64; 3]) -> Result<Self> {
+        check_size(width, height)?;
+        let length = dot(spin, spin).sqrt();
+        if !(length > 0.0 && length.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "spin length",
+                value: length,
+            });
+        }
+        Ok(Self {
+            spins: vec![normalize(spin); width * height],
+            lattice: Hypercubic::new([width, height])?,
+            proposal_width: 1.0,
+        })
+    }
+
+    /// # Width
+    /// Returns the number of columns in the grid.
+    pub fn width(&self) -> usize {
+        self.lattice.shape()[0]
+    }
+
+    /// # Height
+    /// Returns the number of rows in the grid.
+    pub fn height(&self) -> usize {
+        self.lattice.shape()[1]
+    }
+
+    /// # Proposal width
+    /// The size Δ of a Metropolis proposal, which moves a spin to the direction of s + Δu for a
+    /// random unit vector u. It defaults to one, and large widths propose nearly any direction.
+    pub fn proposal_width(&self) -> f64 {
+        self.proposal_width
+    }
+
+    /// # Set the proposal width
+    /// Narrower proposals are accepted more often at strong coupling.
+    pub fn set_proposal_width(&mut self, proposal_width: f64) {
+        assert!(proposal_width > 0.0, "the proposal width must be positive");
+        self.proposal_width = proposal_width;
+    }
+
+    /// # Get a spin
+    /// Retrieves the spin at the given coordinates, with periodic boundary conditions.
+    pub fn get(&self, x: i64, y: i64) -> [f64; 3] {
+        self.spins[self.lattice.site([x, y])]
+    }
+
+    /// # Set a spin
+    /// Sets the spin at the given coordinates to the given direction, which is normalized.
+    pub fn set(&mut self, x: i64, y: i64, spin: [f64; 3]) {
+        let index = self.lattice.site([x, y]);
+        self.spins[index] = normalize(spin);
+    }
+
+    /// # Magnetization
+    /// The magnetization vector per site.
+    pub fn magnetization(&self) -> [f64; 3] {
+        let mut magnetization = [0.0; 3];
+        for spin in &self.spins {
+            for (total, component) in magnetization.iter_mut().zip(spin) {
+                *total += component;
+            }
+        }
+        magnetization.map(|total| total / self.spins.len() as f64)
+    }
+
+    /// # Absolute magnetization
+    /// The length of the magnetization vector per site.
+    pub fn absolute_magnetization(&self) -> f64 {
+        dot(self.magnetization(), self.magnetization()).sqrt()
+    }
+
+    /// # Energy
+    /// The energy per site at temperature T and field h in units of the coupling, in the reduced
+    /// units βE of the Boltzmann weight, with every bond counted once.
+    pub fn energy(&self, temperature: Temperature, field: f64) -> f64 {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        let mut energy = 0.0;
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                let spin = self.get(x, y);
+                energy -= coupling
+                    * (dot(spin, self.get(x + 1, y)) + dot(spin, self.get(x, y + 1)))
+                    + field * spin[2];
+            }
+        }
+        energy / self.spins.len() as f64
+    }
+
+    /// # Twist response
+    /// The bond sums Σ (s_x,i s_x,j + s_y,i s_y,j) and spin currents Σ (s_x,i s_y,j - s_y,i s_x,j)
+    /// over the bonds along each axis, for a twist about the z axis. `HelicityModulus` turns them
+    /// into the spin stiffness.
+    pub fn twist_response(&self) -> TwistResponse {
+        let mut bond_sums = [0.0; 2];
+        let mut spin_currents = [0.0; 2];
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                let spin = self.get(x, y);
+                for (axis, (dx, dy)) in [(1, 0), (0, 1)].into_iter().enumerate() {
+                    let neighbor = self.get(x + dx, y + dy);
+                    bond_sums[axis] += spin[0] * neighbor[0] + spin[1] * neighbor[1];
+                    spin_currents[axis] += spin[0] * neighbor[1] - spin[1] * neighbor[0];
+                }
+            }
+        }
+        TwistResponse {
+            number_of_sites: self.spins.len(),
+            bond_sums,
+            spin_currents,
+        }
+    }
+
+    /// # Local field
+    /// The effective field h = K Σ_j s_j + H ẑ acting on the spin at the given coordinates.
+    fn local_field(&self, x: i64, y: i64, coupling: f64, field: f64) -> [f64; 3] {
+        let mut local_field = [0.0, 0.0, field];
+        for &neighbor in self.lattice.neighbors(self.lattice.site([x, y])) {
+            let neighbor = self.spins[neighbor];
+            for (total, component) in local_field.iter_mut().zip(neighbor) {
+                *total += coupling * component;
+            }
+        }
+        local_field
+    }
+
+    /// # Single site step
+    /// Proposes to move a single spin to the direction of s + Δu, for a uniformly random unit
+    /// vector u, and accepts it with the Metropolis probability. Returns whether the move was
+    /// accepted.
+    pub fn single_site_step<R: Rng>(
+        &mut self,
+        x: i64,
+        y: i64,
+        temperature: Temperature,
+        field: f64,
+        rng: &mut R,
+    ) -> bool {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        let old_spin = self.get(x, y);
+        let step = random_unit_vector(rng);
+        let new_spin = normalize([0, 1, 2].map(|i| old_spin[i] + self.proposal_width * step[i]));
+        let local_field = self.local_field(x, y, coupling, field);
+        let energy_change = -dot(local_field, new_spin) + dot(local_field, old_spin);
+
+        // Accept the move with probability min(1, exp(-ΔE)).
+        let accepted = energy_change <= 0.0 || rng.gen::<f64>() < (-energy_change).exp();
+        if accepted {
+            self.set(x, y, new_spin);
+        }
+        accepted
+    }
+
+    /// # Step
+    /// Performs a single Metropolis sweep over all the sites at temperature T and field h in units
+    /// of the coupling, and returns the fraction of the proposals that were accepted.
+    pub fn step<R: Rng>(&mut self, temperature: Temperature, field: f64, rng: &mut R) -> f64 {
+        let mut accepted = 0;
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                accepted += usize::from(self.single_site_step(x, y, temperature, field, rng));
+            }
+        }
+        accepted as f64 / self.spins.len() as f64
+    }
+
+    /// # Over-relaxation step
+    /// Rotates a single spin by π about its local field, s → 2 (s·h) h / |h|² - s, which leaves
+    /// the energy unchanged. The field h is in units of the coupling, and only the direction of the
+    /// local field matters, so the temperature does not enter. A spin without a local field is
+    /// left alone.
+    pub fn over_relaxation_step(&mut self, x: i64, y: i64, field: f64) {
+        let local_field = self.local_field(x, y, 1.0, field);
+        let strength = dot(local_field, local_field);
+        if strength == 0.0 {
+            return;
+        }
+        let spin = self.get(x, y);
+        let projection = 2.0 * dot(spin, local_field) / strength;
+        self.set(
+            x,
+            y,
+            [0, 1, 2].map(|i| projection * local_field[i] - spin[i]),
+        );
+    }
+
+    /// # Over-relaxation sweep
+    /// Performs an over-relaxation step at every site. The sweeps keep the energy fixed, so they
+    /// must be interleaved with ergodic updates such as `step`.
+    pub fn over_relaxation_sweep(&mut self, field: f64) {
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                self.over_relaxation_step(x, y, field);
+            }
+        }
+    }
+
+    /// # Hybrid step
+    /// Performs the given number of over-relaxation sweeps followed by one Metropolis sweep, and
+    /// returns the acceptance of the Metropolis sweep.
+    pub fn hybrid_step<R: Rng>(
+        &mut self,
+        temperature: Temperature,
+        field: f64,
+        number_of_over_relaxation_sweeps: usize,
+        rng: &mut R,
+    ) -> f64 {
+        for _ in 0..number_of_over_relaxation_sweeps {
+            self.over_relaxation_sweep(field);
+        }
+        self.step(temperature, field, rng)
+    }
+
+    /// # Wolff step
+    /// Grows and reflects a single cluster with Wolff's embedding, and returns its size. A random
+    /// unit vector r defines a mirror plane, and the projections s_i · r behave as Ising spins:
+    /// bonds join the cluster with probability 1 - exp(-2K (s_i·r)(s_j·r)) when the projections
+    /// have the same sign, and the cluster is reflected, s → s - 2 (s·r) r. The field is not
+    /// supported, since it would break the reflection symmetry.
+    pub fn wolff_step<R: Rng>(&mut self, temperature: Temperature, rng: &mut R) -> usize {
+        let coupling = temperature.beta();
+        let mirror = random_unit_vector(rng);
+        let reflect = |spin: [f64; 3]| {
+            let projection = 2.0 * dot(spin, mirror);
+            [0, 1, 2].map(|i| spin[i] - projection * mirror[i])
+        };
+
+        let seed = rng.gen_range(0..self.spins.len());
+        let mut in_cluster = vec![false; self.spins.len()];
+        in_cluster[seed] = true;
+        let mut stack = vec![(seed, dot(self.spins[seed], mirror))];
+        self.spins[seed] = reflect(self.spins[seed]);
+        let mut size = 1;
+
+        while let Some((site, site_projection)) = stack.pop() {
+            for &neighbor in self.lattice.neighbors(site) {
+                if in_cluster[neighbor] {
+                    continue;
+                }
+                let neighbor_projection = dot(self.spins[neighbor], mirror);
+                let product = site_projection * neighbor_projection;
+                if product > 0.0 && rng.gen::<f64>() < 1.0 - (-2.0 * coupling * product).exp() {
+                    in_cluster[neighbor] = true;
+                    self.spins[neighbor] = reflect(self.spins[neighbor]);
+                    stack.push((neighbor, neighbor_projection));
+                    size += 1;
+                }
+            }
+        }
+        size
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalize(vector: [f64; 3]) -> [f64; 3] {
+    let length = dot(vector, vector).sqrt();
+    assert!(length > 0.0, "a spin needs a direction");
+    vector.map(|component| component / length)
+}
+
+/// # Random unit vector
+/// A direction drawn uniformly from the sphere, using that the z component of such a vector is
+/// uniform in [-1, 1].
+fn random_unit_vector<R: Rng>(rng: &mut R) -> [f64; 3] {
+    let z: f64 = rng.gen_range(-1.0..=1.0);
+    let azimuth = rng.gen_range(0.0..2.0 * PI);
+    let radius = (1.0 - z * z).sqrt();
+    [radius * azimuth.cos(), radius * azimuth.sin(), z]
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::helicity::HelicityModulus;
+
+    #[test]
+    fn test_ordered_observables() {
+        let model = HeisenbergModel::new_constant(4, 4, [0.0, 3.0, 4.0]).unwrap();
+        assert!((model.energy(Temperature::new(1.0).unwrap(), 0.5) + 2.4).abs() < 1e-12);
+        let magnetization = model.magnetization();
+        assert!((magnetization[1] - 0.6).abs() < 1e-12);
+        assert!((model.absolute_magnetization() - 1.0).abs() < 1e-12);
+
+        // Only the components perpendicular to the twist axis are stiff.
+        let mut stiffness = HelicityModulus::new();
+        stiffness.add(&model.twist_response());
+        assert!((stiffness.value(Temperature::new(1.0).unwrap()) - 0.36).abs() < 1e-12);
+
+        assert!(HeisenbergModel::new_constant(0, 4, [0.0, 0.0, 1.0]).is_err());
+        assert!(HeisenbergModel::new_constant(4, 4, [0.0; 3]).is_err());
+    }
+
+    #[test]
+    fn test_over_relaxation_conserves_energy() {
+        let (temperature, field) = (Temperature::new(0.9).unwrap(), 0.3);
+        let mut model = HeisenbergModel::new_random(8, 8, &mut StdRng::seed_from_u64(16)).unwrap();
+        let energy = model.energy(temperature, field);
+        model.over_relaxation_sweep(field);
+        assert!((model.energy(temperature, field) - energy).abs() < 1e-12);
+        let spin = model.get(2, 5);
+        assert!((dot(spin, spin) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_wolff_matches_hybrid() {
+        let temperature = Temperature::from_beta(0.7).unwrap();
+        let mut rng = StdRng::seed_from_u64(15);
+        let mut local = HeisenbergModel::new_random(4, 4, &mut rng).unwrap();
+        let mut cluster = HeisenbergModel::new_random(4, 4, &mut rng).unwrap();
+        for _ in 0..1000 {
+            local.hybrid_step(temperature, 0.0, 2, &mut rng);
+            cluster.wolff_step(temperature, &mut rng);
+        }
+        let number_of_steps = 40_000;
+        let (mut local_energy, mut cluster_energy) = (0.0, 0.0);
+        for _ in 0..number_of_steps {
+            local.hybrid_step(temperature, 0.0, 2, &mut rng);
+            cluster.wolff_step(temperature, &mut rng);
+            local_energy += local.energy(temperature, 0.0);
+            cluster_energy += cluster.energy(temperature, 0.0);
+        }
+        let difference = (local_energy - cluster_energy) / number_of_steps as f64;
+        assert!(difference.abs() < 0.03);
+    }
+}
@@ -0,0 +1,72 @@
+use std::f64::consts::PI;
+
+use crate::temperature::Temperature;
+
+/// # Twist response
+/// The two quantities of a single configuration that determine how its free energy responds to
+/// a twist of the boundary conditions along each axis: the sum over the bonds along that axis of
+/// the spin components that rotate with the twist, Σ (s_i · s_j)_⊥, and the spin current
+/// Σ (s_i × s_j)_∥ through them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwistResponse {
+    pub number_of_sites: usize,
+    pub bond_sums: [f64; 2],
+    pub spin_currents: [f64; 2],
+}
+
+/// # Helicity modulus
+/// Accumulates the helicity modulus, or spin stiffness, Υ = ∂²F/∂φ² per site for a twist φ per
+/// bond. In the same reduced units as the models it is estimated by
+/// Υ = (1/N) [⟨Σ (s_i · s_j)_⊥⟩ - K ⟨(Σ (s_i × s_j)_∥)²⟩],
+/// averaged over both axes and given in units of the exchange constant, with K = 1/T. At the
+/// Kosterlitz–Thouless transition of the XY model it jumps from 2T/π to zero, so the crossing
+/// of Υ(T) with `universal_jump` locates the transition.
+#[derive(Debug, Clone, Default)]
+pub struct HelicityModulus {
+    samples: usize,
+    bond_sum: f64,
+    current_squared_sum: f64,
+}
+
+impl HelicityModulus {
+    /// # New accumulator
+    /// Creates an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Add a sample
+    /// Adds the twist response of one configuration, per site.
+    pub fn add(&mut self, response: &TwistResponse) {
+        let number_of_sites = response.number_of_sites as f64;
+        self.samples += 1;
+        self.bond_sum += response.bond_sums.iter().sum::<f64>() / (2.0 * number_of_sites);
+        self.current_squared_sum += response
+            .spin_currents
+            .iter()
+            .map(|current| current * current)
+            .sum::<f64>()
+            / (2.0 * number_of_sites);
+    }
+
+    /// # Number of samples
+    /// The number of configurations added.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// # Value
+    /// The helicity modulus at the temperature the samples were drawn at, in units of the
+    /// exchange constant.
+    pub fn value(&self, temperature: Temperature) -> f64 {
+        let samples = self.samples as f64;
+        (self.bond_sum - temperature.beta() * self.current_squared_sum) / samples
+    }
+
+    /// # Universal jump
+    /// The value 2T/π that the helicity modulus of the XY model takes at the Kosterlitz–Thouless
+    /// temperature T, in units of the exchange constant.
+    pub fn universal_jump(temperature: Temperature) -> f64 {
+        2.0 * temperature.value() / PI
+    }
+}
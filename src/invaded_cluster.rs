@@ -0,0 +1,235 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::couplings::BondDirection;
+use crate::error::{Error, Result};
+use crate::grid::{BoundaryCondition, Grid};
+use crate::random_cluster::{flip_clusters, BondClusters, BondConfiguration};
+
+/// # Spanning rule
+/// When the invaded-cluster algorithm stops occupying bonds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanningRule {
+    /// A cluster wraps around the grid along either axis.
+    EitherAxis,
+    /// A cluster wraps around the grid along both axes.
+    BothAxes,
+}
+
+impl SpanningRule {
+    fn is_met(self, wraps: [bool; 2]) -> bool {
+        match self {
+            SpanningRule::EitherAxis => wraps[0] || wraps[1],
+            SpanningRule::BothAxes => wraps[0] && wraps[1],
+        }
+    }
+}
+
+/// # Invaded-cluster step
+/// The bonds of one invaded-cluster update and how many of the satisfied bonds they took.
+#[derive(Debug, Clone)]
+pub struct InvadedClusterStep {
+    pub bonds: BondConfiguration,
+    pub clusters: BondClusters,
+    /// The number of occupied bonds.
+    pub occupied: usize,
+    /// The number of satisfied bonds, those between parallel spins.
+    pub satisfied: usize,
+}
+
+impl InvadedClusterStep {
+    /// # Occupied fraction
+    /// The fraction of the satisfied bonds that were occupied before a cluster spanned the grid.
+    pub fn occupied_fraction(&self) -> f64 {
+        self.occupied as f64 / self.satisfied as f64
+    }
+
+    /// # Implied coupling
+    /// The reduced coupling K = -½ ln(1 - f) at which the Fortuin–Kasteleyn bonds would occupy the
+    /// fraction f of the satisfied bonds that this update did. Its distribution narrows around the
+    /// critical coupling as the grid grows.
+    pub fn implied_coupling(&self) -> f64 {
+        -0.5 * (1.0 - self.occupied_fraction()).ln()
+    }
+}
+
+/// # Union–find with wrapping
+/// Clusters that grow one bond at a time, recording for every site its displacement from the root
+/// of its cluster on the unwrapped lattice. A bond within a cluster whose ends disagree about
+/// that displacement closes a loop around the periodic grid.
+struct WrappingClusters {
+    parents: Vec<usize>,
+    offsets: Vec<(i64, i64)>,
+    sizes: Vec<usize>,
+    wrapping: Vec<[bool; 2]>,
+}
+
+impl WrappingClusters {
+    fn new(number_of_sites: usize) -> Self {
+        Self {
+            parents: (0..number_of_sites).collect(),
+            offsets: vec![(0, 0); number_of_sites],
+            sizes: vec![1; number_of_sites],
+            wrapping: vec![[false; 2]; number_of_sites],
+        }
+    }
+
+    /// The root of the cluster of a site and the displacement of the site from it.
+    fn find(&mut self, site: usize) -> (usize, (i64, i64)) {
+        let parent = self.parents[site];
+        if parent == site {
+            return (site, (0, 0));
+        }
+        let (root, (px, py)) = self.find(parent);
+        let (ox, oy) = self.offsets[site];
+        self.parents[site] = root;
+        self.offsets[site] = (ox + px, oy + py);
+        (root, self.offsets[site])
+    }
+
+    /// Joins the site to the neighbour the given step away and returns whether their cluster
+    /// wraps along each axis.
+    fn join(&mut self, site: usize, neighbor: usize, step: (i64, i64)) -> [bool; 2] {
+        let (root, (sx, sy)) = self.find(site);
+        let (other, (nx, ny)) = self.find(neighbor);
+        // The displacement of the root of the neighbour from the root of the site.
+        let (dx, dy) = (sx + step.0 - nx, sy + step.1 - ny);
+        if root == other {
+            self.wrapping[root][0] |= dx != 0;
+            self.wrapping[root][1] |= dy != 0;
+            return self.wrapping[root];
+        }
+        let (root, other, offset) = if self.sizes[root] >= self.sizes[other] {
+            (root, other, (dx, dy))
+        } else {
+            (other, root, (-dx, -dy))
+        };
+        self.parents[other] = root;
+        self.offsets[other] = offset;
+        self.sizes[root] += self.sizes[other];
+        let [x, y] = self.wrapping[other];
+        self.wrapping[root][0] |= x;
+        self.wrapping[root][1] |= y;
+        self.wrapping[root]
+    }
+}
+
+/// # Invaded-cluster step
+/// Performs one update of the invaded-cluster algorithm of Machta et al. The satisfied bonds,
+/// those between parallel spins, are occupied in a random order until a cluster spans the grid
+/// under the rule, and the clusters are then flipped as in the Swendsen–Wang update. Without any
+/// temperature, the dynamics settles at the critical point, where the occupied fraction matches
+/// the Fortuin–Kasteleyn bond probability 1 - exp(-2K_c). Clusters that contain a pinned spin keep
+/// their orientation and vacant sites never bond. It needs periodic boundaries, uniform
+/// couplings and no diagonal coupling, or this fails.
+pub fn invaded_cluster_step<R: Rng>(
+    grid: &mut Grid,
+    rule: SpanningRule,
+    rng: &mut R,
+) -> Result<InvadedClusterStep> {
+    if grid.boundary_conditions() != (BoundaryCondition::Periodic, BoundaryCondition::Periodic) {
+        return Err(Error::Unsupported(
+            "the invaded-cluster update needs periodic boundaries",
+        ));
+    }
+    if grid.next_nearest_ratio() != 0.0 || grid.bond_couplings().is_some() {
+        return Err(Error::Unsupported(
+            "the invaded-cluster update needs uniform nearest-neighbour couplings",
+        ));
+    }
+    let (width, height) = (grid.width(), grid.height());
+    let mut satisfied = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let spin = grid.get_spin_as_float(x as i64, y as i64);
+            for (direction, (dx, dy)) in [
+                (BondDirection::Horizontal, (1, 0)),
+                (BondDirection::Vertical, (0, 1)),
+            ] {
+                let neighbor = grid.get_spin_as_float(x as i64 + dx, y as i64 + dy);
+                if spin * neighbor > 0.0 {
+                    satisfied.push((x, y, direction, (dx, dy)));
+                }
+            }
+        }
+    }
+    satisfied.shuffle(rng);
+
+    let mut bonds = BondConfiguration::new(width, height);
+    let mut clusters = WrappingClusters::new(width * height);
+    let mut occupied = 0;
+    for &(x, y, direction, step) in &satisfied {
+        bonds.set(x as i64, y as i64, direction, true);
+        occupied += 1;
+        let neighbor = (y + step.1 as usize) % height * width + (x + step.0 as usize) % width;
+        if rule.is_met(clusters.join(y * width + x, neighbor, step)) {
+            break;
+        }
+    }
+
+    let clusters = bonds.clusters();
+    flip_clusters(grid, &clusters, rng);
+    Ok(InvadedClusterStep {
+        bonds,
+        clusters,
+        occupied,
+        satisfied: satisfied.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::spin::Spin;
+
+    #[test]
+    fn test_stops_when_spanning() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for rule in [SpanningRule::EitherAxis, SpanningRule::BothAxes] {
+            let mut grid = Grid::new_constant(6, 4, Spin::Up).unwrap();
+            let step = invaded_cluster_step(&mut grid, rule, &mut rng).unwrap();
+            assert_eq!(step.satisfied, 48);
+            assert_eq!(step.occupied, step.bonds.number_of_open_bonds());
+            let (along_x, along_y) = step.clusters.wraps();
+            assert!(rule.is_met([along_x, along_y]));
+            // The last bond completed the spanning cluster, so some open bond is needed for it.
+            assert!(step.occupied < step.satisfied);
+            let needed = (0..6_i64)
+                .flat_map(|x| (0..4_i64).map(move |y| (x, y)))
+                .flat_map(|(x, y)| {
+                    [BondDirection::Horizontal, BondDirection::Vertical].map(|d| (x, y, d))
+                })
+                .filter(|&(x, y, direction)| step.bonds.is_open(x, y, direction))
+                .any(|(x, y, direction)| {
+                    let mut fewer = step.bonds.clone();
+                    fewer.set(x, y, direction, false);
+                    let (along_x, along_y) = fewer.clusters().wraps();
+                    !rule.is_met([along_x, along_y])
+                });
+            assert!(needed);
+        }
+
+        let mut open = Grid::new_constant(6, 4, Spin::Up).unwrap();
+        open.set_boundary_conditions(BoundaryCondition::Open, BoundaryCondition::Periodic);
+        assert!(invaded_cluster_step(&mut open, SpanningRule::EitherAxis, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_self_tunes_to_critical_coupling() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut grid = Grid::new_constant(32, 32, Spin::Up).unwrap();
+        for _ in 0..100 {
+            invaded_cluster_step(&mut grid, SpanningRule::EitherAxis, &mut rng).unwrap();
+        }
+        let couplings: Vec<f64> = (0..400)
+            .map(|_| invaded_cluster_step(&mut grid, SpanningRule::EitherAxis, &mut rng))
+            .map(|step| step.unwrap().implied_coupling())
+            .collect();
+        let mean = couplings.iter().sum::<f64>() / couplings.len() as f64;
+        let critical = 0.5 * (1.0 + 2.0_f64.sqrt()).ln();
+        assert!((mean - critical).abs() < 0.02, "{}", mean);
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeSet;
+
+use super::GraphLattice;
+use crate::error::{Error, Result};
+
+/// The offsets of the default Pegasus construction, for the vertical and horizontal qubits.
+const PEGASUS_OFFSETS: [[usize; 12]; 2] = [
+    [2, 2, 2, 2, 10, 10, 10, 10, 6, 6, 6, 6],
+    [6, 6, 6, 6, 2, 2, 2, 2, 10, 10, 10, 10],
+];
+
+/// # Chimera graph
+/// Creates the C(rows, columns, shore_size) Chimera graph of D-Wave quantum annealers. Each unit
+/// cell is a complete bipartite graph between `shore_size` vertical and `shore_size` horizontal
+/// qubits. Vertical qubits also couple to the same qubit in the cell below, and horizontal qubits
+/// to the same qubit in the cell to the right. The sites follow D-Wave's linear numbering,
+/// ((row · columns + column) · 2 + shore) · shore_size + index.
+pub fn chimera(rows: usize, columns: usize, shore_size: usize) -> GraphLattice {
+    let site = |row: usize, column: usize, shore: usize, index: usize| {
+        ((row * columns + column) * 2 + shore) * shore_size + index
+    };
+
+    let mut graph = GraphLattice::new(2 * rows * columns * shore_size);
+    for row in 0..rows {
+        for column in 0..columns {
+            for k in 0..shore_size {
+                for l in 0..shore_size {
+                    graph.add_edge(site(row, column, 0, k), site(row, column, 1, l));
+                }
+                if row + 1 < rows {
+                    graph.add_edge(site(row, column, 0, k), site(row + 1, column, 0, k));
+                }
+                if column + 1 < columns {
+                    graph.add_edge(site(row, column, 1, k), site(row, column + 1, 1, k));
+                }
+            }
+        }
+    }
+    graph
+}
+
+/// # Pegasus graph
+/// Creates the P(size) Pegasus graph of D-Wave Advantage annealers, restricted to the qubits of
+/// the fabric as D-Wave does by default. Each qubit is labelled by its orientation u, its
+/// perpendicular offset w, its index k within a group of twelve parallel qubits, and its position z
+/// along the line of qubits. Qubits couple to their neighbours along the same line (external
+/// couplers), to the paired qubit of their group (odd couplers), and to the crossing perpendicular
+/// qubits shifted by the Pegasus offsets (internal couplers). The sites keep the order of D-Wave's
+/// linear index z + (size - 1)(k + 12(w + size·u)), with the qubits outside the fabric skipped.
+/// Fails for a size below two.
+pub fn pegasus(size: usize) -> Result<GraphLattice> {
+    if size < 2 {
+        return Err(Error::InvalidParameter {
+            name: "Pegasus size",
+            value: size as f64,
+        });
+    }
+    let line_length = size - 1;
+    let label =
+        |u: usize, w: usize, k: usize, z: usize| z + line_length * (k + 12 * (w + size * u));
+
+    let mut edges: BTreeSet<(usize, usize)> = BTreeSet::new();
+    let mut add = |a: usize, b: usize| {
+        edges.insert((a.min(b), a.max(b)));
+    };
+
+    for u in 0..2 {
+        for w in 0..size {
+            for k in 0..12 {
+                // External couplers join consecutive qubits along the same line.
+                for z in 0..line_length - 1 {
+                    add(label(u, w, k, z), label(u, w, k, z + 1));
+                }
+                // Odd couplers join the two qubits of each pair.
+                if k % 2 == 0 {
+                    for z in 0..line_length {
+                        add(label(u, w, k, z), label(u, w, k + 1, z));
+                    }
+                }
+            }
+        }
+    }
+
+    // Internal couplers join each vertical qubit to the horizontal qubits that cross it.
+    let [vertical_offsets, horizontal_offsets] = PEGASUS_OFFSETS;
+    for w in 0..size {
+        for (kk, &horizontal_offset) in horizontal_offsets.iter().enumerate() {
+            let first = if w == 0 { horizontal_offset } else { 0 };
+            let last = if w < line_length {
+                12
+            } else {
+                horizontal_offset
+            };
+            for (k, &vertical_offset) in vertical_offsets.iter().enumerate().take(last).skip(first)
+            {
+                for z in 0..line_length {
+                    let crossing_w = z + usize::from(kk < vertical_offset);
+                    let crossing_z = w - usize::from(k < horizontal_offset);
+                    add(label(0, w, k, z), label(1, crossing_w, kk, crossing_z));
+                }
+            }
+        }
+    }
+
+    // The qubits at the ends of the outermost lines are not part of the fabric.
+    let fabric_start = [
+        *horizontal_offsets.iter().min().unwrap(),
+        *vertical_offsets.iter().min().unwrap(),
+    ];
+    let fabric_end = [
+        12 - *horizontal_offsets.iter().max().unwrap(),
+        12 - *vertical_offsets.iter().max().unwrap(),
+    ];
+    let mut outside_fabric = BTreeSet::new();
+    for u in 0..2 {
+        for z in 0..line_length {
+            for k in 0..fabric_start[u] {
+                outside_fabric.insert(label(u, 0, k, z));
+            }
+            for k in 12 - fabric_end[u]..12 {
+                outside_fabric.insert(label(u, line_length, k, z));
+            }
+        }
+    }
+
+    // Number the remaining qubits contiguously, keeping their order.
+    let mut site_of_label = vec![usize::MAX; 24 * size * line_length];
+    let mut number_of_sites = 0;
+    for (label, site) in site_of_label.iter_mut().enumerate() {
+        if !outside_fabric.contains(&label) {
+            *site = number_of_sites;
+            number_of_sites += 1;
+        }
+    }
+
+    let mut graph = GraphLattice::new(number_of_sites);
+    for (a, b) in edges {
+        if !outside_fabric.contains(&a) && !outside_fabric.contains(&b) {
+            graph.add_edge(site_of_label[a], site_of_label[b]);
+        }
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lattice::Lattice;
+
+    #[test]
+    fn test_chimera() {
+        // The C16 graph of the D-Wave 2000Q has 2048 qubits and 6016 couplers.
+        let graph = chimera(16, 16, 4);
+        assert_eq!(graph.number_of_sites(), 2048);
+        assert_eq!(graph.number_of_bonds(), 6016);
+        assert_eq!(graph.degree_distribution().len() - 1, 6);
+
+        // A single cell is a complete bipartite graph.
+        let cell = chimera(1, 1, 4);
+        assert_eq!(cell.number_of_bonds(), 16);
+        assert!(cell.has_edge(0, 4));
+        assert!(!cell.has_edge(0, 1));
+    }
+
+    #[test]
+    fn test_pegasus() {
+        // The P16 graph of the D-Wave Advantage has 5640 qubits and 40484 couplers.
+        let graph = pegasus(16).unwrap();
+        assert_eq!(graph.number_of_sites(), 5640);
+        assert_eq!(graph.number_of_bonds(), 40484);
+        assert_eq!(graph.degree_distribution().len() - 1, 15);
+
+        let small = pegasus(6).unwrap();
+        assert_eq!(small.number_of_sites(), 680);
+        assert_eq!(small.number_of_bonds(), 4484);
+        assert!(pegasus(1).is_err());
+    }
+}
@@ -0,0 +1,54 @@
+use super::Lattice;
+use crate::error::{check_size, Result};
+
+/// # Chain
+/// A one-dimensional ring of spins, where every site has a left and a right neighbour. The
+/// one-dimensional Ising model can be solved exactly (see `ExactChain`), which makes it ideal for
+/// teaching and for validating measurement code.
+#[derive(Debug, Clone)]
+pub struct Chain {
+    neighbors: Vec<[usize; 2]>,
+}
+
+impl Chain {
+    /// # New chain
+    /// Creates a periodic chain of the given length. Fails if the chain has no sites.
+    pub fn new(length: usize) -> Result<Self> {
+        check_size(length, 1)?;
+        let neighbors = (0..length)
+            .map(|site| [(site + length - 1) % length, (site + 1) % length])
+            .collect();
+
+        Ok(Self { neighbors })
+    }
+
+    /// # Length
+    /// The number of sites in the chain.
+    pub fn length(&self) -> usize {
+        self.neighbors.len()
+    }
+}
+
+impl Lattice for Chain {
+    fn number_of_sites(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    fn neighbors(&self, site: usize) -> &[usize] {
+        &self.neighbors[site]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighbors() {
+        let chain = Chain::new(5).unwrap();
+        assert_eq!(chain.neighbors(0), &[4, 1]);
+        assert_eq!(chain.neighbors(4), &[3, 0]);
+        assert_eq!(chain.number_of_bonds(), 5);
+        assert!(Chain::new(0).is_err());
+    }
+}
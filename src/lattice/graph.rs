@@ -0,0 +1,178 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::Lattice;
+
+/// # Graph lattice
+/// An arbitrary graph stored as an adjacency list, so that the Ising model can be simulated on any
+/// topology: networks, lattices with defects, or graphs produced by external tools.
+#[derive(Debug, Clone, Default)]
+pub struct GraphLattice {
+    neighbors: Vec<Vec<usize>>,
+}
+
+impl GraphLattice {
+    /// # New graph
+    /// Creates a graph with the given number of sites and no edges.
+    pub fn new(number_of_sites: usize) -> Self {
+        Self {
+            neighbors: vec![Vec::new(); number_of_sites],
+        }
+    }
+
+    /// # From edges
+    /// Creates a graph with the given number of sites and undirected edges.
+    pub fn from_edges(number_of_sites: usize, edges: &[(usize, usize)]) -> Self {
+        let mut graph = Self::new(number_of_sites);
+        for &(a, b) in edges {
+            graph.add_edge(a, b);
+        }
+        graph
+    }
+
+    /// # Load an edge list
+    /// Reads a graph from a text file with one edge per line, given as two whitespace-separated
+    /// site indices starting from zero. Empty lines and lines starting with `#` are ignored. The
+    /// number of sites is one more than the largest index that appears.
+    pub fn load_edge_list(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let invalid = |line_number: usize, message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line_number + 1, message),
+            )
+        };
+
+        let mut edges = Vec::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let indices: Vec<usize> = line
+                .split_whitespace()
+                .map(|field| field.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(line_number, "site indices must be non-negative integers"))?;
+            let &[a, b] = indices.as_slice() else {
+                return Err(invalid(line_number, "expected exactly two site indices"));
+            };
+            if a == b {
+                return Err(invalid(line_number, "self-loops are not allowed"));
+            }
+            edges.push((a, b));
+        }
+
+        let number_of_sites = edges.iter().map(|&(a, b)| a.max(b) + 1).max().unwrap_or(0);
+        Ok(Self::from_edges(number_of_sites, &edges))
+    }
+
+    /// # Add an edge
+    /// Adds an undirected edge between two sites. Adding the same edge twice doubles its bond.
+    pub fn add_edge(&mut self, a: usize, b: usize) {
+        assert_ne!(a, b, "self-loops are not allowed");
+        self.neighbors[a].push(b);
+        self.neighbors[b].push(a);
+    }
+
+    /// # Has edge
+    /// Whether there is at least one edge between two sites.
+    pub fn has_edge(&self, a: usize, b: usize) -> bool {
+        self.neighbors[a].contains(&b)
+    }
+
+    /// # Mean degree
+    /// The average number of edges attached to a site.
+    pub fn mean_degree(&self) -> f64 {
+        2.0 * self.number_of_bonds() as f64 / self.neighbors.len() as f64
+    }
+
+    /// # Degree distribution
+    /// The number of sites with each degree, indexed by degree.
+    pub fn degree_distribution(&self) -> Vec<usize> {
+        let largest_degree = self.neighbors.iter().map(Vec::len).max().unwrap_or(0);
+        let mut distribution = vec![0; largest_degree + 1];
+        for neighbors in &self.neighbors {
+            distribution[neighbors.len()] += 1;
+        }
+        distribution
+    }
+
+    /// # Edges
+    /// Every edge of the graph once, as a pair with the smaller site first.
+    pub fn edges(&self) -> Vec<(usize, usize)> {
+        self.neighbors
+            .iter()
+            .enumerate()
+            .flat_map(|(a, neighbors)| {
+                neighbors
+                    .iter()
+                    .filter(move |&&b| a < b)
+                    .map(move |&b| (a, b))
+            })
+            .collect()
+    }
+}
+
+impl Lattice for GraphLattice {
+    fn number_of_sites(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    fn neighbors(&self, site: usize) -> &[usize] {
+        &self.neighbors[site]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lattice::Chain;
+    use crate::model::IsingModel;
+    use crate::spin::Spin;
+    use crate::temperature::Temperature;
+
+    #[test]
+    fn test_from_edges() {
+        let graph = GraphLattice::from_edges(4, &[(0, 1), (1, 2), (2, 0), (2, 3)]);
+        assert_eq!(graph.number_of_sites(), 4);
+        assert_eq!(graph.number_of_bonds(), 4);
+        assert_eq!(graph.degree(2), 3);
+        assert!(graph.has_edge(3, 2));
+        assert!(!graph.has_edge(0, 3));
+        assert_eq!(graph.edges(), vec![(0, 1), (0, 2), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn test_load_edge_list() {
+        let path = std::env::temp_dir().join("ising_model_test_load_edge_list.txt");
+        fs::write(&path, "# A ring of four sites\n0 1\n1 2\n\n2 3\n3 0\n").unwrap();
+        let graph = GraphLattice::load_edge_list(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(graph.number_of_sites(), 4);
+        assert_eq!(graph.number_of_bonds(), 4);
+
+        // A ring graph must behave exactly like a chain.
+        let ring = IsingModel::new_constant(graph, Spin::Up).unwrap();
+        let chain = IsingModel::new_constant(Chain::new(4).unwrap(), Spin::Up).unwrap();
+        let temperature = Temperature::new(1.5).unwrap();
+        assert_eq!(
+            ring.energy(temperature, 0.2),
+            chain.energy(temperature, 0.2)
+        );
+    }
+
+    #[test]
+    fn test_load_invalid_edge_list() {
+        let path = std::env::temp_dir().join("ising_model_test_load_invalid_edge_list.txt");
+        fs::write(&path, "0 1\n1 x\n").unwrap();
+        let error = GraphLattice::load_edge_list(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 2"));
+    }
+}
@@ -0,0 +1,133 @@
+use super::Lattice;
+use crate::error::{check_size, Result};
+
+/// # Honeycomb lattice
+/// A periodic honeycomb lattice made of width × height unit cells, each holding two sites: an A
+/// site and a B site. Every A site is bonded to the B site of its own cell and to the B sites of
+/// the cells to its left and below, so each site has three neighbours.
+#[derive(Debug, Clone)]
+pub struct Honeycomb {
+    width: usize,
+    height: usize,
+    neighbors: Vec<[usize; 3]>,
+}
+
+/// # Sublattice
+/// The two sites of the honeycomb unit cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sublattice {
+    A,
+    B,
+}
+
+impl Honeycomb {
+    /// # New honeycomb lattice
+    /// Creates a periodic honeycomb lattice of width × height unit cells. Fails if the
+    /// lattice has no unit cells.
+    pub fn new(width: usize, height: usize) -> Result<Self> {
+        check_size(width, height)?;
+
+        let mut lattice = Self {
+            width,
+            height,
+            neighbors: Vec::with_capacity(2 * width * height),
+        };
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                lattice.neighbors.push([
+                    lattice.site(x, y, Sublattice::B),
+                    lattice.site(x - 1, y, Sublattice::B),
+                    lattice.site(x, y - 1, Sublattice::B),
+                ]);
+                lattice.neighbors.push([
+                    lattice.site(x, y, Sublattice::A),
+                    lattice.site(x + 1, y, Sublattice::A),
+                    lattice.site(x, y + 1, Sublattice::A),
+                ]);
+            }
+        }
+        Ok(lattice)
+    }
+
+    /// # Site
+    /// The index of a site given its unit cell and sublattice, applying periodic boundary
+    /// conditions to the cell coordinates.
+    pub fn site(&self, x: i64, y: i64, sublattice: Sublattice) -> usize {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        let offset = match sublattice {
+            Sublattice::A => 0,
+            Sublattice::B => 1,
+        };
+        2 * (y * self.width + x) + offset
+    }
+
+    /// # Sublattice of a site
+    /// Which of the two sites of its unit cell a site is.
+    pub fn sublattice(&self, site: usize) -> Sublattice {
+        if site.is_multiple_of(2) {
+            Sublattice::A
+        } else {
+            Sublattice::B
+        }
+    }
+}
+
+impl Lattice for Honeycomb {
+    fn number_of_sites(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    fn neighbors(&self, site: usize) -> &[usize] {
+        &self.neighbors[site]
+    }
+
+    /// The critical coupling satisfies cosh(2K_c) = 2, so K_c = ln(2 + √3) / 2.
+    fn critical_coupling(&self) -> Option<f64> {
+        Some(0.5 * (2.0 + 3.0_f64.sqrt()).ln())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::IsingModel;
+    use crate::spin::Spin;
+    use crate::temperature::Temperature;
+
+    #[test]
+    fn test_neighbors() {
+        assert!(Honeycomb::new(0, 3).is_err());
+        let lattice = Honeycomb::new(4, 3).unwrap();
+        assert_eq!(lattice.number_of_sites(), 24);
+        assert_eq!(lattice.number_of_bonds(), 36);
+
+        for site in 0..lattice.number_of_sites() {
+            for &neighbor in lattice.neighbors(site) {
+                // The honeycomb lattice is bipartite, and every bond goes both ways.
+                assert_ne!(lattice.sublattice(site), lattice.sublattice(neighbor));
+                assert!(lattice.neighbors(neighbor).contains(&site));
+            }
+        }
+    }
+
+    #[test]
+    fn test_critical_coupling() {
+        let lattice = Honeycomb::new(12, 12).unwrap();
+        let critical_coupling = lattice.critical_coupling().unwrap();
+        assert!(((2.0 * critical_coupling).cosh() - 2.0).abs() < 1e-12);
+
+        // Well inside the ordered phase the lattice magnetizes, well inside the disordered phase
+        // it does not.
+        let mut ordered = IsingModel::new_constant(lattice.clone(), Spin::Up).unwrap();
+        let mut disordered = IsingModel::new_constant(lattice, Spin::Up).unwrap();
+        let cold = Temperature::from_beta(1.5 * critical_coupling).unwrap();
+        let hot = Temperature::from_beta(0.5 * critical_coupling).unwrap();
+        for _ in 0..500 {
+            ordered.step(cold, 0.0);
+            disordered.step(hot, 0.0);
+        }
+        assert!(ordered.magnetization().abs() > 0.9);
+        assert!(disordered.magnetization().abs() < 0.3);
+    }
+}
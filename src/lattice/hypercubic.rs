@@ -0,0 +1,132 @@
+use super::Lattice;
+use crate::error::{Error, Result};
+
+/// # Hypercubic lattice
+/// A D-dimensional hypercubic lattice with periodic boundary conditions along every axis. Each site
+/// has 2D neighbours, one on either side along each axis. Sites are numbered with the first axis
+/// varying fastest, as in `Grid`.
+#[derive(Debug, Clone)]
+pub struct Hypercubic<const D: usize> {
+    shape: [usize; D],
+    neighbors: Vec<usize>,
+}
+
+impl<const D: usize> Hypercubic<D> {
+    /// # New hypercubic lattice
+    /// Creates a lattice with the given number of sites along each axis. Fails if the lattice has
+    /// no axes or an axis has no sites.
+    pub fn new(shape: [usize; D]) -> Result<Self> {
+        if D == 0 {
+            return Err(Error::InvalidParameter {
+                name: "dimension",
+                value: 0.0,
+            });
+        }
+        if shape.contains(&0) {
+            return Err(Error::InvalidParameter {
+                name: "axis length",
+                value: 0.0,
+            });
+        }
+
+        let mut lattice = Self {
+            shape,
+            neighbors: Vec::new(),
+        };
+        let number_of_sites: usize = shape.iter().product();
+        lattice.neighbors = (0..number_of_sites)
+            .flat_map(|site| {
+                let coordinates = lattice.coordinates(site).map(|c| c as i64);
+                (0..2 * D).map(move |k| {
+                    let mut neighbor = coordinates;
+                    neighbor[k / 2] += if k % 2 == 0 { -1 } else { 1 };
+                    neighbor
+                })
+            })
+            .map(|neighbor| lattice.site(neighbor))
+            .collect();
+        Ok(lattice)
+    }
+
+    /// # Shape
+    /// The number of sites along each axis.
+    pub fn shape(&self) -> [usize; D] {
+        self.shape
+    }
+
+    /// # Coordinates
+    /// The coordinates of a site.
+    pub fn coordinates(&self, site: usize) -> [usize; D] {
+        let mut remainder = site;
+        let mut coordinates = [0; D];
+        for (coordinate, &length) in coordinates.iter_mut().zip(&self.shape) {
+            *coordinate = remainder % length;
+            remainder /= length;
+        }
+        coordinates
+    }
+
+    /// # Site
+    /// The site at the given coordinates, applying periodic boundary conditions along every axis.
+    pub fn site(&self, coordinates: [i64; D]) -> usize {
+        let mut site = 0;
+        for (&coordinate, &length) in coordinates.iter().zip(&self.shape).rev() {
+            site = site * length + coordinate.rem_euclid(length as i64) as usize;
+        }
+        site
+    }
+}
+
+impl<const D: usize> Lattice for Hypercubic<D> {
+    fn number_of_sites(&self) -> usize {
+        self.neighbors.len() / (2 * D)
+    }
+
+    fn neighbors(&self, site: usize) -> &[usize] {
+        &self.neighbors[2 * D * site..2 * D * (site + 1)]
+    }
+
+    /// Only the two-dimensional case is solved exactly, with Onsager's K_c = ln(1 + √2) / 2.
+    fn critical_coupling(&self) -> Option<f64> {
+        (D == 2).then(|| 0.5 * (1.0 + 2.0_f64.sqrt()).ln())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coordinates_round_trip() {
+        let lattice = Hypercubic::new([3, 4, 5]).unwrap();
+        for site in 0..lattice.number_of_sites() {
+            let coordinates = lattice.coordinates(site).map(|c| c as i64);
+            assert_eq!(lattice.site(coordinates), site);
+        }
+        assert!(Hypercubic::new([3, 0]).is_err());
+        assert!(Hypercubic::new([]).is_err());
+        assert_eq!(lattice.coordinates(1), [1, 0, 0]);
+        assert_eq!(lattice.coordinates(3), [0, 1, 0]);
+    }
+
+    #[test]
+    fn test_periodic_neighbors() {
+        let lattice = Hypercubic::new([4, 4, 4, 4]).unwrap();
+        assert_eq!(lattice.number_of_sites(), 256);
+        assert_eq!(lattice.number_of_bonds(), 4 * 256);
+
+        let origin = lattice.site([0, 0, 0, 0]);
+        let neighbors = lattice.neighbors(origin);
+        assert_eq!(neighbors.len(), 8);
+        assert_eq!(neighbors[0], lattice.site([3, 0, 0, 0]));
+        assert_eq!(neighbors[1], lattice.site([1, 0, 0, 0]));
+        assert_eq!(neighbors[7], lattice.site([0, 0, 0, 1]));
+    }
+
+    #[test]
+    fn test_wrapping() {
+        let lattice = Hypercubic::new([5, 6]).unwrap();
+        assert_eq!(lattice.site([-1, -1]), lattice.site([4, 5]));
+        assert_eq!(lattice.site([5, 12]), lattice.site([0, 0]));
+    }
+}
@@ -0,0 +1,152 @@
+use super::Lattice;
+use crate::error::{check_size, Result};
+
+/// # Kagome lattice
+/// A periodic kagome lattice made of width × height unit cells of three sites each. The sites of a
+/// cell form an up-pointing triangle, and neighbouring cells are joined by down-pointing triangles,
+/// so every site has four neighbours and every bond belongs to exactly one triangle. With an
+/// antiferromagnetic coupling it is even more strongly frustrated than the triangular lattice.
+#[derive(Debug, Clone)]
+pub struct Kagome {
+    width: usize,
+    height: usize,
+    neighbors: Vec<[usize; 4]>,
+}
+
+/// # Sublattice
+/// The three sites of the kagome unit cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sublattice {
+    A,
+    B,
+    C,
+}
+
+impl Kagome {
+    /// # New kagome lattice
+    /// Creates a periodic kagome lattice of width × height unit cells. Fails if the
+    /// lattice has no unit cells.
+    pub fn new(width: usize, height: usize) -> Result<Self> {
+        check_size(width, height)?;
+
+        let mut lattice = Self {
+            width,
+            height,
+            neighbors: Vec::with_capacity(3 * width * height),
+        };
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                // The first two neighbours of each site close the up triangle of its own cell, the
+                // last two close the down triangle it shares with the neighbouring cells.
+                lattice.neighbors.push([
+                    lattice.site(x, y, Sublattice::B),
+                    lattice.site(x, y, Sublattice::C),
+                    lattice.site(x - 1, y, Sublattice::B),
+                    lattice.site(x, y - 1, Sublattice::C),
+                ]);
+                lattice.neighbors.push([
+                    lattice.site(x, y, Sublattice::A),
+                    lattice.site(x, y, Sublattice::C),
+                    lattice.site(x + 1, y, Sublattice::A),
+                    lattice.site(x + 1, y - 1, Sublattice::C),
+                ]);
+                lattice.neighbors.push([
+                    lattice.site(x, y, Sublattice::A),
+                    lattice.site(x, y, Sublattice::B),
+                    lattice.site(x, y + 1, Sublattice::A),
+                    lattice.site(x - 1, y + 1, Sublattice::B),
+                ]);
+            }
+        }
+        Ok(lattice)
+    }
+
+    /// # Site
+    /// The index of a site given its unit cell and sublattice, applying periodic boundary
+    /// conditions to the cell coordinates.
+    pub fn site(&self, x: i64, y: i64, sublattice: Sublattice) -> usize {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        let offset = match sublattice {
+            Sublattice::A => 0,
+            Sublattice::B => 1,
+            Sublattice::C => 2,
+        };
+        3 * (y * self.width + x) + offset
+    }
+
+    /// # Sublattice of a site
+    /// Which of the three sites of its unit cell a site is.
+    pub fn sublattice(&self, site: usize) -> Sublattice {
+        match site % 3 {
+            0 => Sublattice::A,
+            1 => Sublattice::B,
+            _ => Sublattice::C,
+        }
+    }
+}
+
+impl Lattice for Kagome {
+    fn number_of_sites(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    fn neighbors(&self, site: usize) -> &[usize] {
+        &self.neighbors[site]
+    }
+
+    /// The ferromagnetic critical coupling is K_c = ln(3 + 2√3) / 4.
+    fn critical_coupling(&self) -> Option<f64> {
+        Some(0.25 * (3.0 + 2.0 * 3.0_f64.sqrt()).ln())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::IsingModel;
+
+    #[test]
+    fn test_neighbors() {
+        assert!(Kagome::new(0, 3).is_err());
+        let lattice = Kagome::new(4, 5).unwrap();
+        assert_eq!(lattice.number_of_sites(), 60);
+        assert_eq!(lattice.number_of_bonds(), 120);
+
+        for site in 0..lattice.number_of_sites() {
+            for &neighbor in lattice.neighbors(site) {
+                // Bonds only join different sublattices, and every bond goes both ways.
+                assert_ne!(lattice.sublattice(site), lattice.sublattice(neighbor));
+                assert!(lattice.neighbors(neighbor).contains(&site));
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_bond_is_in_a_triangle() {
+        let lattice = Kagome::new(3, 3).unwrap();
+        for site in 0..lattice.number_of_sites() {
+            for &neighbor in lattice.neighbors(site) {
+                let shared = lattice
+                    .neighbors(site)
+                    .iter()
+                    .filter(|other| lattice.neighbors(neighbor).contains(other))
+                    .count();
+                assert_eq!(shared, 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_antiferromagnetic_frustration() {
+        // Each triangle keeps one unsatisfied bond, and there are two triangles for every three
+        // sites, so the energy per site can never drop below -2|K|/3.
+        let mut model = IsingModel::new_random(Kagome::new(6, 6).unwrap()).unwrap();
+        let coupling = -2.0;
+        for _ in 0..300 {
+            model.step_reduced(coupling, 0.0);
+            assert!(model.energy_reduced(coupling, 0.0) >= 2.0 * coupling / 3.0 - 1e-12);
+        }
+        assert!(model.energy_reduced(coupling, 0.0) < 0.6 * coupling);
+    }
+}
@@ -0,0 +1,54 @@
+pub mod annealer;
+pub mod chain;
+pub mod graph;
+pub mod honeycomb;
+pub mod hypercubic;
+pub mod kagome;
+pub mod networks;
+pub mod triangular;
+
+pub use chain::Chain;
+pub use graph::GraphLattice;
+pub use honeycomb::Honeycomb;
+pub use hypercubic::Hypercubic;
+pub use kagome::Kagome;
+pub use triangular::Triangular;
+
+/// # Lattice
+/// The geometry on which the spins of an `IsingModel` live. A lattice is a set of sites labelled
+/// from zero, each with a list of neighbours. The Monte Carlo code only ever asks for neighbours,
+/// so any regular lattice or graph can be simulated by implementing this trait.
+///
+/// A neighbour may appear more than once when the lattice is so small that the periodic boundary
+/// conditions wrap onto the same site. The bond is then counted once for each appearance, exactly
+/// as in `Grid`.
+pub trait Lattice {
+    /// # Number of sites
+    /// The total number of sites on the lattice.
+    fn number_of_sites(&self) -> usize;
+
+    /// # Neighbours
+    /// The sites that share a bond with the given site.
+    fn neighbors(&self, site: usize) -> &[usize];
+
+    /// # Degree
+    /// The number of neighbours of a site.
+    fn degree(&self, site: usize) -> usize {
+        self.neighbors(site).len()
+    }
+
+    /// # Number of bonds
+    /// The total number of bonds, each counted once.
+    fn number_of_bonds(&self) -> usize {
+        (0..self.number_of_sites())
+            .map(|site| self.neighbors(site).len())
+            .sum::<usize>()
+            / 2
+    }
+
+    /// # Critical coupling
+    /// The exact ferromagnetic critical coupling K_c = J / k_BT_c, for lattices where it is known.
+    fn critical_coupling(&self) -> Option<f64> {
+        None
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::HashSet;
+
+use rand::Rng;
+
+use super::GraphLattice;
+use crate::error::{Error, Result};
+
+/// # Watts–Strogatz graph
+/// Creates a small-world network. The sites start on a ring, each joined to the `degree / 2`
+/// nearest sites on either side, and then every edge has its far end rewired to a uniformly random
+/// site with probability `rewiring_probability`. Rewiring never creates self-loops or duplicate
+/// edges, so the number of edges is always N·degree/2. Small probabilities keep the high clustering
+/// of the ring while adding the short paths of a random graph. Fails unless the degree is even and
+/// smaller than the number of sites, and the probability is between zero and one.
+pub fn watts_strogatz<R: Rng>(
+    number_of_sites: usize,
+    degree: usize,
+    rewiring_probability: f64,
+    rng: &mut R,
+) -> Result<GraphLattice> {
+    if !degree.is_multiple_of(2) || degree >= number_of_sites {
+        return Err(invalid_degree(degree));
+    }
+    check_probability("rewiring probability", rewiring_probability)?;
+
+    let mut edges: HashSet<(usize, usize)> = HashSet::new();
+    let ordered = |a: usize, b: usize| (a.min(b), a.max(b));
+    for site in 0..number_of_sites {
+        for offset in 1..=degree / 2 {
+            edges.insert(ordered(site, (site + offset) % number_of_sites));
+        }
+    }
+
+    // Visit the ring edges in a fixed order, so that a seeded generator always gives the same
+    // graph.
+    let mut degrees = vec![degree; number_of_sites];
+    for offset in 1..=degree / 2 {
+        for site in 0..number_of_sites {
+            let far_end = (site + offset) % number_of_sites;
+            // A site already joined to every other site cannot be rewired.
+            if !rng.gen_bool(rewiring_probability) || degrees[site] >= number_of_sites - 1 {
+                continue;
+            }
+
+            let target = loop {
+                let candidate = rng.gen_range(0..number_of_sites);
+                if candidate != site && !edges.contains(&ordered(site, candidate)) {
+                    break candidate;
+                }
+            };
+            edges.remove(&ordered(site, far_end));
+            edges.insert(ordered(site, target));
+            degrees[far_end] -= 1;
+            degrees[target] += 1;
+        }
+    }
+
+    let mut edges: Vec<(usize, usize)> = edges.into_iter().collect();
+    edges.sort();
+    Ok(GraphLattice::from_edges(number_of_sites, &edges))
+}
+
+/// # Erdős–Rényi graph
+/// Creates a G(n, p) random graph, where each of the n(n-1)/2 possible edges is present
+/// independently with the given probability. The gaps between consecutive edges are drawn from
+/// the geometric distribution (Batagelj and Brandes), so sparse graphs cost O(n + m) rather than
+/// O(n²). Fails unless the probability is between zero and one.
+pub fn erdos_renyi<R: Rng>(
+    number_of_sites: usize,
+    edge_probability: f64,
+    rng: &mut R,
+) -> Result<GraphLattice> {
+    check_probability("edge probability", edge_probability)?;
+    let mut graph = GraphLattice::new(number_of_sites);
+    if edge_probability == 0.0 {
+        return Ok(graph);
+    }
+    if edge_probability == 1.0 {
+        for a in 0..number_of_sites {
+            for b in a + 1..number_of_sites {
+                graph.add_edge(a, b);
+            }
+        }
+        return Ok(graph);
+    }
+
+    // Walk through the pairs (v, w) with w < v in order, skipping a geometrically distributed
+    // number of pairs between edges.
+    let log_of_miss = (1.0 - edge_probability).ln();
+    let mut v = 1;
+    let mut w: i64 = -1;
+    while v < number_of_sites {
+        let uniform: f64 = rng.gen();
+        w += 1 + ((1.0 - uniform).ln() / log_of_miss).floor() as i64;
+        while w >= v as i64 && v < number_of_sites {
+            w -= v as i64;
+            v += 1;
+        }
+        if v < number_of_sites {
+            graph.add_edge(v, w as usize);
+        }
+    }
+    Ok(graph)
+}
+
+/// # Random regular graph
+/// Creates a uniformly random simple graph where every site has exactly `degree` neighbours. Each
+/// site starts with `degree` free edge ends, which are paired at random while avoiding self-loops
+/// and duplicate edges (the Steger–Wormald algorithm). In the rare case that the remaining ends
+/// cannot be paired, the construction starts over. Fails unless the degree is smaller than the
+/// number of sites and their product is even.
+pub fn random_regular<R: Rng>(
+    number_of_sites: usize,
+    degree: usize,
+    rng: &mut R,
+) -> Result<GraphLattice> {
+    if !(number_of_sites * degree).is_multiple_of(2) || degree >= number_of_sites {
+        return Err(invalid_degree(degree));
+    }
+
+    let ordered = |a: usize, b: usize| (a.min(b), a.max(b));
+    'attempt: loop {
+        let mut free_ends: Vec<usize> = (0..number_of_sites)
+            .flat_map(|site| std::iter::repeat_n(site, degree))
+            .collect();
+        let mut edges: HashSet<(usize, usize)> = HashSet::new();
+        let mut ordered_edges = Vec::with_capacity(free_ends.len() / 2);
+
+        while !free_ends.is_empty() {
+            let mut paired = false;
+            for _ in 0..100 {
+                let i = rng.gen_range(0..free_ends.len());
+                let j = rng.gen_range(0..free_ends.len());
+                let (a, b) = (free_ends[i], free_ends[j]);
+                if a == b || edges.contains(&ordered(a, b)) {
+                    continue;
+                }
+
+                edges.insert(ordered(a, b));
+                ordered_edges.push((a, b));
+                // Remove the larger index first so that the smaller one stays valid.
+                free_ends.swap_remove(i.max(j));
+                free_ends.swap_remove(i.min(j));
+                paired = true;
+                break;
+            }
+            if !paired {
+                continue 'attempt;
+            }
+        }
+
+        return Ok(GraphLattice::from_edges(number_of_sites, &ordered_edges));
+    }
+}
+
+/// # Barabási–Albert graph
+/// Creates a scale-free network by preferential attachment. The graph starts as a complete graph
+/// on `edges_per_site + 1` sites, and every new site attaches `edges_per_site` edges to distinct
+/// existing sites chosen with probability proportional to their degree. The degree distribution
+/// then decays as k^-3. Fails unless every new site attaches at least one edge and there are more
+/// sites than edges per site.
+pub fn barabasi_albert<R: Rng>(
+    number_of_sites: usize,
+    edges_per_site: usize,
+    rng: &mut R,
+) -> Result<GraphLattice> {
+    let initial_sites = edges_per_site + 1;
+    if edges_per_site == 0 || number_of_sites < initial_sites {
+        return Err(Error::InvalidParameter {
+            name: "edges per site",
+            value: edges_per_site as f64,
+        });
+    }
+
+    let mut graph = GraphLattice::new(number_of_sites);
+    // Every edge contributes both of its ends to this list, so picking a uniformly random entry
+    // picks a site with probability proportional to its degree.
+    let mut edge_ends = Vec::with_capacity(2 * number_of_sites * edges_per_site);
+    for a in 0..initial_sites {
+        for b in a + 1..initial_sites {
+            graph.add_edge(a, b);
+            edge_ends.extend([a, b]);
+        }
+    }
+
+    for site in initial_sites..number_of_sites {
+        let mut targets: Vec<usize> = Vec::with_capacity(edges_per_site);
+        while targets.len() < edges_per_site {
+            let candidate = edge_ends[rng.gen_range(0..edge_ends.len())];
+            if !targets.contains(&candidate) {
+                targets.push(candidate);
+            }
+        }
+        for target in targets {
+            graph.add_edge(site, target);
+            edge_ends.extend([site, target]);
+        }
+    }
+    Ok(graph)
+}
+
+/// # Tree boundary
+/// How the leaves of a Cayley tree are treated. On an open tree the leaves make up a finite
+/// fraction of all sites and dominate the thermodynamics, so comparisons with the Bethe solution
+/// usually close the tree instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeBoundary {
+    /// The leaves keep a single neighbour.
+    Open,
+    /// The leaves are joined to each other at random until every site has the full coordination.
+    /// Far from the leaves the graph is still a tree, but there is no boundary any more, which
+    /// makes it a finite stand-in for the Bethe lattice.
+    RandomClosure,
+}
+
+/// # Cayley tree
+/// Creates a Cayley tree where every interior site has `coordination` neighbours, grown `depth`
+/// generations out from a central root. The sites are numbered generation by generation, starting
+/// with the root. Fails if the coordination is less than two.
+pub fn cayley_tree<R: Rng>(
+    coordination: usize,
+    depth: usize,
+    boundary: TreeBoundary,
+    rng: &mut R,
+) -> Result<GraphLattice> {
+    if coordination < 2 {
+        return Err(Error::InvalidParameter {
+            name: "coordination",
+            value: coordination as f64,
+        });
+    }
+
+    let mut edges = Vec::new();
+    let mut generation = vec![0];
+    let mut number_of_sites = 1;
+    for level in 0..depth {
+        let children_per_site = if level == 0 {
+            coordination
+        } else {
+            coordination - 1
+        };
+        let mut next_generation = Vec::with_capacity(generation.len() * children_per_site);
+        for &parent in &generation {
+            for _ in 0..children_per_site {
+                edges.push((parent, number_of_sites));
+                next_generation.push(number_of_sites);
+                number_of_sites += 1;
+            }
+        }
+        generation = next_generation;
+    }
+
+    if boundary == TreeBoundary::RandomClosure && depth > 0 {
+        close_leaves(&mut edges, &generation, coordination - 1, rng);
+    }
+    Ok(GraphLattice::from_edges(number_of_sites, &edges))
+}
+
+/// The error for a degree that the graph cannot have.
+fn invalid_degree(degree: usize) -> Error {
+    Error::InvalidParameter {
+        name: "degree",
+        value: degree as f64,
+    }
+}
+
+/// Checks that a probability is between zero and one.
+fn check_probability(name: &'static str, probability: f64) -> Result<()> {
+    if !(0.0..=1.0).contains(&probability) {
+        return Err(Error::InvalidParameter {
+            name,
+            value: probability,
+        });
+    }
+    Ok(())
+}
+
+/// # Close leaves
+/// Pairs up the missing edges of the leaves at random, avoiding self-loops and duplicate edges.
+/// When the last few ends cannot be paired, they are left open.
+fn close_leaves<R: Rng>(
+    edges: &mut Vec<(usize, usize)>,
+    leaves: &[usize],
+    missing_per_leaf: usize,
+    rng: &mut R,
+) {
+    let ordered = |a: usize, b: usize| (a.min(b), a.max(b));
+    let mut existing: HashSet<(usize, usize)> = HashSet::new();
+    let mut free_ends: Vec<usize> = leaves
+        .iter()
+        .flat_map(|&leaf| std::iter::repeat_n(leaf, missing_per_leaf))
+        .collect();
+
+    while free_ends.len() >= 2 {
+        let mut paired = false;
+        for _ in 0..100 {
+            let i = rng.gen_range(0..free_ends.len());
+            let j = rng.gen_range(0..free_ends.len());
+            let (a, b) = (free_ends[i], free_ends[j]);
+            if a == b || existing.contains(&ordered(a, b)) {
+                continue;
+            }
+
+            existing.insert(ordered(a, b));
+            edges.push((a, b));
+            free_ends.swap_remove(i.max(j));
+            free_ends.swap_remove(i.min(j));
+            paired = true;
+            break;
+        }
+        if !paired {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::lattice::Lattice;
+    use crate::mean_field::BetheApproximation;
+    use crate::model::IsingModel;
+    use crate::spin::Spin;
+    use crate::temperature::Temperature;
+
+    #[test]
+    fn test_ring_without_rewiring() {
+        let graph = watts_strogatz(20, 4, 0.0, &mut StdRng::seed_from_u64(1)).unwrap();
+        for site in 0..20 {
+            assert_eq!(graph.degree(site), 4);
+            assert!(graph.has_edge(site, (site + 2) % 20));
+        }
+        assert_eq!(graph.number_of_bonds(), 40);
+    }
+
+    #[test]
+    fn test_rewiring_keeps_edge_count() {
+        let graph = watts_strogatz(100, 6, 0.3, &mut StdRng::seed_from_u64(2)).unwrap();
+        assert_eq!(graph.number_of_bonds(), 300);
+        assert_eq!(graph.mean_degree(), 6.0);
+
+        // Some sites must have gained or lost edges.
+        let distribution = graph.degree_distribution();
+        assert!(distribution[6] < 100);
+        assert_eq!(distribution.iter().sum::<usize>(), 100);
+    }
+
+    #[test]
+    fn test_seeded_graphs_are_reproducible() {
+        let first = watts_strogatz(50, 4, 0.5, &mut StdRng::seed_from_u64(3)).unwrap();
+        let second = watts_strogatz(50, 4, 0.5, &mut StdRng::seed_from_u64(3)).unwrap();
+        assert_eq!(first.edges(), second.edges());
+
+        let first = erdos_renyi(50, 0.1, &mut StdRng::seed_from_u64(4)).unwrap();
+        let second = erdos_renyi(50, 0.1, &mut StdRng::seed_from_u64(4)).unwrap();
+        assert_eq!(first.edges(), second.edges());
+
+        let first = random_regular(50, 3, &mut StdRng::seed_from_u64(5)).unwrap();
+        let second = random_regular(50, 3, &mut StdRng::seed_from_u64(5)).unwrap();
+        assert_eq!(first.edges(), second.edges());
+    }
+
+    #[test]
+    fn test_erdos_renyi_edge_count() {
+        assert_eq!(
+            erdos_renyi(30, 0.0, &mut StdRng::seed_from_u64(6))
+                .unwrap()
+                .number_of_bonds(),
+            0
+        );
+        assert_eq!(
+            erdos_renyi(30, 1.0, &mut StdRng::seed_from_u64(6))
+                .unwrap()
+                .number_of_bonds(),
+            435
+        );
+
+        // The expected number of edges is p n(n-1)/2 = 4995, with a standard deviation of 67.
+        let graph = erdos_renyi(1000, 0.01, &mut StdRng::seed_from_u64(7)).unwrap();
+        let edges = graph.edges();
+        assert!((edges.len() as f64 - 4995.0).abs() < 300.0);
+        for (a, b) in edges {
+            assert_ne!(a, b);
+            assert!(b < 1000);
+        }
+    }
+
+    #[test]
+    fn test_barabasi_albert() {
+        let graph = barabasi_albert(2000, 2, &mut StdRng::seed_from_u64(9)).unwrap();
+        assert_eq!(graph.number_of_bonds(), 3 + 1997 * 2);
+        assert!((0..2000).all(|site| graph.degree(site) >= 2));
+
+        // Preferential attachment produces hubs far above the mean degree of four.
+        let largest_degree = graph.degree_distribution().len() - 1;
+        assert!(largest_degree > 30);
+
+        let other = barabasi_albert(2000, 2, &mut StdRng::seed_from_u64(9)).unwrap();
+        assert_eq!(graph.edges(), other.edges());
+    }
+
+    #[test]
+    fn test_open_cayley_tree() {
+        let tree = cayley_tree(3, 4, TreeBoundary::Open, &mut StdRng::seed_from_u64(10)).unwrap();
+        // 1 + 3 + 6 + 12 + 24 sites, and a tree has one edge fewer than sites.
+        assert_eq!(tree.number_of_sites(), 46);
+        assert_eq!(tree.number_of_bonds(), 45);
+        assert_eq!(tree.degree(0), 3);
+        assert_eq!(tree.degree(5), 3);
+        assert_eq!(tree.degree(45), 1);
+    }
+
+    #[test]
+    fn test_closed_cayley_tree_matches_bethe_solution() {
+        let tree = cayley_tree(
+            3,
+            8,
+            TreeBoundary::RandomClosure,
+            &mut StdRng::seed_from_u64(11),
+        )
+        .unwrap();
+        let distribution = tree.degree_distribution();
+        assert!(distribution[3] > 760);
+
+        let bethe = BetheApproximation::new(3).unwrap();
+        let temperature = Temperature::from_beta(1.5 * bethe.critical_coupling()).unwrap();
+        let mut model = IsingModel::new_constant(tree, Spin::Up).unwrap();
+        let mut magnetization = 0.0;
+        for sweep in 0..600 {
+            model.step(temperature, 0.0);
+            if sweep >= 100 {
+                magnetization += model.magnetization().abs() / 500.0;
+            }
+        }
+        assert!((magnetization - bethe.magnetization(temperature, 0.0)).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_random_regular() {
+        let graph = random_regular(40, 5, &mut StdRng::seed_from_u64(8)).unwrap();
+        let edges = graph.edges();
+        assert_eq!(edges.len(), 100);
+        assert_eq!(edges.iter().collect::<HashSet<_>>().len(), 100);
+        for site in 0..40 {
+            assert_eq!(graph.degree(site), 5);
+            assert!(!graph.has_edge(site, site));
+        }
+    }
+
+    #[test]
+    fn test_invalid_graphs() {
+        let mut rng = StdRng::seed_from_u64(12);
+        assert!(watts_strogatz(20, 3, 0.1, &mut rng).is_err());
+        assert!(watts_strogatz(20, 4, 1.5, &mut rng).is_err());
+        assert!(erdos_renyi(20, -0.1, &mut rng).is_err());
+        assert!(random_regular(5, 3, &mut rng).is_err());
+        assert!(random_regular(4, 4, &mut rng).is_err());
+        assert!(barabasi_albert(2, 2, &mut rng).is_err());
+        assert!(cayley_tree(1, 3, TreeBoundary::Open, &mut rng).is_err());
+    }
+}
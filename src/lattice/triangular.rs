@@ -0,0 +1,130 @@
+use super::Lattice;
+use crate::error::{check_size, Result};
+
+/// The offsets to the six neighbours of a site on the sheared square representation of the
+/// triangular lattice. The two extra diagonal offsets turn each square into two triangles.
+const OFFSETS: [(i64, i64); 6] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, -1), (-1, 1)];
+
+/// # Triangular lattice
+/// A periodic triangular lattice, where every site has six neighbours. It is stored as a square
+/// grid with one set of diagonals added. With an antiferromagnetic (negative) coupling the three
+/// bonds of a triangle cannot all be satisfied, which makes this the classic example of geometric
+/// frustration.
+#[derive(Debug, Clone)]
+pub struct Triangular {
+    width: usize,
+    height: usize,
+    neighbors: Vec<[usize; 6]>,
+}
+
+impl Triangular {
+    /// # New triangular lattice
+    /// Creates a periodic triangular lattice of width × height sites. Both sides should be
+    /// multiples of three for the antiferromagnetic ground states to fit without defects. Fails if
+    /// the lattice has no sites.
+    pub fn new(width: usize, height: usize) -> Result<Self> {
+        check_size(width, height)?;
+
+        let mut neighbors = Vec::with_capacity(width * height);
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                neighbors.push(OFFSETS.map(|(dx, dy)| {
+                    let neighbor_x = (x + dx).rem_euclid(width as i64) as usize;
+                    let neighbor_y = (y + dy).rem_euclid(height as i64) as usize;
+                    neighbor_y * width + neighbor_x
+                }));
+            }
+        }
+
+        Ok(Self {
+            width,
+            height,
+            neighbors,
+        })
+    }
+
+    /// # Width
+    /// The number of sites along each row.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Site
+    /// The site at the given coordinates, applying periodic boundary conditions.
+    pub fn site(&self, x: i64, y: i64) -> usize {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        y * self.width + x
+    }
+}
+
+impl Lattice for Triangular {
+    fn number_of_sites(&self) -> usize {
+        self.neighbors.len()
+    }
+
+    fn neighbors(&self, site: usize) -> &[usize] {
+        &self.neighbors[site]
+    }
+
+    /// The ferromagnetic critical coupling is K_c = ln(3) / 4.
+    fn critical_coupling(&self) -> Option<f64> {
+        Some(3.0_f64.ln() / 4.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::IsingModel;
+
+    #[test]
+    fn test_neighbors() {
+        assert!(Triangular::new(6, 0).is_err());
+        let lattice = Triangular::new(6, 6).unwrap();
+        assert_eq!(lattice.number_of_bonds(), 3 * 36);
+
+        let mut neighbors = lattice.neighbors(lattice.site(0, 0)).to_vec();
+        neighbors.sort();
+        let mut expected = vec![
+            lattice.site(1, 0),
+            lattice.site(-1, 0),
+            lattice.site(0, 1),
+            lattice.site(0, -1),
+            lattice.site(1, -1),
+            lattice.site(-1, 1),
+        ];
+        expected.sort();
+        assert_eq!(neighbors, expected);
+    }
+
+    #[test]
+    fn test_neighbors_are_symmetric() {
+        let lattice = Triangular::new(5, 4).unwrap();
+        for site in 0..lattice.number_of_sites() {
+            for &neighbor in lattice.neighbors(site) {
+                assert!(lattice.neighbors(neighbor).contains(&site));
+            }
+        }
+    }
+
+    #[test]
+    fn test_antiferromagnetic_frustration() {
+        // The antiferromagnetic ground state leaves one bond of every triangle unsatisfied, so the
+        // energy per site can never drop below -|K|.
+        let mut model = IsingModel::new_random(Triangular::new(12, 12).unwrap()).unwrap();
+        let final_coupling = -3.0;
+        for sweep in 0..500 {
+            let coupling = final_coupling * (sweep + 1) as f64 / 500.0;
+            model.step_reduced(coupling, 0.0);
+            assert!(model.energy_reduced(coupling, 0.0) >= coupling - 1e-12);
+        }
+        assert!(model.energy_reduced(final_coupling, 0.0) < 0.9 * final_coupling);
+    }
+}
@@ -0,0 +1,358 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::error::Result;
+use crate::grid::{Grid, NEIGHBOR_OFFSETS};
+use crate::spin::Spin;
+
+/// # Lattice gas
+/// Reads a grid as a lattice gas, where an up spin is a site occupied by a particle, n = 1, and
+/// any other site is empty, n = 0. Neighbouring particles attract each other and a reservoir sets
+/// the chemical potential, E = -ε Σ_⟨ij⟩ n_i n_j - μ Σ_i n_i, both in units of k_BT. Substituting
+/// n = (1 + s)/2 turns this into the Ising model with K = ε/4 and H = μ/2 + ε on the square
+/// lattice, so liquid and vapour coexist along μ = -2ε below the critical point ε_c ≈ 1.763.
+/// The mapping assumes the plain nearest-neighbour grid, without diagonal or individual bonds.
+#[derive(Debug)]
+pub struct LatticeGas {
+    grid: Grid,
+}
+
+impl LatticeGas {
+    /// # New lattice gas
+    /// Reads the given grid as a lattice gas.
+    pub fn new(grid: Grid) -> Self {
+        Self { grid }
+    }
+
+    /// # New empty lattice gas
+    /// Creates a lattice gas without any particles. Fails if the grid has no sites.
+    pub fn new_empty(width: usize, height: usize) -> Result<Self> {
+        Ok(Self::new(Grid::new_constant(width, height, Spin::Down)?))
+    }
+
+    /// # Ising parameters
+    /// The reduced coupling and field (K, H) of the Ising model that is equivalent to a lattice
+    /// gas with the given attraction ε and chemical potential μ.
+    pub fn ising_parameters(attraction: f64, chemical_potential: f64) -> (f64, f64) {
+        (attraction / 4.0, chemical_potential / 2.0 + attraction)
+    }
+
+    /// # Grid
+    /// The underlying spins.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// # Mutable grid
+    /// The underlying spins, for setting up pinning, masks or boundary conditions.
+    pub fn grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+
+    /// # Into grid
+    /// Gives back the underlying spins.
+    pub fn into_grid(self) -> Grid {
+        self.grid
+    }
+
+    /// # Occupation
+    /// The number of particles n at the given coordinates, with periodic boundary conditions.
+    pub fn occupation(&self, x: i64, y: i64) -> f64 {
+        if self.grid.get(x, y) == Spin::Up {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    /// # Density
+    /// The fraction of occupied sites.
+    pub fn density(&self) -> f64 {
+        let mut particles = 0.0;
+        for y in 0..self.grid.height() as i64 {
+            for x in 0..self.grid.width() as i64 {
+                particles += self.occupation(x, y);
+            }
+        }
+        particles / (self.grid.width() * self.grid.height()) as f64
+    }
+
+    /// # Density correlation
+    /// The average of n_i n_(i+r) over all the sites of the current configuration, for the
+    /// separation r = (dx, dy).
+    pub fn density_correlation(&self, dx: i64, dy: i64) -> f64 {
+        let mut sum = 0.0;
+        for y in 0..self.grid.height() as i64 {
+            for x in 0..self.grid.width() as i64 {
+                sum += self.occupation(x, y) * self.occupation(x + dx, y + dy);
+            }
+        }
+        sum / (self.grid.width() * self.grid.height()) as f64
+    }
+
+    /// # Energy
+    /// The lattice-gas energy per site, with every bond counted once.
+    pub fn energy(&self, attraction: f64, chemical_potential: f64) -> f64 {
+        let mut energy = 0.0;
+        for y in 0..self.grid.height() as i64 {
+            for x in 0..self.grid.width() as i64 {
+                let occupation = self.occupation(x, y);
+                let neighbors = self.occupation(x + 1, y) + self.occupation(x, y + 1);
+                energy -= attraction * occupation * neighbors + chemical_potential * occupation;
+            }
+        }
+        energy / (self.grid.width() * self.grid.height()) as f64
+    }
+
+    /// # Structure factor
+    /// S(k) = |Σ_j (n_j - ρ) e^(ik·r_j)|² / N of the current configuration, at the wave vector
+    /// k = 2π (mx / width, my / height). Subtracting the density removes the trivial peak at k = 0.
+    pub fn structure_factor(&self, mx: usize, my: usize) -> f64 {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let density = self.density();
+        let (kx, ky) = (
+            2.0 * PI * mx as f64 / width as f64,
+            2.0 * PI * my as f64 / height as f64,
+        );
+        let (mut real, mut imaginary) = (0.0, 0.0);
+        for y in 0..height {
+            for x in 0..width {
+                let fluctuation = self.occupation(x as i64, y as i64) - density;
+                let phase = kx * x as f64 + ky * y as f64;
+                real += fluctuation * phase.cos();
+                imaginary += fluctuation * phase.sin();
+            }
+        }
+        (real * real + imaginary * imaginary) / (width * height) as f64
+    }
+
+    /// # Step
+    /// Performs a single Monte Carlo sweep at the given attraction and chemical potential.
+    pub fn step<R: Rng>(&mut self, attraction: f64, chemical_potential: f64, rng: &mut R) {
+        let (coupling, field) = Self::ising_parameters(attraction, chemical_potential);
+        self.grid.step_reduced_with_rng(coupling, field, rng);
+    }
+
+    /// # Driven step
+    /// Performs a sweep of Kawasaki dynamics in the driven lattice gas of Katz, Lebowitz and Spohn.
+    /// Each of the N attempts picks a random site and a random neighbour, and a particle hops to
+    /// the neighbour if it is empty, with probability min(1, exp(-ΔE + E δx)), where ΔE is the
+    /// change of the lattice-gas energy and δx = ±1 for hops along or against the x axis and zero
+    /// otherwise. The number of particles is conserved, and a drive E ≠ 0 keeps the gas out of
+    /// equilibrium with a steady current: below the critical point the particles order into
+    /// strips parallel to the drive. Returns the net number of hops along x per site, which is
+    /// the particle current. Vacant sites block hops, and pinned particles and holes never move.
+    pub fn driven_step<R: Rng>(&mut self, attraction: f64, drive: f64, rng: &mut R) -> f64 {
+        let (width, height) = (self.grid.width(), self.grid.height());
+        let number_of_sites = width * height;
+        let mut net_hops = 0;
+        for _ in 0..number_of_sites {
+            let site = rng.gen_range(0..number_of_sites);
+            let (x, y) = ((site % width) as i64, (site / width) as i64);
+            let (dx, dy) = NEIGHBOR_OFFSETS[rng.gen_range(0..4)];
+            let (to_x, to_y) = (x + dx, y + dy);
+            if self.grid.get(x, y) != Spin::Up
+                || self.grid.get(to_x, to_y) != Spin::Down
+                || self.grid.is_pinned(x, y)
+                || self.grid.is_pinned(to_x, to_y)
+            {
+                continue;
+            }
+
+            // The particle loses the bonds of its old site and gains those of the new one, apart
+            // from the bond between the two sites, which it would count as a neighbour of itself.
+            let bonds_before = self.occupied_neighbors(x, y);
+            let bonds_after = self.occupied_neighbors(to_x, to_y) - 1.0;
+            let energy_change = -attraction * (bonds_after - bonds_before);
+            let exponent = -energy_change + drive * dx as f64;
+            if exponent >= 0.0 || rng.gen::<f64>() < exponent.exp() {
+                self.grid.set(x, y, Spin::Down);
+                self.grid.set(to_x, to_y, Spin::Up);
+                net_hops += dx;
+            }
+        }
+        net_hops as f64 / number_of_sites as f64
+    }
+
+    fn occupied_neighbors(&self, x: i64, y: i64) -> f64 {
+        NEIGHBOR_OFFSETS
+            .iter()
+            .map(|&(dx, dy)| self.occupation(x + dx, y + dy))
+            .sum()
+    }
+}
+
+/// # Density statistics
+/// Accumulates the density of a lattice gas over many configurations, together with the
+/// density–density correlations along the axes up to a maximum distance.
+#[derive(Debug, Clone)]
+pub struct DensityStatistics {
+    samples: usize,
+    number_of_sites: usize,
+    density: f64,
+    density_squared: f64,
+    correlations: Vec<f64>,
+}
+
+impl DensityStatistics {
+    /// # New density statistics
+    /// Creates an empty accumulator that records correlations up to the given distance.
+    pub fn new(max_distance: usize) -> Self {
+        Self {
+            samples: 0,
+            number_of_sites: 0,
+            density: 0.0,
+            density_squared: 0.0,
+            correlations: vec![0.0; max_distance + 1],
+        }
+    }
+
+    /// # Add a sample
+    /// Adds the current configuration of a lattice gas.
+    pub fn add(&mut self, gas: &LatticeGas) {
+        let density = gas.density();
+        self.samples += 1;
+        self.number_of_sites = gas.grid().width() * gas.grid().height();
+        self.density += density;
+        self.density_squared += density * density;
+        for (distance, correlation) in self.correlations.iter_mut().enumerate() {
+            let distance = distance as i64;
+            *correlation +=
+                0.5 * (gas.density_correlation(distance, 0) + gas.density_correlation(0, distance));
+        }
+    }
+
+    /// # Number of samples
+    /// The number of configurations added so far.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// # Mean density
+    /// The average density ⟨ρ⟩.
+    pub fn mean_density(&self) -> f64 {
+        self.density / self.samples as f64
+    }
+
+    /// # Compressibility
+    /// The reduced isothermal compressibility k_BT κ_T = N (⟨ρ²⟩ - ⟨ρ⟩²) / ⟨ρ⟩², from the density
+    /// fluctuations in the grand canonical ensemble. It is (1 - ρ)/ρ for an ideal lattice gas and
+    /// diverges at the critical point.
+    pub fn compressibility(&self) -> f64 {
+        let density = self.mean_density();
+        let variance = self.density_squared / self.samples as f64 - density * density;
+        self.number_of_sites as f64 * variance / (density * density)
+    }
+
+    /// # Correlation
+    /// The connected density–density correlation ⟨n_0 n_r⟩ - ⟨ρ⟩² at distance r along the axes.
+    pub fn correlation(&self, distance: usize) -> f64 {
+        let density = self.mean_density();
+        self.correlations[distance] / self.samples as f64 - density * density
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_mapping_matches_energy_differences() {
+        let (attraction, chemical_potential) = (1.3, -0.7);
+        let (coupling, field) = LatticeGas::ising_parameters(attraction, chemical_potential);
+        let mut gas = LatticeGas::new(Grid::new_random(6, 6).unwrap());
+
+        // Adding a particle changes both energies by the same amount.
+        gas.grid_mut().set(2, 3, Spin::Down);
+        let gas_before = gas.energy(attraction, chemical_potential);
+        let ising_before = gas.grid().total_energy(2, 3, coupling, field);
+        gas.grid_mut().set(2, 3, Spin::Up);
+        let gas_change = 36.0 * (gas.energy(attraction, chemical_potential) - gas_before);
+        let ising_change = gas.grid().total_energy(2, 3, coupling, field) - ising_before;
+        assert!((gas_change - ising_change).abs() < 1e-12);
+
+        assert_eq!(LatticeGas::ising_parameters(1.0, -2.0), (0.25, 0.0));
+    }
+
+    #[test]
+    fn test_ideal_gas() {
+        // Without attraction every site is independently occupied with probability e^μ/(1 + e^μ).
+        let chemical_potential: f64 = 1.0;
+        let density = 1.0 / (1.0 + (-chemical_potential).exp());
+        let mut gas = LatticeGas::new_empty(16, 16).unwrap();
+        let mut statistics = DensityStatistics::new(2);
+        let mut rng = StdRng::seed_from_u64(4);
+        for sweep in 0..3000 {
+            gas.step(0.0, chemical_potential, &mut rng);
+            if sweep >= 100 {
+                statistics.add(&gas);
+            }
+        }
+        assert!((statistics.mean_density() - density).abs() < 0.01);
+        assert!((statistics.compressibility() - (1.0 - density) / density).abs() < 0.06);
+        assert!((statistics.correlation(0) - density * (1.0 - density)).abs() < 0.01);
+        assert!(statistics.correlation(1).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_structure_factor_of_a_strip() {
+        // A strip along x only modulates the density across the drive.
+        let mut gas = LatticeGas::new_empty(8, 8).unwrap();
+        for y in 0..4 {
+            for x in 0..8 {
+                gas.grid_mut().set(x, y, Spin::Up);
+            }
+        }
+        assert!(gas.structure_factor(1, 0).abs() < 1e-12);
+        // The sum over a row gives 8 Σ_(y<4) e^(iπy/4), whose squared length is 64/sin²(π/8).
+        let expected = 4.0 + 2.0 * 2.0_f64.sqrt();
+        assert!((gas.structure_factor(0, 1) - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_driven_gas() {
+        let mut rng = StdRng::seed_from_u64(19);
+        let grid = Grid::new_with_magnetization(24, 24, 0.0, &mut rng).unwrap();
+        let mut gas = LatticeGas::new(grid);
+        let density = gas.density();
+
+        // Without attraction a hop along the drive is always accepted and one against it with
+        // probability e^-E, so the current is ρ(1 - ρ)(1 - e^-E)/4 ≈ 0.04 per site and sweep.
+        let (mut driven, mut undriven) = (0.0, 0.0);
+        for _ in 0..200 {
+            driven += gas.driven_step(0.0, 1.0, &mut rng);
+            undriven += gas.driven_step(0.0, 0.0, &mut rng);
+        }
+        assert!((driven / 200.0 - 0.0395).abs() < 0.005);
+        assert!((undriven / 200.0).abs() < 0.01);
+        assert_eq!(gas.density(), density);
+
+        // A strong attraction and drive order the particles into strips along x, which may not
+        // yet have merged into a single one.
+        for _ in 0..3000 {
+            gas.driven_step(4.0, 10.0, &mut rng);
+        }
+        let transverse = gas.structure_factor(0, 1) + gas.structure_factor(0, 2);
+        let parallel = gas.structure_factor(1, 0) + gas.structure_factor(2, 0);
+        assert!(transverse > 10.0 * parallel);
+    }
+
+    #[test]
+    fn test_condensation() {
+        // Below the critical point the density jumps as μ crosses the coexistence line.
+        let attraction = 3.0;
+        let mut vapour = LatticeGas::new(Grid::new_random(16, 16).unwrap());
+        let mut liquid = LatticeGas::new(Grid::new_random(16, 16).unwrap());
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..300 {
+            vapour.step(attraction, -2.0 * attraction - 0.3, &mut rng);
+            liquid.step(attraction, -2.0 * attraction + 0.3, &mut rng);
+        }
+        assert!(vapour.density() < 0.1);
+        assert!(liquid.density() > 0.9);
+    }
+}
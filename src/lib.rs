@@ -0,0 +1,69 @@
+pub mod adaptive;
+pub mod analysis;
+pub mod bench;
+pub mod canonical;
+pub mod clock;
+pub mod collapse;
+pub mod config;
+pub mod couplings;
+#[cfg(feature = "server")]
+pub mod dashboard;
+#[cfg(feature = "sqlite")]
+pub mod database;
+pub mod dataset;
+pub mod decomposition;
+pub mod dipolar;
+pub mod disorder;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod distributed;
+pub mod ensemble;
+pub mod error;
+pub mod exact;
+pub mod field;
+pub mod flip_log;
+pub mod free_energy;
+pub mod grid;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod heisenberg;
+pub mod helicity;
+pub mod invaded_cluster;
+pub mod lattice;
+pub mod lattice_gas;
+pub mod long_range;
+pub mod mask;
+pub mod mcrg;
+pub mod mean_field;
+pub mod measurement;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod metrics;
+pub mod model;
+#[cfg(feature = "evcxr")]
+pub mod notebook;
+pub mod output;
+pub mod percolation;
+#[cfg(feature = "plot")]
+pub mod plot;
+pub mod potts;
+pub mod provenance;
+pub mod qubo;
+pub mod quench;
+pub mod random_cluster;
+pub mod random_field;
+pub mod render;
+pub mod scan;
+pub mod simulation;
+pub mod spin;
+pub mod spin_glass;
+pub mod temperature;
+pub mod thermostat;
+pub mod time_correlation;
+pub mod trajectory;
+pub mod transfer_matrix;
+pub mod transverse_field;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tui;
+#[cfg(feature = "web")]
+pub mod web;
+pub mod xy;
+pub mod zeros;
@@ -0,0 +1,265 @@
+use rand::Rng;
+
+use crate::error::{Error, Result};
+use crate::lattice::{Hypercubic, Lattice};
+use crate::spin::Spin;
+use crate::temperature::Temperature;
+
+/// # Long-range Ising model
+/// Spins on a periodic D-dimensional hypercubic lattice where every pair interacts with a
+/// strength J(r) = 1/r^(D+σ) that decays with their distance. The distance is taken to the nearest
+/// periodic image. For σ < D/2 the critical behaviour is mean-field like, for σ ≥ 2 it is that of
+/// the short-range model, and in between the exponents vary continuously with σ.
+///
+/// Because every spin interacts with every other one, single spin flips cost O(N) each. The
+/// model is instead updated with the Luijten–Blöte cluster algorithm, which samples the bonds of a
+/// Wolff cluster directly from the cumulative bond probabilities.
+#[derive(Debug, Clone)]
+pub struct LongRangeIsing<const D: usize> {
+    lattice: Hypercubic<D>,
+    decay_exponent: f64,
+    spins: Vec<Spin>,
+    offsets: Vec<[i64; D]>,
+    couplings: Vec<f64>,
+    cumulative_couplings: Vec<f64>,
+}
+
+impl<const D: usize> LongRangeIsing<D> {
+    /// # New random long-range model
+    /// Creates a model on a lattice of the given shape with interactions decaying as 1/r^(D+σ),
+    /// where each spin has a random orientation. Fails as `new_constant` does.
+    pub fn new_random(shape: [usize; D], sigma: f64) -> Result<Self> {
+        let mut model = Self::new_constant(shape, sigma, Spin::Up)?;
+        for spin in model.spins.iter_mut() {
+            *spin = rand::random();
+        }
+        Ok(model)
+    }
+
+    /// # New constant long-range model
+    /// Creates a model on a lattice of the given shape with interactions decaying as 1/r^(D+σ),
+    /// where each spin has the same orientation. Fails unless σ is positive and finite and the
+    /// lattice has sites.
+    pub fn new_constant(shape: [usize; D], sigma: f64, spin: Spin) -> Result<Self> {
+        if !(sigma > 0.0 && sigma.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "decay exponent",
+                value: sigma,
+            });
+        }
+        let lattice = Hypercubic::new(shape)?;
+        let number_of_sites = lattice.number_of_sites();
+        let decay_exponent = D as f64 + sigma;
+
+        // Every pair of sites is related by one of the N - 1 non-zero displacements, whose coupling
+        // only depends on the distance to the nearest image.
+        let mut offsets = Vec::with_capacity(number_of_sites - 1);
+        let mut couplings = Vec::with_capacity(number_of_sites - 1);
+        for site in 1..number_of_sites {
+            let coordinates = lattice.coordinates(site);
+            let squared_distance: usize = coordinates
+                .iter()
+                .zip(&shape)
+                .map(|(&c, &length)| c.min(length - c).pow(2))
+                .sum();
+            offsets.push(coordinates.map(|c| c as i64));
+            couplings.push((squared_distance as f64).powf(-0.5 * decay_exponent));
+        }
+
+        // The prefix sums of the couplings, starting from zero.
+        let mut cumulative_couplings = vec![0.0];
+        let mut sum = 0.0;
+        for coupling in &couplings {
+            sum += coupling;
+            cumulative_couplings.push(sum);
+        }
+
+        Ok(Self {
+            lattice,
+            decay_exponent,
+            spins: vec![spin; number_of_sites],
+            offsets,
+            couplings,
+            cumulative_couplings,
+        })
+    }
+
+    /// # Lattice
+    /// The lattice that the spins live on.
+    pub fn lattice(&self) -> &Hypercubic<D> {
+        &self.lattice
+    }
+
+    /// # Decay exponent
+    /// The exponent D + σ of the power law.
+    pub fn decay_exponent(&self) -> f64 {
+        self.decay_exponent
+    }
+
+    /// # Get a spin
+    /// Retrieves the spin at the given site.
+    pub fn get(&self, site: usize) -> Spin {
+        self.spins[site]
+    }
+
+    /// # Get a spin as a plus/minus one
+    /// Retrieves the spin at the given site as a plus/minus one.
+    pub fn get_spin_as_float(&self, site: usize) -> f64 {
+        match self.spins[site] {
+            Spin::Up => 1.0,
+            Spin::Down => -1.0,
+            Spin::Zero | Spin::Vacant => 0.0,
+        }
+    }
+
+    /// # Coupling sum
+    /// The sum of the couplings of one site to all the others, which sets the mean-field critical
+    /// coupling 1/Σ_j J_ij.
+    pub fn coupling_sum(&self) -> f64 {
+        *self.cumulative_couplings.last().unwrap()
+    }
+
+    /// # Magnetization
+    /// The magnetization per site.
+    pub fn magnetization(&self) -> f64 {
+        (0..self.spins.len())
+            .map(|site| self.get_spin_as_float(site))
+            .sum::<f64>()
+            / self.spins.len() as f64
+    }
+
+    /// # Energy
+    /// The energy per site at the given reduced coupling, with every pair counted once. This visits
+    /// all the pairs, so it costs O(N²) and is meant for occasional measurements.
+    pub fn energy(&self, coupling: f64) -> f64 {
+        let mut energy = 0.0;
+        for site in 0..self.spins.len() {
+            let our_spin = self.get_spin_as_float(site);
+            let field: f64 = self
+                .offsets
+                .iter()
+                .zip(&self.couplings)
+                .map(|(offset, j)| j * self.get_spin_as_float(self.translate(site, offset)))
+                .sum();
+            energy -= 0.5 * coupling * our_spin * field;
+        }
+        energy / self.spins.len() as f64
+    }
+
+    /// # Cluster step
+    /// Grows and flips a single Wolff cluster with the Luijten–Blöte construction, and returns its
+    /// size. A bond to the site at displacement k is activated with probability
+    /// 1 - exp(-2K J_k), so the displacement of the next activated bond after k₀ is the first k
+    /// with 2K (S_k - S_k₀) ≥ -ln(1 - r), where S are the prefix sums of the couplings. Finding it
+    /// by bisection makes the cost of a cluster proportional to its number of bonds times log N,
+    /// rather than to N per cluster site. The reduced coupling is K = 1/T.
+    pub fn cluster_step<R: Rng>(&mut self, temperature: Temperature, rng: &mut R) -> usize {
+        let coupling = temperature.beta();
+        let seed = rng.gen_range(0..self.spins.len());
+        let cluster_spin = self.spins[seed];
+        self.spins[seed] = cluster_spin.flip();
+        let mut stack = vec![seed];
+        let mut size = 1;
+
+        while let Some(site) = stack.pop() {
+            let mut last = 0;
+            loop {
+                let threshold = self.cumulative_couplings[last]
+                    - (1.0 - rng.gen::<f64>()).ln() / (2.0 * coupling);
+                let next = last
+                    + 1
+                    + self.cumulative_couplings[last + 1..].partition_point(|&sum| sum < threshold);
+                if next >= self.cumulative_couplings.len() {
+                    break;
+                }
+
+                // Sites that already joined the cluster have been flipped, so they never match.
+                let neighbor = self.translate(site, &self.offsets[next - 1]);
+                if self.spins[neighbor] == cluster_spin {
+                    self.spins[neighbor] = cluster_spin.flip();
+                    stack.push(neighbor);
+                    size += 1;
+                }
+                last = next;
+            }
+        }
+        size
+    }
+
+    /// # Translate
+    /// The site displaced from a site by the given offset.
+    fn translate(&self, site: usize, offset: &[i64; D]) -> usize {
+        let mut coordinates = self.lattice.coordinates(site).map(|c| c as i64);
+        for (coordinate, &shift) in coordinates.iter_mut().zip(offset) {
+            *coordinate += shift;
+        }
+        self.lattice.site(coordinates)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_couplings() {
+        let model = LongRangeIsing::new_constant([6], 1.0, Spin::Up).unwrap();
+        // The distances on a ring of six are 1, 2, 3, 2, 1.
+        let expected = 2.0 + 2.0 / 4.0 + 1.0 / 9.0;
+        assert!((model.coupling_sum() - expected).abs() < 1e-12);
+        assert!((model.energy(1.0) + 0.5 * expected).abs() < 1e-12);
+        assert!(LongRangeIsing::new_constant([6], 0.0, Spin::Up).is_err());
+        assert!(LongRangeIsing::<1>::new_random([0], 1.0).is_err());
+    }
+
+    #[test]
+    fn test_cluster_step_matches_enumeration() {
+        let (length, sigma, coupling) = (8, 0.8, 0.25);
+        let mut model = LongRangeIsing::new_random([length], sigma).unwrap();
+
+        // Enumerate all the states to get the exact ⟨m²⟩.
+        let (mut partition_function, mut magnetization_squared) = (0.0, 0.0);
+        for state in 0..1_u32 << length {
+            for site in 0..length {
+                model.spins[site] = if state >> site & 1 == 0 {
+                    Spin::Up
+                } else {
+                    Spin::Down
+                };
+            }
+            let weight = (-(length as f64) * model.energy(coupling)).exp();
+            partition_function += weight;
+            magnetization_squared += weight * model.magnetization().powi(2);
+        }
+        let exact = magnetization_squared / partition_function;
+
+        let temperature = Temperature::from_beta(coupling).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..1000 {
+            model.cluster_step(temperature, &mut rng);
+        }
+        let number_of_steps = 100_000;
+        let mut sampled = 0.0;
+        for _ in 0..number_of_steps {
+            model.cluster_step(temperature, &mut rng);
+            sampled += model.magnetization().powi(2);
+        }
+        sampled /= number_of_steps as f64;
+
+        assert!((sampled - exact).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_two_dimensional_order() {
+        // Far below the critical temperature the whole lattice flips as one cluster.
+        let temperature = Temperature::new(0.5).unwrap();
+        let mut model = LongRangeIsing::new_constant([8, 8], 1.0, Spin::Up).unwrap();
+        let mut rng = StdRng::seed_from_u64(2);
+        for _ in 0..10 {
+            assert_eq!(model.cluster_step(temperature, &mut rng), 64);
+        }
+    }
+}
@@ -1,29 +1,2011 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::thread;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
 use std::time::Instant;
 
-use grid::Grid;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ising_model::adaptive::{self, AdaptiveSelection};
+use ising_model::analysis::{blocked_mean, TimeSeries};
+use ising_model::bench;
+use ising_model::config::RunConfig;
+#[cfg(feature = "sqlite")]
+use ising_model::database::ResultsDatabase;
+use ising_model::dataset::{Dataset, DatasetLayout};
+#[cfg(not(target_arch = "wasm32"))]
+use ising_model::distributed;
+use ising_model::ensemble::Ensemble;
+use ising_model::error::Error;
+use ising_model::free_energy::ThermodynamicIntegration;
+use ising_model::grid::Grid;
+#[cfg(feature = "gui")]
+use ising_model::gui;
+use ising_model::invaded_cluster::{invaded_cluster_step, SpanningRule};
+#[cfg(not(target_arch = "wasm32"))]
+use ising_model::metrics::{MetricsServer, RunMetrics};
+use ising_model::output::{self, JsonLinesWriter, Observation, RunEvent};
+#[cfg(feature = "plot")]
+use ising_model::plot;
+use ising_model::qubo::{Demagnetization, IsingProblem, ProblemFormat};
+#[cfg(feature = "sqlite")]
+use ising_model::scan::ScanPoint;
+use ising_model::scan::{Scan, ScanResult};
+use ising_model::simulation::{Simulation, SimulationParameters};
+use ising_model::temperature::Temperature;
+use ising_model::time_correlation::{Representation, TimeCorrelations};
+// Trajectories are analysed through a memory map where there is one, so that they need not fit in
+// memory.
+#[cfg(not(target_arch = "wasm32"))]
+use ising_model::trajectory::MappedTrajectoryReader as TrajectoryReader;
+#[cfg(target_arch = "wasm32")]
+use ising_model::trajectory::TrajectoryReader;
+use ising_model::trajectory::TrajectoryWriter;
+use ising_model::transverse_field::TransverseFieldIsing;
+#[cfg(not(target_arch = "wasm32"))]
+use ising_model::tui;
+use ising_model::{render, spin};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use tracing::{debug, info, info_span, trace, Level};
 
-pub mod grid;
-pub mod spin;
+/// Monte Carlo simulations of the Ising model.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Without a command, runs a simulation with the default settings.
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// The most detailed log messages written to standard error: error, warn, info, debug or
+    /// trace.
+    #[arg(long, global = true, default_value = "warn", value_name = "LEVEL")]
+    log_level: Level,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Simulates a grid, writing the requested outputs along the way.
+    Run(RunArguments),
+    /// Simulates every combination of sizes, temperatures, fields and seeds in parallel.
+    Scan(ScanArguments),
+    /// Simulates independent replicas of one point in parallel and merges their observables.
+    Ensemble(EnsembleArguments),
+    /// Integrates the energy over the inverse temperature into absolute free energies.
+    FreeEnergy(FreeEnergyArguments),
+    /// Writes labeled configurations across temperatures as a dataset for phase classification.
+    Dataset(DatasetArguments),
+    /// Locates the critical coupling with invaded-cluster updates, which need no temperature.
+    InvadedCluster(InvadedClusterArguments),
+    /// Searches for the ground state of an Ising or QUBO problem file.
+    #[command(alias = "solve")]
+    Anneal(AnnealArguments),
+    /// Summarizes the observables of a saved run.
+    Analyze(AnalyzeArguments),
+    /// Draws a saved configuration or checkpoint.
+    Render(RenderArguments),
+    /// Draws the frames of a saved trajectory as images, an animation or a video.
+    Replay(ReplayArguments),
+    /// Measures how many spin updates per second the algorithms manage.
+    Bench(BenchArguments),
+    /// Simulates the points of a scan handed out by `scan --listen` on another machine.
+    #[cfg(not(target_arch = "wasm32"))]
+    Worker(WorkerArguments),
+}
+
+#[derive(Args, Default)]
+struct RunArguments {
+    /// Reads the run from a TOML or YAML file.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Overrides a field of the configuration, such as `schedule.sweeps=500`.
+    #[arg(long, value_name = "SECTION.FIELD=VALUE")]
+    set: Vec<String>,
+    /// Seeds a fresh run, so that it can be reproduced exactly.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Saves the state every 100 sweeps.
+    #[arg(long, value_name = "FILE")]
+    checkpoint: Option<String>,
+    /// Continues from the checkpoint, which defaults to checkpoint.json.
+    #[arg(long)]
+    resume: bool,
+    /// Explores the simulation interactively in the terminal.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long)]
+    tui: bool,
+    /// Opens the simulation in a window.
+    #[cfg(feature = "gui")]
+    #[arg(long)]
+    gui: bool,
+    /// Streams every 10th sweep to a dashboard in the browser at the given address.
+    #[cfg(feature = "server")]
+    #[arg(long, value_name = "ADDRESS")]
+    serve: Option<String>,
+    /// Serves the sweep rate, acceptance and observables for Prometheus at the given address.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "ADDRESS")]
+    metrics: Option<String>,
+    /// Draws the grid in the terminal every given number of sweeps.
+    #[arg(long, value_name = "SWEEPS", value_parser = clap::value_parser!(u64).range(1..))]
+    live: Option<u64>,
+    /// Colours every domain of the images and videos on its own.
+    #[arg(long)]
+    domains: bool,
+    /// Writes the observables after every sweep as CSV.
+    #[arg(long, value_name = "FILE")]
+    csv: Option<String>,
+    /// Logs the run as JSON Lines, to standard output for `-`.
+    #[arg(long, value_name = "FILE")]
+    jsonl: Option<String>,
+    /// Appends every 100th sweep as a LAMMPS dump frame, or extended XYZ for `.xyz` files.
+    #[arg(long, value_name = "FILE")]
+    dump: Option<String>,
+    /// Saves every configuration at the trajectory interval to a compact trajectory file.
+    #[arg(long, value_name = "FILE")]
+    trajectory: Option<String>,
+    /// The number of sweeps between the frames of the trajectory, 100 by default.
+    #[arg(
+        long,
+        value_name = "SWEEPS",
+        requires = "trajectory",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    trajectory_interval: Option<u64>,
+    /// Records the result of the run in an SQLite database.
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE")]
+    database: Option<String>,
+    /// Writes the observables after every sweep and every 100th configuration to an HDF5 file.
+    #[cfg(feature = "hdf5")]
+    #[arg(long, value_name = "FILE")]
+    hdf5: Option<String>,
+    /// Animates every 100th sweep as a GIF.
+    #[arg(long, value_name = "FILE")]
+    gif: Option<String>,
+    /// Streams every 10th sweep to ffmpeg as a video.
+    #[arg(long, value_name = "FILE")]
+    video: Option<String>,
+    /// Draws the final configuration as a PNG image.
+    #[arg(long, value_name = "FILE")]
+    png: Option<String>,
+    /// Draws the final configuration as SVG, with the domain walls traced.
+    #[arg(long, value_name = "FILE")]
+    svg: Option<String>,
+    /// Prints the autocorrelation function of the spins at lags up to this many sweeps, computed
+    /// while the run goes from the sweeps kept in memory.
+    #[arg(long, value_name = "SWEEPS")]
+    time_correlations: Option<usize>,
+}
+
+#[derive(Args)]
+struct ScanArguments {
+    /// The side lengths of the square grids.
+    #[arg(long, value_delimiter = ',', required = true, value_parser = clap::value_parser!(u64).range(1..))]
+    sizes: Vec<u64>,
+    /// The temperatures, each in units of the coupling or as an inverse temperature such as
+    /// `beta=0.44`.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        required = true,
+        allow_negative_numbers = true
+    )]
+    temperatures: Vec<Temperature>,
+    /// The fields, in units of the coupling.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "0",
+        allow_negative_numbers = true
+    )]
+    fields: Vec<f64>,
+    /// The crystal fields Δ = D/J of the spin-1 Blume–Capel model, by default none, which
+    /// simulates the Ising model.
+    #[arg(long, value_delimiter = ',', allow_negative_numbers = true)]
+    crystal_field: Vec<f64>,
+    /// The seeds of the independent runs at every point.
+    #[arg(long, value_delimiter = ',', default_value = "1")]
+    seeds: Vec<u64>,
+    /// The sweeps before the measurements start.
+    #[arg(long, default_value_t = 1000)]
+    thermalization: usize,
+    /// The sweeps that are measured.
+    #[arg(long, default_value_t = 10_000)]
+    measurement: usize,
+    /// The number of threads, by default one per core.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+    /// Hands the points out to workers that connect to the given address instead.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "ADDRESS", conflicts_with = "threads")]
+    listen: Option<String>,
+    /// The seconds a worker has for a point before the point goes to another worker, by
+    /// default unlimited.
+    #[cfg(not(target_arch = "wasm32"))]
+    #[arg(long, value_name = "SECONDS", requires = "listen", value_parser = clap::value_parser!(u64).range(1..))]
+    task_timeout: Option<u64>,
+    /// Writes the results as Parquet.
+    #[arg(long, value_name = "FILE")]
+    parquet: Option<PathBuf>,
+    /// Writes the time series and configurations of every point to an HDF5 file.
+    #[cfg(feature = "hdf5")]
+    #[arg(long, value_name = "FILE", conflicts_with = "listen")]
+    hdf5: Option<PathBuf>,
+    /// The measurement sweeps between the configurations written to the HDF5 file.
+    #[cfg(feature = "hdf5")]
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    snapshot_interval: u64,
+    /// Records the results in an SQLite database.
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE")]
+    database: Option<PathBuf>,
+    /// Plots |m|, e, χ, C and U₄ against the temperature, as an SVG if the file ends in .svg and
+    /// a PNG otherwise.
+    #[cfg(feature = "plot")]
+    #[arg(long, value_name = "FILE")]
+    plot: Option<PathBuf>,
+}
+
+#[derive(Args)]
+struct EnsembleArguments {
+    /// The side length of the square grid.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    size: u64,
+    /// The temperature, in units of the coupling or as an inverse temperature such as
+    /// `beta=0.44`.
+    #[arg(long)]
+    temperature: Temperature,
+    /// The field, in units of the coupling.
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    field: f64,
+    /// The number of replicas, by default one per core.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    replicas: Option<u64>,
+    /// The seed of the first replica, the others following it.
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+    /// The sweeps before the measurements start.
+    #[arg(long, default_value_t = 1000)]
+    thermalization: usize,
+    /// The sweeps that are measured.
+    #[arg(long, default_value_t = 10_000)]
+    measurement: usize,
+    /// The number of threads, by default one per core.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+}
+
+#[derive(Args)]
+struct FreeEnergyArguments {
+    /// The side length of the square grid.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    size: u64,
+    /// The temperatures, each in units of the coupling or as an inverse temperature such as
+    /// `beta=0.44`, which the integration runs through from the highest down. They have to be
+    /// closely spaced near the critical point.
+    #[arg(long, value_delimiter = ',', required = true)]
+    temperatures: Vec<Temperature>,
+    /// The field, in units of the coupling.
+    #[arg(long, default_value_t = 0.0, allow_negative_numbers = true)]
+    field: f64,
+    /// The seed of every temperature.
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+    /// The sweeps before the measurements start.
+    #[arg(long, default_value_t = 1000)]
+    thermalization: usize,
+    /// The sweeps that are measured.
+    #[arg(long, default_value_t = 10_000)]
+    measurement: usize,
+    /// The number of threads, by default one per core.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+}
+
+#[derive(Args)]
+struct DatasetArguments {
+    /// The directory the dataset is written to.
+    output: PathBuf,
+    /// The side lengths of the square grids, each written as a dataset of its own.
+    #[arg(long, value_delimiter = ',', default_value = "32", value_parser = clap::value_parser!(u64).range(1..))]
+    sizes: Vec<u64>,
+    /// The temperatures, each in units of the coupling or as an inverse temperature such as
+    /// `beta=0.44`. The configurations below the critical temperature 2.269 are labeled ordered.
+    #[arg(long, value_delimiter = ',', required = true)]
+    temperatures: Vec<Temperature>,
+    /// The configurations drawn at every size and temperature.
+    #[arg(long, default_value_t = 1000)]
+    samples: usize,
+    /// The fraction of the configurations of every temperature kept for testing.
+    #[arg(long, default_value_t = 0.2)]
+    test_fraction: f64,
+    /// The layout of the files: npz or images.
+    #[arg(long, default_value = "npz")]
+    layout: DatasetLayout,
+    /// The Swendsen–Wang updates before the first configuration.
+    #[arg(long, default_value_t = 200)]
+    thermalization: usize,
+    /// The Swendsen–Wang updates between configurations.
+    #[arg(long, default_value_t = 5)]
+    decorrelation: usize,
+    /// The seed of the first size and temperature, the others following it.
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+    /// The number of threads, by default one per core.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+}
+
+#[derive(Args)]
+struct InvadedClusterArguments {
+    /// The side length of the square grid.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    size: u64,
+    /// Stops occupying bonds once a cluster wraps along both axes rather than either.
+    #[arg(long)]
+    both_axes: bool,
+    /// The seed of the run.
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+    /// The updates before the measurements start.
+    #[arg(long, default_value_t = 100)]
+    thermalization: usize,
+    /// The updates that are measured.
+    #[arg(long, default_value_t = 1000)]
+    measurement: usize,
+    /// The bins of the histogram of the implied couplings.
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u64).range(1..))]
+    bins: u64,
+}
+
+#[derive(Args)]
+struct AnnealArguments {
+    /// The problem file, with one `i j J_ij` or `i h_i` term per line.
+    problem: PathBuf,
+    /// Reads the file as a QUBO problem over 0/1 variables.
+    #[arg(long)]
+    qubo: bool,
+    /// The sweeps of simulated annealing.
+    #[arg(long, default_value_t = 10_000)]
+    annealing_sweeps: usize,
+    /// The temperature at which simulated annealing starts and the hottest replica of parallel
+    /// tempering runs, in units of the couplings or as an inverse temperature such as `beta=0.1`.
+    #[arg(long, default_value = "10")]
+    initial_temperature: Temperature,
+    /// The temperature at which simulated annealing ends and the coldest replica of parallel
+    /// tempering runs.
+    #[arg(long, default_value = "0.1")]
+    final_temperature: Temperature,
+    /// The sweeps of parallel tempering.
+    #[arg(long, default_value_t = 5_000)]
+    tempering_sweeps: usize,
+    /// The replicas of parallel tempering, at temperatures spaced geometrically from the initial
+    /// to the final temperature.
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u64).range(1..))]
+    replicas: u64,
+    /// The sweeps of simulated quantum annealing, which is skipped if there are none.
+    #[arg(long, default_value_t = 0)]
+    quantum_sweeps: usize,
+    /// The Trotter slices of simulated quantum annealing.
+    #[arg(long, default_value_t = 16)]
+    trotter_slices: usize,
+    /// The temperature of simulated quantum annealing, held fixed while the transverse field
+    /// falls.
+    #[arg(long, default_value = "0.1")]
+    quantum_temperature: Temperature,
+    /// The field cycles of AC demagnetization, which is skipped if there are none. The energy at
+    /// the end of every cycle is printed.
+    #[arg(long, default_value_t = 0)]
+    demagnetization_cycles: usize,
+    /// The sweeps of one field cycle of AC demagnetization.
+    #[arg(long, default_value_t = 20, value_parser = clap::value_parser!(u64).range(1..))]
+    demagnetization_period: u64,
+    /// The temperature of AC demagnetization, held fixed while the field cycles.
+    #[arg(long, default_value = "0.1")]
+    demagnetization_temperature: Temperature,
+    /// Seeds the search, so that it can be reproduced.
+    #[arg(long)]
+    seed: Option<u64>,
+}
+
+#[derive(Args)]
+struct AnalyzeArguments {
+    /// A CSV file of observables written by `run --csv`, a log written by `run --jsonl`, or any
+    /// other file as a trajectory.
+    input: PathBuf,
+    /// The measurements at the start that are discarded as thermalization.
+    #[arg(long, default_value_t = 0)]
+    skip: usize,
+    /// The reduced coupling of the run, for a trajectory written before trajectories recorded it.
+    #[arg(long, value_parser = positive)]
+    coupling: Option<f64>,
+    /// The reduced field of the run, for a trajectory written before trajectories recorded it.
+    #[arg(long, allow_negative_numbers = true)]
+    field: Option<f64>,
+    /// Reweights the measurements to these temperatures at the same field, each in units of the
+    /// coupling or as an inverse temperature such as `beta=0.44`.
+    #[arg(long, value_delimiter = ',')]
+    reweight: Vec<Temperature>,
+}
+
+#[derive(Args)]
+struct RenderArguments {
+    /// A configuration saved by `Grid::save`, or a checkpoint ending in `.json`.
+    input: PathBuf,
+    /// Draws it as a PNG image.
+    #[arg(long, value_name = "FILE")]
+    png: Option<String>,
+    /// Draws it as SVG, with the domain walls traced.
+    #[arg(long, value_name = "FILE")]
+    svg: Option<String>,
+    /// Draws it in the terminal.
+    #[arg(long)]
+    terminal: bool,
+    /// Colours every domain on its own.
+    #[arg(long)]
+    domains: bool,
+    /// The number of pixels per spin.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    scale: u32,
+}
+
+#[derive(Args)]
+struct ReplayArguments {
+    /// A trajectory written by `TrajectoryWriter`.
+    trajectory: PathBuf,
+    /// Draws every frame as a PNG image, named by replacing `{}` in the pattern with the sweep of
+    /// the frame padded to eight digits, such as `frame-{}.png`.
+    #[arg(long, value_name = "PATTERN")]
+    png: Option<String>,
+    /// Draws the frames as an animated GIF.
+    #[arg(long, value_name = "FILE")]
+    gif: Option<String>,
+    /// Streams the frames to ffmpeg as a video.
+    #[arg(long, value_name = "FILE")]
+    video: Option<String>,
+    /// The colours of the spins.
+    #[arg(long, value_enum, default_value_t = PaletteChoice::BlackWhite)]
+    palette: PaletteChoice,
+    /// Colours every domain on its own, following the domains from frame to frame.
+    #[arg(long)]
+    domains: bool,
+    /// The number of pixels per spin.
+    #[arg(long, default_value_t = 4, value_parser = clap::value_parser!(u32).range(1..))]
+    scale: u32,
+    /// The frames per second of the animation and the video.
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    fps: u32,
+    /// The frames at the start that are left out.
+    #[arg(long, default_value_t = 0)]
+    skip: usize,
+    /// Draws only every n-th frame.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    every: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Args)]
+struct WorkerArguments {
+    /// The address the coordinator listens on.
+    #[arg(long, value_name = "ADDRESS")]
+    connect: String,
+    /// The number of points simulated at once, by default one per core.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Option<u64>,
+}
+
+#[derive(Args)]
+struct BenchArguments {
+    /// The side lengths of the square grids.
+    #[arg(long, value_delimiter = ',', default_value = "32,128,512", value_parser = clap::value_parser!(u64).range(1..))]
+    sizes: Vec<u64>,
+    /// The algorithms: metropolis, hypercubic and swendsen-wang.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        default_value = "metropolis,hypercubic,swendsen-wang"
+    )]
+    algorithms: Vec<bench::Algorithm>,
+    /// The numbers of threads, each updating a grid of its own, by default one and one per core.
+    #[arg(long, value_delimiter = ',', value_parser = clap::value_parser!(u64).range(1..))]
+    threads: Vec<u64>,
+    /// The timed sweeps of every grid.
+    #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+    sweeps: u64,
+    /// The reduced coupling, by default close to the critical one.
+    #[arg(long, default_value_t = 0.44, allow_negative_numbers = true)]
+    coupling: f64,
+}
+
+/// The colours that the spins can be drawn with.
+#[derive(Clone, Copy, ValueEnum)]
+enum PaletteChoice {
+    /// Up spins white and down spins black.
+    BlackWhite,
+    /// Up spins red and down spins blue.
+    BlueRed,
+}
+
+impl PaletteChoice {
+    fn palette(self) -> render::Palette {
+        match self {
+            Self::BlackWhite => render::Palette::default(),
+            Self::BlueRed => render::Palette::blue_red(),
+        }
+    }
+}
+
+/// Parses a positive number.
+fn positive(text: &str) -> Result<f64, String> {
+    match text.parse::<f64>() {
+        Ok(value) if value > 0.0 => Ok(value),
+        Ok(_) => Err("must be positive".to_string()),
+        Err(error) => Err(error.to_string()),
+    }
+}
+
+/// # Write a dataset
+/// Generates the configurations of a dataset and writes them in the requested layout.
+fn dataset(arguments: DatasetArguments) {
+    let dataset = Dataset {
+        sizes: arguments.sizes.iter().map(|&size| size as usize).collect(),
+        temperatures: arguments.temperatures,
+        samples: arguments.samples,
+        thermalization_sweeps: arguments.thermalization,
+        decorrelation_sweeps: arguments.decorrelation,
+        test_fraction: arguments.test_fraction,
+        seed: arguments.seed,
+    };
+    let cores = thread::available_parallelism().map_or(1, usize::from);
+    let threads = arguments.threads.map_or(cores, |threads| threads as usize);
+    if let Err(error) = dataset.write(&arguments.output, arguments.layout, threads) {
+        eprintln!("could not write the dataset: {}", error);
+        std::process::exit(1);
+    }
+    println!(
+        "Wrote {} configurations to {}",
+        dataset.sizes.len() * dataset.temperatures.len() * dataset.samples,
+        arguments.output.display()
+    );
+}
+
+/// # Invaded-cluster run
+/// Runs invaded-cluster updates from an ordered grid and prints the mean of the couplings they
+/// imply, the critical temperature that follows and the histogram of the couplings.
+fn invaded_cluster(arguments: InvadedClusterArguments) {
+    let size = arguments.size as usize;
+    let rule = if arguments.both_axes {
+        SpanningRule::BothAxes
+    } else {
+        SpanningRule::EitherAxis
+    };
+    let mut rng = StdRng::seed_from_u64(arguments.seed);
+    let mut grid = Grid::new_constant(size, size, spin::Spin::Up).unwrap_or_else(|error| {
+        eprintln!("Invalid grid: {}", error);
+        std::process::exit(2);
+    });
+    let mut step = || {
+        invaded_cluster_step(&mut grid, rule, &mut rng)
+            .expect("a new grid is periodic with uniform couplings")
+    };
+    for _ in 0..arguments.thermalization {
+        step();
+    }
+    let couplings: Vec<f64> = (0..arguments.measurement)
+        .map(|_| step().implied_coupling())
+        .collect();
+    if couplings.is_empty() {
+        eprintln!("there are no measurements");
+        std::process::exit(2);
+    }
+
+    let coupling = blocked_mean(&couplings);
+    println!("Critical coupling: {}", coupling);
+    println!("Critical temperature: {}", coupling.powi(-1));
+    let low = couplings.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = couplings.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let bins = arguments.bins as usize;
+    let width = (high - low).max(f64::EPSILON) / bins as f64;
+    let mut counts = vec![0_usize; bins];
+    for &coupling in &couplings {
+        counts[(((coupling - low) / width) as usize).min(bins - 1)] += 1;
+    }
+    println!("coupling\tdensity");
+    for (bin, count) in counts.iter().enumerate() {
+        let density = *count as f64 / (couplings.len() as f64 * width);
+        println!("{:.5}\t{:.3}", low + (bin as f64 + 0.5) * width, density);
+    }
+}
+
+/// # Solve a problem file
+/// Runs simulated annealing and parallel tempering on an Ising or QUBO problem file and prints the
+/// best energy and configuration found.
+fn anneal(arguments: AnnealArguments) {
+    let format = if arguments.qubo {
+        ProblemFormat::Qubo
+    } else {
+        ProblemFormat::Ising
+    };
+    let path = &arguments.problem;
+    let problem = match IsingProblem::load(path, format) {
+        Ok(problem) => problem,
+        Err(error) => {
+            eprintln!("could not read {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    };
+
+    let mut rng = match arguments.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    let (initial, last) = (arguments.initial_temperature, arguments.final_temperature);
+    let annealed = problem.simulated_annealing(initial, last, arguments.annealing_sweeps, &mut rng);
+    let ratio = last.value() / initial.value();
+    let spacing = (arguments.replicas.max(2) - 1) as f64;
+    let temperatures: Vec<Temperature> = (0..arguments.replicas)
+        .map(|k| {
+            Temperature::new(initial.value() * ratio.powf(k as f64 / spacing))
+                .expect("the ladder lies between two temperatures")
+        })
+        .collect();
+    let tempered = problem
+        .parallel_tempering(&temperatures, arguments.tempering_sweeps, &mut rng)
+        .unwrap_or_else(|error| {
+            eprintln!("Invalid replicas: {}", error);
+            std::process::exit(2);
+        });
+    println!("Simulated annealing: {}", annealed.energy);
+    println!("Parallel tempering: {}", tempered.energy);
+    let mut solutions = vec![annealed, tempered];
+    if arguments.demagnetization_cycles > 0 {
+        let schedule = Demagnetization {
+            temperature: arguments.demagnetization_temperature,
+            amplitude: problem.saturation_field(),
+            period: arguments.demagnetization_period as usize,
+            cycles: arguments.demagnetization_cycles,
+        };
+        let (demagnetized, trajectory) =
+            problem
+                .demagnetize(&schedule, &mut rng)
+                .unwrap_or_else(|error| {
+                    eprintln!("Invalid demagnetization schedule: {}", error);
+                    std::process::exit(2);
+                });
+        println!("cycle\tamplitude\tenergy");
+        for (cycle, energies) in trajectory.chunks(schedule.period).enumerate() {
+            let amplitude = schedule.amplitude * (1.0 - cycle as f64 / schedule.cycles as f64);
+            println!(
+                "{}\t{}\t{}",
+                cycle + 1,
+                amplitude,
+                energies[energies.len() - 1]
+            );
+        }
+        println!("AC demagnetization: {}", demagnetized.energy);
+        solutions.push(demagnetized);
+    }
+    if arguments.quantum_sweeps > 0 {
+        let model = match TransverseFieldIsing::new(problem, arguments.trotter_slices) {
+            Ok(model) => model,
+            Err(error) => {
+                eprintln!("{}", error);
+                std::process::exit(2);
+            }
+        };
+        let quantum = model
+            .simulated_quantum_annealing(
+                arguments.quantum_temperature,
+                3.0,
+                0.01,
+                arguments.quantum_sweeps,
+                &mut rng,
+            )
+            .expect("the transverse fields are positive");
+        println!("Simulated quantum annealing: {}", quantum.energy);
+        solutions.push(quantum);
+    }
+
+    // The first of the solvers wins a tie.
+    let best = solutions
+        .into_iter()
+        .reduce(|best, solution| {
+            if solution.energy < best.energy {
+                solution
+            } else {
+                best
+            }
+        })
+        .unwrap();
+    let configuration: Vec<String> = match format {
+        ProblemFormat::Qubo => best.bits().iter().map(u8::to_string).collect(),
+        ProblemFormat::Ising => best
+            .spins
+            .iter()
+            .map(|&spin| if spin == spin::Spin::Up { "1" } else { "-1" }.to_string())
+            .collect(),
+    };
+    println!("Best energy: {}", best.energy);
+    println!("Best configuration: {}", configuration.join(" "));
+}
+
+/// # Emit a run event
+/// Writes the event to the JSON Lines log, if there is one.
+fn emit(log: &mut Option<JsonLinesWriter<Box<dyn Write>>>, event: RunEvent) {
+    if let Some(log) = log {
+        if let Err(error) = log.write(&event) {
+            eprintln!("could not write the event log: {}", error);
+        }
+    }
+}
+
+/// # Create an output
+/// Creates an output file, replacing any existing one, or when resuming opens it to append to
+/// what the run before wrote.
+fn create_output(path: &str, resume: bool) -> io::Result<File> {
+    if resume {
+        OpenOptions::new().create(true).append(true).open(path)
+    } else {
+        File::create(path)
+    }
+}
+
+/// # Save a checkpoint
+/// Saves the state of the run together with the lengths of the outputs a resumed run appends to,
+/// and logs it, or reports why it could not be saved.
+fn save_checkpoint(
+    simulation: &Simulation,
+    path: &str,
+    outputs: &[(&str, String)],
+    log: &mut Option<JsonLinesWriter<Box<dyn Write>>>,
+) {
+    let lengths = outputs
+        .iter()
+        .map(|(name, path)| Ok((name.to_string(), fs::metadata(path)?.len())))
+        .collect::<io::Result<BTreeMap<_, _>>>();
+    match lengths.and_then(|lengths| simulation.save_checkpoint_with_outputs(path, &lengths)) {
+        Ok(()) => emit(
+            log,
+            RunEvent::Checkpoint {
+                sweep: simulation.sweep(),
+                path: path.to_string(),
+            },
+        ),
+        Err(error) => eprintln!("could not write the checkpoint {}: {}", path, error),
+    }
+}
 
 fn main() {
-    // Defining initial values.
-    let size_of_the_square_matrix = 100;
-    let coupling_between_neighboring_spins = 0.44;
-    let applied_field = 0.02;
-    let number_of_sweeps = 7000;
+    let cli = Cli::parse();
+    tracing_subscriber::fmt()
+        .with_max_level(cli.log_level)
+        .with_writer(io::stderr)
+        .init();
+    match cli.command {
+        None => run(RunArguments::default()),
+        Some(Command::Run(arguments)) => run(arguments),
+        Some(Command::Scan(arguments)) => scan(arguments),
+        Some(Command::Ensemble(arguments)) => ensemble(arguments),
+        Some(Command::FreeEnergy(arguments)) => free_energy(arguments),
+        Some(Command::Dataset(arguments)) => dataset(arguments),
+        Some(Command::InvadedCluster(arguments)) => invaded_cluster(arguments),
+        Some(Command::Anneal(arguments)) => anneal(arguments),
+        Some(Command::Analyze(arguments)) => analyze(arguments),
+        Some(Command::Render(arguments)) => render(arguments),
+        Some(Command::Replay(arguments)) => replay(arguments),
+        Some(Command::Bench(arguments)) => bench(arguments),
+        #[cfg(not(target_arch = "wasm32"))]
+        Some(Command::Worker(arguments)) => worker(arguments),
+    }
+}
+
+/// # Run a simulation
+/// Simulates the configured grid, writing the requested outputs along the way.
+fn run(arguments: RunArguments) {
+    let mut config = arguments
+        .config
+        .as_ref()
+        .map_or_else(RunConfig::default, |path| {
+            RunConfig::load(path).unwrap_or_else(|error| {
+                eprintln!(
+                    "could not read the configuration {}: {}",
+                    path.display(),
+                    error
+                );
+                std::process::exit(2);
+            })
+        });
+    for assignment in &arguments.set {
+        if let Err(error) = config.set(assignment) {
+            eprintln!("could not set {}: {}", assignment, error);
+            std::process::exit(2);
+        }
+    }
+    // An output given on the command line takes precedence over the configured one.
+    let output = |given: &Option<String>, name: &str| {
+        given
+            .clone()
+            .or_else(|| config.outputs.get(name).map(str::to_string))
+    };
+    let number_of_sweeps = config.schedule.sweeps;
+
+    // The state is saved every 100 sweeps and at the end to the checkpoint, which `--resume`
+    // continues from, appending to the outputs of the run before.
+    let resume = arguments.resume;
+    let quiet = output(&arguments.jsonl, "jsonl").as_deref() == Some("-");
+    let checkpoint = output(&arguments.checkpoint, "checkpoint")
+        .or_else(|| resume.then(|| "checkpoint.json".to_string()));
+
+    // A fresh run with a given seed is reproduced exactly, starting like a point of a scan.
+    let seed = arguments
+        .seed
+        .or(config.schedule.seed)
+        .unwrap_or_else(rand::random);
+
+    // The outputs a resumed run appends to. Their lengths are saved with every checkpoint, and a
+    // resumed run cuts them back to those, so that the sweeps after the checkpoint of a killed run
+    // are not written twice.
+    let appended: Vec<(&str, String)> = [
+        ("csv", &arguments.csv),
+        ("dump", &arguments.dump),
+        ("jsonl", &arguments.jsonl),
+        ("trajectory", &arguments.trajectory),
+    ]
+    .into_iter()
+    .filter_map(|(name, given)| output(given, name).map(|path| (name, path)))
+    .filter(|(_, path)| path != "-")
+    .collect();
+
+    // Create a new grid with random spins, or pick up the saved run.
+    let mut simulation = if resume {
+        let path = checkpoint.as_deref().unwrap();
+        match Simulation::load_checkpoint(path) {
+            Ok(simulation) => {
+                if !quiet {
+                    println!("Resuming from sweep {} of {}", simulation.sweep(), path);
+                }
+                let lengths = Simulation::load_checkpoint_outputs(path).unwrap_or_else(|error| {
+                    eprintln!("could not resume from {}: {}", path, error);
+                    std::process::exit(1);
+                });
+                for (name, output) in &appended {
+                    if let Some(&length) = lengths.get(*name) {
+                        if let Err(error) = output::truncate(output, length) {
+                            eprintln!("could not truncate {}: {}", output, error);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                simulation
+            }
+            Err(error) => {
+                eprintln!("could not resume from {}: {}", path, error);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let (width, height) = (config.lattice.width, config.lattice.height);
+        let mut rng = StdRng::seed_from_u64(seed);
+        Grid::new_with_magnetization(width, height, 0.0, &mut rng)
+            .and_then(|grid| {
+                Simulation::with_seed(
+                    grid,
+                    SimulationParameters {
+                        coupling: config.model.coupling,
+                        field: config.model.field,
+                    },
+                    seed,
+                )
+            })
+            .unwrap_or_else(|error| {
+                eprintln!("invalid configuration: {}", error);
+                std::process::exit(2);
+            })
+    };
+
+    // The configured algorithm sets the schedule of a fresh run, and `adaptive` picks it during a
+    // warm-up. A resumed run keeps the schedule of its checkpoint.
+    if !resume {
+        if config.algorithm == adaptive::ALGORITHM {
+            let selection = simulation.adapt(&AdaptiveSelection::default());
+            info!(schedule = %selection.chosen, "adapted the schedule");
+            if !quiet {
+                println!("{}", selection);
+            }
+        } else {
+            let schedule = config
+                .algorithm
+                .parse()
+                .expect("the configuration was validated");
+            if let Err(error) = simulation.set_schedule(schedule) {
+                eprintln!("invalid configuration: {}", error);
+                std::process::exit(2);
+            }
+        }
+    }
+
+    // With `--tui` the run becomes interactive, and the checkpoint is written when it is quit.
+    #[cfg(not(target_arch = "wasm32"))]
+    if arguments.tui {
+        let simulation = tui::explore(simulation).unwrap_or_else(|error| {
+            eprintln!("the terminal interface failed: {}", error);
+            std::process::exit(1);
+        });
+        if let Some(path) = &checkpoint {
+            if let Err(error) = simulation.save_checkpoint(path) {
+                eprintln!("could not write the checkpoint {}: {}", path, error);
+            }
+        }
+        return;
+    }
+
+    // With `--gui` the simulation opens in a window instead, when built with the gui feature.
+    #[cfg(feature = "gui")]
+    if arguments.gui {
+        if let Err(error) = gui::run(simulation) {
+            eprintln!("the viewer failed: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // With `--domains` the images and videos show every domain in a colour of its own.
+    let domains = arguments.domains;
+
+    // With `--gif <file>` every 100th sweep becomes a frame of an animation.
+    let mut recorder = output(&arguments.gif, "gif").map(|path| {
+        let mut recorder =
+            render::GifRecorder::create(&path, render::Palette::default(), 2, 100, 10)
+                .unwrap_or_else(|error| {
+                    eprintln!("could not create the animation {}: {}", path, error);
+                    std::process::exit(1);
+                });
+        recorder.set_domain_coloring(domains.then(render::DomainColoring::new));
+        recorder
+    });
+
+    // With `--video <file>` every 10th sweep is streamed to ffmpeg, for an MP4 or WebM video.
+    let mut video = output(&arguments.video, "video").map(|path| {
+        let grid = simulation.grid();
+        let mut video = render::VideoRecorder::create(
+            &path,
+            grid.width(),
+            grid.height(),
+            render::Palette::default(),
+            2,
+            10,
+            30,
+        )
+        .unwrap_or_else(|error| {
+            eprintln!("could not start ffmpeg for {}: {}", path, error);
+            std::process::exit(1);
+        });
+        video.set_domain_coloring(domains.then(render::DomainColoring::new));
+        video
+    });
+
+    // With `--live <n>` the grid is drawn in the terminal every n sweeps, two rows to a line.
+    let live = arguments.live.map(|interval| interval as usize);
+    if live.is_some() {
+        print!("\x1b[2J");
+    }
+
+    // With `--serve <address>` every 10th sweep is streamed to a dashboard in the browser, when
+    // built with the server feature.
+    #[cfg(feature = "server")]
+    let dashboard = arguments.serve.as_ref().map(|address| {
+        let dashboard = ising_model::dashboard::Dashboard::bind(address).unwrap_or_else(|error| {
+            eprintln!("could not serve the dashboard on {}: {}", address, error);
+            std::process::exit(1);
+        });
+        println!("Dashboard at http://{}", dashboard.address());
+        dashboard
+    });
+
+    // With `--metrics <address>` the state of the run is updated every 10th sweep for Prometheus
+    // to scrape from /metrics.
+    #[cfg(not(target_arch = "wasm32"))]
+    let metrics = arguments.metrics.as_ref().map(|address| {
+        let server = MetricsServer::bind(address).unwrap_or_else(|error| {
+            eprintln!("could not serve the metrics on {}: {}", address, error);
+            std::process::exit(1);
+        });
+        if !quiet {
+            println!("Metrics at http://{}/metrics", server.address());
+        }
+        server
+    });
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut last_update = (simulation.sweep(), Instant::now());
+
+    // With `--csv <file>` the observables after every sweep are written as rows of a table. A
+    // resumed run adds its rows to those of the run it continues.
+    let mut csv = output(&arguments.csv, "csv").map(|path| {
+        let provenance = simulation.provenance();
+        if resume {
+            output::CsvWriter::append(&path, &provenance)
+        } else {
+            output::CsvWriter::create(&path, &provenance)
+        }
+        .unwrap_or_else(|error| {
+            eprintln!("could not create {}: {}", path, error);
+            std::process::exit(1);
+        })
+    });
+
+    // With `--dump <file>` every 100th sweep is appended as a frame for OVITO or VMD, in the
+    // extended XYZ format if the file ends in `.xyz` and as a LAMMPS dump otherwise.
+    let mut dump = output(&arguments.dump, "dump").map(|path| {
+        let file = create_output(&path, resume).unwrap_or_else(|error| {
+            eprintln!("could not create {}: {}", path, error);
+            std::process::exit(1);
+        });
+        (
+            BufWriter::new(file),
+            path.ends_with(".xyz"),
+            simulation.provenance(),
+        )
+    });
+
+    // With `--trajectory <file>` the configuration every `--trajectory-interval` sweeps, 100 by
+    // default, is saved for `analyze` and `replay`. A resumed run adds its frames to those of the
+    // run it continues.
+    let trajectory_interval = arguments.trajectory_interval.unwrap_or(100) as usize;
+    let mut trajectory = output(&arguments.trajectory, "trajectory").map(|path| {
+        let grid = simulation.grid();
+        let provenance = simulation.provenance();
+        if resume {
+            TrajectoryWriter::append(&path, grid.width(), grid.height(), &provenance)
+        } else {
+            TrajectoryWriter::create(&path, grid.width(), grid.height(), &provenance)
+        }
+        .unwrap_or_else(|error| {
+            eprintln!("could not create {}: {}", path, error);
+            std::process::exit(1);
+        })
+    });
+
+    // With `--database <file>` the result of a fresh run is recorded in an SQLite database, when
+    // built with the sqlite feature.
+    #[cfg(feature = "sqlite")]
+    let database = output(&arguments.database, "database").map(|path| {
+        if resume {
+            eprintln!("a resumed run cannot be recorded, as its seed is not known");
+            std::process::exit(2);
+        }
+        if config.lattice.width != config.lattice.height {
+            eprintln!("only runs on square lattices can be recorded");
+            std::process::exit(2);
+        }
+        if Temperature::from_beta(config.model.coupling).is_err() {
+            eprintln!("only runs with a positive coupling can be recorded");
+            std::process::exit(2);
+        }
+        ResultsDatabase::open(&path).unwrap_or_else(|error| {
+            eprintln!("could not open the database {}: {}", path, error);
+            std::process::exit(1);
+        })
+    });
+    #[cfg(feature = "sqlite")]
+    let mut observations = Vec::new();
+
+    // With `--jsonl <file>` the run is logged as JSON Lines, one event per line. With `--jsonl -`
+    // the log goes to standard output, which then carries nothing else.
+    let mut log = output(&arguments.jsonl, "jsonl").map(|path| {
+        let writer: Box<dyn Write> = if path == "-" {
+            Box::new(io::stdout())
+        } else {
+            match create_output(&path, resume) {
+                Ok(file) => Box::new(BufWriter::new(file)),
+                Err(error) => {
+                    eprintln!("could not create {}: {}", path, error);
+                    std::process::exit(1);
+                }
+            }
+        };
+        JsonLinesWriter::new(writer)
+    });
 
-    // Create a new grid with random spins.
-    let mut grid = Grid::new_random(size_of_the_square_matrix, size_of_the_square_matrix);
+    // With `--hdf5 <file>` the observables after every sweep and the configuration every 100
+    // sweeps are written to an HDF5 file as one parameter point, when built with the hdf5 feature.
+    #[cfg(feature = "hdf5")]
+    let mut hdf5 = output(&arguments.hdf5, "hdf5").map(|path| {
+        let provenance = simulation.provenance();
+        let writer =
+            ising_model::output::Hdf5Writer::create(&path, &provenance).unwrap_or_else(|error| {
+                eprintln!("could not create {}: {}", path, error);
+                std::process::exit(1);
+            });
+        let grid = simulation.grid();
+        let parameters = simulation.parameters();
+        let point = ising_model::output::Hdf5Point::new(
+            grid.width(),
+            grid.height(),
+            &[
+                ("coupling", parameters.coupling),
+                ("field", parameters.field),
+            ],
+        );
+        (path, writer, point, provenance)
+    });
+    #[cfg(feature = "hdf5")]
+    let recording = hdf5.is_some();
+    #[cfg(not(feature = "hdf5"))]
+    let recording = false;
+
+    let _span = info_span!(
+        "run",
+        width = simulation.grid().width(),
+        height = simulation.grid().height(),
+        seed = ?simulation.seed()
+    )
+    .entered();
+    info!(
+        sweep = simulation.sweep(),
+        sweeps = number_of_sweeps,
+        coupling = simulation.parameters().coupling,
+        field = simulation.parameters().field,
+        "starting run"
+    );
+    emit(
+        &mut log,
+        RunEvent::Started {
+            width: simulation.grid().width(),
+            height: simulation.grid().height(),
+            coupling: simulation.parameters().coupling,
+            field: simulation.parameters().field,
+            sweeps: number_of_sweeps,
+            provenance: simulation.provenance(),
+        },
+    );
+
+    // With `--time-correlations <lag>` the last sweeps are kept to correlate every new one with.
+    let mut correlations = arguments.time_correlations.map(|max_lag| {
+        let grid = simulation.grid();
+        TimeCorrelations::new(
+            grid.width(),
+            grid.height(),
+            max_lag,
+            Representation::Configurations,
+        )
+    });
 
     // Start the timer
     let start = Instant::now();
-    for step in 0..number_of_sweeps {
-        if step % 100 == 0 {
-            println!("Sweep number: {}", step);
+    let mut batch = (simulation.sweep(), Instant::now());
+    while simulation.sweep() < number_of_sweeps {
+        if let Some(recorder) = &mut recorder {
+            if let Err(error) = recorder.record(simulation.sweep(), simulation.grid()) {
+                eprintln!("could not record a frame: {}", error);
+            }
+        }
+        if let Some(video) = &mut video {
+            if let Err(error) = video.record(simulation.sweep(), simulation.grid()) {
+                eprintln!("could not stream a frame: {}", error);
+            }
+        }
+        if let Some(interval) = live {
+            if simulation.sweep().is_multiple_of(interval) {
+                let palette = render::Palette::default();
+                print!("\x1b[H{}", render::to_terminal(simulation.grid(), &palette));
+            }
+        }
+        if simulation.sweep() % 100 == 0 {
+            if !quiet {
+                println!("Sweep number: {}", simulation.sweep());
+            }
+            if simulation.sweep() > batch.0 {
+                let sweeps = simulation.sweep() - batch.0;
+                debug!(
+                    sweep = simulation.sweep(),
+                    sweeps_per_second = sweeps as f64 / batch.1.elapsed().as_secs_f64(),
+                    mean_absolute_magnetization = simulation.moments().mean_absolute(),
+                    "finished sweep batch"
+                );
+                batch = (simulation.sweep(), Instant::now());
+            }
+            if let Some(csv) = &mut csv {
+                if let Err(error) = csv.flush() {
+                    eprintln!("could not write the observables: {}", error);
+                }
+            }
+            if let Some(trajectory) = &mut trajectory {
+                if let Err(error) = trajectory.flush() {
+                    eprintln!("could not write the trajectory: {}", error);
+                }
+            }
+            if let Some(path) = &checkpoint {
+                save_checkpoint(&simulation, path, &appended, &mut log);
+            }
+            #[cfg(feature = "hdf5")]
+            if let Some((_, _, point, _)) = &mut hdf5 {
+                point.save_configuration(simulation.grid());
+            }
+            if let Some((writer, xyz, provenance)) = &mut dump {
+                let (sweep, grid) = (simulation.sweep(), simulation.grid());
+                let result = if *xyz {
+                    output::write_xyz(writer, sweep, grid, provenance)
+                } else {
+                    output::write_lammps_dump(writer, sweep, grid, provenance)
+                };
+                if let Err(error) = result.and_then(|()| writer.flush()) {
+                    eprintln!("could not write the snapshot: {}", error);
+                }
+            }
+        }
+        if let Some(trajectory) = &mut trajectory {
+            if simulation.sweep().is_multiple_of(trajectory_interval) {
+                let (sweep, grid) = (simulation.sweep(), simulation.grid());
+                if let Err(error) = trajectory.write(sweep as u64, grid) {
+                    eprintln!("could not write the trajectory: {}", error);
+                }
+            }
+        }
+        let acceptance = simulation.step();
+        let magnetization = simulation.measure();
+        if let Some(correlations) = &mut correlations {
+            correlations
+                .push(simulation.grid())
+                .expect("the grid keeps its size");
+        }
+        trace!(
+            sweep = simulation.sweep(),
+            magnetization,
+            acceptance,
+            "measured"
+        );
+        #[cfg(feature = "sqlite")]
+        if database.is_some() {
+            observations.push(Observation::of(&simulation, acceptance));
+        }
+        if csv.is_some() || log.is_some() || recording {
+            let observation = Observation::of(&simulation, acceptance);
+            if let Some(csv) = &mut csv {
+                if let Err(error) = csv.write(&observation) {
+                    eprintln!("could not write the observables: {}", error);
+                }
+            }
+            #[cfg(feature = "hdf5")]
+            if let Some((_, _, point, _)) = &mut hdf5 {
+                point.observations.push(observation);
+            }
+            emit(&mut log, RunEvent::Measurement(observation));
+        }
+        #[cfg(feature = "server")]
+        if let Some(dashboard) = &dashboard {
+            if simulation.sweep().is_multiple_of(10) {
+                let observables = [("magnetization", magnetization), ("acceptance", acceptance)];
+                dashboard.publish(simulation.sweep(), simulation.grid(), &observables);
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(server) = &metrics {
+            if simulation.sweep().is_multiple_of(10) || simulation.sweep() == number_of_sweeps {
+                let sweeps = simulation.sweep() - last_update.0;
+                server.update(RunMetrics {
+                    sweep: simulation.sweep(),
+                    sweeps: number_of_sweeps,
+                    sweeps_per_second: sweeps as f64 / last_update.1.elapsed().as_secs_f64(),
+                    acceptance,
+                    coupling: simulation.parameters().coupling,
+                    field: simulation.parameters().field,
+                    energy: Observation::of(&simulation, acceptance).energy,
+                    magnetization,
+                    mean_absolute_magnetization: simulation.moments().mean_absolute(),
+                });
+                last_update = (simulation.sweep(), Instant::now());
+            }
+        }
+        #[cfg(not(feature = "server"))]
+        let _ = magnetization;
+    }
+    // The last sweeps are saved too, so that a finished run can be extended.
+    if let Some(csv) = &mut csv {
+        if let Err(error) = csv.flush() {
+            eprintln!("could not write the observables: {}", error);
+        }
+    }
+    if let Some(trajectory) = &mut trajectory {
+        if let Err(error) = trajectory.flush() {
+            eprintln!("could not write the trajectory: {}", error);
+        }
+    }
+    if let Some(path) = &checkpoint {
+        save_checkpoint(&simulation, path, &appended, &mut log);
+    }
+    // The index goes after the checkpoint, which a resumed run cuts the trajectory back to.
+    if let Some(trajectory) = trajectory {
+        if let Err(error) = trajectory.finish() {
+            eprintln!("could not write the trajectory: {}", error);
+        }
+    }
+
+    info!(
+        sweep = simulation.sweep(),
+        elapsed_seconds = start.elapsed().as_secs_f64(),
+        mean_absolute_magnetization = simulation.moments().mean_absolute(),
+        "finished run"
+    );
+    emit(
+        &mut log,
+        RunEvent::Finished {
+            sweep: simulation.sweep(),
+            elapsed_seconds: start.elapsed().as_secs_f64(),
+            mean_absolute_magnetization: simulation.moments().mean_absolute(),
+        },
+    );
+    if !quiet {
+        // A summary rather than the spins, which would flood the terminal on large grids.
+        let grid = simulation.grid();
+        let observation = Observation::of(&simulation, 0.0);
+        println!(
+            "Final configuration: {} × {} after {} sweeps, energy per site {}, magnetization per \
+             site {}",
+            grid.width(),
+            grid.height(),
+            simulation.sweep(),
+            observation.energy,
+            observation.magnetization
+        );
+        println!(
+            "Mean absolute magnetization: {}",
+            simulation.moments().mean_absolute()
+        );
+        println!("Elapsed time: {:?}", start.elapsed());
+        if let Some(estimators) = simulation.cluster_estimators() {
+            if let Ok(temperature) = Temperature::from_beta(simulation.parameters().coupling) {
+                println!(
+                    "N⟨m²⟩/T: {} (conventional), {} (improved)",
+                    estimators.susceptibility(temperature),
+                    estimators.improved_susceptibility(temperature)
+                );
+            }
+            println!("Spin correlation by distance, conventional and improved:");
+            for distance in 0..=estimators.max_distance() {
+                println!(
+                    "{} {} {}",
+                    distance,
+                    estimators.correlation(distance),
+                    estimators.improved_correlation(distance)
+                );
+            }
+        }
+        if let Some(correlations) = &correlations {
+            println!("Spin autocorrelation by lag in sweeps:");
+            for (lag, value) in correlations.autocorrelation().iter().enumerate() {
+                println!("{} {}", lag, value);
+            }
+        }
+    }
+
+    #[cfg(feature = "hdf5")]
+    if let Some((path, mut writer, point, provenance)) = hdf5 {
+        if let Err(error) = writer.write(&point, &provenance) {
+            eprintln!("could not write {}: {}", path, error);
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let Some(database) = &database {
+        let parameters = simulation.parameters();
+        let point = ScanPoint {
+            size: simulation.grid().width(),
+            temperature: Temperature::from_beta(parameters.coupling)
+                .expect("the coupling was checked to be positive"),
+            field: parameters.field / parameters.coupling,
+            crystal_field: simulation.grid().crystal_field_ratio(),
+            seed,
+        };
+        let result = ScanResult::from_observations(point, &observations);
+        let elapsed = start.elapsed().as_secs_f64();
+        let provenance = simulation.provenance();
+        if let Err(error) = database.record(&result, observations.len(), elapsed, &provenance) {
+            eprintln!("could not record the run: {}", error);
+        }
+    }
+
+    if let Some(video) = video {
+        if let Err(error) = video.finish() {
+            eprintln!("could not finish the video: {}", error);
+        }
+    }
+
+    // The final configuration is also drawn, four pixels to a spin.
+    draw(
+        simulation.grid(),
+        output(&arguments.png, "png").as_deref(),
+        output(&arguments.svg, "svg").as_deref(),
+        domains,
+        4,
+    );
+}
+
+/// # Run a scan
+/// Simulates every point of a parameter scan and prints a table of the results.
+fn scan(arguments: ScanArguments) {
+    let scan = Scan {
+        sizes: arguments.sizes.iter().map(|&size| size as usize).collect(),
+        temperatures: arguments.temperatures.clone(),
+        fields: arguments.fields.clone(),
+        crystal_fields: match arguments.crystal_field.as_slice() {
+            [] => vec![None],
+            crystal_fields => crystal_fields.iter().copied().map(Some).collect(),
+        },
+        seeds: arguments.seeds.clone(),
+        thermalization_sweeps: arguments.thermalization,
+        measurement_sweeps: arguments.measurement,
+    };
+    let threads = arguments.threads.map_or_else(
+        || thread::available_parallelism().map_or(1, usize::from),
+        |threads| threads as usize,
+    );
+    let start = Instant::now();
+    #[cfg(not(target_arch = "wasm32"))]
+    let results = match &arguments.listen {
+        Some(address) => TcpListener::bind(address)
+            .map_err(Error::from)
+            .and_then(|listener| {
+                let timeout = arguments.task_timeout.map(Duration::from_secs);
+                scan.run_distributed(&listener, timeout)
+            })
+            .unwrap_or_else(|error| {
+                eprintln!("could not distribute the scan on {}: {}", address, error);
+                std::process::exit(1);
+            }),
+        None => run_scan(&scan, threads, &arguments),
+    };
+    #[cfg(target_arch = "wasm32")]
+    let results = run_scan(&scan, threads, &arguments);
+    let elapsed = start.elapsed().as_secs_f64();
+
+    println!("size\ttemperature\tfield\tcrystal field\tseed\tenergy\t|m|\tchi\tC\tU");
+    for result in &results {
+        println!(
+            "{}\t{}\t{}\t{}\t{}\t{:.5}\t{:.5}\t{:.4}\t{:.4}\t{:.4}",
+            result.size,
+            result.temperature,
+            result.field,
+            result.crystal_field.map_or_else(
+                || "-".to_string(),
+                |crystal_field| crystal_field.to_string()
+            ),
+            result.seed,
+            result.energy,
+            result.absolute_magnetization,
+            result.susceptibility,
+            result.specific_heat,
+            result.binder_cumulant
+        );
+    }
+
+    if let Some(path) = &arguments.parquet {
+        if let Err(error) = output::write_parquet(path, &results, &scan.provenance()) {
+            eprintln!("could not write {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+    #[cfg(feature = "plot")]
+    if let Some(path) = &arguments.plot {
+        if let Err(error) = plot::plot_scan(path, &results) {
+            eprintln!("could not plot {}: {}", path.display(), error);
+            std::process::exit(1);
+        }
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &arguments.database {
+        let recorded = ResultsDatabase::open(path).and_then(|database| {
+            // The points ran in parallel, so each is charged an equal share of the time. The
+            // workers of a distributed scan are not counted, so their points are charged less.
+            let share = elapsed * threads as f64 / results.len() as f64;
+            results.iter().try_for_each(|result| {
+                let provenance = scan.provenance();
+                database
+                    .record(result, scan.measurement_sweeps, share, &provenance)
+                    .map(|_| ())
+            })
+        });
+        if let Err(error) = recorded {
+            eprintln!(
+                "could not record the results in {}: {}",
+                path.display(),
+                error
+            );
+            std::process::exit(1);
+        }
+    }
+    #[cfg(not(feature = "sqlite"))]
+    let _ = elapsed;
+}
+
+/// Simulates the points of a scan on this machine, writing the record of each to the HDF5 file as
+/// soon as it is finished if one was asked for.
+fn run_scan(scan: &Scan, threads: usize, arguments: &ScanArguments) -> Vec<ScanResult> {
+    let invalid = |error: Error| -> ! {
+        eprintln!("invalid scan: {}", error);
+        std::process::exit(2);
+    };
+    #[cfg(feature = "hdf5")]
+    if let Some(path) = &arguments.hdf5 {
+        let interval = arguments.snapshot_interval as usize;
+        let write_failed = |error: &dyn std::fmt::Display| -> ! {
+            eprintln!("could not write {}: {}", path.display(), error);
+            std::process::exit(1);
+        };
+        let mut writer = output::Hdf5Writer::create(path, &scan.provenance())
+            .unwrap_or_else(|error| write_failed(&error));
+        let results = scan.record(threads, Some(interval), |record| {
+            let provenance = scan.point_provenance(record.point);
+            Ok(writer.write(&record.into(), &provenance)?)
+        });
+        return match results {
+            Ok(results) => results,
+            Err(Error::Io(error)) => write_failed(&error),
+            Err(error) => invalid(error),
+        };
+    }
+    #[cfg(not(feature = "hdf5"))]
+    let _ = arguments;
+    scan.run(threads).unwrap_or_else(|error| invalid(error))
+}
+
+fn ensemble(arguments: EnsembleArguments) {
+    let cores = thread::available_parallelism().map_or(1, usize::from);
+    let replicas = arguments.replicas.unwrap_or(cores as u64);
+    let ensemble = Ensemble {
+        size: arguments.size as usize,
+        temperature: arguments.temperature,
+        field: arguments.field,
+        seeds: (0..replicas)
+            .map(|replica| arguments.seed + replica)
+            .collect(),
+        thermalization_sweeps: arguments.thermalization,
+        measurement_sweeps: arguments.measurement,
+    };
+    let threads = arguments.threads.map_or(cores, |threads| threads as usize);
+    let result = ensemble.run(threads).unwrap_or_else(|error| {
+        eprintln!("invalid ensemble: {}", error);
+        std::process::exit(2);
+    });
+
+    println!("seed\tenergy\t|m|\tchi\tC\tU");
+    for replica in &result.replicas {
+        println!(
+            "{}\t{:.5}\t{:.5}\t{:.4}\t{:.4}\t{:.4}",
+            replica.seed,
+            replica.energy,
+            replica.absolute_magnetization,
+            replica.susceptibility,
+            replica.specific_heat,
+            replica.binder_cumulant
+        );
+    }
+    println!();
+    println!("{} replicas", result.replicas.len());
+    println!("energy: {}", result.energy);
+    println!("magnetization: {}", result.magnetization);
+    println!("|magnetization|: {}", result.absolute_magnetization);
+    println!("susceptibility: {}", result.susceptibility);
+    println!("specific heat: {}", result.specific_heat);
+    println!("Binder cumulant: {}", result.binder_cumulant);
+    println!("acceptance: {}", result.acceptance);
+}
+
+/// # Integrate the free energy
+/// Simulates the temperatures from the highest down and prints the energy, the free energy and
+/// the entropy per site at each of them.
+fn free_energy(arguments: FreeEnergyArguments) {
+    let mut temperatures = arguments.temperatures.clone();
+    temperatures.sort_by(|a, b| b.value().total_cmp(&a.value()));
+    temperatures.dedup();
+    let integration = ThermodynamicIntegration {
+        size: arguments.size as usize,
+        field: arguments.field,
+        temperatures,
+        seed: arguments.seed,
+        thermalization_sweeps: arguments.thermalization,
+        measurement_sweeps: arguments.measurement,
+    };
+    let cores = thread::available_parallelism().map_or(1, usize::from);
+    let threads = arguments.threads.map_or(cores, |threads| threads as usize);
+    let points = integration.run(threads).unwrap_or_else(|error| {
+        eprintln!("invalid integration: {}", error);
+        std::process::exit(2);
+    });
+
+    println!("T\tenergy\terror\tfree energy\terror\tentropy\terror");
+    for point in &points {
+        println!(
+            "{}\t{:.6}\t{:.6}\t{:.6}\t{:.6}\t{:.6}\t{:.6}",
+            point.temperature,
+            point.energy.value,
+            point.energy.error,
+            point.free_energy.value,
+            point.free_energy.error,
+            point.entropy.value,
+            point.entropy.error
+        );
+    }
+}
+
+/// # Read a saved run
+/// The time series of a saved run and the acceptance rates of its sweeps, which only the CSV
+/// files and logs record, after dropping the first `skip` measurements. The coupling and field
+/// given on the command line take precedence over those recorded in the file.
+fn read_series(arguments: &AnalyzeArguments) -> io::Result<(TimeSeries, Vec<f64>)> {
+    let path = &arguments.input;
+    let missing = |what: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} does not record the {}", path.display(), what),
+        )
+    };
+    let temperature_of = |coupling: f64| {
+        Temperature::from_beta(coupling).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "only runs with a positive coupling can be analyzed",
+            )
+        })
+    };
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    let (observations, width, height, coupling, field) = match extension {
+        Some("csv") => {
+            let provenance = output::read_csv_provenance(path)?;
+            let parameter = |name: &str| {
+                provenance
+                    .as_ref()
+                    .and_then(|provenance| provenance.parameters.get(name))
+                    .and_then(|value| value.as_f64())
+            };
+            (
+                output::read_csv(path)?,
+                parameter("width"),
+                parameter("height"),
+                parameter("coupling"),
+                parameter("field"),
+            )
+        }
+        Some("jsonl") => {
+            let mut observations = Vec::new();
+            let mut started = None;
+            for event in output::read_json_lines(path)? {
+                match event {
+                    RunEvent::Started {
+                        width,
+                        height,
+                        coupling,
+                        field,
+                        ..
+                    } => started = Some((width, height, coupling, field)),
+                    RunEvent::Measurement(observation) => observations.push(observation),
+                    _ => {}
+                }
+            }
+            let (width, height, coupling, field) = started.ok_or_else(|| missing("run"))?;
+            (
+                observations,
+                Some(width as f64),
+                Some(height as f64),
+                Some(coupling),
+                Some(field),
+            )
+        }
+        _ => {
+            let trajectory = TrajectoryReader::open(path)?;
+            let index = trajectory.index();
+            let parameter = |name: &str| {
+                index
+                    .provenance
+                    .as_ref()
+                    .and_then(|provenance| provenance.parameters.get(name))
+                    .and_then(|value| value.as_f64())
+            };
+            let coupling = arguments
+                .coupling
+                .or(parameter("coupling"))
+                .ok_or_else(|| missing("coupling"))?;
+            let field = arguments
+                .field
+                .or(parameter("field"))
+                .ok_or_else(|| missing("field"))?;
+            // The vacancies are quenched, so any frame gives the number of occupied sites.
+            let number_of_sites = match trajectory.len() {
+                0 => index.width * index.height,
+                _ => trajectory.read(0)?.1.number_of_occupied_sites(),
+            };
+            let mut series =
+                TimeSeries::new(number_of_sites, temperature_of(coupling)?, field / coupling);
+            for frame in arguments.skip..trajectory.len() {
+                series.push_grid(&trajectory.read(frame)?.1);
+            }
+            return Ok((series, Vec::new()));
+        }
+    };
+
+    let coupling = arguments
+        .coupling
+        .or(coupling)
+        .ok_or_else(|| missing("coupling"))?;
+    let field = arguments.field.or(field).ok_or_else(|| missing("field"))?;
+    let (width, height) = width.zip(height).ok_or_else(|| missing("grid size"))?;
+    let observations = observations.get(arguments.skip..).unwrap_or_default();
+    let series = TimeSeries::from_observations(
+        observations,
+        (width * height) as usize,
+        temperature_of(coupling)?,
+        field / coupling,
+    );
+    let acceptances = observations
+        .iter()
+        .map(|observation| observation.acceptance)
+        .collect();
+    Ok((series, acceptances))
+}
+
+/// # Analyze a run
+/// Prints the observables of a saved run with their errors and autocorrelation times, at the
+/// temperature of the run and at every temperature it is reweighted to.
+fn analyze(arguments: AnalyzeArguments) {
+    let (series, acceptances) = read_series(&arguments).unwrap_or_else(|error| {
+        eprintln!("could not read {}: {}", arguments.input.display(), error);
+        std::process::exit(1);
+    });
+    if series.is_empty() {
+        eprintln!("there are no measurements left to analyze");
+        std::process::exit(1);
+    }
+
+    println!("Measurements: {}", series.len());
+    if !acceptances.is_empty() {
+        println!("acceptance: {}", blocked_mean(&acceptances));
+    }
+    let temperatures = std::iter::once(series.temperature).chain(arguments.reweight.clone());
+    for (index, temperature) in temperatures.enumerate() {
+        let analysis = series.reweight(temperature);
+        println!();
+        if index == 0 {
+            println!("T = {} (simulated)", temperature);
+        } else {
+            println!("T = {} (reweighted)", temperature);
+        }
+        println!("energy: {}", analysis.energy);
+        println!("magnetization: {}", analysis.magnetization);
+        println!("|magnetization|: {}", analysis.absolute_magnetization);
+        println!("susceptibility: {}", analysis.susceptibility);
+        println!("specific heat: {}", analysis.specific_heat);
+        println!("Binder cumulant: {}", analysis.binder_cumulant);
+    }
+    let analysis = series.analyze();
+    println!();
+    println!("τ_int(energy): {}", analysis.energy_time);
+    println!(
+        "τ_int(|magnetization|): {}",
+        analysis.absolute_magnetization_time
+    );
+}
+
+/// # Render a configuration
+/// Draws a saved configuration or the configuration of a checkpoint.
+fn render(arguments: RenderArguments) {
+    let path = &arguments.input;
+    let loaded = if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        Simulation::load_checkpoint(path).map(Simulation::into_grid)
+    } else {
+        Grid::load(path).map_err(io::Error::from)
+    };
+    let grid = loaded.unwrap_or_else(|error| {
+        eprintln!("could not read {}: {}", path.display(), error);
+        std::process::exit(1);
+    });
+    if arguments.terminal {
+        print!(
+            "{}",
+            render::to_terminal(&grid, &render::Palette::default())
+        );
+    }
+    draw(
+        &grid,
+        arguments.png.as_deref(),
+        arguments.svg.as_deref(),
+        arguments.domains,
+        arguments.scale,
+    );
+}
+
+/// # Replay a trajectory
+/// Draws the frames of a saved trajectory with the chosen styling, so that the pictures of a run
+/// can be made again without running it.
+fn replay(arguments: ReplayArguments) {
+    let path = &arguments.trajectory;
+    let trajectory = TrajectoryReader::open(path).unwrap_or_else(|error| {
+        eprintln!("could not read {}: {}", path.display(), error);
+        std::process::exit(1);
+    });
+    if arguments.png.is_none() && arguments.gif.is_none() && arguments.video.is_none() {
+        eprintln!("nothing to draw, ask for --png, --gif or --video");
+        std::process::exit(1);
+    }
+    if arguments
+        .png
+        .as_ref()
+        .is_some_and(|pattern| !pattern.contains("{}"))
+    {
+        eprintln!("the --png pattern needs a {{}} for the sweep");
+        std::process::exit(1);
+    }
+    let palette = arguments.palette.palette();
+    let scale = arguments.scale;
+
+    let mut recorder = arguments.gif.as_ref().map(|path| {
+        let mut recorder = render::GifRecorder::create(path, palette, scale, 1, arguments.fps)
+            .unwrap_or_else(|error| {
+                eprintln!("could not create the animation {}: {}", path, error);
+                std::process::exit(1);
+            });
+        recorder.set_domain_coloring(arguments.domains.then(render::DomainColoring::new));
+        recorder
+    });
+    let mut video = arguments.video.as_ref().map(|path| {
+        let index = trajectory.index();
+        let mut video = render::VideoRecorder::create(
+            path,
+            index.width,
+            index.height,
+            palette,
+            scale,
+            1,
+            arguments.fps,
+        )
+        .unwrap_or_else(|error| {
+            eprintln!("could not start ffmpeg for {}: {}", path, error);
+            std::process::exit(1);
+        });
+        video.set_domain_coloring(arguments.domains.then(render::DomainColoring::new));
+        video
+    });
+    let mut domains = arguments.domains.then(render::DomainColoring::new);
+
+    let mut frames = 0;
+    for frame in (arguments.skip..trajectory.len()).step_by(arguments.every as usize) {
+        let (sweep, grid) = trajectory.read(frame).unwrap_or_else(|error| {
+            eprintln!(
+                "could not read frame {} of {}: {}",
+                frame,
+                path.display(),
+                error
+            );
+            std::process::exit(1);
+        });
+        if let Some(recorder) = &mut recorder {
+            if let Err(error) = recorder.record(0, &grid) {
+                eprintln!("could not add a frame to the animation: {}", error);
+                std::process::exit(1);
+            }
         }
-        grid.step(coupling_between_neighboring_spins, applied_field);
+        if let Some(video) = &mut video {
+            if let Err(error) = video.record(0, &grid) {
+                eprintln!("could not send a frame to ffmpeg: {}", error);
+                std::process::exit(1);
+            }
+        }
+        if let Some(pattern) = &arguments.png {
+            let path = pattern.replace("{}", &format!("{:08}", sweep));
+            let image = match &mut domains {
+                Some(domains) => domains.to_image(&grid, scale),
+                None => grid.to_image(&palette, scale),
+            };
+            let result = image.map_err(|error| error.to_string()).and_then(|image| {
+                image
+                    .save_with_format(&path, image::ImageFormat::Png)
+                    .map_err(|error| error.to_string())
+            });
+            if let Err(error) = result {
+                eprintln!("could not write the image {}: {}", path, error);
+                std::process::exit(1);
+            }
+        }
+        frames += 1;
     }
 
-    println!("Final configuration (sample element): {:?}", grid);
-    println!("Elapsed time: {:?}", start.elapsed());
+    if let Some(video) = video {
+        if let Err(error) = video.finish() {
+            eprintln!("could not finish the video: {}", error);
+            std::process::exit(1);
+        }
+    }
+    println!("Drew {} of {} frames", frames, trajectory.len());
+}
+
+/// # Work for a distributed scan
+/// Simulates the points that a coordinator started with `scan --listen` hands out, until it has
+/// none left.
+#[cfg(not(target_arch = "wasm32"))]
+fn worker(arguments: WorkerArguments) {
+    let threads = arguments.threads.map_or_else(
+        || thread::available_parallelism().map_or(1, usize::from),
+        |threads| threads as usize,
+    );
+    match distributed::work(&arguments.connect, threads) {
+        Ok(points) => println!("Simulated {} points", points),
+        Err(error) => {
+            eprintln!("could not work for {}: {}", arguments.connect, error);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// # Benchmark the algorithms
+/// Times every combination of size, algorithm and number of threads and prints their throughput,
+/// with the speed-up over the first number of threads.
+fn bench(arguments: BenchArguments) {
+    let mut threads: Vec<usize> = arguments.threads.iter().map(|&n| n as usize).collect();
+    if threads.is_empty() {
+        let cores = thread::available_parallelism().map_or(1, usize::from);
+        threads = if cores > 1 { vec![1, cores] } else { vec![1] };
+    }
+    let benchmark = bench::Benchmark {
+        sizes: arguments.sizes.iter().map(|&size| size as usize).collect(),
+        algorithms: arguments.algorithms,
+        threads,
+        sweeps: arguments.sweeps as usize,
+        coupling: arguments.coupling,
+    };
+
+    let results = benchmark.run().unwrap_or_else(|error| {
+        eprintln!("Invalid benchmark: {}", error);
+        std::process::exit(2);
+    });
+    println!("size\talgorithm\tthreads\tsweeps/s\tupdates/s\tspeed-up");
+    let mut baseline = f64::NAN;
+    for result in results {
+        if result.threads == benchmark.threads[0] {
+            baseline = result.updates_per_second;
+        }
+        println!(
+            "{}\t{}\t{}\t{:.1}\t{:.3e}\t{:.2}",
+            result.size,
+            result.algorithm,
+            result.threads,
+            result.sweeps_per_second,
+            result.updates_per_second,
+            result.updates_per_second / baseline
+        );
+    }
+}
+
+/// # Draw a configuration
+/// Draws the grid as a PNG image and as SVG with its domain walls, where asked for.
+fn draw(grid: &Grid, png: Option<&str>, svg: Option<&str>, domains: bool, scale: u32) {
+    if let Some(path) = png {
+        let result = if domains {
+            render::DomainColoring::new()
+                .to_image(grid, scale)
+                .map_err(|error| error.to_string())
+                .and_then(|image| {
+                    image
+                        .save_with_format(path, image::ImageFormat::Png)
+                        .map_err(|error| error.to_string())
+                })
+        } else {
+            grid.save_png(path, &render::Palette::default(), scale)
+                .map_err(|error| error.to_string())
+        };
+        if let Err(error) = result {
+            eprintln!("could not write the image {}: {}", path, error);
+        }
+    }
+
+    if let Some(path) = svg {
+        let palette = render::Palette::default();
+        if let Err(error) = render::save_svg(path, grid, &palette, scale, true) {
+            eprintln!("could not write the drawing {}: {}", path, error);
+        }
+    }
 }
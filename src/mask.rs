@@ -0,0 +1,218 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{check_same_size, Result};
+
+/// # Site mask
+/// A boolean mask over a width × height grid that marks the sites belonging to the simulated
+/// region. Applying it to a grid with `Grid::apply_mask` empties every inactive site, so the
+/// sums and updates are confined to shapes such as disks, L-shapes or imported bitmaps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SiteMask {
+    width: usize,
+    height: usize,
+    active: Vec<bool>,
+}
+
+impl SiteMask {
+    /// # Mask from a function
+    /// Creates a mask where the site at (x, y) is active if `f(x, y)` is true.
+    pub fn from_fn(width: usize, height: usize, mut f: impl FnMut(usize, usize) -> bool) -> Self {
+        let active = (0..width * height)
+            .map(|index| f(index % width, index / width))
+            .collect();
+        Self {
+            width,
+            height,
+            active,
+        }
+    }
+
+    /// # Rectangle
+    /// The sites with `x_range.0 <= x < x_range.1` and `y_range.0 <= y < y_range.1`.
+    pub fn rectangle(
+        width: usize,
+        height: usize,
+        x_range: (usize, usize),
+        y_range: (usize, usize),
+    ) -> Self {
+        Self::from_fn(width, height, |x, y| {
+            (x_range.0..x_range.1).contains(&x) && (y_range.0..y_range.1).contains(&y)
+        })
+    }
+
+    /// # Disk
+    /// The sites whose centres lie within `radius` of `center`.
+    pub fn disk(width: usize, height: usize, center: (f64, f64), radius: f64) -> Self {
+        Self::from_fn(width, height, |x, y| {
+            (x as f64 - center.0).hypot(y as f64 - center.1) <= radius
+        })
+    }
+
+    /// # L-shape
+    /// Two arms of the given thickness along the left and bottom (low y) edges of the grid.
+    pub fn l_shape(width: usize, height: usize, thickness: usize) -> Self {
+        Self::from_fn(width, height, |x, y| x < thickness || y < thickness)
+    }
+
+    /// # Load a bitmap
+    /// Reads a mask from a plain (P1) portable bitmap, in which `1` marks an active site and `0`
+    /// an inactive one. The first row of the bitmap becomes y = 0, and comments starting with `#`
+    /// are ignored.
+    pub fn load_pbm(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        let mut tokens = contents
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(|line| line.split_whitespace());
+        if tokens.next() != Some("P1") {
+            return Err(invalid("expected a plain bitmap starting with P1"));
+        }
+        let mut dimension = || -> io::Result<usize> {
+            tokens
+                .next()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| invalid("expected the width and height of the bitmap"))
+        };
+        let (width, height) = (dimension()?, dimension()?);
+
+        // Pixels may be written without separators, so read them one character at a time.
+        let active: Vec<bool> = tokens
+            .flat_map(|token| token.chars())
+            .map(|pixel| match pixel {
+                '0' => Ok(false),
+                '1' => Ok(true),
+                _ => Err(invalid("pixels must be 0 or 1")),
+            })
+            .collect::<io::Result<_>>()?;
+        if active.len() != width * height {
+            return Err(invalid("the number of pixels does not match the size"));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            active,
+        })
+    }
+
+    /// # Width
+    /// The number of columns of the mask.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows of the mask.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Is active
+    /// Whether the site at the given coordinates is part of the region, with periodic boundary
+    /// conditions.
+    pub fn is_active(&self, x: i64, y: i64) -> bool {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.active[y * self.width + x]
+    }
+
+    /// # Number of active sites
+    /// The number of sites in the region.
+    pub fn number_of_active(&self) -> usize {
+        self.active.iter().filter(|&&active| active).count()
+    }
+
+    /// # Complement
+    /// The sites outside the region.
+    pub fn complement(&self) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            active: self.active.iter().map(|&active| !active).collect(),
+        }
+    }
+
+    /// # Union
+    /// The sites in either region. Fails if the other mask has a different size.
+    pub fn union(&self, other: &SiteMask) -> Result<Self> {
+        self.combine(other, |a, b| a || b)
+    }
+
+    /// # Intersection
+    /// The sites in both regions. Fails if the other mask has a different size.
+    pub fn intersection(&self, other: &SiteMask) -> Result<Self> {
+        self.combine(other, |a, b| a && b)
+    }
+
+    fn combine(&self, other: &SiteMask, rule: impl Fn(bool, bool) -> bool) -> Result<Self> {
+        check_same_size(
+            "mask",
+            (self.width, self.height),
+            (other.width, other.height),
+        )?;
+        Ok(Self {
+            width: self.width,
+            height: self.height,
+            active: self
+                .active
+                .iter()
+                .zip(&other.active)
+                .map(|(&a, &b)| rule(a, b))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shapes() {
+        let disk = SiteMask::disk(9, 9, (4.0, 4.0), 2.0);
+        assert_eq!(disk.number_of_active(), 13);
+        assert!(disk.is_active(4, 6));
+        assert!(!disk.is_active(6, 6));
+
+        // An L-shape is a square with one corner removed.
+        let square = SiteMask::rectangle(6, 6, (0, 6), (0, 6));
+        let corner = SiteMask::rectangle(6, 6, (2, 6), (2, 6));
+        let l_shape = square.intersection(&corner.complement()).unwrap();
+        assert_eq!(l_shape, SiteMask::l_shape(6, 6, 2));
+        assert_eq!(l_shape.number_of_active(), 20);
+        assert_eq!(l_shape.union(&corner).unwrap(), square);
+        assert!(square
+            .union(&SiteMask::disk(9, 9, (4.0, 4.0), 2.0))
+            .is_err());
+    }
+
+    #[test]
+    fn test_load_pbm() {
+        let path = std::env::temp_dir().join("ising_model_test_load_pbm.pbm");
+        fs::write(&path, "P1\n# A small cross\n3 3\n0 1 0\n111\n0 1 0\n").unwrap();
+        let mask = SiteMask::load_pbm(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!((mask.width(), mask.height()), (3, 3));
+        assert_eq!(mask.number_of_active(), 5);
+        assert!(mask.is_active(0, 1));
+        assert!(!mask.is_active(0, 0));
+    }
+
+    #[test]
+    fn test_load_invalid_pbm() {
+        let path = std::env::temp_dir().join("ising_model_test_load_invalid_pbm.pbm");
+        fs::write(&path, "P1\n2 2\n0 1 1\n").unwrap();
+        let error = SiteMask::load_pbm(&path).unwrap_err();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
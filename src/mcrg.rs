@@ -0,0 +1,337 @@
+use crate::error::{Error, Result};
+use crate::grid::{CoarseGrainRule, Grid};
+
+/// The number of even (spin-flip symmetric) operators used for the thermal exponent.
+const EVEN_OPERATORS: usize = 3;
+
+/// The number of odd operators used for the magnetic exponent.
+const ODD_OPERATORS: usize = 2;
+
+/// # Even operators
+/// The spin-flip symmetric operators whose couplings flow under the renormalization group: the sum
+/// of nearest-neighbour products, the sum of next-nearest (diagonal) products, and the sum of
+/// plaquette four-spin products.
+fn even_operators(grid: &Grid) -> [f64; EVEN_OPERATORS] {
+    let mut operators = [0.0; EVEN_OPERATORS];
+    for y in 0..grid.height() as i64 {
+        for x in 0..grid.width() as i64 {
+            let spin = grid.get_spin_as_float(x, y);
+            let right = grid.get_spin_as_float(x + 1, y);
+            let below = grid.get_spin_as_float(x, y + 1);
+            let diagonal = grid.get_spin_as_float(x + 1, y + 1);
+            let anti_diagonal = grid.get_spin_as_float(x - 1, y + 1);
+
+            operators[0] += spin * (right + below);
+            operators[1] += spin * (diagonal + anti_diagonal);
+            operators[2] += spin * right * below * diagonal;
+        }
+    }
+    operators
+}
+
+/// # Odd operators
+/// The operators that change sign under a global spin flip: the magnetization and the sum of
+/// three-spin products on the corners of each plaquette.
+fn odd_operators(grid: &Grid) -> [f64; ODD_OPERATORS] {
+    let mut operators = [0.0; ODD_OPERATORS];
+    for y in 0..grid.height() as i64 {
+        for x in 0..grid.width() as i64 {
+            let spin = grid.get_spin_as_float(x, y);
+            operators[0] += spin;
+            operators[1] +=
+                spin * grid.get_spin_as_float(x + 1, y) * grid.get_spin_as_float(x, y + 1);
+        }
+    }
+    operators
+}
+
+/// # Renormalization exponents
+/// The leading eigenvalues of the linearized renormalization group transformation at one blocking
+/// level, and the exponents y = ln λ / ln b they imply. For the 2D Ising model the exact values
+/// are y_t = 1 and y_h = 15/8.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenormalizationExponents {
+    pub level: usize,
+    pub thermal_eigenvalue: f64,
+    pub magnetic_eigenvalue: f64,
+    pub thermal_exponent: f64,
+    pub magnetic_exponent: f64,
+}
+
+/// # Correlations of one sector
+/// The running sums needed for the connected correlations between the operators of one level and
+/// those of the same and the previous level.
+#[derive(Debug, Clone)]
+struct SectorSums<const N: usize> {
+    operators: Vec<[f64; N]>,
+    same_level: Vec<[[f64; N]; N]>,
+    previous_level: Vec<[[f64; N]; N]>,
+}
+
+impl<const N: usize> SectorSums<N> {
+    fn new(levels: usize) -> Self {
+        Self {
+            operators: vec![[0.0; N]; levels + 1],
+            same_level: vec![[[0.0; N]; N]; levels + 1],
+            previous_level: vec![[[0.0; N]; N]; levels + 1],
+        }
+    }
+
+    fn add(&mut self, values: &[[f64; N]]) {
+        for (level, current) in values.iter().enumerate() {
+            for a in 0..N {
+                self.operators[level][a] += current[a];
+                for b in 0..N {
+                    self.same_level[level][a][b] += current[a] * current[b];
+                    if level > 0 {
+                        self.previous_level[level][a][b] += current[a] * values[level - 1][b];
+                    }
+                }
+            }
+        }
+    }
+
+    /// Solves A T = B for the linearized transformation T at a level, where
+    /// A = <S^(n) S^(n)>_c and B = <S^(n) S^(n-1)>_c, and returns its leading eigenvalue.
+    fn leading_eigenvalue(&self, level: usize, samples: f64) -> f64 {
+        let mean = |l: usize, a: usize| self.operators[l][a] / samples;
+        let mut a_matrix = [[0.0; N]; N];
+        let mut b_matrix = [[0.0; N]; N];
+        for a in 0..N {
+            for b in 0..N {
+                a_matrix[a][b] =
+                    self.same_level[level][a][b] / samples - mean(level, a) * mean(level, b);
+                b_matrix[a][b] = self.previous_level[level][a][b] / samples
+                    - mean(level, a) * mean(level - 1, b);
+            }
+        }
+        leading_eigenvalue(&solve(a_matrix, b_matrix))
+    }
+}
+
+/// # Monte Carlo renormalization group
+/// Accumulates the operator correlations of sampled configurations across several levels of
+/// majority-rule blocking, and estimates the thermal and magnetic exponents from them with
+/// Swendsen's linearization of the renormalization group transformation.
+#[derive(Debug, Clone)]
+pub struct MonteCarloRenormalization {
+    block_size: usize,
+    levels: usize,
+    samples: usize,
+    even: SectorSums<EVEN_OPERATORS>,
+    odd: SectorSums<ODD_OPERATORS>,
+}
+
+impl MonteCarloRenormalization {
+    /// # New MCRG accumulator
+    /// Creates an accumulator that blocks each configuration `levels` times by `block_size`.
+    /// Fails unless the block size is at least two and there is at least one level.
+    pub fn new(block_size: usize, levels: usize) -> Result<Self> {
+        if block_size < 2 {
+            return Err(Error::InvalidParameter {
+                name: "block size",
+                value: block_size as f64,
+            });
+        }
+        if levels == 0 {
+            return Err(Error::InvalidParameter {
+                name: "level",
+                value: 0.0,
+            });
+        }
+        Ok(Self {
+            block_size,
+            levels,
+            samples: 0,
+            even: SectorSums::new(levels),
+            odd: SectorSums::new(levels),
+        })
+    }
+
+    /// # Measure
+    /// Blocks a sampled configuration repeatedly and adds its operators to the running sums.
+    /// Fails if the grid is too small to be blocked `levels` times, in which case nothing is
+    /// added.
+    pub fn measure(&mut self, grid: &Grid) -> Result<()> {
+        let smallest = self
+            .block_size
+            .checked_pow(self.levels as u32)
+            .unwrap_or(usize::MAX);
+        if grid.width() < smallest || grid.height() < smallest {
+            return Err(Error::SizeMismatch {
+                name: "grid",
+                expected: (grid.width().max(smallest), grid.height().max(smallest)),
+                found: (grid.width(), grid.height()),
+            });
+        }
+
+        let mut even = vec![even_operators(grid)];
+        let mut odd = vec![odd_operators(grid)];
+        let mut blocked = grid.coarse_grain(self.block_size, CoarseGrainRule::Majority)?;
+        for level in 1..=self.levels {
+            even.push(even_operators(&blocked));
+            odd.push(odd_operators(&blocked));
+            if level < self.levels {
+                blocked = blocked.coarse_grain(self.block_size, CoarseGrainRule::Majority)?;
+            }
+        }
+
+        self.even.add(&even);
+        self.odd.add(&odd);
+        self.samples += 1;
+        Ok(())
+    }
+
+    /// # Exponents
+    /// Estimates the exponents from the transformation between `level - 1` and `level`. Higher
+    /// levels suffer less from the truncation of the coupling space but more from finite-size
+    /// effects. Fails unless the level is between one and the number of blocking levels.
+    pub fn exponents(&self, level: usize) -> Result<RenormalizationExponents> {
+        if !(1..=self.levels).contains(&level) {
+            return Err(Error::InvalidParameter {
+                name: "level",
+                value: level as f64,
+            });
+        }
+
+        let samples = self.samples as f64;
+        let thermal_eigenvalue = self.even.leading_eigenvalue(level, samples);
+        let magnetic_eigenvalue = self.odd.leading_eigenvalue(level, samples);
+        let log_block_size = (self.block_size as f64).ln();
+
+        Ok(RenormalizationExponents {
+            level,
+            thermal_eigenvalue,
+            magnetic_eigenvalue,
+            thermal_exponent: thermal_eigenvalue.ln() / log_block_size,
+            magnetic_exponent: magnetic_eigenvalue.ln() / log_block_size,
+        })
+    }
+}
+
+/// # Solve
+/// Solves A X = B for X with Gaussian elimination and partial pivoting.
+fn solve<const N: usize>(mut a: [[f64; N]; N], mut b: [[f64; N]; N]) -> [[f64; N]; N] {
+    for column in 0..N {
+        let pivot = (column..N)
+            .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+            .unwrap();
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+
+        for row in column + 1..N {
+            let factor = a[row][column] / a[column][column];
+            for k in 0..N {
+                a[row][k] -= factor * a[column][k];
+                b[row][k] -= factor * b[column][k];
+            }
+        }
+    }
+
+    let mut x = [[0.0; N]; N];
+    for row in (0..N).rev() {
+        for k in 0..N {
+            let known: f64 = (row + 1..N).map(|j| a[row][j] * x[j][k]).sum();
+            x[row][k] = (b[row][k] - known) / a[row][row];
+        }
+    }
+    x
+}
+
+/// # Leading eigenvalue
+/// Finds the eigenvalue of largest magnitude of a small matrix with power iteration.
+fn leading_eigenvalue<const N: usize>(matrix: &[[f64; N]; N]) -> f64 {
+    let mut vector = [1.0; N];
+    let mut eigenvalue = 0.0;
+    for _ in 0..10_000 {
+        let mut next = [0.0; N];
+        for (row, value) in matrix.iter().zip(next.iter_mut()) {
+            *value = row.iter().zip(&vector).map(|(a, b)| a * b).sum();
+        }
+        let norm = next.iter().map(|value| value * value).sum::<f64>().sqrt();
+        if norm == 0.0 {
+            return 0.0;
+        }
+        let new_eigenvalue = next.iter().zip(&vector).map(|(a, b)| a * b).sum::<f64>()
+            / vector.iter().map(|value| value * value).sum::<f64>();
+        for value in next.iter_mut() {
+            *value /= norm;
+        }
+        vector = next;
+        if (new_eigenvalue - eigenvalue).abs() < 1e-13 * new_eigenvalue.abs() {
+            return new_eigenvalue;
+        }
+        eigenvalue = new_eigenvalue;
+    }
+    eigenvalue
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::spin::Spin;
+    use crate::temperature::Temperature;
+
+    #[test]
+    fn test_operators_of_ordered_grid() {
+        let grid = Grid::new_constant(4, 4, Spin::Up).unwrap();
+        assert_eq!(even_operators(&grid), [32.0, 32.0, 16.0]);
+        assert_eq!(odd_operators(&grid), [16.0, 16.0]);
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        assert!(MonteCarloRenormalization::new(1, 2).is_err());
+        assert!(MonteCarloRenormalization::new(2, 0).is_err());
+        let mut mcrg = MonteCarloRenormalization::new(2, 3).unwrap();
+        assert!(matches!(
+            mcrg.measure(&Grid::new_constant(8, 4, Spin::Up).unwrap()),
+            Err(Error::SizeMismatch {
+                expected: (8, 8),
+                ..
+            })
+        ));
+        mcrg.measure(&Grid::new_constant(8, 8, Spin::Up).unwrap())
+            .unwrap();
+        assert!(mcrg.exponents(0).is_err());
+        assert!(mcrg.exponents(4).is_err());
+    }
+
+    #[test]
+    fn test_solve_and_eigenvalue() {
+        let a = [[2.0, 0.0], [1.0, 1.0]];
+        let b = [[4.0, 2.0], [3.0, 4.0]];
+        let x = solve(a, b);
+        assert!((x[0][0] - 2.0).abs() < 1e-12);
+        assert!((x[0][1] - 1.0).abs() < 1e-12);
+        assert!((x[1][0] - 1.0).abs() < 1e-12);
+        assert!((x[1][1] - 3.0).abs() < 1e-12);
+
+        // The eigenvalues of [[2, 1], [1, 3]] are (5 ± √5) / 2.
+        let eigenvalue = leading_eigenvalue(&[[2.0, 1.0], [1.0, 3.0]]);
+        assert!((eigenvalue - (5.0 + 5.0_f64.sqrt()) / 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_exponents_at_criticality() {
+        let temperature = Temperature::new(2.0 / (1.0 + 2.0_f64.sqrt()).ln()).unwrap();
+        let mut rng = StdRng::seed_from_u64(11);
+        let mut grid = Grid::new_with_magnetization(16, 16, 0.0, &mut rng).unwrap();
+        for _ in 0..200 {
+            grid.step_with_rng(temperature, 0.0, &mut rng);
+        }
+
+        let mut mcrg = MonteCarloRenormalization::new(2, 2).unwrap();
+        for _ in 0..2000 {
+            grid.step_with_rng(temperature, 0.0, &mut rng);
+            mcrg.measure(&grid).unwrap();
+        }
+
+        let exponents = mcrg.exponents(1).unwrap();
+        assert!((exponents.magnetic_exponent - 1.875).abs() < 0.2);
+        assert!((exponents.thermal_exponent - 1.0).abs() < 0.15);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::error::{Error, Result};
+use crate::temperature::Temperature;
+
+/// The tolerance used when solving the self-consistency equations.
+const TOLERANCE: f64 = 1e-13;
+
+/// The maximum number of iterations used when solving the self-consistency equations. Close to
+/// the critical point the convergence is slow, so this is deliberately generous.
+const MAX_ITERATIONS: usize = 1_000_000;
+
+/// # Mean field
+/// The Weiss mean-field approximation, where every spin feels the average magnetization of its z
+/// neighbours. The self-consistency equation is m = tanh(z K m + H), with the coupling K = J/k_BT
+/// and the field H = h/k_BT of `Grid::step`. The solvers take the temperature and the field h in
+/// units of the coupling, like `SimulationParameters::at_temperature`, so the curves can be drawn
+/// directly on top of Monte Carlo data generated at the same temperature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeanField {
+    pub coordination: usize,
+}
+
+impl MeanField {
+    /// # New mean-field solver
+    /// Creates a mean-field solver for a lattice where every site has `coordination` neighbours.
+    /// Fails for sites without neighbours.
+    pub fn new(coordination: usize) -> Result<Self> {
+        if coordination < 1 {
+            return Err(Error::InvalidParameter {
+                name: "coordination",
+                value: coordination as f64,
+            });
+        }
+        Ok(Self { coordination })
+    }
+
+    /// # Critical coupling
+    /// The coupling at which the spontaneous magnetization appears, K_c = 1/z.
+    pub fn critical_coupling(&self) -> f64 {
+        1.0 / self.coordination as f64
+    }
+
+    /// # Magnetization
+    /// Solves the self-consistency equation for the magnetization per spin. In zero field below the
+    /// critical temperature the positive branch is returned.
+    pub fn magnetization(&self, temperature: Temperature, field: f64) -> f64 {
+        // The solution is odd in the field, so only non-negative fields need to be solved.
+        if field < 0.0 {
+            return -self.magnetization(temperature, -field);
+        }
+
+        let coupling = temperature.beta();
+        let field = field * coupling;
+        let z = self.coordination as f64;
+        // Newton's method from m = 1 approaches the largest root monotonically, since the
+        // self-consistency function is concave for positive arguments.
+        let mut m = 1.0_f64;
+        for _ in 0..MAX_ITERATIONS {
+            let argument = z * coupling * m + field;
+            let g = argument.tanh() - m;
+            let derivative = z * coupling / argument.cosh().powi(2) - 1.0;
+            let next = if derivative < 0.0 {
+                m - g / derivative
+            } else {
+                argument.tanh()
+            };
+            if (next - m).abs() < TOLERANCE {
+                return next;
+            }
+            m = next;
+        }
+        m
+    }
+}
+
+/// # Bethe approximation
+/// The Bethe–Peierls approximation, which is exact on the Bethe lattice. Each spin feels a cavity
+/// field from every neighbour, and the cavity field satisfies
+/// h_c = H + (z - 1) atanh(tanh K tanh h_c). The temperature and field are given as for
+/// `MeanField`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BetheApproximation {
+    pub coordination: usize,
+}
+
+impl BetheApproximation {
+    /// # New Bethe solver
+    /// Creates a Bethe solver for a lattice where every site has `coordination` neighbours. Fails
+    /// for fewer than two, where the cavity has no branches left to carry order.
+    pub fn new(coordination: usize) -> Result<Self> {
+        if coordination < 2 {
+            return Err(Error::InvalidParameter {
+                name: "coordination",
+                value: coordination as f64,
+            });
+        }
+        Ok(Self { coordination })
+    }
+
+    /// # Critical coupling
+    /// The coupling at which the spontaneous magnetization appears, K_c = atanh(1 / (z - 1)).
+    pub fn critical_coupling(&self) -> f64 {
+        (1.0 / (self.coordination as f64 - 1.0)).atanh()
+    }
+
+    /// # Cavity field
+    /// Solves the self-consistency equation for the cavity field, in the reduced units of H.
+    pub fn cavity_field(&self, temperature: Temperature, field: f64) -> f64 {
+        if field < 0.0 {
+            return -self.cavity_field(temperature, -field);
+        }
+
+        let coupling = temperature.beta();
+        let field = field * coupling;
+        let branches = self.coordination as f64 - 1.0;
+        // Each neighbour contributes at most K, so this is an upper bound on the largest fixed
+        // point. Iterating the increasing map from above converges to it monotonically.
+        let mut cavity = field + branches * coupling.abs();
+        for _ in 0..MAX_ITERATIONS {
+            let next = field + branches * bond_field(coupling, cavity);
+            if (next - cavity).abs() < TOLERANCE {
+                return next;
+            }
+            cavity = next;
+        }
+        cavity
+    }
+
+    /// # Magnetization
+    /// The magnetization per spin, m = tanh(H + z u(h_c)), where u is the field transmitted
+    /// through a single bond.
+    pub fn magnetization(&self, temperature: Temperature, field: f64) -> f64 {
+        let cavity = self.cavity_field(temperature, field);
+        let coupling = temperature.beta();
+        (field * coupling + self.coordination as f64 * bond_field(coupling, cavity)).tanh()
+    }
+}
+
+/// # Bond field
+/// The effective field transmitted through a bond of strength K from a spin that feels the field h.
+fn bond_field(coupling: f64, field: f64) -> f64 {
+    (coupling.tanh() * field.tanh()).atanh()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beta(beta: f64) -> Temperature {
+        Temperature::from_beta(beta).unwrap()
+    }
+
+    #[test]
+    fn test_mean_field_critical_coupling() {
+        let mean_field = MeanField::new(4).unwrap();
+        assert_eq!(mean_field.critical_coupling(), 0.25);
+        assert!(MeanField::new(0).is_err());
+        assert!(mean_field.magnetization(beta(0.2), 0.0).abs() < 1e-6);
+        assert!(mean_field.magnetization(beta(0.3), 0.0) > 0.5);
+    }
+
+    #[test]
+    fn test_mean_field_self_consistency() {
+        let mean_field = MeanField::new(4).unwrap();
+        let m = mean_field.magnetization(beta(0.4), 0.25);
+        assert!((m - (4.0 * 0.4 * m + 0.1_f64).tanh()).abs() < 1e-10);
+        assert_eq!(mean_field.magnetization(beta(0.4), -0.25), -m);
+    }
+
+    #[test]
+    fn test_bethe_critical_coupling() {
+        let bethe = BetheApproximation::new(4).unwrap();
+        assert!(BetheApproximation::new(1).is_err());
+        assert!((bethe.critical_coupling() - (1.0_f64 / 3.0).atanh()).abs() < 1e-12);
+        assert!(bethe.magnetization(beta(0.3), 0.0).abs() < 1e-6);
+        assert!(bethe.magnetization(beta(0.4), 0.0) > 0.5);
+    }
+
+    #[test]
+    fn test_bethe_one_dimension() {
+        // For z = 2 the Bethe approximation is exact, so it must reproduce the 1D chain result
+        // m = sinh H / sqrt(sinh² H + e^(-4K)).
+        let bethe = BetheApproximation::new(2).unwrap();
+        let (coupling, field) = (0.7_f64, 0.2_f64);
+        let exact = field.sinh() / (field.sinh().powi(2) + (-4.0 * coupling).exp()).sqrt();
+        let temperature = beta(coupling);
+        assert!((bethe.magnetization(temperature, field / coupling) - exact).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_bethe_below_mean_field() {
+        // The Bethe approximation accounts for fluctuations, so its ordering is weaker.
+        let temperature = beta(0.3);
+        assert!(
+            BetheApproximation::new(4)
+                .unwrap()
+                .magnetization(temperature, 0.0)
+                < MeanField::new(4).unwrap().magnetization(temperature, 0.0)
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use serde::{Deserialize, Serialize};
+
+/// # Measurement
+/// A value with its standard error, which is NaN when it could not be estimated. The arithmetic
+/// operators propagate the errors to first order as if the operands were independent, and
+/// `combine` does it for correlated ones. An exact number enters with zero error, and a NaN error
+/// stays NaN through every operation that depends on it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    pub value: f64,
+    pub error: f64,
+}
+
+impl Measurement {
+    /// # New measurement
+    pub fn new(value: f64, error: f64) -> Self {
+        Self { value, error }
+    }
+
+    /// # Exact value
+    /// A value without any error.
+    pub fn exact(value: f64) -> Self {
+        Self { value, error: 0.0 }
+    }
+
+    /// # Relative error
+    /// The error divided by the magnitude of the value.
+    pub fn relative_error(&self) -> f64 {
+        self.error / self.value.abs()
+    }
+
+    /// # Map
+    /// The measurement of f(x), given f and its derivative f'.
+    pub fn map(self, f: impl Fn(f64) -> f64, derivative: impl Fn(f64) -> f64) -> Self {
+        Self {
+            value: f(self.value),
+            error: (derivative(self.value) * self.error).abs(),
+        }
+    }
+
+    /// # Combine
+    /// The measurement of f(x, y), given its value and its partial derivatives at the measured
+    /// values, for measurements with the given correlation coefficient, which is 0 for
+    /// independent ones and 1 for two measurements from the same samples that move together.
+    pub fn combine(
+        self,
+        other: Self,
+        correlation: f64,
+        value: f64,
+        derivatives: (f64, f64),
+    ) -> Self {
+        let (first, second) = (derivatives.0 * self.error, derivatives.1 * other.error);
+        let variance = first * first + second * second + 2.0 * correlation * first * second;
+        // Rounding can leave a tiny negative variance for fully correlated measurements.
+        Self {
+            value,
+            error: if variance < 0.0 { 0.0 } else { variance.sqrt() },
+        }
+    }
+
+    /// # Integer power
+    pub fn powi(self, power: i32) -> Self {
+        self.map(|x| x.powi(power), |x| power as f64 * x.powi(power - 1))
+    }
+
+    /// # Square root
+    pub fn sqrt(self) -> Self {
+        self.map(f64::sqrt, |x| 0.5 / x.sqrt())
+    }
+
+    /// # Natural logarithm
+    pub fn ln(self) -> Self {
+        self.map(f64::ln, |x| 1.0 / x)
+    }
+
+    /// # Exponential
+    pub fn exp(self) -> Self {
+        self.map(f64::exp, f64::exp)
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ± {}", self.value, self.error)
+    }
+}
+
+impl From<f64> for Measurement {
+    fn from(value: f64) -> Self {
+        Self::exact(value)
+    }
+}
+
+impl Neg for Measurement {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self {
+            value: -self.value,
+            error: self.error,
+        }
+    }
+}
+
+impl Add for Measurement {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        self.combine(other, 0.0, self.value + other.value, (1.0, 1.0))
+    }
+}
+
+impl Sub for Measurement {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self.combine(other, 0.0, self.value - other.value, (1.0, -1.0))
+    }
+}
+
+impl Mul for Measurement {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        self.combine(
+            other,
+            0.0,
+            self.value * other.value,
+            (other.value, self.value),
+        )
+    }
+}
+
+impl Div for Measurement {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self {
+        let quotient = self.value / other.value;
+        self.combine(
+            other,
+            0.0,
+            quotient,
+            (1.0 / other.value, -quotient / other.value),
+        )
+    }
+}
+
+impl Add<f64> for Measurement {
+    type Output = Self;
+
+    fn add(self, other: f64) -> Self {
+        Self {
+            value: self.value + other,
+            error: self.error,
+        }
+    }
+}
+
+impl Sub<f64> for Measurement {
+    type Output = Self;
+
+    fn sub(self, other: f64) -> Self {
+        self + -other
+    }
+}
+
+impl Mul<f64> for Measurement {
+    type Output = Self;
+
+    fn mul(self, factor: f64) -> Self {
+        Self {
+            value: self.value * factor,
+            error: self.error * factor.abs(),
+        }
+    }
+}
+
+impl Div<f64> for Measurement {
+    type Output = Self;
+
+    fn div(self, divisor: f64) -> Self {
+        Self {
+            value: self.value / divisor,
+            error: self.error / divisor.abs(),
+        }
+    }
+}
+
+impl Mul<Measurement> for f64 {
+    type Output = Measurement;
+
+    fn mul(self, measurement: Measurement) -> Measurement {
+        measurement * self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_arithmetic() {
+        let (x, y) = (Measurement::new(3.0, 0.3), Measurement::new(4.0, 0.4));
+        assert_eq!(x + y, Measurement::new(7.0, 0.5));
+        assert_eq!((x - y).error, 0.5);
+        assert_eq!(-x, Measurement::new(-3.0, 0.3));
+        // Products and quotients add the relative errors in quadrature.
+        let product = x * y;
+        assert_eq!(product.value, 12.0);
+        assert!((product.relative_error() - 0.02_f64.sqrt()).abs() < 1e-12);
+        let quotient = x / y;
+        assert_eq!(quotient.value, 0.75);
+        assert!((quotient.relative_error() - 0.02_f64.sqrt()).abs() < 1e-12);
+        assert_eq!(x * 2.0, Measurement::new(6.0, 0.6));
+        assert_eq!(-2.0 * x, Measurement::new(-6.0, 0.6));
+        assert_eq!(x / -2.0, Measurement::new(-1.5, 0.15));
+        assert_eq!(x + Measurement::from(1.0), Measurement::new(4.0, 0.3));
+        assert!((x + Measurement::new(1.0, f64::NAN)).error.is_nan());
+    }
+
+    #[test]
+    fn test_functions() {
+        let x = Measurement::new(4.0, 0.2);
+        assert_eq!(x.sqrt(), Measurement::new(2.0, 0.05));
+        assert_eq!(x.powi(2), Measurement::new(16.0, 1.6));
+        assert!((x.ln().error - 0.05).abs() < 1e-15);
+        assert!((x.exp().relative_error() - 0.2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_correlations() {
+        let x = Measurement::new(2.0, 0.1);
+        // A measurement less itself is exact, and twice itself has twice the error.
+        assert_eq!(x.combine(x, 1.0, 0.0, (1.0, -1.0)).error, 0.0);
+        assert!((x.combine(x, 1.0, 4.0, (1.0, 1.0)).error - 0.2).abs() < 1e-15);
+        assert!((x.combine(x, 0.0, 4.0, (1.0, 1.0)).error - 0.02_f64.sqrt()).abs() < 1e-15);
+    }
+}
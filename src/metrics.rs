@@ -0,0 +1,235 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// # Run metrics
+/// The state of a run at its last update: the sweeps done and planned, the recent sweep rate, the
+/// acceptance rate of the last sweep, the current reduced coupling and field, which change along
+/// an annealing schedule, and the current and averaged observables per site.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunMetrics {
+    pub sweep: usize,
+    pub sweeps: usize,
+    pub sweeps_per_second: f64,
+    pub acceptance: f64,
+    pub coupling: f64,
+    pub field: f64,
+    pub energy: f64,
+    pub magnetization: f64,
+    pub mean_absolute_magnetization: f64,
+}
+
+impl Default for RunMetrics {
+    fn default() -> Self {
+        Self {
+            sweep: 0,
+            sweeps: 0,
+            sweeps_per_second: f64::NAN,
+            acceptance: f64::NAN,
+            coupling: f64::NAN,
+            field: f64::NAN,
+            energy: f64::NAN,
+            magnetization: f64::NAN,
+            mean_absolute_magnetization: f64::NAN,
+        }
+    }
+}
+
+impl RunMetrics {
+    /// # To the Prometheus format
+    /// The metrics in the text exposition format of Prometheus, each with its help and type
+    /// lines and named with the `ising_` prefix. Values that are not known yet are NaN.
+    pub fn to_prometheus(&self) -> String {
+        let metrics = [
+            (
+                "sweeps_total",
+                "counter",
+                "Sweeps done so far.",
+                self.sweep as f64,
+            ),
+            (
+                "planned_sweeps",
+                "gauge",
+                "Sweeps the run is set to do.",
+                self.sweeps as f64,
+            ),
+            (
+                "sweeps_per_second",
+                "gauge",
+                "Recent rate of sweeps.",
+                self.sweeps_per_second,
+            ),
+            (
+                "acceptance",
+                "gauge",
+                "Fraction of moves accepted in the last sweep.",
+                self.acceptance,
+            ),
+            (
+                "coupling",
+                "gauge",
+                "Reduced coupling K = J/k_BT.",
+                self.coupling,
+            ),
+            ("field", "gauge", "Reduced field H = h/k_BT.", self.field),
+            (
+                "energy",
+                "gauge",
+                "Energy per site in units of k_BT.",
+                self.energy,
+            ),
+            (
+                "magnetization",
+                "gauge",
+                "Magnetization per site.",
+                self.magnetization,
+            ),
+            (
+                "mean_absolute_magnetization",
+                "gauge",
+                "Mean absolute magnetization per site over the run.",
+                self.mean_absolute_magnetization,
+            ),
+        ];
+        let mut text = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(text, "# HELP ising_{} {}", name, help);
+            let _ = writeln!(text, "# TYPE ising_{} {}", name, kind);
+            let _ = writeln!(text, "ising_{} {}", name, format_value(value));
+        }
+        text
+    }
+}
+
+/// Writes a value as Prometheus expects it, with `+Inf` and `-Inf` for the infinities.
+fn format_value(value: f64) -> String {
+    match value {
+        f64::INFINITY => "+Inf".to_string(),
+        f64::NEG_INFINITY => "-Inf".to_string(),
+        value => value.to_string(),
+    }
+}
+
+/// # Metrics server
+/// A small HTTP server that answers `GET /metrics` with the latest `RunMetrics` in the Prometheus
+/// format, so that a batch job can be scraped and alerted on while it runs. Other paths get a
+/// 404. Connections are answered one at a time on a background thread, and a slow scraper only
+/// holds up the other scrapers, never the run.
+pub struct MetricsServer {
+    address: SocketAddr,
+    metrics: Arc<Mutex<RunMetrics>>,
+}
+
+impl MetricsServer {
+    /// # Bind a server
+    /// Listens on the given address, such as `0.0.0.0:9184`, and starts answering scrapes.
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let metrics = Arc::new(Mutex::new(RunMetrics::default()));
+        let scraped = Arc::clone(&metrics);
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                // A failing connection only affects its own scraper.
+                let _ = Self::answer(stream, &scraped);
+            }
+        });
+        Ok(Self { address, metrics })
+    }
+
+    /// Reads the request line of a connection and answers it.
+    fn answer(mut stream: TcpStream, metrics: &Mutex<RunMetrics>) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(1)))?;
+        let mut reader = BufReader::new(&stream);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        // Closing the connection with headers left unread could reset it before the scraper
+        // reads the response.
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+            header.clear();
+        }
+        let path = request.split_whitespace().nth(1).unwrap_or("");
+        let (status, body) = if path == "/metrics" || path.starts_with("/metrics?") {
+            ("200 OK", metrics.lock().unwrap().to_prometheus())
+        } else {
+            (
+                "404 Not Found",
+                "not found, the metrics are at /metrics\n".to_string(),
+            )
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    /// # Address
+    /// The address the server listens on, with the actual port if it was bound to port 0.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// # Update
+    /// Replaces the metrics that the following scrapes see.
+    pub fn update(&self, metrics: RunMetrics) {
+        *self.metrics.lock().unwrap() = metrics;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    fn get(address: SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, address).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_to_prometheus() {
+        let metrics = RunMetrics {
+            sweep: 1200,
+            sweeps_per_second: f64::INFINITY,
+            ..RunMetrics::default()
+        };
+        let text = metrics.to_prometheus();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "# HELP ising_sweeps_total Sweeps done so far.");
+        assert_eq!(lines[1], "# TYPE ising_sweeps_total counter");
+        assert_eq!(lines[2], "ising_sweeps_total 1200");
+        assert!(lines.contains(&"ising_sweeps_per_second +Inf"));
+        assert!(lines.contains(&"ising_energy NaN"));
+        assert_eq!(lines.len(), 27);
+    }
+
+    #[test]
+    fn test_metrics_server() {
+        let server = MetricsServer::bind("127.0.0.1:0").unwrap();
+        server.update(RunMetrics {
+            sweep: 40,
+            acceptance: 0.25,
+            ..RunMetrics::default()
+        });
+
+        let response = get(server.address(), "/metrics");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("text/plain; version=0.0.4"));
+        assert!(response.contains("\nising_sweeps_total 40\n"));
+        assert!(response.contains("\nising_acceptance 0.25\n"));
+
+        assert!(get(server.address(), "/").starts_with("HTTP/1.1 404 Not Found"));
+    }
+}
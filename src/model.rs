@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+
+use rand::Rng;
+
+use crate::error::{Error, Result};
+use crate::lattice::Lattice;
+use crate::spin::Spin;
+use crate::temperature::Temperature;
+
+/// # Ising model
+/// This is a struct that represents a configuration of spins on an arbitrary lattice. It offers the
+/// same Monte Carlo interface as `Grid`, but addresses sites by their index on the lattice rather
+/// than by coordinates.
+#[derive(Debug, Clone)]
+pub struct IsingModel<L: Lattice> {
+    lattice: L,
+    spins: Vec<Spin>,
+}
+
+impl<L: Lattice> IsingModel<L> {
+    /// # New random model
+    /// Creates a new model on the given lattice, where each spin has a random orientation. Fails
+    /// for a lattice without sites, like `Grid::new_random`.
+    pub fn new_random(lattice: L) -> Result<Self> {
+        check_sites(&lattice)?;
+        let spins = (0..lattice.number_of_sites())
+            .map(|_| rand::random::<Spin>())
+            .collect();
+
+        Ok(Self { lattice, spins })
+    }
+
+    /// # New constant model
+    /// Creates a new model on the given lattice, where each spin has the same orientation. Fails
+    /// for a lattice without sites, like `Grid::new_constant`.
+    pub fn new_constant(lattice: L, spin: Spin) -> Result<Self> {
+        check_sites(&lattice)?;
+        let spins = vec![spin; lattice.number_of_sites()];
+
+        Ok(Self { lattice, spins })
+    }
+
+    /// # Lattice
+    /// The lattice that the spins live on.
+    pub fn lattice(&self) -> &L {
+        &self.lattice
+    }
+
+    /// # Number of sites
+    /// The number of spins in the model.
+    pub fn number_of_sites(&self) -> usize {
+        self.spins.len()
+    }
+
+    /// # Get a spin
+    /// Retrieves the spin at the given site.
+    pub fn get(&self, site: usize) -> Spin {
+        self.spins[site]
+    }
+
+    /// # Get a spin as a plus/minus one
+    /// Retrieves the spin at the given site as a plus/minus one.
+    pub fn get_spin_as_float(&self, site: usize) -> f64 {
+        match self.spins[site] {
+            Spin::Up => 1.0,
+            Spin::Down => -1.0,
+            Spin::Zero | Spin::Vacant => 0.0,
+        }
+    }
+
+    /// # Set a spin
+    /// Sets the spin at the given site.
+    pub fn set(&mut self, site: usize, spin: Spin) {
+        self.spins[site] = spin;
+    }
+
+    /// # Get total energy
+    /// Gets the energy of all the bonds of a site and of the site in the field.
+    pub fn total_energy(&self, site: usize, coupling: f64, field: f64) -> f64 {
+        let our_spin = self.get_spin_as_float(site);
+        let neighbor_sum: f64 = self
+            .lattice
+            .neighbors(site)
+            .iter()
+            .map(|&neighbor| self.get_spin_as_float(neighbor))
+            .sum();
+
+        -coupling * our_spin * neighbor_sum - field * our_spin
+    }
+
+    /// # Single site step
+    /// Performs a single Metropolis step at a single site, at temperature T and field h in units
+    /// of the coupling. Vacant sites are left alone.
+    pub fn single_site_step(&mut self, site: usize, temperature: Temperature, field: f64) {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        self.single_site_step_reduced_with_rng(site, coupling, field, &mut rand::thread_rng());
+    }
+
+    fn single_site_step_reduced_with_rng<R: Rng>(
+        &mut self,
+        site: usize,
+        coupling: f64,
+        field: f64,
+        rng: &mut R,
+    ) {
+        if self.spins[site] == Spin::Vacant {
+            return;
+        }
+
+        // Flipping the spin negates every term of its local energy.
+        let energy_change = -2.0 * self.total_energy(site, coupling, field);
+
+        // Accept the flip with probability min(1, exp(-ΔE)).
+        if energy_change <= 0.0 || rng.gen::<f64>() < (-energy_change).exp() {
+            self.spins[site] = self.spins[site].flip();
+        }
+    }
+
+    /// # Step
+    /// Performs a single Monte Carlo sweep, made of as many single site steps as there are sites.
+    /// The sites are picked at random rather than in order: on a chain an ordered sweep carries
+    /// every domain wall along with it, since a spin between two opposite neighbours is always
+    /// flipped, and the samples then stop representing the equilibrium distribution. The sweep is
+    /// at temperature T and field h in units of the coupling.
+    pub fn step(&mut self, temperature: Temperature, field: f64) {
+        self.step_with_rng(temperature, field, &mut rand::thread_rng());
+    }
+
+    /// # Step with a generator
+    /// Performs the same sweep as `step`, drawing its random numbers from the given generator.
+    pub fn step_with_rng<R: Rng>(&mut self, temperature: Temperature, field: f64, rng: &mut R) {
+        self.step_reduced_with_rng(temperature.beta(), field * temperature.beta(), rng);
+    }
+
+    /// # Step at a reduced coupling
+    /// Performs the sweep of `step` at the reduced coupling K = βJ and field H = βh. Antiferromagnets,
+    /// whose reduced coupling is negative, are stepped with this.
+    pub fn step_reduced(&mut self, coupling: f64, field: f64) {
+        self.step_reduced_with_rng(coupling, field, &mut rand::thread_rng());
+    }
+
+    /// # Step at a reduced coupling with a generator
+    /// Performs the same sweep as `step_reduced`, drawing its random numbers from the given
+    /// generator.
+    pub fn step_reduced_with_rng<R: Rng>(&mut self, coupling: f64, field: f64, rng: &mut R) {
+        for _ in 0..self.spins.len() {
+            let site = rng.gen_range(0..self.spins.len());
+            self.single_site_step_reduced_with_rng(site, coupling, field, rng);
+        }
+    }
+
+    /// # Magnetization
+    /// The magnetization per site, unlike the total `Grid::magnetization`.
+    pub fn magnetization(&self) -> f64 {
+        (0..self.spins.len())
+            .map(|site| self.get_spin_as_float(site))
+            .sum::<f64>()
+            / self.spins.len() as f64
+    }
+
+    /// # Degree-weighted magnetization
+    /// The magnetization with every spin weighted by its number of neighbours, Σ k_i s_i / Σ k_i.
+    /// On heterogeneous networks the well-connected sites order first, so this picks up order
+    /// that the plain magnetization dilutes.
+    pub fn degree_weighted_magnetization(&self) -> f64 {
+        let mut weighted_sum = 0.0;
+        let mut total_degree = 0.0;
+        for site in 0..self.spins.len() {
+            let degree = self.lattice.degree(site) as f64;
+            weighted_sum += degree * self.get_spin_as_float(site);
+            total_degree += degree;
+        }
+        weighted_sum / total_degree
+    }
+
+    /// # Degree-resolved magnetization
+    /// The average spin of the sites of each degree, as a map from degree to magnetization. On
+    /// scale-free networks the hubs can be ordered while the poorly connected sites are not.
+    pub fn degree_resolved_magnetization(&self) -> BTreeMap<usize, f64> {
+        let mut sums: BTreeMap<usize, (f64, usize)> = BTreeMap::new();
+        for site in 0..self.spins.len() {
+            let entry = sums.entry(self.lattice.degree(site)).or_insert((0.0, 0));
+            entry.0 += self.get_spin_as_float(site);
+            entry.1 += 1;
+        }
+        sums.into_iter()
+            .map(|(degree, (sum, count))| (degree, sum / count as f64))
+            .collect()
+    }
+
+    /// # Energy
+    /// The energy per site at temperature T and field h in units of the coupling, in the reduced
+    /// units βE of the Boltzmann weight, with every bond counted once, unlike the total
+    /// `Grid::energy`.
+    pub fn energy(&self, temperature: Temperature, field: f64) -> f64 {
+        self.energy_reduced(temperature.beta(), field * temperature.beta())
+    }
+
+    /// # Energy at a reduced coupling
+    /// The energy per site of `energy` at the reduced coupling K = βJ and field H = βh, which may be
+    /// negative for an antiferromagnet.
+    pub fn energy_reduced(&self, coupling: f64, field: f64) -> f64 {
+        let mut energy = 0.0;
+        for site in 0..self.spins.len() {
+            let our_spin = self.get_spin_as_float(site);
+            let neighbor_sum: f64 = self
+                .lattice
+                .neighbors(site)
+                .iter()
+                .map(|&neighbor| self.get_spin_as_float(neighbor))
+                .sum();
+
+            // Each bond is seen from both of its ends, so only half of it belongs to this site.
+            energy -= 0.5 * coupling * our_spin * neighbor_sum + field * our_spin;
+        }
+        energy / self.spins.len() as f64
+    }
+}
+
+/// Checks that a lattice has a site to put a spin on.
+fn check_sites(lattice: &impl Lattice) -> Result<()> {
+    if lattice.number_of_sites() == 0 {
+        return Err(Error::InvalidParameter {
+            name: "number of sites",
+            value: 0.0,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::exact::{ExactChain, ExactEnumeration};
+    use crate::lattice::{Chain, GraphLattice, Hypercubic};
+
+    #[test]
+    fn test_energy_of_ordered_chain() {
+        let model = IsingModel::new_constant(Chain::new(10).unwrap(), Spin::Up).unwrap();
+        assert_eq!(model.energy(Temperature::new(1.0).unwrap(), 0.5), -1.5);
+        assert_eq!(model.magnetization(), 1.0);
+        assert_eq!(model.total_energy(3, 1.0, 0.5), -2.5);
+        assert!(IsingModel::new_random(GraphLattice::new(0)).is_err());
+    }
+
+    #[test]
+    fn test_degree_weighted_magnetization() {
+        // A star: the hub has three neighbours, each leaf has one.
+        let star = GraphLattice::from_edges(4, &[(0, 1), (0, 2), (0, 3)]);
+        let mut model = IsingModel::new_constant(star, Spin::Up).unwrap();
+        model.set(1, Spin::Down);
+        assert_eq!(model.magnetization(), 0.5);
+        assert_eq!(model.degree_weighted_magnetization(), 4.0 / 6.0);
+
+        let resolved = model.degree_resolved_magnetization();
+        assert_eq!(resolved.len(), 2);
+        assert_eq!(resolved[&1], 1.0 / 3.0);
+        assert_eq!(resolved[&3], 1.0);
+    }
+
+    #[test]
+    fn test_ordered_hypercubic_energy() {
+        // Every site of a D-dimensional hypercubic lattice owns D bonds.
+        let model =
+            IsingModel::new_constant(Hypercubic::new([3, 3, 3, 3]).unwrap(), Spin::Down).unwrap();
+        assert_eq!(model.energy(Temperature::new(1.0).unwrap(), 0.0), -4.0);
+    }
+
+    #[test]
+    fn test_hypercubic_matches_exact_enumeration() {
+        // The reduced field H = 0.1 at K = 0.3, given at the temperature T = 1/K as h = H/K.
+        let (temperature, field) = (Temperature::from_beta(0.3).unwrap(), 0.1 / 0.3);
+        let exact = ExactEnumeration::new(3, 3)
+            .unwrap()
+            .observables(temperature, field);
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut model =
+            IsingModel::new_constant(Hypercubic::new([3, 3]).unwrap(), Spin::Up).unwrap();
+        for _ in 0..1000 {
+            model.step_with_rng(temperature, field, &mut rng);
+        }
+
+        let number_of_sweeps = 40_000;
+        let mut energy = 0.0;
+        for _ in 0..number_of_sweeps {
+            model.step_with_rng(temperature, field, &mut rng);
+            energy += model.energy(temperature, field);
+        }
+        assert!((energy / number_of_sweeps as f64 - exact.energy).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_chain_matches_exact_solution() {
+        let (length, temperature) = (16, Temperature::new(2.0).unwrap());
+        let exact = ExactChain::new(length);
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut model = IsingModel::new_constant(Chain::new(length).unwrap(), Spin::Up).unwrap();
+        for _ in 0..1000 {
+            model.step_with_rng(temperature, 0.0, &mut rng);
+        }
+
+        let number_of_sweeps = 50_000;
+        let mut magnetization_squared = 0.0;
+        let mut correlation = [0.0; 3];
+        for _ in 0..number_of_sweeps {
+            model.step_with_rng(temperature, 0.0, &mut rng);
+            magnetization_squared += model.magnetization().powi(2);
+            for (distance, value) in correlation.iter_mut().enumerate() {
+                *value += model.get_spin_as_float(0) * model.get_spin_as_float(distance + 1);
+            }
+        }
+
+        let susceptibility = length as f64 * magnetization_squared / number_of_sweeps as f64;
+        let exact_susceptibility = exact.susceptibility(temperature);
+        assert!((susceptibility - exact_susceptibility).abs() < 0.05 * exact_susceptibility);
+        for (distance, value) in correlation.iter().enumerate() {
+            let sampled = value / number_of_sweeps as f64;
+            assert!((sampled - exact.correlation(temperature, distance + 1)).abs() < 0.03);
+        }
+    }
+}
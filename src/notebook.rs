@@ -0,0 +1,169 @@
+use std::io::Cursor;
+
+use image::ImageFormat;
+
+use crate::grid::Grid;
+use crate::render::Palette;
+use crate::scan::ScanResult;
+
+/// The longest side, in pixels, that a grid is drawn with in a notebook.
+const IMAGE_SIZE: usize = 400;
+
+/// The columns of a table of scan results.
+const COLUMNS: [&str; 10] = ["L", "T", "h", "Δ", "seed", "e", "|m|", "χ", "C", "U"];
+
+/// # HTML
+/// A fragment of HTML that the evcxr Jupyter kernel shows inline when it is the value of a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Html(pub String);
+
+impl Html {
+    /// # Display in evcxr
+    /// Prints the fragment in the form the evcxr kernel picks up.
+    pub fn evcxr_display(&self) {
+        print!("{}", content("text/html", &self.0));
+    }
+}
+
+impl Grid {
+    /// # Display in evcxr
+    /// Shows the grid inline in the evcxr Jupyter kernel, drawn with the default palette and
+    /// scaled up to about 400 pixels.
+    pub fn evcxr_display(&self) {
+        print!("{}", content("image/png", &base64(&self.to_png())));
+    }
+
+    /// Encodes the image of the grid as PNG.
+    fn to_png(&self) -> Vec<u8> {
+        let scale = (IMAGE_SIZE / self.width().max(self.height())).max(1) as u32;
+        let mut png = Cursor::new(Vec::new());
+        self.to_image(&Palette::default(), scale)
+            .expect("the scale is at least one")
+            .write_to(&mut png, ImageFormat::Png)
+            .expect("an image can be encoded in memory");
+        png.into_inner()
+    }
+}
+
+impl ScanResult {
+    /// # Display in evcxr
+    /// Shows the result inline in the evcxr Jupyter kernel as a table of one row.
+    pub fn evcxr_display(&self) {
+        results_table(std::slice::from_ref(self)).evcxr_display();
+    }
+}
+
+/// # Table of results
+/// The results of a scan as an HTML table, with the errors of the energy and the absolute
+/// magnetization next to them, which the evcxr kernel shows inline.
+pub fn results_table(results: &[ScanResult]) -> Html {
+    let mut html = String::from("<table>\n<tr>");
+    for column in COLUMNS {
+        html.push_str(&format!("<th>{}</th>", column));
+    }
+    html.push_str("</tr>\n");
+    for result in results {
+        let cells = [
+            result.size.to_string(),
+            result.temperature.to_string(),
+            result.field.to_string(),
+            result.crystal_field.map_or_else(
+                || "-".to_string(),
+                |crystal_field| crystal_field.to_string(),
+            ),
+            result.seed.to_string(),
+            format!("{:.5} ± {:.5}", result.energy.value, result.energy.error),
+            format!(
+                "{:.5} ± {:.5}",
+                result.absolute_magnetization.value, result.absolute_magnetization.error
+            ),
+            format!("{:.4}", result.susceptibility),
+            format!("{:.4}", result.specific_heat),
+            format!("{:.4}", result.binder_cumulant),
+        ];
+        html.push_str("<tr>");
+        for cell in cells {
+            html.push_str(&format!("<td>{}</td>", cell));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>");
+    Html(html)
+}
+
+/// Wraps content of the given MIME type in the markers that the evcxr kernel looks for.
+fn content(mime_type: &str, body: &str) -> String {
+    format!(
+        "EVCXR_BEGIN_CONTENT {}\n{}\nEVCXR_END_CONTENT\n",
+        mime_type, body
+    )
+}
+
+/// Encodes bytes in the standard base64 alphabet with padding, as images are embedded.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0_u32, |group, (i, &byte)| {
+            group | u32::from(byte) << (16 - 8 * i)
+        });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::measurement::Measurement;
+    use crate::spin::Spin;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64(&[255, 254, 253]), "//79");
+    }
+
+    #[test]
+    fn test_grid_png() {
+        let png = Grid::new_constant(8, 4, Spin::Up).unwrap().to_png();
+        let image = image::load_from_memory(&png).unwrap();
+        assert_eq!((image.width(), image.height()), (400, 200));
+        assert_eq!(
+            content("image/png", "abc"),
+            "EVCXR_BEGIN_CONTENT image/png\nabc\nEVCXR_END_CONTENT\n"
+        );
+    }
+
+    #[test]
+    fn test_results_table() {
+        let result = ScanResult {
+            size: 16,
+            temperature: 2.2,
+            field: 0.0,
+            crystal_field: None,
+            seed: 7,
+            energy: Measurement::new(-1.4, 0.01),
+            magnetization: Measurement::new(0.0, 0.02),
+            absolute_magnetization: Measurement::new(0.7, 0.03),
+            susceptibility: 12.0,
+            specific_heat: 1.6,
+            binder_cumulant: 0.55,
+            acceptance: 0.2,
+        };
+        let Html(html) = results_table(&[result, result]);
+        assert_eq!(html.matches("<tr>").count(), 3);
+        assert!(html.contains("<td>-1.40000 ± 0.01000</td>"));
+        assert!(html.starts_with("<table>") && html.ends_with("</table>"));
+    }
+}
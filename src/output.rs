@@ -0,0 +1,1318 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, UInt64Array};
+use arrow_schema::{Field, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::metadata::KeyValue;
+use parquet::file::properties::WriterProperties;
+use serde::{Deserialize, Serialize};
+
+use crate::grid::Grid;
+use crate::lattice::Hypercubic;
+use crate::model::IsingModel;
+use crate::provenance::Provenance;
+#[cfg(feature = "hdf5")]
+use crate::scan::ScanRecord;
+use crate::scan::ScanResult;
+use crate::simulation::Simulation;
+use crate::spin::Spin;
+
+/// # Observation
+/// The observables of a simulation measured after one sweep: the energy per site in units of
+/// k_BT, E/N, with every term that `Grid::energy` counts, the nearest- and
+/// next-nearest-neighbour bonds, the field and the crystal field, the magnetization per site, and
+/// the fraction of accepted moves in the sweep. Both are per occupied site, N excluding the
+/// vacancies, as in `Simulation::measure`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Observation {
+    pub sweep: usize,
+    pub energy: f64,
+    pub magnetization: f64,
+    pub acceptance: f64,
+}
+
+impl Observation {
+    /// # Observe a simulation
+    /// Measures the current configuration of the simulation, given the acceptance rate returned
+    /// by its last step.
+    pub fn of(simulation: &Simulation, acceptance: f64) -> Self {
+        let grid = simulation.grid();
+        let parameters = simulation.parameters();
+        let occupied = grid.number_of_occupied_sites() as f64;
+        Self {
+            sweep: simulation.sweep(),
+            energy: grid.energy_reduced(parameters.coupling, parameters.field) / occupied,
+            magnetization: grid.magnetization() / occupied,
+            acceptance,
+        }
+    }
+}
+
+/// # CSV writer
+/// Writes observations to a CSV file, one row per measurement under a header naming the columns,
+/// which spreadsheets, pandas and most plotting tools read directly. The file starts with the
+/// provenance of the run as JSON on a comment line, `# provenance: {...}`, which pandas skips
+/// with `comment="#"`.
+pub struct CsvWriter {
+    writer: csv::Writer<File>,
+}
+
+impl CsvWriter {
+    /// # Create a writer
+    /// Creates the file, replacing any existing one, and writes the provenance line.
+    pub fn create(path: impl AsRef<Path>, provenance: &Provenance) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(file, "# provenance: {}", provenance.to_json())?;
+        Ok(Self {
+            writer: csv::Writer::from_writer(file),
+        })
+    }
+
+    /// # Append to a writer
+    /// Opens the file to add rows after those already in it, as a resumed run does, without
+    /// writing the provenance line or the header again. A missing or empty file is created as by
+    /// `create`.
+    pub fn append(path: impl AsRef<Path>, provenance: &Provenance) -> io::Result<Self> {
+        let path = path.as_ref();
+        if fs::metadata(path).map_or(true, |metadata| metadata.len() == 0) {
+            return Self::create(path, provenance);
+        }
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(Self {
+            writer: csv::WriterBuilder::new()
+                .has_headers(false)
+                .from_writer(file),
+        })
+    }
+
+    /// # Write an observation
+    /// Appends a row. The header is written before the first row.
+    pub fn write(&mut self, observation: &Observation) -> io::Result<()> {
+        Ok(self.writer.serialize(observation)?)
+    }
+
+    /// # Flush
+    /// Writes the buffered rows to the file, so that they can be read while the run goes on.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// # Truncate an output
+/// Cuts a file back to the given length in bytes, as a resumed run does to drop what the run
+/// before wrote after its last checkpoint. A missing file or one that is not longer is left alone.
+pub fn truncate(path: impl AsRef<Path>, length: u64) -> io::Result<()> {
+    match OpenOptions::new().write(true).open(path) {
+        Ok(file) if file.metadata()?.len() > length => file.set_len(length),
+        Ok(_) => Ok(()),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error),
+    }
+}
+
+/// # Read observations
+/// Reads the observations of a CSV file written by `CsvWriter`, skipping its provenance line.
+pub fn read_csv(path: impl AsRef<Path>) -> io::Result<Vec<Observation>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(path)?;
+    reader
+        .deserialize()
+        .map(|observation| Ok(observation?))
+        .collect()
+}
+
+/// # Read the provenance of observations
+/// Reads the provenance line of a CSV file written by `CsvWriter`, if it has one.
+pub fn read_csv_provenance(path: impl AsRef<Path>) -> io::Result<Option<Provenance>> {
+    let mut line = String::new();
+    BufReader::new(File::open(path)?).read_line(&mut line)?;
+    line.trim_end()
+        .strip_prefix("# provenance: ")
+        .map(|json| serde_json::from_str(json).map_err(io::Error::from))
+        .transpose()
+}
+
+/// # Read run events
+/// Reads the events of a JSON Lines file written by `JsonLinesWriter`, skipping blank lines.
+pub fn read_json_lines(path: impl AsRef<Path>) -> io::Result<Vec<RunEvent>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+/// # Run event
+/// Something that happens during a run, as written by `JsonLinesWriter`. Each event becomes an
+/// object whose `event` field names its kind, so `jq 'select(.event == "measurement")'` picks out
+/// the observations. The first event of a run carries its provenance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum RunEvent {
+    /// The run starts on a width × height grid at the given reduced coupling and field.
+    Started {
+        width: usize,
+        height: usize,
+        coupling: f64,
+        field: f64,
+        sweeps: usize,
+        provenance: Provenance,
+    },
+    /// The observables after a sweep.
+    Measurement(Observation),
+    /// A checkpoint was written after the given sweep.
+    Checkpoint { sweep: usize, path: String },
+    /// The run ended after the given sweep.
+    Finished {
+        sweep: usize,
+        elapsed_seconds: f64,
+        mean_absolute_magnetization: f64,
+    },
+}
+
+/// # JSON Lines writer
+/// Writes run events as JSON Lines, one compact object per line. Every line is flushed as soon as
+/// it is written, so the output can be followed with `tail -f` or piped into jq while the run is
+/// still going.
+pub struct JsonLinesWriter<W: Write> {
+    writer: W,
+}
+
+impl JsonLinesWriter<BufWriter<File>> {
+    /// # Create a writer
+    /// Creates the file, replacing any existing one.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+    /// # New writer
+    /// Writes to the given destination, such as standard output.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// # Write an event
+    /// Appends the event as a line and flushes it.
+    pub fn write(&mut self, event: &RunEvent) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, event)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    /// # Into the destination
+    /// Gives back the destination.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// # HDF5 point
+/// What an HDF5 file holds about one parameter point: the size of the lattice, the parameters it
+/// was simulated at, the observations after every measurement sweep and the configurations saved
+/// along the way, each as its spins row by row with 1 for up, -1 for down and 0 for zero and
+/// vacant sites.
+#[cfg(feature = "hdf5")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Hdf5Point {
+    pub width: usize,
+    pub height: usize,
+    pub parameters: Vec<(String, f64)>,
+    pub observations: Vec<Observation>,
+    pub configurations: Vec<Vec<i8>>,
+}
+
+#[cfg(feature = "hdf5")]
+impl Hdf5Point {
+    /// # New point
+    /// A point on a lattice of the given size at the named parameters, with nothing recorded yet.
+    pub fn new(width: usize, height: usize, parameters: &[(&str, f64)]) -> Self {
+        Self {
+            width,
+            height,
+            parameters: parameters
+                .iter()
+                .map(|&(name, value)| (name.to_string(), value))
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    /// # Save a configuration
+    /// Appends the spins of the grid, which must have the size of the point, to the
+    /// configurations.
+    pub fn save_configuration(&mut self, grid: &Grid) {
+        let mut spins = Vec::with_capacity(grid.width() * grid.height());
+        for y in 0..grid.height() as i64 {
+            for x in 0..grid.width() as i64 {
+                spins.push(grid.get_spin_as_float(x, y) as i8);
+            }
+        }
+        self.configurations.push(spins);
+    }
+}
+
+#[cfg(feature = "hdf5")]
+impl From<ScanRecord> for Hdf5Point {
+    fn from(record: ScanRecord) -> Self {
+        let point = record.point;
+        Self {
+            width: point.size,
+            height: point.size,
+            parameters: [
+                ("temperature", Some(point.temperature.value())),
+                ("field", Some(point.field)),
+                ("crystal_field", point.crystal_field),
+            ]
+            .into_iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value?)))
+            .collect(),
+            observations: record.observations,
+            configurations: record.configurations,
+        }
+    }
+}
+
+/// # HDF5 writer
+/// Writes parameter points to an HDF5 file with one group per point, named `point_0000`,
+/// `point_0001` and so on in the order they are written. The width, height and parameters of the
+/// point are attributes of its group, the observations are one-dimensional datasets named like
+/// the fields of `Observation`, and the saved configurations form an `int8` dataset
+/// `configurations` of shape (snapshots, height, width), which h5py reads as a NumPy array. The
+/// provenance of the file is written as attributes of its root and that of each point as
+/// attributes of its group: the program, version, algorithm and creation time as strings, the
+/// seed if there is one, and the parameters as JSON.
+#[cfg(feature = "hdf5")]
+pub struct Hdf5Writer {
+    file: hdf5::Handle,
+    points: usize,
+}
+
+#[cfg(feature = "hdf5")]
+impl Hdf5Writer {
+    /// # Create a writer
+    /// Creates the file, replacing any existing one, with the provenance of the run or scan.
+    pub fn create(path: impl AsRef<Path>, provenance: &Provenance) -> io::Result<Self> {
+        let file = hdf5::create_file(path.as_ref())?;
+        hdf5::write_provenance(&file, provenance)?;
+        Ok(Self { file, points: 0 })
+    }
+
+    /// # Write a point
+    /// Adds a group holding everything recorded at a parameter point and its provenance, whose
+    /// seed is that of the point.
+    pub fn write(&mut self, point: &Hdf5Point, provenance: &Provenance) -> io::Result<()> {
+        let group = hdf5::create_group(&self.file, &format!("point_{:04}", self.points))?;
+        self.points += 1;
+        hdf5::write_provenance(&group, provenance)?;
+        hdf5::write_attribute(&group, "width", point.width as u64)?;
+        hdf5::write_attribute(&group, "height", point.height as u64)?;
+        for (name, value) in &point.parameters {
+            hdf5::write_attribute(&group, name, *value)?;
+        }
+
+        let observations = &point.observations;
+        let sweeps: Vec<u64> = observations
+            .iter()
+            .map(|observation| observation.sweep as u64)
+            .collect();
+        hdf5::write_dataset(&group, "sweep", &[sweeps.len()], &sweeps)?;
+        let series = |name: &str, value: fn(&Observation) -> f64| {
+            let values: Vec<f64> = observations.iter().map(value).collect();
+            hdf5::write_dataset(&group, name, &[values.len()], &values)
+        };
+        series("energy", |observation| observation.energy)?;
+        series("magnetization", |observation| observation.magnetization)?;
+        series("acceptance", |observation| observation.acceptance)?;
+
+        let spins = point.configurations.concat();
+        let shape = [point.configurations.len(), point.height, point.width];
+        hdf5::write_dataset(&group, "configurations", &shape, &spins)
+    }
+}
+
+/// Safe wrappers around the few calls of the HDF5 C library that `Hdf5Writer` needs.
+#[cfg(feature = "hdf5")]
+mod hdf5 {
+    use std::ffi::{c_void, CString};
+    use std::io;
+    use std::path::Path;
+
+    use hdf5_sys::h5::{herr_t, hsize_t, H5open};
+    use hdf5_sys::h5a::{H5Aclose, H5Acreate2, H5Awrite};
+    use hdf5_sys::h5d::{H5Dclose, H5Dcreate2, H5Dwrite};
+    use hdf5_sys::h5f::{H5Fclose, H5Fcreate, H5F_ACC_TRUNC};
+    use hdf5_sys::h5g::{H5Gclose, H5Gcreate2};
+    use hdf5_sys::h5i::hid_t;
+    use hdf5_sys::h5p::H5P_DEFAULT;
+    use hdf5_sys::h5s::{H5Sclose, H5Screate, H5Screate_simple, H5S_ALL, H5S_SCALAR};
+    use hdf5_sys::h5t::{
+        H5Tclose, H5Tcopy, H5Tset_cset, H5Tset_size, H5T_CSET_UTF8, H5T_C_S1, H5T_NATIVE_DOUBLE,
+        H5T_NATIVE_INT8, H5T_NATIVE_UINT64,
+    };
+
+    use crate::provenance::Provenance;
+
+    type Close = unsafe extern "C" fn(hid_t) -> herr_t;
+
+    /// An open HDF5 object, closed when it is dropped.
+    pub struct Handle {
+        id: hid_t,
+        close: Close,
+    }
+
+    impl Handle {
+        /// Takes the identifier returned by the named call, which is negative if the call failed.
+        fn new(id: hid_t, close: Close, call: &str) -> io::Result<Self> {
+            if id < 0 {
+                return Err(io::Error::other(format!("{} failed", call)));
+            }
+            Ok(Self { id, close })
+        }
+    }
+
+    impl Drop for Handle {
+        fn drop(&mut self) {
+            // Safety: the identifier is open, as it was checked when the handle was made, and it
+            // is closed only here.
+            unsafe { (self.close)(self.id) };
+        }
+    }
+
+    /// Fails if the status returned by the named call is negative.
+    fn check(status: herr_t, call: &str) -> io::Result<()> {
+        if status < 0 {
+            return Err(io::Error::other(format!("{} failed", call)));
+        }
+        Ok(())
+    }
+
+    /// A name as a C string.
+    fn c_string(name: &str) -> io::Result<CString> {
+        CString::new(name).map_err(io::Error::other)
+    }
+
+    /// A value that HDF5 stores natively.
+    pub trait Element: Copy {
+        /// The HDF5 type of the value in memory.
+        fn type_id() -> hid_t;
+    }
+
+    impl Element for i8 {
+        fn type_id() -> hid_t {
+            *H5T_NATIVE_INT8
+        }
+    }
+
+    impl Element for u64 {
+        fn type_id() -> hid_t {
+            *H5T_NATIVE_UINT64
+        }
+    }
+
+    impl Element for f64 {
+        fn type_id() -> hid_t {
+            *H5T_NATIVE_DOUBLE
+        }
+    }
+
+    /// Creates a file, replacing any existing one.
+    pub fn create_file(path: &Path) -> io::Result<Handle> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| io::Error::other("HDF5 paths must be valid UTF-8"))?;
+        let path = c_string(path)?;
+        // Safety: the library is initialised before its type identifiers are read, and the path
+        // is a C string that outlives the call.
+        unsafe {
+            check(H5open(), "H5open")?;
+            Handle::new(
+                H5Fcreate(path.as_ptr(), H5F_ACC_TRUNC, H5P_DEFAULT, H5P_DEFAULT),
+                H5Fclose,
+                "H5Fcreate",
+            )
+        }
+    }
+
+    /// Creates a group in a file or group.
+    pub fn create_group(parent: &Handle, name: &str) -> io::Result<Handle> {
+        let name = c_string(name)?;
+        // Safety: the parent is open and the name is a C string that outlives the call.
+        let id = unsafe {
+            H5Gcreate2(
+                parent.id,
+                name.as_ptr(),
+                H5P_DEFAULT,
+                H5P_DEFAULT,
+                H5P_DEFAULT,
+            )
+        };
+        Handle::new(id, H5Gclose, "H5Gcreate2")
+    }
+
+    /// Writes a scalar attribute of a file or group.
+    pub fn write_attribute<T: Element>(parent: &Handle, name: &str, value: T) -> io::Result<()> {
+        let name = c_string(name)?;
+        // Safety: the parent and the data space are open, the name is a C string, and the value
+        // is a single element of the type the attribute is created with.
+        unsafe {
+            let space = Handle::new(H5Screate(H5S_SCALAR), H5Sclose, "H5Screate")?;
+            let attribute = Handle::new(
+                H5Acreate2(
+                    parent.id,
+                    name.as_ptr(),
+                    T::type_id(),
+                    space.id,
+                    H5P_DEFAULT,
+                    H5P_DEFAULT,
+                ),
+                H5Aclose,
+                "H5Acreate2",
+            )?;
+            check(
+                H5Awrite(
+                    attribute.id,
+                    T::type_id(),
+                    &value as *const T as *const c_void,
+                ),
+                "H5Awrite",
+            )
+        }
+    }
+
+    /// Writes a UTF-8 string attribute of a file or group.
+    pub fn write_string_attribute(parent: &Handle, name: &str, value: &str) -> io::Result<()> {
+        let name = c_string(name)?;
+        let value = c_string(value)?;
+        // Safety: the parent, the string type and the data space are open, and the name and
+        // value are C strings whose length, with the terminating nul, is the size of the type.
+        unsafe {
+            let string = Handle::new(H5Tcopy(*H5T_C_S1), H5Tclose, "H5Tcopy")?;
+            check(
+                H5Tset_size(string.id, value.as_bytes_with_nul().len()),
+                "H5Tset_size",
+            )?;
+            check(H5Tset_cset(string.id, H5T_CSET_UTF8), "H5Tset_cset")?;
+            let space = Handle::new(H5Screate(H5S_SCALAR), H5Sclose, "H5Screate")?;
+            let attribute = Handle::new(
+                H5Acreate2(
+                    parent.id,
+                    name.as_ptr(),
+                    string.id,
+                    space.id,
+                    H5P_DEFAULT,
+                    H5P_DEFAULT,
+                ),
+                H5Aclose,
+                "H5Acreate2",
+            )?;
+            check(
+                H5Awrite(attribute.id, string.id, value.as_ptr() as *const c_void),
+                "H5Awrite",
+            )
+        }
+    }
+
+    /// Writes a provenance record as attributes of a file or group.
+    pub fn write_provenance(parent: &Handle, provenance: &Provenance) -> io::Result<()> {
+        write_string_attribute(parent, "program", &provenance.program)?;
+        write_string_attribute(parent, "version", &provenance.version)?;
+        write_string_attribute(parent, "algorithm", &provenance.algorithm)?;
+        write_string_attribute(parent, "created", &provenance.created)?;
+        if let Some(seed) = provenance.seed {
+            write_attribute(parent, "seed", seed)?;
+        }
+        let parameters = serde_json::to_string(&provenance.parameters)?;
+        write_string_attribute(parent, "parameters", &parameters)
+    }
+
+    /// Writes a dataset of the given shape in a file or group from its values in row-major
+    /// order.
+    pub fn write_dataset<T: Element>(
+        parent: &Handle,
+        name: &str,
+        shape: &[usize],
+        values: &[T],
+    ) -> io::Result<()> {
+        if shape.iter().product::<usize>() != values.len() {
+            return Err(io::Error::other(format!(
+                "{} values do not fill a dataset of shape {:?}",
+                values.len(),
+                shape
+            )));
+        }
+        let name = c_string(name)?;
+        let dimensions: Vec<hsize_t> = shape.iter().map(|&length| length as hsize_t).collect();
+        // Safety: the parent and the data space are open, the name is a C string, and the values
+        // fill the shape of the data space exactly.
+        unsafe {
+            let space = Handle::new(
+                H5Screate_simple(
+                    dimensions.len() as i32,
+                    dimensions.as_ptr(),
+                    std::ptr::null(),
+                ),
+                H5Sclose,
+                "H5Screate_simple",
+            )?;
+            let dataset = Handle::new(
+                H5Dcreate2(
+                    parent.id,
+                    name.as_ptr(),
+                    T::type_id(),
+                    space.id,
+                    H5P_DEFAULT,
+                    H5P_DEFAULT,
+                    H5P_DEFAULT,
+                ),
+                H5Dclose,
+                "H5Dcreate2",
+            )?;
+            check(
+                H5Dwrite(
+                    dataset.id,
+                    T::type_id(),
+                    H5S_ALL,
+                    H5S_ALL,
+                    H5P_DEFAULT,
+                    values.as_ptr() as *const c_void,
+                ),
+                "H5Dwrite",
+            )
+        }
+    }
+}
+
+/// # Write scan results as Parquet
+/// Writes the results of a parameter scan to a Snappy-compressed Parquet file with one row per
+/// scan point and one column per field of `ScanResult`, named like the fields, so that pandas or
+/// polars can load and query it directly. The crystal field is null at the Ising points. The
+/// provenance of the scan is stored as JSON in the key-value metadata of the file under
+/// `provenance`.
+pub fn write_parquet(
+    path: impl AsRef<Path>,
+    results: &[ScanResult],
+    provenance: &Provenance,
+) -> io::Result<()> {
+    let integers = |f: fn(&ScanResult) -> u64| -> ArrayRef {
+        Arc::new(UInt64Array::from_iter_values(results.iter().map(f)))
+    };
+    let floats = |f: fn(&ScanResult) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(results.iter().map(f)))
+    };
+    let columns: Vec<(&str, ArrayRef)> = vec![
+        ("size", integers(|result| result.size as u64)),
+        ("temperature", floats(|result| result.temperature)),
+        ("field", floats(|result| result.field)),
+        (
+            "crystal_field",
+            Arc::new(Float64Array::from_iter(
+                results.iter().map(|result| result.crystal_field),
+            )),
+        ),
+        ("seed", integers(|result| result.seed)),
+        ("energy", floats(|result| result.energy.value)),
+        ("energy_error", floats(|result| result.energy.error)),
+        ("magnetization", floats(|result| result.magnetization.value)),
+        (
+            "magnetization_error",
+            floats(|result| result.magnetization.error),
+        ),
+        (
+            "absolute_magnetization",
+            floats(|result| result.absolute_magnetization.value),
+        ),
+        (
+            "absolute_magnetization_error",
+            floats(|result| result.absolute_magnetization.error),
+        ),
+        ("susceptibility", floats(|result| result.susceptibility)),
+        ("specific_heat", floats(|result| result.specific_heat)),
+        ("binder_cumulant", floats(|result| result.binder_cumulant)),
+        ("acceptance", floats(|result| result.acceptance)),
+    ];
+    let schema = Arc::new(Schema::new(
+        columns
+            .iter()
+            .map(|(name, column)| {
+                Field::new(*name, column.data_type().clone(), column.null_count() > 0)
+            })
+            .collect::<Vec<Field>>(),
+    ));
+    let batch = RecordBatch::try_new(
+        Arc::clone(&schema),
+        columns.into_iter().map(|(_, column)| column).collect(),
+    )
+    .map_err(io::Error::other)?;
+
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .set_key_value_metadata(Some(vec![KeyValue::new(
+            "provenance".to_string(),
+            provenance.to_json(),
+        )]))
+        .build();
+    let mut writer = ArrowWriter::try_new(File::create(path)?, schema, Some(properties))
+        .map_err(io::Error::other)?;
+    writer.write(&batch).map_err(io::Error::other)?;
+    writer.close().map_err(io::Error::other)?;
+    Ok(())
+}
+
+/// # VTK image data
+/// Formats the spins of a model on a hypercubic lattice of up to three dimensions as a VTK
+/// ImageData (`.vti`) XML file, which ParaView and other VTK-based tools open directly. Every site
+/// is a point of unit spacing carrying an `Int8` array `spin` with the values +1, -1 and 0 for zero
+/// and vacant sites, so the configuration can be thresholded, sliced or volume rendered. Lower
+/// dimensional lattices become a single layer. The provenance is written as JSON in a comment,
+/// `<!-- provenance: {...} -->`, after the XML declaration.
+pub fn to_vti<const D: usize>(
+    model: &IsingModel<Hypercubic<D>>,
+    provenance: &Provenance,
+) -> String {
+    assert!(D <= 3, "VTK image data has at most three dimensions");
+    let mut extent = [0; 3];
+    for (axis, &length) in model.lattice().shape().iter().enumerate() {
+        extent[axis] = length - 1;
+    }
+    let extent = format!("0 {} 0 {} 0 {}", extent[0], extent[1], extent[2]);
+
+    let mut vti = String::new();
+    vti.push_str("<?xml version=\"1.0\"?>\n");
+    // A double hyphen cannot appear in an XML comment, and in JSON it can only appear inside a
+    // string, where the second hyphen can be escaped.
+    let provenance = provenance.to_json().replace("--", "-\\u002d");
+    vti.push_str(&format!("<!-- provenance: {provenance} -->\n"));
+    vti.push_str("<VTKFile type=\"ImageData\" version=\"1.0\" byte_order=\"LittleEndian\">\n");
+    vti.push_str(&format!(
+        "  <ImageData WholeExtent=\"{extent}\" Origin=\"0 0 0\" Spacing=\"1 1 1\">\n"
+    ));
+    vti.push_str(&format!("    <Piece Extent=\"{extent}\">\n"));
+    vti.push_str("      <PointData Scalars=\"spin\">\n");
+    vti.push_str("        <DataArray type=\"Int8\" Name=\"spin\" format=\"ascii\">\n");
+    // Sites are numbered with the first axis varying fastest, which is also the order of VTK.
+    let spins: Vec<String> = (0..model.number_of_sites())
+        .map(|site| (model.get_spin_as_float(site) as i8).to_string())
+        .collect();
+    for row in spins.chunks(model.lattice().shape()[0]) {
+        vti.push_str(&format!("          {}\n", row.join(" ")));
+    }
+    vti.push_str("        </DataArray>\n");
+    vti.push_str("      </PointData>\n");
+    vti.push_str("    </Piece>\n");
+    vti.push_str("  </ImageData>\n");
+    vti.push_str("</VTKFile>\n");
+    vti
+}
+
+/// # Save VTK image data
+/// Saves the spins of a model on a hypercubic lattice and the provenance of the run as a `.vti`
+/// file, as described in `to_vti`.
+pub fn save_vti<const D: usize>(
+    path: impl AsRef<Path>,
+    model: &IsingModel<Hypercubic<D>>,
+    provenance: &Provenance,
+) -> io::Result<()> {
+    fs::write(path, to_vti(model, provenance))
+}
+
+/// # Snapshot
+/// A configuration whose sites sit at integer positions in a periodic box, as needed by the
+/// particle formats of `write_lammps_dump` and `write_xyz`.
+pub trait Snapshot {
+    /// # Box size
+    /// The size of the periodic box along x, y and z, with 1 for the unused axes.
+    fn box_size(&self) -> [usize; 3];
+
+    /// # Sites
+    /// The position and spin of every site, in the order of the site numbers.
+    fn sites(&self) -> Vec<([usize; 3], Spin)>;
+}
+
+impl Snapshot for Grid {
+    fn box_size(&self) -> [usize; 3] {
+        [self.width(), self.height(), 1]
+    }
+
+    fn sites(&self) -> Vec<([usize; 3], Spin)> {
+        let mut sites = Vec::with_capacity(self.width() * self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                sites.push(([x, y, 0], self.get(x as i64, y as i64)));
+            }
+        }
+        sites
+    }
+}
+
+impl<const D: usize> Snapshot for IsingModel<Hypercubic<D>> {
+    fn box_size(&self) -> [usize; 3] {
+        assert!(D <= 3, "particle files have at most three dimensions");
+        let mut size = [1; 3];
+        size[..D].copy_from_slice(&self.lattice().shape());
+        size
+    }
+
+    fn sites(&self) -> Vec<([usize; 3], Spin)> {
+        assert!(D <= 3, "particle files have at most three dimensions");
+        (0..self.number_of_sites())
+            .map(|site| {
+                let mut position = [0; 3];
+                position[..D].copy_from_slice(&self.lattice().coordinates(site));
+                (position, self.get(site))
+            })
+            .collect()
+    }
+}
+
+/// The atom type of a spin in the particle formats: 1 for up, 2 for down and 3 for zero. Vacant
+/// sites are left out.
+fn atom_type(spin: Spin) -> Option<u8> {
+    match spin {
+        Spin::Up => Some(1),
+        Spin::Down => Some(2),
+        Spin::Zero => Some(3),
+        Spin::Vacant => None,
+    }
+}
+
+/// # Write a LAMMPS dump frame
+/// Appends a snapshot to a LAMMPS text dump as the frame of the given timestep, with the columns
+/// `id type x y z spin`. The atom type is 1 for up, 2 for down and 3 for zero spins, and vacant
+/// sites are left out. The provenance follows the timestep as JSON in an `ITEM: PROVENANCE`
+/// section, which OVITO and VMD skip. Writing one frame after another gives a trajectory that
+/// they play back as an animation.
+pub fn write_lammps_dump(
+    writer: &mut impl Write,
+    timestep: usize,
+    snapshot: &impl Snapshot,
+    provenance: &Provenance,
+) -> io::Result<()> {
+    let size = snapshot.box_size();
+    let sites = snapshot.sites();
+    let atoms: Vec<(usize, [usize; 3], Spin, u8)> = sites
+        .into_iter()
+        .enumerate()
+        .filter_map(|(site, (position, spin))| {
+            atom_type(spin).map(|kind| (site + 1, position, spin, kind))
+        })
+        .collect();
+    writeln!(writer, "ITEM: TIMESTEP\n{}", timestep)?;
+    writeln!(writer, "ITEM: PROVENANCE\n{}", provenance.to_json())?;
+    writeln!(writer, "ITEM: NUMBER OF ATOMS\n{}", atoms.len())?;
+    writeln!(writer, "ITEM: BOX BOUNDS pp pp pp")?;
+    for length in size {
+        writeln!(writer, "0 {}", length)?;
+    }
+    writeln!(writer, "ITEM: ATOMS id type x y z spin")?;
+    for (id, [x, y, z], spin, kind) in atoms {
+        writeln!(
+            writer,
+            "{} {} {} {} {} {}",
+            id,
+            kind,
+            x,
+            y,
+            z,
+            spin_value(spin)
+        )?;
+    }
+    Ok(())
+}
+
+/// # Write an XYZ frame
+/// Appends a snapshot to an extended XYZ file as a frame. The comment line carries the periodic
+/// box, the columns, `species pos spin`, and the provenance as a quoted JSON string with its
+/// quotes and backslashes escaped. The species are `U`, `D` and `Z` for up, down and zero spins,
+/// and vacant sites are left out.
+pub fn write_xyz(
+    writer: &mut impl Write,
+    timestep: usize,
+    snapshot: &impl Snapshot,
+    provenance: &Provenance,
+) -> io::Result<()> {
+    let [width, height, depth] = snapshot.box_size();
+    let atoms: Vec<([usize; 3], Spin)> = snapshot
+        .sites()
+        .into_iter()
+        .filter(|&(_, spin)| atom_type(spin).is_some())
+        .collect();
+    let provenance = provenance
+        .to_json()
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"");
+    writeln!(writer, "{}", atoms.len())?;
+    writeln!(
+        writer,
+        "Lattice=\"{} 0 0 0 {} 0 0 0 {}\" Properties=species:S:1:pos:R:3:spin:I:1 Time={} pbc=\"T T T\" provenance=\"{}\"",
+        width, height, depth, timestep, provenance
+    )?;
+    for ([x, y, z], spin) in atoms {
+        let species = match spin {
+            Spin::Up => "U",
+            Spin::Down => "D",
+            _ => "Z",
+        };
+        writeln!(writer, "{} {} {} {} {}", species, x, y, z, spin_value(spin))?;
+    }
+    Ok(())
+}
+
+/// The spin as the integer +1, -1 or 0.
+fn spin_value(spin: Spin) -> i8 {
+    match spin {
+        Spin::Up => 1,
+        Spin::Down => -1,
+        Spin::Zero | Spin::Vacant => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::*;
+    use crate::measurement::Measurement;
+    use crate::simulation::SimulationParameters;
+
+    #[test]
+    fn test_observation() {
+        let parameters = SimulationParameters {
+            coupling: 0.5,
+            field: 0.25,
+        };
+        let simulation =
+            Simulation::with_seed(Grid::new_constant(4, 3, Spin::Up).unwrap(), parameters, 1)
+                .unwrap();
+        let observation = Observation::of(&simulation, 0.1);
+        assert_eq!(observation.sweep, 0);
+        assert_eq!(observation.magnetization, 1.0);
+        assert_eq!(observation.energy, -(0.5 * 2.0 + 0.25));
+
+        let simulation = Simulation::with_seed(
+            Grid::new_checkerboard(4, 4).unwrap(),
+            SimulationParameters {
+                coupling: 1.0,
+                field: 0.0,
+            },
+            1,
+        )
+        .unwrap();
+        assert_eq!(Observation::of(&simulation, 0.0).energy, 2.0);
+
+        // A vacancy takes a site and its four bonds, and the observables are per occupied site,
+        // as the moments of the simulation are.
+        let mut grid = Grid::new_constant(4, 3, Spin::Up).unwrap();
+        grid.set(0, 0, Spin::Vacant);
+        let mut simulation = Simulation::with_seed(grid, parameters, 1).unwrap();
+        let observation = Observation::of(&simulation, 0.0);
+        assert_eq!(observation.magnetization, simulation.measure());
+        assert_eq!(observation.magnetization, 1.0);
+        assert!((observation.energy + (0.5 * 20.0 + 0.25 * 11.0) / 11.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_csv_writer() {
+        let path = env::temp_dir().join("ising_model_test_observations.csv");
+        let provenance = Provenance::new("metropolis-sequential", Some(3));
+        let mut writer = CsvWriter::create(&path, &provenance).unwrap();
+        for sweep in 1..=3 {
+            let observation = Observation {
+                sweep,
+                energy: -1.5,
+                magnetization: 0.25 * sweep as f64,
+                acceptance: 0.5,
+            };
+            writer.write(&observation).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines[0], format!("# provenance: {}", provenance.to_json()));
+        assert_eq!(lines[1], "sweep,energy,magnetization,acceptance");
+        assert_eq!(lines[3], "2,-1.5,0.5,0.5");
+        assert_eq!(lines.len(), 5);
+
+        let read = read_csv(&path).unwrap();
+        assert_eq!(read[2].magnetization, 0.75);
+        assert_eq!(
+            read_csv_provenance(&path).unwrap(),
+            Some(provenance.clone())
+        );
+
+        let mut writer = CsvWriter::append(&path, &provenance).unwrap();
+        writer
+            .write(&Observation {
+                sweep: 4,
+                energy: -1.5,
+                magnetization: 1.0,
+                acceptance: 0.5,
+            })
+            .unwrap();
+        writer.flush().unwrap();
+        let read = read_csv(&path).unwrap();
+        assert_eq!(read.len(), 4);
+        assert_eq!(read[3].sweep, 4);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 6);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_resume_after_kill() {
+        let csv = env::temp_dir().join("ising_model_test_killed.csv");
+        let jsonl = env::temp_dir().join("ising_model_test_killed.jsonl");
+        let checkpoint = env::temp_dir().join("ising_model_test_killed_checkpoint.json");
+        let parameters = SimulationParameters {
+            coupling: 0.44,
+            field: 0.0,
+        };
+        let mut simulation =
+            Simulation::with_seed(Grid::new_random(6, 6).unwrap(), parameters, 4).unwrap();
+        let provenance = simulation.provenance();
+        let mut writer = CsvWriter::create(&csv, &provenance).unwrap();
+        let mut log = JsonLinesWriter::create(&jsonl).unwrap();
+        // The run is killed 3 sweeps after its checkpoint at sweep 5, with its rows on disk.
+        for sweep in 1..=8 {
+            let acceptance = simulation.step();
+            let observation = Observation::of(&simulation, acceptance);
+            writer.write(&observation).unwrap();
+            log.write(&RunEvent::Measurement(observation)).unwrap();
+            writer.flush().unwrap();
+            if sweep == 5 {
+                let outputs = [&csv, &jsonl]
+                    .into_iter()
+                    .zip(["csv", "jsonl"])
+                    .map(|(path, name)| (name.to_string(), fs::metadata(path).unwrap().len()))
+                    .collect();
+                simulation
+                    .save_checkpoint_with_outputs(&checkpoint, &outputs)
+                    .unwrap();
+            }
+        }
+        drop((writer, log));
+
+        let mut simulation = Simulation::load_checkpoint(&checkpoint).unwrap();
+        let outputs = Simulation::load_checkpoint_outputs(&checkpoint).unwrap();
+        fs::remove_file(&checkpoint).unwrap();
+        truncate(&csv, outputs["csv"]).unwrap();
+        truncate(&jsonl, outputs["jsonl"]).unwrap();
+        let mut writer = CsvWriter::append(&csv, &provenance).unwrap();
+        let file = OpenOptions::new().append(true).open(&jsonl).unwrap();
+        let mut log = JsonLinesWriter::new(file);
+        while simulation.sweep() < 10 {
+            let acceptance = simulation.step();
+            let observation = Observation::of(&simulation, acceptance);
+            writer.write(&observation).unwrap();
+            log.write(&RunEvent::Measurement(observation)).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let sweeps: Vec<usize> = read_csv(&csv)
+            .unwrap()
+            .iter()
+            .map(|row| row.sweep)
+            .collect();
+        assert_eq!(sweeps, (1..=10).collect::<Vec<_>>());
+        let logged: Vec<usize> = read_json_lines(&jsonl)
+            .unwrap()
+            .iter()
+            .map(|event| match event {
+                RunEvent::Measurement(observation) => observation.sweep,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(logged, sweeps);
+        fs::remove_file(&csv).unwrap();
+        fs::remove_file(&jsonl).unwrap();
+        assert!(truncate(&csv, 0).is_ok());
+    }
+
+    #[test]
+    fn test_write_parquet() {
+        use arrow_array::cast::AsArray;
+        use arrow_array::types::{Float64Type, UInt64Type};
+        use arrow_array::Array;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        let results: Vec<ScanResult> = (0..3)
+            .map(|seed| ScanResult {
+                size: 16,
+                temperature: 2.0 + 0.1 * seed as f64,
+                field: 0.0,
+                crystal_field: (seed == 1).then_some(1.5),
+                seed,
+                energy: Measurement::new(-1.2, 0.01),
+                magnetization: Measurement::new(0.0, 0.02),
+                absolute_magnetization: Measurement::new(0.5, 0.02),
+                susceptibility: 10.0,
+                specific_heat: 1.5,
+                binder_cumulant: 0.4,
+                acceptance: 0.3,
+            })
+            .collect();
+        let path = env::temp_dir().join("ising_model_test_scan.parquet");
+        let provenance =
+            Provenance::new("metropolis-sequential", None).with_parameter("sizes", [16]);
+        write_parquet(&path, &results, &provenance).unwrap();
+
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap()).unwrap();
+        let metadata = builder
+            .metadata()
+            .file_metadata()
+            .key_value_metadata()
+            .unwrap();
+        let stored = metadata
+            .iter()
+            .find(|entry| entry.key == "provenance")
+            .unwrap();
+        assert_eq!(stored.value.as_deref(), Some(provenance.to_json().as_str()));
+        let reader = builder.build().unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(batches.iter().map(RecordBatch::num_rows).sum::<usize>(), 3);
+        let batch = &batches[0];
+        assert_eq!(batch.num_columns(), 15);
+        let seeds = batch
+            .column_by_name("seed")
+            .unwrap()
+            .as_primitive::<UInt64Type>();
+        assert_eq!(seeds.values().to_vec(), vec![0, 1, 2]);
+        let temperatures = batch
+            .column_by_name("temperature")
+            .unwrap()
+            .as_primitive::<Float64Type>();
+        assert_eq!(temperatures.value(2), 2.2);
+        let crystal_fields = batch
+            .column_by_name("crystal_field")
+            .unwrap()
+            .as_primitive::<Float64Type>();
+        assert!(crystal_fields.is_null(0));
+        assert_eq!(crystal_fields.value(1), 1.5);
+    }
+
+    #[test]
+    fn test_vti() {
+        let mut model =
+            IsingModel::new_constant(Hypercubic::new([3, 2, 2]).unwrap(), Spin::Up).unwrap();
+        let lattice = model.lattice().clone();
+        model.set(lattice.site([1, 0, 1]), Spin::Down);
+        model.set(lattice.site([2, 1, 1]), Spin::Vacant);
+
+        let path = env::temp_dir().join("ising_model_test_spins.vti");
+        let provenance = Provenance::new("metropolis--sequential", Some(5));
+        save_vti(&path, &model, &provenance).unwrap();
+        let vti = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(vti.contains("<ImageData WholeExtent=\"0 2 0 1 0 1\""));
+        let comment = vti.lines().nth(1).unwrap();
+        let json = comment
+            .strip_prefix("<!-- provenance: ")
+            .and_then(|comment| comment.strip_suffix(" -->"))
+            .unwrap();
+        assert!(!json.contains("--"));
+        assert_eq!(
+            serde_json::from_str::<Provenance>(json).unwrap(),
+            provenance
+        );
+        let values: Vec<i8> = vti
+            .split("format=\"ascii\">")
+            .nth(1)
+            .unwrap()
+            .split("</DataArray>")
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .map(|value| value.parse().unwrap())
+            .collect();
+        assert_eq!(values.len(), 12);
+        assert_eq!(values[6 + 1], -1);
+        assert_eq!(values[6 + 3 + 2], 0);
+        assert_eq!(values.iter().sum::<i8>(), 9);
+
+        let square =
+            IsingModel::new_constant(Hypercubic::new([4, 5]).unwrap(), Spin::Down).unwrap();
+        assert!(to_vti(&square, &provenance).contains("Extent=\"0 3 0 4 0 0\""));
+    }
+
+    #[test]
+    fn test_lammps_dump() {
+        let mut grid = Grid::new_constant(3, 2, Spin::Up).unwrap();
+        grid.set(1, 0, Spin::Down);
+        grid.set(2, 1, Spin::Vacant);
+        let provenance = Provenance::new("metropolis-sequential", Some(2));
+        let mut dump = Vec::new();
+        write_lammps_dump(&mut dump, 0, &grid, &provenance).unwrap();
+        write_lammps_dump(&mut dump, 100, &grid, &provenance).unwrap();
+        let text = String::from_utf8(dump).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2 * (11 + 5));
+        assert_eq!(lines[2], "ITEM: PROVENANCE");
+        assert_eq!(lines[3], provenance.to_json());
+        assert_eq!(lines[5], "5");
+        assert_eq!(&lines[7..10], ["0 3", "0 2", "0 1"]);
+        assert_eq!(lines[10], "ITEM: ATOMS id type x y z spin");
+        assert_eq!(lines[12], "2 2 1 0 0 -1");
+        assert_eq!(lines[15], "5 1 1 1 0 1");
+        assert_eq!(lines[17], "100");
+
+        let model =
+            IsingModel::new_constant(Hypercubic::new([2, 2, 3]).unwrap(), Spin::Down).unwrap();
+        let mut dump = Vec::new();
+        write_lammps_dump(&mut dump, 7, &model, &provenance).unwrap();
+        let text = String::from_utf8(dump).unwrap();
+        assert!(text.contains("0 3\nITEM: ATOMS"));
+        assert!(text.ends_with("12 2 1 1 2 -1\n"));
+    }
+
+    #[test]
+    fn test_xyz() {
+        let model = IsingModel::new_constant(Hypercubic::new([2, 3]).unwrap(), Spin::Up).unwrap();
+        let provenance = Provenance::new("wolff", Some(4)).with_parameter("label", "\"a\\b\"");
+        let mut xyz = Vec::new();
+        write_xyz(&mut xyz, 5, &model, &provenance).unwrap();
+        let text = String::from_utf8(xyz).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 8);
+        assert_eq!(lines[0], "6");
+        assert!(lines[1].starts_with("Lattice=\"2 0 0 0 3 0 0 0 1\""));
+        assert!(lines[1].contains("Time=5"));
+        let quoted = lines[1]
+            .split_once(" provenance=\"")
+            .and_then(|(_, value)| value.strip_suffix('"'))
+            .unwrap();
+        let mut json = String::new();
+        let mut characters = quoted.chars();
+        while let Some(character) = characters.next() {
+            match character {
+                '\\' => json.extend(characters.next()),
+                '"' => panic!("unescaped quote in {}", quoted),
+                _ => json.push(character),
+            }
+        }
+        assert_eq!(
+            serde_json::from_str::<Provenance>(&json).unwrap(),
+            provenance
+        );
+        assert_eq!(lines[3], "U 1 0 0 1");
+        assert_eq!(lines[7], "U 1 2 0 1");
+    }
+
+    #[test]
+    fn test_json_lines_writer() {
+        let events = vec![
+            RunEvent::Started {
+                width: 8,
+                height: 8,
+                coupling: 0.44,
+                field: 0.0,
+                sweeps: 10,
+                provenance: Provenance::new("metropolis-sequential", Some(1)),
+            },
+            RunEvent::Measurement(Observation {
+                sweep: 1,
+                energy: -0.5,
+                magnetization: 0.125,
+                acceptance: 0.25,
+            }),
+            RunEvent::Checkpoint {
+                sweep: 1,
+                path: "checkpoint.json".to_string(),
+            },
+        ];
+        let mut writer = JsonLinesWriter::new(Vec::new());
+        for event in &events {
+            writer.write(event).unwrap();
+        }
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[1],
+            r#"{"event":"measurement","sweep":1,"energy":-0.5,"magnetization":0.125,"acceptance":0.25}"#
+        );
+        let read: Vec<RunEvent> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(read, events);
+
+        let path = env::temp_dir().join("ising_model_test_events.jsonl");
+        fs::write(&path, format!("{}\n", text)).unwrap();
+        assert_eq!(read_json_lines(&path).unwrap(), events);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "hdf5")]
+    #[test]
+    fn test_hdf5_writer() {
+        let parameters = SimulationParameters {
+            coupling: 0.5,
+            field: 0.0,
+        };
+        let mut simulation =
+            Simulation::with_seed(Grid::new_random(4, 3).unwrap(), parameters, 1).unwrap();
+        let provenance = simulation.provenance();
+        let mut point = Hdf5Point::new(4, 3, &[("coupling", 0.5), ("field", 0.0)]);
+        for _ in 0..10 {
+            let acceptance = simulation.step();
+            point
+                .observations
+                .push(Observation::of(&simulation, acceptance));
+            point.save_configuration(simulation.grid());
+        }
+        assert_eq!(point.configurations[0].len(), 12);
+
+        let path = env::temp_dir().join("ising_model_test_run.h5");
+        let mut writer = Hdf5Writer::create(&path, &provenance).unwrap();
+        writer.write(&point, &provenance).unwrap();
+        point.configurations.pop();
+        point.configurations[0].pop();
+        assert!(writer.write(&point, &provenance).is_err());
+        drop(writer);
+
+        let contents = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(&contents[..8], b"\x89HDF\r\n\x1a\n");
+    }
+
+    #[cfg(feature = "hdf5")]
+    #[test]
+    fn test_hdf5_scan_point() {
+        use crate::scan::Scan;
+        use crate::temperature::Temperature;
+
+        let scan = Scan {
+            sizes: vec![4],
+            temperatures: vec![Temperature::new(2.0).unwrap()],
+            fields: vec![0.0],
+            crystal_fields: vec![None],
+            seeds: vec![1, 2],
+            thermalization_sweeps: 5,
+            measurement_sweeps: 10,
+        };
+        let path = env::temp_dir().join("ising_model_test_scan.h5");
+        let mut writer = Hdf5Writer::create(&path, &scan.provenance()).unwrap();
+        let results = scan
+            .record(1, Some(5), |record| {
+                let provenance = scan.point_provenance(record.point);
+                let point = Hdf5Point::from(record);
+                assert_eq!((point.width, point.height), (4, 4));
+                assert_eq!(point.configurations.len(), 2);
+                Ok(writer.write(&point, &provenance)?)
+            })
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        drop(writer);
+
+        let contents = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(&contents[..8], b"\x89HDF\r\n\x1a\n");
+    }
+}
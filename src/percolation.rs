@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+
+use crate::grid::{Grid, NEIGHBOR_OFFSETS};
+use crate::spin::Spin;
+
+/// # Occupied clusters
+/// The connected clusters of occupied sites of a diluted grid. In a diluted magnet the spins can
+/// only order within a cluster, so long-range order needs a cluster that wraps around the periodic
+/// grid, which on the square lattice happens above an occupation of about 0.5927.
+#[derive(Debug, Clone)]
+pub struct OccupiedClusters {
+    width: usize,
+    labels: Vec<Option<usize>>,
+    sizes: Vec<usize>,
+    wrapping: Vec<bool>,
+}
+
+impl OccupiedClusters {
+    /// # Find the clusters
+    /// Labels the clusters of occupied sites with a breadth-first search. Each site also records
+    /// its unwrapped position relative to the first site of its cluster, so reaching a site a
+    /// second time at a different unwrapped position shows that the cluster winds around the grid.
+    pub fn new(grid: &Grid) -> Self {
+        let (width, height) = (grid.width(), grid.height());
+
+        let mut labels = vec![None; width * height];
+        let mut unwrapped = vec![(0, 0); width * height];
+        let mut sizes = Vec::new();
+        let mut wrapping = Vec::new();
+        let mut queue = VecDeque::new();
+
+        for start in 0..width * height {
+            let (x, y) = ((start % width) as i64, (start / width) as i64);
+            if labels[start].is_some() || grid.get(x, y) == Spin::Vacant {
+                continue;
+            }
+
+            let label = sizes.len();
+            let mut size = 0;
+            let mut wraps = false;
+            labels[start] = Some(label);
+            unwrapped[start] = (x, y);
+            queue.push_back(start);
+
+            while let Some(site) = queue.pop_front() {
+                size += 1;
+                let (ux, uy) = unwrapped[site];
+                for (dx, dy) in NEIGHBOR_OFFSETS {
+                    let (nx, ny) = (ux + dx, uy + dy);
+                    if grid.get(nx, ny) == Spin::Vacant {
+                        continue;
+                    }
+                    let neighbor = grid.get_index(nx, ny);
+                    if labels[neighbor].is_none() {
+                        labels[neighbor] = Some(label);
+                        unwrapped[neighbor] = (nx, ny);
+                        queue.push_back(neighbor);
+                    } else if unwrapped[neighbor] != (nx, ny) {
+                        wraps = true;
+                    }
+                }
+            }
+
+            sizes.push(size);
+            wrapping.push(wraps);
+        }
+
+        Self {
+            width,
+            labels,
+            sizes,
+            wrapping,
+        }
+    }
+
+    /// # Label
+    /// The label of the cluster that contains the site, or `None` for a vacancy. The coordinates
+    /// must lie inside the grid.
+    pub fn label(&self, x: usize, y: usize) -> Option<usize> {
+        self.labels[y * self.width + x]
+    }
+
+    /// # Sizes
+    /// The number of sites in each cluster, indexed by label.
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// # Largest cluster
+    /// The label of the largest cluster, if there are any occupied sites.
+    pub fn largest(&self) -> Option<usize> {
+        (0..self.sizes.len()).max_by_key(|&label| self.sizes[label])
+    }
+
+    /// # Percolates
+    /// Whether any cluster wraps around the grid in at least one direction.
+    pub fn percolates(&self) -> bool {
+        self.wrapping.iter().any(|&wraps| wraps)
+    }
+
+    /// # Percolation strength
+    /// The fraction of all sites that belong to the largest cluster, P∞, which is the order
+    /// parameter of the percolation transition.
+    pub fn percolation_strength(&self) -> f64 {
+        self.largest().map_or(0.0, |label| {
+            self.sizes[label] as f64 / self.labels.len() as f64
+        })
+    }
+
+    /// # Mean cluster size
+    /// The average size of the cluster that a site belongs to, Σ s² / N, leaving out the largest
+    /// cluster. It diverges at the percolation threshold, like a susceptibility.
+    pub fn mean_cluster_size(&self) -> f64 {
+        let largest = self.largest();
+        let sum: usize = (0..self.sizes.len())
+            .filter(|&label| Some(label) != largest)
+            .map(|label| self.sizes[label] * self.sizes[label])
+            .sum();
+        sum as f64 / self.labels.len() as f64
+    }
+
+    /// # Cluster magnetization
+    /// The magnetization per spin of a single cluster. Since clusters are disconnected from each
+    /// other, the largest cluster can be ordered while the small ones still fluctuate freely.
+    pub fn cluster_magnetization(&self, grid: &Grid, label: usize) -> f64 {
+        let sum: f64 = self
+            .labels
+            .iter()
+            .enumerate()
+            .filter(|&(_, &site_label)| site_label == Some(label))
+            .map(|(site, _)| {
+                grid.get_spin_as_float((site % self.width) as i64, (site / self.width) as i64)
+            })
+            .sum();
+        sum / self.sizes[label] as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_full_grid_percolates() {
+        let clusters = OccupiedClusters::new(&Grid::new_constant(6, 5, Spin::Up).unwrap());
+        assert_eq!(clusters.sizes(), &[30]);
+        assert!(clusters.percolates());
+        assert_eq!(clusters.percolation_strength(), 1.0);
+        assert_eq!(clusters.mean_cluster_size(), 0.0);
+    }
+
+    #[test]
+    fn test_checkerboard_vacancies() {
+        let mut grid = Grid::new_constant(4, 4, Spin::Up).unwrap();
+        for y in 0..4 {
+            for x in 0..4 {
+                if (x + y) % 2 == 1 {
+                    grid.set(x, y, Spin::Vacant);
+                }
+            }
+        }
+
+        let clusters = OccupiedClusters::new(&grid);
+        assert_eq!(clusters.sizes().len(), 8);
+        assert!(!clusters.percolates());
+        assert_eq!(clusters.label(1, 0), None);
+        assert_eq!(clusters.mean_cluster_size(), 7.0 / 16.0);
+    }
+
+    #[test]
+    fn test_single_row_wraps() {
+        let mut grid = Grid::new_constant(5, 5, Spin::Vacant).unwrap();
+        for x in 0..5 {
+            grid.set(x, 2, Spin::Down);
+        }
+        grid.set(0, 0, Spin::Up);
+
+        let clusters = OccupiedClusters::new(&grid);
+        assert!(clusters.percolates());
+        let largest = clusters.largest().unwrap();
+        assert_eq!(clusters.sizes()[largest], 5);
+        assert_eq!(clusters.cluster_magnetization(&grid, largest), -1.0);
+        assert_eq!(
+            clusters.cluster_magnetization(&grid, clusters.label(0, 0).unwrap()),
+            1.0
+        );
+    }
+
+    #[test]
+    fn test_percolation_threshold() {
+        // Well below and well above the site percolation threshold of the square lattice.
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut sparse = Grid::new_constant(64, 64, Spin::Up).unwrap();
+        sparse.dilute(0.7, &mut rng).unwrap();
+        let mut dense = Grid::new_constant(64, 64, Spin::Up).unwrap();
+        dense.dilute(0.2, &mut rng).unwrap();
+
+        assert!(!OccupiedClusters::new(&sparse).percolates());
+        assert!(OccupiedClusters::new(&dense).percolates());
+        assert!(OccupiedClusters::new(&dense).percolation_strength() > 0.7);
+    }
+}
@@ -0,0 +1,244 @@
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use crate::scan::ScanResult;
+
+/// An observable of a scan result.
+type Observable = fn(&ScanResult) -> f64;
+
+/// The observables of a scan that are plotted, with the captions of their panels.
+const OBSERVABLES: [(&str, Observable); 5] = [
+    ("magnetization |m|", |result| {
+        result.absolute_magnetization.value
+    }),
+    ("energy e", |result| result.energy.value),
+    ("susceptibility χ", |result| result.susceptibility),
+    ("specific heat C", |result| result.specific_heat),
+    ("Binder cumulant U₄", |result| result.binder_cumulant),
+];
+
+/// # Series of a scan
+/// The results of a scan grouped by size, field and crystal field, each group averaged over its
+/// seeds at every temperature and sorted by temperature, with the label of its curve.
+struct Series {
+    label: String,
+    points: Vec<(f64, [f64; 5])>,
+}
+
+fn series(results: &[ScanResult]) -> Vec<Series> {
+    let several_fields = results
+        .iter()
+        .any(|result| result.field != results[0].field);
+    let several_crystal_fields = results
+        .iter()
+        .any(|result| result.crystal_field != results[0].crystal_field);
+    // The sums of the observables at each temperature of each size, field and crystal field, and
+    // their number.
+    type Key = (usize, f64, Option<f64>);
+    type Sums = (f64, [f64; 5], usize);
+    let mut groups: Vec<(Key, Vec<Sums>)> = Vec::new();
+    for result in results {
+        let key = (result.size, result.field, result.crystal_field);
+        let index = match groups.iter().position(|(group, _)| *group == key) {
+            Some(index) => index,
+            None => {
+                groups.push((key, Vec::new()));
+                groups.len() - 1
+            }
+        };
+        let values = OBSERVABLES.map(|(_, observable)| observable(result));
+        let points = &mut groups[index].1;
+        match points
+            .iter_mut()
+            .find(|(temperature, _, _)| *temperature == result.temperature)
+        {
+            Some((_, sums, count)) => {
+                for (sum, value) in sums.iter_mut().zip(values) {
+                    *sum += value;
+                }
+                *count += 1;
+            }
+            None => points.push((result.temperature, values, 1)),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|((size, field, crystal_field), points)| {
+            let mut points: Vec<(f64, [f64; 5])> = points
+                .into_iter()
+                .map(|(temperature, sums, count)| (temperature, sums.map(|sum| sum / count as f64)))
+                .collect();
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let mut label = format!("L = {}", size);
+            if several_fields {
+                label.push_str(&format!(", h = {}", field));
+            }
+            if several_crystal_fields {
+                match crystal_field {
+                    Some(crystal_field) => label.push_str(&format!(", Δ = {}", crystal_field)),
+                    None => label.push_str(", Ising"),
+                }
+            }
+            Series { label, points }
+        })
+        .collect()
+}
+
+/// The range spanned by the finite values, widened by a margin so that no point sits on an axis.
+fn range(values: impl Iterator<Item = f64>) -> Range<f64> {
+    let (low, high) = values
+        .filter(|value| value.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), value| {
+            (low.min(value), high.max(value))
+        });
+    if low > high {
+        return 0.0..1.0;
+    }
+    let margin = if high > low {
+        0.05 * (high - low)
+    } else {
+        0.5 * low.abs().max(1.0)
+    };
+    low - margin..high + margin
+}
+
+/// # Plot a scan
+/// Draws |m|, e, χ, C and U₄ against the temperature in one panel each, with a curve for every
+/// size, and every field if there are several, averaged over the seeds. The image is an SVG if
+/// the path ends in `.svg` and a PNG otherwise.
+pub fn plot_scan(path: impl AsRef<Path>, results: &[ScanResult]) -> io::Result<()> {
+    let path = path.as_ref();
+    let size = (1200, 1100);
+    if path.extension().is_some_and(|extension| extension == "svg") {
+        draw(SVGBackend::new(path, size).into_drawing_area(), results)
+            .map_err(|error| io::Error::other(error.to_string()))
+    } else {
+        draw(BitMapBackend::new(path, size).into_drawing_area(), results)
+            .map_err(|error| io::Error::other(error.to_string()))
+    }
+}
+
+fn draw<DB: DrawingBackend>(
+    root: DrawingArea<DB, Shift>,
+    results: &[ScanResult],
+) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+    root.fill(&WHITE)?;
+    let series = series(results);
+    let temperatures = range(results.iter().map(|result| result.temperature));
+    for (index, (panel, (caption, _))) in root
+        .split_evenly((3, 2))
+        .iter()
+        .zip(OBSERVABLES)
+        .enumerate()
+    {
+        let values = range(
+            series
+                .iter()
+                .flat_map(|curve| curve.points.iter().map(|(_, values)| values[index])),
+        );
+        let mut chart = ChartBuilder::on(panel)
+            .caption(caption, ("sans-serif", 22))
+            .margin(12)
+            .x_label_area_size(35)
+            .y_label_area_size(60)
+            .build_cartesian_2d(temperatures.clone(), values)?;
+        chart.configure_mesh().x_desc("T").draw()?;
+        for (number, curve) in series.iter().enumerate() {
+            let color = Palette99::pick(number).to_rgba();
+            let points: Vec<(f64, f64)> = curve
+                .points
+                .iter()
+                .map(|(temperature, values)| (*temperature, values[index]))
+                .filter(|(_, value)| value.is_finite())
+                .collect();
+            chart
+                .draw_series(LineSeries::new(points.clone(), color.stroke_width(2)))?
+                .label(curve.label.as_str())
+                .legend(move |(x, y)| {
+                    PathElement::new([(x, y), (x + 20, y)], color.stroke_width(2))
+                });
+            chart.draw_series(
+                points
+                    .into_iter()
+                    .map(|point| Circle::new(point, 3, color.filled())),
+            )?;
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperRight)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+    }
+    root.present()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+    use crate::scan::Scan;
+    use crate::temperature::Temperature;
+
+    #[test]
+    fn test_series() {
+        let scan = Scan {
+            sizes: vec![4, 6],
+            temperatures: [3.0, 2.0].map(|t| Temperature::new(t).unwrap()).to_vec(),
+            fields: vec![0.0],
+            crystal_fields: vec![None],
+            seeds: vec![1, 2],
+            thermalization_sweeps: 10,
+            measurement_sweeps: 20,
+        };
+        let results = scan.run(2).unwrap();
+        let series = series(&results);
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].label, "L = 4");
+        assert_eq!(series[0].points.len(), 2);
+        assert_eq!(series[0].points[0].0, 2.0);
+        let energies: Vec<f64> = results
+            .iter()
+            .filter(|result| result.size == 4 && result.temperature == 2.0)
+            .map(|result| result.energy.value)
+            .collect();
+        let mean = energies.iter().sum::<f64>() / 2.0;
+        assert!((series[0].points[0].1[1] - mean).abs() < 1e-12);
+
+        assert_eq!(range([1.0, f64::NAN, 3.0].into_iter()), 0.9..3.1);
+        assert_eq!(range([2.0].into_iter()), 1.0..3.0);
+        assert_eq!(range(std::iter::empty()), 0.0..1.0);
+    }
+
+    #[test]
+    fn test_plot_scan() {
+        let scan = Scan {
+            sizes: vec![4],
+            temperatures: [1.5, 2.5, 3.5]
+                .map(|t| Temperature::new(t).unwrap())
+                .to_vec(),
+            fields: vec![0.0, 0.1],
+            crystal_fields: vec![None],
+            seeds: vec![1],
+            thermalization_sweeps: 10,
+            measurement_sweeps: 20,
+        };
+        let results = scan.run(2).unwrap();
+        let svg = env::temp_dir().join("ising_plot_scan_test.svg");
+        plot_scan(&svg, &results).unwrap();
+        let text = fs::read_to_string(&svg).unwrap();
+        assert!(text.contains("<svg") && text.contains("L = 4, h = 0.1"));
+        fs::remove_file(&svg).unwrap();
+
+        let png = env::temp_dir().join("ising_plot_scan_test.png");
+        plot_scan(&png, &results).unwrap();
+        assert!(fs::read(&png).unwrap().starts_with(b"\x89PNG"));
+        fs::remove_file(&png).unwrap();
+    }
+}
@@ -0,0 +1,282 @@
+use rand::Rng;
+
+use crate::error::{check_size, Error, Result};
+use crate::lattice::{Hypercubic, Lattice};
+use crate::temperature::Temperature;
+
+/// # Potts model
+/// The q-state Potts model on a periodic width × height grid. Every site holds one of q states,
+/// and neighbouring sites in the same state lower the energy, E = -K Σ_⟨ij⟩ δ(σ_i, σ_j), in the
+/// same reduced units as `Grid::step`. For q = 2 it is the Ising model with K_Ising = K / 2. On the
+/// square lattice the transition is continuous for q ≤ 4 and first order above.
+#[derive(Debug, Clone)]
+pub struct PottsModel {
+    states: Vec<usize>,
+    lattice: Hypercubic<2>,
+    q: usize,
+}
+
+impl PottsModel {
+    /// # New random Potts model
+    /// Creates a grid where every site is in a uniformly random state.
+    pub fn new_random<R: Rng>(width: usize, height: usize, q: usize, rng: &mut R) -> Result<Self> {
+        let mut model = Self::new_constant(width, height, q, 0)?;
+        for state in model.states.iter_mut() {
+            *state = rng.gen_range(0..q);
+        }
+        Ok(model)
+    }
+
+    /// # New constant Potts model
+    /// Creates a grid where every site is in the same state. Fails if the grid has no sites, there
+    /// are fewer than two states or the state is not one of them.
+    pub fn new_constant(width: usize, height: usize, q: usize, state: usize) -> Result<Self> {
+        check_size(width, height)?;
+        if q < 2 {
+            return Err(Error::InvalidParameter {
+                name: "states",
+                value: q as f64,
+            });
+        }
+        if state >= q {
+            return Err(Error::InvalidParameter {
+                name: "state",
+                value: state as f64,
+            });
+        }
+        Ok(Self {
+            states: vec![state; width * height],
+            lattice: Hypercubic::new([width, height])?,
+            q,
+        })
+    }
+
+    /// # Critical coupling
+    /// The exact transition point of the square lattice, K_c = ln(1 + √q).
+    pub fn critical_coupling(q: usize) -> f64 {
+        (1.0 + (q as f64).sqrt()).ln()
+    }
+
+    /// # Number of states
+    /// The number of states q.
+    pub fn q(&self) -> usize {
+        self.q
+    }
+
+    /// # Width
+    /// Returns the number of columns in the grid.
+    pub fn width(&self) -> usize {
+        self.lattice.shape()[0]
+    }
+
+    /// # Height
+    /// Returns the number of rows in the grid.
+    pub fn height(&self) -> usize {
+        self.lattice.shape()[1]
+    }
+
+    /// # Get a state
+    /// Retrieves the state at the given coordinates, with periodic boundary conditions.
+    pub fn get(&self, x: i64, y: i64) -> usize {
+        self.states[self.lattice.site([x, y])]
+    }
+
+    /// # Set a state
+    /// Sets the state at the given coordinates, with periodic boundary conditions.
+    pub fn set(&mut self, x: i64, y: i64, state: usize) {
+        assert!(state < self.q, "the state must be smaller than q");
+        let site = self.lattice.site([x, y]);
+        self.states[site] = state;
+    }
+
+    /// # State fractions
+    /// The fraction of sites in each state.
+    pub fn state_fractions(&self) -> Vec<f64> {
+        let mut counts = vec![0; self.q];
+        for &state in &self.states {
+            counts[state] += 1;
+        }
+        counts
+            .into_iter()
+            .map(|count| count as f64 / self.states.len() as f64)
+            .collect()
+    }
+
+    /// # Order parameter
+    /// m = (q ρ_max - 1) / (q - 1), where ρ_max is the fraction of sites in the most common state.
+    /// It is one in an ordered state and zero when all the states are equally common.
+    pub fn order_parameter(&self) -> f64 {
+        let largest = self.state_fractions().into_iter().fold(0.0, f64::max);
+        (self.q as f64 * largest - 1.0) / (self.q as f64 - 1.0)
+    }
+
+    /// # Energy
+    /// The energy per site at temperature T in units of the coupling, in the reduced units βE of
+    /// the Boltzmann weight, with every bond counted once.
+    pub fn energy(&self, temperature: Temperature) -> f64 {
+        let coupling = temperature.beta();
+        let mut satisfied_bonds = 0;
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                let state = self.get(x, y);
+                satisfied_bonds += usize::from(self.get(x + 1, y) == state);
+                satisfied_bonds += usize::from(self.get(x, y + 1) == state);
+            }
+        }
+        -coupling * satisfied_bonds as f64 / self.states.len() as f64
+    }
+
+    /// # Heat-bath step
+    /// Draws a new state for a single site from its conditional distribution given the
+    /// neighbours, P(s) ∝ exp(K n_s), where n_s counts the neighbours in state s. Unlike Metropolis
+    /// this can jump straight to any of the q states, which matters for large q.
+    pub fn heat_bath_step<R: Rng>(
+        &mut self,
+        x: i64,
+        y: i64,
+        temperature: Temperature,
+        rng: &mut R,
+    ) {
+        let coupling = temperature.beta();
+        let site = self.lattice.site([x, y]);
+        let mut weights = vec![0.0; self.q];
+        for &neighbor in self.lattice.neighbors(site) {
+            weights[self.states[neighbor]] += coupling;
+        }
+        for weight in weights.iter_mut() {
+            *weight = weight.exp();
+        }
+
+        let mut threshold = rng.gen::<f64>() * weights.iter().sum::<f64>();
+        let mut new_state = self.q - 1;
+        for (state, weight) in weights.iter().enumerate() {
+            if threshold < *weight {
+                new_state = state;
+                break;
+            }
+            threshold -= weight;
+        }
+        self.states[site] = new_state;
+    }
+
+    /// # Step
+    /// Performs a heat-bath sweep over all the sites at temperature T = 1/K.
+    pub fn step<R: Rng>(&mut self, temperature: Temperature, rng: &mut R) {
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                self.heat_bath_step(x, y, temperature, rng);
+            }
+        }
+    }
+
+    /// # Wolff step
+    /// Grows a cluster from a random site by adding neighbours in the same state with probability
+    /// 1 - exp(-K), moves the whole cluster to a different random state, and returns its size.
+    pub fn wolff_step<R: Rng>(&mut self, temperature: Temperature, rng: &mut R) -> usize {
+        let coupling = temperature.beta();
+        let bond_probability = 1.0 - (-coupling).exp();
+        let seed = rng.gen_range(0..self.states.len());
+        let old_state = self.states[seed];
+        let new_state = (old_state + rng.gen_range(1..self.q)) % self.q;
+
+        // Sites are moved to the new state as they join, so they are never added twice.
+        self.states[seed] = new_state;
+        let mut stack = vec![seed];
+        let mut size = 1;
+        while let Some(site) = stack.pop() {
+            for &neighbor in self.lattice.neighbors(site) {
+                if self.states[neighbor] == old_state && rng.gen::<f64>() < bond_probability {
+                    self.states[neighbor] = new_state;
+                    stack.push(neighbor);
+                    size += 1;
+                }
+            }
+        }
+        size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::exact::ExactEnumeration;
+
+    /// The exact Potts energy per site of a 3×3 grid for q = 2, from the Ising enumeration with
+    /// K_Ising = K / 2 and δ(σ_i, σ_j) = (1 + s_i s_j) / 2.
+    fn exact_two_state_energy(coupling: f64) -> f64 {
+        let ising_coupling = coupling / 2.0;
+        let exact = ExactEnumeration::new(3, 3)
+            .unwrap()
+            .observables(Temperature::from_beta(ising_coupling).unwrap(), 0.0);
+        let bond_sum_per_site = -exact.energy / ising_coupling;
+        -coupling * (2.0 + bond_sum_per_site) / 2.0
+    }
+
+    #[test]
+    fn test_ordered_observables() {
+        let model = PottsModel::new_constant(4, 4, 3, 1).unwrap();
+        assert_eq!(model.energy(Temperature::new(1.0).unwrap()), -2.0);
+        assert_eq!(model.order_parameter(), 1.0);
+        assert_eq!(model.state_fractions(), [0.0, 1.0, 0.0]);
+        assert!((PottsModel::critical_coupling(2) - 2.0 * 0.4406867935).abs() < 1e-9);
+
+        assert!(PottsModel::new_constant(0, 4, 3, 1).is_err());
+        assert!(PottsModel::new_constant(4, 4, 1, 0).is_err());
+        assert!(PottsModel::new_constant(4, 4, 3, 3).is_err());
+    }
+
+    #[test]
+    fn test_heat_bath_matches_ising() {
+        let coupling = 0.8;
+        let temperature = Temperature::from_beta(coupling).unwrap();
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut model = PottsModel::new_random(3, 3, 2, &mut rng).unwrap();
+        for _ in 0..1000 {
+            model.step(temperature, &mut rng);
+        }
+        let number_of_sweeps = 40_000;
+        let mut energy = 0.0;
+        for _ in 0..number_of_sweeps {
+            model.step(temperature, &mut rng);
+            energy += model.energy(temperature);
+        }
+        energy /= number_of_sweeps as f64;
+        assert!((energy - exact_two_state_energy(coupling)).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_wolff_matches_ising() {
+        let coupling = 0.8;
+        let temperature = Temperature::from_beta(coupling).unwrap();
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut model = PottsModel::new_random(3, 3, 2, &mut rng).unwrap();
+        for _ in 0..1000 {
+            model.wolff_step(temperature, &mut rng);
+        }
+        let number_of_steps = 40_000;
+        let mut energy = 0.0;
+        for _ in 0..number_of_steps {
+            model.wolff_step(temperature, &mut rng);
+            energy += model.energy(temperature);
+        }
+        energy /= number_of_steps as f64;
+        assert!((energy - exact_two_state_energy(coupling)).abs() < 0.03);
+    }
+
+    #[test]
+    fn test_orders_below_transition() {
+        let q = 3;
+        let coupling = 1.5 * PottsModel::critical_coupling(q);
+        let temperature = Temperature::from_beta(coupling).unwrap();
+        let mut rng = StdRng::seed_from_u64(10);
+        let mut model = PottsModel::new_random(16, 16, q, &mut rng).unwrap();
+        for _ in 0..200 {
+            model.wolff_step(temperature, &mut rng);
+            model.step(temperature, &mut rng);
+        }
+        assert!(model.order_parameter() > 0.8);
+    }
+}
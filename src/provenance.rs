@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// # Provenance
+/// Where a result came from: the program and its version, the update algorithm, the seed of the
+/// random numbers, every parameter of the run, and the UTC time at which the record was made, in
+/// RFC 3339 form. It is embedded in the files the runs write, so that a result can be reproduced
+/// and attributed long after it was produced.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    pub program: String,
+    pub version: String,
+    pub algorithm: String,
+    pub seed: Option<u64>,
+    pub parameters: BTreeMap<String, Value>,
+    pub created: String,
+}
+
+impl Provenance {
+    /// # New provenance
+    /// A record of this version of the program running the given algorithm with the given seed,
+    /// if there was one, made now.
+    pub fn new(algorithm: &str, seed: Option<u64>) -> Self {
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        Self {
+            program: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            algorithm: algorithm.to_string(),
+            seed,
+            parameters: BTreeMap::new(),
+            created: timestamp(seconds),
+        }
+    }
+
+    /// # With a parameter
+    /// Adds a named parameter of the run.
+    pub fn with_parameter(mut self, name: &str, value: impl Serialize) -> Self {
+        let value = serde_json::to_value(value).expect("parameters can be written as JSON");
+        self.parameters.insert(name.to_string(), value);
+        self
+    }
+
+    /// # To JSON
+    /// The record as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("provenance can be written as JSON")
+    }
+}
+
+/// Formats a number of seconds since the Unix epoch as an RFC 3339 UTC time, converting the days
+/// to a date of the proleptic Gregorian calendar.
+fn timestamp(seconds: u64) -> String {
+    let (days, time) = ((seconds / 86_400) as i64, seconds % 86_400);
+    // Count from 1 March 0000, so that the leap day ends each 400-year era.
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(timestamp(1_709_251_199), "2024-02-29T23:59:59Z");
+        assert_eq!(timestamp(4_107_542_400), "2100-03-01T00:00:00Z");
+    }
+
+    #[test]
+    fn test_provenance() {
+        let provenance = Provenance::new("metropolis-sequential", Some(42))
+            .with_parameter("width", 16)
+            .with_parameter("coupling", 0.44);
+        assert_eq!(provenance.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(provenance.parameters["width"], 16);
+        assert!(provenance.created.ends_with('Z'));
+
+        let read: Provenance = serde_json::from_str(&provenance.to_json()).unwrap();
+        assert_eq!(read, provenance);
+    }
+}
@@ -0,0 +1,577 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::Rng;
+use tracing::{debug, debug_span, trace};
+
+use crate::error::{Error, Result};
+use crate::spin::Spin;
+use crate::temperature::Temperature;
+
+/// # Problem format
+/// How the entries of a problem file are read. Both formats list one term per line as
+/// `i j value`, where `i == j` gives a linear term, and skip blank lines, comment lines starting
+/// with `c` or `#`, and a `p` header line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProblemFormat {
+    /// The Ising form E = Σ h_i s_i + Σ J_ij s_i s_j with s_i = ±1.
+    Ising,
+    /// The QUBO form E = Σ Q_ij x_i x_j with x_i = 0 or 1.
+    Qubo,
+}
+
+/// # Ising problem
+/// An optimization problem on arbitrary spins, with energy
+/// E = offset + Σ h_i s_i + Σ_{i<j} J_ij s_i s_j to be minimized, the sign convention of annealing
+/// hardware. Unlike `Grid`, whose energies favour aligned spins for a positive coupling, a
+/// positive J_ij here favours antialigned spins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IsingProblem {
+    fields: Vec<f64>,
+    neighbors: Vec<Vec<(usize, f64)>>,
+    offset: f64,
+}
+
+/// # Solution
+/// The best configuration found by a solver and its energy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Solution {
+    pub energy: f64,
+    pub spins: Vec<Spin>,
+}
+
+impl Solution {
+    /// # Bits
+    /// The configuration as the binary variables x_i = (1 + s_i) / 2 of the QUBO form.
+    pub fn bits(&self) -> Vec<u8> {
+        self.spins
+            .iter()
+            .map(|&spin| u8::from(spin == Spin::Up))
+            .collect()
+    }
+}
+
+/// # AC demagnetization
+/// A schedule that demagnetizes a problem as a ferromagnet is demagnetized, by a uniform field
+/// H(t) = A(t) sin(2πt / period) that favours up spins, with an energy −H Σ s_i, while its
+/// amplitude A(t) falls linearly from `amplitude` to zero over the cycles at a fixed low
+/// temperature. Each cycle drives the spins back and forth through their hysteresis loops, and
+/// the shrinking loops leave them in a state with few frustrated bonds. The amplitude should
+/// reach the `saturation_field` of the problem so that the first cycles erase the starting state.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Demagnetization {
+    /// The temperature, held fixed throughout.
+    pub temperature: Temperature,
+    /// The amplitude of the field during the first cycle.
+    pub amplitude: f64,
+    /// The sweeps of one cycle of the field.
+    pub period: usize,
+    /// The cycles over which the amplitude falls to zero.
+    pub cycles: usize,
+}
+
+impl Demagnetization {
+    /// # Number of sweeps
+    pub fn sweeps(&self) -> usize {
+        self.period * self.cycles
+    }
+
+    /// # Field
+    /// The uniform field H(t) at the given sweep.
+    pub fn field(&self, sweep: usize) -> f64 {
+        let envelope = 1.0 - sweep as f64 / self.sweeps().max(1) as f64;
+        let phase = std::f64::consts::TAU * sweep as f64 / self.period as f64;
+        self.amplitude * envelope.max(0.0) * phase.sin()
+    }
+}
+
+impl IsingProblem {
+    /// # New problem
+    /// Creates a problem from its linear terms and a list of couplings (i, j, J_ij). Repeated
+    /// pairs add up.
+    pub fn new(fields: Vec<f64>, couplings: &[(usize, usize, f64)]) -> Self {
+        let mut problem = Self {
+            neighbors: vec![Vec::new(); fields.len()],
+            fields,
+            offset: 0.0,
+        };
+        for &(i, j, value) in couplings {
+            problem.add_coupling(i, j, value);
+        }
+        problem
+    }
+
+    /// # From a QUBO
+    /// Converts the QUBO objective Σ Q_ij x_i x_j into Ising form through x_i = (1 + s_i) / 2, so
+    /// that every configuration keeps its objective value. The terms are given as (i, j, Q_ij),
+    /// with i == j for the diagonal.
+    pub fn from_qubo(number_of_variables: usize, terms: &[(usize, usize, f64)]) -> Self {
+        let mut problem = Self::new(vec![0.0; number_of_variables], &[]);
+        for &(i, j, value) in terms {
+            if i == j {
+                problem.fields[i] += value / 2.0;
+                problem.offset += value / 2.0;
+            } else {
+                problem.add_coupling(i, j, value / 4.0);
+                problem.fields[i] += value / 4.0;
+                problem.fields[j] += value / 4.0;
+                problem.offset += value / 4.0;
+            }
+        }
+        problem
+    }
+
+    /// # Load a problem
+    /// Reads a problem file in the given format. The number of variables is one more than the
+    /// largest index that appears.
+    pub fn load(path: impl AsRef<Path>, format: ProblemFormat) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let invalid = |line_number: usize, message: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line_number + 1, message),
+            )
+        };
+
+        let mut terms = Vec::new();
+        for (line_number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['c', '#', 'p']) {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let &[i, j, value] = fields.as_slice() else {
+                return Err(invalid(line_number, "expected two indices and a value"));
+            };
+            let (Ok(i), Ok(j)) = (i.parse::<usize>(), j.parse::<usize>()) else {
+                return Err(invalid(
+                    line_number,
+                    "indices must be non-negative integers",
+                ));
+            };
+            let value: f64 = value
+                .parse()
+                .map_err(|_| invalid(line_number, "the value must be a number"))?;
+            terms.push((i, j, value));
+        }
+
+        let number_of_variables = terms
+            .iter()
+            .map(|&(i, j, _)| i.max(j) + 1)
+            .max()
+            .unwrap_or(0);
+        Ok(match format {
+            ProblemFormat::Qubo => Self::from_qubo(number_of_variables, &terms),
+            ProblemFormat::Ising => {
+                let mut fields = vec![0.0; number_of_variables];
+                let mut couplings = Vec::new();
+                for (i, j, value) in terms {
+                    if i == j {
+                        fields[i] += value;
+                    } else {
+                        couplings.push((i, j, value));
+                    }
+                }
+                Self::new(fields, &couplings)
+            }
+        })
+    }
+
+    fn add_coupling(&mut self, i: usize, j: usize, value: f64) {
+        assert_ne!(i, j, "a coupling needs two different spins");
+        self.neighbors[i].push((j, value));
+        self.neighbors[j].push((i, value));
+    }
+
+    /// # Number of spins
+    /// The number of variables of the problem.
+    pub fn number_of_spins(&self) -> usize {
+        self.fields.len()
+    }
+
+    /// # Fields
+    /// The linear terms h_i.
+    pub(crate) fn fields(&self) -> &[f64] {
+        &self.fields
+    }
+
+    /// # Couplings
+    /// The terms (i, j, J_ij) with i < j, a pair listed twice if it was given twice.
+    pub(crate) fn couplings(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        self.neighbors
+            .iter()
+            .enumerate()
+            .flat_map(|(i, neighbors)| {
+                neighbors
+                    .iter()
+                    .filter(move |&&(j, _)| j > i)
+                    .map(move |&(j, coupling)| (i, j, coupling))
+            })
+    }
+
+    /// # Saturation field
+    /// The smallest uniform field that aligns every spin with itself whatever its neighbours do,
+    /// max_i (|h_i| + Σ_j |J_ij|).
+    pub fn saturation_field(&self) -> f64 {
+        self.fields
+            .iter()
+            .zip(&self.neighbors)
+            .map(|(field, neighbors)| {
+                field.abs()
+                    + neighbors
+                        .iter()
+                        .map(|(_, coupling)| coupling.abs())
+                        .sum::<f64>()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// # Energy
+    /// The energy of a configuration.
+    pub fn energy(&self, spins: &[Spin]) -> f64 {
+        let values: Vec<f64> = spins.iter().map(|&spin| spin_value(spin)).collect();
+        let mut energy = self.offset;
+        for (i, &value) in values.iter().enumerate() {
+            energy += self.fields[i] * value;
+            for &(j, coupling) in &self.neighbors[i] {
+                if j > i {
+                    energy += coupling * value * values[j];
+                }
+            }
+        }
+        energy
+    }
+
+    /// # Energy change of a flip
+    /// The change of the energy when spin i flips.
+    pub(crate) fn flip_energy_change(&self, spins: &[Spin], i: usize) -> f64 {
+        let local_field: f64 = self.fields[i]
+            + self.neighbors[i]
+                .iter()
+                .map(|&(j, coupling)| coupling * spin_value(spins[j]))
+                .sum::<f64>();
+        -2.0 * spin_value(spins[i]) * local_field
+    }
+
+    /// # Metropolis sweep
+    /// Visits every spin once in order and flips it with probability min(1, exp(-βΔE)), where
+    /// the energy includes −H Σ s_i for a uniform field H, keeping track of the energy of the
+    /// problem without the field and of the best configuration seen.
+    fn sweep<R: Rng>(
+        &self,
+        spins: &mut [Spin],
+        energy: &mut f64,
+        beta: f64,
+        field: f64,
+        best: &mut Solution,
+        rng: &mut R,
+    ) {
+        for i in 0..spins.len() {
+            let energy_change = self.flip_energy_change(spins, i);
+            let total_change = energy_change + 2.0 * field * spin_value(spins[i]);
+            if total_change <= 0.0 || rng.gen::<f64>() < (-beta * total_change).exp() {
+                spins[i] = spins[i].flip();
+                *energy += energy_change;
+                if *energy < best.energy {
+                    best.energy = *energy;
+                    best.spins.copy_from_slice(spins);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn random_configuration<R: Rng>(&self, rng: &mut R) -> Vec<Spin> {
+        (0..self.number_of_spins())
+            .map(|_| if rng.gen() { Spin::Up } else { Spin::Down })
+            .collect()
+    }
+
+    /// # Simulated annealing
+    /// Starts from a random configuration and performs Metropolis sweeps while the temperature
+    /// falls geometrically from `initial_temperature` to `final_temperature`. Returns the best
+    /// configuration seen along the way.
+    pub fn simulated_annealing<R: Rng>(
+        &self,
+        initial_temperature: Temperature,
+        final_temperature: Temperature,
+        sweeps: usize,
+        rng: &mut R,
+    ) -> Solution {
+        let (initial_beta, final_beta) = (initial_temperature.beta(), final_temperature.beta());
+        let mut spins = self.random_configuration(rng);
+        let mut energy = self.energy(&spins);
+        let mut best = Solution {
+            energy,
+            spins: spins.clone(),
+        };
+        let _span = debug_span!("simulated_annealing", sweeps).entered();
+        let ratio = final_beta / initial_beta;
+        for sweep in 0..sweeps {
+            let progress = sweep as f64 / (sweeps.max(2) - 1) as f64;
+            let beta = initial_beta * ratio.powf(progress);
+            self.sweep(&mut spins, &mut energy, beta, 0.0, &mut best, rng);
+            trace!(sweep, beta, energy, "annealing sweep");
+        }
+        debug!(best_energy = best.energy, "finished annealing");
+        best
+    }
+
+    /// # Parallel tempering
+    /// Runs one replica at each of the given temperatures. After every sweep, neighbouring
+    /// replicas exchange their configurations with probability min(1, exp(Δβ ΔE)), which lets
+    /// configurations trapped at low temperature escape through the hot replicas. Returns the
+    /// best configuration seen by any replica. Fails without temperatures.
+    pub fn parallel_tempering<R: Rng>(
+        &self,
+        temperatures: &[Temperature],
+        sweeps: usize,
+        rng: &mut R,
+    ) -> Result<Solution> {
+        if temperatures.is_empty() {
+            return Err(Error::InvalidParameter {
+                name: "replicas",
+                value: 0.0,
+            });
+        }
+        let betas: Vec<f64> = temperatures.iter().map(Temperature::beta).collect();
+        let mut replicas: Vec<Vec<Spin>> = betas
+            .iter()
+            .map(|_| self.random_configuration(rng))
+            .collect();
+        let mut energies: Vec<f64> = replicas.iter().map(|spins| self.energy(spins)).collect();
+        let lowest = (0..replicas.len())
+            .min_by(|&a, &b| energies[a].total_cmp(&energies[b]))
+            .unwrap();
+        let mut best = Solution {
+            energy: energies[lowest],
+            spins: replicas[lowest].clone(),
+        };
+
+        let _span = debug_span!("parallel_tempering", replicas = betas.len(), sweeps).entered();
+        let mut swaps = vec![0_usize; betas.len()];
+        for sweep in 0..sweeps {
+            for (k, &beta) in betas.iter().enumerate() {
+                self.sweep(
+                    &mut replicas[k],
+                    &mut energies[k],
+                    beta,
+                    0.0,
+                    &mut best,
+                    rng,
+                );
+            }
+            for k in 1..betas.len() {
+                let exponent = (betas[k] - betas[k - 1]) * (energies[k] - energies[k - 1]);
+                if exponent >= 0.0 || rng.gen::<f64>() < exponent.exp() {
+                    replicas.swap(k, k - 1);
+                    energies.swap(k, k - 1);
+                    swaps[k] += 1;
+                    trace!(sweep, replica = k, "swapped replicas");
+                }
+            }
+        }
+        // Swap rates far below a few tenths point at too wide a gap between the temperatures.
+        for k in 1..betas.len() {
+            debug!(
+                lower_beta = betas[k - 1],
+                upper_beta = betas[k],
+                rate = swaps[k] as f64 / sweeps.max(1) as f64,
+                "replica swap rate"
+            );
+        }
+        debug!(best_energy = best.energy, "finished parallel tempering");
+        Ok(best)
+    }
+
+    /// # AC demagnetization
+    /// Starts from a random configuration and performs one Metropolis sweep at every step of the
+    /// schedule. Returns the best configuration seen, scored by the energy without the field, and
+    /// that energy after every sweep. Fails if a cycle has no sweeps.
+    pub fn demagnetize<R: Rng>(
+        &self,
+        schedule: &Demagnetization,
+        rng: &mut R,
+    ) -> Result<(Solution, Vec<f64>)> {
+        if schedule.period == 0 {
+            return Err(Error::InvalidParameter {
+                name: "period",
+                value: 0.0,
+            });
+        }
+        let mut spins = self.random_configuration(rng);
+        let mut energy = self.energy(&spins);
+        let mut best = Solution {
+            energy,
+            spins: spins.clone(),
+        };
+        let _span = debug_span!("demagnetize", cycles = schedule.cycles).entered();
+        let mut trajectory = Vec::with_capacity(schedule.sweeps());
+        for sweep in 0..schedule.sweeps() {
+            let field = schedule.field(sweep);
+            self.sweep(
+                &mut spins,
+                &mut energy,
+                schedule.temperature.beta(),
+                field,
+                &mut best,
+                rng,
+            );
+            trajectory.push(energy);
+            trace!(sweep, field, energy, "demagnetization sweep");
+        }
+        debug!(best_energy = best.energy, "finished demagnetization");
+        Ok((best, trajectory))
+    }
+}
+
+fn spin_value(spin: Spin) -> f64 {
+    match spin {
+        Spin::Up => 1.0,
+        _ => -1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    /// The lowest energy of a small problem, found by visiting every configuration.
+    fn brute_force_ground_energy(problem: &IsingProblem) -> f64 {
+        let n = problem.number_of_spins();
+        (0..1_u32 << n)
+            .map(|state| {
+                let spins: Vec<Spin> = (0..n)
+                    .map(|i| {
+                        if state >> i & 1 == 1 {
+                            Spin::Up
+                        } else {
+                            Spin::Down
+                        }
+                    })
+                    .collect();
+                problem.energy(&spins)
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    #[test]
+    fn test_qubo_conversion() {
+        let terms = [(0, 0, -1.0), (1, 1, 2.0), (0, 1, -3.0), (1, 2, 1.5)];
+        let problem = IsingProblem::from_qubo(3, &terms);
+        for state in 0..8_u8 {
+            let bits: Vec<u8> = (0..3).map(|i| state >> i & 1).collect();
+            let spins: Vec<Spin> = bits
+                .iter()
+                .map(|&bit| if bit == 1 { Spin::Up } else { Spin::Down })
+                .collect();
+            let objective: f64 = terms
+                .iter()
+                .map(|&(i, j, value)| value * f64::from(bits[i] * bits[j]))
+                .sum();
+            assert!((problem.energy(&spins) - objective).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_load() {
+        let path = env::temp_dir().join("ising_model_test_problem.txt");
+        fs::write(
+            &path,
+            "c a small problem\np qubo 0 2 2 1\n0 0 -1\n1 1 -1\n0 1 3\n",
+        )
+        .unwrap();
+        let problem = IsingProblem::load(&path, ProblemFormat::Qubo).unwrap();
+        assert_eq!(problem.number_of_spins(), 2);
+        let mut rng = StdRng::seed_from_u64(1);
+        let solution = problem.simulated_annealing(
+            Temperature::new(10.0).unwrap(),
+            Temperature::new(0.2).unwrap(),
+            50,
+            &mut rng,
+        );
+        assert_eq!(solution.energy, -1.0);
+        assert_eq!(solution.bits().iter().sum::<u8>(), 1);
+
+        fs::write(&path, "0 1 x\n").unwrap();
+        let error = IsingProblem::load(&path, ProblemFormat::Ising).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_solvers_find_ground_state() {
+        // A dense Sherrington–Kirkpatrick instance is small enough to check by enumeration.
+        let mut rng = StdRng::seed_from_u64(2);
+        let n = 14;
+        let fields: Vec<f64> = (0..n).map(|_| rng.gen_range(-0.5..0.5)).collect();
+        let mut couplings = Vec::new();
+        for i in 0..n {
+            for j in i + 1..n {
+                couplings.push((i, j, rng.gen_range(-1.0..1.0)));
+            }
+        }
+        let problem = IsingProblem::new(fields, &couplings);
+        let expected = brute_force_ground_energy(&problem);
+
+        let annealed = problem.simulated_annealing(
+            Temperature::new(10.0).unwrap(),
+            Temperature::new(0.1).unwrap(),
+            2000,
+            &mut rng,
+        );
+        assert!((annealed.energy - expected).abs() < 1e-9);
+        assert!((problem.energy(&annealed.spins) - annealed.energy).abs() < 1e-9);
+        let temperatures: Vec<Temperature> = (0..8)
+            .map(|k| Temperature::from_beta(0.2 * 1.6_f64.powi(k)).unwrap())
+            .collect();
+        let tempered = problem
+            .parallel_tempering(&temperatures, 500, &mut rng)
+            .unwrap();
+        assert!(problem.parallel_tempering(&[], 500, &mut rng).is_err());
+        assert!((tempered.energy - expected).abs() < 1e-9);
+
+        let schedule = Demagnetization {
+            temperature: Temperature::new(0.2).unwrap(),
+            amplitude: problem.saturation_field(),
+            period: 20,
+            cycles: 100,
+        };
+        let (demagnetized, trajectory) = problem.demagnetize(&schedule, &mut rng).unwrap();
+        assert!((demagnetized.energy - expected).abs() < 1e-9);
+        assert_eq!(trajectory.len(), 2000);
+        assert!(trajectory
+            .iter()
+            .all(|&energy| energy >= demagnetized.energy - 1e-9));
+        let empty = Demagnetization {
+            period: 0,
+            ..schedule
+        };
+        assert!(problem.demagnetize(&empty, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_demagnetization_schedule() {
+        let schedule = Demagnetization {
+            temperature: Temperature::new(1.0).unwrap(),
+            amplitude: 2.0,
+            period: 4,
+            cycles: 10,
+        };
+        assert_eq!(schedule.sweeps(), 40);
+        assert_eq!(schedule.field(0), 0.0);
+        // Five sweeps in, the field is at a crest and its envelope has fallen by an eighth.
+        assert!((schedule.field(5) - 2.0 * (1.0 - 5.0 / 40.0)).abs() < 1e-12);
+        assert!(schedule.field(7) < 0.0);
+        assert_eq!(schedule.field(40), 0.0);
+
+        let problem = IsingProblem::new(vec![0.5, 0.0, 0.0], &[(0, 1, -1.0), (1, 2, 2.0)]);
+        assert_eq!(problem.saturation_field(), 3.0);
+    }
+}
@@ -0,0 +1,279 @@
+use rand::Rng;
+
+use crate::grid::Grid;
+use crate::spin::Spin;
+
+/// # Tie breaking
+/// What a zero-temperature update does with a flip that leaves the energy unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TieBreaking {
+    /// Always make the flip, as the Metropolis rule does.
+    Accept,
+    /// Never make the flip, so that only strictly downhill moves are taken.
+    Reject,
+    /// Make the flip with the given probability, a half for the Glauber rule.
+    Probability(f64),
+}
+
+impl TieBreaking {
+    fn accepts<R: Rng>(&self, rng: &mut R) -> bool {
+        match *self {
+            TieBreaking::Accept => true,
+            TieBreaking::Reject => false,
+            TieBreaking::Probability(probability) => rng.gen::<f64>() < probability,
+        }
+    }
+}
+
+/// # Quench outcome
+/// The state that a zero-temperature quench ends in. On the periodic square lattice about a third
+/// of the quenches from a random state never reach the ground state but freeze into straight
+/// stripes, since a flat interface cannot move without raising the energy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuenchOutcome {
+    /// All the spins that can flip point the same way.
+    GroundState,
+    /// No flip lowers or keeps the energy, but the spins are not all aligned, as for the
+    /// straight stripes of the square lattice.
+    Stripes,
+    /// No flip lowers the energy, and the flips that keep it are never taken by the tie rule.
+    Blocked,
+    /// The quench ran out of sweeps, as happens for diagonal stripes that keep wandering through
+    /// flips that keep the energy.
+    Unfinished,
+}
+
+/// # Quench result
+/// The outcome of a zero-temperature quench and the number of sweeps it took.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuenchResult {
+    pub outcome: QuenchOutcome,
+    pub sweeps: usize,
+}
+
+/// # Energy change of a flip
+/// The energy change of flipping the spin at a site, in units of the coupling. Zero temperature
+/// makes only the sign of the coupling matter, so the field is given relative to it.
+fn flip_energy_change(grid: &mut Grid, x: i64, y: i64, field: f64) -> f64 {
+    let spin = grid.get(x, y);
+    let energy_before = grid.total_energy(x, y, 1.0, field);
+    grid.set(x, y, spin.flip());
+    let energy_change = grid.total_energy(x, y, 1.0, field) - energy_before;
+    grid.set(x, y, spin);
+    energy_change
+}
+
+/// # Can flip
+/// Whether the spin at a site takes part in the quench, which leaves out zero, vacant and pinned
+/// sites.
+fn can_flip(grid: &Grid, x: i64, y: i64) -> bool {
+    matches!(grid.get(x, y), Spin::Up | Spin::Down) && !grid.is_pinned(x, y)
+}
+
+/// # Zero-temperature step
+/// Performs one sweep of random sequential zero-temperature updates: a randomly chosen spin flips
+/// if that lowers the energy, never if it raises it, and according to `ties` if the energy stays
+/// the same. Returns the number of flips.
+pub fn zero_temperature_step<R: Rng>(
+    grid: &mut Grid,
+    field: f64,
+    ties: TieBreaking,
+    rng: &mut R,
+) -> usize {
+    let (width, height) = (grid.width(), grid.height());
+    let number_of_sites = width * height;
+    let mut flips = 0;
+    for _ in 0..number_of_sites {
+        let site = rng.gen_range(0..number_of_sites);
+        let (x, y) = ((site % width) as i64, (site / width) as i64);
+        if !can_flip(grid, x, y) {
+            continue;
+        }
+        let energy_change = flip_energy_change(grid, x, y, field);
+        let flip = if energy_change.abs() < 1e-12 {
+            ties.accepts(rng)
+        } else {
+            energy_change < 0.0
+        };
+        if flip {
+            grid.set(x, y, grid.get(x, y).flip());
+            flips += 1;
+        }
+    }
+    flips
+}
+
+/// # Classify a zero-temperature state
+/// Classifies the grid as a final state of a quench with the given tie rule, or returns `None`
+/// if the quench can still lower its energy or move through flips that keep it.
+pub fn classify(grid: &mut Grid, field: f64, ties: TieBreaking) -> Option<QuenchOutcome> {
+    let mut has_ties = false;
+    let mut spins = (false, false);
+    for y in 0..grid.height() as i64 {
+        for x in 0..grid.width() as i64 {
+            if !can_flip(grid, x, y) {
+                continue;
+            }
+            let energy_change = flip_energy_change(grid, x, y, field);
+            if energy_change.abs() < 1e-12 {
+                has_ties = true;
+            } else if energy_change < 0.0 {
+                return None;
+            }
+            match grid.get(x, y) {
+                Spin::Up => spins.0 = true,
+                _ => spins.1 = true,
+            }
+        }
+    }
+
+    if has_ties && ties != TieBreaking::Reject {
+        None
+    } else if !(spins.0 && spins.1) {
+        Some(QuenchOutcome::GroundState)
+    } else if has_ties {
+        Some(QuenchOutcome::Blocked)
+    } else {
+        Some(QuenchOutcome::Stripes)
+    }
+}
+
+/// # Quench
+/// Runs zero-temperature sweeps until the grid reaches a state that the dynamics can no longer
+/// leave, or until `max_sweeps` sweeps have been done.
+pub fn quench<R: Rng>(
+    grid: &mut Grid,
+    field: f64,
+    ties: TieBreaking,
+    max_sweeps: usize,
+    rng: &mut R,
+) -> QuenchResult {
+    for sweeps in 0..max_sweeps {
+        if let Some(outcome) = classify(grid, field, ties) {
+            return QuenchResult { outcome, sweeps };
+        }
+        zero_temperature_step(grid, field, ties, rng);
+    }
+    QuenchResult {
+        outcome: classify(grid, field, ties).unwrap_or(QuenchOutcome::Unfinished),
+        sweeps: max_sweeps,
+    }
+}
+
+/// # Quench statistics
+/// Counts the outcomes of many quenches, to give the frequency of each final state.
+#[derive(Debug, Clone, Default)]
+pub struct QuenchStatistics {
+    counts: [usize; 4],
+    total_sweeps: usize,
+}
+
+impl QuenchStatistics {
+    /// # New statistics
+    /// Creates an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn slot(outcome: QuenchOutcome) -> usize {
+        match outcome {
+            QuenchOutcome::GroundState => 0,
+            QuenchOutcome::Stripes => 1,
+            QuenchOutcome::Blocked => 2,
+            QuenchOutcome::Unfinished => 3,
+        }
+    }
+
+    /// # Add a quench
+    /// Adds the result of one quench.
+    pub fn add(&mut self, result: &QuenchResult) {
+        self.counts[Self::slot(result.outcome)] += 1;
+        self.total_sweeps += result.sweeps;
+    }
+
+    /// # Number of samples
+    /// The number of quenches added.
+    pub fn samples(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// # Frequency
+    /// The fraction of the quenches that ended in the given outcome.
+    pub fn frequency(&self, outcome: QuenchOutcome) -> f64 {
+        self.counts[Self::slot(outcome)] as f64 / self.samples() as f64
+    }
+
+    /// # Mean number of sweeps
+    /// The average number of sweeps a quench took.
+    pub fn mean_sweeps(&self) -> f64 {
+        self.total_sweeps as f64 / self.samples() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let mut stripes = Grid::new_stripes(16, 16, 8).unwrap();
+        assert_eq!(
+            classify(&mut stripes, 0.0, TieBreaking::Accept),
+            Some(QuenchOutcome::Stripes)
+        );
+
+        let mut uniform = Grid::new_constant(8, 8, Spin::Down).unwrap();
+        let mut rng = StdRng::seed_from_u64(8);
+        let result = quench(&mut uniform, 0.0, TieBreaking::Accept, 10, &mut rng);
+        assert_eq!(result.outcome, QuenchOutcome::GroundState);
+        assert_eq!(result.sweeps, 0);
+
+        // A square domain shrinks from its corners, which costs no energy, so a rule that
+        // rejects ties leaves it blocked.
+        let mut square = Grid::new_constant(8, 8, Spin::Down).unwrap();
+        for y in 2..5 {
+            for x in 2..5 {
+                square.set(x, y, Spin::Up);
+            }
+        }
+        assert_eq!(
+            classify(&mut square, 0.0, TieBreaking::Reject),
+            Some(QuenchOutcome::Blocked)
+        );
+        assert_eq!(classify(&mut square, 0.0, TieBreaking::Accept), None);
+    }
+
+    #[test]
+    fn test_quench_statistics() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let mut statistics = QuenchStatistics::new();
+        let mut blocked = QuenchStatistics::new();
+        for _ in 0..60 {
+            let mut metropolis = Grid::new_random(12, 12).unwrap();
+            statistics.add(&quench(
+                &mut metropolis,
+                0.0,
+                TieBreaking::Accept,
+                5000,
+                &mut rng,
+            ));
+            let mut strict = Grid::new_random(12, 12).unwrap();
+            blocked.add(&quench(
+                &mut strict,
+                0.0,
+                TieBreaking::Reject,
+                5000,
+                &mut rng,
+            ));
+        }
+        let ground = statistics.frequency(QuenchOutcome::GroundState);
+        let stripes = statistics.frequency(QuenchOutcome::Stripes);
+        assert!(ground > 0.4 && ground < 0.9);
+        assert!(stripes > 0.1);
+        assert_eq!(statistics.frequency(QuenchOutcome::Blocked), 0.0);
+        assert!(blocked.frequency(QuenchOutcome::Blocked) > 0.9);
+    }
+}
@@ -0,0 +1,678 @@
+use std::collections::VecDeque;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::couplings::BondDirection;
+use crate::error::{check_same_size, Error, Result};
+use crate::grid::{BoundaryCondition, Grid};
+use crate::temperature::Temperature;
+
+/// # Bond configuration
+/// A set of open and closed nearest-neighbour bonds on a periodic width × height grid, such as the
+/// Fortuin–Kasteleyn bonds that the Swendsen–Wang algorithm lays between parallel spins. Like
+/// `BondCouplings`, each site owns the bond to its right and the bond below it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BondConfiguration {
+    width: usize,
+    height: usize,
+    horizontal: Vec<bool>,
+    vertical: Vec<bool>,
+}
+
+impl BondConfiguration {
+    /// # New bond configuration
+    /// Creates a configuration where every bond is closed.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            horizontal: vec![false; width * height],
+            vertical: vec![false; width * height],
+        }
+    }
+
+    /// # Bonds from a function
+    /// Creates a configuration where the bond leaving the site at (x, y) in the given direction is
+    /// open if `f(x, y, direction)` is true.
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        mut f: impl FnMut(usize, usize, BondDirection) -> bool,
+    ) -> Self {
+        let mut bonds = Self::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                bonds.horizontal[y * width + x] = f(x, y, BondDirection::Horizontal);
+                bonds.vertical[y * width + x] = f(x, y, BondDirection::Vertical);
+            }
+        }
+        bonds
+    }
+
+    /// # Fortuin–Kasteleyn bonds
+    /// Draws the FK bonds of an Ising grid at temperature T in units of the coupling. A bond is
+    /// opened with probability 1 - exp(-2K J_ij s_i s_j), with K = 1/T, when it is satisfied,
+    /// J_ij s_i s_j > 0, and stays closed otherwise, where J_ij is one unless the grid has bond
+    /// couplings. Vacant and zero spins never bond.
+    pub fn fortuin_kasteleyn<R: Rng>(grid: &Grid, temperature: Temperature, rng: &mut R) -> Self {
+        Self::fortuin_kasteleyn_reduced(grid, temperature.beta(), rng)
+    }
+
+    /// # Fortuin–Kasteleyn bonds at a reduced coupling
+    /// Draws the bonds of `fortuin_kasteleyn` at the reduced coupling K, which may be negative for
+    /// an antiferromagnet.
+    pub fn fortuin_kasteleyn_reduced<R: Rng>(grid: &Grid, coupling: f64, rng: &mut R) -> Self {
+        Self::from_fn(grid.width(), grid.height(), |x, y, direction| {
+            let (x, y) = (x as i64, y as i64);
+            let neighbor = match direction {
+                BondDirection::Horizontal => grid.get_spin_as_float(x + 1, y),
+                BondDirection::Vertical => grid.get_spin_as_float(x, y + 1),
+            };
+            let strength = grid
+                .bond_couplings()
+                .map_or(1.0, |couplings| couplings.get(x, y, direction));
+            let satisfaction = coupling * strength * grid.get_spin_as_float(x, y) * neighbor;
+            satisfaction > 0.0 && rng.gen::<f64>() < 1.0 - (-2.0 * satisfaction).exp()
+        })
+    }
+
+    /// # Width
+    /// The number of columns of the grid.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows of the grid.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: i64, y: i64) -> usize {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        y * self.width + x
+    }
+
+    /// # Is open
+    /// Whether the bond leaving the site at (x, y) in the given direction is open, with periodic
+    /// boundary conditions.
+    pub fn is_open(&self, x: i64, y: i64, direction: BondDirection) -> bool {
+        let index = self.index(x, y);
+        match direction {
+            BondDirection::Horizontal => self.horizontal[index],
+            BondDirection::Vertical => self.vertical[index],
+        }
+    }
+
+    /// # Set a bond
+    /// Opens or closes the bond leaving the site at (x, y) in the given direction.
+    pub fn set(&mut self, x: i64, y: i64, direction: BondDirection, open: bool) {
+        let index = self.index(x, y);
+        match direction {
+            BondDirection::Horizontal => self.horizontal[index] = open,
+            BondDirection::Vertical => self.vertical[index] = open,
+        }
+    }
+
+    /// # Number of open bonds
+    /// The number of open bonds |A|.
+    pub fn number_of_open_bonds(&self) -> usize {
+        self.horizontal
+            .iter()
+            .chain(&self.vertical)
+            .filter(|&&open| open)
+            .count()
+    }
+
+    /// # Clusters
+    /// Finds the clusters of sites joined by open bonds.
+    pub fn clusters(&self) -> BondClusters {
+        BondClusters::new(self)
+    }
+
+    /// # Log weight
+    /// The logarithm of the unnormalized random-cluster weight p^|A| (1 - p)^(|E| - |A|) q^C(A) of
+    /// the configuration, where C(A) counts its clusters, including isolated sites.
+    pub fn log_weight(&self, bond_probability: f64, q: f64) -> f64 {
+        let open = self.number_of_open_bonds() as f64;
+        let closed = 2.0 * (self.width * self.height) as f64 - open;
+        let clusters = self.clusters().number_of_clusters() as f64;
+        open * bond_probability.ln() + closed * (1.0 - bond_probability).ln() + clusters * q.ln()
+    }
+
+    /// # Open neighbours
+    /// The neighbours of a site that are joined to it by open bonds, with their offsets.
+    fn open_neighbors(&self, x: i64, y: i64) -> impl Iterator<Item = (i64, i64)> + '_ {
+        [
+            (1, 0, self.is_open(x, y, BondDirection::Horizontal)),
+            (-1, 0, self.is_open(x - 1, y, BondDirection::Horizontal)),
+            (0, 1, self.is_open(x, y, BondDirection::Vertical)),
+            (0, -1, self.is_open(x, y - 1, BondDirection::Vertical)),
+        ]
+        .into_iter()
+        .filter(|&(_, _, open)| open)
+        .map(|(dx, dy, _)| (dx, dy))
+    }
+}
+
+/// # Bond clusters
+/// The clusters of a bond configuration, found with a breadth-first search that also records
+/// whether each cluster wraps around the periodic grid along either axis.
+#[derive(Debug, Clone)]
+pub struct BondClusters {
+    width: usize,
+    labels: Vec<usize>,
+    sizes: Vec<usize>,
+    wrapping: Vec<[bool; 2]>,
+}
+
+impl BondClusters {
+    fn new(bonds: &BondConfiguration) -> Self {
+        let (width, height) = (bonds.width, bonds.height);
+        let number_of_sites = width * height;
+        let mut labels = vec![usize::MAX; number_of_sites];
+        let mut unwrapped = vec![(0, 0); number_of_sites];
+        let mut sizes = Vec::new();
+        let mut wrapping = Vec::new();
+        let mut queue = VecDeque::new();
+
+        for start in 0..number_of_sites {
+            if labels[start] != usize::MAX {
+                continue;
+            }
+            let label = sizes.len();
+            let mut size = 0;
+            let mut wraps = [false; 2];
+            labels[start] = label;
+            unwrapped[start] = ((start % width) as i64, (start / width) as i64);
+            queue.push_back(start);
+
+            while let Some(site) = queue.pop_front() {
+                size += 1;
+                let (ux, uy) = unwrapped[site];
+                for (dx, dy) in bonds.open_neighbors(ux, uy) {
+                    let (nx, ny) = (ux + dx, uy + dy);
+                    let neighbor = bonds.index(nx, ny);
+                    if labels[neighbor] == usize::MAX {
+                        labels[neighbor] = label;
+                        unwrapped[neighbor] = (nx, ny);
+                        queue.push_back(neighbor);
+                    } else {
+                        let (px, py) = unwrapped[neighbor];
+                        wraps[0] |= px != nx;
+                        wraps[1] |= py != ny;
+                    }
+                }
+            }
+
+            sizes.push(size);
+            wrapping.push(wraps);
+        }
+
+        Self {
+            width,
+            labels,
+            sizes,
+            wrapping,
+        }
+    }
+
+    /// # Label
+    /// The label of the cluster that contains the site. The coordinates must lie inside the grid.
+    pub fn label(&self, x: usize, y: usize) -> usize {
+        self.labels[y * self.width + x]
+    }
+
+    /// # Sizes
+    /// The number of sites in each cluster, indexed by label.
+    pub fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+
+    /// # Number of clusters
+    /// The number of clusters C(A), counting isolated sites.
+    pub fn number_of_clusters(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// # Largest cluster
+    /// The fraction of the sites in the largest cluster.
+    pub fn largest_fraction(&self) -> f64 {
+        self.sizes.iter().max().copied().unwrap_or(0) as f64 / self.labels.len() as f64
+    }
+
+    /// # Mean cluster size
+    /// The average size of the cluster that a site belongs to, Σ s² / N. For the FK clusters of an
+    /// Ising model this is an improved estimator of N⟨m²⟩, since the clusters flip independently.
+    pub fn mean_cluster_size(&self) -> f64 {
+        let sum: usize = self.sizes.iter().map(|size| size * size).sum();
+        sum as f64 / self.labels.len() as f64
+    }
+
+    /// # Cluster wraps
+    /// Whether the cluster with the given label wraps around the grid along the x axis and along
+    /// the y axis.
+    pub fn cluster_wraps(&self, label: usize) -> (bool, bool) {
+        let [along_x, along_y] = self.wrapping[label];
+        (along_x, along_y)
+    }
+
+    /// # Wraps
+    /// Whether any cluster wraps around the grid along the x axis and along the y axis. Averaged
+    /// over configurations these give the wrapping probabilities, whose curves for different sizes
+    /// cross at the critical point.
+    pub fn wraps(&self) -> (bool, bool) {
+        let along_x = self.wrapping.iter().any(|wraps| wraps[0]);
+        let along_y = self.wrapping.iter().any(|wraps| wraps[1]);
+        (along_x, along_y)
+    }
+}
+
+/// # Swendsen–Wang step
+/// Draws the Fortuin–Kasteleyn bonds of the grid, gives every cluster a new random orientation,
+/// and returns the bonds. The update works in zero field for any sign of the bond couplings, and
+/// clusters that contain a pinned spin keep their orientation. It needs periodic boundaries and no
+/// diagonal coupling.
+pub fn swendsen_wang_step<R: Rng>(
+    grid: &mut Grid,
+    coupling: f64,
+    rng: &mut R,
+) -> BondConfiguration {
+    swendsen_wang_step_with_clusters(grid, coupling, rng).0
+}
+
+/// # Swendsen–Wang step with clusters
+/// Performs the same update as `swendsen_wang_step`, and returns its clusters along with the
+/// bonds, for the improved estimators of `ClusterEstimators`.
+pub fn swendsen_wang_step_with_clusters<R: Rng>(
+    grid: &mut Grid,
+    coupling: f64,
+    rng: &mut R,
+) -> (BondConfiguration, BondClusters) {
+    assert_eq!(
+        grid.boundary_conditions(),
+        (BoundaryCondition::Periodic, BoundaryCondition::Periodic),
+        "the Swendsen–Wang update needs periodic boundaries"
+    );
+    assert_eq!(
+        grid.next_nearest_ratio(),
+        0.0,
+        "the Swendsen–Wang update does not support diagonal couplings"
+    );
+    let bonds = BondConfiguration::fortuin_kasteleyn_reduced(grid, coupling, rng);
+    let clusters = bonds.clusters();
+    flip_clusters(grid, &clusters, rng);
+    (bonds, clusters)
+}
+
+/// # Flip clusters
+/// Flips every cluster with probability one half, except the clusters that contain a pinned spin.
+pub(crate) fn flip_clusters<R: Rng>(grid: &mut Grid, clusters: &BondClusters, rng: &mut R) {
+    let mut flipped: Vec<bool> = (0..clusters.number_of_clusters())
+        .map(|_| rng.gen())
+        .collect();
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            if grid.is_pinned(x as i64, y as i64) {
+                flipped[clusters.label(x, y)] = false;
+            }
+        }
+    }
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            let (x_i, y_i) = (x as i64, y as i64);
+            if flipped[clusters.label(x, y)] {
+                grid.set(x_i, y_i, grid.get(x_i, y_i).flip());
+            }
+        }
+    }
+}
+
+/// # Cluster estimators
+/// Averages over the configurations of a Swendsen–Wang run of the squared magnetization and of
+/// the spin correlation G(r) = ⟨s_i s_j⟩ between sites r apart along either axis, each measured
+/// both conventionally from the spins and with the improved estimator from the clusters. Since
+/// the clusters flip independently, N⟨m²⟩ is the mean size ⟨Σ |C|²⟩/N of the cluster of a site,
+/// and ⟨s_i s_j⟩ is the probability that i and j are in the same cluster. The improved estimators
+/// average over all the orientations of the clusters at once, which cuts their variance
+/// dramatically near the critical point. They hold in zero field, without vacancies or pinned
+/// spins.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterEstimators {
+    width: usize,
+    height: usize,
+    samples: usize,
+    magnetization_squared: f64,
+    cluster_sizes: f64,
+    correlations: Vec<f64>,
+    cluster_correlations: Vec<f64>,
+}
+
+impl ClusterEstimators {
+    /// # New cluster estimators
+    /// Empty averages for a width × height grid, with correlations up to half the shorter side.
+    pub fn new(width: usize, height: usize) -> Self {
+        let distances = width.min(height) / 2 + 1;
+        Self {
+            width,
+            height,
+            samples: 0,
+            magnetization_squared: 0.0,
+            cluster_sizes: 0.0,
+            correlations: vec![0.0; distances],
+            cluster_correlations: vec![0.0; distances],
+        }
+    }
+
+    /// # Add a configuration
+    /// Measures a configuration and the clusters of the update that produced it. Fails unless the
+    /// grid has the size of the estimators.
+    pub fn add(&mut self, grid: &Grid, clusters: &BondClusters) -> Result<()> {
+        check_same_size(
+            "cluster estimators",
+            (grid.width(), grid.height()),
+            (self.width, self.height),
+        )?;
+        let number_of_sites = (self.width * self.height) as f64;
+        let magnetization = grid.magnetization() / number_of_sites;
+        self.samples += 1;
+        self.magnetization_squared += magnetization * magnetization;
+        self.cluster_sizes += clusters.mean_cluster_size() / number_of_sites;
+
+        for (distance, (correlation, cluster_correlation)) in self
+            .correlations
+            .iter_mut()
+            .zip(&mut self.cluster_correlations)
+            .enumerate()
+        {
+            let (mut products, mut shared) = (0.0, 0);
+            for y in 0..self.height {
+                for x in 0..self.width {
+                    let spin = grid.get_spin_as_float(x as i64, y as i64);
+                    let label = clusters.label(x, y);
+                    for (nx, ny) in [
+                        ((x + distance) % self.width, y),
+                        (x, (y + distance) % self.height),
+                    ] {
+                        products += spin * grid.get_spin_as_float(nx as i64, ny as i64);
+                        shared += usize::from(clusters.label(nx, ny) == label);
+                    }
+                }
+            }
+            *correlation += products / (2.0 * number_of_sites);
+            *cluster_correlation += shared as f64 / (2.0 * number_of_sites);
+        }
+        Ok(())
+    }
+
+    /// # Number of samples
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// # Largest distance
+    /// The largest distance that the correlations are measured at.
+    pub fn max_distance(&self) -> usize {
+        self.correlations.len() - 1
+    }
+
+    /// # Squared magnetization
+    /// The conventional estimate of ⟨m²⟩, with m the magnetization per site.
+    pub fn magnetization_squared(&self) -> f64 {
+        self.magnetization_squared / self.samples as f64
+    }
+
+    /// # Improved squared magnetization
+    /// The estimate of ⟨m²⟩ from the sizes of the clusters.
+    pub fn improved_magnetization_squared(&self) -> f64 {
+        self.cluster_sizes / self.samples as f64
+    }
+
+    /// # Susceptibility
+    /// The conventional estimate of χ = N⟨m²⟩/T at the temperature T = 1/K, which in zero field
+    /// and finite volume is the susceptibility above the critical point.
+    pub fn susceptibility(&self, temperature: Temperature) -> f64 {
+        (self.width * self.height) as f64 * self.magnetization_squared() / temperature.value()
+    }
+
+    /// # Improved susceptibility
+    /// The estimate of N⟨m²⟩/T from the mean cluster size.
+    pub fn improved_susceptibility(&self, temperature: Temperature) -> f64 {
+        (self.width * self.height) as f64 * self.improved_magnetization_squared()
+            / temperature.value()
+    }
+
+    /// # Correlation
+    /// The conventional estimate of G(r) at the given distance.
+    pub fn correlation(&self, distance: usize) -> f64 {
+        self.correlations[distance] / self.samples as f64
+    }
+
+    /// # Improved correlation
+    /// The estimate of G(r) from the probability that two sites share a cluster.
+    pub fn improved_correlation(&self, distance: usize) -> f64 {
+        self.cluster_correlations[distance] / self.samples as f64
+    }
+}
+
+/// # Random-cluster model
+/// The Fortuin–Kasteleyn random-cluster model on a periodic grid, where a bond configuration A
+/// has the weight p^|A| (1 - p)^(|E| - |A|) q^C(A). For integer q it is the graphical
+/// representation of the q-state Potts model with p = 1 - exp(-K), but q can take any real value
+/// of at least one, with q = 1 being bond percolation.
+#[derive(Debug, Clone)]
+pub struct RandomClusterModel {
+    bonds: BondConfiguration,
+    q: f64,
+}
+
+impl RandomClusterModel {
+    /// # New random-cluster model
+    /// Creates a model with the given cluster weight q and every bond closed. Fails unless q is
+    /// finite and at least one.
+    pub fn new(width: usize, height: usize, q: f64) -> Result<Self> {
+        if !(q >= 1.0 && q.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "cluster weight q",
+                value: q,
+            });
+        }
+        Ok(Self {
+            bonds: BondConfiguration::new(width, height),
+            q,
+        })
+    }
+
+    /// # Critical probability
+    /// The self-dual bond probability p_c = √q / (1 + √q) of the square lattice, which is the
+    /// critical point for 1 ≤ q ≤ 4 and the first-order transition point above.
+    pub fn critical_probability(q: f64) -> f64 {
+        q.sqrt() / (1.0 + q.sqrt())
+    }
+
+    /// # Cluster weight
+    /// The weight q of every cluster.
+    pub fn q(&self) -> f64 {
+        self.q
+    }
+
+    /// # Bonds
+    /// The current bond configuration.
+    pub fn bonds(&self) -> &BondConfiguration {
+        &self.bonds
+    }
+
+    /// # Step
+    /// Performs one Chayes–Machta update at the given bond probability. Every cluster is
+    /// activated with probability 1/q, and then every bond between two active sites is redrawn,
+    /// open with probability p, while all the other bonds are kept. For q = 2 this is exactly a
+    /// Swendsen–Wang step, and it works for every real q ≥ 1.
+    pub fn step<R: Rng>(&mut self, bond_probability: f64, rng: &mut R) {
+        let clusters = self.bonds.clusters();
+        let active: Vec<bool> = (0..clusters.number_of_clusters())
+            .map(|_| rng.gen::<f64>() < 1.0 / self.q)
+            .collect();
+        let is_active = |x: i64, y: i64| {
+            let x = x.rem_euclid(self.bonds.width as i64) as usize;
+            let y = y.rem_euclid(self.bonds.height as i64) as usize;
+            active[clusters.label(x, y)]
+        };
+
+        let mut bonds = self.bonds.clone();
+        for y in 0..self.bonds.height as i64 {
+            for x in 0..self.bonds.width as i64 {
+                if !is_active(x, y) {
+                    continue;
+                }
+                for (direction, (dx, dy)) in [
+                    (BondDirection::Horizontal, (1, 0)),
+                    (BondDirection::Vertical, (0, 1)),
+                ] {
+                    if is_active(x + dx, y + dy) {
+                        bonds.set(x, y, direction, rng.gen::<f64>() < bond_probability);
+                    }
+                }
+            }
+        }
+        self.bonds = bonds;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::exact::ExactEnumeration;
+
+    #[test]
+    fn test_clusters_and_wrapping() {
+        // A full row wraps along x, a pair of sites forms one more cluster, and the remaining ten
+        // sites are isolated.
+        let mut bonds = BondConfiguration::new(4, 4);
+        for x in 0..4 {
+            bonds.set(x, 1, BondDirection::Horizontal, true);
+        }
+        bonds.set(0, 3, BondDirection::Vertical, true);
+        let clusters = bonds.clusters();
+        assert_eq!(clusters.number_of_clusters(), 1 + 1 + 10);
+        assert_eq!(clusters.label(3, 1), clusters.label(0, 1));
+        assert_eq!(clusters.label(0, 0), clusters.label(0, 3));
+        assert_eq!(clusters.wraps(), (true, false));
+        assert_eq!(clusters.largest_fraction(), 0.25);
+        assert_eq!(clusters.mean_cluster_size(), (16.0 + 4.0 + 10.0) / 16.0);
+
+        let weight = bonds.log_weight(0.5, 2.0);
+        assert!((weight - (32.0 * 0.5_f64.ln() + 12.0 * 2.0_f64.ln())).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_swendsen_wang_matches_enumeration() {
+        // Every open bond is satisfied, so ⟨|A|⟩ = p (2N + ⟨Σ s_i s_j⟩) / 2.
+        let coupling = 0.35;
+        let exact = ExactEnumeration::new(3, 3)
+            .unwrap()
+            .observables(Temperature::from_beta(coupling).unwrap(), 0.0);
+        let bond_probability = 1.0 - (-2.0 * coupling).exp();
+        let expected_bonds = bond_probability * (18.0 - 9.0 * exact.energy / coupling) / 2.0;
+
+        let mut rng = StdRng::seed_from_u64(16);
+        let mut grid = Grid::new_random(3, 3).unwrap();
+        for _ in 0..1000 {
+            swendsen_wang_step(&mut grid, coupling, &mut rng);
+        }
+        let number_of_steps = 40_000;
+        let (mut bonds, mut susceptibility) = (0.0, 0.0);
+        for _ in 0..number_of_steps {
+            let configuration = swendsen_wang_step(&mut grid, coupling, &mut rng);
+            bonds += configuration.number_of_open_bonds() as f64;
+            susceptibility += configuration.clusters().mean_cluster_size();
+        }
+        bonds /= number_of_steps as f64;
+        susceptibility /= number_of_steps as f64;
+        assert!((bonds - expected_bonds).abs() < 0.05);
+        assert!((susceptibility - 9.0 * exact.magnetization_squared).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_cluster_estimators() {
+        let coupling = 0.25;
+        let exact = ExactEnumeration::new(4, 4)
+            .unwrap()
+            .observables(Temperature::from_beta(coupling).unwrap(), 0.0);
+        let mut rng = StdRng::seed_from_u64(21);
+        let mut grid = Grid::new_random(4, 4).unwrap();
+        for _ in 0..500 {
+            swendsen_wang_step(&mut grid, coupling, &mut rng);
+        }
+        let mut estimators = ClusterEstimators::new(4, 4);
+        let (mut conventional, mut improved) = (Vec::new(), Vec::new());
+        for _ in 0..20_000 {
+            let (_, clusters) = swendsen_wang_step_with_clusters(&mut grid, coupling, &mut rng);
+            estimators.add(&grid, &clusters).unwrap();
+            conventional.push(grid.magnetization().powi(2) / 16.0);
+            improved.push(clusters.mean_cluster_size());
+        }
+        assert_eq!(estimators.samples(), 20_000);
+        assert_eq!(estimators.max_distance(), 2);
+        assert_eq!(estimators.correlation(0), 1.0);
+        assert_eq!(estimators.improved_correlation(0), 1.0);
+
+        let expected = exact.magnetization_squared;
+        assert!((estimators.magnetization_squared() - expected).abs() < 0.02);
+        assert!((estimators.improved_magnetization_squared() - expected).abs() < 0.01);
+        // The exact susceptibility is N⟨m²⟩ in zero field, without the temperature.
+        let susceptibility = estimators.improved_susceptibility(Temperature::new(1.0).unwrap());
+        assert!((susceptibility - exact.susceptibility).abs() < 0.02 * exact.susceptibility);
+        for distance in 1..=2 {
+            let difference =
+                estimators.correlation(distance) - estimators.improved_correlation(distance);
+            assert!(difference.abs() < 0.02);
+        }
+
+        // The cluster sizes fluctuate far less than the squared magnetization.
+        let variance = |values: &[f64]| {
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / values.len() as f64
+        };
+        assert!(variance(&improved) < 0.5 * variance(&conventional));
+    }
+
+    #[test]
+    fn test_random_cluster_matches_swendsen_wang() {
+        let coupling: f64 = 0.35;
+        let bond_probability = 1.0 - (-2.0 * coupling).exp();
+        let mut rng = StdRng::seed_from_u64(17);
+        let mut model = RandomClusterModel::new(3, 3, 2.0).unwrap();
+        let mut grid = Grid::new_random(3, 3).unwrap();
+        let number_of_steps = 40_000;
+        let (mut cluster_bonds, mut spin_bonds) = (0.0, 0.0);
+        for _ in 0..number_of_steps {
+            model.step(bond_probability, &mut rng);
+            cluster_bonds += model.bonds().number_of_open_bonds() as f64;
+            let configuration = swendsen_wang_step(&mut grid, coupling, &mut rng);
+            spin_bonds += configuration.number_of_open_bonds() as f64;
+        }
+        let difference = (cluster_bonds - spin_bonds) / number_of_steps as f64;
+        assert!(difference.abs() < 0.1);
+    }
+
+    #[test]
+    fn test_percolation_limit() {
+        // With q = 1 every bond is independent, so after one step each is open with probability p.
+        let mut rng = StdRng::seed_from_u64(18);
+        let mut model = RandomClusterModel::new(32, 32, 1.0).unwrap();
+        assert!(RandomClusterModel::new(32, 32, 0.5).is_err());
+        model.step(0.3, &mut rng);
+        let fraction = model.bonds().number_of_open_bonds() as f64 / 2048.0;
+        assert!((fraction - 0.3).abs() < 0.03);
+        assert!((RandomClusterModel::critical_probability(1.0) - 0.5).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::couplings::BondDirection;
+use crate::error::{check_finite, Error, Result};
+use crate::field::FieldMap;
+use crate::grid::{BoundaryCondition, Grid};
+use crate::spin::Spin;
+use crate::temperature::Temperature;
+
+/// # Gaussian random field
+/// Draws a quenched random field with independent Gaussian values of zero mean and standard
+/// deviation `strength` at every site, the usual disorder of the random-field Ising model.
+pub fn gaussian_random_field<R: Rng>(
+    width: usize,
+    height: usize,
+    strength: f64,
+    rng: &mut R,
+) -> FieldMap {
+    FieldMap::from_fn(width, height, |_, _| {
+        // Box–Muller transform of two uniform numbers, the first kept away from zero.
+        let radius = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt();
+        strength * radius * (2.0 * PI * rng.gen::<f64>()).cos()
+    })
+}
+
+/// # Bimodal random field
+/// Draws a quenched random field that is +`strength` or -`strength` with equal probability at
+/// every site.
+pub fn bimodal_random_field<R: Rng>(
+    width: usize,
+    height: usize,
+    strength: f64,
+    rng: &mut R,
+) -> FieldMap {
+    FieldMap::from_fn(
+        width,
+        height,
+        |_, _| {
+            if rng.gen() {
+                strength
+            } else {
+                -strength
+            }
+        },
+    )
+}
+
+/// # Flow network
+/// A directed network with real capacities, whose maximum flow is found with Dinic's algorithm.
+struct FlowNetwork {
+    heads: Vec<usize>,
+    capacities: Vec<f64>,
+    edges: Vec<Vec<usize>>,
+}
+
+impl FlowNetwork {
+    /// Flows below this are treated as zero, so that rounding errors do not leave residual edges.
+    const TOLERANCE: f64 = 1e-12;
+
+    fn new(number_of_nodes: usize) -> Self {
+        Self {
+            heads: Vec::new(),
+            capacities: Vec::new(),
+            edges: vec![Vec::new(); number_of_nodes],
+        }
+    }
+
+    /// Adds an edge and its reverse, with a capacity in each direction. Edge 2k and 2k + 1 are
+    /// always each other's reverse.
+    fn add_edge(&mut self, from: usize, to: usize, capacity: f64, reverse_capacity: f64) {
+        self.edges[from].push(self.heads.len());
+        self.heads.push(to);
+        self.capacities.push(capacity);
+        self.edges[to].push(self.heads.len());
+        self.heads.push(from);
+        self.capacities.push(reverse_capacity);
+    }
+
+    /// The distance of every node from the source along edges with residual capacity.
+    fn levels(&self, source: usize) -> Vec<Option<usize>> {
+        let mut levels = vec![None; self.edges.len()];
+        let mut queue = VecDeque::from([source]);
+        levels[source] = Some(0);
+        while let Some(node) = queue.pop_front() {
+            for &edge in &self.edges[node] {
+                let head = self.heads[edge];
+                if levels[head].is_none() && self.capacities[edge] > Self::TOLERANCE {
+                    levels[head] = Some(levels[node].unwrap() + 1);
+                    queue.push_back(head);
+                }
+            }
+        }
+        levels
+    }
+
+    /// Pushes flow from a node towards the sink along the level graph, returning the amount.
+    fn push(
+        &mut self,
+        node: usize,
+        sink: usize,
+        limit: f64,
+        levels: &[Option<usize>],
+        next_edge: &mut [usize],
+    ) -> f64 {
+        if node == sink {
+            return limit;
+        }
+        while next_edge[node] < self.edges[node].len() {
+            let edge = self.edges[node][next_edge[node]];
+            let head = self.heads[edge];
+            let capacity = self.capacities[edge];
+            if capacity > Self::TOLERANCE && levels[head] == levels[node].map(|level| level + 1) {
+                let pushed = self.push(head, sink, limit.min(capacity), levels, next_edge);
+                if pushed > Self::TOLERANCE {
+                    self.capacities[edge] -= pushed;
+                    self.capacities[edge ^ 1] += pushed;
+                    return pushed;
+                }
+            }
+            next_edge[node] += 1;
+        }
+        0.0
+    }
+
+    /// Saturates the network and returns the maximum flow, which equals the capacity of the
+    /// minimum cut.
+    fn max_flow(&mut self, source: usize, sink: usize) -> f64 {
+        let mut flow = 0.0;
+        loop {
+            let levels = self.levels(source);
+            if levels[sink].is_none() {
+                return flow;
+            }
+            let mut next_edge = vec![0; self.edges.len()];
+            loop {
+                let pushed = self.push(source, sink, f64::INFINITY, &levels, &mut next_edge);
+                if pushed <= Self::TOLERANCE {
+                    break;
+                }
+                flow += pushed;
+            }
+        }
+    }
+}
+
+/// # Configuration energy
+/// The energy -K Σ J_ij s_i s_j - Σ h_i s_i of the whole grid, with each bond counted once.
+fn configuration_energy(grid: &Grid, coupling: f64, field: f64) -> f64 {
+    let mut energy = 0.0;
+    for y in 0..grid.height() as i64 {
+        for x in 0..grid.width() as i64 {
+            let spin = grid.get_spin_as_float(x, y);
+            let [upper, _, _, right] = match grid.bond_couplings() {
+                Some(couplings) => couplings.neighbor_couplings(x, y),
+                None => [1.0; 4],
+            };
+            let local_field = field + grid.field_map().map_or(0.0, |map| map.get(x, y));
+            energy -= coupling
+                * spin
+                * (upper * grid.get_neighbor_as_float(x, y, 0, 1)
+                    + right * grid.get_neighbor_as_float(x, y, 1, 0));
+            energy -= local_field * spin;
+        }
+    }
+    energy
+}
+
+/// # Exact ground state
+/// Sets the grid to an exact ground state of the random-field Ising model and returns its energy
+/// -K Σ J_ij s_i s_j - Σ H_i s_i, where K = β and H_i is the reduced uniform field βh plus the
+/// field map, at temperature T and field h in units of the coupling. The temperature only sets
+/// how strong the field map is compared to the bonds. With ferromagnetic bonds the energy, up to
+/// a constant, is the capacity of a cut in a network in which every site is a node, every bond an
+/// edge of capacity 2K J_ij, and every field a link of capacity 2|H_i| to the source or the sink.
+/// The minimum cut, found as a maximum flow, separates the up spins on the source side from the
+/// down spins. Pinned spins keep their values and vacant sites are left out. The field must be
+/// finite and the grid must have periodic boundaries, non-negative bond couplings, no diagonal
+/// coupling and no crystal field, or this fails. Of several degenerate ground states, the one
+/// with the fewest up spins is returned.
+pub fn ground_state(grid: &mut Grid, temperature: Temperature, field: f64) -> Result<f64> {
+    check_finite("field", field)?;
+    let (coupling, field) = (temperature.beta(), field * temperature.beta());
+    if grid.boundary_conditions() != (BoundaryCondition::Periodic, BoundaryCondition::Periodic) {
+        return Err(Error::Unsupported(
+            "the ground state solver needs periodic boundaries",
+        ));
+    }
+    if grid.next_nearest_ratio() != 0.0 {
+        return Err(Error::Unsupported(
+            "the ground state solver does not support diagonal couplings",
+        ));
+    }
+    if grid.crystal_field_ratio().is_some() {
+        return Err(Error::Unsupported(
+            "the ground state solver does not support a crystal field",
+        ));
+    }
+    let (width, height) = (grid.width() as i64, grid.height() as i64);
+    let ferromagnetic = grid.bond_couplings().is_none_or(|couplings| {
+        (0..height).all(|y| {
+            (0..width).all(|x| {
+                [BondDirection::Horizontal, BondDirection::Vertical]
+                    .into_iter()
+                    .all(|direction| couplings.get(x, y, direction) >= 0.0)
+            })
+        })
+    });
+    if !ferromagnetic {
+        return Err(Error::Unsupported(
+            "the ground state solver needs ferromagnetic bonds",
+        ));
+    }
+
+    let (width, height) = (grid.width(), grid.height());
+    let number_of_sites = width * height;
+    let (source, sink) = (number_of_sites, number_of_sites + 1);
+    let mut network = FlowNetwork::new(number_of_sites + 2);
+    let index = |x: i64, y: i64| {
+        y.rem_euclid(height as i64) as usize * width + x.rem_euclid(width as i64) as usize
+    };
+
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let site = index(x, y);
+            if grid.get(x, y) == Spin::Vacant {
+                continue;
+            }
+            for (dx, dy, direction) in [
+                (1, 0, BondDirection::Horizontal),
+                (0, 1, BondDirection::Vertical),
+            ] {
+                if grid.get(x + dx, y + dy) == Spin::Vacant {
+                    continue;
+                }
+                let strength = grid
+                    .bond_couplings()
+                    .map_or(1.0, |couplings| couplings.get(x, y, direction));
+                let capacity = 2.0 * coupling * strength;
+                network.add_edge(site, index(x + dx, y + dy), capacity, capacity);
+            }
+
+            if grid.is_pinned(x, y) {
+                match grid.get(x, y) {
+                    Spin::Up => network.add_edge(source, site, f64::INFINITY, 0.0),
+                    _ => network.add_edge(site, sink, f64::INFINITY, 0.0),
+                }
+                continue;
+            }
+            let local_field = field + grid.field_map().map_or(0.0, |map| map.get(x, y));
+            if local_field > 0.0 {
+                network.add_edge(source, site, 2.0 * local_field, 0.0);
+            } else if local_field < 0.0 {
+                network.add_edge(site, sink, -2.0 * local_field, 0.0);
+            }
+        }
+    }
+
+    network.max_flow(source, sink);
+    let levels = network.levels(source);
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            if grid.get(x, y) == Spin::Vacant || grid.is_pinned(x, y) {
+                continue;
+            }
+            let spin = if levels[index(x, y)].is_some() {
+                Spin::Up
+            } else {
+                Spin::Down
+            };
+            grid.set(x, y, spin);
+        }
+    }
+    Ok(configuration_energy(grid, coupling, field))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::couplings::BondCouplings;
+
+    /// The lowest energy of a small grid, found by visiting every configuration.
+    fn brute_force_ground_energy(grid: &mut Grid, coupling: f64, field: f64) -> f64 {
+        let (width, height) = (grid.width(), grid.height());
+        let number_of_sites = width * height;
+        let mut lowest = f64::INFINITY;
+        for state in 0..1_u32 << number_of_sites {
+            for site in 0..number_of_sites {
+                let spin = if state >> site & 1 == 1 {
+                    Spin::Up
+                } else {
+                    Spin::Down
+                };
+                grid.set((site % width) as i64, (site / width) as i64, spin);
+            }
+            lowest = lowest.min(configuration_energy(grid, coupling, field));
+        }
+        lowest
+    }
+
+    #[test]
+    fn test_matches_enumeration() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..10 {
+            let mut grid = Grid::new_random(4, 3).unwrap();
+            grid.set_field_map(Some(gaussian_random_field(4, 3, 1.5, &mut rng)))
+                .unwrap();
+            grid.set_bond_couplings(Some(BondCouplings::from_fn(4, 3, |_, _, _| {
+                rng.gen_range(0.2..1.0)
+            })))
+            .unwrap();
+            let temperature = Temperature::from_beta(0.8).unwrap();
+            let energy = ground_state(&mut grid, temperature, 0.125).unwrap();
+            let expected = brute_force_ground_energy(&mut grid, 0.8, 0.1);
+            assert!((energy - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_limits() {
+        let temperature = Temperature::new(1.0).unwrap();
+        let mut rng = StdRng::seed_from_u64(9);
+        let field = bimodal_random_field(8, 8, 1.0, &mut rng);
+
+        // A weak random field cannot break up the ferromagnet, which follows the uniform field.
+        let mut grid = Grid::new_random(8, 8).unwrap();
+        grid.set_field_map(Some(field.scaled(0.1))).unwrap();
+        let energy = ground_state(&mut grid, temperature, 0.05).unwrap();
+        let random_field_sum: f64 = (0..64).map(|site| field.get(site % 8, site / 8)).sum();
+        assert!((energy - (-128.0 - 64.0 * 0.05 - 0.1 * random_field_sum)).abs() < 1e-9);
+        assert!((0..8).all(|y| (0..8).all(|x| grid.get(x, y) == Spin::Up)));
+
+        // A strong one aligns every spin with its local field.
+        let mut grid = Grid::new_random(8, 8).unwrap();
+        grid.set_field_map(Some(field.scaled(10.0))).unwrap();
+        ground_state(&mut grid, temperature, 0.0).unwrap();
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(grid.get_spin_as_float(x, y), field.get(x, y));
+            }
+        }
+
+        // Antiferromagnetic bonds and open boundaries are refused instead of solved wrongly.
+        assert!(ground_state(&mut grid, temperature, f64::NAN).is_err());
+        let mut couplings = BondCouplings::uniform(8, 8, 1.0);
+        couplings.set(2, 3, BondDirection::Vertical, -0.5);
+        grid.set_bond_couplings(Some(couplings)).unwrap();
+        assert!(ground_state(&mut grid, temperature, 0.0).is_err());
+        grid.set_bond_couplings(None).unwrap();
+        grid.set_boundary_conditions(BoundaryCondition::Open, BoundaryCondition::Periodic);
+        assert!(ground_state(&mut grid, temperature, 0.0).is_err());
+        for y in 0..8 {
+            for x in 0..8 {
+                assert_eq!(grid.get_spin_as_float(x, y), field.get(x, y));
+            }
+        }
+    }
+}
@@ -0,0 +1,746 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, ImageError, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::couplings::BondDirection;
+use crate::error::{Error, Result};
+use crate::grid::Grid;
+use crate::random_cluster::BondConfiguration;
+use crate::spin::Spin;
+
+/// Unwraps the I/O errors of the image crate and reports the rest as invalid data.
+fn io_error(error: ImageError) -> io::Error {
+    match error {
+        ImageError::IoError(error) => error,
+        error => io::Error::new(io::ErrorKind::InvalidData, error),
+    }
+}
+
+/// Checks that a recorder keeps at least every sweep it is given.
+fn check_interval(interval: usize) -> Result<()> {
+    if interval == 0 {
+        return Err(Error::InvalidParameter {
+            name: "recording interval",
+            value: 0.0,
+        });
+    }
+    Ok(())
+}
+
+/// # Palette
+/// The RGB colours that a spin configuration is drawn with. The default draws up spins white and
+/// down spins black, so that an image written with it can be read back by `Grid::from_image`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+    pub up: [u8; 3],
+    pub down: [u8; 3],
+    pub zero: [u8; 3],
+    pub vacant: [u8; 3],
+    pub wall: [u8; 3],
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self {
+            up: [255, 255, 255],
+            down: [0, 0, 0],
+            zero: [128, 128, 128],
+            vacant: [200, 60, 60],
+            wall: [255, 170, 0],
+        }
+    }
+}
+
+impl Palette {
+    /// # Blue and red
+    /// Draws up spins red and down spins blue, a common choice in papers.
+    pub fn blue_red() -> Self {
+        Self {
+            up: [200, 40, 40],
+            down: [40, 70, 200],
+            zero: [235, 235, 235],
+            vacant: [0, 0, 0],
+            wall: [20, 20, 20],
+        }
+    }
+
+    /// # Colour of a spin
+    /// The colour a spin is drawn with.
+    pub fn color(&self, spin: Spin) -> [u8; 3] {
+        match spin {
+            Spin::Up => self.up,
+            Spin::Down => self.down,
+            Spin::Zero => self.zero,
+            Spin::Vacant => self.vacant,
+        }
+    }
+
+    /// # Hex colour
+    /// A colour in the `#rrggbb` notation of SVG and CSS.
+    pub fn hex(color: [u8; 3]) -> String {
+        format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+    }
+}
+
+/// # Domain walls
+/// Traces the boundaries between neighbouring sites of different spin as contours along the edges
+/// of the lattice. Each contour is a list of corners (x, y), from (0, 0) at the top left corner of
+/// the site at the origin to (width, height), that keeps only the points where it turns. A closed
+/// contour repeats its first corner at the end, while an open one runs between two points on the
+/// edge of the grid. Walls across the periodic boundaries would lie on the edge of the drawing and
+/// are left out.
+pub fn domain_walls(grid: &Grid) -> Vec<Vec<(usize, usize)>> {
+    let (width, height) = (grid.width(), grid.height());
+    let corner = |x: usize, y: usize| y * (width + 1) + x;
+    let mut edges = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let spin = grid.get(x as i64, y as i64);
+            if x > 0 && grid.get(x as i64 - 1, y as i64) != spin {
+                edges.push((corner(x, y), corner(x, y + 1)));
+            }
+            if y > 0 && grid.get(x as i64, y as i64 - 1) != spin {
+                edges.push((corner(x, y), corner(x + 1, y)));
+            }
+        }
+    }
+    let mut incident = vec![Vec::new(); (width + 1) * (height + 1)];
+    for (edge, &(from, to)) in edges.iter().enumerate() {
+        incident[from].push(edge);
+        incident[to].push(edge);
+    }
+
+    // Open contours end on corners with an odd number of wall edges, which are all on the edge of
+    // the grid, so they are traced first and whatever is left forms closed loops.
+    let mut used = vec![false; edges.len()];
+    let starts: Vec<usize> = (0..incident.len())
+        .filter(|&start| incident[start].len() % 2 == 1)
+        .chain(0..incident.len())
+        .collect();
+    let mut contours = Vec::new();
+    for start in starts {
+        while let Some(&edge) = incident[start].iter().find(|&&edge| !used[edge]) {
+            let mut contour = vec![start];
+            let mut current = start;
+            let mut next = Some(edge);
+            while let Some(edge) = next {
+                used[edge] = true;
+                let (from, to) = edges[edge];
+                current = if from == current { to } else { from };
+                contour.push(current);
+                next = incident[current].iter().copied().find(|&edge| !used[edge]);
+            }
+            let points: Vec<(usize, usize)> = contour
+                .iter()
+                .map(|&corner| (corner % (width + 1), corner / (width + 1)))
+                .collect();
+            contours.push(turning_points(&points));
+        }
+    }
+    contours
+}
+
+/// Drops the points of a polyline that lie on a straight line between their neighbours.
+fn turning_points(points: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut kept = vec![points[0]];
+    for window in points.windows(3) {
+        let [before, point, after] = [window[0], window[1], window[2]];
+        if (before.0 == point.0) != (point.0 == after.0) {
+            kept.push(point);
+        }
+    }
+    kept.push(points[points.len() - 1]);
+    kept
+}
+
+/// # To SVG
+/// Draws the grid as an SVG image with one square per spin in the colours of the palette, merging
+/// the squares of equal spins along each row. The drawing measures `scale` pixels per spin, but
+/// being vector graphics it can be scaled freely for print. With `walls` the contours traced by
+/// `domain_walls` are drawn on top in the wall colour of the palette.
+pub fn to_svg(grid: &Grid, palette: &Palette, scale: u32, walls: bool) -> String {
+    let (width, height) = (grid.width(), grid.height());
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width as u32 * scale,
+        height as u32 * scale,
+        width,
+        height
+    );
+    svg.push_str("<g shape-rendering=\"crispEdges\">\n");
+    for y in 0..height {
+        let mut x = 0;
+        while x < width {
+            let spin = grid.get(x as i64, y as i64);
+            let run = (x..width)
+                .take_while(|&end| grid.get(end as i64, y as i64) == spin)
+                .count();
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\"/>\n",
+                x,
+                y,
+                run,
+                Palette::hex(palette.color(spin))
+            ));
+            x += run;
+        }
+    }
+    svg.push_str("</g>\n");
+
+    if walls {
+        let mut path = String::new();
+        for contour in domain_walls(grid) {
+            let closed = contour.len() > 2 && contour[0] == contour[contour.len() - 1];
+            for (index, &(x, y)) in contour.iter().enumerate() {
+                if index == 0 {
+                    path.push_str(&format!("M{} {}", x, y));
+                } else if !(closed && index == contour.len() - 1) {
+                    path.push_str(&format!("L{} {}", x, y));
+                }
+            }
+            if closed {
+                path.push('Z');
+            }
+        }
+        if !path.is_empty() {
+            svg.push_str(&format!(
+                "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"0.2\" stroke-linejoin=\"round\"/>\n",
+                path,
+                Palette::hex(palette.wall)
+            ));
+        }
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// # Save as SVG
+/// Writes the drawing made by `to_svg` to a file.
+pub fn save_svg(
+    path: impl AsRef<Path>,
+    grid: &Grid,
+    palette: &Palette,
+    scale: u32,
+    walls: bool,
+) -> io::Result<()> {
+    std::fs::write(path, to_svg(grid, palette, scale, walls))
+}
+
+/// # To the terminal
+/// Draws the grid as text for a terminal with 24-bit colour, two rows per line: each character is
+/// an upper half block whose foreground is the spin at y and whose background is the spin at
+/// y + 1. Every line ends by resetting the colours, so the text can be printed as it is.
+pub fn to_terminal(grid: &Grid, palette: &Palette) -> String {
+    let mut text = String::new();
+    for y in (0..grid.height()).step_by(2) {
+        let mut current = None;
+        for x in 0..grid.width() {
+            let upper = palette.color(grid.get(x as i64, y as i64));
+            let lower =
+                (y + 1 < grid.height()).then(|| palette.color(grid.get(x as i64, y as i64 + 1)));
+            if current != Some((upper, lower)) {
+                text.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m",
+                    upper[0], upper[1], upper[2]
+                ));
+                match lower {
+                    Some(lower) => text.push_str(&format!(
+                        "\x1b[48;2;{};{};{}m",
+                        lower[0], lower[1], lower[2]
+                    )),
+                    None => text.push_str("\x1b[49m"),
+                }
+                current = Some((upper, lower));
+            }
+            text.push('▀');
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    text
+}
+
+/// # Domain colouring
+/// Draws every domain, a connected cluster of equal spins on the periodic grid, in a colour of its
+/// own, which makes coarsening and percolation easy to see. The colouring remembers the previous
+/// frame: a domain keeps the colour of the earlier domain it overlaps most, so that when domains
+/// merge the survivor is the one that covered the larger area, and only new domains get new
+/// colours. Vacant sites are drawn black.
+#[derive(Debug, Clone, Default)]
+pub struct DomainColoring {
+    colors: Vec<usize>,
+    next_color: usize,
+}
+
+impl DomainColoring {
+    /// # New domain colouring
+    /// Creates a colouring without a previous frame.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Colour of a domain
+    /// The colour with the given index, with hues spread by the golden ratio so that consecutive
+    /// indices look clearly different.
+    pub fn color(index: usize) -> [u8; 3] {
+        let hue = (index as f64 * 0.618_033_988_749_895).fract() * 6.0;
+        let value = if index.is_multiple_of(2) { 0.95 } else { 0.75 };
+        let (saturation, sector) = (0.65, hue.floor());
+        let fraction = hue - sector;
+        let (p, q, t) = (
+            value * (1.0 - saturation),
+            value * (1.0 - saturation * fraction),
+            value * (1.0 - saturation * (1.0 - fraction)),
+        );
+        let (red, green, blue) = match sector as u32 {
+            0 => (value, t, p),
+            1 => (q, value, p),
+            2 => (p, value, t),
+            3 => (p, q, value),
+            4 => (t, p, value),
+            _ => (value, p, q),
+        };
+        [red, green, blue].map(|channel| (255.0 * channel).round() as u8)
+    }
+
+    /// # Update
+    /// Labels the domains of the grid, matches them to those of the previous frame, and returns the
+    /// colour index of every site, row by row, with `usize::MAX` for vacant sites.
+    pub fn update(&mut self, grid: &Grid) -> &[usize] {
+        let (width, height) = (grid.width(), grid.height());
+        let bonds = BondConfiguration::from_fn(width, height, |x, y, direction| {
+            let (x, y) = (x as i64, y as i64);
+            let (nx, ny) = match direction {
+                BondDirection::Horizontal => (x + 1, y),
+                BondDirection::Vertical => (x, y + 1),
+            };
+            grid.get(x, y) != Spin::Vacant && grid.get(x, y) == grid.get(nx, ny)
+        });
+        let clusters = bonds.clusters();
+        let sites: Vec<(usize, bool)> = (0..width * height)
+            .map(|site| {
+                let (x, y) = (site % width, site / width);
+                let vacant = grid.get(x as i64, y as i64) == Spin::Vacant;
+                (clusters.label(x, y), vacant)
+            })
+            .collect();
+
+        // Hand the colours of the previous frame to the domains with the largest overlaps first.
+        let mut assigned = vec![None; clusters.number_of_clusters()];
+        if self.colors.len() == sites.len() {
+            let mut overlaps = BTreeMap::new();
+            for (&(label, vacant), &color) in sites.iter().zip(&self.colors) {
+                if !vacant && color != usize::MAX {
+                    *overlaps.entry((label, color)).or_insert(0) += 1;
+                }
+            }
+            let mut overlaps: Vec<((usize, usize), usize)> = overlaps.into_iter().collect();
+            overlaps.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+            let mut taken = vec![false; self.next_color];
+            for ((label, color), _) in overlaps {
+                if assigned[label].is_none() && !taken[color] {
+                    assigned[label] = Some(color);
+                    taken[color] = true;
+                }
+            }
+        }
+
+        self.colors = sites
+            .iter()
+            .map(|&(label, vacant)| {
+                if vacant {
+                    return usize::MAX;
+                }
+                *assigned[label].get_or_insert_with(|| {
+                    self.next_color += 1;
+                    self.next_color - 1
+                })
+            })
+            .collect();
+        &self.colors
+    }
+
+    /// # To an image
+    /// Updates the colouring with the grid and draws it with one square of `scale` × `scale`
+    /// pixels per spin. Fails if the scale is zero.
+    pub fn to_image(&mut self, grid: &Grid, scale: u32) -> Result<RgbImage> {
+        if scale == 0 {
+            return Err(Error::InvalidParameter {
+                name: "scale",
+                value: 0.0,
+            });
+        }
+        let width = grid.width();
+        let colors = self.update(grid);
+        Ok(RgbImage::from_fn(
+            width as u32 * scale,
+            grid.height() as u32 * scale,
+            |x, y| match colors[(y / scale) as usize * width + (x / scale) as usize] {
+                usize::MAX => Rgb([0, 0, 0]),
+                index => Rgb(Self::color(index)),
+            },
+        ))
+    }
+}
+
+/// # GIF recorder
+/// Collects a frame every `interval` sweeps of a run and writes them as an animated GIF that loops
+/// forever, which makes the evolution of the domains easy to follow.
+pub struct GifRecorder {
+    encoder: GifEncoder<BufWriter<File>>,
+    palette: Palette,
+    domains: Option<DomainColoring>,
+    scale: u32,
+    interval: usize,
+    delay: Delay,
+    frames: usize,
+}
+
+impl GifRecorder {
+    /// # Create a recorder
+    /// Creates the GIF file. Each frame shows the grid as `Grid::to_image` draws it, and the
+    /// animation plays `frames_per_second` frames per second; recording every `interval`-th sweep
+    /// skips the sweeps in between. Fails for an interval or a frame rate of zero.
+    pub fn create(
+        path: impl AsRef<Path>,
+        palette: Palette,
+        scale: u32,
+        interval: usize,
+        frames_per_second: u32,
+    ) -> io::Result<Self> {
+        check_interval(interval)?;
+        if frames_per_second == 0 {
+            return Err(Error::InvalidParameter {
+                name: "frame rate",
+                value: 0.0,
+            }
+            .into());
+        }
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = GifEncoder::new_with_speed(file, 10);
+        encoder.set_repeat(Repeat::Infinite).map_err(io_error)?;
+        Ok(Self {
+            encoder,
+            palette,
+            domains: None,
+            scale,
+            interval,
+            delay: Delay::from_numer_denom_ms(1000, frames_per_second),
+            frames: 0,
+        })
+    }
+
+    /// # Record a sweep
+    /// Adds the grid as a frame if the sweep is a multiple of the interval, and returns whether it
+    /// did.
+    pub fn record(&mut self, sweep: usize, grid: &Grid) -> io::Result<bool> {
+        if !sweep.is_multiple_of(self.interval) {
+            return Ok(false);
+        }
+        let image = match &mut self.domains {
+            Some(domains) => domains.to_image(grid, self.scale),
+            None => grid.to_image(&self.palette, self.scale),
+        }?;
+        let image = DynamicImage::ImageRgb8(image).into_rgba8();
+        self.encoder
+            .encode_frame(Frame::from_parts(image, 0, 0, self.delay))
+            .map_err(io_error)?;
+        self.frames += 1;
+        Ok(true)
+    }
+
+    /// # Number of frames
+    /// The number of frames recorded so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// # Set the domain colouring
+    /// Draws the following frames with a colour per domain instead of the palette, or with the
+    /// palette again for `None`.
+    pub fn set_domain_coloring(&mut self, domains: Option<DomainColoring>) {
+        self.domains = domains;
+    }
+}
+
+/// # Video recorder
+/// Streams a frame every `interval` sweeps of a run as raw RGB pixels into the standard input of a
+/// child process, normally ffmpeg, which encodes the video as it goes. Nothing is held in memory,
+/// so long runs of large grids can be turned into MP4 or WebM videos directly.
+pub struct VideoRecorder {
+    child: Child,
+    stdin: BufWriter<ChildStdin>,
+    palette: Palette,
+    domains: Option<DomainColoring>,
+    scale: u32,
+    interval: usize,
+    frames: usize,
+}
+
+impl VideoRecorder {
+    /// # Create a recorder
+    /// Starts ffmpeg to encode frames of a width × height grid, drawn with `scale` × `scale` pixels
+    /// per spin, into the given file at `frames_per_second`. The format follows from the file
+    /// extension, such as `.mp4` or `.webm`. Odd image sizes are padded by a pixel, as the usual
+    /// video codecs need even ones.
+    pub fn create(
+        path: impl AsRef<Path>,
+        width: usize,
+        height: usize,
+        palette: Palette,
+        scale: u32,
+        interval: usize,
+        frames_per_second: u32,
+    ) -> io::Result<Self> {
+        let mut command = Command::new("ffmpeg");
+        command
+            .args([
+                "-loglevel",
+                "error",
+                "-y",
+                "-f",
+                "rawvideo",
+                "-pix_fmt",
+                "rgb24",
+            ])
+            .arg("-s")
+            .arg(format!(
+                "{}x{}",
+                width as u32 * scale,
+                height as u32 * scale
+            ))
+            .arg("-r")
+            .arg(frames_per_second.to_string())
+            .args(["-i", "-", "-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2"])
+            .args(["-pix_fmt", "yuv420p"])
+            .arg(path.as_ref());
+        Self::spawn(command, palette, scale, interval)
+    }
+
+    /// # Spawn a recorder
+    /// Starts the given command and streams the raw frames, rows of RGB bytes from the top, into
+    /// its standard input. Fails for an interval of zero.
+    pub fn spawn(
+        mut command: Command,
+        palette: Palette,
+        scale: u32,
+        interval: usize,
+    ) -> io::Result<Self> {
+        check_interval(interval)?;
+        let mut child = command.stdin(Stdio::piped()).spawn()?;
+        let stdin = BufWriter::new(child.stdin.take().unwrap());
+        Ok(Self {
+            child,
+            stdin,
+            palette,
+            domains: None,
+            scale,
+            interval,
+            frames: 0,
+        })
+    }
+
+    /// # Record a sweep
+    /// Sends the grid as a frame if the sweep is a multiple of the interval, and returns whether it
+    /// did.
+    pub fn record(&mut self, sweep: usize, grid: &Grid) -> io::Result<bool> {
+        if !sweep.is_multiple_of(self.interval) {
+            return Ok(false);
+        }
+        let image = match &mut self.domains {
+            Some(domains) => domains.to_image(grid, self.scale),
+            None => grid.to_image(&self.palette, self.scale),
+        }?;
+        self.stdin.write_all(image.as_raw())?;
+        self.frames += 1;
+        Ok(true)
+    }
+
+    /// # Number of frames
+    /// The number of frames recorded so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// # Set the domain colouring
+    /// Draws the following frames with a colour per domain instead of the palette, or with the
+    /// palette again for `None`.
+    pub fn set_domain_coloring(&mut self, domains: Option<DomainColoring>) {
+        self.domains = domains;
+    }
+
+    /// # Finish
+    /// Closes the stream and waits for the encoder, failing if it did not exit successfully.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.stdin.flush()?;
+        drop(self.stdin);
+        let status = self.child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(io::Error::other(format!(
+                "the encoder failed with {}",
+                status
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use image::codecs::gif::GifDecoder;
+    use image::AnimationDecoder;
+
+    use super::*;
+    use crate::temperature::Temperature;
+
+    #[test]
+    fn test_gif_recorder() {
+        let path = env::temp_dir().join("ising_model_test_recorder.gif");
+        let mut grid = Grid::new_random(12, 8).unwrap();
+        let mut recorder = GifRecorder::create(&path, Palette::default(), 2, 3, 10).unwrap();
+        for sweep in 0..10 {
+            recorder.record(sweep, &grid).unwrap();
+            grid.step(Temperature::from_beta(0.6).unwrap(), 0.0);
+        }
+        assert_eq!(recorder.frames(), 4);
+        drop(recorder);
+
+        let decoder = GifDecoder::new(fs::File::open(&path).unwrap()).unwrap();
+        let frames = decoder.into_frames().collect_frames().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[0].buffer().dimensions(), (24, 16));
+        assert_eq!(frames[0].delay().numer_denom_ms(), (100, 1));
+
+        assert!(GifRecorder::create(&path, Palette::default(), 2, 0, 10).is_err());
+        assert!(GifRecorder::create(&path, Palette::default(), 2, 3, 0).is_err());
+    }
+
+    #[test]
+    fn test_domain_walls() {
+        // A square droplet is bounded by one closed contour with four corners.
+        let mut grid = Grid::new_constant(6, 5, Spin::Down).unwrap();
+        for y in 1..3 {
+            for x in 2..5 {
+                grid.set(x, y, Spin::Up);
+            }
+        }
+        assert_eq!(
+            domain_walls(&grid),
+            vec![vec![(2, 1), (2, 3), (5, 3), (5, 1), (2, 1)]]
+        );
+
+        // The two halves of an interface grid meet along a single straight wall.
+        let grid = Grid::new_interface(6, 4).unwrap();
+        assert_eq!(domain_walls(&grid), vec![vec![(3, 0), (3, 4)]]);
+
+        // Every wall edge of a random grid belongs to exactly one contour.
+        let grid = Grid::new_random(12, 9).unwrap();
+        let mut edges = 0;
+        for y in 0..9 {
+            for x in 0..12 {
+                edges += (x > 0 && grid.get(x - 1, y) != grid.get(x, y)) as usize;
+                edges += (y > 0 && grid.get(x, y - 1) != grid.get(x, y)) as usize;
+            }
+        }
+        let length: usize = domain_walls(&grid)
+            .iter()
+            .flat_map(|contour| contour.windows(2))
+            .map(|pair| pair[0].0.abs_diff(pair[1].0) + pair[0].1.abs_diff(pair[1].1))
+            .sum();
+        assert_eq!(length, edges);
+    }
+
+    #[test]
+    fn test_to_svg() {
+        let grid = Grid::new_interface(6, 4).unwrap();
+        let palette = Palette::default();
+        let svg = to_svg(&grid, &palette, 10, true);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("width=\"60\" height=\"40\""));
+        assert_eq!(svg.matches("<rect").count(), 8);
+        assert!(svg.contains("<rect x=\"3\" y=\"2\" width=\"3\" height=\"1\" fill=\"#000000\"/>"));
+        assert!(svg.contains("d=\"M3 0L3 4\""));
+        assert!(svg.contains(&Palette::hex(palette.wall)));
+        assert!(!to_svg(&grid, &palette, 10, false).contains("<path"));
+    }
+
+    #[test]
+    fn test_to_terminal() {
+        let palette = Palette::default();
+        let grid = Grid::new_stripes(4, 3, 2).unwrap();
+        let text = to_terminal(&grid, &palette);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(text.matches('▀').count(), 8);
+        assert_eq!(
+            lines[0],
+            "\x1b[38;2;255;255;255m\x1b[48;2;255;255;255m▀▀\x1b[38;2;0;0;0m\x1b[48;2;0;0;0m▀▀\x1b[0m"
+        );
+        // The last line of an odd grid has no row below it.
+        assert!(lines[1].starts_with("\x1b[38;2;255;255;255m\x1b[49m▀▀"));
+    }
+
+    #[test]
+    fn test_domain_coloring() {
+        // Two stripes of each sign make four domains with four different colours.
+        let mut grid = Grid::new_stripes(12, 4, 3).unwrap();
+        let mut coloring = DomainColoring::new();
+        let colors = coloring.update(&grid).to_vec();
+        let stripes: Vec<usize> = (0..4).map(|stripe| colors[3 * stripe]).collect();
+        assert_eq!(stripes, vec![0, 1, 2, 3]);
+        assert!(colors.chunks(12).all(|row| row == &colors[..12]));
+
+        // Flipping a column of the third stripe adds it to the second, which keeps its colour as
+        // the larger part of the merged domain, and so do the rest of the third and the fourth
+        // stripe. A flipped spin inside the first stripe is a new domain with a fresh colour.
+        for y in 0..4 {
+            grid.set(6, y, Spin::Down);
+        }
+        grid.set(10, 1, Spin::Vacant);
+        grid.set(1, 1, Spin::Down);
+        let colors = coloring.update(&grid).to_vec();
+        assert_eq!(&colors[..12], &[0, 0, 0, 1, 1, 1, 1, 2, 2, 3, 3, 3]);
+        assert_eq!(&colors[12..14], &[0, 4]);
+        assert_eq!(colors[12 + 10], usize::MAX);
+        assert_ne!(DomainColoring::color(0), DomainColoring::color(1));
+
+        let image = coloring.to_image(&grid, 2).unwrap();
+        assert_eq!(image.dimensions(), (24, 8));
+        assert_eq!(image.get_pixel(21, 3).0, [0, 0, 0]);
+        assert_eq!(image.get_pixel(13, 0).0, DomainColoring::color(1));
+    }
+
+    #[test]
+    fn test_video_recorder_stream() {
+        // Stand in for the encoder with a command that stores the raw stream.
+        let path = env::temp_dir().join("ising_model_test_video.rgb");
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(format!("cat > '{}'", path.display()));
+        let mut recorder = VideoRecorder::spawn(command, Palette::default(), 3, 2).unwrap();
+        let grid = Grid::new_random(5, 4).unwrap();
+        for sweep in 0..5 {
+            recorder.record(sweep, &grid).unwrap();
+        }
+        assert_eq!(recorder.frames(), 3);
+        recorder.finish().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let frame = grid.to_image(&Palette::default(), 3).unwrap().into_raw();
+        assert_eq!(bytes.len(), 3 * frame.len());
+        assert_eq!(&bytes[..frame.len()], frame.as_slice());
+
+        let failing = VideoRecorder::spawn(Command::new("false"), Palette::default(), 1, 1);
+        assert!(failing.unwrap().finish().is_err());
+    }
+}
@@ -0,0 +1,516 @@
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, info_span};
+
+use crate::analysis::blocked_mean;
+use crate::error::{check_finite, check_size, Error, Result};
+use crate::grid::Grid;
+use crate::measurement::Measurement;
+use crate::output::Observation;
+use crate::provenance::Provenance;
+use crate::simulation::{self, Simulation, SimulationParameters};
+use crate::spin::Spin;
+use crate::temperature::Temperature;
+
+/// # Scan point
+/// One simulation of a parameter scan: an L × L periodic grid at temperature T and field h, both in
+/// units of the coupling, with its own seed. With a crystal field Δ = D/J the grid is the spin-1
+/// Blume–Capel model, and without one an Ising model.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScanPoint {
+    pub size: usize,
+    pub temperature: Temperature,
+    pub field: f64,
+    #[serde(default)]
+    pub crystal_field: Option<f64>,
+    pub seed: u64,
+}
+
+/// # Scan result
+/// The observables measured at one scan point. The energy is per site in units of the coupling,
+/// the energy and magnetizations carry standard errors from blocking the time series, and the
+/// susceptibility χ = N (⟨m²⟩ - ⟨|m|⟩²)/T, the specific heat C = N (⟨e²⟩ - ⟨e⟩²)/T² and the
+/// Binder cumulant U = 1 - ⟨m⁴⟩/(3⟨m²⟩²) come from the moments of the whole series.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub size: usize,
+    pub temperature: f64,
+    pub field: f64,
+    pub crystal_field: Option<f64>,
+    pub seed: u64,
+    pub energy: Measurement,
+    pub magnetization: Measurement,
+    pub absolute_magnetization: Measurement,
+    pub susceptibility: f64,
+    pub specific_heat: f64,
+    pub binder_cumulant: f64,
+    pub acceptance: f64,
+}
+
+impl ScanResult {
+    /// # Summarize observations
+    /// The result of a scan point from the observations after each measurement sweep, whose
+    /// energies are in units of k_BT as returned by `Observation::of`.
+    pub fn from_observations(point: ScanPoint, observations: &[Observation]) -> Self {
+        let series =
+            |f: fn(&Observation) -> f64| -> Vec<f64> { observations.iter().map(f).collect() };
+        let energies: Vec<f64> = observations
+            .iter()
+            .map(|observation| observation.energy * point.temperature.value())
+            .collect();
+        let magnetizations = series(|observation| observation.magnetization);
+        let absolute = series(|observation| observation.magnetization.abs());
+        let mean = |values: &[f64], power: i32| {
+            values.iter().map(|value| value.powi(power)).sum::<f64>() / values.len() as f64
+        };
+
+        let number_of_sites = (point.size * point.size) as f64;
+        let energy = blocked_mean(&energies);
+        let absolute_magnetization = blocked_mean(&absolute);
+        let (second, fourth) = (mean(&magnetizations, 2), mean(&magnetizations, 4));
+        Self {
+            size: point.size,
+            temperature: point.temperature.value(),
+            field: point.field,
+            crystal_field: point.crystal_field,
+            seed: point.seed,
+            energy,
+            magnetization: blocked_mean(&magnetizations),
+            absolute_magnetization,
+            susceptibility: number_of_sites * (second - absolute_magnetization.value.powi(2))
+                / point.temperature.value(),
+            specific_heat: number_of_sites * (mean(&energies, 2) - energy.value.powi(2))
+                / point.temperature.value().powi(2),
+            binder_cumulant: 1.0 - fourth / (3.0 * second * second),
+            acceptance: mean(&series(|observation| observation.acceptance), 1),
+        }
+    }
+}
+
+/// # Scan record
+/// Everything measured at one scan point: its result, the observations after every measurement
+/// sweep and the configurations saved at regular intervals during the measurements, each as its
+/// spins row by row as +1, 0 or -1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanRecord {
+    pub point: ScanPoint,
+    pub result: ScanResult,
+    pub observations: Vec<Observation>,
+    pub configurations: Vec<Vec<i8>>,
+}
+
+/// # Parameter scan
+/// Every combination of the given sizes, temperatures, fields, crystal fields and seeds, each
+/// simulated from a random start for the thermalization sweeps and then measured after every one
+/// of the measurement sweeps. A crystal field of `None` is the Ising model, so a scan across the
+/// Blume–Capel phase diagram to its tricritical point can include the Ising line for comparison.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scan {
+    pub sizes: Vec<usize>,
+    pub temperatures: Vec<Temperature>,
+    pub fields: Vec<f64>,
+    pub crystal_fields: Vec<Option<f64>>,
+    pub seeds: Vec<u64>,
+    pub thermalization_sweeps: usize,
+    pub measurement_sweeps: usize,
+}
+
+impl Scan {
+    /// # Points
+    /// The scan points, with the seeds varying fastest and the sizes slowest.
+    pub fn points(&self) -> Vec<ScanPoint> {
+        let mut points = Vec::new();
+        for &size in &self.sizes {
+            for &temperature in &self.temperatures {
+                for &field in &self.fields {
+                    for &crystal_field in &self.crystal_fields {
+                        for &seed in &self.seeds {
+                            points.push(ScanPoint {
+                                size,
+                                temperature,
+                                field,
+                                crystal_field,
+                                seed,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        points
+    }
+
+    /// # Validate
+    /// Checks that every size has sites and every field and crystal field is finite.
+    pub fn validate(&self) -> Result<()> {
+        for &size in &self.sizes {
+            check_size(size, size)?;
+        }
+        for &field in &self.fields {
+            check_finite("field", field)?;
+        }
+        for &crystal_field in self.crystal_fields.iter().flatten() {
+            check_finite("crystal field", crystal_field)?;
+        }
+        Ok(())
+    }
+
+    /// # Run a point
+    /// Simulates one scan point. The result only depends on the point, so it can be reproduced
+    /// on its own. Fails if the point has no sites or an invalid field or crystal field.
+    pub fn run_point(&self, point: ScanPoint) -> Result<ScanResult> {
+        self.record_point(point, None).map(|record| record.result)
+    }
+
+    /// # Record a point
+    /// Simulates one scan point like `run_point`, keeping the observations and, given a snapshot
+    /// interval, the configuration after every that many measurement sweeps.
+    pub fn record_point(
+        &self,
+        point: ScanPoint,
+        snapshot_interval: Option<usize>,
+    ) -> Result<ScanRecord> {
+        let _span = info_span!(
+            "scan_point",
+            size = point.size,
+            temperature = point.temperature.value(),
+            field = point.field,
+            crystal_field = point.crystal_field,
+            seed = point.seed
+        )
+        .entered();
+        let start = Instant::now();
+        let parameters = SimulationParameters::at_temperature(point.temperature, point.field)?;
+        if let Some(crystal_field) = point.crystal_field {
+            check_finite("crystal field", crystal_field)?;
+        }
+        let mut rng = StdRng::seed_from_u64(point.seed);
+        let mut grid = Grid::new_with_magnetization(point.size, point.size, 0.0, &mut rng)?;
+        grid.set_crystal_field_ratio(point.crystal_field);
+        let mut simulation = Simulation::with_seed(grid, parameters, point.seed)?;
+        simulation.run(self.thermalization_sweeps);
+
+        let mut observations = Vec::with_capacity(self.measurement_sweeps);
+        let mut configurations = Vec::new();
+        for sweep in 1..=self.measurement_sweeps {
+            let acceptance = simulation.step();
+            observations.push(Observation::of(&simulation, acceptance));
+            if snapshot_interval.is_some_and(|interval| sweep.is_multiple_of(interval)) {
+                let spins = simulation.grid().iter_sites();
+                configurations.push(
+                    spins
+                        .map(|(_, spin)| match spin {
+                            Spin::Up => 1,
+                            Spin::Down => -1,
+                            _ => 0,
+                        })
+                        .collect(),
+                );
+            }
+        }
+        let result = ScanResult::from_observations(point, &observations);
+        info!(
+            elapsed_seconds = start.elapsed().as_secs_f64(),
+            absolute_magnetization = result.absolute_magnetization.value,
+            "finished scan point"
+        );
+        Ok(ScanRecord {
+            point,
+            result,
+            observations,
+            configurations,
+        })
+    }
+
+    /// # Provenance
+    /// A record of the algorithm and all the parameters of the scan. The seeds are those of the
+    /// points, so the record has none of its own.
+    pub fn provenance(&self) -> Provenance {
+        Provenance::new(simulation::ALGORITHM, None)
+            .with_parameter("sizes", &self.sizes)
+            .with_parameter("temperatures", &self.temperatures)
+            .with_parameter("fields", &self.fields)
+            .with_parameter("crystal_fields", &self.crystal_fields)
+            .with_parameter("seeds", &self.seeds)
+            .with_parameter("thermalization_sweeps", self.thermalization_sweeps)
+            .with_parameter("measurement_sweeps", self.measurement_sweeps)
+    }
+
+    /// # Provenance of a point
+    /// A record of the algorithm, the seed and the parameters of one scan point, from which
+    /// `run_point` reproduces it.
+    pub fn point_provenance(&self, point: ScanPoint) -> Provenance {
+        Provenance::new(simulation::ALGORITHM, Some(point.seed))
+            .with_parameter("size", point.size)
+            .with_parameter("temperature", point.temperature)
+            .with_parameter("field", point.field)
+            .with_parameter("crystal_field", point.crystal_field)
+            .with_parameter("thermalization_sweeps", self.thermalization_sweeps)
+            .with_parameter("measurement_sweeps", self.measurement_sweeps)
+    }
+
+    /// # Run
+    /// Simulates all the scan points, spread over the given number of threads, and returns the
+    /// results in the order of `points`. Fails if the scan does not validate or there are no
+    /// threads.
+    pub fn run(&self, threads: usize) -> Result<Vec<ScanResult>> {
+        self.for_each_point(threads, |point| self.run_point(point))
+    }
+
+    /// # Record
+    /// Simulates all the scan points like `run`, handing the record of `record_point` for each to
+    /// `write` on the calling thread as soon as the point is finished, so that only the points in
+    /// flight are held in memory. The records arrive in the order the points finish and the
+    /// results come back in the order of `points`. Fails like `run` or with the first error of
+    /// `write`, after which no more records are handed over.
+    pub fn record(
+        &self,
+        threads: usize,
+        snapshot_interval: Option<usize>,
+        mut write: impl FnMut(ScanRecord) -> Result<()>,
+    ) -> Result<Vec<ScanResult>> {
+        let (sender, receiver) = mpsc::sync_channel(threads);
+        thread::scope(|scope| {
+            let simulations = scope.spawn(move || {
+                self.for_each_point(threads, |point| {
+                    let record = self.record_point(point, snapshot_interval)?;
+                    let result = record.result;
+                    // The receiver only hangs up when writing failed, which is reported instead.
+                    let _ = sender.send(record);
+                    Ok(result)
+                })
+            });
+            let mut written = Ok(());
+            for record in receiver.iter() {
+                written = write(record);
+                if written.is_err() {
+                    break;
+                }
+            }
+            drop(receiver);
+            let results = simulations.join().unwrap()?;
+            written.map(|()| results)
+        })
+    }
+
+    /// Simulates every point with the given function, spread over the threads, and returns what
+    /// it gives in the order of `points`.
+    fn for_each_point<T: Send>(
+        &self,
+        threads: usize,
+        simulate: impl Fn(ScanPoint) -> Result<T> + Sync,
+    ) -> Result<Vec<T>> {
+        self.validate()?;
+        if threads == 0 {
+            return Err(Error::InvalidParameter {
+                name: "threads",
+                value: 0.0,
+            });
+        }
+        let points = self.points();
+        debug!(points = points.len(), threads, "starting scan");
+        let (points, simulate) = (&points, &simulate);
+        let mut results: Vec<(usize, Result<T>)> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|thread| {
+                    scope.spawn(move || {
+                        (thread..points.len())
+                            .step_by(threads)
+                            .map(|index| (index, simulate(points[index])))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap())
+                .collect()
+        });
+        results.sort_by_key(|&(index, _)| index);
+        results.into_iter().map(|(_, result)| result).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The records of all the scan points, in the order of `points`.
+    fn record_all(
+        scan: &Scan,
+        threads: usize,
+        snapshot_interval: Option<usize>,
+    ) -> Vec<ScanRecord> {
+        let mut records = Vec::new();
+        let results = scan
+            .record(threads, snapshot_interval, |record| {
+                records.push(record);
+                Ok(())
+            })
+            .unwrap();
+        let points = scan.points();
+        records.sort_by_key(|record| points.iter().position(|&point| point == record.point));
+        assert_eq!(
+            results,
+            records
+                .iter()
+                .map(|record| record.result)
+                .collect::<Vec<_>>()
+        );
+        records
+    }
+
+    /// The given reduced temperatures.
+    fn temperatures(values: &[f64]) -> Vec<Temperature> {
+        values
+            .iter()
+            .map(|&value| Temperature::new(value).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_scan() {
+        let scan = Scan {
+            sizes: vec![8],
+            temperatures: temperatures(&[1.0, 10.0]),
+            fields: vec![0.0],
+            crystal_fields: vec![None],
+            seeds: vec![1, 2],
+            thermalization_sweeps: 200,
+            measurement_sweeps: 320,
+        };
+        assert_eq!(scan.points().len(), 4);
+        let results = scan.run(2).unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[1].seed, 2);
+        assert_eq!(results[1], scan.run_point(scan.points()[1]).unwrap());
+
+        // Deep in the ordered phase nearly all spins align, and at high temperature the energy is
+        // close to its expansion -2 tanh(1/T).
+        assert!(results[0].absolute_magnetization.value > 0.99);
+        assert!((results[0].energy.value + 2.0).abs() < 0.02);
+        assert!(results[2].absolute_magnetization.value < 0.3);
+        assert!((results[2].energy.value + 2.0 * 0.1_f64.tanh()).abs() < 0.05);
+        assert!(results[2].energy.error > 0.0 && results[2].energy.error < 0.02);
+        assert!(results[2].binder_cumulant < 0.4);
+
+        let provenance = scan.provenance();
+        assert_eq!(
+            provenance.parameters["temperatures"],
+            serde_json::json!([1.0, 10.0])
+        );
+        assert_eq!(provenance.parameters["measurement_sweeps"], 320);
+    }
+
+    #[test]
+    fn test_record() {
+        let scan = Scan {
+            sizes: vec![6],
+            temperatures: temperatures(&[2.5]),
+            fields: vec![0.0],
+            crystal_fields: vec![None],
+            seeds: vec![1, 2],
+            thermalization_sweeps: 10,
+            measurement_sweeps: 25,
+        };
+        let records = record_all(&scan, 2, Some(10));
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].point, scan.points()[1]);
+        assert_eq!(records[1].result, scan.run_point(scan.points()[1]).unwrap());
+        assert_eq!(records[1].observations.len(), 25);
+        assert_eq!(records[1].configurations.len(), 2);
+        assert_eq!(records[1].configurations[0].len(), 36);
+        assert!(record_all(&scan, 1, None)[0].configurations.is_empty());
+
+        let mut written = 0;
+        let failed = scan.record(2, None, |_| {
+            written += 1;
+            Err(Error::Unsupported("no space left"))
+        });
+        assert!(matches!(failed, Err(Error::Unsupported(_))));
+        assert_eq!(written, 1);
+
+        let provenance = scan.point_provenance(records[1].point);
+        assert_eq!(provenance.seed, Some(2));
+        assert_eq!(provenance.parameters["size"], 6);
+        assert_eq!(provenance.parameters["temperature"], 2.5);
+        assert_eq!(
+            provenance.parameters["crystal_field"],
+            serde_json::Value::Null
+        );
+    }
+
+    #[test]
+    fn test_crystal_field() {
+        let scan = Scan {
+            sizes: vec![6],
+            temperatures: temperatures(&[0.5]),
+            fields: vec![0.0],
+            crystal_fields: vec![None, Some(3.0)],
+            seeds: vec![1],
+            thermalization_sweeps: 200,
+            measurement_sweeps: 50,
+        };
+        let records = record_all(&scan, 2, Some(50));
+        assert_eq!(records[1].result.crystal_field, Some(3.0));
+        // Beyond Δ = 2 the zero state wins even at low temperature, where the Ising grid orders.
+        assert!(records[0].result.absolute_magnetization.value > 0.99);
+        assert!(records[1].result.absolute_magnetization.value < 0.05);
+        assert!(records[1].configurations[0].iter().all(|&spin| spin == 0));
+        assert_eq!(
+            scan.point_provenance(records[1].point).parameters["crystal_field"],
+            3.0
+        );
+    }
+
+    #[test]
+    fn test_invalid_scan() {
+        let scan = Scan {
+            sizes: vec![4],
+            temperatures: temperatures(&[2.0]),
+            fields: vec![0.0, f64::NAN],
+            crystal_fields: vec![None],
+            seeds: vec![1],
+            thermalization_sweeps: 1,
+            measurement_sweeps: 1,
+        };
+        assert!(matches!(
+            scan.run(1),
+            Err(Error::InvalidParameter { name: "field", .. })
+        ));
+        let empty = Scan {
+            sizes: vec![0],
+            fields: vec![0.0],
+            ..scan.clone()
+        };
+        assert!(matches!(empty.run(1), Err(Error::EmptyGrid { .. })));
+        let crystal_field = Scan {
+            fields: vec![0.0],
+            crystal_fields: vec![Some(f64::INFINITY)],
+            ..scan.clone()
+        };
+        assert!(matches!(
+            crystal_field.run(1),
+            Err(Error::InvalidParameter {
+                name: "crystal field",
+                ..
+            })
+        ));
+        let threads = Scan {
+            fields: vec![0.0],
+            ..scan
+        };
+        assert!(matches!(
+            threads.run(0),
+            Err(Error::InvalidParameter {
+                name: "threads",
+                ..
+            })
+        ));
+    }
+}
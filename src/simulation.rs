@@ -0,0 +1,578 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::adaptive::{AdaptiveSelection, Schedule, Selection};
+use crate::error::{check_finite, check_size, Result};
+use crate::flip_log::FlipLog;
+use crate::grid::Grid;
+use crate::provenance::Provenance;
+use crate::random_cluster::{swendsen_wang_step_with_clusters, BondClusters, ClusterEstimators};
+use crate::temperature::Temperature;
+
+/// The identifier of the update of `Grid::step`, a Metropolis sweep over the sites in order.
+pub const ALGORITHM: &str = "metropolis-sequential";
+
+/// # Simulation parameters
+/// The reduced coupling and field that a simulation passes to `Grid::step`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SimulationParameters {
+    pub coupling: f64,
+    pub field: f64,
+}
+
+impl SimulationParameters {
+    /// # Parameters at a temperature
+    /// The reduced coupling β = 1/T and field βh at temperature T and field h in units of the
+    /// coupling. Fails unless the field is finite.
+    pub fn at_temperature(temperature: Temperature, field: f64) -> Result<Self> {
+        check_finite("field", field)?;
+        Ok(Self {
+            coupling: temperature.beta(),
+            field: field * temperature.beta(),
+        })
+    }
+
+    /// Checks that the parameters are finite.
+    fn validate(&self) -> Result<()> {
+        check_finite("coupling", self.coupling)?;
+        check_finite("field", self.field)
+    }
+}
+
+/// # Magnetization moments
+/// The running sums of the magnetization per site and its powers, from which the averages, the
+/// susceptibility and the Binder cumulant follow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct MagnetizationMoments {
+    samples: usize,
+    magnetization: f64,
+    absolute: f64,
+    squared: f64,
+    fourth: f64,
+}
+
+impl MagnetizationMoments {
+    /// # Add a sample
+    /// Adds a measured magnetization per site.
+    pub fn add(&mut self, magnetization: f64) {
+        let squared = magnetization * magnetization;
+        self.samples += 1;
+        self.magnetization += magnetization;
+        self.absolute += magnetization.abs();
+        self.squared += squared;
+        self.fourth += squared * squared;
+    }
+
+    /// # Number of samples
+    /// The number of magnetizations added so far.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// # Mean magnetization
+    /// The average of m.
+    pub fn mean(&self) -> f64 {
+        self.magnetization / self.samples as f64
+    }
+
+    /// # Mean absolute magnetization
+    /// The average of |m|.
+    pub fn mean_absolute(&self) -> f64 {
+        self.absolute / self.samples as f64
+    }
+
+    /// # Binder cumulant
+    /// U = 1 - ⟨m⁴⟩ / 3⟨m²⟩².
+    pub fn binder_cumulant(&self) -> f64 {
+        let samples = self.samples as f64;
+        let squared = self.squared / samples;
+        1.0 - self.fourth / samples / (3.0 * squared * squared)
+    }
+}
+
+/// # Simulation
+/// A grid together with the parameters it is simulated at, the number of sweeps done so far, the
+/// observables measured along the way and the state of its random number generator. All of it
+/// can be serialized, so a run can be saved, inspected and continued later exactly as if it had
+/// never stopped, except for the flip log, which is saved on its own.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Simulation {
+    grid: Grid,
+    parameters: SimulationParameters,
+    sweep: usize,
+    moments: MagnetizationMoments,
+    rng: ChaCha8Rng,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    schedule: Schedule,
+    #[serde(default)]
+    cluster_estimators: Option<ClusterEstimators>,
+    #[serde(skip)]
+    clusters: Option<BondClusters>,
+    #[serde(skip)]
+    flip_log: Option<FlipLog>,
+}
+
+impl Simulation {
+    /// # New simulation
+    /// Starts a simulation of the grid at the given parameters, with a generator seeded from the
+    /// operating system. Fails if the grid has no sites or the parameters are not finite.
+    pub fn new(grid: Grid, parameters: SimulationParameters) -> Result<Self> {
+        Self::with_rng(grid, parameters, ChaCha8Rng::from_entropy())
+    }
+
+    /// # New seeded simulation
+    /// Starts a simulation whose random numbers all follow from the given seed, so that the run
+    /// can be reproduced. Fails like `new`.
+    pub fn with_seed(grid: Grid, parameters: SimulationParameters, seed: u64) -> Result<Self> {
+        Ok(Self {
+            seed: Some(seed),
+            ..Self::with_rng(grid, parameters, ChaCha8Rng::seed_from_u64(seed))?
+        })
+    }
+
+    fn with_rng(grid: Grid, parameters: SimulationParameters, rng: ChaCha8Rng) -> Result<Self> {
+        check_size(grid.width(), grid.height())?;
+        parameters.validate()?;
+        Ok(Self {
+            grid,
+            parameters,
+            sweep: 0,
+            moments: MagnetizationMoments::default(),
+            rng,
+            seed: None,
+            schedule: Schedule::default(),
+            cluster_estimators: None,
+            clusters: None,
+            flip_log: None,
+        })
+    }
+
+    /// # Seed
+    /// The seed that the simulation was started with, if it was seeded.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// # Provenance
+    /// A record of the algorithm, seed and parameters of the simulation at this point.
+    pub fn provenance(&self) -> Provenance {
+        Provenance::new(self.schedule.name(), self.seed)
+            .with_parameter("width", self.grid.width())
+            .with_parameter("height", self.grid.height())
+            .with_parameter("coupling", self.parameters.coupling)
+            .with_parameter("field", self.parameters.field)
+            .with_parameter("boundary_conditions", self.grid.boundary_conditions())
+            .with_parameter("sweep", self.sweep)
+    }
+
+    /// # Into the grid
+    /// Ends the simulation and gives back its current configuration.
+    pub fn into_grid(self) -> Grid {
+        self.grid
+    }
+
+    /// # Grid
+    /// The current configuration.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// # Mutable grid
+    /// The current configuration, for changes such as pinning spins between sweeps.
+    pub fn grid_mut(&mut self) -> &mut Grid {
+        &mut self.grid
+    }
+
+    /// # Parameters
+    /// The parameters of the next sweeps.
+    pub fn parameters(&self) -> SimulationParameters {
+        self.parameters
+    }
+
+    /// # Set the parameters
+    /// Changes the parameters of the next sweeps, as in an annealing schedule. A field that the
+    /// cluster updates of the schedule cannot sample switches it back to Metropolis sweeps.
+    pub fn set_parameters(&mut self, parameters: SimulationParameters) {
+        self.parameters = parameters;
+        if self.schedule.check(&self.grid, parameters.field).is_err() {
+            self.schedule = Schedule::Metropolis;
+        }
+    }
+
+    /// # Schedule
+    /// The updates that make up a step.
+    pub fn schedule(&self) -> Schedule {
+        self.schedule
+    }
+
+    /// # Set the schedule
+    /// Changes the updates of the next steps. Fails if they cannot sample the grid at the current
+    /// field.
+    pub fn set_schedule(&mut self, schedule: Schedule) -> Result<()> {
+        schedule.check(&self.grid, self.parameters.field)?;
+        self.schedule = schedule;
+        Ok(())
+    }
+
+    /// # Adapt the schedule
+    /// Warms the grid up with the simulation's generator while picking the schedule of the next
+    /// steps, as described at `AdaptiveSelection`, and returns what it measured. The warm-up does
+    /// not count as sweeps and is not measured.
+    pub fn adapt(&mut self, selection: &AdaptiveSelection) -> Selection {
+        let (coupling, field) = (self.parameters.coupling, self.parameters.field);
+        let selection = selection.select(&mut self.grid, coupling, field, &mut self.rng);
+        self.schedule = selection.chosen;
+        debug!(schedule = %self.schedule, "adapted the schedule");
+        selection
+    }
+
+    /// # Sweep counter
+    /// The number of sweeps done so far.
+    pub fn sweep(&self) -> usize {
+        self.sweep
+    }
+
+    /// # Magnetization moments
+    /// The moments of the magnetization measured so far.
+    pub fn moments(&self) -> &MagnetizationMoments {
+        &self.moments
+    }
+
+    /// # Record flips
+    /// Starts recording every accepted Metropolis move of the next sweeps in a new flip log,
+    /// whose sweeps are counted from here. Recording does not change the moves that are made.
+    /// Cluster updates are not recorded, so only a log of Metropolis sweeps replays the run.
+    pub fn record_flips(&mut self) {
+        self.flip_log = Some(FlipLog::new(self.grid.width(), self.grid.height()));
+    }
+
+    /// # Flip log
+    /// The moves recorded since `record_flips`, if it was called.
+    pub fn flip_log(&self) -> Option<&FlipLog> {
+        self.flip_log.as_ref()
+    }
+
+    /// # Take the flip log
+    /// Stops recording and gives back the moves recorded so far.
+    pub fn take_flip_log(&mut self) -> Option<FlipLog> {
+        self.flip_log.take()
+    }
+
+    /// # Step
+    /// Performs one step of the schedule with the simulation's generator and advances the
+    /// counter, recording the accepted moves if a flip log is kept. Returns the fraction of the
+    /// attempted Metropolis moves that were accepted, which is 1 for the rejection-free cluster
+    /// updates alone.
+    pub fn step(&mut self) -> f64 {
+        let (coupling, field) = (self.parameters.coupling, self.parameters.field);
+        let mut accepted = 0;
+        for _ in 0..self.schedule.metropolis_sweeps() {
+            accepted += match &mut self.flip_log {
+                Some(log) => self
+                    .grid
+                    .step_with_flip_log(coupling, field, &mut self.rng, log)
+                    .expect("the flip log is made for the grid"),
+                None => self
+                    .grid
+                    .step_reduced_with_rng(coupling, field, &mut self.rng),
+            };
+        }
+        for _ in 0..self.schedule.cluster_updates() {
+            let (_, clusters) =
+                swendsen_wang_step_with_clusters(&mut self.grid, coupling, &mut self.rng);
+            self.clusters = Some(clusters);
+        }
+        self.sweep += 1;
+        let attempted = self.schedule.metropolis_sweeps() * self.grid.width() * self.grid.height();
+        if attempted == 0 {
+            return 1.0;
+        }
+        accepted as f64 / attempted as f64
+    }
+
+    /// # Run
+    /// Performs the given number of sweeps.
+    pub fn run(&mut self, sweeps: usize) {
+        for _ in 0..sweeps {
+            self.step();
+        }
+        debug!(sweeps, sweep = self.sweep, "ran sweeps");
+    }
+
+    /// # Measure
+    /// Adds the magnetization per occupied site of the current configuration to the moments, and
+    /// returns it. After a step with a cluster update, the configuration and the clusters of that
+    /// update are also added to the cluster estimators.
+    pub fn measure(&mut self) -> f64 {
+        let occupied = self.grid.number_of_occupied_sites() as f64;
+        let magnetization = self.grid.magnetization() / occupied;
+        self.moments.add(magnetization);
+        if let Some(clusters) = self.clusters.take() {
+            let (width, height) = (self.grid.width(), self.grid.height());
+            self.cluster_estimators
+                .get_or_insert_with(|| ClusterEstimators::new(width, height))
+                .add(&self.grid, &clusters)
+                .expect("the estimators are made for the grid");
+        }
+        magnetization
+    }
+
+    /// # Cluster estimators
+    /// The conventional and improved estimators measured after the steps with cluster updates,
+    /// if there were any.
+    pub fn cluster_estimators(&self) -> Option<&ClusterEstimators> {
+        self.cluster_estimators.as_ref()
+    }
+
+    /// # Save a checkpoint
+    /// Writes the full state as JSON, together with its provenance under `provenance`. The file
+    /// is written next to its destination first and then moved into place, so an interruption
+    /// never leaves a half-written checkpoint behind.
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.save_checkpoint_with_outputs(path, &BTreeMap::new())
+    }
+
+    /// # Save a checkpoint with outputs
+    /// Writes the state as `save_checkpoint` does, together with the lengths in bytes of the
+    /// outputs of the run under `outputs`, keyed by their names. A resumed run cuts its outputs
+    /// back to these lengths, so that what was written after the checkpoint is not repeated.
+    pub fn save_checkpoint_with_outputs(
+        &self,
+        path: impl AsRef<Path>,
+        outputs: &BTreeMap<String, u64>,
+    ) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        let mut checkpoint = serde_json::to_value(self)?;
+        checkpoint["provenance"] = serde_json::to_value(self.provenance())?;
+        checkpoint["outputs"] = serde_json::to_value(outputs)?;
+        fs::write(&temporary, checkpoint.to_string())?;
+        fs::rename(&temporary, path)?;
+        debug!(sweep = self.sweep, path = %path.display(), "saved checkpoint");
+        Ok(())
+    }
+
+    /// # Load a checkpoint
+    /// Reads a state written by `save_checkpoint`, failing on an empty grid or parameters that are
+    /// not finite.
+    pub fn load_checkpoint(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)?;
+        let simulation: Self = serde_json::from_str(&contents)?;
+        check_size(simulation.grid.width(), simulation.grid.height())?;
+        simulation.parameters.validate()?;
+        debug!(sweep = simulation.sweep, path = %path.display(), "loaded checkpoint");
+        Ok(simulation)
+    }
+
+    /// # Load the outputs of a checkpoint
+    /// Reads the lengths of the outputs saved by `save_checkpoint_with_outputs`, which are none
+    /// for a checkpoint written without them.
+    pub fn load_checkpoint_outputs(path: impl AsRef<Path>) -> io::Result<BTreeMap<String, u64>> {
+        let mut checkpoint: serde_json::Value = serde_json::from_str(&fs::read_to_string(path)?)?;
+        match checkpoint.get_mut("outputs") {
+            Some(outputs) => Ok(serde_json::from_value(outputs.take())?),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::couplings::BondCouplings;
+    use crate::error::Error;
+    use crate::field::FieldMap;
+    use crate::grid::BoundaryCondition;
+    use crate::spin::Spin;
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut grid = Grid::new_random(6, 4).unwrap();
+        grid.set(2, 1, Spin::Vacant);
+        grid.pin(0, 0);
+        grid.set_boundary_conditions(
+            BoundaryCondition::Fixed(Spin::Up),
+            BoundaryCondition::Antiperiodic,
+        );
+        grid.set_bond_couplings(Some(BondCouplings::uniform(6, 4, 0.5)))
+            .unwrap();
+        grid.set_field_map(Some(FieldMap::uniform(6, 4, -0.1)))
+            .unwrap();
+        let mut simulation = Simulation::new(
+            grid,
+            SimulationParameters {
+                coupling: 0.4,
+                field: 0.01,
+            },
+        )
+        .unwrap();
+        simulation.run(3);
+
+        let json = serde_json::to_string(&simulation).unwrap();
+        let restored: Simulation = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.sweep(), 3);
+        assert_eq!(restored.parameters(), simulation.parameters());
+        assert_eq!(restored.grid().get(2, 1), Spin::Vacant);
+        assert!(restored.grid().is_pinned(0, 0));
+        assert_eq!(
+            restored.grid().boundary_conditions(),
+            simulation.grid().boundary_conditions()
+        );
+        for y in 0..4 {
+            for x in 0..6 {
+                assert_eq!(restored.grid().get(x, y), simulation.grid().get(x, y));
+            }
+        }
+        assert_eq!(serde_json::to_string(&restored).unwrap(), json);
+        assert_eq!(serde_json::to_string(&Spin::Zero).unwrap(), "\"Zero\"");
+    }
+
+    #[test]
+    fn test_invalid_simulations() {
+        let temperature = Temperature::new(2.0).unwrap();
+        let parameters = SimulationParameters::at_temperature(temperature, 0.5).unwrap();
+        assert_eq!((parameters.coupling, parameters.field), (0.5, 0.25));
+        assert!(matches!(
+            SimulationParameters::at_temperature(temperature, f64::NAN),
+            Err(Error::InvalidParameter { name: "field", .. })
+        ));
+        assert!(matches!(
+            Grid::new_random(0, 3),
+            Err(Error::EmptyGrid {
+                width: 0,
+                height: 3
+            })
+        ));
+        let parameters = SimulationParameters {
+            coupling: f64::NAN,
+            field: 0.0,
+        };
+        assert!(matches!(
+            Simulation::new(Grid::new_random(2, 3).unwrap(), parameters),
+            Err(Error::InvalidParameter {
+                name: "coupling",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_resume_is_exact() {
+        let parameters = SimulationParameters {
+            coupling: 0.44,
+            field: 0.0,
+        };
+        let run = |simulation: &mut Simulation, sweeps: usize| {
+            for _ in 0..sweeps {
+                simulation.step();
+                simulation.measure();
+            }
+        };
+
+        let mut uninterrupted = Simulation::with_seed(
+            Grid::new_constant(10, 10, Spin::Up).unwrap(),
+            parameters,
+            17,
+        )
+        .unwrap();
+        run(&mut uninterrupted, 40);
+
+        let path = env::temp_dir().join("ising_model_test_checkpoint.json");
+        let mut interrupted = Simulation::with_seed(
+            Grid::new_constant(10, 10, Spin::Up).unwrap(),
+            parameters,
+            17,
+        )
+        .unwrap();
+        run(&mut interrupted, 25);
+        interrupted.save_checkpoint(&path).unwrap();
+        drop(interrupted);
+        let checkpoint: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(checkpoint["provenance"]["seed"], 17);
+        assert_eq!(checkpoint["provenance"]["parameters"]["sweep"], 25);
+        assert_eq!(checkpoint["provenance"]["algorithm"], ALGORITHM);
+        let mut resumed = Simulation::load_checkpoint(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        run(&mut resumed, 15);
+
+        assert_eq!(resumed.sweep(), 40);
+        assert_eq!(resumed.moments(), uninterrupted.moments());
+        assert_eq!(
+            serde_json::to_string(&resumed).unwrap(),
+            serde_json::to_string(&uninterrupted).unwrap()
+        );
+        assert!(resumed.moments().mean_absolute() > 0.0);
+    }
+
+    #[test]
+    fn test_schedule() {
+        let parameters = SimulationParameters {
+            coupling: 0.44,
+            field: 0.0,
+        };
+        let mut simulation =
+            Simulation::with_seed(Grid::new_random(8, 8).unwrap(), parameters, 6).unwrap();
+        assert_eq!(simulation.schedule(), Schedule::Metropolis);
+        simulation.set_schedule(Schedule::SwendsenWang).unwrap();
+        assert_eq!(simulation.step(), 1.0);
+        assert_eq!(simulation.provenance().algorithm, "swendsen-wang");
+        assert!(simulation.cluster_estimators().is_none());
+        simulation.measure();
+        simulation.measure();
+        assert_eq!(simulation.cluster_estimators().unwrap().samples(), 1);
+
+        simulation.set_parameters(SimulationParameters {
+            field: 0.1,
+            ..parameters
+        });
+        assert_eq!(simulation.schedule(), Schedule::Metropolis);
+        assert!(matches!(
+            simulation.set_schedule(Schedule::Mixed),
+            Err(Error::Unsupported(_))
+        ));
+    }
+
+    #[test]
+    fn test_flip_log() {
+        let parameters = SimulationParameters {
+            coupling: 0.3,
+            field: 0.0,
+        };
+        let mut plain =
+            Simulation::with_seed(Grid::new_constant(6, 6, Spin::Up).unwrap(), parameters, 3)
+                .unwrap();
+        let mut logged =
+            Simulation::with_seed(Grid::new_constant(6, 6, Spin::Up).unwrap(), parameters, 3)
+                .unwrap();
+        plain.run(5);
+        logged.run(5);
+        assert!(logged.flip_log().is_none());
+
+        logged.record_flips();
+        let acceptance: f64 = (0..10).map(|_| logged.step()).sum();
+        plain.run(10);
+        assert_eq!(
+            serde_json::to_string(&plain).unwrap(),
+            serde_json::to_string(&logged).unwrap()
+        );
+
+        let log = logged.take_flip_log().unwrap();
+        assert!(logged.flip_log().is_none());
+        assert_eq!(log.sweeps(), 10);
+        assert_eq!(log.len() as f64, (acceptance * 36.0).round());
+    }
+}
@@ -1,28 +1,113 @@
-/// Represents the spin at a site on a lattice.
-#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq)]
+use std::fmt;
+
+use rand::distributions::{Distribution, Standard};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Represents the spin at a site on a lattice. A vacant site carries no spin at all: it counts as
+/// zero in every sum and is never updated. The zero state is the S = 0 state of a spin-1 site in
+/// the Blume–Capel model; it also counts as zero, but unlike a vacancy it takes part in updates.
+#[derive(Debug, Clone, Copy, PartialEq, Hash, Eq, Serialize, Deserialize)]
 pub enum Spin {
     Up,
     Down,
+    Zero,
+    Vacant,
 }
 
 impl Spin {
+    /// # Random spin
+    /// Draws up or down with equal probability from the given generator.
+    pub fn random<R: Rng + ?Sized>(rng: &mut R) -> Spin {
+        rng.gen()
+    }
+
+    /// # Value
+    /// The spin as a number: plus or minus one for up and down, and zero for the zero state and
+    /// vacancies.
+    pub fn value(&self) -> f64 {
+        match self {
+            Spin::Up => 1.0,
+            Spin::Down => -1.0,
+            Spin::Zero | Spin::Vacant => 0.0,
+        }
+    }
+
     /// # Flip
-    /// Returns a new spin that is the opposite of the current spin.
+    /// Returns a new spin that is the opposite of the current spin. The zero state and vacancies
+    /// are their own opposites.
     pub fn flip(&self) -> Spin {
         match self {
             Spin::Up => Spin::Down,
             Spin::Down => Spin::Up,
+            Spin::Zero => Spin::Zero,
+            Spin::Vacant => Spin::Vacant,
+        }
+    }
+}
+
+impl Distribution<Spin> for Standard {
+    /// Draws up or down with equal probability, so that `rng.gen::<Spin>()` gives an infinite
+    /// temperature spin. It never draws the zero state or a vacancy.
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Spin {
+        if rng.gen::<bool>() {
+            Spin::Up
+        } else {
+            Spin::Down
         }
     }
 }
 
+impl fmt::Display for Spin {
+    /// Writes the spin as one character: `+` and `-` for up and down, `0` for the zero state and
+    /// `.` for a vacancy, as in the files written by `Grid::save`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let symbol = match self {
+            Spin::Up => '+',
+            Spin::Down => '-',
+            Spin::Zero => '0',
+            Spin::Vacant => '.',
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
     use super::*;
 
     #[test]
     fn test_flip() {
         assert_eq!(Spin::Up.flip(), Spin::Down);
         assert_eq!(Spin::Down.flip(), Spin::Up);
+        assert_eq!(Spin::Zero.flip(), Spin::Zero);
+        assert_eq!(Spin::Vacant.flip(), Spin::Vacant);
+    }
+
+    #[test]
+    fn test_random() {
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let spins: Vec<Spin> = (0..1000).map(|_| rng.gen()).collect();
+        let up = spins.iter().filter(|&&spin| spin == Spin::Up).count();
+        assert!((450..550).contains(&up));
+        assert!(spins
+            .iter()
+            .all(|&spin| spin == Spin::Up || spin == Spin::Down));
+
+        // The helper draws the same spins from the same generator.
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        assert!(spins.iter().all(|&spin| Spin::random(&mut rng) == spin));
+    }
+
+    #[test]
+    fn test_display() {
+        let symbols: String = [Spin::Up, Spin::Down, Spin::Zero, Spin::Vacant]
+            .iter()
+            .map(Spin::to_string)
+            .collect();
+        assert_eq!(symbols, "+-0.");
     }
 }
@@ -0,0 +1,384 @@
+use rand::Rng;
+
+use crate::couplings::{BondCouplings, BondDirection};
+use crate::error::{Error, Result};
+use crate::grid::Grid;
+use crate::temperature::Temperature;
+
+/// # ±J couplings
+/// Draws a quenched disorder realization of the Edwards–Anderson model, where each bond is
+/// independently antiferromagnetic (-1) with probability `antiferromagnetic_fraction` and
+/// ferromagnetic (+1) otherwise. Passing a seeded generator reproduces the same realization.
+/// Fails unless the fraction is between zero and one.
+pub fn plus_minus_couplings<R: Rng>(
+    width: usize,
+    height: usize,
+    antiferromagnetic_fraction: f64,
+    rng: &mut R,
+) -> Result<BondCouplings> {
+    if !(0.0..=1.0).contains(&antiferromagnetic_fraction) {
+        return Err(Error::InvalidParameter {
+            name: "antiferromagnetic fraction",
+            value: antiferromagnetic_fraction,
+        });
+    }
+    Ok(BondCouplings::from_fn(width, height, |_, _, _| {
+        if rng.gen_bool(antiferromagnetic_fraction) {
+            -1.0
+        } else {
+            1.0
+        }
+    }))
+}
+
+/// # Frustrated plaquettes
+/// Counts the plaquettes whose four bonds have a negative product. No spin configuration can
+/// satisfy all the bonds of such a plaquette, and their density is what separates a spin glass
+/// from a ferromagnet in disguise.
+pub fn frustrated_plaquettes(couplings: &BondCouplings) -> usize {
+    let mut count = 0;
+    for y in 0..couplings.height() as i64 {
+        for x in 0..couplings.width() as i64 {
+            let product = couplings.get(x, y, BondDirection::Horizontal)
+                * couplings.get(x + 1, y, BondDirection::Vertical)
+                * couplings.get(x, y + 1, BondDirection::Horizontal)
+                * couplings.get(x, y, BondDirection::Vertical);
+            if product < 0.0 {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// # Overlap
+/// The spin overlap q = (1/N) Σ s_i^a s_i^b between two replicas that share the same disorder.
+/// The global spin-flip symmetry makes the magnetization useless in a spin glass, so this takes
+/// its place as the order parameter.
+pub fn overlap(a: &Grid, b: &Grid) -> f64 {
+    assert_same_size(a, b);
+    a.overlap(b).expect("the replicas have the same size")
+}
+
+/// # Link overlap
+/// The bond overlap q_l = (1/2N) Σ_⟨ij⟩ s_i^a s_j^a s_i^b s_j^b between two replicas, which
+/// measures how many bonds the two replicas satisfy in the same way.
+pub fn link_overlap(a: &Grid, b: &Grid) -> f64 {
+    assert_same_size(a, b);
+    let bond = |grid: &Grid, x: i64, y: i64, dx: i64, dy: i64| {
+        grid.get_spin_as_float(x, y) * grid.get_spin_as_float(x + dx, y + dy)
+    };
+
+    let mut sum = 0.0;
+    for y in 0..a.height() as i64 {
+        for x in 0..a.width() as i64 {
+            sum += bond(a, x, y, 1, 0) * bond(b, x, y, 1, 0);
+            sum += bond(a, x, y, 0, 1) * bond(b, x, y, 0, 1);
+        }
+    }
+    sum / (2 * a.width() * a.height()) as f64
+}
+
+fn assert_same_size(a: &Grid, b: &Grid) {
+    assert!(
+        a.width() == b.width() && a.height() == b.height(),
+        "the replicas must have the same size"
+    );
+}
+
+/// # Overlap statistics
+/// Accumulates the moments of the overlap over many samples and disorder realizations, from which
+/// the spin-glass susceptibility and the Binder ratio follow.
+#[derive(Debug, Clone, Default)]
+pub struct OverlapStatistics {
+    samples: usize,
+    absolute: f64,
+    squared: f64,
+    fourth: f64,
+}
+
+impl OverlapStatistics {
+    /// # New overlap statistics
+    /// Creates an empty accumulator.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// # Add a sample
+    /// Adds a measured overlap.
+    pub fn add(&mut self, overlap: f64) {
+        let squared = overlap * overlap;
+        self.samples += 1;
+        self.absolute += overlap.abs();
+        self.squared += squared;
+        self.fourth += squared * squared;
+    }
+
+    /// # Number of samples
+    /// The number of overlaps added so far.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// # Mean absolute overlap
+    /// The average of |q|.
+    pub fn mean_absolute(&self) -> f64 {
+        self.absolute / self.samples as f64
+    }
+
+    /// # Spin-glass susceptibility
+    /// χ_SG = N⟨q²⟩ for a system of N spins.
+    pub fn susceptibility(&self, number_of_sites: usize) -> f64 {
+        number_of_sites as f64 * self.squared / self.samples as f64
+    }
+
+    /// # Binder ratio
+    /// g = (3 - ⟨q⁴⟩/⟨q²⟩²) / 2, which goes from zero in the paramagnet to one in a phase with a
+    /// single pair of ordered states. Curves for different sizes cross at the spin-glass
+    /// transition.
+    pub fn binder_ratio(&self) -> f64 {
+        let samples = self.samples as f64;
+        let squared = self.squared / samples;
+        0.5 * (3.0 - self.fourth / samples / (squared * squared))
+    }
+}
+
+/// # Replica pair
+/// Two independent copies of a grid that share the same disorder realization, so that their
+/// overlap can be measured while both are simulated.
+#[derive(Debug)]
+pub struct ReplicaPair {
+    a: Grid,
+    b: Grid,
+}
+
+impl ReplicaPair {
+    /// # New replica pair
+    /// Creates two grids with independent random spins and the given bond couplings. Fails if the
+    /// couplings have no sites.
+    pub fn new(couplings: &BondCouplings) -> Result<Self> {
+        let replica = || -> Result<Grid> {
+            let mut grid = Grid::new_random(couplings.width(), couplings.height())?;
+            grid.set_bond_couplings(Some(couplings.clone()))?;
+            Ok(grid)
+        };
+        Ok(Self {
+            a: replica()?,
+            b: replica()?,
+        })
+    }
+
+    /// # Replicas
+    /// The two replicas.
+    pub fn replicas(&self) -> (&Grid, &Grid) {
+        (&self.a, &self.b)
+    }
+
+    /// # Step
+    /// Performs a Monte Carlo sweep of both replicas at the same temperature T and field h, in
+    /// units of the coupling.
+    pub fn step(&mut self, temperature: Temperature, field: f64) {
+        self.a.step(temperature, field);
+        self.b.step(temperature, field);
+    }
+
+    /// # Overlap
+    /// The spin overlap between the two replicas.
+    pub fn overlap(&self) -> f64 {
+        overlap(&self.a, &self.b)
+    }
+
+    /// # Link overlap
+    /// The bond overlap between the two replicas.
+    pub fn link_overlap(&self) -> f64 {
+        link_overlap(&self.a, &self.b)
+    }
+}
+
+/// # Overlap distribution
+/// A histogram of the overlap over [-1, 1], accumulated over samples and disorder realizations.
+/// In a paramagnet P(q) is a single peak at zero, in a ferromagnet two peaks at ±q_EA, and in a
+/// spin glass with replica symmetry breaking a continuous weight remains between the peaks.
+#[derive(Debug, Clone)]
+pub struct OverlapDistribution {
+    counts: Vec<usize>,
+    samples: usize,
+}
+
+impl OverlapDistribution {
+    /// # New overlap distribution
+    /// Creates an empty histogram with the given number of bins. An odd number keeps q = 0 in the
+    /// middle of a bin. Fails if there are no bins.
+    pub fn new(number_of_bins: usize) -> Result<Self> {
+        if number_of_bins == 0 {
+            return Err(Error::InvalidParameter {
+                name: "number of bins",
+                value: 0.0,
+            });
+        }
+        Ok(Self {
+            counts: vec![0; number_of_bins],
+            samples: 0,
+        })
+    }
+
+    /// # Add a sample
+    /// Adds a measured overlap.
+    pub fn add(&mut self, overlap: f64) {
+        let number_of_bins = self.counts.len();
+        let bin = ((overlap + 1.0) / 2.0 * number_of_bins as f64) as usize;
+        self.counts[bin.min(number_of_bins - 1)] += 1;
+        self.samples += 1;
+    }
+
+    /// # Number of samples
+    /// The number of overlaps added so far.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// # Density
+    /// The centre of each bin and the estimated probability density P(q) there, normalized so that
+    /// it integrates to one over [-1, 1].
+    pub fn density(&self) -> Vec<(f64, f64)> {
+        let bin_width = 2.0 / self.counts.len() as f64;
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(bin, &count)| {
+                let center = -1.0 + (bin as f64 + 0.5) * bin_width;
+                (center, count as f64 / (self.samples as f64 * bin_width))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::spin::Spin;
+
+    #[test]
+    fn test_plus_minus_couplings() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let couplings = plus_minus_couplings(100, 100, 0.3, &mut rng).unwrap();
+        let mut negative = 0;
+        for y in 0..100 {
+            for x in 0..100 {
+                for direction in [BondDirection::Horizontal, BondDirection::Vertical] {
+                    if couplings.get(x, y, direction) < 0.0 {
+                        negative += 1;
+                    }
+                }
+            }
+        }
+        assert!((negative as f64 / 20_000.0 - 0.3).abs() < 0.02);
+
+        // The same seed gives the same disorder realization.
+        let again = plus_minus_couplings(100, 100, 0.3, &mut StdRng::seed_from_u64(7)).unwrap();
+        assert_eq!(couplings, again);
+        assert!(plus_minus_couplings(100, 100, 1.5, &mut rng).is_err());
+    }
+
+    #[test]
+    fn test_frustrated_plaquettes() {
+        let mut couplings = BondCouplings::uniform(4, 4, 1.0);
+        assert_eq!(frustrated_plaquettes(&couplings), 0);
+
+        // A single antiferromagnetic bond frustrates the two plaquettes it borders.
+        couplings.set(1, 1, BondDirection::Horizontal, -1.0);
+        assert_eq!(frustrated_plaquettes(&couplings), 2);
+
+        // A Mattis model, J_ij = ε_i ε_j, is a gauge-transformed ferromagnet with no frustration.
+        let mut rng = StdRng::seed_from_u64(3);
+        let signs: Vec<f64> = (0..16)
+            .map(|_| if rng.gen_bool(0.5) { 1.0 } else { -1.0 })
+            .collect();
+        let sign = |x: usize, y: usize| signs[(y % 4) * 4 + x % 4];
+        let mattis = BondCouplings::from_fn(4, 4, |x, y, direction| match direction {
+            BondDirection::Horizontal => sign(x, y) * sign(x + 1, y),
+            BondDirection::Vertical => sign(x, y) * sign(x, y + 1),
+        });
+        assert_eq!(frustrated_plaquettes(&mattis), 0);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let a = Grid::new_random(8, 8).unwrap();
+        let mut b = Grid::new_constant(8, 8, Spin::Up).unwrap();
+        for y in 0..8 {
+            for x in 0..8 {
+                b.set(x, y, a.get(x, y).flip());
+            }
+        }
+        assert_eq!(overlap(&a, &a), 1.0);
+        assert_eq!(overlap(&a, &b), -1.0);
+
+        // Flipping every spin leaves every bond as it was.
+        assert_eq!(link_overlap(&a, &b), 1.0);
+    }
+
+    #[test]
+    fn test_binder_ratio() {
+        // An overlap of ±q with equal weight is a perfectly ordered phase.
+        let mut statistics = OverlapStatistics::new();
+        for sample in 0..100 {
+            statistics.add(if sample % 2 == 0 { 0.8 } else { -0.8 });
+        }
+        assert!((statistics.binder_ratio() - 1.0).abs() < 1e-12);
+        assert!((statistics.mean_absolute() - 0.8).abs() < 1e-12);
+        assert!((statistics.susceptibility(10) - 6.4).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_overlap_distribution() {
+        let mut distribution = OverlapDistribution::new(5).unwrap();
+        assert!(OverlapDistribution::new(0).is_err());
+        for overlap in [-1.0, -0.5, 0.0, 0.1, 1.0] {
+            distribution.add(overlap);
+        }
+        let density = distribution.density();
+        assert_eq!(density.len(), 5);
+        assert!((density[2].0).abs() < 1e-12);
+        assert!((density[2].1 - 2.0 / (5.0 * 0.4)).abs() < 1e-12);
+        let total: f64 = density.iter().map(|(_, value)| value * 0.4).sum();
+        assert!((total - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_replica_overlaps() {
+        // Without antiferromagnetic bonds the replicas order along one of two directions each, so
+        // P(q) splits into peaks at ±1 in the cold phase and collapses to zero in the hot phase.
+        let mut rng = StdRng::seed_from_u64(5);
+        let couplings = plus_minus_couplings(12, 12, 0.0, &mut rng).unwrap();
+        let mut results = Vec::new();
+        for coupling in [0.15, 1.0] {
+            let temperature = Temperature::from_beta(coupling).unwrap();
+            let mut statistics = OverlapStatistics::new();
+            let mut distribution = OverlapDistribution::new(11).unwrap();
+            for _ in 0..10 {
+                let mut replicas = ReplicaPair::new(&couplings).unwrap();
+                for sweep in 0..200 {
+                    replicas.step(temperature, 0.0);
+                    if sweep >= 100 {
+                        statistics.add(replicas.overlap());
+                        distribution.add(replicas.overlap());
+                    }
+                }
+            }
+            let density = distribution.density();
+            results.push((
+                statistics.binder_ratio(),
+                density[5].1,
+                density[10].1 + density[0].1,
+            ));
+        }
+        let (hot, cold) = (results[0], results[1]);
+        assert!(hot.0 < 0.3 && cold.0 > 0.9);
+        assert!(hot.1 > cold.1);
+        assert!(cold.2 > 4.0);
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{check_temperature, Error, Result};
+
+/// The Boltzmann constant k_B in eV/K.
+pub const BOLTZMANN_CONSTANT: f64 = 8.617_333_262e-5;
+
+/// # Temperature
+/// A positive, finite temperature in the reduced units of the simulations, T = k_BT/J, so that
+/// the critical temperature of the square lattice is 2/ln(1 + √2) ≈ 2.269. The grids themselves
+/// only see the inverse temperature β = J/k_BT, the reduced coupling K of `Grid::step`, which
+/// the temperature converts to with `beta`. It can also be given in kelvin for a material whose
+/// coupling J is known in eV. It is stored as the reduced temperature, also when serialized,
+/// and checked when deserialized.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+#[cfg_attr(feature = "web", wasm_bindgen::prelude::wasm_bindgen)]
+pub struct Temperature(f64);
+
+impl Temperature {
+    /// # Reduced temperature
+    /// The temperature T = k_BT/J in units of the coupling. Fails unless it is positive and
+    /// finite.
+    pub fn new(temperature: f64) -> Result<Self> {
+        check_temperature(temperature)?;
+        Ok(Self(temperature))
+    }
+
+    /// # From the inverse temperature
+    /// The temperature 1/β of the inverse temperature β = J/k_BT, which is the reduced coupling
+    /// K. Fails unless it is positive and finite.
+    pub fn from_beta(beta: f64) -> Result<Self> {
+        if !(beta > 0.0 && beta.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "inverse temperature",
+                value: beta,
+            });
+        }
+        Self::new(1.0 / beta)
+    }
+
+    /// # From kelvin
+    /// The temperature k_BT/J of a material at the given temperature in kelvin, whose coupling J
+    /// is given in eV. Fails unless both are positive and finite.
+    pub fn from_kelvin(kelvin: f64, coupling: f64) -> Result<Self> {
+        check_temperature(kelvin)?;
+        if !(coupling > 0.0 && coupling.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "coupling",
+                value: coupling,
+            });
+        }
+        Self::new(BOLTZMANN_CONSTANT * kelvin / coupling)
+    }
+
+    /// # Value
+    /// The reduced temperature k_BT/J.
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    /// # Inverse temperature
+    /// β = J/k_BT, the reduced coupling K.
+    pub fn beta(&self) -> f64 {
+        1.0 / self.0
+    }
+
+    /// # In kelvin
+    /// The temperature in kelvin for a coupling J in eV.
+    pub fn kelvin(&self, coupling: f64) -> f64 {
+        self.0 * coupling / BOLTZMANN_CONSTANT
+    }
+}
+
+impl TryFrom<f64> for Temperature {
+    type Error = Error;
+
+    fn try_from(temperature: f64) -> Result<Self> {
+        Self::new(temperature)
+    }
+}
+
+impl From<Temperature> for f64 {
+    fn from(temperature: Temperature) -> Self {
+        temperature.0
+    }
+}
+
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for Temperature {
+    type Err = String;
+
+    /// Parses a reduced temperature such as `2.269`, or an inverse temperature such as
+    /// `beta=0.4407` or `β=0.4407`.
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        let inverse = text
+            .strip_prefix("beta=")
+            .or_else(|| text.strip_prefix("β="));
+        let number = inverse.unwrap_or(text);
+        let value: f64 = number
+            .trim()
+            .parse()
+            .map_err(|error| format!("{}: {}", number, error))?;
+        match inverse {
+            Some(_) => Self::from_beta(value),
+            None => Self::new(value),
+        }
+        .map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conventions() {
+        let critical = Temperature::new(2.269).unwrap();
+        assert_eq!(critical.value(), 2.269);
+        assert_eq!(critical.beta(), 1.0 / 2.269);
+        assert_eq!(Temperature::from_beta(0.5).unwrap().value(), 2.0);
+        assert!(matches!(
+            Temperature::new(-1.0),
+            Err(Error::InvalidTemperature(t)) if t == -1.0
+        ));
+        assert!(Temperature::new(f64::INFINITY).is_err());
+        assert!(Temperature::from_beta(0.0).is_err());
+
+        // Iron has J ≈ 0.0137 eV per bond, which puts 300 K at k_BT/J ≈ 1.89.
+        let room = Temperature::from_kelvin(300.0, 0.0137).unwrap();
+        assert!((room.value() - 1.887).abs() < 1e-3);
+        assert!((room.kelvin(0.0137) - 300.0).abs() < 1e-9);
+        assert!(Temperature::from_kelvin(300.0, -0.01).is_err());
+        assert!(Temperature::from_kelvin(0.0, 0.01).is_err());
+    }
+
+    #[test]
+    fn test_parse_and_serialize() {
+        assert_eq!("2.5".parse(), Ok(Temperature::new(2.5).unwrap()));
+        assert_eq!("beta=0.5".parse(), Ok(Temperature::new(2.0).unwrap()));
+        assert_eq!("β=0.25".parse(), Ok(Temperature::new(4.0).unwrap()));
+        assert!("-2".parse::<Temperature>().is_err());
+        assert!("beta=hot".parse::<Temperature>().is_err());
+
+        let temperature = Temperature::new(1.5).unwrap();
+        assert_eq!(serde_json::to_string(&temperature).unwrap(), "1.5");
+        assert_eq!(
+            serde_json::from_str::<Temperature>("1.5").unwrap(),
+            temperature
+        );
+        assert!(serde_json::from_str::<Temperature>("-1.5").is_err());
+        assert_eq!(temperature.to_string(), "1.5");
+    }
+}
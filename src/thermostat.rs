@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+
+use crate::couplings::BondDirection;
+use crate::temperature::Temperature;
+
+/// # Thermostat map
+/// The temperature of the heat bath that each site of a periodic width × height grid is coupled
+/// to, relative to the temperature implied by the coupling passed to `Grid::step`. A site at
+/// relative temperature τ accepts a move with probability min(1, exp(-ΔE/τ)), so different regions
+/// can be held at different temperatures and the grid settles into a nonequilibrium steady state
+/// with heat flowing from the hot baths to the cold ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThermostatMap {
+    width: usize,
+    height: usize,
+    temperatures: Vec<f64>,
+}
+
+impl ThermostatMap {
+    /// # Uniform thermostat map
+    /// Couples every site to the same bath.
+    pub fn uniform(width: usize, height: usize, temperature: Temperature) -> Self {
+        Self::from_fn(width, height, |_, _| temperature)
+    }
+
+    /// # Thermostat map from a function
+    /// Couples the site at (x, y) to a bath at relative temperature `f(x, y)`.
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        mut f: impl FnMut(usize, usize) -> Temperature,
+    ) -> Self {
+        let temperatures: Vec<f64> = (0..width * height)
+            .map(|index| f(index % width, index / width).value())
+            .collect();
+        Self {
+            width,
+            height,
+            temperatures,
+        }
+    }
+
+    /// # Two halves
+    /// Couples the left half of the grid, x < width / 2, to one bath and the right half to
+    /// another. On a periodic grid there are two interfaces between the halves, so heat flows
+    /// through both.
+    pub fn halves(width: usize, height: usize, left: Temperature, right: Temperature) -> Self {
+        Self::from_fn(
+            width,
+            height,
+            |x, _| if x < width / 2 { left } else { right },
+        )
+    }
+
+    /// # Sublattices
+    /// Couples the two sublattices of the checkerboard, x + y even and odd, to different baths.
+    /// Every bond then joins a hot and a cold site.
+    pub fn sublattices(width: usize, height: usize, even: Temperature, odd: Temperature) -> Self {
+        Self::from_fn(
+            width,
+            height,
+            |x, y| if (x + y) % 2 == 0 { even } else { odd },
+        )
+    }
+
+    /// # Linear profile
+    /// Changes the temperature linearly along x, from `first` in the first column to `last` in
+    /// the last one. With open boundaries along x this imposes a uniform temperature gradient.
+    pub fn linear_profile(
+        width: usize,
+        height: usize,
+        first: Temperature,
+        last: Temperature,
+    ) -> Self {
+        let span = (width.max(2) - 1) as f64;
+        let (first, last) = (first.value(), last.value());
+        Self::from_fn(width, height, |x, _| {
+            Temperature::new(first + (last - first) * x as f64 / span)
+                .expect("the profile lies between two temperatures")
+        })
+    }
+
+    /// # Width
+    /// The number of columns of the map.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows of the map.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// # Get the temperature
+    /// The relative temperature at the given coordinates, with periodic boundary conditions.
+    pub fn get(&self, x: i64, y: i64) -> f64 {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        self.temperatures[y * self.width + x]
+    }
+}
+
+/// # Energy current
+/// Records how energy moves through a grid during its updates. When a spin changes, the bath at
+/// its site supplies the energy change ΔE, and every bond energy e_ij changes by some δe_ij. Half
+/// of each bond's energy belongs to either end, so the change carries an energy current δe_ij / 2
+/// from the updated site to its neighbour. Summed over many sweeps, the bond currents give the
+/// local energy current, and their sum across a cut gives the total current through it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnergyCurrent {
+    width: usize,
+    height: usize,
+    sweeps: usize,
+    heat: Vec<f64>,
+    horizontal: Vec<f64>,
+    vertical: Vec<f64>,
+}
+
+impl EnergyCurrent {
+    /// # New energy current
+    /// Creates an empty record for a width × height grid.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            sweeps: 0,
+            heat: vec![0.0; width * height],
+            horizontal: vec![0.0; width * height],
+            vertical: vec![0.0; width * height],
+        }
+    }
+
+    /// # Width
+    /// The number of columns of the grid.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Height
+    /// The number of rows of the grid.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    fn index(&self, x: i64, y: i64) -> usize {
+        let x = x.rem_euclid(self.width as i64) as usize;
+        let y = y.rem_euclid(self.height as i64) as usize;
+        y * self.width + x
+    }
+
+    /// # Add an update
+    /// Records an accepted update of the site at (x, y) that took `heat` from its bath and
+    /// changed the energies of its bonds to the neighbours at y + 1, y - 1, x - 1 and x + 1 by
+    /// the given amounts.
+    pub fn add_update(&mut self, x: i64, y: i64, heat: f64, bond_changes: [f64; 4]) {
+        let [upper, lower, left, right] = bond_changes.map(|change| 0.5 * change);
+        let index = self.index(x, y);
+        self.heat[index] += heat;
+        self.vertical[index] += upper;
+        self.horizontal[index] += right;
+        let below = self.index(x, y - 1);
+        self.vertical[below] -= lower;
+        let before = self.index(x - 1, y);
+        self.horizontal[before] -= left;
+    }
+
+    /// # Add a sweep
+    /// Marks the end of a sweep, so that the currents can be given per sweep.
+    pub fn add_sweep(&mut self) {
+        self.sweeps += 1;
+    }
+
+    /// # Number of sweeps
+    /// The number of sweeps recorded so far.
+    pub fn sweeps(&self) -> usize {
+        self.sweeps
+    }
+
+    /// # Heat absorbed
+    /// The energy per sweep that the bath of the given site has put into the grid, which is
+    /// negative for a bath that takes energy out.
+    pub fn heat_absorbed(&self, x: i64, y: i64) -> f64 {
+        self.heat[self.index(x, y)] / self.sweeps as f64
+    }
+
+    /// # Total heat
+    /// The energy per sweep that all the baths together have put into the grid. In a steady
+    /// state it vanishes on average.
+    pub fn total_heat(&self) -> f64 {
+        self.heat.iter().sum::<f64>() / self.sweeps as f64
+    }
+
+    /// # Bond current
+    /// The energy per sweep that has flowed from the site at (x, y) to its neighbour at x + 1 or
+    /// y + 1.
+    pub fn bond_current(&self, x: i64, y: i64, direction: BondDirection) -> f64 {
+        let index = self.index(x, y);
+        let total = match direction {
+            BondDirection::Horizontal => self.horizontal[index],
+            BondDirection::Vertical => self.vertical[index],
+        };
+        total / self.sweeps as f64
+    }
+
+    /// # Current across a column
+    /// The total energy per sweep that has flowed from column x to column x + 1.
+    pub fn current_across_column(&self, x: i64) -> f64 {
+        (0..self.height as i64)
+            .map(|y| self.bond_current(x, y, BondDirection::Horizontal))
+            .sum()
+    }
+
+    /// # Current across a row
+    /// The total energy per sweep that has flowed from row y to row y + 1.
+    pub fn current_across_row(&self, y: i64) -> f64 {
+        (0..self.width as i64)
+            .map(|x| self.bond_current(x, y, BondDirection::Vertical))
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profiles() {
+        let temperature = |value: f64| Temperature::new(value).unwrap();
+        let halves = ThermostatMap::halves(6, 2, temperature(0.5), temperature(2.0));
+        assert_eq!(halves.get(2, 0), 0.5);
+        assert_eq!(halves.get(3, 1), 2.0);
+        assert_eq!(halves.get(-1, 0), 2.0);
+
+        let sublattices = ThermostatMap::sublattices(4, 4, temperature(1.0), temperature(3.0));
+        assert_eq!(sublattices.get(1, 1), 1.0);
+        assert_eq!(sublattices.get(1, 2), 3.0);
+
+        let profile = ThermostatMap::linear_profile(5, 1, temperature(1.0), temperature(3.0));
+        assert_eq!(profile.get(0, 0), 1.0);
+        assert_eq!(profile.get(2, 0), 2.0);
+        assert_eq!(profile.get(4, 0), 3.0);
+    }
+}
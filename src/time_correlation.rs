@@ -0,0 +1,317 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use crate::error::{check_same_size, Result};
+use crate::grid::Grid;
+
+/// # Ring buffer
+/// The most recent items pushed into it, up to a fixed capacity, after which every new item
+/// replaces the oldest one. Its memory does not grow with the length of a run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RingBuffer<T> {
+    items: VecDeque<T>,
+    capacity: usize,
+}
+
+impl<T> RingBuffer<T> {
+    /// # New ring buffer
+    /// An empty buffer that keeps the last `capacity` items.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// # Capacity
+    /// The number of items the buffer keeps.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// # Number of items
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// # Is empty
+    /// Whether nothing has been pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// # Push
+    /// Adds an item, returning the oldest one if the buffer was full.
+    pub fn push(&mut self, item: T) -> Option<T> {
+        if self.capacity == 0 {
+            return Some(item);
+        }
+        let oldest = if self.items.len() == self.capacity {
+            self.items.pop_back()
+        } else {
+            None
+        };
+        self.items.push_front(item);
+        oldest
+    }
+
+    /// # Get
+    /// The item pushed `age` pushes ago, so that 0 is the newest.
+    pub fn get(&self, age: usize) -> Option<&T> {
+        self.items.get(age)
+    }
+
+    /// # Iterate
+    /// The items from the newest to the oldest.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.items.iter()
+    }
+}
+
+/// # Stored representation
+/// What `TimeCorrelations` keeps of each configuration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Representation {
+    /// Every spin, for the autocorrelation of the spins at each site.
+    Configurations,
+    /// The Fourier components ŝ(k) = Σ_r s_r exp(-i k·r) at the wave vectors
+    /// k = 2π (mx / width, my / height) of the given (mx, my), for the intermediate scattering
+    /// function. A few long-wavelength modes hold the slow dynamics in far less memory than the
+    /// configurations.
+    FourierModes(Vec<(usize, usize)>),
+}
+
+/// # Time-displaced correlations
+/// The correlations between the configurations of a run at time lags up to a maximum, computed
+/// on the fly as the configurations are pushed. The last configurations, or their Fourier modes,
+/// are kept in a ring buffer, so the memory is fixed however long the run. For the
+/// configurations the correlation at lag t is (1/N) Σ_i ⟨s_i(t₀) s_i(t₀ + t)⟩, and for the
+/// modes it is the mean over the modes of Re⟨ŝ(k, t₀) ŝ*(k, t₀ + t)⟩/N. Both are averaged over
+/// every pair of pushed configurations t apart, with lags in units of the pushes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeCorrelations {
+    width: usize,
+    height: usize,
+    representation: Representation,
+    history: RingBuffer<Vec<f64>>,
+    products: Vec<f64>,
+    pairs: Vec<usize>,
+    sum: Vec<f64>,
+    pushes: usize,
+}
+
+impl TimeCorrelations {
+    /// # New time correlations
+    /// Correlations of width × height configurations at lags up to `max_lag`.
+    pub fn new(
+        width: usize,
+        height: usize,
+        max_lag: usize,
+        representation: Representation,
+    ) -> Self {
+        let components = match &representation {
+            Representation::Configurations => width * height,
+            Representation::FourierModes(modes) => 2 * modes.len(),
+        };
+        Self {
+            width,
+            height,
+            representation,
+            history: RingBuffer::new(max_lag),
+            products: vec![0.0; max_lag + 1],
+            pairs: vec![0; max_lag + 1],
+            sum: vec![0.0; components],
+            pushes: 0,
+        }
+    }
+
+    /// # Maximum lag
+    pub fn max_lag(&self) -> usize {
+        self.products.len() - 1
+    }
+
+    /// # Number of configurations
+    /// The number of configurations pushed so far.
+    pub fn len(&self) -> usize {
+        self.pushes
+    }
+
+    /// # Is empty
+    /// Whether no configuration has been pushed yet.
+    pub fn is_empty(&self) -> bool {
+        self.pushes == 0
+    }
+
+    /// # Push a configuration
+    /// Correlates a configuration with the ones kept from before and keeps it in their place.
+    /// Fails if it does not have the size of the correlations.
+    pub fn push(&mut self, grid: &Grid) -> Result<()> {
+        check_same_size(
+            "configuration",
+            (self.width, self.height),
+            (grid.width(), grid.height()),
+        )?;
+        let components = self.components(grid);
+        let dot = |other: &[f64]| -> f64 {
+            components
+                .iter()
+                .zip(other)
+                .map(|(first, second)| first * second)
+                .sum()
+        };
+        self.products[0] += dot(&components);
+        self.pairs[0] += 1;
+        for (age, earlier) in self.history.iter().enumerate() {
+            self.products[age + 1] += dot(earlier);
+            self.pairs[age + 1] += 1;
+        }
+        for (sum, component) in self.sum.iter_mut().zip(&components) {
+            *sum += component;
+        }
+        self.history.push(components);
+        self.pushes += 1;
+        Ok(())
+    }
+
+    /// The spins, or the real and imaginary parts of the Fourier modes, of a configuration.
+    fn components(&self, grid: &Grid) -> Vec<f64> {
+        match &self.representation {
+            Representation::Configurations => {
+                grid.iter_sites().map(|(_, spin)| spin.value()).collect()
+            }
+            Representation::FourierModes(modes) => modes
+                .iter()
+                .flat_map(|&(mx, my)| {
+                    let (kx, ky) = (
+                        2.0 * PI * mx as f64 / self.width as f64,
+                        2.0 * PI * my as f64 / self.height as f64,
+                    );
+                    let (mut real, mut imaginary) = (0.0, 0.0);
+                    for ((x, y), spin) in grid.iter_sites() {
+                        let phase = kx * x as f64 + ky * y as f64;
+                        real += spin.value() * phase.cos();
+                        imaginary -= spin.value() * phase.sin();
+                    }
+                    [real, imaginary]
+                })
+                .collect(),
+        }
+    }
+
+    /// The normalization of the products: the sites, times the modes for Fourier modes.
+    fn normalization(&self) -> f64 {
+        let sites = (self.width * self.height) as f64;
+        match &self.representation {
+            Representation::Configurations => sites,
+            Representation::FourierModes(modes) => sites * modes.len() as f64,
+        }
+    }
+
+    /// # Correlation
+    /// The correlation at the given lag, which is NaN before any pair of configurations that far
+    /// apart has been pushed.
+    pub fn correlation(&self, lag: usize) -> f64 {
+        self.products[lag] / self.pairs[lag] as f64 / self.normalization()
+    }
+
+    /// # Connected correlation
+    /// The correlation at the given lag less its value for uncorrelated configurations, the
+    /// square of the mean of the stored components, so that it decays to zero.
+    pub fn connected_correlation(&self, lag: usize) -> f64 {
+        let mean_squared = self
+            .sum
+            .iter()
+            .map(|sum| (sum / self.pushes as f64).powi(2))
+            .sum::<f64>();
+        self.correlation(lag) - mean_squared / self.normalization()
+    }
+
+    /// # Autocorrelation function
+    /// The connected correlations at every lag up to the maximum, divided by the one at lag 0,
+    /// which starts at 1 and decays with the relaxation of the dynamics.
+    pub fn autocorrelation(&self) -> Vec<f64> {
+        let variance = self.connected_correlation(0);
+        (0..=self.max_lag())
+            .map(|lag| self.connected_correlation(lag) / variance)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+    use crate::error::Error;
+    use crate::spin::Spin;
+    use crate::temperature::Temperature;
+
+    #[test]
+    fn test_ring_buffer() {
+        let mut buffer = RingBuffer::new(3);
+        assert!(buffer.is_empty());
+        for item in 0..3 {
+            assert_eq!(buffer.push(item), None);
+        }
+        assert_eq!(buffer.push(3), Some(0));
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.get(0), Some(&3));
+        assert_eq!(buffer.get(2), Some(&1));
+        assert_eq!(buffer.get(3), None);
+        assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), [3, 2, 1]);
+        assert_eq!(RingBuffer::new(0).push(7), Some(7));
+    }
+
+    #[test]
+    fn test_frozen_and_alternating() {
+        let mut up = Grid::new_constant(4, 4, Spin::Up).unwrap();
+        let mut correlations = TimeCorrelations::new(4, 4, 3, Representation::Configurations);
+        let modes = Representation::FourierModes(vec![(0, 0), (2, 2)]);
+        let mut fourier = TimeCorrelations::new(4, 4, 3, modes);
+        for _ in 0..6 {
+            for grid in [&Grid::new_checkerboard(4, 4).unwrap(), &up] {
+                correlations.push(grid).unwrap();
+                fourier.push(grid).unwrap();
+            }
+        }
+        assert_eq!(correlations.len(), 12);
+        // The spins are the same at even lags, and agree on half the sites at odd ones.
+        assert_eq!(correlations.correlation(0), 1.0);
+        assert_eq!(correlations.correlation(1), 0.0);
+        assert_eq!(correlations.correlation(2), 1.0);
+        // The mean configuration has every other spin 0 and the rest 1.
+        assert_eq!(correlations.connected_correlation(2), 0.5);
+        assert_eq!(correlations.autocorrelation(), [1.0, -1.0, 1.0, -1.0]);
+
+        // The uniform configuration is all in k = 0 and the checkerboard all in k = (π, π), each
+        // with |ŝ(k)|² = N², so every lag averages to N/2 over the two modes.
+        assert!((fourier.correlation(0) - 8.0).abs() < 1e-9);
+        assert!(fourier.correlation(1).abs() < 1e-9);
+
+        up.set(0, 0, Spin::Down);
+        let mut correlations = TimeCorrelations::new(4, 4, 2, Representation::Configurations);
+        correlations.push(&up).unwrap();
+        assert!(correlations.correlation(1).is_nan());
+        assert!(matches!(
+            correlations.push(&Grid::new_checkerboard(4, 2).unwrap()),
+            Err(Error::SizeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decay() {
+        // The spins at a high temperature forget their past within a few sweeps.
+        let mut grid = Grid::new_checkerboard(16, 16).unwrap();
+        let mut rng = ChaCha8Rng::seed_from_u64(4);
+        let mut correlations = TimeCorrelations::new(16, 16, 10, Representation::Configurations);
+        for _ in 0..400 {
+            grid.step_with_rng(Temperature::from_beta(0.35).unwrap(), 0.0, &mut rng);
+            correlations.push(&grid).unwrap();
+        }
+        let autocorrelation = correlations.autocorrelation();
+        assert_eq!(autocorrelation[0], 1.0);
+        assert!(autocorrelation[1] > 0.05 && autocorrelation[1] < 0.9);
+        assert!(autocorrelation[10].abs() < 0.05);
+    }
+}
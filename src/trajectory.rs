@@ -0,0 +1,659 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
+
+use crate::grid::Grid;
+use crate::provenance::Provenance;
+use crate::spin::Spin;
+
+/// The bytes at the start of every trajectory file.
+const MAGIC: &[u8; 4] = b"ISTR";
+
+/// The bytes at the very end of a trajectory file whose index has been written.
+const INDEX_MAGIC: &[u8; 4] = b"ISTI";
+
+/// The format version written after the magic bytes.
+const VERSION: u8 = 2;
+
+/// The format version of the files written before the header held the provenance of the run.
+const VERSION_WITHOUT_PROVENANCE: u8 = 1;
+
+/// The length of the file header before the provenance: magic, version, width and height.
+const HEADER_LENGTH: usize = 13;
+
+/// The length of a frame header: sweep, encoding and payload length.
+const FRAME_HEADER_LENGTH: usize = 13;
+
+/// The length of the trailer after the index entries: count, index offset and magic.
+const TRAILER_LENGTH: usize = 20;
+
+/// A frame payload that holds the packed spins as they are.
+const RAW: u8 = 0;
+
+/// A frame payload that holds the packed spins as (run length, byte) pairs.
+const RUN_LENGTH: u8 = 1;
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// # Pack the spins
+/// Stores each spin in two bits, four to a byte.
+fn pack(grid: &Grid) -> Vec<u8> {
+    let number_of_sites = grid.width() * grid.height();
+    let mut packed = vec![0; number_of_sites.div_ceil(4)];
+    for site in 0..number_of_sites {
+        let (x, y) = ((site % grid.width()) as i64, (site / grid.width()) as i64);
+        let code = match grid.get(x, y) {
+            Spin::Up => 0,
+            Spin::Down => 1,
+            Spin::Zero => 2,
+            Spin::Vacant => 3,
+        };
+        packed[site / 4] |= code << (2 * (site % 4));
+    }
+    packed
+}
+
+/// # Unpack the spins
+/// Rebuilds a grid from spins packed by `pack`.
+fn unpack(packed: &[u8], width: usize, height: usize) -> io::Result<Grid> {
+    if packed.len() != (width * height).div_ceil(4) {
+        return Err(invalid("the frame does not match the size of the grid"));
+    }
+    let mut grid = Grid::new_constant(width, height, Spin::Up)?;
+    for site in 0..width * height {
+        let spin = match packed[site / 4] >> (2 * (site % 4)) & 0b11 {
+            0 => Spin::Up,
+            1 => Spin::Down,
+            2 => Spin::Zero,
+            _ => Spin::Vacant,
+        };
+        grid.set((site % width) as i64, (site / width) as i64, spin);
+    }
+    Ok(grid)
+}
+
+/// # Run-length encode
+/// Replaces each run of equal bytes by its length, at most 255, and the byte. Ordered domains pack
+/// into long runs of equal bytes, so this shrinks them by orders of magnitude.
+fn run_length_encode(bytes: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    let mut rest = bytes;
+    while let Some(&byte) = rest.first() {
+        let run = rest
+            .iter()
+            .take(255)
+            .take_while(|&&other| other == byte)
+            .count();
+        encoded.extend([run as u8, byte]);
+        rest = &rest[run..];
+    }
+    encoded
+}
+
+/// # Run-length decode
+/// Reverses `run_length_encode`.
+fn run_length_decode(encoded: &[u8]) -> io::Result<Vec<u8>> {
+    if !encoded.len().is_multiple_of(2) {
+        return Err(invalid("the run-length encoding has an odd length"));
+    }
+    let mut bytes = Vec::new();
+    for pair in encoded.chunks(2) {
+        bytes.extend(std::iter::repeat_n(pair[1], pair[0] as usize));
+    }
+    Ok(bytes)
+}
+
+/// # Decode a frame
+/// Decodes the frame that starts at `offset` in the bytes of a trajectory file, returning its
+/// sweep, its grid and the offset of the next frame. The bytes can come from a file read into
+/// memory or from a memory map.
+pub fn decode_frame(
+    bytes: &[u8],
+    offset: usize,
+    width: usize,
+    height: usize,
+) -> io::Result<(u64, Grid, usize)> {
+    let payload_start = offset
+        .checked_add(FRAME_HEADER_LENGTH)
+        .filter(|&start| start <= bytes.len())
+        .ok_or_else(|| invalid("the frame header is cut off"))?;
+    let sweep = read_u64(bytes, offset);
+    let encoding = bytes[offset + 8];
+    let payload_end = payload_start + read_u32(bytes, offset + 9) as usize;
+    if bytes.len() < payload_end {
+        return Err(invalid("the frame is cut off"));
+    }
+    let payload = &bytes[payload_start..payload_end];
+    let grid = match encoding {
+        RAW => unpack(payload, width, height)?,
+        RUN_LENGTH => unpack(&run_length_decode(payload)?, width, height)?,
+        _ => return Err(invalid("unknown frame encoding")),
+    };
+    Ok((sweep, grid, payload_end))
+}
+
+/// # Trajectory writer
+/// Writes a sequence of configurations of one grid size to a compact file. Each frame stores the
+/// spins in two bits each, and is run-length encoded whenever that makes it smaller, which turns
+/// an ordered 1000 × 1000 configuration into a few kilobytes. Only the spins are stored, not the
+/// couplings, fields or pinned sites, but the header holds the provenance of the run as JSON.
+/// Calling `finish` appends an index of the frames, so that a reader can jump straight to any of
+/// them.
+#[derive(Debug)]
+pub struct TrajectoryWriter {
+    file: BufWriter<File>,
+    width: usize,
+    height: usize,
+    offset: u64,
+    index: Vec<(u64, u64)>,
+}
+
+impl TrajectoryWriter {
+    /// # Create a trajectory
+    /// Creates the file, replacing any existing one, for grids of the given size, and writes the
+    /// provenance of the run into its header.
+    pub fn create(
+        path: impl AsRef<Path>,
+        width: usize,
+        height: usize,
+        provenance: &Provenance,
+    ) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let provenance = provenance.to_json();
+        file.write_all(MAGIC)?;
+        file.write_all(&[VERSION])?;
+        file.write_all(&(width as u32).to_le_bytes())?;
+        file.write_all(&(height as u32).to_le_bytes())?;
+        file.write_all(&(provenance.len() as u32).to_le_bytes())?;
+        file.write_all(provenance.as_bytes())?;
+        Ok(Self {
+            file,
+            width,
+            height,
+            offset: (HEADER_LENGTH + 4 + provenance.len()) as u64,
+            index: Vec::new(),
+        })
+    }
+
+    /// # Append to a trajectory
+    /// Opens the file to add frames after those already in it, as a resumed run does, dropping
+    /// its index and any frame cut off at the end. The provenance in the header is kept, and is
+    /// only written when a missing or empty file is created as by `create`. A file for grids of
+    /// another size is refused.
+    pub fn append(
+        path: impl AsRef<Path>,
+        width: usize,
+        height: usize,
+        provenance: &Provenance,
+    ) -> io::Result<Self> {
+        let path = path.as_ref();
+        if fs::metadata(path).map_or(true, |metadata| metadata.len() == 0) {
+            return Self::create(path, width, height, provenance);
+        }
+        let bytes = fs::read(path)?;
+        let index = TrajectoryIndex::from_bytes(&bytes)?;
+        if index.width != width || index.height != height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the grid does not match the size of the trajectory",
+            ));
+        }
+        let offset = match index.frames.last() {
+            Some(&(_, last)) => decode_frame(&bytes, last, width, height)?.2,
+            None => index.start,
+        };
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(offset as u64)?;
+        let mut file = BufWriter::new(file);
+        file.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            file,
+            width,
+            height,
+            offset: offset as u64,
+            index: index
+                .frames
+                .into_iter()
+                .map(|(sweep, offset)| (sweep, offset as u64))
+                .collect(),
+        })
+    }
+
+    /// # Write a frame
+    /// Appends the configuration of the grid after the given sweep.
+    pub fn write(&mut self, sweep: u64, grid: &Grid) -> io::Result<()> {
+        if grid.width() != self.width || grid.height() != self.height {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the grid does not match the size of the trajectory",
+            ));
+        }
+        let packed = pack(grid);
+        let encoded = run_length_encode(&packed);
+        let (encoding, payload) = if encoded.len() < packed.len() {
+            (RUN_LENGTH, encoded)
+        } else {
+            (RAW, packed)
+        };
+        self.file.write_all(&sweep.to_le_bytes())?;
+        self.file.write_all(&[encoding])?;
+        self.file.write_all(&(payload.len() as u32).to_le_bytes())?;
+        self.file.write_all(&payload)?;
+
+        self.index.push((sweep, self.offset));
+        self.offset += (FRAME_HEADER_LENGTH + payload.len()) as u64;
+        Ok(())
+    }
+
+    /// # Number of frames
+    /// The number of frames written so far.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// # Is empty
+    /// Whether no frame has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// # Flush
+    /// Writes the buffered frames to the file, without the index.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+    /// # Finish
+    /// Writes the index and flushes the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        for (sweep, offset) in &self.index {
+            self.file.write_all(&sweep.to_le_bytes())?;
+            self.file.write_all(&offset.to_le_bytes())?;
+        }
+        self.file
+            .write_all(&(self.index.len() as u64).to_le_bytes())?;
+        self.file.write_all(&self.offset.to_le_bytes())?;
+        self.file.write_all(INDEX_MAGIC)?;
+        self.file.flush()
+    }
+}
+
+/// # Trajectory index
+/// The size of the grids in a trajectory file, the provenance of the run that wrote it, which
+/// files of the first version of the format lack, and the sweep and byte offset of each frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrajectoryIndex {
+    pub width: usize,
+    pub height: usize,
+    pub provenance: Option<Provenance>,
+    pub frames: Vec<(u64, usize)>,
+    /// The byte offset of the first frame, just after the header.
+    start: usize,
+}
+
+impl TrajectoryIndex {
+    /// # Read the index
+    /// Reads the index from the bytes of a trajectory file. A file whose writer never finished,
+    /// for example because the run was interrupted, has no index; its frames are then found by
+    /// walking through them, and a frame cut off at the end is dropped.
+    pub fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        if bytes.len() < HEADER_LENGTH || &bytes[..4] != MAGIC {
+            return Err(invalid("not a trajectory file"));
+        }
+        let width = read_u32(bytes, 5) as usize;
+        let height = read_u32(bytes, 9) as usize;
+        let (provenance, start) = match bytes[4] {
+            VERSION_WITHOUT_PROVENANCE => (None, HEADER_LENGTH),
+            VERSION => {
+                let length = bytes
+                    .get(HEADER_LENGTH..HEADER_LENGTH + 4)
+                    .map(|_| read_u32(bytes, HEADER_LENGTH) as usize)
+                    .ok_or_else(|| invalid("the trajectory header is cut off"))?;
+                let start = HEADER_LENGTH + 4 + length;
+                let json = bytes
+                    .get(HEADER_LENGTH + 4..start)
+                    .ok_or_else(|| invalid("the trajectory header is cut off"))?;
+                let provenance = serde_json::from_slice(json)
+                    .map_err(|error| invalid(&format!("invalid provenance: {}", error)))?;
+                (Some(provenance), start)
+            }
+            _ => return Err(invalid("unsupported trajectory version")),
+        };
+
+        let length = bytes.len();
+        if length >= start + TRAILER_LENGTH && &bytes[length - 4..] == INDEX_MAGIC {
+            // A corrupt trailer can hold any numbers, so the index is only trusted when it fits the
+            // file exactly, and the frames are walked through otherwise.
+            let count = read_u64(bytes, length - TRAILER_LENGTH) as usize;
+            let index_start = read_u64(bytes, length - TRAILER_LENGTH + 8) as usize;
+            let index_end = count
+                .checked_mul(16)
+                .and_then(|entries| entries.checked_add(index_start))
+                .and_then(|end| end.checked_add(TRAILER_LENGTH));
+            if index_end == Some(length) {
+                let frames = (0..count)
+                    .map(|frame| {
+                        let entry = index_start + 16 * frame;
+                        (read_u64(bytes, entry), read_u64(bytes, entry + 8) as usize)
+                    })
+                    .collect();
+                return Ok(Self {
+                    width,
+                    height,
+                    provenance,
+                    frames,
+                    start,
+                });
+            }
+        }
+
+        let mut frames = Vec::new();
+        let mut offset = start;
+        while let Ok((sweep, _, next)) = decode_frame(bytes, offset, width, height) {
+            frames.push((sweep, offset));
+            offset = next;
+        }
+        Ok(Self {
+            width,
+            height,
+            provenance,
+            frames,
+            start,
+        })
+    }
+
+    /// # Offset of a frame
+    /// The byte offset of frame number `frame`, failing when the trajectory has no such frame.
+    pub fn offset(&self, frame: usize) -> io::Result<usize> {
+        self.frames
+            .get(frame)
+            .map(|&(_, offset)| offset)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "frame {} is out of range for a trajectory of {} frames",
+                        frame,
+                        self.frames.len()
+                    ),
+                )
+            })
+    }
+}
+
+/// # Trajectory reader
+/// Reads a trajectory file written by `TrajectoryWriter` into memory and decodes its frames on
+/// demand.
+#[derive(Debug, Clone)]
+pub struct TrajectoryReader {
+    bytes: Vec<u8>,
+    index: TrajectoryIndex,
+}
+
+impl TrajectoryReader {
+    /// # Open a trajectory
+    /// Reads the file and its index.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let index = TrajectoryIndex::from_bytes(&bytes)?;
+        Ok(Self { bytes, index })
+    }
+
+    /// # Index
+    /// The grid size and the frames of the trajectory.
+    pub fn index(&self) -> &TrajectoryIndex {
+        &self.index
+    }
+
+    /// # Number of frames
+    /// The number of configurations in the trajectory.
+    pub fn len(&self) -> usize {
+        self.index.frames.len()
+    }
+
+    /// # Is empty
+    /// Whether the trajectory holds no configurations.
+    pub fn is_empty(&self) -> bool {
+        self.index.frames.is_empty()
+    }
+
+    /// # Read a frame
+    /// Decodes frame number `frame`, returning its sweep and configuration, or fails when there is
+    /// no such frame.
+    pub fn read(&self, frame: usize) -> io::Result<(u64, Grid)> {
+        let offset = self.index.offset(frame)?;
+        let (sweep, grid, _) =
+            decode_frame(&self.bytes, offset, self.index.width, self.index.height)?;
+        Ok((sweep, grid))
+    }
+}
+
+/// # Mapped trajectory reader
+/// Reads a trajectory file through a memory map instead of loading it, so that trajectories much
+/// larger than the available memory can be analysed. Only the index is read up front, and the
+/// operating system pages in the bytes of each frame when it is decoded. Memory maps are not
+/// available on wasm32.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+pub struct MappedTrajectoryReader {
+    map: Mmap,
+    index: TrajectoryIndex,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MappedTrajectoryReader {
+    /// # Open a trajectory
+    /// Maps the file and reads its index. The file must not be changed while it is mapped.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::open(path)?;
+        // Safety: the map is only read, and a trajectory is not written to once it is analysed.
+        let map = unsafe { Mmap::map(&file)? };
+        let index = TrajectoryIndex::from_bytes(&map)?;
+        Ok(Self { map, index })
+    }
+
+    /// # Index
+    /// The grid size and the frames of the trajectory.
+    pub fn index(&self) -> &TrajectoryIndex {
+        &self.index
+    }
+
+    /// # Number of frames
+    /// The number of configurations in the trajectory.
+    pub fn len(&self) -> usize {
+        self.index.frames.len()
+    }
+
+    /// # Is empty
+    /// Whether the trajectory holds no configurations.
+    pub fn is_empty(&self) -> bool {
+        self.index.frames.is_empty()
+    }
+
+    /// # Read a frame
+    /// Decodes frame number `frame`, returning its sweep and configuration, or fails when there is
+    /// no such frame.
+    pub fn read(&self, frame: usize) -> io::Result<(u64, Grid)> {
+        let offset = self.index.offset(frame)?;
+        let (sweep, grid, _) =
+            decode_frame(&self.map, offset, self.index.width, self.index.height)?;
+        Ok((sweep, grid))
+    }
+
+    /// # Frames
+    /// Decodes the frames one at a time, in order, so that only one configuration is held in
+    /// memory at once.
+    pub fn frames(&self) -> impl Iterator<Item = io::Result<(u64, Grid)>> + '_ {
+        (0..self.len()).map(|frame| self.read(frame))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn provenance() -> Provenance {
+        let mut provenance =
+            Provenance::new("metropolis-sequential", Some(7)).with_parameter("coupling", 0.44);
+        provenance.created = "2024-02-29T12:00:00Z".to_string();
+        provenance
+    }
+
+    fn same_spins(a: &Grid, b: &Grid) -> bool {
+        (0..a.height() as i64).all(|y| (0..a.width() as i64).all(|x| a.get(x, y) == b.get(x, y)))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = env::temp_dir().join("ising_model_test_trajectory.istr");
+        let mut random = Grid::new_random(37, 11).unwrap();
+        random.set(3, 4, Spin::Zero);
+        random.set(36, 10, Spin::Vacant);
+        let grids = [
+            random,
+            Grid::new_constant(37, 11, Spin::Down).unwrap(),
+            Grid::new_stripes(37, 11, 5).unwrap(),
+        ];
+
+        let mut writer = TrajectoryWriter::create(&path, 37, 11, &provenance()).unwrap();
+        for (frame, grid) in grids.iter().enumerate() {
+            writer.write(10 * frame as u64, grid).unwrap();
+        }
+        assert!(writer.write(30, &Grid::new_random(5, 5).unwrap()).is_err());
+        assert_eq!(writer.len(), 3);
+        writer.finish().unwrap();
+
+        let reader = TrajectoryReader::open(&path).unwrap();
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.index().provenance, Some(provenance()));
+        for (frame, grid) in grids.iter().enumerate() {
+            let (sweep, read) = reader.read(frame).unwrap();
+            assert_eq!(sweep, 10 * frame as u64);
+            assert!(same_spins(&read, grid));
+        }
+
+        // An ordered frame takes a small fraction of its packed size, and the packed size is a
+        // quarter of a byte per spin.
+        let frames = &reader.index().frames;
+        assert_eq!(frames[1].1 - frames[0].1, FRAME_HEADER_LENGTH + 102);
+        assert!(frames[2].1 - frames[1].1 < FRAME_HEADER_LENGTH + 10);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_unfinished_trajectory() {
+        let path = env::temp_dir().join("ising_model_test_unfinished.istr");
+        let mut writer = TrajectoryWriter::create(&path, 8, 8, &provenance()).unwrap();
+        for sweep in 0..4 {
+            writer
+                .write(sweep, &Grid::new_random(8, 8).unwrap())
+                .unwrap();
+        }
+        drop(writer);
+
+        // Cut the last frame in half, as an interrupted write would.
+        let mut bytes = fs::read(&path).unwrap();
+        bytes.truncate(bytes.len() - 5);
+        fs::write(&path, &bytes).unwrap();
+        let reader = TrajectoryReader::open(&path).unwrap();
+        assert_eq!(reader.len(), 3);
+        assert_eq!(reader.read(2).unwrap().0, 2);
+        assert!(reader.read(3).is_err());
+        fs::remove_file(&path).unwrap();
+
+        assert!(TrajectoryIndex::from_bytes(b"not a trajectory").is_err());
+
+        // A trailer with absurd numbers is ignored and the frames are walked through instead.
+        let path = env::temp_dir().join("ising_model_test_corrupt.istr");
+        let mut writer = TrajectoryWriter::create(&path, 8, 8, &provenance()).unwrap();
+        for sweep in 0..2 {
+            writer
+                .write(sweep, &Grid::new_random(8, 8).unwrap())
+                .unwrap();
+        }
+        writer.finish().unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let length = bytes.len();
+        bytes[length - TRAILER_LENGTH..length - 4].fill(0xff);
+        let index = TrajectoryIndex::from_bytes(&bytes).unwrap();
+        assert_eq!(index.frames.len(), 2);
+        assert!(decode_frame(&bytes, usize::MAX, 8, 8).is_err());
+
+        // Files of the first version have no provenance in their header.
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION_WITHOUT_PROVENANCE);
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        bytes.extend_from_slice(&8u32.to_le_bytes());
+        let index = TrajectoryIndex::from_bytes(&bytes).unwrap();
+        assert_eq!(index.provenance, None);
+        assert!(index.frames.is_empty());
+    }
+
+    #[test]
+    fn test_append() {
+        let path = env::temp_dir().join("ising_model_test_append.istr");
+        let grids: Vec<Grid> = (0..4).map(|_| Grid::new_random(9, 7).unwrap()).collect();
+        let mut writer = TrajectoryWriter::create(&path, 9, 7, &provenance()).unwrap();
+        for (sweep, grid) in grids[..2].iter().enumerate() {
+            writer.write(sweep as u64, grid).unwrap();
+        }
+        writer.finish().unwrap();
+
+        assert!(TrajectoryWriter::append(&path, 7, 9, &provenance()).is_err());
+        let mut writer = TrajectoryWriter::append(&path, 9, 7, &provenance()).unwrap();
+        assert_eq!(writer.len(), 2);
+        for (sweep, grid) in grids.iter().enumerate().skip(2) {
+            writer.write(sweep as u64, grid).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = TrajectoryReader::open(&path).unwrap();
+        assert_eq!(reader.len(), 4);
+        assert_eq!(reader.index().provenance, Some(provenance()));
+        for (frame, grid) in grids.iter().enumerate() {
+            let (sweep, read) = reader.read(frame).unwrap();
+            assert_eq!(sweep, frame as u64);
+            assert!(same_spins(&read, grid));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_mapped_reader() {
+        let path = env::temp_dir().join("ising_model_test_mapped.istr");
+        let grids: Vec<Grid> = (0..5).map(|_| Grid::new_random(16, 9).unwrap()).collect();
+        let mut writer = TrajectoryWriter::create(&path, 16, 9, &provenance()).unwrap();
+        for (sweep, grid) in grids.iter().enumerate() {
+            writer.write(100 * sweep as u64, grid).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let mapped = MappedTrajectoryReader::open(&path).unwrap();
+        let loaded = TrajectoryReader::open(&path).unwrap();
+        assert_eq!(mapped.index(), loaded.index());
+        for (frame, result) in mapped.frames().enumerate() {
+            let (sweep, grid) = result.unwrap();
+            assert_eq!(sweep, 100 * frame as u64);
+            assert!(same_spins(&grid, &grids[frame]));
+        }
+        assert_eq!(mapped.frames().count(), 5);
+        assert!(mapped.read(5).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,306 @@
+use crate::error::{check_finite, Error, Result};
+use crate::temperature::Temperature;
+
+/// The default tolerance on the eigenvalue residual.
+const TOLERANCE: f64 = 1e-11;
+
+/// The default maximum number of power iterations per eigenvector.
+const MAX_ITERATIONS: usize = 100_000;
+
+/// # Transfer matrix
+/// The transfer matrix of an L×∞ strip with periodic boundary conditions across the strip, at a
+/// temperature and a field h in units of the coupling. The matrix itself is built from the reduced
+/// coupling K = β and field H = βh of `Grid::step`.
+///
+/// The matrix has 2^L rows, so it is never stored. Instead it is applied in its symmetric
+/// factorized form T = D^(1/2) V D^(1/2), where D is the diagonal weight of the bonds and field
+/// inside a column and V = ⊗ [[e^K, e^-K], [e^-K, e^K]] couples neighbouring columns. Applying V
+/// one spin at a time costs O(L 2^L) instead of O(4^L).
+#[derive(Debug, Clone)]
+pub struct TransferMatrix {
+    width: usize,
+    coupling: f64,
+    half_column_weights: Vec<f64>,
+}
+
+/// # Strip solution
+/// The quantities obtained from the two largest eigenvalues of the transfer matrix. The free
+/// energy is per site and in units of k_BT, and the correlation length is measured in lattice
+/// spacings along the strip.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StripSolution {
+    pub leading_eigenvalue: f64,
+    pub second_eigenvalue: f64,
+    pub free_energy: f64,
+    pub correlation_length: f64,
+    pub magnetization: f64,
+}
+
+impl TransferMatrix {
+    /// # New transfer matrix
+    /// Creates the transfer matrix of a strip of the given width. Fails unless the width is
+    /// between 1 and 24 and the field is finite.
+    pub fn new(width: usize, temperature: Temperature, field: f64) -> Result<Self> {
+        if !(1..=24).contains(&width) {
+            return Err(Error::InvalidParameter {
+                name: "strip width",
+                value: width as f64,
+            });
+        }
+        check_finite("field", field)?;
+        let coupling = temperature.beta();
+        let field = field * coupling;
+
+        let half_column_weights = (0..1usize << width)
+            .map(|state| {
+                let mut exponent = 0.0;
+                for i in 0..width {
+                    let spin = spin_of(state, i);
+                    exponent += coupling * spin * spin_of(state, (i + 1) % width) + field * spin;
+                }
+                (0.5 * exponent).exp()
+            })
+            .collect();
+
+        Ok(Self {
+            width,
+            coupling,
+            half_column_weights,
+        })
+    }
+
+    /// # Width
+    /// The number of spins in a column of the strip.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// # Apply
+    /// Multiplies a vector by the transfer matrix.
+    pub fn apply(&self, vector: &[f64]) -> Vec<f64> {
+        let mut result: Vec<f64> = vector
+            .iter()
+            .zip(&self.half_column_weights)
+            .map(|(value, weight)| value * weight)
+            .collect();
+
+        // Apply the inter-column bond of each spin in turn. Each one mixes the pairs of states
+        // that differ only in that spin.
+        let same = self.coupling.exp();
+        let opposite = (-self.coupling).exp();
+        for i in 0..self.width {
+            let bit = 1 << i;
+            for state in 0..result.len() {
+                if state & bit == 0 {
+                    let up = result[state];
+                    let down = result[state | bit];
+                    result[state] = same * up + opposite * down;
+                    result[state | bit] = opposite * up + same * down;
+                }
+            }
+        }
+
+        for (value, weight) in result.iter_mut().zip(&self.half_column_weights) {
+            *value *= weight;
+        }
+        result
+    }
+
+    /// # Solve
+    /// Finds the two largest eigenvalues by power iteration and derives the free energy, the
+    /// correlation length and the magnetization of the strip.
+    pub fn solve(&self) -> StripSolution {
+        let size = 1usize << self.width;
+
+        // The leading eigenvector is positive, so a uniform start vector overlaps with it.
+        let (leading_eigenvalue, leading_vector) = self.power_iteration(vec![1.0; size], None);
+
+        // The second eigenvector is odd under a global spin flip in zero field, so start from the
+        // column magnetization and keep the iteration orthogonal to the leading eigenvector.
+        let start = (0..size)
+            .map(|state| self.column_magnetization(state))
+            .collect();
+        let (second_eigenvalue, _) = self.power_iteration(start, Some(&leading_vector));
+
+        // With a symmetric transfer matrix the probability of a column state is the square of
+        // the leading eigenvector.
+        let magnetization = leading_vector
+            .iter()
+            .enumerate()
+            .map(|(state, amplitude)| amplitude * amplitude * self.column_magnetization(state))
+            .sum::<f64>()
+            / self.width as f64;
+
+        StripSolution {
+            leading_eigenvalue,
+            second_eigenvalue,
+            free_energy: -leading_eigenvalue.ln() / self.width as f64,
+            correlation_length: 1.0 / (leading_eigenvalue / second_eigenvalue.abs()).ln(),
+            magnetization,
+        }
+    }
+
+    /// # Column magnetization
+    /// The sum of the spins in a column state.
+    fn column_magnetization(&self, state: usize) -> f64 {
+        (0..self.width).map(|i| spin_of(state, i)).sum()
+    }
+
+    /// # Power iteration
+    /// Finds the dominant eigenpair, optionally restricted to the subspace orthogonal to a given
+    /// normalized vector. Returns the eigenvalue and the normalized eigenvector.
+    fn power_iteration(&self, start: Vec<f64>, orthogonal_to: Option<&[f64]>) -> (f64, Vec<f64>) {
+        let mut vector = start;
+        if let Some(other) = orthogonal_to {
+            project_out(&mut vector, other);
+        }
+        normalize(&mut vector);
+
+        let mut eigenvalue = 0.0;
+        for _ in 0..MAX_ITERATIONS {
+            let mut next = self.apply(&vector);
+            if let Some(other) = orthogonal_to {
+                project_out(&mut next, other);
+            }
+
+            // The Rayleigh quotient, and the residual of the eigenvalue equation.
+            eigenvalue = dot(&vector, &next);
+            let residual = next
+                .iter()
+                .zip(&vector)
+                .map(|(a, b)| (a - eigenvalue * b).powi(2))
+                .sum::<f64>()
+                .sqrt();
+
+            vector = next;
+            normalize(&mut vector);
+            if residual <= TOLERANCE * eigenvalue.abs() {
+                break;
+            }
+        }
+        (eigenvalue, vector)
+    }
+}
+
+/// # Phenomenological renormalization
+/// Finds the temperature at which the scaled correlation lengths of two strips coincide,
+/// ξ_a / a = ξ_b / b, by bisection between `lower` and `upper`. This is Nightingale's estimate of
+/// the critical temperature, which converges quickly with the strip widths. Fails for widths that
+/// `TransferMatrix::new` does not accept.
+pub fn phenomenological_temperature(
+    width_a: usize,
+    width_b: usize,
+    lower: Temperature,
+    upper: Temperature,
+) -> Result<Temperature> {
+    TransferMatrix::new(width_a, lower, 0.0)?;
+    TransferMatrix::new(width_b, lower, 0.0)?;
+    let difference = |beta: f64| {
+        let temperature = Temperature::from_beta(beta).expect("the bounds are positive");
+        let solve = |width| {
+            TransferMatrix::new(width, temperature, 0.0)
+                .expect("the widths were checked")
+                .solve()
+        };
+        let (a, b) = (solve(width_a), solve(width_b));
+        a.correlation_length / width_a as f64 - b.correlation_length / width_b as f64
+    };
+
+    // The bisection runs in the inverse temperature, where the strips were solved before.
+    let (mut lower, mut upper) = (lower.beta(), upper.beta());
+    let lower_sign = difference(lower).signum();
+    while (upper - lower).abs() > 1e-9 {
+        let middle = 0.5 * (lower + upper);
+        if difference(middle).signum() == lower_sign {
+            lower = middle;
+        } else {
+            upper = middle;
+        }
+    }
+    Temperature::from_beta(0.5 * (lower + upper))
+}
+
+/// # Spin of a state
+/// The value (plus/minus one) of the i-th spin in a column state.
+fn spin_of(state: usize, i: usize) -> f64 {
+    if state & (1 << i) == 0 {
+        1.0
+    } else {
+        -1.0
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalize(vector: &mut [f64]) {
+    let norm = dot(vector, vector).sqrt();
+    for value in vector.iter_mut() {
+        *value /= norm;
+    }
+}
+
+fn project_out(vector: &mut [f64], direction: &[f64]) {
+    let overlap = dot(vector, direction);
+    for (value, component) in vector.iter_mut().zip(direction) {
+        *value -= overlap * component;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beta(beta: f64) -> Temperature {
+        Temperature::from_beta(beta).unwrap()
+    }
+
+    #[test]
+    fn test_chain() {
+        // A strip of width one is a chain whose single spin is also bonded to itself, which only
+        // adds e^K to the eigenvalues, so λ = e^K (e^K cosh H + √(e^2K sinh² H + e^-2K)) and
+        // m = sinh H / √(sinh² H + e^-4K).
+        let (coupling, field) = (0.6_f64, 0.2_f64);
+        let solution = TransferMatrix::new(1, beta(coupling), field / coupling)
+            .unwrap()
+            .solve();
+        let root = ((2.0 * coupling).exp() * field.sinh().powi(2) + (-2.0 * coupling).exp()).sqrt();
+        let eigenvalue = coupling.exp() * (coupling.exp() * field.cosh() + root);
+        let magnetization = field.sinh() / (field.sinh().powi(2) + (-4.0 * coupling).exp()).sqrt();
+        assert!((solution.leading_eigenvalue - eigenvalue).abs() < 1e-8);
+        assert!((solution.free_energy + eigenvalue.ln()).abs() < 1e-10);
+        assert!((solution.magnetization - magnetization).abs() < 1e-8);
+        assert!(TransferMatrix::new(25, beta(coupling), 0.0).is_err());
+        assert!(TransferMatrix::new(1, beta(coupling), f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_apply_is_symmetric() {
+        let matrix = TransferMatrix::new(3, beta(0.4), 0.25).unwrap();
+        let a: Vec<f64> = (0..8).map(|i| (i as f64).sin()).collect();
+        let b: Vec<f64> = (0..8).map(|i| (i as f64).cos()).collect();
+        assert!((dot(&a, &matrix.apply(&b)) - dot(&b, &matrix.apply(&a))).abs() < 1e-10);
+    }
+
+    #[test]
+    fn test_zero_field_magnetization() {
+        let solution = TransferMatrix::new(5, beta(0.5), 0.0).unwrap().solve();
+        assert!(solution.magnetization.abs() < 1e-8);
+        assert!(solution.correlation_length > 1.0);
+    }
+
+    #[test]
+    fn test_correlation_length_grows_with_coupling() {
+        let weak = TransferMatrix::new(4, beta(0.2), 0.0).unwrap().solve();
+        let strong = TransferMatrix::new(4, beta(0.4), 0.0).unwrap().solve();
+        assert!(strong.correlation_length > weak.correlation_length);
+    }
+
+    #[test]
+    fn test_phenomenological_temperature() {
+        let exact = 2.0 / (1.0 + 2.0_f64.sqrt()).ln();
+        let estimate = phenomenological_temperature(4, 6, beta(0.3), beta(0.6)).unwrap();
+        assert!((estimate.value() - exact).abs() < 0.05);
+    }
+}
@@ -0,0 +1,462 @@
+use rand::Rng;
+use tracing::{debug, debug_span, trace};
+
+use crate::analysis::blocked_mean;
+use crate::error::{check_finite, check_size, Error, Result};
+use crate::measurement::Measurement;
+use crate::qubo::{IsingProblem, Solution};
+use crate::spin::Spin;
+use crate::temperature::Temperature;
+
+/// # Transverse-field Ising model
+/// The quantum Hamiltonian H = E(σᶻ) − Γ Σ σˣ_i, where E is the energy of an `IsingProblem`
+/// evaluated on the σᶻ components and Γ is the transverse field. It is simulated with the
+/// Suzuki–Trotter decomposition, which maps it at inverse temperature β onto a classical problem
+/// of P coupled copies of the spins, the Trotter slices, each seeing the energy E/P and coupled
+/// ferromagnetically to the same spin in the neighbouring slices of a periodic imaginary-time
+/// ring. The mapping is exact as P grows, with an error of order (βΓ/P)².
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransverseFieldIsing {
+    problem: IsingProblem,
+    slices: usize,
+}
+
+/// # Quantum observables
+/// Averages per spin of a path-integral run, with their errors from blocking.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantumObservables {
+    /// ⟨σᶻ⟩.
+    pub magnetization: Measurement,
+    /// ⟨|σᶻ|⟩, the magnitude of the magnetization of a slice.
+    pub absolute_magnetization: Measurement,
+    /// ⟨σˣ⟩.
+    pub transverse_magnetization: Measurement,
+    /// ⟨H⟩.
+    pub energy: Measurement,
+}
+
+impl TransverseFieldIsing {
+    /// # New model
+    /// The problem in a transverse field, simulated with the given number of Trotter slices.
+    /// Fails if there are none.
+    pub fn new(problem: IsingProblem, slices: usize) -> Result<Self> {
+        if slices == 0 {
+            return Err(Error::InvalidParameter {
+                name: "trotter slices",
+                value: 0.0,
+            });
+        }
+        Ok(Self { problem, slices })
+    }
+
+    /// # Square lattice
+    /// The periodic square lattice of `Grid`, with energy −J Σ s_i s_j − h Σ s_i, site (x, y)
+    /// being spin y · width + x.
+    pub fn square_lattice(
+        width: usize,
+        height: usize,
+        coupling: f64,
+        field: f64,
+        slices: usize,
+    ) -> Result<Self> {
+        check_size(width, height)?;
+        check_finite("coupling", coupling)?;
+        check_finite("field", field)?;
+        let mut couplings = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let site = y * width + x;
+                // The right and lower neighbours visit every bond once, which on a lattice one
+                // site wide or high means a site coupled to itself, a constant left out.
+                if width > 1 {
+                    couplings.push((site, y * width + (x + 1) % width, -coupling));
+                }
+                if height > 1 {
+                    couplings.push((site, (y + 1) % height * width + x, -coupling));
+                }
+            }
+        }
+        Self::new(
+            IsingProblem::new(vec![-field; width * height], &couplings),
+            slices,
+        )
+    }
+
+    /// # Problem
+    /// The classical part E of the Hamiltonian.
+    pub fn problem(&self) -> &IsingProblem {
+        &self.problem
+    }
+
+    /// # Trotter slices
+    pub fn slices(&self) -> usize {
+        self.slices
+    }
+
+    /// # Coupling between slices
+    /// The reduced coupling J⊥ = −½ ln tanh(βΓ/P) of a spin to itself in the neighbouring
+    /// slices at the given temperature, which grows as the transverse field weakens and ties the
+    /// slices together.
+    pub fn interslice_coupling(&self, temperature: Temperature, transverse_field: f64) -> f64 {
+        -0.5 * (temperature.beta() * transverse_field / self.slices as f64)
+            .tanh()
+            .ln()
+    }
+
+    /// # Classical problem
+    /// The (2+1)-dimensional classical problem that the model maps onto, to be sampled at the same
+    /// temperature, up to constants. Spin i of slice k is spin k · N + i, each slice has the
+    /// couplings and fields of the problem divided by P, and neighbouring slices are coupled by
+    /// −J⊥/β. Fails unless Γ is positive and finite.
+    pub fn classical_problem(
+        &self,
+        temperature: Temperature,
+        transverse_field: f64,
+    ) -> Result<IsingProblem> {
+        check_transverse_field(transverse_field)?;
+        let beta = temperature.beta();
+        let n = self.problem.number_of_spins();
+        let p = self.slices as f64;
+        let mut fields = Vec::with_capacity(n * self.slices);
+        let mut couplings = Vec::new();
+        for k in 0..self.slices {
+            fields.extend(self.problem.fields().iter().map(|field| field / p));
+            for (i, j, coupling) in self.problem.couplings() {
+                couplings.push((k * n + i, k * n + j, coupling / p));
+            }
+            if self.slices > 1 {
+                let interslice = -self.interslice_coupling(temperature, transverse_field) / beta;
+                let next = (k + 1) % self.slices;
+                couplings.extend((0..n).map(|i| (k * n + i, next * n + i, interslice)));
+            }
+        }
+        Ok(IsingProblem::new(fields, &couplings))
+    }
+
+    /// # Change of the action
+    /// β times the change of the classical energy when spin i of slice k flips.
+    fn flip_action_change(
+        &self,
+        spins: &[Spin],
+        beta: f64,
+        interslice: f64,
+        k: usize,
+        i: usize,
+    ) -> f64 {
+        let n = self.problem.number_of_spins();
+        let slice = &spins[k * n..(k + 1) * n];
+        let mut change = beta / self.slices as f64 * self.problem.flip_energy_change(slice, i);
+        if self.slices > 1 {
+            let previous = (k + self.slices - 1) % self.slices;
+            let next = (k + 1) % self.slices;
+            change += 2.0
+                * interslice
+                * spins[k * n + i].value()
+                * (spins[previous * n + i].value() + spins[next * n + i].value());
+        }
+        change
+    }
+
+    /// # Metropolis sweep
+    /// Visits every spin of every slice once and flips it with probability min(1, exp(−βΔE)) of
+    /// the classical problem at the given temperature, keeping track of the energy E of each
+    /// slice and of the best slice seen.
+    fn sweep<R: Rng>(
+        &self,
+        spins: &mut [Spin],
+        energies: &mut [f64],
+        temperature: Temperature,
+        transverse_field: f64,
+        best: &mut Solution,
+        rng: &mut R,
+    ) {
+        let n = self.problem.number_of_spins();
+        let beta = temperature.beta();
+        let interslice = self.interslice_coupling(temperature, transverse_field);
+        for k in 0..self.slices {
+            for i in 0..n {
+                let action_change = self.flip_action_change(spins, beta, interslice, k, i);
+                if action_change <= 0.0 || rng.gen::<f64>() < (-action_change).exp() {
+                    let slice = &mut spins[k * n..(k + 1) * n];
+                    energies[k] += self.problem.flip_energy_change(slice, i);
+                    slice[i] = slice[i].flip();
+                    if energies[k] < best.energy {
+                        best.energy = energies[k];
+                        best.spins.copy_from_slice(slice);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Random slices, their energies and the best of them.
+    fn random_slices<R: Rng>(&self, rng: &mut R) -> (Vec<Spin>, Vec<f64>, Solution) {
+        let slices: Vec<Vec<Spin>> = (0..self.slices)
+            .map(|_| self.problem.random_configuration(rng))
+            .collect();
+        let energies: Vec<f64> = slices
+            .iter()
+            .map(|slice| self.problem.energy(slice))
+            .collect();
+        let lowest = (0..self.slices)
+            .min_by(|&a, &b| energies[a].total_cmp(&energies[b]))
+            .unwrap();
+        let best = Solution {
+            energy: energies[lowest],
+            spins: slices[lowest].clone(),
+        };
+        (slices.concat(), energies, best)
+    }
+
+    /// # Sample
+    /// Thermalizes random slices at the temperature and transverse field, then measures the
+    /// observables after every one of the measurement sweeps. The transverse magnetization comes
+    /// from the bonds between the slices, each contributing tanh(βΓ/P) if its spins are aligned
+    /// and coth(βΓ/P) otherwise. Fails unless Γ is positive and finite.
+    pub fn sample<R: Rng>(
+        &self,
+        temperature: Temperature,
+        transverse_field: f64,
+        thermalization_sweeps: usize,
+        measurement_sweeps: usize,
+        rng: &mut R,
+    ) -> Result<QuantumObservables> {
+        check_transverse_field(transverse_field)?;
+        let beta = temperature.beta();
+        let n = self.problem.number_of_spins();
+        let p = self.slices as f64;
+        let (mut spins, mut energies, mut best) = self.random_slices(rng);
+        let _span = debug_span!("sample", slices = self.slices, measurement_sweeps).entered();
+        for _ in 0..thermalization_sweeps {
+            self.sweep(
+                &mut spins,
+                &mut energies,
+                temperature,
+                transverse_field,
+                &mut best,
+                rng,
+            );
+        }
+
+        let epsilon = beta * transverse_field / p;
+        let (aligned, antialigned) = (epsilon.tanh(), 1.0 / epsilon.tanh());
+        let mut magnetizations = Vec::with_capacity(measurement_sweeps);
+        let mut absolute_magnetizations = Vec::with_capacity(measurement_sweeps);
+        let mut transverse_magnetizations = Vec::with_capacity(measurement_sweeps);
+        let mut energies_z = Vec::with_capacity(measurement_sweeps);
+        for _ in 0..measurement_sweeps {
+            self.sweep(
+                &mut spins,
+                &mut energies,
+                temperature,
+                transverse_field,
+                &mut best,
+                rng,
+            );
+            let slice_magnetizations: Vec<f64> = spins
+                .chunks(n)
+                .map(|slice| slice.iter().map(Spin::value).sum::<f64>() / n as f64)
+                .collect();
+            magnetizations.push(slice_magnetizations.iter().sum::<f64>() / p);
+            absolute_magnetizations
+                .push(slice_magnetizations.iter().map(|m| m.abs()).sum::<f64>() / p);
+            let transverse: f64 = if self.slices == 1 {
+                // A single slice has no bonds, and its estimator is tanh(βΓ).
+                aligned
+            } else {
+                (0..self.slices)
+                    .flat_map(|k| (0..n).map(move |i| (k, i)))
+                    .map(|(k, i)| {
+                        let next = (k + 1) % self.slices;
+                        if spins[k * n + i] == spins[next * n + i] {
+                            aligned
+                        } else {
+                            antialigned
+                        }
+                    })
+                    .sum::<f64>()
+                    / (n * self.slices) as f64
+            };
+            transverse_magnetizations.push(transverse);
+            energies_z.push(energies.iter().sum::<f64>() / (p * n as f64));
+            trace!(transverse, "measured slices");
+        }
+
+        let transverse_magnetization = blocked_mean(&transverse_magnetizations);
+        // The two terms of the energy come from the same samples, so they are measured together.
+        let energy: Vec<f64> = energies_z
+            .iter()
+            .zip(&transverse_magnetizations)
+            .map(|(energy, transverse)| energy - transverse_field * transverse)
+            .collect();
+        Ok(QuantumObservables {
+            magnetization: blocked_mean(&magnetizations),
+            absolute_magnetization: blocked_mean(&absolute_magnetizations),
+            transverse_magnetization,
+            energy: blocked_mean(&energy),
+        })
+    }
+
+    /// # Simulated quantum annealing
+    /// Starts from random slices at the given temperature and performs Metropolis sweeps of the
+    /// classical problem while the transverse field falls linearly from `initial_field` to
+    /// `final_field`, so that quantum fluctuations rather than thermal ones carry the slices over
+    /// the barriers. Returns the best configuration of any slice seen along the way, scored by the
+    /// energy E of the problem. Fails unless both transverse fields are positive and finite.
+    pub fn simulated_quantum_annealing<R: Rng>(
+        &self,
+        temperature: Temperature,
+        initial_field: f64,
+        final_field: f64,
+        sweeps: usize,
+        rng: &mut R,
+    ) -> Result<Solution> {
+        check_transverse_field(initial_field)?;
+        check_transverse_field(final_field)?;
+        let (mut spins, mut energies, mut best) = self.random_slices(rng);
+        let _span =
+            debug_span!("simulated_quantum_annealing", slices = self.slices, sweeps).entered();
+        for sweep in 0..sweeps {
+            let progress = sweep as f64 / (sweeps.max(2) - 1) as f64;
+            let transverse_field = initial_field + (final_field - initial_field) * progress;
+            self.sweep(
+                &mut spins,
+                &mut energies,
+                temperature,
+                transverse_field,
+                &mut best,
+                rng,
+            );
+            trace!(
+                sweep,
+                transverse_field,
+                best_energy = best.energy,
+                "annealing sweep"
+            );
+        }
+        debug!(best_energy = best.energy, "finished quantum annealing");
+        Ok(best)
+    }
+}
+
+/// Checks that a transverse field is positive and finite.
+fn check_transverse_field(transverse_field: f64) -> Result<()> {
+    if !(transverse_field > 0.0 && transverse_field.is_finite()) {
+        return Err(Error::InvalidParameter {
+            name: "transverse field",
+            value: transverse_field,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_classical_problem() {
+        let model = TransverseFieldIsing::square_lattice(3, 2, 1.0, 0.3, 4).unwrap();
+        let (temperature, transverse_field) = (Temperature::from_beta(0.8).unwrap(), 1.2);
+        let beta = temperature.beta();
+        let classical = model
+            .classical_problem(temperature, transverse_field)
+            .unwrap();
+        assert_eq!(classical.number_of_spins(), 24);
+        let mut rng = StdRng::seed_from_u64(1);
+        let spins = classical.random_configuration(&mut rng);
+        let interslice = model.interslice_coupling(temperature, transverse_field);
+        for k in 0..4 {
+            for i in 0..6 {
+                let expected = beta * classical.flip_energy_change(&spins, k * 6 + i);
+                let change = model.flip_action_change(&spins, beta, interslice, k, i);
+                assert!((change - expected).abs() < 1e-12);
+            }
+        }
+        // The slices average the energy of the lattice when they are all alike.
+        let aligned = vec![Spin::Up; 24];
+        let energy = classical.energy(&aligned) + 24.0 * interslice / beta;
+        assert!((energy - model.problem().energy(&aligned[..6])).abs() < 1e-12);
+
+        assert!(TransverseFieldIsing::square_lattice(0, 2, 1.0, 0.0, 4).is_err());
+        assert!(TransverseFieldIsing::square_lattice(2, 2, 1.0, 0.0, 0).is_err());
+        assert!(model.classical_problem(temperature, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_single_spin() {
+        // A spin in fields h along z and Γ along x has the energies ±λ with λ = √(h² + Γ²).
+        let (field, transverse_field) = (0.5, 1.0);
+        let model = TransverseFieldIsing::new(IsingProblem::new(vec![field], &[]), 32).unwrap();
+        let mut rng = StdRng::seed_from_u64(3);
+        let observables = model
+            .sample(
+                Temperature::new(1.0).unwrap(),
+                transverse_field,
+                1000,
+                20_000,
+                &mut rng,
+            )
+            .unwrap();
+        let lambda = f64::hypot(field, transverse_field);
+        let polarization = lambda.tanh();
+        let expected = [
+            (observables.magnetization, -field / lambda * polarization),
+            (
+                observables.transverse_magnetization,
+                transverse_field / lambda * polarization,
+            ),
+            (observables.energy, -lambda * polarization),
+        ];
+        for (measurement, exact) in expected {
+            assert!(
+                (measurement.value - exact).abs() < 5.0 * measurement.error + 0.01,
+                "{} against {}",
+                measurement,
+                exact
+            );
+        }
+    }
+
+    #[test]
+    fn test_simulated_quantum_annealing() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let n = 10;
+        let fields: Vec<f64> = (0..n).map(|_| rng.gen_range(-0.5..0.5)).collect();
+        let mut couplings = Vec::new();
+        for i in 0..n {
+            for j in i + 1..n {
+                couplings.push((i, j, rng.gen_range(-1.0..1.0)));
+            }
+        }
+        let problem = IsingProblem::new(fields, &couplings);
+        let ground_energy = (0..1_u32 << n)
+            .map(|state| {
+                let spins: Vec<Spin> = (0..n)
+                    .map(|i| {
+                        if state >> i & 1 == 1 {
+                            Spin::Up
+                        } else {
+                            Spin::Down
+                        }
+                    })
+                    .collect();
+                problem.energy(&spins)
+            })
+            .fold(f64::INFINITY, f64::min);
+
+        let model = TransverseFieldIsing::new(problem, 8).unwrap();
+        let temperature = Temperature::new(0.1).unwrap();
+        let solution = model
+            .simulated_quantum_annealing(temperature, 3.0, 0.01, 500, &mut rng)
+            .unwrap();
+        assert!((solution.energy - ground_energy).abs() < 1e-9);
+        assert!((model.problem().energy(&solution.spins) - solution.energy).abs() < 1e-9);
+        assert!(model
+            .simulated_quantum_annealing(temperature, 3.0, 0.0, 500, &mut rng)
+            .is_err());
+    }
+}
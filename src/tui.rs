@@ -0,0 +1,330 @@
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style};
+use ratatui::symbols::Marker;
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Axis, Block, Chart, Dataset, GraphType, Paragraph};
+use ratatui::Frame;
+
+use crate::error::{Error, Result};
+use crate::render::Palette;
+use crate::simulation::{Simulation, SimulationParameters};
+
+/// The number of sweeps kept for the plots.
+const HISTORY_LENGTH: usize = 500;
+
+/// # Explorer
+/// An interactive terminal interface on top of a simulation. It shows the lattice, plots of the
+/// energy and magnetization per site over the last sweeps, and the acceptance rate, and lets the
+/// temperature T = 1/K and the field h = H/K, both in units of the coupling, be changed while the
+/// simulation runs.
+///
+/// The keys are: space or `p` to pause and resume, `n` for a single sweep while paused, up and
+/// down to raise and lower T, right and left to raise and lower h, `s` to save the lattice as
+/// `snapshot_<sweep>.png`, and `q` or escape to quit.
+pub struct Explorer {
+    simulation: Simulation,
+    temperature: f64,
+    field: f64,
+    paused: bool,
+    acceptance: f64,
+    history: VecDeque<[f64; 3]>,
+    status: String,
+}
+
+impl Explorer {
+    /// # New explorer
+    /// Starts exploring from the state and parameters of the given simulation, which needs a
+    /// positive and finite coupling, or this fails.
+    pub fn new(simulation: Simulation) -> Result<Self> {
+        let parameters = simulation.parameters();
+        if !(parameters.coupling > 0.0 && parameters.coupling.is_finite()) {
+            return Err(Error::InvalidParameter {
+                name: "coupling",
+                value: parameters.coupling,
+            });
+        }
+        Ok(Self {
+            temperature: 1.0 / parameters.coupling,
+            field: parameters.field / parameters.coupling,
+            simulation,
+            paused: false,
+            acceptance: 0.0,
+            history: VecDeque::with_capacity(HISTORY_LENGTH),
+            status: String::new(),
+        })
+    }
+
+    /// # Simulation
+    /// The simulation being explored.
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
+
+    /// # Into simulation
+    /// Gives back the simulation, for example to save a checkpoint after exploring.
+    pub fn into_simulation(self) -> Simulation {
+        self.simulation
+    }
+
+    /// # Temperature
+    /// The temperature T in units of the coupling.
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// # Field
+    /// The field h in units of the coupling.
+    pub fn field(&self) -> f64 {
+        self.field
+    }
+
+    /// # Is paused
+    /// Whether the simulation is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    fn set_parameters(&mut self, temperature: f64, field: f64) {
+        self.temperature = temperature.max(0.05);
+        self.field = field;
+        self.simulation.set_parameters(SimulationParameters {
+            coupling: 1.0 / self.temperature,
+            field: self.field / self.temperature,
+        });
+    }
+
+    /// # Handle a key
+    /// Acts on a key press, and returns false when the explorer should quit.
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char(' ') | KeyCode::Char('p') => self.paused = !self.paused,
+            KeyCode::Char('n') if self.paused => self.sweep(),
+            KeyCode::Up => self.set_parameters(self.temperature + 0.05, self.field),
+            KeyCode::Down => self.set_parameters(self.temperature - 0.05, self.field),
+            KeyCode::Right => self.set_parameters(self.temperature, self.field + 0.01),
+            KeyCode::Left => self.set_parameters(self.temperature, self.field - 0.01),
+            KeyCode::Char('s') => {
+                let path = format!("snapshot_{}.png", self.simulation.sweep());
+                self.status = match self
+                    .simulation
+                    .grid()
+                    .save_png(&path, &Palette::default(), 4)
+                {
+                    Ok(()) => format!("Saved {}", path),
+                    Err(error) => format!("Could not save {}: {}", path, error),
+                };
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// # Advance
+    /// Performs a sweep unless the simulation is paused.
+    pub fn advance(&mut self) {
+        if !self.paused {
+            self.sweep();
+        }
+    }
+
+    fn sweep(&mut self) {
+        self.acceptance = self.simulation.step();
+        let grid = self.simulation.grid();
+        let occupied = grid.number_of_occupied_sites() as f64;
+        let magnetization = grid.magnetization() / occupied;
+        let energy = grid.energy_reduced(1.0, self.field) / occupied;
+        if self.history.len() == HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history
+            .push_back([self.simulation.sweep() as f64, energy, magnetization]);
+    }
+
+    /// # Draw
+    /// Draws the lattice, the plots and the status into a frame.
+    pub fn draw(&self, frame: &mut Frame) {
+        let [lattice, side] =
+            Layout::horizontal([Constraint::Min(0), Constraint::Length(48)]).areas(frame.area());
+        let [plots, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(11)]).areas(side);
+        self.draw_lattice(frame, lattice);
+        self.draw_plots(frame, plots);
+
+        let lines = vec![
+            Line::from(format!("Sweep: {}", self.simulation.sweep())),
+            Line::from(format!("Temperature T: {:.2}", self.temperature)),
+            Line::from(format!("Field h: {:.2}", self.field)),
+            Line::from(format!("Acceptance rate: {:.3}", self.acceptance)),
+            Line::from(if self.paused { "Paused" } else { "Running" }),
+            Line::from(self.status.as_str()),
+            Line::from("space pause · n step · ↑↓ T · ←→ h"),
+            Line::from("s snapshot · q quit"),
+        ];
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Status")),
+            status,
+        );
+    }
+
+    fn draw_lattice(&self, frame: &mut Frame, area: Rect) {
+        let block = Block::bordered().title("Lattice");
+        let inner = block.inner(area);
+        let grid = self.simulation.grid();
+        let palette = Palette::default();
+        let color = |x: usize, y: usize| {
+            let [red, green, blue] = palette.color(grid.get(x as i64, y as i64));
+            Color::Rgb(red, green, blue)
+        };
+
+        // Each character shows two rows with an upper half block, as in `render::to_terminal`.
+        let columns = grid.width().min(inner.width as usize);
+        let rows = grid.height().min(2 * inner.height as usize);
+        let lines: Vec<Line> = (0..rows)
+            .step_by(2)
+            .map(|y| {
+                let spans: Vec<Span> = (0..columns)
+                    .map(|x| {
+                        let mut style = Style::default().fg(color(x, y));
+                        if y + 1 < rows {
+                            style = style.bg(color(x, y + 1));
+                        }
+                        Span::styled("▀", style)
+                    })
+                    .collect();
+                Line::from(spans)
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_plots(&self, frame: &mut Frame, area: Rect) {
+        let energy: Vec<(f64, f64)> = self.history.iter().map(|&[s, e, _]| (s, e)).collect();
+        let magnetization: Vec<(f64, f64)> = self.history.iter().map(|&[s, _, m]| (s, m)).collect();
+        let first = self.history.front().map_or(0.0, |point| point[0]);
+        let last = self.history.back().map_or(1.0, |point| point[0]);
+        let datasets = vec![
+            Dataset::default()
+                .name("E/N")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Yellow))
+                .data(&energy),
+            Dataset::default()
+                .name("M/N")
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Cyan))
+                .data(&magnetization),
+        ];
+        let chart = Chart::new(datasets)
+            .block(Block::bordered().title("Energy and magnetization"))
+            .x_axis(
+                Axis::default()
+                    .bounds([first, last.max(first + 1.0)])
+                    .labels([format!("{}", first), format!("{}", last)]),
+            )
+            .y_axis(Axis::default().bounds([-2.5, 2.5]).labels(["-2", "0", "2"]));
+        frame.render_widget(chart, area);
+    }
+}
+
+/// # Explore
+/// Runs the explorer in the terminal until it is quit, and gives back the simulation. The terminal
+/// is restored afterwards, also when drawing fails. Fails like `Explorer::new` before the terminal
+/// is touched.
+pub fn explore(simulation: Simulation) -> io::Result<Simulation> {
+    let mut explorer = Explorer::new(simulation)?;
+    let mut terminal = ratatui::init();
+    let result = (|| -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| explorer.draw(frame))?;
+            let timeout = if explorer.is_paused() { 100 } else { 0 };
+            if event::poll(Duration::from_millis(timeout))? {
+                if let Event::Key(key) = event::read()? {
+                    if key.kind == KeyEventKind::Press && !explorer.handle_key(key.code) {
+                        return Ok(());
+                    }
+                }
+            }
+            explorer.advance();
+        }
+    })();
+    ratatui::restore();
+    result.map(|()| explorer.into_simulation())
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    use super::*;
+    use crate::grid::Grid;
+
+    fn explorer() -> Explorer {
+        let parameters = SimulationParameters {
+            coupling: 0.5,
+            field: 0.1,
+        };
+        Explorer::new(
+            Simulation::with_seed(Grid::new_random(12, 10).unwrap(), parameters, 3).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_explorer_keys() {
+        let mut explorer = explorer();
+        assert_eq!((explorer.temperature(), explorer.field()), (2.0, 0.2));
+
+        assert!(explorer.handle_key(KeyCode::Up));
+        assert!(explorer.handle_key(KeyCode::Right));
+        let parameters = explorer.simulation().parameters();
+        assert!((parameters.coupling - 1.0 / 2.05).abs() < 1e-12);
+        assert!((parameters.field - 0.21 / 2.05).abs() < 1e-12);
+
+        explorer.advance();
+        assert_eq!(explorer.simulation().sweep(), 1);
+        assert!(explorer.handle_key(KeyCode::Char(' ')));
+        explorer.advance();
+        assert_eq!(explorer.simulation().sweep(), 1);
+        explorer.handle_key(KeyCode::Char('n'));
+        assert_eq!(explorer.simulation().sweep(), 2);
+        assert!(!explorer.handle_key(KeyCode::Char('q')));
+
+        let antiferromagnet = SimulationParameters {
+            coupling: -0.5,
+            field: 0.0,
+        };
+        let simulation =
+            Simulation::with_seed(Grid::new_random(12, 10).unwrap(), antiferromagnet, 3).unwrap();
+        assert!(Explorer::new(simulation).is_err());
+    }
+
+    #[test]
+    fn test_explorer_draw() {
+        let mut explorer = explorer();
+        for _ in 0..5 {
+            explorer.advance();
+        }
+        explorer.handle_key(KeyCode::Char('p'));
+        let mut terminal = Terminal::new(TestBackend::new(90, 30)).unwrap();
+        terminal.draw(|frame| explorer.draw(frame)).unwrap();
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(text.contains("Paused"));
+        assert!(text.contains("Sweep: 5"));
+        assert_eq!(text.matches('▀').count(), 12 * 5);
+    }
+}
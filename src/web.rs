@@ -0,0 +1,132 @@
+use wasm_bindgen::prelude::*;
+
+use crate::grid::Grid;
+use crate::render::Palette;
+use crate::simulation::{Simulation, SimulationParameters};
+use crate::temperature::Temperature;
+
+#[wasm_bindgen]
+impl Temperature {
+    /// # Temperature from JavaScript
+    /// The reduced temperature T in units of the coupling. Throws unless it is positive and
+    /// finite.
+    #[wasm_bindgen(constructor)]
+    pub fn from_js(temperature: f64) -> Result<Temperature, JsError> {
+        Ok(Temperature::new(temperature)?)
+    }
+}
+
+/// # Web simulation
+/// The simulation as seen from JavaScript, for the browser front-end in `web/`. It runs sweeps on
+/// request and hands out the configuration as RGBA pixels, ready to be put on a canvas as
+/// `ImageData`. The temperature T and the field h are in units of the coupling.
+#[wasm_bindgen]
+pub struct WebSimulation {
+    simulation: Simulation,
+    palette: Palette,
+}
+
+#[wasm_bindgen]
+impl WebSimulation {
+    /// # New web simulation
+    /// Starts from random spins on a periodic width × height grid at the given temperature and
+    /// field, with a seeded generator. Throws if the grid is empty or the field invalid.
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        width: usize,
+        height: usize,
+        temperature: Temperature,
+        field: f64,
+        seed: u32,
+    ) -> Result<WebSimulation, JsError> {
+        Ok(Self {
+            simulation: Simulation::with_seed(
+                Grid::new_random(width, height)?,
+                SimulationParameters::at_temperature(temperature, field)?,
+                seed as u64,
+            )?,
+            palette: Palette::blue_red(),
+        })
+    }
+
+    /// # Width
+    /// The number of columns of the grid.
+    pub fn width(&self) -> usize {
+        self.simulation.grid().width()
+    }
+
+    /// # Height
+    /// The number of rows of the grid.
+    pub fn height(&self) -> usize {
+        self.simulation.grid().height()
+    }
+
+    /// # Sweep
+    /// The number of sweeps performed so far.
+    pub fn sweep(&self) -> usize {
+        self.simulation.sweep()
+    }
+
+    /// # Set the parameters
+    /// Changes the temperature T and the field h. Throws if the field is invalid, keeping the
+    /// old parameters.
+    pub fn set_parameters(&mut self, temperature: Temperature, field: f64) -> Result<(), JsError> {
+        self.simulation
+            .set_parameters(SimulationParameters::at_temperature(temperature, field)?);
+        Ok(())
+    }
+
+    /// # Step
+    /// Performs the given number of sweeps and returns the acceptance rate of the last one.
+    pub fn step(&mut self, sweeps: usize) -> f64 {
+        let mut acceptance = 0.0;
+        for _ in 0..sweeps {
+            acceptance = self.simulation.step();
+        }
+        acceptance
+    }
+
+    /// # Magnetization
+    /// The magnetization per occupied site of the current configuration.
+    pub fn magnetization(&self) -> f64 {
+        let grid = self.simulation.grid();
+        grid.magnetization() / grid.number_of_occupied_sites() as f64
+    }
+
+    /// # Pixels
+    /// The configuration as opaque RGBA pixels, one per spin, row by row from the top.
+    pub fn pixels(&self) -> Vec<u8> {
+        let grid = self.simulation.grid();
+        let mut pixels = Vec::with_capacity(4 * grid.width() * grid.height());
+        for y in 0..grid.height() as i64 {
+            for x in 0..grid.width() as i64 {
+                pixels.extend(self.palette.color(grid.get(x, y)));
+                pixels.push(255);
+            }
+        }
+        pixels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_web_simulation() {
+        let temperature = Temperature::from_js(2.0).unwrap();
+        let mut simulation = WebSimulation::new(10, 6, temperature, 0.2, 7).unwrap();
+        assert_eq!((simulation.width(), simulation.height()), (10, 6));
+        assert_eq!(simulation.simulation.parameters().coupling, 0.5);
+        assert_eq!(simulation.simulation.parameters().field, 0.1);
+
+        let acceptance = simulation.step(3);
+        assert!((0.0..=1.0).contains(&acceptance));
+        assert_eq!(simulation.sweep(), 3);
+        assert!(simulation.magnetization().abs() <= 1.0);
+
+        let pixels = simulation.pixels();
+        assert_eq!(pixels.len(), 4 * 60);
+        assert!(pixels.chunks(4).all(|pixel| pixel[3] == 255));
+    }
+}
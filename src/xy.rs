@@ -0,0 +1,476 @@
+use std::f64::consts::PI;
+
+use rand::Rng;
+
+use crate::error::{check_finite, check_size, Result};
+use crate::helicity::TwistResponse;
+use crate::lattice::{Hypercubic, Lattice};
+use crate::temperature::Temperature;
+
+/// # XY model
+/// Planar O(2) spins on a periodic width × height grid. Every site holds an angle θ in [0, 2π),
+/// and neighbours interact through the cosine of their relative angle,
+/// E = -K Σ_⟨ij⟩ cos(θ_i - θ_j) - H Σ_i cos θ_i, in the same reduced units as `Grid::step`. In two
+/// dimensions there is no long-range order at any temperature, but the model has a
+/// Kosterlitz–Thouless transition near K ≈ 1.12 into a phase with power-law correlations.
+#[derive(Debug, Clone)]
+pub struct XYModel {
+    angles: Vec<f64>,
+    lattice: Hypercubic<2>,
+    proposal_width: f64,
+}
+
+impl XYModel {
+    /// # New random XY model
+    /// Creates a grid where every spin points at a uniformly random angle.
+    pub fn new_random<R: Rng>(width: usize, height: usize, rng: &mut R) -> Result<Self> {
+        let mut model = Self::new_constant(width, height, 0.0)?;
+        for angle in model.angles.iter_mut() {
+            *angle = rng.gen_range(0.0..2.0 * PI);
+        }
+        Ok(model)
+    }
+
+    /// # New constant XY model
+    /// Creates a grid where every spin points at the same angle. Fails if the grid has no sites
+    /// or the angle is not finite.
+    pub fn new_constant(width: usize, height: usize, angle: f64) -> Result<Self> {
+        check_size(width, height)?;
+        check_finite("angle", angle)?;
+        Ok(Self {
+            angles: vec![angle.rem_euclid(2.0 * PI); width * height],
+            lattice: Hypercubic::new([width, height])?,
+            proposal_width: PI,
+        })
+    }
+
+    /// # Width
+    /// Returns the number of columns in the grid.
+    pub fn width(&self) -> usize {
+        self.lattice.shape()[0]
+    }
+
+    /// # Height
+    /// Returns the number of rows in the grid.
+    pub fn height(&self) -> usize {
+        self.lattice.shape()[1]
+    }
+
+    /// # Proposal width
+    /// The largest rotation Δ that a Metropolis step proposes, so new angles are drawn uniformly
+    /// from [θ - Δ, θ + Δ]. It defaults to π, which proposes any angle.
+    pub fn proposal_width(&self) -> f64 {
+        self.proposal_width
+    }
+
+    /// # Set the proposal width
+    /// Narrower proposals are accepted more often at strong coupling, and a width that keeps
+    /// the acceptance near one half usually decorrelates fastest.
+    pub fn set_proposal_width(&mut self, proposal_width: f64) {
+        assert!(
+            proposal_width > 0.0 && proposal_width <= PI,
+            "the proposal width must lie in (0, π]"
+        );
+        self.proposal_width = proposal_width;
+    }
+
+    /// # Get an angle
+    /// Retrieves the angle at the given coordinates, with periodic boundary conditions.
+    pub fn get(&self, x: i64, y: i64) -> f64 {
+        self.angles[self.lattice.site([x, y])]
+    }
+
+    /// # Set an angle
+    /// Sets the angle at the given coordinates, reduced to [0, 2π).
+    pub fn set(&mut self, x: i64, y: i64, angle: f64) {
+        let index = self.lattice.site([x, y]);
+        self.angles[index] = angle.rem_euclid(2.0 * PI);
+    }
+
+    /// # Magnetization
+    /// The magnetization vector per site, (⟨cos θ⟩, ⟨sin θ⟩).
+    pub fn magnetization(&self) -> (f64, f64) {
+        let (mut mx, mut my) = (0.0, 0.0);
+        for angle in &self.angles {
+            mx += angle.cos();
+            my += angle.sin();
+        }
+        let number_of_sites = self.angles.len() as f64;
+        (mx / number_of_sites, my / number_of_sites)
+    }
+
+    /// # Absolute magnetization
+    /// The length of the magnetization vector per site.
+    pub fn absolute_magnetization(&self) -> f64 {
+        let (mx, my) = self.magnetization();
+        mx.hypot(my)
+    }
+
+    /// # Energy
+    /// The energy per site at temperature T and field h in units of the coupling, in the reduced
+    /// units βE of the Boltzmann weight, with every bond counted once.
+    pub fn energy(&self, temperature: Temperature, field: f64) -> f64 {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        let mut energy = 0.0;
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                let angle = self.get(x, y);
+                energy -= coupling
+                    * ((angle - self.get(x + 1, y)).cos() + (angle - self.get(x, y + 1)).cos())
+                    + field * angle.cos();
+            }
+        }
+        energy / self.angles.len() as f64
+    }
+
+    /// # Twist response
+    /// The bond cosines Σ cos(θ_i - θ_j) and spin currents Σ sin(θ_i - θ_j) over the bonds along
+    /// each axis, from which `HelicityModulus` estimates the spin stiffness.
+    pub fn twist_response(&self) -> TwistResponse {
+        let mut bond_sums = [0.0; 2];
+        let mut spin_currents = [0.0; 2];
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                let angle = self.get(x, y);
+                for (axis, (dx, dy)) in [(1, 0), (0, 1)].into_iter().enumerate() {
+                    let difference = angle - self.get(x + dx, y + dy);
+                    bond_sums[axis] += difference.cos();
+                    spin_currents[axis] += difference.sin();
+                }
+            }
+        }
+        TwistResponse {
+            number_of_sites: self.angles.len(),
+            bond_sums,
+            spin_currents,
+        }
+    }
+
+    /// # Vorticity
+    /// The winding number of the plaquette whose lower-left corner is at the given coordinates.
+    /// The angle differences around the plaquette, taken counterclockwise and each reduced to
+    /// [-π, π), add up to 2π times the number of vortices inside it, so the result is +1 for a
+    /// vortex, -1 for an antivortex, and 0 otherwise.
+    pub fn vorticity(&self, x: i64, y: i64) -> i32 {
+        let corners = [
+            self.get(x, y),
+            self.get(x + 1, y),
+            self.get(x + 1, y + 1),
+            self.get(x, y + 1),
+        ];
+        let circulation: f64 = (0..4)
+            .map(|i| (corners[(i + 1) % 4] - corners[i] + PI).rem_euclid(2.0 * PI) - PI)
+            .sum();
+        (circulation / (2.0 * PI)).round() as i32
+    }
+
+    /// # Vortices
+    /// The lower-left corners and charges of all the plaquettes with a non-zero winding number.
+    /// On a periodic grid the charges always add up to zero.
+    pub fn vortices(&self) -> Vec<(usize, usize, i32)> {
+        let mut vortices = Vec::new();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let charge = self.vorticity(x as i64, y as i64);
+                if charge != 0 {
+                    vortices.push((x, y, charge));
+                }
+            }
+        }
+        vortices
+    }
+
+    /// # Vortex density
+    /// The number of vortices and antivortices per plaquette. Below the Kosterlitz–Thouless
+    /// transition they only appear as tightly bound pairs and the density is exponentially small,
+    /// while above it they unbind and proliferate. Random angles give a density of one third.
+    pub fn vortex_density(&self) -> f64 {
+        let charges: i32 = self
+            .vortices()
+            .iter()
+            .map(|&(_, _, charge)| charge.abs())
+            .sum();
+        charges as f64 / self.angles.len() as f64
+    }
+
+    /// # Local energy
+    /// The energy of the bonds of a single site and of its coupling to the field, if it pointed at
+    /// the given angle.
+    fn local_energy(&self, x: i64, y: i64, angle: f64, coupling: f64, field: f64) -> f64 {
+        let neighbors: f64 = self
+            .lattice
+            .neighbors(self.lattice.site([x, y]))
+            .iter()
+            .map(|&neighbor| (angle - self.angles[neighbor]).cos())
+            .sum();
+        -coupling * neighbors - field * angle.cos()
+    }
+
+    /// # Single site step
+    /// Proposes a rotation of a single spin by a uniform angle in [-Δ, Δ] and accepts it with the
+    /// Metropolis probability. Returns whether the rotation was accepted.
+    pub fn single_site_step<R: Rng>(
+        &mut self,
+        x: i64,
+        y: i64,
+        temperature: Temperature,
+        field: f64,
+        rng: &mut R,
+    ) -> bool {
+        let (coupling, field) = (temperature.beta(), field * temperature.beta());
+        let old_angle = self.get(x, y);
+        let new_angle = old_angle + self.proposal_width * (2.0 * rng.gen::<f64>() - 1.0);
+        let energy_change = self.local_energy(x, y, new_angle, coupling, field)
+            - self.local_energy(x, y, old_angle, coupling, field);
+
+        // Accept the rotation with probability min(1, exp(-ΔE)).
+        let accepted = energy_change <= 0.0 || rng.gen::<f64>() < (-energy_change).exp();
+        if accepted {
+            self.set(x, y, new_angle);
+        }
+        accepted
+    }
+
+    /// # Step
+    /// Performs a single Metropolis sweep over all the sites at temperature T and field h in units
+    /// of the coupling, and returns the fraction of the proposals that were accepted.
+    pub fn step<R: Rng>(&mut self, temperature: Temperature, field: f64, rng: &mut R) -> f64 {
+        let mut accepted = 0;
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                accepted += usize::from(self.single_site_step(x, y, temperature, field, rng));
+            }
+        }
+        accepted as f64 / self.angles.len() as f64
+    }
+
+    /// # Over-relaxation step
+    /// Reflects a single spin about the direction of its local field h = Σ_j s_j + h x̂, with the
+    /// field h in units of the coupling, θ → 2φ_h - θ. The reflected spin makes the same angle
+    /// with the field, so the energy is unchanged and the move is always accepted, but it carries
+    /// the spin as far as possible through the configurations of equal energy. Only the direction
+    /// of the local field matters, so the temperature does not enter. A spin without a local field
+    /// is left alone.
+    pub fn over_relaxation_step(&mut self, x: i64, y: i64, field: f64) {
+        let (mut hx, mut hy) = (field, 0.0);
+        for &neighbor in self.lattice.neighbors(self.lattice.site([x, y])) {
+            let neighbor = self.angles[neighbor];
+            hx += neighbor.cos();
+            hy += neighbor.sin();
+        }
+        if hx == 0.0 && hy == 0.0 {
+            return;
+        }
+        self.set(x, y, 2.0 * hy.atan2(hx) - self.get(x, y));
+    }
+
+    /// # Over-relaxation sweep
+    /// Performs an over-relaxation step at every site. The sweeps keep the energy fixed, so they
+    /// must be interleaved with ergodic updates such as `step`.
+    pub fn over_relaxation_sweep(&mut self, field: f64) {
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                self.over_relaxation_step(x, y, field);
+            }
+        }
+    }
+
+    /// # Hybrid step
+    /// Performs the given number of over-relaxation sweeps followed by one Metropolis sweep, and
+    /// returns the acceptance of the Metropolis sweep. A handful of over-relaxation sweeps per
+    /// Metropolis sweep decorrelates the angles far faster than Metropolis alone at little cost.
+    pub fn hybrid_step<R: Rng>(
+        &mut self,
+        temperature: Temperature,
+        field: f64,
+        number_of_over_relaxation_sweeps: usize,
+        rng: &mut R,
+    ) -> f64 {
+        for _ in 0..number_of_over_relaxation_sweeps {
+            self.over_relaxation_sweep(field);
+        }
+        self.step(temperature, field, rng)
+    }
+
+    /// # Wolff step
+    /// Grows and reflects a single cluster with Wolff's embedding, and returns its size. A mirror
+    /// line at a random angle φ splits every spin into a part along the line and a projection
+    /// s_i = sin(θ_i - φ) onto its normal. Reflecting θ → 2φ - θ flips the projection, which then
+    /// behaves as an Ising spin with couplings K |s_i s_j|, so bonds join the cluster with
+    /// probability 1 - exp(-2K s_i s_j) when s_i s_j > 0. The field is not supported, since it
+    /// would break the reflection symmetry.
+    pub fn wolff_step<R: Rng>(&mut self, temperature: Temperature, rng: &mut R) -> usize {
+        let coupling = temperature.beta();
+        let mirror = rng.gen_range(0.0..PI);
+        let reflect = |angle: f64| (2.0 * mirror - angle).rem_euclid(2.0 * PI);
+
+        let seed = rng.gen_range(0..self.angles.len());
+        let mut in_cluster = vec![false; self.angles.len()];
+        in_cluster[seed] = true;
+        let mut stack = vec![(seed, (self.angles[seed] - mirror).sin())];
+        self.angles[seed] = reflect(self.angles[seed]);
+        let mut size = 1;
+
+        while let Some((site, site_projection)) = stack.pop() {
+            for &neighbor in self.lattice.neighbors(site) {
+                if in_cluster[neighbor] {
+                    continue;
+                }
+                let neighbor_projection = (self.angles[neighbor] - mirror).sin();
+                let product = site_projection * neighbor_projection;
+                if product > 0.0 && rng.gen::<f64>() < 1.0 - (-2.0 * coupling * product).exp() {
+                    in_cluster[neighbor] = true;
+                    self.angles[neighbor] = reflect(self.angles[neighbor]);
+                    stack.push((neighbor, neighbor_projection));
+                    size += 1;
+                }
+            }
+        }
+        size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::helicity::HelicityModulus;
+
+    #[test]
+    fn test_ordered_observables() {
+        let model = XYModel::new_constant(4, 4, PI / 3.0).unwrap();
+        assert!((model.energy(Temperature::new(1.0).unwrap(), 0.5) + 2.25).abs() < 1e-12);
+        let (mx, my) = model.magnetization();
+        assert!((mx - 0.5).abs() < 1e-12);
+        assert!((my - 0.75_f64.sqrt()).abs() < 1e-12);
+
+        assert!(XYModel::new_constant(0, 0, 0.0).is_err());
+        assert!(XYModel::new_constant(4, 4, f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_vortex_pair() {
+        // A vortex centred in the plaquette at (2, 4) and an antivortex in the one at (9, 4).
+        let mut model = XYModel::new_constant(12, 8, 0.0).unwrap();
+        for y in 0..8 {
+            for x in 0..12 {
+                let (x, y) = (x as f64, y as f64);
+                let angle = (y - 4.6).atan2(x - 2.4) - (y - 4.3).atan2(x - 9.7);
+                model.set(x as i64, y as i64, angle);
+            }
+        }
+        assert_eq!(model.vorticity(2, 4), 1);
+        assert_eq!(model.vorticity(9, 4), -1);
+        assert_eq!(model.vorticity(6, 1), 0);
+        let total: i32 = model.vortices().iter().map(|&(_, _, charge)| charge).sum();
+        assert_eq!(total, 0);
+    }
+
+    #[test]
+    fn test_vortex_density() {
+        assert_eq!(
+            XYModel::new_constant(8, 8, 1.0).unwrap().vortex_density(),
+            0.0
+        );
+        let random = XYModel::new_random(64, 64, &mut StdRng::seed_from_u64(15)).unwrap();
+        assert!((random.vortex_density() - 1.0 / 3.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_helicity_modulus() {
+        let mut ordered = HelicityModulus::new();
+        ordered.add(&XYModel::new_constant(6, 6, 2.0).unwrap().twist_response());
+        assert!((ordered.value(Temperature::new(1.0).unwrap()) - 1.0).abs() < 1e-12);
+
+        // Deep in the low-temperature phase the stiffness is close to one, well above the
+        // universal value, and far above the transition it vanishes.
+        let mut rng = StdRng::seed_from_u64(14);
+        for (temperature, lower, upper) in [(0.25, 0.8, 1.0), (2.5, -0.1, 0.1)] {
+            let temperature = Temperature::new(temperature).unwrap();
+            let mut model = XYModel::new_random(8, 8, &mut rng).unwrap();
+            let mut helicity = HelicityModulus::new();
+            for step in 0..3000 {
+                model.wolff_step(temperature, &mut rng);
+                model.hybrid_step(temperature, 0.0, 1, &mut rng);
+                if step >= 500 {
+                    helicity.add(&model.twist_response());
+                }
+            }
+            let value = helicity.value(temperature);
+            assert!(value > lower && value < upper);
+        }
+        assert!(HelicityModulus::universal_jump(Temperature::new(0.25).unwrap()) < 0.8);
+    }
+
+    #[test]
+    fn test_proposal_width_sets_acceptance() {
+        let temperature = Temperature::new(0.5).unwrap();
+        let mut rng = StdRng::seed_from_u64(16);
+        let mut model = XYModel::new_random(16, 16, &mut rng).unwrap();
+        for _ in 0..100 {
+            model.step(temperature, 0.0, &mut rng);
+        }
+        let wide = model.step(temperature, 0.0, &mut rng);
+        model.set_proposal_width(0.2);
+        let narrow = model.step(temperature, 0.0, &mut rng);
+        assert!(narrow > 0.8);
+        assert!(wide < narrow);
+    }
+
+    #[test]
+    fn test_over_relaxation_conserves_energy() {
+        let (temperature, field) = (Temperature::new(0.8).unwrap(), 0.3);
+        let mut model = XYModel::new_random(8, 8, &mut StdRng::seed_from_u64(17)).unwrap();
+        let energy = model.energy(temperature, field);
+        let before = model.get(3, 5);
+        model.over_relaxation_sweep(field);
+        assert!((model.energy(temperature, field) - energy).abs() < 1e-12);
+        assert!((model.get(3, 5) - before).abs() > 1e-9);
+    }
+
+    #[test]
+    fn test_hybrid_matches_wolff() {
+        let temperature = Temperature::from_beta(0.9).unwrap();
+        let mut rng = StdRng::seed_from_u64(13);
+        let mut hybrid = XYModel::new_random(4, 4, &mut rng).unwrap();
+        let mut cluster = XYModel::new_random(4, 4, &mut rng).unwrap();
+        for _ in 0..1000 {
+            hybrid.hybrid_step(temperature, 0.0, 3, &mut rng);
+            cluster.wolff_step(temperature, &mut rng);
+        }
+        let number_of_steps = 40_000;
+        let (mut hybrid_energy, mut cluster_energy) = (0.0, 0.0);
+        for _ in 0..number_of_steps {
+            hybrid.hybrid_step(temperature, 0.0, 3, &mut rng);
+            cluster.wolff_step(temperature, &mut rng);
+            hybrid_energy += hybrid.energy(temperature, 0.0);
+            cluster_energy += cluster.energy(temperature, 0.0);
+        }
+        let difference = (hybrid_energy - cluster_energy) / number_of_steps as f64;
+        assert!(difference.abs() < 0.03);
+    }
+
+    #[test]
+    fn test_wolff_matches_metropolis() {
+        let temperature = Temperature::from_beta(0.9).unwrap();
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut local = XYModel::new_random(4, 4, &mut rng).unwrap();
+        let mut cluster = XYModel::new_random(4, 4, &mut rng).unwrap();
+        for _ in 0..1000 {
+            local.step(temperature, 0.0, &mut rng);
+            cluster.wolff_step(temperature, &mut rng);
+        }
+        let number_of_steps = 40_000;
+        let (mut local_energy, mut cluster_energy) = (0.0, 0.0);
+        for _ in 0..number_of_steps {
+            local.step(temperature, 0.0, &mut rng);
+            cluster.wolff_step(temperature, &mut rng);
+            local_energy += local.energy(temperature, 0.0);
+            cluster_energy += cluster.energy(temperature, 0.0);
+        }
+        let difference = (local_energy - cluster_energy) / number_of_steps as f64;
+        assert!(difference.abs() < 0.03);
+    }
+}
@@ -0,0 +1,240 @@
+use num_complex::Complex64;
+
+use crate::error::Result;
+use crate::exact::ExactEnumeration;
+use crate::temperature::Temperature;
+
+/// The tolerance on the relative size of the root corrections.
+const TOLERANCE: f64 = 1e-14;
+
+/// The maximum number of root-finding iterations.
+const MAX_ITERATIONS: usize = 10_000;
+
+/// # Leading zero
+/// The partition-function zero of an L×L lattice that lies closest to the positive real axis,
+/// together with the size of the imaginary part it corresponds to in the physical parameter (the
+/// field H for Lee–Yang zeros, the coupling K for Fisher zeros). In the thermodynamic limit this
+/// imaginary part goes to zero at the transition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LeadingZero {
+    pub size: usize,
+    pub zero: Complex64,
+    pub imaginary_part: f64,
+}
+
+/// # Lee–Yang zeros
+/// The zeros of the partition function in the complex fugacity z = e^(-2H) at a fixed temperature,
+/// that is at the reduced coupling K = 1/T. Writing the magnetization as M = N - 2k, the
+/// partition function is e^(HN) times a polynomial of degree N in z whose k-th coefficient is
+/// Σ_B g(B, N - 2k) e^(KB). For a ferromagnet the Lee–Yang theorem puts every zero on the unit
+/// circle.
+pub fn lee_yang_zeros(enumeration: &ExactEnumeration, temperature: Temperature) -> Vec<Complex64> {
+    let coupling = temperature.beta();
+    let n = enumeration.number_of_sites() as i64;
+    let states = enumeration.density_of_states();
+
+    // Shift the exponents so that the largest coefficient is of order one.
+    let largest = states
+        .iter()
+        .map(|state| coupling * state.bond_sum as f64)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let mut coefficients = vec![0.0; n as usize + 1];
+    for state in states {
+        let down_spins = ((n - state.magnetization) / 2) as usize;
+        coefficients[down_spins] +=
+            state.count as f64 * (coupling * state.bond_sum as f64 - largest).exp();
+    }
+
+    polynomial_roots(&coefficients)
+}
+
+/// # Fisher zeros
+/// The zeros of the partition function in the complex variable x = e^(-2K) at a fixed reduced
+/// field. Writing the bond sum as B = 2N - 2u, where u counts the broken bonds, the partition
+/// function is e^(2KN) times a polynomial in x whose u-th coefficient is Σ_M g(B, M) e^(HM).
+pub fn fisher_zeros(enumeration: &ExactEnumeration, field: f64) -> Vec<Complex64> {
+    let n = enumeration.number_of_sites() as i64;
+    let states = enumeration.density_of_states();
+
+    let largest = states
+        .iter()
+        .map(|state| field * state.magnetization as f64)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let mut coefficients = vec![0.0; 2 * n as usize + 1];
+    for state in states {
+        let broken_bonds = ((2 * n - state.bond_sum) / 2) as usize;
+        coefficients[broken_bonds] +=
+            state.count as f64 * (field * state.magnetization as f64 - largest).exp();
+    }
+
+    polynomial_roots(&coefficients)
+}
+
+/// # Leading zero of a set
+/// Picks the zero in the upper half plane with the smallest argument, which is the one that
+/// pinches the positive real axis first. The physical parameter is -ln(z)/2 in both the Lee–Yang
+/// and the Fisher variable, so the size of its imaginary part is half the argument.
+pub fn leading_zero(size: usize, zeros: &[Complex64]) -> Option<LeadingZero> {
+    zeros
+        .iter()
+        .filter(|zero| zero.im > 0.0)
+        .min_by(|a, b| a.arg().total_cmp(&b.arg()))
+        .map(|&zero| LeadingZero {
+            size,
+            zero,
+            imaginary_part: 0.5 * zero.arg(),
+        })
+}
+
+/// # Lee–Yang scaling
+/// The leading Lee–Yang zero for each L×L lattice in `sizes`, showing how the zeros approach the
+/// real field axis as the system grows. Fails for a size that cannot be enumerated.
+pub fn lee_yang_scaling(sizes: &[usize], temperature: Temperature) -> Result<Vec<LeadingZero>> {
+    let mut zeros = Vec::new();
+    for &size in sizes {
+        let enumeration = ExactEnumeration::new(size, size)?;
+        zeros.extend(leading_zero(
+            size,
+            &lee_yang_zeros(&enumeration, temperature),
+        ));
+    }
+    Ok(zeros)
+}
+
+/// # Fisher scaling
+/// The leading Fisher zero for each L×L lattice in `sizes`, showing how the zeros approach the
+/// real coupling axis as the system grows. Fails for a size that cannot be enumerated.
+pub fn fisher_scaling(sizes: &[usize], field: f64) -> Result<Vec<LeadingZero>> {
+    let mut zeros = Vec::new();
+    for &size in sizes {
+        let enumeration = ExactEnumeration::new(size, size)?;
+        zeros.extend(leading_zero(size, &fisher_zeros(&enumeration, field)));
+    }
+    Ok(zeros)
+}
+
+/// # Polynomial roots
+/// Finds all the roots of a polynomial with the Aberth–Ehrlich method. The coefficients are given
+/// in order of increasing power, and vanishing leading coefficients are ignored.
+fn polynomial_roots(coefficients: &[f64]) -> Vec<Complex64> {
+    let Some(last) = coefficients.iter().rposition(|&c| c != 0.0) else {
+        return Vec::new();
+    };
+    let coefficients = &coefficients[..=last];
+    let degree = coefficients.len() - 1;
+    if degree == 0 {
+        return Vec::new();
+    }
+
+    // Start on a circle whose radius is the geometric mean of the roots' magnitudes, slightly
+    // rotated so that no start point sits on a symmetry axis of the problem.
+    let radius = (coefficients[0].abs() / coefficients[degree].abs())
+        .powf(1.0 / degree as f64)
+        .max(f64::MIN_POSITIVE);
+    let mut roots: Vec<Complex64> = (0..degree)
+        .map(|k| {
+            let angle = std::f64::consts::TAU * (k as f64 + 0.25) / degree as f64 + 0.4;
+            Complex64::from_polar(radius, angle)
+        })
+        .collect();
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut largest_correction: f64 = 0.0;
+        for i in 0..degree {
+            let (value, derivative) = evaluate(coefficients, roots[i]);
+            if value == Complex64::new(0.0, 0.0) {
+                continue;
+            }
+            let ratio = value / derivative;
+            let repulsion: Complex64 = (0..degree)
+                .filter(|&j| j != i)
+                .map(|j| (roots[i] - roots[j]).inv())
+                .sum();
+            let correction = ratio / (Complex64::new(1.0, 0.0) - ratio * repulsion);
+            roots[i] -= correction;
+            largest_correction = largest_correction.max(correction.norm() / roots[i].norm());
+        }
+        if largest_correction < TOLERANCE {
+            break;
+        }
+    }
+    roots
+}
+
+/// # Evaluate
+/// Evaluates a polynomial and its derivative at a point with Horner's scheme.
+fn evaluate(coefficients: &[f64], point: Complex64) -> (Complex64, Complex64) {
+    let mut value = Complex64::new(0.0, 0.0);
+    let mut derivative = Complex64::new(0.0, 0.0);
+    for &coefficient in coefficients.iter().rev() {
+        derivative = derivative * point + value;
+        value = value * point + coefficient;
+    }
+    (value, derivative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polynomial_roots() {
+        // (z - 1)(z - 2)(z^2 + 1) = z^4 - 3z^3 + 3z^2 - 3z + 2
+        let mut roots = polynomial_roots(&[2.0, -3.0, 3.0, -3.0, 1.0]);
+        roots.sort_by(|a, b| (a.re, a.im).partial_cmp(&(b.re, b.im)).unwrap());
+        let expected = [
+            Complex64::new(0.0, -1.0),
+            Complex64::new(0.0, 1.0),
+            Complex64::new(1.0, 0.0),
+            Complex64::new(2.0, 0.0),
+        ];
+        for (root, expected) in roots.iter().zip(expected) {
+            assert!((root - expected).norm() < 1e-10);
+        }
+    }
+
+    #[test]
+    fn test_lee_yang_circle_theorem() {
+        let enumeration = ExactEnumeration::new(3, 3).unwrap();
+        let zeros = lee_yang_zeros(&enumeration, Temperature::from_beta(0.3).unwrap());
+        assert_eq!(zeros.len(), 9);
+        for zero in zeros {
+            assert!((zero.norm() - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_lee_yang_zeros_approach_real_axis() {
+        let scaling = lee_yang_scaling(&[2, 3, 4], Temperature::new(2.27).unwrap()).unwrap();
+        assert_eq!(scaling.len(), 3);
+        assert!(scaling[0].imaginary_part > scaling[1].imaginary_part);
+        assert!(scaling[1].imaginary_part > scaling[2].imaginary_part);
+    }
+
+    #[test]
+    fn test_fisher_zeros_are_roots() {
+        let enumeration = ExactEnumeration::new(3, 3).unwrap();
+        let zeros = fisher_zeros(&enumeration, 0.0);
+        let leading = leading_zero(3, &zeros).unwrap();
+
+        // The partition function at the corresponding complex coupling must vanish.
+        let coupling = -0.5 * leading.zero.ln();
+        let partition_function: Complex64 = enumeration
+            .density_of_states()
+            .iter()
+            .map(|state| state.count as f64 * (coupling * state.bond_sum as f64).exp())
+            .sum();
+        let scale: f64 = enumeration
+            .density_of_states()
+            .iter()
+            .map(|state| state.count as f64 * (coupling.re * state.bond_sum as f64).exp())
+            .sum();
+        assert!(partition_function.norm() / scale < 1e-8);
+    }
+
+    #[test]
+    fn test_fisher_zeros_approach_real_axis() {
+        let scaling = fisher_scaling(&[3, 4], 0.0).unwrap();
+        assert!(scaling[0].imaginary_part > scaling[1].imaginary_part);
+    }
+}
@@ -0,0 +1,51 @@
+// Runs the simulation compiled to WebAssembly and draws it on the canvas. Build the package first
+// with `wasm-pack build --target web --out-dir web/pkg -- --features web` and serve this folder.
+import init, { Temperature, WebSimulation } from "./pkg/ising_model.js";
+
+const canvas = document.getElementById("lattice");
+const context = canvas.getContext("2d");
+const control = (id) => document.getElementById(id);
+
+await init();
+
+let simulation;
+let running = true;
+
+function restart() {
+  const size = Number(control("size").value);
+  canvas.width = size;
+  canvas.height = size;
+  const seed = Math.floor(Math.random() * 2 ** 32);
+  simulation = new WebSimulation(size, size, temperature(), field(), seed);
+}
+
+const temperature = () => new Temperature(Number(control("temperature").value));
+const field = () => Number(control("field").value);
+
+for (const id of ["temperature", "field", "speed"]) {
+  control(id).addEventListener("input", () => {
+    control(`${id}-value`).textContent = Number(control(id).value).toFixed(id === "speed" ? 0 : 2);
+    simulation.set_parameters(temperature(), field());
+  });
+}
+control("size").addEventListener("change", restart);
+control("restart").addEventListener("click", restart);
+control("pause").addEventListener("click", () => {
+  running = !running;
+  control("pause").textContent = running ? "Pause" : "Run";
+});
+
+function frame() {
+  if (running) {
+    const acceptance = simulation.step(Number(control("speed").value));
+    control("acceptance").textContent = acceptance.toFixed(3);
+  }
+  const pixels = new Uint8ClampedArray(simulation.pixels());
+  context.putImageData(new ImageData(pixels, simulation.width(), simulation.height()), 0, 0);
+  control("sweep").textContent = simulation.sweep();
+  control("magnetization").textContent = simulation.magnetization().toFixed(3);
+  requestAnimationFrame(frame);
+}
+
+restart();
+requestAnimationFrame(frame);